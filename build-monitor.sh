@@ -0,0 +1,4 @@
+# assembles the monitor guest program into content/monitor.bin (needs llvm-mc & llvm-objcopy)
+llvm-mc -triple=armv6-none-eabi -mcpu=arm1176jzf-s -filetype=obj ./guest-src/monitor.s -o ./guest-src/monitor.o || exit 1
+llvm-objcopy -O binary ./guest-src/monitor.o ./content/monitor.bin || exit 1
+rm ./guest-src/monitor.o
@@ -1,3 +1,27 @@
+# compiles shaders-src into content/shaders. the compiled shaders are committed, so this is only needed after changing
+# one. tools/linux/glslc lives in git LFS - without it checked out, a glslc or glslangValidator on the PATH is used
+compile() {
+    if ./tools/linux/glslc --version > /dev/null 2>&1; then
+        ./tools/linux/glslc -fshader-stage=$1 "$2" -o "$3" || exit 1
+    elif command -v glslc > /dev/null; then
+        glslc -fshader-stage=$1 "$2" -o "$3" || exit 1
+    elif command -v glslangValidator > /dev/null; then
+        case $1 in
+            compute) stage=comp ;;
+            vertex) stage=vert ;;
+            fragment) stage=frag ;;
+        esac
+        glslangValidator -V -S $stage "$2" -o "$3" > /dev/null || exit 1
+    else
+        echo "no GLSL compiler: check out tools/linux/glslc (git lfs pull), or put glslc or glslangValidator on the PATH"
+        exit 1
+    fi
+}
+
 mkdir -p ./content/shaders/
-./tools/linux/glslc -fshader-stage=compute ./shaders-src/vu.glsl -o ./content/shaders/vu.spv
-./tools/linux/glslc -fshader-stage=compute ./shaders-src/draw_tri_list.glsl -o ./content/shaders/draw_tri_list.spv
\ No newline at end of file
+compile compute ./shaders-src/vu.glsl ./content/shaders/vu.spv
+compile compute ./shaders-src/draw_tri_list.glsl ./content/shaders/draw_tri_list.spv
+compile compute ./shaders-src/draw_lines.glsl ./content/shaders/draw_lines.spv
+compile compute ./shaders-src/fill.glsl ./content/shaders/fill.spv
+compile vertex ./shaders-src/present_vert.glsl ./content/shaders/present_vert.spv
+compile fragment ./shaders-src/present_frag.glsl ./content/shaders/present_frag.spv
\ No newline at end of file
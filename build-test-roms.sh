@@ -0,0 +1,7 @@
+# assembles the guest test ROMs in tests/roms (needs llvm-mc & llvm-objcopy). guest-src is on the include path too, for
+# tests that run the guest programs in there
+for src in ./tests/roms/*.s; do
+    llvm-mc -triple=armv6-none-eabi -mcpu=arm1176jzf-s -I ./tests/roms -I ./guest-src -filetype=obj "$src" -o "${src%.s}.o" || exit 1
+    llvm-objcopy -O binary "${src%.s}.o" "${src%.s}.bin" || exit 1
+    rm "${src%.s}.o"
+done
@@ -0,0 +1,747 @@
+@ NyxBox monitor - a tiny interactive guest program for poking at the hardware over the UART
+@
+@ boot it with `nyxbox run --monitor`, then type commands on the terminal (numbers are hex, 0x prefix optional):
+@
+@   m ADDR [COUNT]      dump COUNT words (default 8)
+@   w ADDR VALUE        write a word
+@   d ADDR [COUNT]      disassemble COUNT ARM instructions (default 8)
+@   g ADDR              call ADDR, then show the registers it returned with
+@   r                   show the registers saved by the last g
+@   i                   show the system info block
+@   ?                   help
+@
+@ it's also meant as a small worked example of talking to the peripherals from assembly
+@
+@ calls go through the call macro (mov lr, pc / b) rather than bl - the ROM is flattened straight from the object file with no
+@ linker, & the assembler leaves every bl as a relocation for one
+
+    @ memory map
+    .equ MAIN_RAM,          0x1000000
+    .equ STACK_TOP,         0x2000000
+    .equ UART,              0x6000000
+    .equ SYSINFO,           0x9000000
+
+    @ UART registers & status bits
+    .equ UART_STATUS,       0x00
+    .equ UART_TX,           0x04
+    .equ UART_RX,           0x08
+    .equ UART_TXFULL,       4
+    .equ UART_RXEMPTY,      8
+
+    @ system info registers
+    .equ SYSINFO_ID,        0x00
+    .equ SYSINFO_VERSION,   0x04
+    .equ SYSINFO_FEATURES,  0x08
+    .equ SYSINFO_RAMSIZE,   0x1C
+
+    @ monitor state, at the bottom of main RAM
+    .equ SAVED_REGS,        (MAIN_RAM + 0x100)     @ r0-r12, cpsr
+    .equ LINE_BUF,          (MAIN_RAM + 0x200)
+    .equ LINE_MAX,          128
+
+    @ mov lr, pc reads as the instruction after the b, which is where the callee returns to
+    .macro call target
+        mov lr, pc
+        b \target
+    .endm
+
+    .text
+    .global _start
+_start:
+    ldr sp, =STACK_TOP
+
+    ldr r0, =s_banner
+    call puts
+
+main_loop:
+    ldr r0, =s_prompt
+    call puts
+
+    ldr r0, =LINE_BUF
+    mov r1, #LINE_MAX
+    call readline
+
+    ldr r0, =LINE_BUF
+    call skip_spaces
+    ldrb r5, [r0], #1
+    mov r4, r0                  @ r4 = rest of the line
+
+    cmp r5, #0
+    beq main_loop
+    cmp r5, #'m'
+    beq cmd_mem
+    cmp r5, #'w'
+    beq cmd_write
+    cmp r5, #'d'
+    beq cmd_disasm
+    cmp r5, #'g'
+    beq cmd_go
+    cmp r5, #'r'
+    beq cmd_regs
+    cmp r5, #'i'
+    beq cmd_info
+    cmp r5, #'?'
+    beq cmd_help
+
+    ldr r0, =s_unknown
+    call puts
+    b main_loop
+
+bad_args:
+    ldr r0, =s_bad_args
+    call puts
+    b main_loop
+
+@ m ADDR [COUNT]
+cmd_mem:
+    mov r0, r4
+    call parse_hex
+    cmp r2, #0
+    beq bad_args
+    bic r6, r0, #3              @ r6 = address
+    mov r0, r1
+    call parse_hex
+    cmp r2, #0
+    moveq r7, #8
+    movne r7, r0                @ r7 = count
+
+1:  cmp r7, #0
+    beq main_loop
+    mov r0, r6
+    call puthex
+    ldr r0, =s_colon
+    call puts
+    ldr r0, [r6], #4
+    call puthex
+    call newline
+    sub r7, r7, #1
+    b 1b
+
+@ w ADDR VALUE
+cmd_write:
+    mov r0, r4
+    call parse_hex
+    cmp r2, #0
+    beq bad_args
+    bic r6, r0, #3
+    mov r0, r1
+    call parse_hex
+    cmp r2, #0
+    beq bad_args
+    str r0, [r6]
+    b main_loop
+
+@ d ADDR [COUNT]
+cmd_disasm:
+    mov r0, r4
+    call parse_hex
+    cmp r2, #0
+    beq bad_args
+    bic r6, r0, #3
+    mov r0, r1
+    call parse_hex
+    cmp r2, #0
+    moveq r7, #8
+    movne r7, r0
+
+1:  cmp r7, #0
+    beq main_loop
+    mov r0, r6
+    call puthex
+    ldr r0, =s_colon
+    call puts
+    ldr r0, [r6]
+    call puthex
+    ldr r0, =s_gap
+    call puts
+    ldr r0, [r6]
+    mov r1, r6
+    call disasm
+    call newline
+    add r6, r6, #4
+    sub r7, r7, #1
+    b 1b
+
+@ g ADDR
+cmd_go:
+    mov r0, r4
+    call parse_hex
+    cmp r2, #0
+    beq bad_args
+    mov r12, r0
+
+    push {r4-r11}
+    mov lr, pc                  @ pc reads as the instruction after next, i.e. the push below
+    bx r12
+    push {r12}
+    ldr r12, =SAVED_REGS
+    stmia r12, {r0-r11}
+    pop {r0}
+    str r0, [r12, #48]
+    mrs r0, cpsr
+    str r0, [r12, #52]
+    pop {r4-r11}
+    ldr sp, =STACK_TOP          @ in case the callee didn't leave the stack balanced
+
+    @ fall through & show what came back
+
+@ r
+cmd_regs:
+    ldr r6, =SAVED_REGS
+    mov r7, #0
+
+1:  cmp r7, #13
+    beq 2f
+    mov r0, r7
+    call put_reg
+    mov r0, #'='
+    call putc
+    ldr r0, [r6, r7, lsl #2]
+    call puthex
+    add r7, r7, #1
+    and r0, r7, #3
+    cmp r0, #0
+    moveq r0, #'\n'
+    movne r0, #' '
+    call putc
+    b 1b
+
+2:  ldr r0, =s_cpsr
+    call puts
+    ldr r0, [r6, #52]
+    call puthex
+    call newline
+    b main_loop
+
+@ i
+cmd_info:
+    ldr r6, =SYSINFO
+
+    ldr r0, =s_id
+    call puts
+    ldr r0, [r6, #SYSINFO_ID]
+    call puthex
+    call newline
+
+    ldr r0, =s_version
+    call puts
+    ldr r0, [r6, #SYSINFO_VERSION]
+    call puthex
+    call newline
+
+    ldr r0, =s_features
+    call puts
+    ldr r0, [r6, #SYSINFO_FEATURES]
+    call puthex
+    call newline
+
+    ldr r0, =s_ramsize
+    call puts
+    ldr r0, [r6, #SYSINFO_RAMSIZE]
+    call puthex
+    call newline
+    b main_loop
+
+@ ?
+cmd_help:
+    ldr r0, =s_help
+    call puts
+    b main_loop
+
+    .ltorg
+
+@ ---------------------------------------------------------------------------------------------------------------------
+@ UART I/O
+
+@ write the byte in r0
+putc:
+    ldr r1, =UART
+1:  ldr r2, [r1, #UART_STATUS]
+    tst r2, #UART_TXFULL
+    bne 1b
+    str r0, [r1, #UART_TX]
+    bx lr
+
+@ read a byte into r0, idling until one arrives
+getc:
+    ldr r1, =UART
+1:  ldr r2, [r1, #UART_STATUS]
+    tst r2, #UART_RXEMPTY
+    beq 2f
+    wfi
+    b 1b
+2:  ldr r0, [r1, #UART_RX]
+    bx lr
+
+@ write the nul terminated string at r0
+puts:
+    push {r4, lr}
+    mov r4, r0
+1:  ldrb r0, [r4], #1
+    cmp r0, #0
+    popeq {r4, pc}
+    call putc
+    b 1b
+
+newline:
+    mov r0, #'\n'
+    b putc
+
+@ write r0 as 8 hex digits
+puthex:
+    push {r4, r5, lr}
+    mov r4, r0
+    mov r5, #28
+1:  mov r0, r4, lsr r5
+    call put_digit
+    subs r5, r5, #4
+    bpl 1b
+    pop {r4, r5, pc}
+
+@ write r0 as hex without leading zeros
+puthex_short:
+    push {r4, r5, lr}
+    mov r4, r0
+    mov r5, #28
+1:  cmp r5, #0                  @ always print the last digit
+    beq 2f
+    movs r0, r4, lsr r5
+    bne 2f
+    sub r5, r5, #4
+    b 1b
+2:  mov r0, r4, lsr r5
+    call put_digit
+    subs r5, r5, #4
+    bpl 2b
+    pop {r4, r5, pc}
+
+@ write the low nibble of r0 as a hex digit
+put_digit:
+    and r0, r0, #0xF
+    cmp r0, #10
+    addlo r0, r0, #'0'
+    addhs r0, r0, #('a' - 10)
+    b putc
+
+@ read a line into the buffer at r0 (max r1 bytes including the nul), returns the length in r0
+readline:
+    push {r4-r6, lr}
+    mov r4, r0
+    sub r5, r1, #1
+    mov r6, #0
+1:  call getc
+    cmp r0, #'\n'
+    cmpne r0, #'\r'
+    beq 3f
+    cmp r0, #8
+    cmpne r0, #0x7F
+    beq 2f
+    cmp r6, r5
+    strblo r0, [r4, r6]
+    addlo r6, r6, #1
+    b 1b
+2:  cmp r6, #0                  @ backspace
+    subne r6, r6, #1
+    b 1b
+3:  mov r0, #0
+    strb r0, [r4, r6]
+    mov r0, r6
+    pop {r4-r6, pc}
+
+@ ---------------------------------------------------------------------------------------------------------------------
+@ parsing
+
+@ advance r0 past spaces
+skip_spaces:
+1:  ldrb r1, [r0]
+    cmp r1, #' '
+    cmpne r1, #'\t'
+    addeq r0, r0, #1
+    beq 1b
+    bx lr
+
+@ parse a hex number at r0. returns the value in r0, the position after it in r1, & the number of digits in r2 (0 = none)
+parse_hex:
+    push {lr}
+    call skip_spaces
+    mov r1, r0
+    ldrb r3, [r1]
+    cmp r3, #'0'
+    bne 1f
+    ldrb r3, [r1, #1]
+    orr r3, r3, #0x20
+    cmp r3, #'x'
+    addeq r1, r1, #2
+1:  mov r0, #0
+    mov r2, #0
+2:  ldrb r3, [r1]
+    sub r12, r3, #'0'
+    cmp r12, #10
+    blo 3f
+    orr r3, r3, #0x20           @ lowercase
+    sub r12, r3, #'a'
+    cmp r12, #6
+    bhs 4f
+    add r12, r12, #10
+3:  orr r0, r12, r0, lsl #4
+    add r1, r1, #1
+    add r2, r2, #1
+    b 2b
+4:  pop {pc}
+
+@ ---------------------------------------------------------------------------------------------------------------------
+@ disassembler - covers the common ARM instruction classes (data processing, single & multiple loads/stores, branches,
+@ bx, swi, wfi) & prints ??? for everything else. shifted register offsets & addressing mode details are simplified
+
+@ r0 = instruction, r1 = its address
+disasm:
+    push {r4-r7, lr}
+    mov r4, r0
+    mov r5, r1
+    mov r6, r4, lsr #28         @ r6 = condition
+
+    cmp r6, #0xF
+    beq dis_unknown
+
+    ldr r0, =0x0320F003
+    bic r1, r4, #0xF0000000
+    cmp r1, r0
+    beq dis_wfi
+
+    ldr r0, =0x012FFF10
+    bic r1, r4, #0xF000000F
+    cmp r1, r0
+    beq dis_bx
+
+    and r0, r4, #0x0E000000
+    cmp r0, #0x0A000000
+    beq dis_branch
+    cmp r0, #0x08000000
+    beq dis_ldm
+
+    and r0, r4, #0x0F000000
+    cmp r0, #0x0F000000
+    beq dis_swi
+
+    and r0, r4, #0x0C000000
+    cmp r0, #0x04000000
+    beq dis_ldr
+    cmp r0, #0
+    beq dis_dataproc
+
+dis_unknown:
+    ldr r0, =s_unknown_op
+    call puts
+    pop {r4-r7, pc}
+
+dis_wfi:
+    ldr r0, =s_wfi
+    call puts
+    mov r0, r6
+    call put_cond
+    pop {r4-r7, pc}
+
+dis_bx:
+    ldr r0, =s_bx
+    call puts
+    mov r0, r6
+    call put_cond
+    mov r0, #' '
+    call putc
+    and r0, r4, #0xF
+    call put_reg
+    pop {r4-r7, pc}
+
+dis_branch:
+    mov r0, #'b'
+    call putc
+    tst r4, #0x01000000
+    movne r0, #'l'
+    movne lr, pc
+    bne putc
+    mov r0, r6
+    call put_cond
+    mov r0, #' '
+    call putc
+    mov r0, r4, lsl #8          @ sign extend imm24, times 4
+    add r0, r5, r0, asr #6
+    add r0, r0, #8
+    call puthex
+    pop {r4-r7, pc}
+
+dis_swi:
+    ldr r0, =s_swi
+    call puts
+    mov r0, r6
+    call put_cond
+    ldr r0, =s_space_hash
+    call puts
+    bic r0, r4, #0xFF000000
+    call puthex_short
+    pop {r4-r7, pc}
+
+dis_ldr:
+    @ register offsets with bit 4 set are media instructions, not loads/stores
+    ldr r1, =0x02000010
+    and r0, r4, r1
+    cmp r0, r1
+    beq dis_unknown
+
+    tst r4, #0x00100000
+    ldreq r0, =s_str
+    ldrne r0, =s_ldr
+    call puts
+    tst r4, #0x00400000
+    movne r0, #'b'
+    movne lr, pc
+    bne putc
+    mov r0, r6
+    call put_cond
+    mov r0, #' '
+    call putc
+    mov r0, r4, lsr #12
+    and r0, r0, #0xF
+    call put_reg
+    ldr r0, =s_comma_bracket
+    call puts
+    mov r0, r4, lsr #16
+    and r0, r0, #0xF
+    call put_reg
+    ldr r0, =s_comma
+    call puts
+
+    tst r4, #0x02000000
+    bne 1f
+    ldr r0, =s_hash
+    call puts
+    tst r4, #0x00800000
+    moveq r0, #'-'
+    moveq lr, pc
+    beq putc
+    ldr r0, =0xFFF
+    and r0, r4, r0
+    call puthex_short
+    b 2f
+
+1:  tst r4, #0x00800000
+    moveq r0, #'-'
+    moveq lr, pc
+    beq putc
+    and r0, r4, #0xF
+    call put_reg
+
+2:  mov r0, #']'
+    call putc
+    pop {r4-r7, pc}
+
+dis_ldm:
+    tst r4, #0x00100000
+    ldreq r0, =s_stm
+    ldrne r0, =s_ldm
+    call puts
+    mov r0, r4, lsr #23         @ P & U select the addressing mode
+    and r0, r0, #3
+    ldr r1, =t_ldm_modes
+    add r0, r1, r0, lsl #1
+    ldrb r7, [r0, #1]
+    ldrb r0, [r0]
+    call putc
+    mov r0, r7
+    call putc
+    mov r0, r6
+    call put_cond
+    mov r0, #' '
+    call putc
+    mov r0, r4, lsr #16
+    and r0, r0, #0xF
+    call put_reg
+    tst r4, #0x00200000
+    movne r0, #'!'
+    movne lr, pc
+    bne putc
+    ldr r0, =s_comma_brace
+    call puts
+    mov r0, r4, lsl #16
+    mov r0, r0, lsr #16
+    call puthex_short
+    mov r0, #'}'
+    call putc
+    pop {r4-r7, pc}
+
+dis_dataproc:
+    @ multiplies & the extra load/store encodings live in the register-shifted-by-register space
+    ldr r1, =0x02000090
+    and r0, r4, r1
+    cmp r0, #0x90
+    beq dis_unknown
+
+    mov r7, r4, lsr #21
+    and r7, r7, #0xF            @ r7 = opcode
+
+    @ tst/teq/cmp/cmn without S are the status register & misc instructions
+    sub r0, r7, #8
+    cmp r0, #4
+    bhs 1f
+    tst r4, #0x00100000
+    beq dis_unknown
+
+1:  ldr r0, =t_dp_names
+    add r0, r0, r7, lsl #2
+    call puts
+
+    @ S suffix, except on the compare ops where it's implied
+    sub r0, r7, #8
+    cmp r0, #4
+    blo 2f
+    tst r4, #0x00100000
+    movne r0, #'s'
+    movne lr, pc
+    bne putc
+2:  mov r0, r6
+    call put_cond
+    mov r0, #' '
+    call putc
+
+    @ rd, except for compares
+    sub r0, r7, #8
+    cmp r0, #4
+    blo 3f
+    mov r0, r4, lsr #12
+    and r0, r0, #0xF
+    call put_reg
+    ldr r0, =s_comma
+    call puts
+
+    @ rn, except for mov & mvn
+3:  cmp r7, #13
+    cmpne r7, #15
+    beq 4f
+    mov r0, r4, lsr #16
+    and r0, r0, #0xF
+    call put_reg
+    ldr r0, =s_comma
+    call puts
+
+4:  tst r4, #0x02000000
+    beq 5f
+
+    @ rotated immediate
+    ldr r0, =s_hash
+    call puts
+    and r0, r4, #0xFF
+    mov r1, r4, lsr #8
+    and r1, r1, #0xF
+    mov r1, r1, lsl #1
+    mov r0, r0, ror r1
+    call puthex_short
+    pop {r4-r7, pc}
+
+    @ register, possibly shifted
+5:  and r0, r4, #0xF
+    call put_reg
+    ldr r0, =0xFF0
+    tst r4, r0
+    popeq {r4-r7, pc}
+
+    ldr r0, =s_comma
+    call puts
+    mov r0, r4, lsr #5
+    and r0, r0, #3
+    ldr r1, =t_shift_names
+    add r0, r1, r0, lsl #2
+    call puts
+    mov r0, #' '
+    call putc
+
+    tst r4, #0x10
+    bne 6f
+    mov r0, #'#'
+    call putc
+    mov r0, r4, lsr #7
+    and r0, r0, #0x1F
+    call puthex_short
+    pop {r4-r7, pc}
+
+6:  mov r0, r4, lsr #8
+    and r0, r0, #0xF
+    call put_reg
+    pop {r4-r7, pc}
+
+@ write the condition suffix for condition code r0 (nothing for AL)
+put_cond:
+    cmp r0, #14
+    bxhs lr
+    push {r4, lr}
+    ldr r1, =t_conds
+    add r4, r1, r0, lsl #1
+    ldrb r0, [r4]
+    call putc
+    ldrb r0, [r4, #1]
+    call putc
+    pop {r4, pc}
+
+@ write the name of register r0
+put_reg:
+    cmp r0, #13
+    blo 1f
+    ldr r1, =t_reg_names
+    sub r0, r0, #13
+    add r0, r1, r0, lsl #2
+    b puts
+1:  push {r4, lr}
+    mov r4, r0
+    mov r0, #'r'
+    call putc
+    cmp r4, #10
+    movhs r0, #'1'
+    movhs lr, pc
+    bhs putc
+    cmp r4, #10
+    subhs r4, r4, #10
+    add r0, r4, #'0'
+    call putc
+    pop {r4, pc}
+
+    .ltorg
+
+@ ---------------------------------------------------------------------------------------------------------------------
+@ strings & tables
+
+t_conds:        .ascii "eqnecsccmiplvsvchilsgeltgtle"
+t_ldm_modes:    .ascii "daiadbib"
+t_dp_names:     .asciz "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr", "mov", "bic", "mvn"
+t_shift_names:  .asciz "lsl", "lsr", "asr", "ror"
+t_reg_names:    .asciz "sp\0", "lr\0", "pc\0"
+
+s_banner:       .asciz "NyxBox monitor - ? for help\n"
+s_prompt:       .asciz "> "
+s_unknown:      .asciz "unknown command (? for help)\n"
+s_bad_args:     .asciz "bad arguments\n"
+s_colon:        .asciz ": "
+s_gap:          .asciz "  "
+s_cpsr:         .asciz "cpsr="
+s_id:           .asciz "id       "
+s_version:      .asciz "version  "
+s_features:     .asciz "features "
+s_ramsize:      .asciz "ram size "
+s_unknown_op:   .asciz "???"
+s_wfi:          .asciz "wfi"
+s_bx:           .asciz "bx"
+s_swi:          .asciz "swi"
+s_ldr:          .asciz "ldr"
+s_str:          .asciz "str"
+s_ldm:          .asciz "ldm"
+s_stm:          .asciz "stm"
+s_space_hash:   .asciz " #"
+s_hash:         .asciz "#"
+s_comma:        .asciz ", "
+s_comma_bracket: .asciz ", ["
+s_comma_brace:  .asciz ", {"
+s_help:
+    .ascii "m ADDR [COUNT]   dump words\n"
+    .ascii "w ADDR VALUE     write a word\n"
+    .ascii "d ADDR [COUNT]   disassemble\n"
+    .ascii "g ADDR           call ADDR & show the registers it returns with\n"
+    .ascii "r                show saved registers\n"
+    .ascii "i                show system info\n"
+    .asciz "numbers are hex\n"
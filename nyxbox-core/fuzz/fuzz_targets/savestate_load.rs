@@ -0,0 +1,23 @@
+#![no_main]
+
+// feeds arbitrary files to the save state loader, & whatever loads to everything the offline tools do with a state. the
+// compatibility corpus makes a good start: run with `cargo fuzz run savestate_load fuzz/corpus/savestate_load ../tests/savestates`
+// from nyxbox-core
+
+use libfuzzer_sys::fuzz_target;
+use nyxbox_core::{savestate::{SaveState, SECTION_CPU, SECTION_VDP_REGS, SECTION_VRAM}, screenshot::framebuffer_rgba};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(state) = SaveState::from_bytes(data) else {
+        return;
+    };
+
+    let _ = state.section_words(&SECTION_CPU);
+
+    if let (Some(regs), Some(vram)) = (state.section_words(&SECTION_VDP_REGS), state.section(&SECTION_VRAM)) {
+        let _ = framebuffer_rgba(&regs, vram);
+    }
+
+    // a state that loaded has to load again once it's been saved
+    SaveState::from_bytes(&state.to_bytes()).expect("a state that loaded failed to load again after saving");
+});
@@ -0,0 +1,77 @@
+#![no_main]
+
+// feeds arbitrary command queues to the VDP's command decoder, in both validation modes. the input is a queue start
+// address followed by VRAM contents, which go at the end of VRAM so a queue that doesn't end runs off it quickly (or,
+// unchecked, wraps round into the rest of VRAM until it hits the queue length limit). run with `cargo fuzz run vdp_cmd_queue` from nyxbox-core
+
+use std::sync::Mutex;
+
+use libfuzzer_sys::fuzz_target;
+use nyxbox_core::{vdp::{Topology, Unchecked, VDPCommand, ValidationMode, Validating, INTERNALREG_COUNT, VRAM_SIZE}, vdpqueue::{QueueReader, MAX_LIST_COUNT, MAX_QUEUE_WORDS}, vucapture::VU_OUTPUT_WORDS};
+
+const VRAM_WORDS: usize = (VRAM_SIZE / 4) as usize;
+
+// whether `vertices` vertices from word address addr all lie in VRAM - whatever the mode, the shaders are only ever
+// handed lists that do
+fn in_vram(addr: u32, vertices: u32) -> bool {
+    return addr as usize + vertices as usize * VU_OUTPUT_WORDS <= VRAM_WORDS;
+}
+
+// one VRAM image reused between runs - zeroing 8MiB every time would make for a slow fuzzer
+static VRAM: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+fn run<V: ValidationMode>(vram: &[u32], addr: u32) {
+    let mut reader = QueueReader::<V>::new(vram, addr);
+
+    // every command reads at least one word, so a queue that's still going after this many commands has hung
+    for _ in 0..=MAX_QUEUE_WORDS {
+        match reader.next_cmd() {
+            Ok((_, _, VDPCommand::EndOfQueue { .. })) | Err(_) => return,
+            Ok((_, _, VDPCommand::WriteInternalRegister { reg, .. })) => assert!(reg < INTERNALREG_COUNT),
+            Ok((_, _, VDPCommand::ProcessVertexList { count, src, dst })) => {
+                assert!(count <= MAX_LIST_COUNT);
+                assert!((src as usize) < VRAM_WORDS && in_vram(dst, count));
+            }
+            Ok((_, _, VDPCommand::DrawList { topology, count, addr })) => {
+                let vertices = match topology {
+                    Topology::TriangleList => count * 3,
+                    Topology::LineList => count * 2,
+                    Topology::TriangleStrip | Topology::LineStrip => count,
+                };
+
+                assert!(count <= MAX_LIST_COUNT);
+                assert!(in_vram(addr, vertices));
+            }
+            Ok((_, _, VDPCommand::SwapBuffers { copy_target })) => assert!(copy_target.map_or(true, |addr| (addr as usize) < VRAM_WORDS)),
+            Ok(_) => {
+            }
+        }
+    }
+
+    panic!("queue at {:#X} didn't end", addr);
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 4 {
+        return;
+    }
+
+    let addr = u32::from_le_bytes(data[..4].try_into().unwrap());
+    let words: Vec<u32> = data[4..].chunks(4).map(|c| c.iter().rev().fold(0, |word, b| (word << 8) | *b as u32)).take(VRAM_WORDS).collect();
+
+    let mut vram = VRAM.lock().unwrap();
+    if vram.is_empty() {
+        vram.resize(VRAM_WORDS, 0);
+    }
+
+    let start = VRAM_WORDS - words.len();
+    vram[start..].copy_from_slice(&words);
+
+    // addresses relative to where the input went, most of the time - otherwise nearly every queue would start in zeroes
+    let addr = if addr & 0x8000_0000 == 0 { (start as u32).wrapping_add(addr & 0xFFFF) } else { addr };
+
+    run::<Validating>(&vram, addr);
+    run::<Unchecked>(&vram, addr);
+
+    vram[start..].fill(0);
+});
@@ -0,0 +1,114 @@
+use std::{sync::{atomic::{AtomicU32, AtomicU64, Ordering}, Mutex}, time::Instant};
+
+use serde_json::{json, Map, Value};
+
+// BIOS calls are SWIs the emulator services itself, through the machine's SWI dispatch table: the guest's SWI just returns
+// to the next instruction once the call is done. calls are numbered by the SWI immediate's low byte, arguments go in r0-r3
+// & results come back in r0
+//
+//   BIOS_WAITVBLANK     park until the next frame signal - the idiomatic end of a guest main loop iteration
+//   BIOS_SLEEPFRAMES    park until r0 frame signals have arrived (0 returns straight away)
+//   BIOS_PUTC           write the low byte of r0 to the UART, exactly as a write to its TX register would
+//   BIOS_MEMCPY         copy r2 bytes from r1 to r0 (which mustn't overlap), returning how many were copied - fewer
+//                       if either range runs into something that isn't plain memory
+//
+// a parked CPU sits in the same place WFI leaves it, so the frame budget, lockstep runs, & pausing all treat it the same.
+// unlike a hand-rolled WFI loop it costs nothing per frame it sleeps through, & it doesn't wake early for other interrupts
+pub const BIOS_WAITVBLANK: u8     = 0x01;
+pub const BIOS_SLEEPFRAMES: u8    = 0x02;
+pub const BIOS_PUTC: u8           = 0x03;
+pub const BIOS_MEMCPY: u8         = 0x04;
+
+pub const BIOS_CALL_NAMES: [(u8, &str);4] = [(BIOS_WAITVBLANK, "waitvblank"), (BIOS_SLEEPFRAMES, "sleepframes"), (BIOS_PUTC, "putc"), (BIOS_MEMCPY, "memcpy")];
+
+const SWI_NUMBERS: usize = 256;
+
+pub struct Bios {
+    // frame signals still to sleep through after the current one
+    sleep_frames: AtomicU32,
+    parked: Mutex<Option<(u8, Instant)>>,
+    calls: [AtomicU64;SWI_NUMBERS],
+    host_ns: [AtomicU64;SWI_NUMBERS],
+}
+
+impl Bios {
+    pub fn new() -> Self {
+        Self {
+            sleep_frames: AtomicU32::new(0),
+            parked: Mutex::new(None),
+            calls: std::array::from_fn(|_| AtomicU64::new(0)),
+            host_ns: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    // CPU thread, from the interrupt hook, for every SWI that has a handler
+    pub fn record_call(self: &Self, num: u8) {
+        self.calls[num as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    // CPU thread, from a call's handler: park the CPU until that many frame signals have arrived. true if the CPU should
+    // stop for it, which is what the handler returns
+    pub fn park(self: &Self, num: u8, frames: u32) -> bool {
+        if frames == 0 {
+            return false;
+        }
+
+        self.sleep_frames.store(frames - 1, Ordering::Relaxed);
+        *self.parked.lock().unwrap() = Some((num, Instant::now()));
+        return true;
+    }
+
+    // CPU thread, after each frame signal while parked: true if there are more to sleep through
+    pub fn sleep_more(self: &Self) -> bool {
+        let remaining = self.sleep_frames.load(Ordering::Relaxed);
+
+        if remaining == 0 {
+            return false;
+        }
+
+        self.sleep_frames.store(remaining - 1, Ordering::Relaxed);
+        return true;
+    }
+
+    pub fn is_parked(self: &Self) -> bool {
+        return self.parked.lock().unwrap().is_some();
+    }
+
+    // CPU thread: the guest is running again, so whatever call parked it is over
+    pub fn unpark(self: &Self) {
+        self.sleep_frames.store(0, Ordering::Relaxed);
+
+        if let Some((num, start)) = self.parked.lock().unwrap().take() {
+            self.host_ns[num as usize].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    // e.g. "waitvblank 600 calls, 9.61s parked (16.0ms avg), putc 12 calls" - only calls the guest actually made
+    pub fn summary(self: &Self) -> String {
+        let parts: Vec<String> = BIOS_CALL_NAMES.iter().filter_map(|(num, name)| {
+            let calls = self.calls[*num as usize].load(Ordering::Relaxed);
+            let ns = self.host_ns[*num as usize].load(Ordering::Relaxed);
+
+            if calls == 0 {
+                return None;
+            }
+
+            if ns == 0 {
+                return Some(format!("{} {} calls", name, calls));
+            }
+
+            return Some(format!("{} {} calls, {:.2}s parked ({:.1}ms avg)", name, calls, ns as f64 / 1e9, ns as f64 / 1e6 / calls as f64));
+        }).collect();
+
+        return if parts.is_empty() { "none".to_string() } else { parts.join(", ") };
+    }
+
+    pub fn to_json(self: &Self) -> Value {
+        let calls: Map<String, Value> = BIOS_CALL_NAMES.iter().map(|(num, name)| (name.to_string(), json!({
+            "calls": self.calls[*num as usize].load(Ordering::Relaxed),
+            "host_ns": self.host_ns[*num as usize].load(Ordering::Relaxed),
+        }))).collect();
+
+        return Value::from(calls);
+    }
+}
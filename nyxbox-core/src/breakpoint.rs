@@ -0,0 +1,499 @@
+use std::{sync::{atomic::{AtomicBool, AtomicU32, Ordering}, mpsc::{self, Receiver, Sender}, Mutex}};
+
+use unicorn_engine::Unicorn;
+
+use crate::{inspect::parse_addr, machine::DEBUG_REGS, symbols::Symbols};
+
+// breakpoints & watchpoints are unicorn hooks that stop the CPU the same way a pause request does: the hook records why &
+// calls emu_stop, & the run loop parks the CPU thread & reports the stop to the frontend over a channel. resuming carries on
+// from where it stopped - a breakpoint lets its own instruction through once, rather than stopping on it again
+//
+// a breakpoint stops before its instruction runs. a watchpoint stops after the access, with the PC on the next instruction.
+// stepping reports through the same channel, once the instruction (or the guest's frame) it ran is done
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        return match s {
+            "r" => Ok(WatchKind::Read),
+            "w" => Ok(WatchKind::Write),
+            "rw" => Ok(WatchKind::ReadWrite),
+            _ => Err(format!("unknown watchpoint kind '{}' (expected r, w or rw)", s)),
+        };
+    }
+
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            WatchKind::Read => "r",
+            WatchKind::Write => "w",
+            WatchKind::ReadWrite => "rw",
+        };
+    }
+}
+
+// a --watch argument: ADDR[:LEN[:KIND]], e.g. "0x1000000:4:w" (LEN defaults to 4, KIND to rw)
+pub fn parse_watch(s: &str) -> Result<(u32, u32, WatchKind), String> {
+    let mut parts = s.split(':');
+
+    let addr = parse_addr(parts.next().unwrap_or(""))?;
+    let len = match parts.next() {
+        Some(len) => parse_addr(len)?,
+        None => 4,
+    };
+    let kind = match parts.next() {
+        Some(kind) => WatchKind::parse(kind)?,
+        None => WatchKind::ReadWrite,
+    };
+
+    if len == 0 {
+        return Err("watchpoint length must be at least 1".to_string());
+    }
+
+    return Ok((addr, len, kind));
+}
+
+// a --break argument: ADDR [after N] [once] [if COND]
+//
+//   after N    let the first N hits through & stop from the one after
+//   once       a temporary breakpoint - it goes away once it's stopped the CPU
+//   if COND    only counts as a hit when COND holds, checked each time the instruction is reached. COND compares values with
+//              == != < <= > >= (unsigned), & combines comparisons with && & || (or parentheses). a value is a number, a
+//              register (r0-r12, sp, lr, pc, cpsr), a memory read ([ADDR] for a word, half[ADDR] or byte[ADDR]), or those
+//              combined with + - & - e.g. "0x1000400 after 2 if r0 == 5 && byte[r1 + 4] != 0"
+//
+// memory reads in a condition go through the bus like a guest read would, so they're best kept to RAM - a read that faults
+// makes the condition false
+#[derive(Clone)]
+pub struct BreakSpec {
+    pub addr: u32,
+    pub ignore: u32,
+    pub temporary: bool,
+    pub cond: Option<(String, Cond)>,
+}
+
+pub fn parse_break(s: &str) -> Result<BreakSpec, String> {
+    let (head, cond) = match s.split_once(" if ") {
+        Some((head, cond)) => (head, Some(cond.trim())),
+        None => (s, None),
+    };
+
+    let mut words = head.split_whitespace();
+    let mut spec = BreakSpec {
+        addr: parse_addr(words.next().unwrap_or(""))?,
+        ignore: 0,
+        temporary: false,
+        cond: None,
+    };
+
+    while let Some(word) = words.next() {
+        match word {
+            "after" => spec.ignore = words.next().ok_or("'after' needs a hit count".to_string())?.parse().map_err(|e| format!("invalid hit count: {}", e))?,
+            "once" => spec.temporary = true,
+            _ => return Err(format!("unexpected '{}' (expected after N, once, or if COND)", word)),
+        }
+    }
+
+    if let Some(cond) = cond {
+        spec.cond = Some((cond.to_string(), Cond::parse(cond)?));
+    }
+
+    return Ok(spec);
+}
+
+impl BreakSpec {
+    // back in the form parse_break reads, with the address named when there's a symbol for it
+    pub fn describe(self: &Self, symbols: &Symbols) -> String {
+        let mut desc = format!("break {}", symbols.describe(self.addr));
+
+        if self.ignore != 0 {
+            desc += &format!(" after {}", self.ignore);
+        }
+
+        if self.temporary {
+            desc += " once";
+        }
+
+        if let Some((cond, _)) = &self.cond {
+            desc += &format!(" if {}", cond);
+        }
+
+        return desc;
+    }
+}
+
+#[derive(Clone)]
+pub enum Cond {
+    Or(Box<Cond>, Box<Cond>),
+    And(Box<Cond>, Box<Cond>),
+    Compare(CmpOp, Value, Value),
+    // a bare value is true when it isn't 0
+    NonZero(Value),
+}
+
+#[derive(Clone, Copy)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone)]
+pub enum Value {
+    Const(u32),
+    Reg(usize),
+    Mem(u32, Box<Value>),
+    Add(Box<Value>, Box<Value>),
+    Sub(Box<Value>, Box<Value>),
+    And(Box<Value>, Box<Value>),
+}
+
+impl Cond {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let tokens = tokenize(s)?;
+        let mut pos = 0;
+        let cond = parse_or(&tokens, &mut pos)?;
+
+        if let Some(token) = tokens.get(pos) {
+            return Err(format!("unexpected '{}' in condition", token));
+        }
+
+        return Ok(cond);
+    }
+
+    // CPU thread, from the breakpoint's code hook. None if a memory read faulted
+    pub fn eval(self: &Self, uc: &Unicorn<'_, ()>) -> Option<bool> {
+        return Some(match self {
+            Cond::Or(a, b) => a.eval(uc)? || b.eval(uc)?,
+            Cond::And(a, b) => a.eval(uc)? && b.eval(uc)?,
+            Cond::NonZero(v) => v.eval(uc)? != 0,
+            Cond::Compare(op, a, b) => {
+                let (a, b) = (a.eval(uc)?, b.eval(uc)?);
+
+                match op {
+                    CmpOp::Eq => a == b,
+                    CmpOp::Ne => a != b,
+                    CmpOp::Lt => a < b,
+                    CmpOp::Le => a <= b,
+                    CmpOp::Gt => a > b,
+                    CmpOp::Ge => a >= b,
+                }
+            }
+        });
+    }
+}
+
+impl Value {
+    fn eval(self: &Self, uc: &Unicorn<'_, ()>) -> Option<u32> {
+        return Some(match self {
+            Value::Const(v) => *v,
+            Value::Reg(idx) => uc.reg_read(DEBUG_REGS[*idx].1).ok()? as u32,
+            Value::Add(a, b) => a.eval(uc)?.wrapping_add(b.eval(uc)?),
+            Value::Sub(a, b) => a.eval(uc)?.wrapping_sub(b.eval(uc)?),
+            Value::And(a, b) => a.eval(uc)? & b.eval(uc)?,
+            Value::Mem(size, addr) => {
+                let mut data = [0;4];
+                uc.mem_read(addr.eval(uc)? as u64, &mut data[..*size as usize]).ok()?;
+                u32::from_le_bytes(data)
+            }
+        });
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        }
+        else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut word = String::new();
+
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                word.push(c);
+                chars.next();
+            }
+
+            tokens.push(word);
+        }
+        else {
+            chars.next();
+
+            // two-character operators first
+            let pair: String = [c, *chars.peek().unwrap_or(&' ')].iter().collect();
+
+            if ["==", "!=", "<=", ">=", "&&", "||"].contains(&pair.as_str()) {
+                chars.next();
+                tokens.push(pair);
+            }
+            else if "<>+-&[]()".contains(c) {
+                tokens.push(c.to_string());
+            }
+            else {
+                return Err(format!("unexpected '{}' in condition", c));
+            }
+        }
+    }
+
+    return Ok(tokens);
+}
+
+fn peek<'a>(tokens: &'a [String], pos: usize) -> &'a str {
+    return tokens.get(pos).map(String::as_str).unwrap_or("");
+}
+
+fn expect(tokens: &[String], pos: &mut usize, token: &str) -> Result<(), String> {
+    if peek(tokens, *pos) != token {
+        return Err(format!("expected '{}' in condition", token));
+    }
+
+    *pos += 1;
+    return Ok(());
+}
+
+fn parse_or(tokens: &[String], pos: &mut usize) -> Result<Cond, String> {
+    let mut cond = parse_and(tokens, pos)?;
+
+    while peek(tokens, *pos) == "||" {
+        *pos += 1;
+        cond = Cond::Or(Box::new(cond), Box::new(parse_and(tokens, pos)?));
+    }
+
+    return Ok(cond);
+}
+
+fn parse_and(tokens: &[String], pos: &mut usize) -> Result<Cond, String> {
+    let mut cond = parse_compare(tokens, pos)?;
+
+    while peek(tokens, *pos) == "&&" {
+        *pos += 1;
+        cond = Cond::And(Box::new(cond), Box::new(parse_compare(tokens, pos)?));
+    }
+
+    return Ok(cond);
+}
+
+fn parse_compare(tokens: &[String], pos: &mut usize) -> Result<Cond, String> {
+    // a parenthesised condition, rather than a parenthesised value
+    if peek(tokens, *pos) == "(" {
+        let start = *pos;
+        *pos += 1;
+
+        if let Ok(cond) = parse_or(tokens, pos) {
+            if peek(tokens, *pos) == ")" && !is_value_op(peek(tokens, *pos + 1)) && cmp_op(peek(tokens, *pos + 1)).is_none() {
+                *pos += 1;
+                return Ok(cond);
+            }
+        }
+
+        *pos = start;
+    }
+
+    let a = parse_value(tokens, pos)?;
+
+    let Some(op) = cmp_op(peek(tokens, *pos)) else {
+        return Ok(Cond::NonZero(a));
+    };
+
+    *pos += 1;
+    return Ok(Cond::Compare(op, a, parse_value(tokens, pos)?));
+}
+
+fn cmp_op(token: &str) -> Option<CmpOp> {
+    return match token {
+        "==" => Some(CmpOp::Eq),
+        "!=" => Some(CmpOp::Ne),
+        "<" => Some(CmpOp::Lt),
+        "<=" => Some(CmpOp::Le),
+        ">" => Some(CmpOp::Gt),
+        ">=" => Some(CmpOp::Ge),
+        _ => None,
+    };
+}
+
+fn is_value_op(token: &str) -> bool {
+    return matches!(token, "+" | "-" | "&");
+}
+
+fn parse_value(tokens: &[String], pos: &mut usize) -> Result<Value, String> {
+    let mut value = parse_atom(tokens, pos)?;
+
+    while is_value_op(peek(tokens, *pos)) {
+        let op = peek(tokens, *pos).to_string();
+        *pos += 1;
+
+        let rhs = Box::new(parse_atom(tokens, pos)?);
+        let lhs = Box::new(value);
+
+        value = match op.as_str() {
+            "+" => Value::Add(lhs, rhs),
+            "-" => Value::Sub(lhs, rhs),
+            _ => Value::And(lhs, rhs),
+        };
+    }
+
+    return Ok(value);
+}
+
+fn parse_atom(tokens: &[String], pos: &mut usize) -> Result<Value, String> {
+    let token = peek(tokens, *pos).to_lowercase();
+    *pos += 1;
+
+    let size = match token.as_str() {
+        "[" => Some(4),
+        "half" => Some(2),
+        "byte" => Some(1),
+        _ => None,
+    };
+
+    if let Some(size) = size {
+        if size != 4 {
+            expect(tokens, pos, "[")?;
+        }
+
+        let addr = parse_value(tokens, pos)?;
+        expect(tokens, pos, "]")?;
+        return Ok(Value::Mem(size, Box::new(addr)));
+    }
+
+    if token == "(" {
+        let value = parse_value(tokens, pos)?;
+        expect(tokens, pos, ")")?;
+        return Ok(value);
+    }
+
+    if let Some(idx) = DEBUG_REGS.iter().position(|(name, _)| *name == token) {
+        return Ok(Value::Reg(idx));
+    }
+
+    if token.is_empty() {
+        return Err("condition ends too soon".to_string());
+    }
+
+    return parse_addr(&token).map(Value::Const).map_err(|_| format!("unexpected '{}' in condition", token));
+}
+
+// a breakpoint's state while it's set: how many times it's been hit, & whether a temporary one has had its stop
+pub struct Breakpoint {
+    pub spec: BreakSpec,
+    hits: AtomicU32,
+    spent: AtomicBool,
+}
+
+impl Breakpoint {
+    pub fn new(spec: BreakSpec) -> Self {
+        Self {
+            spec,
+            hits: AtomicU32::new(0),
+            spent: AtomicBool::new(false),
+        }
+    }
+
+    pub fn hits(self: &Self) -> u32 {
+        return self.hits.load(Ordering::Relaxed);
+    }
+
+    // a temporary breakpoint that's done its job, & only waits for its hook to be removed
+    pub fn is_spent(self: &Self) -> bool {
+        return self.spent.load(Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone)]
+pub enum DebugStop {
+    Breakpoint { id: u32, pc: u32, hits: u32 },
+    Watchpoint { id: u32, pc: u32, addr: u32, size: u32, write: bool },
+    Step { pc: u32 },
+    FrameStep { pc: u32 },
+}
+
+impl DebugStop {
+    pub fn describe(self: &Self, symbols: &Symbols) -> String {
+        return match self {
+            DebugStop::Breakpoint { id, pc, hits } => format!("breakpoint {} @ pc {} (hit {})", id, symbols.describe(*pc), hits),
+            DebugStop::Watchpoint { id, pc, addr, size, write } => format!("watchpoint {}: {}-byte {} {} @ pc {}", id, size, if *write { "write to" } else { "read of" }, symbols.describe(*addr), symbols.describe(*pc)),
+            DebugStop::Step { pc } => format!("step @ pc {}", symbols.describe(*pc)),
+            DebugStop::FrameStep { pc } => format!("frame step @ pc {}", symbols.describe(*pc)),
+        };
+    }
+}
+
+// shared between the hooks (which record a hit) & the run loop (which acts on it)
+pub struct DebugTraps {
+    hit: Mutex<Option<DebugStop>>,
+    skip: Mutex<Option<u32>>,
+    stops: Mutex<Sender<DebugStop>>,
+}
+
+impl DebugTraps {
+    pub fn new() -> (Self, Receiver<DebugStop>) {
+        let (stops, stops_rx) = mpsc::channel();
+
+        return (Self {
+            hit: Mutex::new(None),
+            skip: Mutex::new(None),
+            stops: Mutex::new(stops),
+        }, stops_rx);
+    }
+
+    // CPU thread, from a breakpoint's code hook: true if the CPU should stop here. resuming past it doesn't count as another
+    // hit, & neither does reaching it with its condition false
+    pub fn on_breakpoint(self: &Self, id: u32, bp: &Breakpoint, uc: &Unicorn<'_, ()>, pc: u32) -> bool {
+        if self.skip.lock().unwrap().take() == Some(pc) || bp.is_spent() {
+            return false;
+        }
+
+        if let Some((_, cond)) = &bp.spec.cond {
+            if cond.eval(uc) != Some(true) {
+                return false;
+            }
+        }
+
+        let hits = bp.hits.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if hits <= bp.spec.ignore {
+            return false;
+        }
+
+        if bp.spec.temporary {
+            bp.spent.store(true, Ordering::Relaxed);
+        }
+
+        self.on_hit(DebugStop::Breakpoint { id, pc, hits });
+        return true;
+    }
+
+    // CPU thread, from a hook. the first hit wins if an instruction trips several before the CPU stops
+    pub fn on_hit(self: &Self, stop: DebugStop) {
+        self.hit.lock().unwrap().get_or_insert(stop);
+    }
+
+    // CPU thread, once emu_start has returned: report the hit, if that's why it stopped
+    pub fn take_hit(self: &Self) -> bool {
+        let Some(stop) = self.hit.lock().unwrap().take() else {
+            return false;
+        };
+
+        self.report(stop);
+        return true;
+    }
+
+    pub fn report(self: &Self, stop: DebugStop) {
+        let _ = self.stops.lock().unwrap().send(stop);
+    }
+
+    // CPU thread, resuming at pc after being parked: a breakpoint there has already stopped the CPU (or the user stopped
+    // in front of it), so let its instruction through once rather than stopping again without making progress
+    pub fn skip_once(self: &Self, pc: u32) {
+        *self.skip.lock().unwrap() = Some(pc);
+    }
+}
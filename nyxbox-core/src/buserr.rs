@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use unicorn_engine::{uc_error, MemType};
+
+use crate::{peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}};
+
+pub const BUSERR_MEM_SIZE: u32 = 4096;
+
+pub const BUSERRBIT_READ: u32       = 1;
+pub const BUSERRBIT_WRITE: u32      = 2;
+pub const BUSERRBIT_FETCH: u32      = 4;
+
+// what an access to an address nothing is mapped at does
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum UnmappedPolicy {
+    /// Treat it like any other guest fault, as --guest-faults says
+    #[default]
+    Fault,
+    /// Reads return the open-bus value & writes are dropped. Instruction fetches still fault
+    OpenBus,
+    /// Always deliver a data (or prefetch) abort to the guest, whatever --guest-faults says
+    Abort,
+}
+
+// whether emulation stopped because of an access to nothing at all, rather than memory the guest isn't allowed to touch
+pub fn is_unmapped(e: uc_error) -> bool {
+    return matches!(e, uc_error::READ_UNMAPPED | uc_error::WRITE_UNMAPPED | uc_error::FETCH_UNMAPPED);
+}
+
+// diagnostics for accesses that hit nothing. whichever way the access goes (open bus, abort, or halting the CPU) it's latched
+// here first, so a guest abort handler - or the user, from the debugger - can see what was touched:
+//
+//   STATUS     kinds of access seen since last cleared (READ, WRITE, FETCH bits) - write clears
+//   ADDR       address of the most recent one
+//   PC         guest PC of the most recent one
+//   COUNT      (read only) how many there have been, saturating
+//   OPENBUS    (read only) what an open-bus read returns, per byte lane
+pub struct BusError {
+    open_bus: u32,
+    state: PeripheralLock<BusErrorState>,
+    count: AtomicU64,
+}
+
+#[derive(Default)]
+struct BusErrorState {
+    status: u32,
+    addr: u32,
+    pc: u32,
+}
+
+impl BusError {
+    pub fn new(open_bus: u32) -> Self {
+        Self {
+            open_bus,
+            state: PeripheralLock::new(BusErrorState::default()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    // CPU thread, from an unmapped access hook
+    pub fn record_fault(self: &Self, mem_type: MemType, addr: u64, pc: u32) {
+        let kind = match mem_type {
+            MemType::READ_UNMAPPED => BUSERRBIT_READ,
+            MemType::WRITE_UNMAPPED => BUSERRBIT_WRITE,
+            MemType::FETCH_UNMAPPED => BUSERRBIT_FETCH,
+            _ => 0,
+        };
+
+        self.record(kind, addr as u32, pc);
+    }
+
+    // CPU thread, from an open-bus read: the value on the bus, in the lanes the access covers
+    pub fn on_read(self: &Self, addr: u32, size: u32, pc: u32) -> u32 {
+        self.record(BUSERRBIT_READ, addr, pc);
+
+        let lanes = if size >= 4 { u32::MAX } else { (1 << (size * 8)) - 1 };
+        return (self.open_bus >> ((addr & 3) * 8)) & lanes;
+    }
+
+    // CPU thread, from an open-bus write - which goes nowhere
+    pub fn on_write(self: &Self, addr: u32, pc: u32) {
+        self.record(BUSERRBIT_WRITE, addr, pc);
+    }
+
+    fn record(self: &Self, kind: u32, addr: u32, pc: u32) {
+        let mut state = self.state.lock();
+
+        state.status |= kind;
+        state.addr = addr;
+        state.pc = pc;
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(self: &Self) -> u64 {
+        return self.count.load(Ordering::Relaxed);
+    }
+
+    // the most recent access, for the exit summary
+    pub fn last(self: &Self) -> (u32, u32) {
+        let state = self.state.lock();
+        return (state.addr, state.pc);
+    }
+}
+
+impl Peripheral for BusError {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // STATUS
+                return self.state.lock().status;
+            }
+            0x01 => {
+                // ADDR
+                return self.state.lock().addr;
+            }
+            0x02 => {
+                // PC
+                return self.state.lock().pc;
+            }
+            0x03 => {
+                // COUNT
+                return self.count().min(u32::MAX as u64) as u32;
+            }
+            0x04 => {
+                // OPENBUS
+                return self.open_bus;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, _val: u32) {
+        match addr {
+            0x00 => {
+                // STATUS (write clears)
+                self.state.lock().status = 0;
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("buserr", self.state.stats())];
+    }
+
+    fn save_state(self: &Self) -> Vec<u8> {
+        let state = self.state.lock();
+        let mut w = StateWriter::new();
+
+        w.u32(state.status);
+        w.u32(state.addr);
+        w.u32(state.pc);
+        w.u64(self.count());
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let status = r.u32()?;
+        let addr = r.u32()?;
+        let pc = r.u32()?;
+        let count = r.u64()?;
+        r.finish()?;
+
+        *self.state.lock() = BusErrorState { status, addr, pc };
+        self.count.store(count, Ordering::Relaxed);
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{path::PathBuf, sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, Receiver, SyncSender, TrySendError}, Arc, Mutex}, thread::{self, JoinHandle}};
+
+use crate::screenshot;
+
+// capture output (offscreen frames, screenshots) is encoded & written on worker threads, so recording doesn't hold up
+// emulation. the frontend still pays for reading the pixels back, & that readback is synchronous (it waits for the GPU
+// to go idle), so every captured frame costs a GPU stall. jobs wait in a bounded queue, & when the workers fall behind
+// the overflow policy decides whether the frontend waits or the job is dropped
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CaptureOverflow {
+    /// Drop captures while the encoders are behind - emulation never waits on them
+    #[default]
+    Drop,
+    /// Wait for room in the queue - every capture is kept, even if emulation slows down
+    Block,
+}
+
+pub enum CaptureJob {
+    Png { path: PathBuf, width: u32, height: u32, rgba: Vec<u8> },
+}
+
+impl CaptureJob {
+    fn run(self: Self) -> Result<(), String> {
+        match self {
+            CaptureJob::Png { path, width, height, rgba } => {
+                return screenshot::write_png(&path, width, height, &rgba).map_err(|e| format!("failed to write {}: {}", path.display(), e));
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct CaptureCounters {
+    written: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+pub struct CaptureWriter {
+    sender: Mutex<Option<SyncSender<CaptureJob>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    overflow: CaptureOverflow,
+    counters: Arc<CaptureCounters>,
+}
+
+impl CaptureWriter {
+    pub fn new(threads: usize, queue_len: usize, overflow: CaptureOverflow) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<CaptureJob>(queue_len.max(1));
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(CaptureCounters::default());
+
+        let workers = (0..threads.max(1)).map(|idx| {
+            let receiver = receiver.clone();
+            let counters = counters.clone();
+
+            thread::Builder::new()
+                .name(format!("capture{}", idx))
+                .spawn(move || worker(receiver, counters))
+                .unwrap()
+        }).collect();
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(workers),
+            overflow,
+            counters,
+        }
+    }
+
+    // returns false if the job was dropped instead of queued
+    pub fn submit(self: &Self, job: CaptureJob) -> bool {
+        let sender = self.sender.lock().unwrap();
+        let Some(sender) = sender.as_ref() else {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+
+        let sent = match self.overflow {
+            CaptureOverflow::Drop => match sender.try_send(job) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+            CaptureOverflow::Block => sender.send(job).is_ok(),
+        };
+
+        if !sent {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        return sent;
+    }
+
+    pub fn write_png(self: &Self, path: PathBuf, width: u32, height: u32, rgba: Vec<u8>) -> bool {
+        return self.submit(CaptureJob::Png { path, width, height, rgba });
+    }
+
+    // write out everything still queued & stop the workers. anything submitted afterwards is ignored
+    pub fn finish(self: &Self) {
+        self.sender.lock().unwrap().take();
+
+        for worker in self.workers.lock().unwrap().drain(..) {
+            let _ = worker.join();
+        }
+    }
+
+    pub fn summary(self: &Self) -> (u64, u64, u64) {
+        return (self.counters.written.load(Ordering::Relaxed), self.counters.dropped.load(Ordering::Relaxed), self.counters.failed.load(Ordering::Relaxed));
+    }
+}
+
+impl Drop for CaptureWriter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn worker(receiver: Arc<Mutex<Receiver<CaptureJob>>>, counters: Arc<CaptureCounters>) {
+    loop {
+        // only hold the lock while waiting for a job, so the others can encode in the meantime
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => break,
+        };
+
+        match job.run() {
+            Ok(()) => {
+                counters.written.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                println!("{}", e);
+            }
+        }
+    }
+}
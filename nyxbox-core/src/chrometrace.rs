@@ -0,0 +1,150 @@
+use std::{borrow::Cow, fs::File, io::{BufWriter, Write}, path::Path, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Instant};
+
+use serde_json::{json, Value};
+
+// a timeline of what the machine's threads were doing, in the Trace Event Format chrome://tracing & Perfetto load: spans
+// for each CPU slice & WFI wait, each VDP command queue & command, & the frontend's GPU work, with interrupts as instants
+// where they're raised & where the CPU takes them. events are coarse enough (nothing per instruction) that they're just
+// kept in memory & written out at the end
+pub const TRACK_CPU: u32        = 1;
+pub const TRACK_FRONTEND: u32   = 2;
+pub const TRACK_INTERRUPTS: u32 = 3;
+
+const TRACK_NAMES: [(u32, &str);3] = [(TRACK_CPU, "CPU"), (TRACK_FRONTEND, "frontend (VDP & GPU)"), (TRACK_INTERRUPTS, "interrupts")];
+
+// past this many events, new ones are counted but dropped - a long session traced by mistake shouldn't eat all the memory
+const MAX_EVENTS: usize = 2_000_000;
+
+struct Event {
+    name: Cow<'static, str>,
+    cat: &'static str,
+    track: u32,
+    // microseconds since the trace started; a duration for spans, none for instants
+    ts: f64,
+    dur: Option<f64>,
+    args: Option<Value>,
+}
+
+pub struct ChromeTrace {
+    start: Instant,
+    events: Mutex<Vec<Event>>,
+    dropped: AtomicU64,
+}
+
+impl ChromeTrace {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Mutex::new(Vec::new()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    // the timestamp for a span about to start
+    pub fn now(self: &Self) -> f64 {
+        return self.start.elapsed().as_secs_f64() * 1_000_000.0;
+    }
+
+    fn push(self: &Self, event: Event) {
+        let mut events = self.events.lock().unwrap();
+
+        if events.len() < MAX_EVENTS {
+            events.push(event);
+        }
+        else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // something that started at `start` (from now()) & has just finished
+    pub fn span(self: &Self, track: u32, cat: &'static str, name: impl Into<Cow<'static, str>>, start: f64, args: Option<Value>) {
+        let end = self.now();
+        self.push(Event { name: name.into(), cat, track, ts: start, dur: Some((end - start).max(0.0)), args });
+    }
+
+    // a span that ends when the returned guard is dropped, however the scope it's in is left
+    pub fn begin(self: &Arc<Self>, track: u32, cat: &'static str, name: impl Into<Cow<'static, str>>) -> Span {
+        return Span {
+            trace: self.clone(),
+            track,
+            cat,
+            name: Some(name.into()),
+            start: self.now(),
+        };
+    }
+
+    pub fn instant(self: &Self, track: u32, cat: &'static str, name: impl Into<Cow<'static, str>>, args: Option<Value>) {
+        let ts = self.now();
+        self.push(Event { name: name.into(), cat, track, ts, dur: None, args });
+    }
+
+    // write everything so far as a JSON trace, returning how many events went in
+    pub fn save(self: &Self, path: &Path) -> Result<usize, String> {
+        let err = |e: std::io::Error| format!("failed to write {}: {}", path.display(), e);
+        let mut out = BufWriter::new(File::create(path).map_err(err)?);
+
+        let mut events = self.events.lock().unwrap();
+
+        // spans are pushed when they end, so nested ones come before the spans around them - viewers want start order, &
+        // the outer of two spans starting together first
+        events.sort_by(|a, b| a.ts.total_cmp(&b.ts).then(b.dur.unwrap_or(0.0).total_cmp(&a.dur.unwrap_or(0.0))));
+
+        write!(out, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[").map_err(err)?;
+
+        let names = TRACK_NAMES.iter().map(|(track, name)| json!({ "ph": "M", "name": "thread_name", "pid": 1, "tid": track, "args": { "name": name } }));
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        let note = (dropped != 0).then(|| json!({ "ph": "M", "name": "process_labels", "pid": 1, "args": { "labels": format!("{} events dropped", dropped) } }));
+
+        for (idx, event) in names.chain(note).chain(events.iter().map(Event::to_json)).enumerate() {
+            write!(out, "{}\n{}", if idx == 0 { "" } else { "," }, event).map_err(err)?;
+        }
+
+        write!(out, "]}}").map_err(err)?;
+        out.flush().map_err(err)?;
+
+        return Ok(events.len());
+    }
+}
+
+pub struct Span {
+    trace: Arc<ChromeTrace>,
+    track: u32,
+    cat: &'static str,
+    name: Option<Cow<'static, str>>,
+    start: f64,
+}
+
+impl Drop for Span {
+    fn drop(self: &mut Self) {
+        self.trace.span(self.track, self.cat, self.name.take().unwrap(), self.start, None);
+    }
+}
+
+impl Event {
+    fn to_json(self: &Self) -> Value {
+        let mut event = json!({
+            "name": self.name,
+            "cat": self.cat,
+            "pid": 1,
+            "tid": self.track,
+            "ts": self.ts,
+        });
+
+        match self.dur {
+            Some(dur) => {
+                event["ph"] = json!("X");
+                event["dur"] = json!(dur);
+            }
+            None => {
+                event["ph"] = json!("i");
+                event["s"] = json!("t");
+            }
+        }
+
+        if let Some(args) = &self.args {
+            event["args"] = args.clone();
+        }
+
+        return event;
+    }
+}
@@ -0,0 +1,479 @@
+use std::{sync::{Arc, Condvar}, thread, time::Duration};
+
+use crate::{intc::IrqLine, peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}, timebase::{frame_to_ns, ns_to_us, Timebase, NS_PER_US}};
+
+pub const CLOCK_MEM_SIZE: u32 = 4096;
+
+// bits in the IRQ register
+pub const CLOCKIRQ_CTR0: u32    = 1;
+pub const CLOCKIRQ_CTR1: u32    = 2;
+
+// everything in the clock is interdependent, so it's guarded by one lock. the frontend only touches it at startup & shutdown
+//
+// a counter with its interrupt enabled (STATUS bit 5/6) & a non-zero period (CTRnP, in microseconds) fires every time it
+// crosses a multiple of the period: the counter's bit latches in IRQ (write 1s to acknowledge) & the timer line is raised
+// on the interrupt controller. a timer thread sleeps until the next crossing, so interrupts land mid-frame rather than
+// at frame boundaries
+pub struct Clock {
+    state: PeripheralLock<ClockState>,
+    // wakes the timer thread when its next deadline might have moved
+    timer_wake: Condvar,
+}
+
+struct ClockState {
+    rtc_en: bool,
+    ctr0_en: bool,
+    ctr1_en: bool,
+    ctr0_intr: bool,
+    ctr1_intr: bool,
+    ctr0_intr_p: u32,
+    ctr1_intr_p: u32,
+    ctr0: u64,
+    ctr1: u64,
+    dt_adjust: i64,
+    timebase: Arc<Timebase>,
+    ctr0_base: u64,
+    ctr1_base: u64,
+    // counter values the next interrupts are due at
+    ctr0_next: u64,
+    ctr1_next: u64,
+    irq_pending: u32,
+    irq: IrqLine,
+    shutdown: bool,
+    timestamp: u32,
+}
+
+impl Clock {
+    // counters tick in emulated microseconds & the RTC in emulated seconds, both off the shared timebase
+    pub fn new(timebase: Arc<Timebase>, irq: IrqLine) -> Self {
+        Self {
+            state: PeripheralLock::new(ClockState::new(timebase, irq)),
+            timer_wake: Condvar::new(),
+        }
+    }
+
+    pub fn start_timer(self: &Arc<Self>) {
+        let clock = self.clone();
+
+        thread::Builder::new()
+            .name("clock-timer".to_string())
+            .spawn(move || clock.timer_thread())
+            .unwrap();
+    }
+
+    pub fn stop_timer(self: &Self) {
+        self.state.lock().shutdown = true;
+        self.timer_wake.notify_one();
+    }
+
+    // frontend: emulated time has moved on to a new frame, so deadlines past the old frame's end are reachable now
+    pub fn frame_advanced(self: &Self) {
+        self.timer_wake.notify_one();
+    }
+
+    // idle skip has moved emulated time on within the frame, so the timer thread's next deadline is closer than it was
+    pub fn time_skipped(self: &Self) {
+        self.timer_wake.notify_one();
+    }
+
+    // deterministic mode has no timer thread - the CPU thread raises the interrupts itself, at the instruction their time
+    // comes round on. this is the emulated nanosecond the next one is due at
+    pub fn next_deadline_ns(self: &Self) -> Option<u64> {
+        return self.state.lock().next_deadline().map(|us| us * NS_PER_US);
+    }
+
+    // ...& this raises whatever's due by now
+    pub fn poll(self: &Self) {
+        self.state.lock().check_timers();
+    }
+
+    fn timer_thread(self: &Self) {
+        let mut state = self.state.lock();
+
+        while !state.shutdown {
+            state.check_timers();
+
+            // emulated time stops at the end of the current frame until the frontend starts the next one, so a deadline
+            // beyond that waits for frame_advanced() rather than spinning on a clock that isn't moving
+            let now = state.timebase.now_us();
+            let frame_end = ns_to_us(frame_to_ns(state.timebase.frame() + 1));
+
+            state = match state.next_deadline().filter(|deadline| *deadline < frame_end) {
+                Some(deadline) => self.timer_wake.wait_timeout(state, Duration::from_micros(deadline.saturating_sub(now))).unwrap().0,
+                None => self.timer_wake.wait(state).unwrap(),
+            };
+        }
+    }
+
+    // offset between the guest's RTC and the host's wall clock, in seconds - this is what gets persisted across power cycles
+    pub fn rtc_host_offset(self: &Self) -> i64 {
+        let state = self.state.lock();
+        let secs_since_startup = state.timebase.now_secs() as i64;
+        return (secs_since_startup + state.dt_adjust) - chrono::Utc::now().timestamp();
+    }
+
+    // the guest's RTC reading at emulated time zero, in seconds - what a movie needs to see the same dates again
+    pub fn rtc_start(self: &Self) -> i64 {
+        return self.state.lock().dt_adjust;
+    }
+
+    pub fn set_rtc_start(self: &Self, start: i64) {
+        let mut state = self.state.lock();
+        let secs_since_startup = state.timebase.now_secs() as i64;
+        state.dt_adjust = start;
+        state.timestamp = (secs_since_startup + start) as u32;
+    }
+
+    pub fn set_rtc_host_offset(self: &Self, offset: i64) {
+        let mut state = self.state.lock();
+        let secs_since_startup = state.timebase.now_secs() as i64;
+        state.dt_adjust = chrono::Utc::now().timestamp() + offset - secs_since_startup;
+        state.timestamp = (secs_since_startup + state.dt_adjust) as u32;
+    }
+}
+
+impl ClockState {
+    fn new(timebase: Arc<Timebase>, irq: IrqLine) -> Self {
+        let ctr_base = timebase.now_us();
+
+        Self {
+            rtc_en: false,
+            ctr0_en: false,
+            ctr1_en: false,
+            ctr0_intr: false,
+            ctr1_intr: false,
+            ctr0_intr_p: 0,
+            ctr1_intr_p: 0,
+            ctr0: 0,
+            ctr1: 0,
+            ctr0_base: ctr_base,
+            ctr1_base: ctr_base,
+            ctr0_next: 0,
+            ctr1_next: 0,
+            irq_pending: 0,
+            irq,
+            shutdown: false,
+            dt_adjust: 0,
+            timebase,
+            timestamp: 0,
+        }
+    }
+
+    // bring the stored counter values up to date (running) or keep them frozen (stopped). the timer thread calls this
+    // constantly, so it must never panic - a panic there would leave the state locked & poisoned for every later access
+    fn sync_counters(self: &mut Self) {
+        let now = self.timebase.now_us();
+
+        if self.ctr0_en {
+            self.ctr0 = now.saturating_sub(self.ctr0_base);
+        }
+        else {
+            self.ctr0_base = now.saturating_sub(self.ctr0);
+        }
+
+        if self.ctr1_en {
+            self.ctr1 = now.saturating_sub(self.ctr1_base);
+        }
+        else {
+            self.ctr1_base = now.saturating_sub(self.ctr1);
+        }
+    }
+
+    // next interrupts are due at the first multiple of each period past the counter's current value
+    fn rearm(self: &mut Self) {
+        self.sync_counters();
+        self.ctr0_next = next_multiple(self.ctr0, self.ctr0_intr_p);
+        self.ctr1_next = next_multiple(self.ctr1, self.ctr1_intr_p);
+    }
+
+    fn check_timers(self: &mut Self) {
+        self.sync_counters();
+
+        let mut fired = 0;
+
+        if self.ctr0_en && self.ctr0_intr && self.ctr0_intr_p != 0 && self.ctr0 >= self.ctr0_next {
+            // a late check covers several periods with the one interrupt
+            self.ctr0_next = next_multiple(self.ctr0, self.ctr0_intr_p);
+            fired |= CLOCKIRQ_CTR0;
+        }
+
+        if self.ctr1_en && self.ctr1_intr && self.ctr1_intr_p != 0 && self.ctr1 >= self.ctr1_next {
+            self.ctr1_next = next_multiple(self.ctr1, self.ctr1_intr_p);
+            fired |= CLOCKIRQ_CTR1;
+        }
+
+        if fired != 0 {
+            crate::log!(Clock, Trace, "timer interrupt {:#x} at {}us", fired, self.timebase.now_us());
+            self.irq_pending |= fired;
+            self.irq.raise();
+        }
+    }
+
+    // emulated microsecond the next interrupt is due at, if any are armed
+    fn next_deadline(self: &Self) -> Option<u64> {
+        let ctr0 = (self.ctr0_en && self.ctr0_intr && self.ctr0_intr_p != 0).then(|| self.ctr0_base + self.ctr0_next);
+        let ctr1 = (self.ctr1_en && self.ctr1_intr && self.ctr1_intr_p != 0).then(|| self.ctr1_base + self.ctr1_next);
+
+        return match (ctr0, ctr1) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+fn next_multiple(ctr: u64, period: u32) -> u64 {
+    if period == 0 {
+        return 0;
+    }
+
+    return (ctr / period as u64 + 1) * period as u64;
+}
+
+impl Peripheral for Clock {
+    fn read(self: &Self, addr: u32) -> u32 {
+        return self.state.lock().read(addr);
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        self.state.lock().write(addr, val);
+        self.timer_wake.notify_one();
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("clock", self.state.stats())];
+    }
+
+    // counters are saved as their values rather than their bases, so they pick up from wherever emulated time is on load
+    fn save_state(self: &Self) -> Vec<u8> {
+        let mut state = self.state.lock();
+        let mut w = StateWriter::new();
+
+        state.sync_counters();
+
+        w.bool(state.rtc_en);
+        w.bool(state.ctr0_en);
+        w.bool(state.ctr1_en);
+        w.bool(state.ctr0_intr);
+        w.bool(state.ctr1_intr);
+        w.u32(state.ctr0_intr_p);
+        w.u32(state.ctr1_intr_p);
+        w.u64(state.ctr0);
+        w.u64(state.ctr1);
+        w.u64(state.ctr0_next);
+        w.u64(state.ctr1_next);
+        w.u32(state.irq_pending);
+        w.u64(state.dt_adjust as u64);
+        w.u32(state.timestamp);
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let rtc_en = r.bool()?;
+        let ctr0_en = r.bool()?;
+        let ctr1_en = r.bool()?;
+        let ctr0_intr = r.bool()?;
+        let ctr1_intr = r.bool()?;
+        let ctr0_intr_p = r.u32()?;
+        let ctr1_intr_p = r.u32()?;
+        let ctr0 = r.u64()?;
+        let ctr1 = r.u64()?;
+        let ctr0_next = r.u64()?;
+        let ctr1_next = r.u64()?;
+        let irq_pending = r.u32()?;
+        let dt_adjust = r.u64()? as i64;
+        let timestamp = r.u32()?;
+        r.finish()?;
+
+        let mut state = self.state.lock();
+        let now = state.timebase.now_us();
+
+        if ctr0 > now || ctr1 > now {
+            return Err("counters are ahead of the state's emulated time".to_string());
+        }
+
+        state.rtc_en = rtc_en;
+        state.ctr0_en = ctr0_en;
+        state.ctr1_en = ctr1_en;
+        state.ctr0_intr = ctr0_intr;
+        state.ctr1_intr = ctr1_intr;
+        state.ctr0_intr_p = ctr0_intr_p;
+        state.ctr1_intr_p = ctr1_intr_p;
+        state.ctr0 = ctr0;
+        state.ctr1 = ctr1;
+        state.ctr0_base = now - ctr0;
+        state.ctr1_base = now - ctr1;
+        state.ctr0_next = ctr0_next;
+        state.ctr1_next = ctr1_next;
+        state.irq_pending = irq_pending;
+        state.dt_adjust = dt_adjust;
+        state.timestamp = timestamp;
+        drop(state);
+
+        self.timer_wake.notify_one();
+        return Ok(());
+    }
+
+    // the counters keep the values they'd reached by old_ns, counting on from the new time - a state without a clock
+    // section (or one load_state refuses) would otherwise leave bases past the new time
+    fn time_jumped(self: &Self, old_ns: u64) {
+        let mut state = self.state.lock();
+        let old = ns_to_us(old_ns);
+        let now = state.timebase.now_us();
+
+        if state.ctr0_en {
+            state.ctr0 = old.saturating_sub(state.ctr0_base);
+        }
+
+        if state.ctr1_en {
+            state.ctr1 = old.saturating_sub(state.ctr1_base);
+        }
+
+        // a counter can't have counted for longer than emulated time has run
+        state.ctr0 = state.ctr0.min(now);
+        state.ctr1 = state.ctr1.min(now);
+        state.ctr0_base = now - state.ctr0;
+        state.ctr1_base = now - state.ctr1;
+        state.rearm();
+        drop(state);
+
+        self.timer_wake.notify_one();
+    }
+}
+
+impl ClockState {
+    fn read(self: &mut Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // STATUS
+                return
+                    if self.rtc_en { 1 } else { 0 } |
+                    if self.ctr0_en { 2 } else { 0 } |
+                    if self.ctr1_en { 4 } else { 0 } |
+                    if self.ctr0_intr { 32 } else { 0 } |
+                    if self.ctr1_intr { 64 } else { 0 };
+            }
+            0x01 => {
+                // DT
+                if self.rtc_en {
+                    let secs_since_startup = self.timebase.now_secs() as i64;
+                    self.timestamp = (secs_since_startup + self.dt_adjust) as u32;
+                }
+                else {
+                    let secs_since_startup = self.timebase.now_secs() as i64;
+                    let desired_secs = self.timestamp as i64;
+                    self.dt_adjust = desired_secs - secs_since_startup;
+                }
+
+                return self.timestamp;
+            }
+            0x02 => {
+                // CTR0LO
+                if self.ctr0_en {
+                    self.ctr0 = self.timebase.now_us().saturating_sub(self.ctr0_base);
+                }
+                else {
+                    self.ctr0_base = self.timebase.now_us().saturating_sub(self.ctr0);
+                }
+                return (self.ctr0 & 0xFFFFFFFF) as u32;
+            }
+            0x03 => {
+                // CTR0HI
+                return (self.ctr0 >> 32) as u32;
+            }
+            0x04 => {
+                // CTR1LO
+                if self.ctr1_en {
+                    self.ctr1 = self.timebase.now_us().saturating_sub(self.ctr1_base);
+                }
+                else {
+                    self.ctr1_base = self.timebase.now_us().saturating_sub(self.ctr1);
+                }
+                return (self.ctr1 & 0xFFFFFFFF) as u32;
+            }
+            0x05 => {
+                // CTR1HI
+                return (self.ctr1 >> 32) as u32;
+            }
+            0x06 => {
+                // CTR0P
+                return self.ctr0_intr_p;
+            }
+            0x07 => {
+                // CTR1P
+                return self.ctr1_intr_p;
+            }
+            0x08 => {
+                // IRQ
+                return self.irq_pending;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &mut Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // STATUS - counters being stopped or started keep the value they had up to now, & anything already due
+                // under the old settings still fires
+                self.check_timers();
+
+                self.rtc_en = (val & 1) != 0;
+                self.ctr0_en = (val & 2) != 0;
+                self.ctr1_en = (val & 4) != 0;
+                self.ctr0_intr = (val & 32) != 0;
+                self.ctr1_intr = (val & 64) != 0;
+
+                crate::log!(Clock, Debug, "status {:#x}: rtc {}, ctr0 {}{}, ctr1 {}{}", val, self.rtc_en, self.ctr0_en, if self.ctr0_intr { " (irq)" } else { "" }, self.ctr1_en, if self.ctr1_intr { " (irq)" } else { "" });
+
+                let ctr_base = self.timebase.now_us();
+
+                if (val & 8) != 0 {
+                    // reset ctr0
+                    self.ctr0_base = ctr_base;
+                    self.ctr0 = 0;
+                }
+
+                if (val & 16) != 0 {
+                    // reset ctr1
+                    self.ctr1_base = ctr_base;
+                    self.ctr1 = 0;
+                }
+
+                self.rearm();
+
+                let secs_since_startup = self.timebase.now_secs() as i64;
+                self.timestamp = (secs_since_startup + self.dt_adjust) as u32;
+            }
+            0x01 => {
+                // DT
+                if !self.rtc_en {
+                    let secs_since_startup = self.timebase.now_secs() as i64;
+                    let desired_secs = val as i64;
+                    self.dt_adjust = desired_secs - secs_since_startup;
+                }
+            }
+            0x06 => {
+                // CTR0P
+                crate::log!(Clock, Debug, "ctr0 period {}us", val);
+                self.ctr0_intr_p = val;
+                self.rearm();
+            }
+            0x07 => {
+                // CTR1P
+                crate::log!(Clock, Debug, "ctr1 period {}us", val);
+                self.ctr1_intr_p = val;
+                self.rearm();
+            }
+            0x08 => {
+                // IRQ
+                self.irq_pending &= !val;
+            }
+            _ => {
+            }
+        }
+    }
+}
\ No newline at end of file
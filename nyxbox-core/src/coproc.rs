@@ -0,0 +1,128 @@
+use std::{sync::Arc, thread::{self, JoinHandle}};
+
+use unicorn_engine::{ffi::uc_handle, Permission, RegisterARM, Unicorn};
+
+use crate::{fault::register_dump, machine::{after_wfi, mmio_read, mmio_write, resume_addr, CpuModel, CPSR_RESET, CPSR_T, DEBUG_REGS}, mailbox::{CopStart, Mailbox, Side}, symbols::Symbols};
+
+// emu_start runs the coprocessor in slices of this many microseconds, so a halt that lands just before a slice starts
+// (which emu_stop can't catch) is still seen promptly
+const SLICE_US: u64 = 10_000;
+
+// a second ARM core for offloading I/O & housekeeping. it has its own unicorn instance on its own thread, shares the
+// main CPU's memory (mapped from the same host buffers), & sees only the mailbox of all the peripherals - that's how the
+// main CPU starts it, stops it, & talks to it. it runs freely rather than to the frame budget, & takes no interrupts, so
+// anything that stops it short (SWI, an abort) is a fault that halts it & is reported to the main CPU
+pub struct Coprocessor<'a> {
+    cpu: Unicorn<'a, ()>,
+    mailbox: Arc<Mailbox>,
+    symbols: Symbols,
+}
+
+pub struct CoprocessorRunContext {
+    join_handle: JoinHandle<()>,
+    mailbox: Arc<Mailbox>,
+}
+
+impl <'a> Coprocessor<'a> {
+    pub fn new(model: CpuModel, mailbox: Arc<Mailbox>, start_addr: u32, length: u32, symbols: Symbols) -> Self {
+        let (mode, uc_model) = model.unicorn_model();
+        let mut cpu = Unicorn::new(unicorn_engine::Arch::ARM, mode).unwrap();
+        cpu.ctl_set_cpu_model(uc_model as i32).unwrap();
+
+        let rd_dev = mailbox.port(Side::Coprocessor);
+        let wr_dev = mailbox.port(Side::Coprocessor);
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&rd_dev, addr, size);
+        };
+
+        let wr = move |_uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            mmio_write(&wr_dev, addr, size, value);
+        };
+
+        cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+
+        return Self {
+            cpu,
+            mailbox,
+            symbols,
+        };
+    }
+
+    // the same host memory the main CPU has mapped at start_addr
+    pub unsafe fn map_shared(self: &mut Self, ptr: *mut u8, len: usize, start_addr: u32, permission: Permission) {
+        self.cpu.mem_map_ptr(start_addr as u64, len, permission, ptr.cast()).unwrap();
+    }
+
+    // machine reset: the coprocessor is halted until the main CPU starts it
+    pub fn reset(self: &mut Self) {
+        self.mailbox.reset();
+    }
+
+    // same handle trick as Machine::run - the Unicorn instance stays owned by the Coprocessor
+    pub fn start(self: &Self) -> CoprocessorRunContext {
+        let cpu_send = self.cpu.get_handle() as usize;
+        let mailbox = self.mailbox.clone();
+
+        mailbox.restart();
+        mailbox.set_cop_stop(Some(Box::new(move || {
+            let mut cpu = unsafe { Unicorn::<()>::from_handle(cpu_send as uc_handle).unwrap() };
+            cpu.emu_stop().unwrap();
+        })));
+
+        let thread_mailbox = mailbox.clone();
+        let symbols = self.symbols.clone();
+
+        let join_handle = thread::Builder::new()
+            .name("coprocessor".to_string())
+            .spawn(move || {
+                let mut cpu = unsafe { Unicorn::from_handle(cpu_send as uc_handle).unwrap() };
+
+                while let Some(start) = thread_mailbox.wait_start() {
+                    let fault = run(&mut cpu, &thread_mailbox, &start, &symbols);
+                    thread_mailbox.cop_stopped(start.generation, fault);
+                }
+            })
+            .unwrap();
+
+        return CoprocessorRunContext {
+            join_handle,
+            mailbox,
+        };
+    }
+}
+
+impl CoprocessorRunContext {
+    pub fn stop(self: Self) {
+        self.mailbox.shutdown();
+        self.join_handle.join().unwrap();
+        self.mailbox.set_cop_stop(None);
+    }
+}
+
+// one start of the coprocessor, until it's halted or faults. true if it faulted
+fn run(cpu: &mut Unicorn<'_, ()>, mailbox: &Mailbox, start: &CopStart, symbols: &Symbols) -> bool {
+    for (_, reg) in DEBUG_REGS {
+        cpu.reg_write(reg, 0).unwrap();
+    }
+
+    // supervisor mode with interrupts masked, in whichever instruction set bit 0 of the entry point asks for
+    let thumb = if start.entry & 1 != 0 { CPSR_T } else { 0 };
+    cpu.reg_write(RegisterARM::PC, start.entry as u64).unwrap();
+    cpu.reg_write(RegisterARM::CPSR, CPSR_RESET | thumb).unwrap();
+    cpu.reg_write(RegisterARM::SP, start.stack as u64).unwrap();
+
+    while mailbox.cop_should_run(start.generation) {
+        if let Err(e) = cpu.emu_start(resume_addr(cpu), u64::MAX, SLICE_US, 0) {
+            crate::log!(Cpu, Error, "coprocessor: {:?} @ pc {}\n{}", e, symbols.describe(cpu.pc_read().unwrap_or(0) as u32), register_dump(cpu, symbols));
+            return true;
+        }
+
+        // sitting in WFI until there's mail or a doorbell (or it's halted)
+        if after_wfi(cpu) && !mailbox.wait_wake(start.generation) {
+            break;
+        }
+    }
+
+    return false;
+}
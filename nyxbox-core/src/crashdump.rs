@@ -0,0 +1,162 @@
+use std::{fmt::Write, fs, panic, path::{Path, PathBuf}, sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, OnceLock}};
+
+use sdl3::gpu::Device;
+
+use crate::{machine::{CpuProbe, MachineRunContext}, mem::MemoryMap, savestate::{self, SaveState, Snapshots, SECTION_CPU, SECTION_CRASH_REPORT, SECTION_PC_HISTORY, SECTION_RAM, SECTION_XRAM}, symbols::Symbols, timebase::Timebase, uart::UART, vdp::{self, VDP}};
+
+// when the guest crashes, or the emulator itself does, what was going on goes into <capture dir>/crashes/crash-<time>.nyxs
+// for attaching to a bug report. it's a save state file, so `nyxbox dump --state` & `nyxbox state ...` work on it, with
+// the report in a section of its own (`nyxbox state crash` prints it):
+//
+//   - a guest crash is dumped with the machine stopped, so it's a complete save state - it can be loaded to look around
+//   - a host panic can't ask the frontend for anything, so it only gets registers & RAM, read while the CPU may well
+//     still be running
+pub const CRASH_DIR: &str = "crashes";
+
+// blocks the CPU most recently started executing, & VDP commands it most recently processed
+pub const PC_HISTORY_LEN: usize = 256;
+pub const VDP_CMD_HISTORY_LEN: usize = 64;
+
+// UART output the report ends with
+const UART_TAIL_LEN: usize = 1024;
+
+// a fixed-size ring of the most recent values, safe to push to from the CPU thread & read from anywhere (even a panic hook)
+// without taking a lock
+pub struct History {
+    entries: Vec<AtomicU64>,
+    next: AtomicUsize,
+}
+
+impl History {
+    pub fn new(len: usize) -> Self {
+        Self {
+            entries: (0..len).map(|_| AtomicU64::new(0)).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn push(self: &Self, val: u64) {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed);
+        self.entries[idx % self.entries.len()].store(val, Ordering::Relaxed);
+    }
+
+    // oldest first
+    pub fn recent(self: &Self) -> Vec<u64> {
+        let next = self.next.load(Ordering::Relaxed);
+        let len = next.min(self.entries.len());
+
+        return (next - len..next).map(|idx| self.entries[idx % self.entries.len()].load(Ordering::Relaxed)).collect();
+    }
+}
+
+// what the panic hook has to work with
+struct CrashContext {
+    dir: PathBuf,
+    pcs: Arc<History>,
+    vdp_cmds: Arc<History>,
+    cpu: CpuProbe,
+    timebase: Arc<Timebase>,
+    map: MemoryMap,
+    symbols: Symbols,
+    expansion_ram: bool,
+}
+
+static CONTEXT: OnceLock<CrashContext> = OnceLock::new();
+
+// dump on host panics from here on (as well as reporting them as usual)
+pub fn install(dir: &Path, pcs: Arc<History>, vdp_cmds: Arc<History>, cpu: CpuProbe, timebase: Arc<Timebase>, map: MemoryMap,
+    symbols: Symbols, expansion_ram: bool) {
+    let _ = CONTEXT.set(CrashContext { dir: dir.to_path_buf(), pcs, vdp_cmds, cpu, timebase, map, symbols, expansion_ram });
+
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        // a second panic while dumping would abort before the first was reported, so only the first one dumps
+        static DUMPING: AtomicUsize = AtomicUsize::new(0);
+
+        if DUMPING.fetch_add(1, Ordering::Relaxed) == 0 {
+            if let Some(ctx) = CONTEXT.get() {
+                match host_dump(ctx, &info.to_string()) {
+                    Ok(path) => eprintln!("crash dump written to {}", path.display()),
+                    Err(e) => eprintln!("crash dump failed: {}", e),
+                }
+            }
+        }
+    }));
+}
+
+// the guest has crashed & the CPU thread with it - the machine is stopped, so everything can be had
+pub fn guest_dump(reason: &str, frame: u64, run_ctx: &MachineRunContext, vdp: &mut VDP, gfx_device: &Device, timebase: &Timebase,
+    snapshots: &Snapshots, uart: &UART, expansion_ram: bool) -> Result<PathBuf, String> {
+    let ctx = CONTEXT.get().ok_or("crash dumps are off".to_string())?;
+    let mut state = savestate::capture_state(run_ctx, vdp, gfx_device, timebase, snapshots, &ctx.map, expansion_ram)?;
+
+    let mut text = report(&format!("guest crashed @ frame {}: {}", frame, reason), &run_ctx.registers(), ctx);
+    let tx = uart.tx_history();
+    let _ = write!(text, "\nuart output (most recent last):\n{}\n", String::from_utf8_lossy(&tx[tx.len().saturating_sub(UART_TAIL_LEN)..]));
+
+    add_report(&mut state, text, ctx);
+    return save(ctx, &state);
+}
+
+fn host_dump(ctx: &CrashContext, message: &str) -> Result<PathBuf, String> {
+    let mut state = SaveState::new();
+    let regs = ctx.cpu.registers();
+
+    state.set_section_words(SECTION_CPU, &regs.iter().map(|(_, val)| *val).collect::<Vec<u32>>());
+
+    let map = &ctx.map;
+    let mut regions = vec![(SECTION_RAM, map.main_ram_begin, map.main_ram_size)];
+    if ctx.expansion_ram {
+        regions.push((SECTION_XRAM, map.expansion_ram_begin, map.expansion_ram_size));
+    }
+
+    for (tag, base, len) in regions {
+        if let Ok(data) = ctx.cpu.mem_read(base as u32, len) {
+            state.set_section(tag, data);
+        }
+    }
+
+    let text = report(&format!("emulator crashed @ frame {}: {}", ctx.timebase.frame(), message), &regs, ctx);
+    add_report(&mut state, text, ctx);
+    return save(ctx, &state);
+}
+
+fn report(what: &str, regs: &[(&str, u32)], ctx: &CrashContext) -> String {
+    let mut text = format!("{}\n\nregisters:\n", what);
+
+    for (name, val) in regs {
+        let _ = writeln!(text, "  {:<5} {:08x}", name, val);
+    }
+
+    text.push_str("\nrecent blocks (most recent last):\n");
+
+    for pc in ctx.pcs.recent() {
+        let _ = writeln!(text, "  {}", ctx.symbols.describe(pc as u32));
+    }
+
+    text.push_str("\nrecent VDP commands (most recent last):\n");
+
+    for cmd in ctx.vdp_cmds.recent() {
+        let (addr, hdr) = ((cmd >> 32) as u32, cmd as u32);
+        let _ = writeln!(text, "  {:08x}  {:08x}  {}", addr, hdr, vdp::cmd_name(hdr & 0xFF));
+    }
+
+    return text;
+}
+
+fn add_report(state: &mut SaveState, text: String, ctx: &CrashContext) {
+    state.set_section(SECTION_CRASH_REPORT, text.into_bytes());
+    state.set_section_words(SECTION_PC_HISTORY, &ctx.pcs.recent().iter().map(|pc| *pc as u32).collect::<Vec<u32>>());
+}
+
+fn save(ctx: &CrashContext, state: &SaveState) -> Result<PathBuf, String> {
+    fs::create_dir_all(&ctx.dir).map_err(|e| format!("failed to create {}: {}", ctx.dir.display(), e))?;
+
+    let path = ctx.dir.join(format!("crash-{}.nyxs", chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")));
+    state.save(&path).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+
+    return Ok(path);
+}
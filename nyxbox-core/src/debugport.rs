@@ -0,0 +1,126 @@
+use std::{collections::VecDeque, sync::atomic::{AtomicU32, Ordering}};
+
+use crate::peripheral::{LockStats, Peripheral, PeripheralLock};
+
+pub const DEBUGPORT_MEM_SIZE: u32 = 4096;
+
+pub const DEBUGCMD_SCREENSHOT: u32  = 1;
+pub const DEBUGCMD_MARKER: u32      = 2;
+pub const DEBUGCMD_EXIT: u32        = 3;
+pub const DEBUGCMD_ASSERT: u32      = 4;
+
+// longest name the guest can attach to a request - extra characters are dropped
+pub const DEBUGPORT_NAME_MAX: usize = 64;
+
+pub enum DebugEvent {
+    Screenshot { name: String },
+    Marker { name: String },
+    Exit { code: i32, result: String },
+    Assert { message: String },
+}
+
+// lets guest code (mostly automated tests) ask the host for screenshots, drop named markers into captures, report failed
+// asserts, & end the run with an exit code & a result string (the name, for EXIT)
+pub struct DebugPort {
+    name: PeripheralLock<Vec<u8>>,
+    exit_code: AtomicU32,
+    events: PeripheralLock<VecDeque<DebugEvent>>,
+    serviced: AtomicU32,
+}
+
+impl DebugPort {
+    pub fn new() -> Self {
+        Self {
+            name: PeripheralLock::new(Vec::new()),
+            exit_code: AtomicU32::new(0),
+            events: PeripheralLock::new(VecDeque::new()),
+            serviced: AtomicU32::new(0),
+        }
+    }
+
+    // called by the frontend once per frame - events are serviced after the frame has been presented
+    pub fn take_event(self: &Self) -> Option<DebugEvent> {
+        let ev = self.events.lock().pop_front();
+
+        if ev.is_some() {
+            self.serviced.fetch_add(1, Ordering::Relaxed);
+        }
+
+        return ev;
+    }
+
+    // run-ahead: requests made by frames that are about to be thrown away are dropped along with them
+    pub fn pending(self: &Self) -> usize {
+        return self.events.lock().len();
+    }
+
+    pub fn truncate(self: &Self, len: usize) {
+        self.events.lock().truncate(len);
+    }
+}
+
+impl Peripheral for DebugPort {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x02 => {
+                // PENDING
+                return self.events.lock().len() as u32;
+            }
+            0x03 => {
+                // SERVICED
+                return self.serviced.load(Ordering::Relaxed);
+            }
+            0x04 => {
+                // EXITCODE
+                return self.exit_code.load(Ordering::Relaxed);
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // NAME
+                let mut name = self.name.lock();
+                if name.len() < DEBUGPORT_NAME_MAX {
+                    name.push((val & 0xFF) as u8);
+                }
+            }
+            0x01 => {
+                // CMD
+                let name = String::from_utf8_lossy(&std::mem::take(&mut *self.name.lock())).into_owned();
+
+                match val {
+                    DEBUGCMD_SCREENSHOT => {
+                        self.events.lock().push_back(DebugEvent::Screenshot { name });
+                    }
+                    DEBUGCMD_MARKER => {
+                        self.events.lock().push_back(DebugEvent::Marker { name });
+                    }
+                    DEBUGCMD_ASSERT => {
+                        self.events.lock().push_back(DebugEvent::Assert { message: name });
+                    }
+                    DEBUGCMD_EXIT => {
+                        let code = self.exit_code.load(Ordering::Relaxed) as i32;
+                        self.events.lock().push_back(DebugEvent::Exit { code, result: name });
+                    }
+                    _ => {
+                    }
+                }
+            }
+            0x04 => {
+                // EXITCODE - reported by the next EXIT command
+                self.exit_code.store(val, Ordering::Relaxed);
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("debugport.name", self.name.stats()), ("debugport.events", self.events.stats())];
+    }
+}
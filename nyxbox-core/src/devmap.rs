@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use crate::{machine::Machine, mem::{MMIO_SLOT_SIZE, PAGE_SIZE}, peripheral::Peripheral};
+
+// peripherals to map before boot - the machine's own, or new hardware being tried out - registered by name & address
+// range rather than wired up one by one. where two ranges overlap, the higher priority peripheral takes the overlap & the
+// other keeps what's left of its range, so a device can be laid over part of another. an overlap at the same priority is
+// an error, as is overlapping memory or anything the machine has mapped already (peripherals with a mapping of their own,
+// like the MPU or the VDP port)
+pub struct DeviceMap {
+    entries: Vec<DeviceEntry>,
+}
+
+struct DeviceEntry {
+    name: String,
+    device: Arc<dyn Peripheral>,
+    start: u64,
+    end: u64,
+    priority: i32,
+}
+
+impl DeviceMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(self: &mut Self, name: &str, device: Arc<dyn Peripheral>, start: u32, len: u32, priority: i32) -> Result<(), String> {
+        if len == 0 || start as usize % PAGE_SIZE != 0 || len as usize % PAGE_SIZE != 0 {
+            return Err(format!("{}: {:08x}+{:x} isn't a whole number of {} byte pages", name, start, len, PAGE_SIZE));
+        }
+
+        // register offsets only go up to a slot's worth (see machine::mmio_read)
+        if len as usize > MMIO_SLOT_SIZE {
+            return Err(format!("{}: {:x} bytes is more than a peripheral can address (max {:x})", name, len, MMIO_SLOT_SIZE));
+        }
+
+        let (start, end) = (start as u64, start as u64 + len as u64);
+
+        if end > 1 << 32 {
+            return Err(format!("{}: {:08x}+{:x} runs past the end of the address space", name, start, len));
+        }
+
+        if self.entries.iter().any(|entry| entry.name == name) {
+            return Err(format!("{}: a peripheral by that name is already registered", name));
+        }
+
+        if let Some(other) = self.entries.iter().find(|entry| entry.priority == priority && entry.start < end && start < entry.end) {
+            return Err(format!("{} ({:08x}-{:08x}) overlaps {} ({:08x}-{:08x}) at the same priority", name, start, end - 1, other.name, other.start, other.end - 1));
+        }
+
+        self.entries.push(DeviceEntry { name: name.to_string(), device, start, end, priority });
+        return Ok(());
+    }
+
+    // every peripheral registered, whether or not anything's left of its range to map
+    pub fn peripherals(self: &Self) -> Vec<Arc<dyn Peripheral>> {
+        return self.entries.iter().map(|entry| entry.device.clone()).collect();
+    }
+
+    // what's left of each peripheral's range once higher priority ones have taken theirs: (entry, start, end)
+    fn pieces(self: &Self) -> Vec<(&DeviceEntry, u64, u64)> {
+        let mut pieces = Vec::new();
+
+        for entry in &self.entries {
+            let mut ranges = vec![(entry.start, entry.end)];
+
+            for above in self.entries.iter().filter(|other| other.priority > entry.priority) {
+                ranges = ranges.into_iter().flat_map(|(start, end)| {
+                    if above.end <= start || end <= above.start {
+                        return vec![(start, end)];
+                    }
+
+                    return [(start, above.start), (above.end, end)].into_iter().filter(|(start, end)| start < end).collect();
+                }).collect();
+            }
+
+            pieces.extend(ranges.into_iter().map(|(start, end)| (entry, start, end)));
+        }
+
+        return pieces;
+    }
+
+    // map everything, after memory & the peripherals the machine maps itself, but before anything that fills the gaps
+    // (open bus)
+    pub fn map(self: &Self, machine: &mut Machine) -> Result<(), String> {
+        let pieces = self.pieces();
+
+        for (entry, start, end) in &pieces {
+            if let Some((used_start, used_end)) = machine.mapped_overlap(*start as u32, (end - start) as u32) {
+                return Err(format!("{} ({:08x}-{:08x}) overlaps memory or a peripheral already mapped at {:08x}-{:08x}", entry.name, start, end - 1, used_start, used_end));
+            }
+        }
+
+        for (entry, start, end) in pieces {
+            machine.map_peripheral_range(entry.device.clone(), start as u32, (end - start) as u32, (start - entry.start) as u32);
+        }
+
+        return Ok(());
+    }
+}
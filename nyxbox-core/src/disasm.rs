@@ -0,0 +1,331 @@
+// a small ARMv6 disassembler for trace output - enough of the ARM & Thumb instruction sets to follow what a guest is doing.
+// anything it doesn't recognize comes out as a .word / .hword, so a trace never loses an instruction, only its mnemonic
+
+const CONDS: [&str;16] = ["eq", "ne", "cs", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", "nv"];
+const DP_OPS: [&str;16] = ["and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr", "mov", "bic", "mvn"];
+const SHIFTS: [&str;4] = ["lsl", "lsr", "asr", "ror"];
+
+fn reg(r: u32) -> String {
+    return match r {
+        13 => "sp".to_string(),
+        14 => "lr".to_string(),
+        15 => "pc".to_string(),
+        _ => format!("r{}", r),
+    };
+}
+
+fn reg_list(mask: u32) -> String {
+    let regs: Vec<String> = (0..16).filter(|r| mask & (1 << r) != 0).map(reg).collect();
+    return format!("{{{}}}", regs.join(", "));
+}
+
+pub fn disassemble(pc: u32, op: u32, thumb: bool) -> String {
+    return if thumb { thumb_insn(pc, op as u16) } else { arm_insn(pc, op) };
+}
+
+// operand 2 of a data processing instruction: a rotated immediate, or a register shifted by an immediate or a register
+fn arm_operand2(op: u32) -> String {
+    if op & (1 << 25) != 0 {
+        let rot = ((op >> 8) & 0xF) * 2;
+        return format!("#0x{:x}", (op & 0xFF).rotate_right(rot));
+    }
+
+    let rm = reg(op & 0xF);
+    let shift = SHIFTS[((op >> 5) & 3) as usize];
+
+    if op & (1 << 4) != 0 {
+        return format!("{}, {} {}", rm, shift, reg((op >> 8) & 0xF));
+    }
+
+    let amount = (op >> 7) & 0x1F;
+
+    return match (shift, amount) {
+        ("lsl", 0) => rm,
+        ("ror", 0) => format!("{}, rrx", rm),
+        ("lsr", 0) | ("asr", 0) => format!("{}, {} #32", rm, shift),
+        _ => format!("{}, {} #{}", rm, shift, amount),
+    };
+}
+
+fn arm_insn(pc: u32, op: u32) -> String {
+    let cond = CONDS[(op >> 28) as usize];
+    let rn = (op >> 16) & 0xF;
+    let rd = (op >> 12) & 0xF;
+
+    // unconditional space: only CPS is of interest here
+    if op >> 28 == 0xF {
+        if op & 0xFFF1FE20 == 0xF1000000 {
+            let imod = (op >> 18) & 3;
+            let flags: String = [('a', 8), ('i', 7), ('f', 6)].iter().filter(|(_, bit)| op & (1 << bit) != 0).map(|(c, _)| *c).collect();
+            return format!("cps{} {}", if imod == 2 { "ie" } else { "id" }, flags);
+        }
+
+        return format!(".word 0x{:08x}", op);
+    }
+
+    if op & 0x0FFFFFFF == 0x0320F003 {
+        return format!("wfi{}", cond);
+    }
+
+    if op & 0x0FFFFFFF == 0x0320F000 {
+        return format!("nop{}", cond);
+    }
+
+    if op & 0x0FFFFFD0 == 0x012FFF10 {
+        return format!("{}{} {}", if op & (1 << 5) != 0 { "blx" } else { "bx" }, cond, reg(op & 0xF));
+    }
+
+    match (op >> 25) & 7 {
+        0b101 => {
+            let offset = (((op & 0xFFFFFF) << 8) as i32 >> 6) as u32;
+            return format!("{}{} 0x{:08x}", if op & (1 << 24) != 0 { "bl" } else { "b" }, cond, pc.wrapping_add(8).wrapping_add(offset));
+        }
+        0b111 if op & (1 << 24) != 0 => {
+            return format!("swi{} 0x{:x}", cond, op & 0xFFFFFF);
+        }
+        0b100 => {
+            let modes = ["da", "ia", "db", "ib"];
+            let name = if op & (1 << 20) != 0 { "ldm" } else { "stm" };
+            let wb = if op & (1 << 21) != 0 { "!" } else { "" };
+            let user = if op & (1 << 22) != 0 { "^" } else { "" };
+            return format!("{}{}{} {}{}, {}{}", name, modes[((op >> 23) & 3) as usize], cond, reg(rn), wb, reg_list(op & 0xFFFF), user);
+        }
+        0b010 | 0b011 => {
+            if (op >> 25) & 7 == 0b011 && op & (1 << 4) != 0 {
+                return format!(".word 0x{:08x}", op);
+            }
+
+            let name = if op & (1 << 20) != 0 { "ldr" } else { "str" };
+            let byte = if op & (1 << 22) != 0 { "b" } else { "" };
+            let sign = if op & (1 << 23) != 0 { "" } else { "-" };
+
+            let offset = if op & (1 << 25) == 0 {
+                format!("#{}{}", sign, op & 0xFFF)
+            }
+            else {
+                format!("{}{}", sign, arm_operand2(op & !(1 << 25) & !(1 << 4)))
+            };
+
+            return format!("{}{}{} {}, {}", name, cond, byte, reg(rd), arm_address(op, rn, &offset));
+        }
+        0b000 | 0b001 => {
+        }
+        _ => {
+            return format!(".word 0x{:08x}", op);
+        }
+    }
+
+    // multiplies & extra load/stores live in the register form of data processing, marked by bits 7 & 4
+    if op & (1 << 25) == 0 && op & 0x90 == 0x90 {
+        let rs = (op >> 8) & 0xF;
+        let rm = op & 0xF;
+        let s = if op & (1 << 20) != 0 { "s" } else { "" };
+
+        if (op >> 5) & 3 == 0 {
+            return match (op >> 21) & 0x7F {
+                0b0000000 => format!("mul{}{} {}, {}, {}", cond, s, reg(rn), reg(rm), reg(rs)),
+                0b0000001 => format!("mla{}{} {}, {}, {}, {}", cond, s, reg(rn), reg(rm), reg(rs), reg(rd)),
+                0b0000100 => format!("umull{}{} {}, {}, {}, {}", cond, s, reg(rd), reg(rn), reg(rm), reg(rs)),
+                0b0000101 => format!("umlal{}{} {}, {}, {}, {}", cond, s, reg(rd), reg(rn), reg(rm), reg(rs)),
+                0b0000110 => format!("smull{}{} {}, {}, {}, {}", cond, s, reg(rd), reg(rn), reg(rm), reg(rs)),
+                0b0000111 => format!("smlal{}{} {}, {}, {}, {}", cond, s, reg(rd), reg(rn), reg(rm), reg(rs)),
+                0b0001000 => format!("swp{} {}, {}, [{}]", cond, reg(rd), reg(rm), reg(rn)),
+                0b0001010 => format!("swp{}b {}, {}, [{}]", cond, reg(rd), reg(rm), reg(rn)),
+                _ => format!(".word 0x{:08x}", op),
+            };
+        }
+
+        let load = op & (1 << 20) != 0;
+        let name = match ((op >> 5) & 3, load) {
+            (1, true) => "ldrh",
+            (1, false) => "strh",
+            (2, true) => "ldrsb",
+            (3, true) => "ldrsh",
+            (2, false) => "ldrd",
+            _ => "strd",
+        };
+        let sign = if op & (1 << 23) != 0 { "" } else { "-" };
+
+        let offset = if op & (1 << 22) != 0 {
+            format!("#{}{}", sign, ((op >> 4) & 0xF0) | (op & 0xF))
+        }
+        else {
+            format!("{}{}", sign, reg(rm))
+        };
+
+        return format!("{}{} {}, {}", name, cond, reg(rd), arm_address(op, rn, &offset));
+    }
+
+    let opcode = (op >> 21) & 0xF;
+    let set_flags = op & (1 << 20) != 0;
+
+    // the compare ops without S are where the status register transfers live
+    if (8..=11).contains(&opcode) && !set_flags {
+        let psr = if op & (1 << 22) != 0 { "spsr" } else { "cpsr" };
+
+        if opcode & 1 == 0 && op & (1 << 25) == 0 {
+            return format!("mrs{} {}, {}", cond, reg(rd), psr);
+        }
+
+        if opcode & 1 == 1 {
+            let fields: String = [('c', 16), ('x', 17), ('s', 18), ('f', 19)].iter().filter(|(_, bit)| op & (1 << bit) != 0).map(|(c, _)| *c).collect();
+            let src = if op & (1 << 25) != 0 { arm_operand2(op) } else { reg(op & 0xF) };
+            return format!("msr{} {}_{}, {}", cond, psr, fields, src);
+        }
+
+        return format!(".word 0x{:08x}", op);
+    }
+
+    let name = DP_OPS[opcode as usize];
+    let s = if set_flags && !(8..=11).contains(&opcode) { "s" } else { "" };
+    let op2 = arm_operand2(op);
+
+    return match opcode {
+        8..=11 => format!("{}{} {}, {}", name, cond, reg(rn), op2),
+        13 | 15 => format!("{}{}{} {}, {}", name, cond, s, reg(rd), op2),
+        _ => format!("{}{}{} {}, {}, {}", name, cond, s, reg(rd), reg(rn), op2),
+    };
+}
+
+// [rn, offset], [rn, offset]! or [rn], offset, from the P & W bits
+fn arm_address(op: u32, rn: u32, offset: &str) -> String {
+    let pre = op & (1 << 24) != 0;
+    let wb = op & (1 << 21) != 0;
+
+    if !pre {
+        return format!("[{}], {}", reg(rn), offset);
+    }
+
+    if offset == "#0" {
+        return format!("[{}]{}", reg(rn), if wb { "!" } else { "" });
+    }
+
+    return format!("[{}, {}]{}", reg(rn), offset, if wb { "!" } else { "" });
+}
+
+fn thumb_insn(pc: u32, op: u16) -> String {
+    let op = op as u32;
+    let rd = op & 7;
+    let rs = (op >> 3) & 7;
+
+    match op >> 11 {
+        0b00000..=0b00010 => {
+            let amount = (op >> 6) & 0x1F;
+            return format!("{}s {}, {}, #{}", SHIFTS[(op >> 11) as usize], reg(rd), reg(rs), amount);
+        }
+        0b00011 => {
+            let name = if op & (1 << 9) != 0 { "subs" } else { "adds" };
+            let operand = if op & (1 << 10) != 0 { format!("#{}", (op >> 6) & 7) } else { reg((op >> 6) & 7) };
+            return format!("{} {}, {}, {}", name, reg(rd), reg(rs), operand);
+        }
+        0b00100..=0b00111 => {
+            let names = ["movs", "cmp", "adds", "subs"];
+            return format!("{} {}, #{}", names[((op >> 11) & 3) as usize], reg((op >> 8) & 7), op & 0xFF);
+        }
+        0b01000 => {
+            if op & (1 << 10) == 0 {
+                let names = ["ands", "eors", "lsls", "lsrs", "asrs", "adcs", "sbcs", "rors", "tst", "negs", "cmp", "cmn", "orrs", "muls", "bics", "mvns"];
+                return format!("{} {}, {}", names[((op >> 6) & 0xF) as usize], reg(rd), reg(rs));
+            }
+
+            // hi register ops & bx, which reach r8-r15 through the H bits
+            let hd = rd | ((op >> 4) & 8);
+            let hs = (op >> 3) & 0xF;
+
+            return match (op >> 8) & 3 {
+                0 => format!("add {}, {}", reg(hd), reg(hs)),
+                1 => format!("cmp {}, {}", reg(hd), reg(hs)),
+                2 => format!("mov {}, {}", reg(hd), reg(hs)),
+                _ => format!("{} {}", if op & 0x80 != 0 { "blx" } else { "bx" }, reg(hs)),
+            };
+        }
+        0b01001 => {
+            let target = ((pc + 4) & !3) + (op & 0xFF) * 4;
+            return format!("ldr {}, [pc, #{}] ; 0x{:08x}", reg((op >> 8) & 7), (op & 0xFF) * 4, target);
+        }
+        0b01010 | 0b01011 => {
+            let names = ["str", "strh", "strb", "ldrsb", "ldr", "ldrh", "ldrb", "ldrsh"];
+            return format!("{} {}, [{}, {}]", names[((op >> 9) & 7) as usize], reg(rd), reg(rs), reg((op >> 6) & 7));
+        }
+        0b01100..=0b01111 => {
+            let byte = op & (1 << 12) != 0;
+            let name = match (op & (1 << 11) != 0, byte) {
+                (true, true) => "ldrb",
+                (true, false) => "ldr",
+                (false, true) => "strb",
+                (false, false) => "str",
+            };
+            let offset = ((op >> 6) & 0x1F) * if byte { 1 } else { 4 };
+            return format!("{} {}, [{}, #{}]", name, reg(rd), reg(rs), offset);
+        }
+        0b10000 | 0b10001 => {
+            let name = if op & (1 << 11) != 0 { "ldrh" } else { "strh" };
+            return format!("{} {}, [{}, #{}]", name, reg(rd), reg(rs), ((op >> 6) & 0x1F) * 2);
+        }
+        0b10010 | 0b10011 => {
+            let name = if op & (1 << 11) != 0 { "ldr" } else { "str" };
+            return format!("{} {}, [sp, #{}]", name, reg((op >> 8) & 7), (op & 0xFF) * 4);
+        }
+        0b10100 | 0b10101 => {
+            let base = if op & (1 << 11) != 0 { "sp" } else { "pc" };
+            return format!("add {}, {}, #{}", reg((op >> 8) & 7), base, (op & 0xFF) * 4);
+        }
+        0b10110 | 0b10111 => {
+            if op & 0xFF00 == 0xB000 {
+                let name = if op & 0x80 != 0 { "sub" } else { "add" };
+                return format!("{} sp, #{}", name, (op & 0x7F) * 4);
+            }
+
+            if op & 0xF600 == 0xB400 {
+                let extra = if op & (1 << 8) != 0 { if op & (1 << 11) != 0 { 1 << 15 } else { 1 << 14 } } else { 0 };
+                let name = if op & (1 << 11) != 0 { "pop" } else { "push" };
+                return format!("{} {}", name, reg_list((op & 0xFF) | extra));
+            }
+
+            if op & 0xFFE8 == 0xB660 {
+                let flags: String = [('a', 2), ('i', 1), ('f', 0)].iter().filter(|(_, bit)| op & (1 << bit) != 0).map(|(c, _)| *c).collect();
+                return format!("cps{} {}", if op & 0x10 != 0 { "id" } else { "ie" }, flags);
+            }
+
+            if op == 0xBF30 {
+                return "wfi".to_string();
+            }
+
+            return format!(".hword 0x{:04x}", op);
+        }
+        0b11000 | 0b11001 => {
+            let name = if op & (1 << 11) != 0 { "ldmia" } else { "stmia" };
+            return format!("{} {}!, {}", name, reg((op >> 8) & 7), reg_list(op & 0xFF));
+        }
+        0b11010 | 0b11011 => {
+            let cond = (op >> 8) & 0xF;
+
+            if cond == 0xF {
+                return format!("swi 0x{:x}", op & 0xFF);
+            }
+
+            if cond == 0xE {
+                return format!(".hword 0x{:04x}", op);
+            }
+
+            let offset = ((op & 0xFF) as i8 as i32 * 2) as u32;
+            return format!("b{} 0x{:08x}", CONDS[cond as usize], pc.wrapping_add(4).wrapping_add(offset));
+        }
+        0b11100 => {
+            let offset = (((op & 0x7FF) << 21) as i32 >> 20) as u32;
+            return format!("b 0x{:08x}", pc.wrapping_add(4).wrapping_add(offset));
+        }
+        0b11110 => {
+            // first half of a bl/blx pair - the trace shows the second half on its own line
+            let offset = (((op & 0x7FF) << 21) as i32 >> 9) as u32;
+            return format!("bl.hi 0x{:08x}", pc.wrapping_add(4).wrapping_add(offset));
+        }
+        0b11111 | 0b11101 => {
+            let name = if op >> 11 == 0b11111 { "bl.lo" } else { "blx.lo" };
+            return format!("{} #0x{:x}", name, (op & 0x7FF) << 1);
+        }
+        _ => {
+            return format!(".hword 0x{:04x}", op);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::mem::MemoryMap;
+
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_ARM: u16 = 40;
+const PT_LOAD: u32 = 1;
+
+// one loadable segment's file contents, & where they go (also used for --load files)
+pub struct Segment {
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+// a guest executable, as the toolchain links it - ROM contents & initialized RAM, & where to start
+pub struct Executable {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+}
+
+pub fn is_elf(data: &[u8]) -> bool {
+    return data.starts_with(b"\x7FELF");
+}
+
+fn u16_at(data: &[u8], offs: usize) -> Result<u16, String> {
+    return data.get(offs..offs + 2).map(|v| u16::from_le_bytes(v.try_into().unwrap())).ok_or("truncated ELF".to_string());
+}
+
+fn u32_at(data: &[u8], offs: usize) -> Result<u32, String> {
+    return data.get(offs..offs + 4).map(|v| u32::from_le_bytes(v.try_into().unwrap())).ok_or("truncated ELF".to_string());
+}
+
+// segments load at their physical (load) address, so data that startup code copies out of ROM lands in ROM like it would
+// on a flashed cartridge. only what's in the file is loaded - zeroing bss is the startup code's job, same as on hardware
+pub fn parse(data: &[u8], map: &MemoryMap) -> Result<Executable, String> {
+    if data.len() < 0x34 || !is_elf(data) {
+        return Err("not an ELF file".to_string());
+    }
+
+    if data[4] != ELFCLASS32 || data[5] != ELFDATA2LSB || u16_at(data, 0x12)? != EM_ARM {
+        return Err("not a 32-bit little endian ARM executable".to_string());
+    }
+
+    if u16_at(data, 0x10)? != ET_EXEC {
+        return Err("not an executable (relocatable objects & shared libraries can't be loaded)".to_string());
+    }
+
+    let entry = u32_at(data, 0x18)?;
+    let phoff = u32_at(data, 0x1C)? as usize;
+    let phentsize = u16_at(data, 0x2A)? as usize;
+    let phnum = u16_at(data, 0x2C)? as usize;
+
+    let mut segments = Vec::new();
+
+    for idx in 0..phnum {
+        let ph = phoff + idx * phentsize;
+
+        if u32_at(data, ph)? != PT_LOAD {
+            continue;
+        }
+
+        let offset = u32_at(data, ph + 0x04)? as usize;
+        let paddr = u32_at(data, ph + 0x0C)?;
+        let filesz = u32_at(data, ph + 0x10)? as usize;
+
+        if filesz == 0 {
+            continue;
+        }
+
+        if !map.in_memory(paddr as usize, filesz) {
+            return Err(format!("segment at {:#x} ({} bytes) isn't within boot ROM or RAM", paddr, filesz));
+        }
+
+        let contents = data.get(offset..offset + filesz).ok_or("truncated ELF".to_string())?;
+        segments.push(Segment { addr: paddr, data: contents.to_vec() });
+    }
+
+    if segments.is_empty() {
+        return Err("no loadable segments".to_string());
+    }
+
+    return Ok(Executable { entry, segments });
+}
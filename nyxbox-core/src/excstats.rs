@@ -0,0 +1,135 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::{Duration, Instant}};
+
+use serde_json::{json, Map, Value};
+
+// exception numbers as unicorn's interrupt hook reports them (qemu's EXCP_* for ARM)
+pub const EXCP_UDEF: u32            = 1;
+pub const EXCP_SWI: u32             = 2;
+pub const EXCP_PREFETCH_ABORT: u32  = 3;
+pub const EXCP_DATA_ABORT: u32      = 4;
+pub const EXCP_IRQ: u32             = 5;
+pub const EXCP_FIQ: u32             = 6;
+pub const EXCP_BKPT: u32            = 7;
+
+// display names, indexed by exception number (0 collects anything unrecognized)
+pub const EXCEPTION_NAMES: [&str;8] = ["other", "undef", "swi", "pabort", "dabort", "irq", "fiq", "bkpt"];
+
+const SWI_NUMBERS: usize = 256;
+
+// counts every exception the guest takes, by kind & (for SWIs) by number. bumped from the CPU thread's interrupt hook
+pub struct ExceptionStats {
+    by_kind: [AtomicU64;EXCEPTION_NAMES.len()],
+    by_swi: [AtomicU64;SWI_NUMBERS],
+}
+
+#[derive(Clone)]
+pub struct ExceptionCounts {
+    pub by_kind: [u64;EXCEPTION_NAMES.len()],
+    pub by_swi: Vec<u64>,
+}
+
+impl ExceptionStats {
+    pub fn new() -> Self {
+        Self {
+            by_kind: std::array::from_fn(|_| AtomicU64::new(0)),
+            by_swi: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    pub fn record(self: &Self, intno: u32, swi_num: Option<u8>) {
+        let kind = if (intno as usize) < EXCEPTION_NAMES.len() { intno as usize } else { 0 };
+        self.by_kind[kind].fetch_add(1, Ordering::Relaxed);
+
+        if let Some(num) = swi_num {
+            self.by_swi[num as usize].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(self: &Self) -> ExceptionCounts {
+        return ExceptionCounts {
+            by_kind: std::array::from_fn(|i| self.by_kind[i].load(Ordering::Relaxed)),
+            by_swi: self.by_swi.iter().map(|v| v.load(Ordering::Relaxed)).collect(),
+        };
+    }
+}
+
+impl ExceptionCounts {
+    pub fn zero() -> Self {
+        Self {
+            by_kind: [0;EXCEPTION_NAMES.len()],
+            by_swi: vec![0;SWI_NUMBERS],
+        }
+    }
+
+    pub fn since(self: &Self, earlier: &ExceptionCounts) -> ExceptionCounts {
+        return ExceptionCounts {
+            by_kind: std::array::from_fn(|i| self.by_kind[i] - earlier.by_kind[i]),
+            by_swi: self.by_swi.iter().zip(&earlier.by_swi).map(|(a, b)| a - b).collect(),
+        };
+    }
+
+    pub fn total(self: &Self) -> u64 {
+        return self.by_kind.iter().sum();
+    }
+
+    // e.g. "swi 120 (#1 100, #3 20), irq 60" - only what actually happened
+    pub fn summary(self: &Self) -> String {
+        let mut parts = Vec::new();
+
+        for (kind, count) in self.by_kind.iter().enumerate().filter(|(_, c)| **c != 0) {
+            if kind == EXCP_SWI as usize {
+                let swis: Vec<String> = self.by_swi.iter().enumerate().filter(|(_, c)| **c != 0).map(|(n, c)| format!("#{} {}", n, c)).collect();
+                parts.push(format!("{} {} ({})", EXCEPTION_NAMES[kind], count, swis.join(", ")));
+            }
+            else {
+                parts.push(format!("{} {}", EXCEPTION_NAMES[kind], count));
+            }
+        }
+
+        return if parts.is_empty() { "none".to_string() } else { parts.join(", ") };
+    }
+
+    pub fn to_json(self: &Self) -> Value {
+        let kinds: Map<String, Value> = EXCEPTION_NAMES.iter().zip(self.by_kind).map(|(name, count)| (name.to_string(), json!(count))).collect();
+        let swis: Map<String, Value> = self.by_swi.iter().enumerate().filter(|(_, c)| **c != 0).map(|(n, c)| (n.to_string(), json!(c))).collect();
+
+        return json!({ "kinds": kinds, "swi": swis });
+    }
+}
+
+// samples the counters once a second so the frontend can show rates (an interrupt storm stands out a lot better as
+// "irq 48000/s" than as an ever-growing total)
+pub struct ExceptionMonitor {
+    last: ExceptionCounts,
+    last_time: Instant,
+    rate: ExceptionCounts,
+}
+
+impl ExceptionMonitor {
+    pub fn new() -> Self {
+        Self {
+            last: ExceptionCounts::zero(),
+            last_time: Instant::now(),
+            rate: ExceptionCounts::zero(),
+        }
+    }
+
+    // returns true when a new one-second sample was taken
+    pub fn update(self: &mut Self, stats: &ExceptionStats) -> bool {
+        if self.last_time.elapsed() < Duration::from_secs(1) {
+            return false;
+        }
+
+        let now = stats.snapshot();
+        self.rate = now.since(&self.last);
+        self.last = now;
+        self.last_time = Instant::now();
+
+        return true;
+    }
+
+    // counts over the last whole second
+    pub fn rate(self: &Self) -> &ExceptionCounts {
+        return &self.rate;
+    }
+}
@@ -0,0 +1,53 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use sdl3::gpu::Device;
+
+use crate::{machine::MachineRunContext, mem::MemoryMap, savestate::{self, Snapshots}, screenshot, timebase::Timebase, uart::UART, vdp::VDP};
+
+// when a guest reports a failed assert, everything needed to look into it goes into <capture dir>/failures/<message>/:
+//
+//   state.nyxs      save state (inspect with `nyxbox state ...`, `nyxbox dump --state ...`)
+//   screenshot.png  the framebuffer at the time
+//   trace.txt       the assert, registers, & the tail of the guest's UART output leading up to it
+//
+// a later failure with the same message replaces the earlier one
+pub const FAILURE_DIR: &str = "failures";
+
+// guest-supplied names can't be allowed to escape the capture directory
+pub fn sanitize_name(name: &str) -> String {
+    return name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+}
+
+pub fn failure_dir(capture_dir: &Path, message: &str) -> PathBuf {
+    let name = sanitize_name(message.trim());
+    let name = if name.is_empty() { "assert".to_string() } else { name };
+
+    return capture_dir.join(FAILURE_DIR).join(name);
+}
+
+pub fn capture_failure(dir: &Path, message: &str, frame: u64, run_ctx: &MachineRunContext, vdp: &mut VDP, gfx_device: &Device,
+    timebase: &Timebase, snapshots: &Snapshots, uart: &UART, map: &MemoryMap, expansion_ram: bool) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+
+    let state_path = dir.join("state.nyxs");
+    savestate::capture_state(run_ctx, vdp, gfx_device, timebase, snapshots, map, expansion_ram)?
+        .save(&state_path)
+        .map_err(|e| format!("failed to write {}: {}", state_path.display(), e))?;
+
+    let mut trace = format!("assert failed @ frame {}: {}\n\nregisters:\n", frame, message);
+
+    for (name, val) in run_ctx.registers() {
+        trace.push_str(&format!("  {:<5} {:08x}\n", name, val));
+    }
+
+    // a guest that never set up a framebuffer still gets the rest of the report
+    if let Err(e) = screenshot::save_framebuffer(vdp, gfx_device, &dir.join("screenshot.png")) {
+        trace.push_str(&format!("\nno screenshot: {}\n", e));
+    }
+
+    trace.push_str("\nuart output (most recent last):\n");
+    trace.push_str(&String::from_utf8_lossy(&uart.tx_history()));
+
+    let trace_path = dir.join("trace.txt");
+    return fs::write(&trace_path, trace).map_err(|e| format!("failed to write {}: {}", trace_path.display(), e));
+}
@@ -0,0 +1,122 @@
+use unicorn_engine::{uc_error, RegisterARM, Unicorn};
+
+use crate::{excstats::{EXCP_DATA_ABORT, EXCP_PREFETCH_ABORT, EXCP_UDEF}, machine::{CPSR_I, CPSR_MODE_MASK, CPSR_T, DEBUG_REGS}, symbols::Symbols};
+
+// words of stack shown under the registers in a fault report
+const STACK_DUMP_WORDS: u32 = 8;
+
+const CPSR_A: u64           = 0x100;
+const CPSR_MODE_ABT: u64    = 0x17;
+const CPSR_MODE_UND: u64    = 0x1B;
+
+// what happens when the guest does something the CPU can't just carry on from - touching unmapped or protected memory, or
+// executing an undefined instruction. either way a register & stack dump is printed at the point it happened
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum FaultMode {
+    /// Stop the CPU & report the fault. The frontend keeps running, so the machine can be reset or a state loaded
+    #[default]
+    Halt,
+    /// Deliver the matching ARM exception to the guest (data abort, prefetch abort, or undefined instruction)
+    Abort,
+}
+
+#[derive(Clone, Copy)]
+pub enum GuestException {
+    Undefined,
+    PrefetchAbort,
+    DataAbort,
+}
+
+impl GuestException {
+    // the exception the hardware would have taken for what stopped emulation, if it's one the guest can handle
+    pub fn from_error(e: uc_error) -> Option<Self> {
+        return match e {
+            uc_error::INSN_INVALID => Some(GuestException::Undefined),
+            uc_error::FETCH_UNMAPPED | uc_error::FETCH_PROT | uc_error::FETCH_UNALIGNED => Some(GuestException::PrefetchAbort),
+            uc_error::READ_UNMAPPED | uc_error::WRITE_UNMAPPED | uc_error::READ_PROT | uc_error::WRITE_PROT |
+                uc_error::READ_UNALIGNED | uc_error::WRITE_UNALIGNED => Some(GuestException::DataAbort),
+            _ => None,
+        };
+    }
+
+    // the number the interrupt hook would have reported it as, for the exception stats
+    pub fn excp(self: &Self) -> u32 {
+        return match self {
+            GuestException::Undefined => EXCP_UDEF,
+            GuestException::PrefetchAbort => EXCP_PREFETCH_ABORT,
+            GuestException::DataAbort => EXCP_DATA_ABORT,
+        };
+    }
+
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            GuestException::Undefined => "undefined instruction",
+            GuestException::PrefetchAbort => "prefetch abort",
+            GuestException::DataAbort => "data abort",
+        };
+    }
+}
+
+// take the exception with the PC on the faulting instruction, the way the core does: switch mode (banking SP & LR) with
+// IRQs masked & in ARM state, save the old CPSR in SPSR, & jump to the vector. LR is set up so the handler can return to
+// retry the instruction with `subs pc, lr, #8` (data abort) or `subs pc, lr, #4` (prefetch abort), or skip an undefined
+// one with `movs pc, lr`
+pub fn enter_exception(cpu: &mut Unicorn<'_, ()>, exception: GuestException) {
+    let cpsr = cpu.reg_read(RegisterARM::CPSR).unwrap();
+    let pc = cpu.pc_read().unwrap();
+    let thumb = cpsr & CPSR_T != 0;
+
+    let (mode, vector, ret) = match exception {
+        GuestException::Undefined => (CPSR_MODE_UND, 0x04, pc + if thumb { 2 } else { 4 }),
+        GuestException::PrefetchAbort => (CPSR_MODE_ABT, 0x0C, pc + 4),
+        GuestException::DataAbort => (CPSR_MODE_ABT, 0x10, pc + 8),
+    };
+    let masks = if mode == CPSR_MODE_ABT { CPSR_I | CPSR_A } else { CPSR_I };
+
+    cpu.reg_write(RegisterARM::CPSR, (cpsr & !(CPSR_MODE_MASK | CPSR_T)) | mode | masks).unwrap();
+    cpu.reg_write(RegisterARM::SPSR, cpsr).unwrap();
+    cpu.reg_write(RegisterARM::LR, ret).unwrap();
+    cpu.reg_write(RegisterARM::PC, vector).unwrap();
+}
+
+// every register, four to a line, then the words at the top of the stack
+pub fn register_dump(cpu: &Unicorn<'_, ()>, symbols: &Symbols) -> String {
+    let mut lines = Vec::new();
+
+    for regs in DEBUG_REGS.chunks(4) {
+        let line: Vec<String> = regs.iter().map(|(name, reg)| format!("{:>4} {:08x}", name, cpu.reg_read(*reg).unwrap_or(0))).collect();
+        lines.push(line.join("  "));
+    }
+
+    // where it was, & where it was called from
+    let pc = cpu.pc_read().unwrap_or(0) as u32;
+    let lr = cpu.reg_read(RegisterARM::LR).unwrap_or(0) as u32;
+
+    if let (Some(pc_name), lr_name) = (symbols.name(pc), symbols.name(lr & !1)) {
+        lines.push(format!("  in {}, lr {}", pc_name, lr_name.unwrap_or(format!("{:08x}", lr))));
+    }
+
+    let sp = cpu.reg_read(RegisterARM::SP).unwrap_or(0) as u32;
+    let mut stack = Vec::new();
+
+    for i in 0..STACK_DUMP_WORDS {
+        let mut word = [0;4];
+
+        // the stack pointer itself may be what's bad
+        if cpu.mem_read(sp.wrapping_add(i * 4) as u64, &mut word).is_err() {
+            break;
+        }
+
+        stack.push(format!("{:08x}", u32::from_le_bytes(word)));
+    }
+
+    if stack.is_empty() {
+        lines.push(format!("stack @ {:08x}: unreadable", sp));
+    }
+    else {
+        lines.push(format!("stack @ {:08x}: {}", sp, stack.join(" ")));
+    }
+
+    return lines.join("\n");
+}
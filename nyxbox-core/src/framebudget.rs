@@ -0,0 +1,136 @@
+use std::{sync::{atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering}, Arc}, time::Instant};
+
+use crate::{peripheral::{LockStats, Peripheral, PeripheralLock}, timebase::{frame_to_ns, ns_to_us, Timebase}};
+
+pub const FRAMEBUDGET_MEM_SIZE: u32 = 4096;
+
+pub const FRAMEBUDGETBIT_OVERRUN: u32       = 1;
+
+// lets guest engines notice when they've blown their per-frame CPU budget (& skip effects, etc) instead of spiraling into slowdown.
+// a frame runs from its frame signal to the CPU going back to WFI, measured in emulated time - so in deterministic mode it's
+// down to the instructions the guest ran, & the same on every host. nothing is measured before the first frame signal, so
+// boot code doesn't count against anything
+pub struct FrameBudget {
+    timebase: Arc<Timebase>,
+    budget_us: AtomicU32,
+    last_us: AtomicU32,
+    overruns: AtomicU32,
+    overrun: AtomicBool,
+    busy: AtomicBool,
+    // emulated time the frame being measured was signalled at
+    start_ns: AtomicU64,
+    // host time spent on frames, for perf reports - the guest never sees it
+    host_start: PeripheralLock<Instant>,
+    host_total_us: AtomicU64,
+}
+
+impl FrameBudget {
+    pub fn new(budget_us: u32, timebase: Arc<Timebase>) -> Self {
+        Self {
+            timebase,
+            budget_us: AtomicU32::new(budget_us),
+            last_us: AtomicU32::new(0),
+            overruns: AtomicU32::new(0),
+            overrun: AtomicBool::new(false),
+            busy: AtomicBool::new(false),
+            start_ns: AtomicU64::new(0),
+            host_start: PeripheralLock::new(Instant::now()),
+            host_total_us: AtomicU64::new(0),
+        }
+    }
+
+    fn flag_overrun(self: &Self) {
+        self.overrun.store(true, Ordering::Relaxed);
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // CPU thread: woken up to run a frame
+    pub fn begin_frame(self: &Self) {
+        *self.host_start.lock() = Instant::now();
+        self.busy.store(true, Ordering::Release);
+    }
+
+    // CPU thread: reached WFI
+    pub fn end_frame(self: &Self) {
+        if !self.busy.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        let elapsed = ns_to_us(self.timebase.now_ns().saturating_sub(self.start_ns.load(Ordering::Acquire))).min(u32::MAX as u64) as u32;
+        self.last_us.store(elapsed, Ordering::Relaxed);
+
+        let host_elapsed = self.host_start.lock().elapsed().as_micros() as u64;
+        self.host_total_us.fetch_add(host_elapsed, Ordering::Relaxed);
+
+        let budget = self.budget_us.load(Ordering::Relaxed);
+        if budget != 0 && elapsed > budget {
+            self.flag_overrun();
+        }
+    }
+
+    // host CPU time spent on frames since boot
+    pub fn total_us(self: &Self) -> u64 {
+        return self.host_total_us.load(Ordering::Relaxed);
+    }
+
+    // frontend: a new frame signal is being raised, with the timebase already on the new frame. if the CPU still hasn't
+    // finished the last frame, that's an overrun no matter what the budget is - & the rest of its work counts against this one
+    pub fn frame_signal(self: &Self) {
+        self.start_ns.store(frame_to_ns(self.timebase.frame()), Ordering::Release);
+
+        if self.busy.load(Ordering::Acquire) {
+            self.flag_overrun();
+        }
+    }
+}
+
+impl Peripheral for FrameBudget {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // STATUS
+                return if self.overrun.load(Ordering::Relaxed) { FRAMEBUDGETBIT_OVERRUN } else { 0 };
+            }
+            0x01 => {
+                // OVERRUNS
+                return self.overruns.load(Ordering::Relaxed);
+            }
+            0x02 => {
+                // BUDGET
+                return self.budget_us.load(Ordering::Relaxed);
+            }
+            0x03 => {
+                // LASTFRAME
+                return self.last_us.load(Ordering::Relaxed);
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // STATUS (write 1 to clear)
+                if val & FRAMEBUDGETBIT_OVERRUN != 0 {
+                    self.overrun.store(false, Ordering::Relaxed);
+                }
+            }
+            0x01 => {
+                // OVERRUNS
+                self.overruns.store(val, Ordering::Relaxed);
+            }
+            0x02 => {
+                // BUDGET
+                self.budget_us.store(val, Ordering::Relaxed);
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("framebudget", self.host_start.stats())];
+    }
+}
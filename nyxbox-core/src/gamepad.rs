@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{peripheral::Peripheral, savestate::{StateReader, StateWriter}};
+
+pub const GAMEPAD_MEM_SIZE: u32 = 4096;
+
+pub const BUTTON_UP: u32        = 0x001;
+pub const BUTTON_DOWN: u32      = 0x002;
+pub const BUTTON_LEFT: u32      = 0x004;
+pub const BUTTON_RIGHT: u32     = 0x008;
+pub const BUTTON_A: u32         = 0x010;
+pub const BUTTON_B: u32         = 0x020;
+pub const BUTTON_X: u32         = 0x040;
+pub const BUTTON_Y: u32         = 0x080;
+pub const BUTTON_L: u32         = 0x100;
+pub const BUTTON_R: u32         = 0x200;
+pub const BUTTON_START: u32     = 0x400;
+pub const BUTTON_SELECT: u32    = 0x800;
+
+// button names, for the CLI & anything else that needs to talk about them
+pub const BUTTON_NAMES: &[(&str, u32)] = &[
+    ("up", BUTTON_UP),
+    ("down", BUTTON_DOWN),
+    ("left", BUTTON_LEFT),
+    ("right", BUTTON_RIGHT),
+    ("a", BUTTON_A),
+    ("b", BUTTON_B),
+    ("x", BUTTON_X),
+    ("y", BUTTON_Y),
+    ("l", BUTTON_L),
+    ("r", BUTTON_R),
+    ("start", BUTTON_START),
+    ("select", BUTTON_SELECT),
+];
+
+// single player pad. the frontend latches the button state once per frame, so the guest sees one stable value for the whole frame
+pub struct Gamepad {
+    buttons: AtomicU32,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Self {
+            buttons: AtomicU32::new(0),
+        }
+    }
+
+    pub fn latch(self: &Self, buttons: u32) {
+        self.buttons.store(buttons, Ordering::Relaxed);
+    }
+}
+
+impl Peripheral for Gamepad {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // BUTTONS
+                return self.buttons.load(Ordering::Relaxed);
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, _addr: u32, _val: u32) {
+    }
+
+    fn save_state(self: &Self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.u32(self.buttons.load(Ordering::Relaxed));
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let buttons = r.u32()?;
+        r.finish()?;
+
+        self.buttons.store(buttons, Ordering::Relaxed);
+        return Ok(());
+    }
+}
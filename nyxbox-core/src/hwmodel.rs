@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// one switch for how closely the machine holds the guest to the real hardware's limits. every behavior that differs
+// between the two is decided here, so peripherals ask the policy rather than checking the mode themselves:
+//
+//   limit               strict                                      developer
+//   VDP command FIFO    CMD_FIFO_DEPTH queues per frame, further    unbounded
+//                       submissions dropped & CMDFIFOFULL set
+//   VRAM DMA bandwidth  DMA_BYTES_PER_FRAME, later DMAs wait for    unlimited
+//                       the next frame (DMABUSY stays set)
+//   UART TX FIFO        bytes written while full are dropped        the CPU stalls until there's room
+//   MMIO alignment      an access not aligned to its own size       split across byte lanes (see machine.rs)
+//                       stops the CPU with a fault
+//
+// strict mode enforces quietly, the way the hardware would. developer mode lets the guest past each limit, but reports the
+// first time it goes over one (with a count on exit), so code that only works in the emulator doesn't go unnoticed.
+// the model is visible to the guest as FEATUREBIT_STRICTHW, so tests can check both sides of each limit
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum HardwareModel {
+    /// Enforce every modeled hardware limit, so what runs here runs on the real machine
+    Strict,
+    /// Relax the hardware limits, & warn the first time the guest relies on that
+    #[default]
+    Developer,
+}
+
+pub const CMD_FIFO_DEPTH: usize       = 16;
+pub const DMA_BYTES_PER_FRAME: u32    = 1024 * 1024;
+
+#[derive(Clone, Copy)]
+pub enum Limit {
+    CmdFifo,
+    DmaBandwidth,
+    UartTxFifo,
+    MmioAlignment,
+}
+
+const LIMIT_NAMES: [&str;4] = ["command FIFO", "DMA bandwidth", "UART TX FIFO", "MMIO alignment"];
+
+pub struct HwLimits {
+    model: HardwareModel,
+    hits: [AtomicU64;4],
+}
+
+impl HwLimits {
+    pub fn new(model: HardwareModel) -> Self {
+        Self {
+            model,
+            hits: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    pub fn is_strict(self: &Self) -> bool {
+        return self.model == HardwareModel::Strict;
+    }
+
+    // the guest went over a limit. it's counted either way - true means the limit is enforced & the access has to be refused,
+    // false means developer mode lets it through (saying so the first time)
+    pub fn exceeded(self: &Self, limit: Limit, detail: impl FnOnce() -> String) -> bool {
+        let prev = self.hits[limit as usize].fetch_add(1, Ordering::Relaxed);
+
+        if self.is_strict() {
+            return true;
+        }
+
+        if prev == 0 {
+            crate::log!(Hw, Warn, "{} exceeded: {} (strict mode would enforce this - further occurrences are only counted)", LIMIT_NAMES[limit as usize], detail());
+        }
+
+        return false;
+    }
+
+    // e.g. "command FIFO 3, DMA bandwidth 12" - None if the guest stayed within every limit
+    pub fn summary(self: &Self) -> Option<String> {
+        let parts: Vec<String> = LIMIT_NAMES.iter().zip(&self.hits)
+            .map(|(name, hits)| (name, hits.load(Ordering::Relaxed)))
+            .filter(|(_, hits)| *hits != 0)
+            .map(|(name, hits)| format!("{} {}", name, hits))
+            .collect();
+
+        return if parts.is_empty() { None } else { Some(parts.join(", ")) };
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+
+use crate::{mem::{MemoryMap, BOOT_ROM_BEGIN, VRAM_DEBUG_BEGIN}, savestate::{SaveState, SECTION_RAM, SECTION_ROM, SECTION_VRAM, SECTION_XRAM}};
+
+// a flat view over every memory region we know about, addressed the same way the guest (or the VDP) sees it
+pub struct Region {
+    pub name: &'static str,
+    pub base: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct AddressSpace {
+    regions: Vec<Region>,
+}
+
+impl AddressSpace {
+    pub fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn add_region(self: &mut Self, name: &'static str, base: u32, data: Vec<u8>) {
+        self.regions.push(Region { name, base, data });
+    }
+
+    // the ROM, RAM, & VRAM images a save state has, each where the guest (or the VDP) sees it under the given memory map
+    pub fn add_state(self: &mut Self, state: &SaveState, map: &MemoryMap) {
+        for (name, tag, base) in [("rom", SECTION_ROM, BOOT_ROM_BEGIN), ("ram", SECTION_RAM, map.main_ram_begin), ("xram", SECTION_XRAM, map.expansion_ram_begin), ("vram", SECTION_VRAM, VRAM_DEBUG_BEGIN)] {
+            if let Some(data) = state.section(&tag) {
+                self.add_region(name, base as u32, data.to_vec());
+            }
+        }
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        return self.regions.is_empty();
+    }
+
+    pub fn region_at(self: &Self, addr: u32) -> Option<&Region> {
+        return self.regions.iter().find(|r| addr >= r.base && ((addr - r.base) as usize) < r.data.len());
+    }
+
+    // returns up to len bytes starting at addr - reads never cross into another region
+    pub fn read(self: &Self, addr: u32, len: usize) -> Option<&[u8]> {
+        let region = self.region_at(addr)?;
+        let offs = (addr - region.base) as usize;
+        let end = (offs + len).min(region.data.len());
+        return Some(&region.data[offs..end]);
+    }
+
+    pub fn find(self: &Self, pattern: &[u8]) -> Vec<(u32, &'static str)> {
+        let mut results = Vec::new();
+
+        if pattern.is_empty() {
+            return results;
+        }
+
+        for region in &self.regions {
+            for (i, window) in region.data.windows(pattern.len()).enumerate() {
+                if window == pattern {
+                    results.push((region.base + i as u32, region.name));
+                }
+            }
+        }
+
+        return results;
+    }
+}
+
+pub fn parse_addr(s: &str) -> Result<u32, String> {
+    let res = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    }
+    else {
+        s.parse::<u32>()
+    };
+
+    return res.map_err(|e| format!("invalid address '{}': {}", s, e));
+}
+
+// accepts "deadbeef", "de ad be ef", or "de,ad,be,ef"
+pub fn parse_hex_pattern(s: &str) -> Result<Vec<u8>, String> {
+    let digits: String = s.chars().filter(|c| !c.is_whitespace() && *c != ',').collect();
+
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return Err(format!("invalid hex pattern '{}'", s));
+    }
+
+    let mut bytes = Vec::new();
+    for i in (0..digits.len()).step_by(2) {
+        let b = u8::from_str_radix(&digits[i..i+2], 16).map_err(|_| format!("invalid hex pattern '{}'", s))?;
+        bytes.push(b);
+    }
+
+    return Ok(bytes);
+}
+
+pub fn hexdump<W: Write>(out: &mut W, addr: u32, data: &[u8]) -> io::Result<()> {
+    for (i, line) in data.chunks(16).enumerate() {
+        write!(out, "{:08x} ", addr + (i * 16) as u32)?;
+
+        for j in 0..16 {
+            if j == 8 {
+                write!(out, " ")?;
+            }
+
+            match line.get(j) {
+                Some(b) => write!(out, " {:02x}", b)?,
+                None => write!(out, "   ")?,
+            }
+        }
+
+        let ascii: String = line.iter().map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' }).collect();
+        writeln!(out, "  |{}|", ascii)?;
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,203 @@
+use std::sync::{atomic::{AtomicU32, Ordering}, Arc, Mutex, OnceLock};
+
+use crate::{chrometrace::{ChromeTrace, TRACK_INTERRUPTS}, peripheral::Peripheral, savestate::{StateReader, StateWriter}};
+
+pub const INTC_MEM_SIZE: u32 = 4096;
+
+// interrupt lines, one bit each in every INTC register
+pub const IRQ_VBLANK: u32       = 1;
+pub const IRQ_VDP: u32          = 2;
+pub const IRQ_UART_RX: u32      = 4;
+pub const IRQ_TIMER: u32        = 8;
+pub const IRQ_MAILBOX: u32      = 16;
+pub const IRQ_WATCHDOG: u32     = 32;
+
+const LINE_NAMES: [(u32, &str);6] = [(IRQ_VBLANK, "vblank"), (IRQ_VDP, "vdp"), (IRQ_UART_RX, "uart rx"), (IRQ_TIMER, "timer"), (IRQ_MAILBOX, "mailbox"), (IRQ_WATCHDOG, "watchdog")];
+
+// the ARM IRQ & FIQ vectors (low vectors - the boot ROM holds the vector table)
+pub const IRQ_VECTOR: u32 = 0x18;
+pub const FIQ_VECTOR: u32 = 0x1C;
+
+// peripherals assert lines, which latch in PENDING until the guest acknowledges them. a line that is both pending & enabled
+// makes the controller request an IRQ from the CPU, which takes it as soon as its I bit is clear - or an FIQ, for lines
+// routed there, which is taken as soon as its F bit is clear. FIQ wins when both are wanted, & can interrupt an IRQ handler:
+//
+//   PENDING    (read only) lines raised & not yet acknowledged
+//   ENABLE     lines allowed to interrupt the CPU
+//   ACTIVE     (read only) PENDING & ENABLE, less FIQSEL - what the IRQ handler should service
+//   ACK        write 1s to clear those lines from PENDING
+//   RAISE      write 1s to raise lines from software
+//   FIQSEL     lines delivered as FIQ rather than IRQ
+//   FIQACTIVE  (read only) PENDING & ENABLE & FIQSEL - what the FIQ handler should service
+//
+// lines are edge triggered: a line raised again before it's acknowledged stays a single pending interrupt
+pub struct InterruptController {
+    pending: AtomicU32,
+    enable: AtomicU32,
+    fiq_select: AtomicU32,
+    // how the CPU gets told there's an IRQ to take - set by the machine while the CPU thread is running
+    notify: Mutex<Option<Box<dyn Fn() + Send + Sync>>>,
+    chrome_trace: OnceLock<Arc<ChromeTrace>>,
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        Self {
+            pending: AtomicU32::new(0),
+            enable: AtomicU32::new(0),
+            fiq_select: AtomicU32::new(0),
+            notify: Mutex::new(None),
+            chrome_trace: OnceLock::new(),
+        }
+    }
+
+    pub fn set_notify(self: &Self, notify: Option<Box<dyn Fn() + Send + Sync>>) {
+        *self.notify.lock().unwrap() = notify;
+    }
+
+    // an instant for every line raised, from whichever thread raised it
+    pub fn set_chrome_trace(self: &Self, trace: Arc<ChromeTrace>) {
+        let _ = self.chrome_trace.set(trace);
+    }
+
+    pub fn raise(self: &Self, lines: u32) {
+        if let Some(trace) = self.chrome_trace.get() {
+            trace.instant(TRACK_INTERRUPTS, "intc", line_names(lines), None);
+        }
+
+        self.pending.fetch_or(lines, Ordering::AcqRel);
+        self.update();
+    }
+
+    // lines that want the CPU's attention, whichever way they're delivered
+    pub fn active(self: &Self) -> u32 {
+        return self.pending.load(Ordering::Acquire) & self.enable.load(Ordering::Acquire);
+    }
+
+    // ...those that want an IRQ
+    pub fn active_irq(self: &Self) -> u32 {
+        return self.active() & !self.fiq_select.load(Ordering::Acquire);
+    }
+
+    // ...& those that want an FIQ
+    pub fn active_fiq(self: &Self) -> u32 {
+        return self.active() & self.fiq_select.load(Ordering::Acquire);
+    }
+
+    // a handle a peripheral keeps for raising its own line
+    pub fn line(self: &Arc<Self>, line: u32) -> IrqLine {
+        return IrqLine {
+            intc: self.clone(),
+            line,
+        };
+    }
+
+    fn update(self: &Self) {
+        if self.active() != 0 {
+            if let Some(notify) = self.notify.lock().unwrap().as_ref() {
+                notify();
+            }
+        }
+    }
+}
+
+impl Peripheral for InterruptController {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // PENDING
+                return self.pending.load(Ordering::Acquire);
+            }
+            0x01 => {
+                // ENABLE
+                return self.enable.load(Ordering::Acquire);
+            }
+            0x02 => {
+                // ACTIVE
+                return self.active_irq();
+            }
+            0x05 => {
+                // FIQSEL
+                return self.fiq_select.load(Ordering::Acquire);
+            }
+            0x06 => {
+                // FIQACTIVE
+                return self.active_fiq();
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        match addr {
+            0x01 => {
+                // ENABLE
+                self.enable.store(val, Ordering::Release);
+                self.update();
+            }
+            0x03 => {
+                // ACK
+                self.pending.fetch_and(!val, Ordering::AcqRel);
+            }
+            0x04 => {
+                // RAISE
+                self.raise(val);
+            }
+            0x05 => {
+                // FIQSEL
+                self.fiq_select.store(val, Ordering::Release);
+                self.update();
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn save_state(self: &Self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+
+        w.u32(self.pending.load(Ordering::Acquire));
+        w.u32(self.enable.load(Ordering::Acquire));
+        w.u32(self.fiq_select.load(Ordering::Acquire));
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let pending = r.u32()?;
+        let enable = r.u32()?;
+        let fiq_select = r.u32()?;
+        r.finish()?;
+
+        self.pending.store(pending, Ordering::Release);
+        self.enable.store(enable, Ordering::Release);
+        self.fiq_select.store(fiq_select, Ordering::Release);
+        self.update();
+
+        return Ok(());
+    }
+}
+
+// e.g. "vblank|timer", for diagnostics
+pub fn line_names(lines: u32) -> String {
+    let names: Vec<String> = (0..32).map(|bit| 1 << bit).filter(|line| lines & line != 0)
+        .map(|line| LINE_NAMES.iter().find(|(l, _)| *l == line).map_or(format!("line {:#x}", line), |(_, name)| name.to_string()))
+        .collect();
+
+    return names.join("|");
+}
+
+#[derive(Clone)]
+pub struct IrqLine {
+    intc: Arc<InterruptController>,
+    line: u32,
+}
+
+impl IrqLine {
+    pub fn raise(self: &Self) {
+        self.intc.raise(self.line);
+    }
+}
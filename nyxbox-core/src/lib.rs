@@ -0,0 +1,59 @@
+// the emulated machine - CPU, memory map, peripherals, VDP & save states - with nothing of a frontend: no window, input,
+// or event loop. the VDP does its work through an SDL GPU device, which is the one part of SDL the core needs. frontends
+// (nyxbox-sdl being the one there is) build a system::System, drive it a frame at a time, & present what it draws
+extern crate sdl3;
+extern crate unicorn_engine;
+extern crate rsevents;
+
+pub mod log;
+pub mod mem;
+pub mod peripheral;
+pub mod devmap;
+pub mod machine;
+pub mod inspect;
+pub mod storage;
+pub mod savestate;
+pub mod screenshot;
+pub mod pacing;
+pub mod movie;
+pub mod clock;
+pub mod uart;
+pub mod vdp;
+pub mod vdpqueue;
+pub mod vdpport;
+pub mod present;
+pub mod sysinfo;
+pub mod debugport;
+pub mod mpu;
+pub mod framebudget;
+pub mod gamepad;
+pub mod failcapture;
+pub mod excstats;
+pub mod memfill;
+pub mod poison;
+pub mod texdump;
+pub mod renderdebug;
+pub mod vucapture;
+pub mod vdpcheck;
+pub mod bios;
+pub mod timebase;
+pub mod capture;
+pub mod hwmodel;
+pub mod intc;
+pub mod breakpoint;
+pub mod disasm;
+pub mod trace;
+pub mod chrometrace;
+pub mod fault;
+pub mod buserr;
+pub mod mailbox;
+pub mod coproc;
+pub mod symbols;
+pub mod lines;
+pub mod locals;
+pub mod watchdog;
+pub mod rewind;
+pub mod crashdump;
+pub mod elf;
+pub mod preload;
+pub mod system;
@@ -0,0 +1,421 @@
+use std::{collections::HashMap, fs, path::Path, sync::{Arc, OnceLock}};
+
+use gimli::{AttributeValue, DebugLine, DebugLineOffset, DebugLineStr, DebugStr, EndianSlice, LittleEndian};
+
+use crate::symbols::{check_elf, find_section};
+
+// source lines from the DWARF line table (.debug_line) of an ELF built with -g, so a debugger can put breakpoints on lines
+// & step by them. gimli does the reading, so every line table version & both DWARF formats are understood
+
+// a debug section as gimli reads it, borrowed straight out of the ELF - the other debug info parsers use it too
+pub(crate) type Section<'a> = EndianSlice<'a, LittleEndian>;
+
+// ARM code has 4 byte addresses
+pub(crate) const ADDRESS_SIZE: u8 = 4;
+
+// a row of the line table: the instructions from addr up to the next row's address are for this line
+struct Row {
+    addr: u32,
+    file: u32,
+    line: u32,
+    // a good place for a breakpoint on the line
+    is_stmt: bool,
+    // the first address past a sequence of instructions, rather than a line
+    end: bool,
+}
+
+pub struct LineTable {
+    files: Vec<String>,
+    // in the order the line programs gave them, sequence by sequence
+    rows: Vec<Row>,
+    // (start, end, row) for every row that covers some code, sorted by start address
+    ranges: Vec<(u32, u32, usize)>,
+}
+
+// a System's line table, shared with whatever steps or sets breakpoints by line
+#[derive(Clone, Default)]
+pub struct Lines(Arc<OnceLock<LineTable>>);
+
+impl LineTable {
+    // an ELF without a line table has no lines in it, rather than being an error
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        check_elf(data)?;
+
+        let mut table = Self { files: Vec::new(), rows: Vec::new(), ranges: Vec::new() };
+
+        let Some(debug_line) = find_section(data, ".debug_line")? else {
+            return Ok(table);
+        };
+
+        let debug_str = DebugStr::from(section(data, ".debug_str")?);
+        let debug_line_str = DebugLineStr::from(section(data, ".debug_line_str")?);
+        let len = debug_line.len();
+        let debug_line = DebugLine::new(debug_line, LittleEndian);
+
+        let mut file_ids = HashMap::new();
+        let mut offset = 0;
+
+        // one line program per compilation unit. they're walked straight through rather than found from .debug_info,
+        // which a stripped-down ELF may not have
+        while offset < len {
+            offset = table.parse_unit(&debug_line, offset, &debug_str, &debug_line_str, &mut file_ids)
+                .map_err(|e| format!(".debug_line @ {:#x}: {}", offset, e))?;
+        }
+
+        for (idx, pair) in table.rows.windows(2).enumerate() {
+            if !pair[0].end && pair[1].addr > pair[0].addr {
+                table.ranges.push((pair[0].addr, pair[1].addr, idx));
+            }
+        }
+
+        table.ranges.sort_by_key(|(start, _, _)| *start);
+
+        return Ok(table);
+    }
+
+    // returns where the next unit starts
+    fn parse_unit(self: &mut Self, debug_line: &DebugLine<Section>, offset: usize, debug_str: &DebugStr<Section>, debug_line_str: &DebugLineStr<Section>, file_ids: &mut HashMap<String, u32>) -> gimli::Result<usize> {
+        let program = debug_line.program(DebugLineOffset(offset), ADDRESS_SIZE, None, None)?;
+        let header = program.header();
+        let end = offset + header.format().initial_length_size() as usize + header.unit_length();
+
+        // the unit's file numbers, as ids in self.files - a table can define more files as it goes, so they're looked up
+        // as rows name them
+        let mut ids: HashMap<u64, u32> = HashMap::new();
+        let mut rows = program.rows();
+
+        while let Some((header, row)) = rows.next_row()? {
+            let file = match ids.get(&row.file_index()) {
+                Some(file) => *file,
+                None => {
+                    // a file the unit doesn't list, which a broken table could name - the row can't be placed anywhere
+                    let Some(entry) = row.file(header) else {
+                        continue;
+                    };
+
+                    let dir = entry.directory(header).map_or(Ok(String::new()), |dir| attr_string(dir, debug_str, debug_line_str))?;
+                    let path = join_path(&dir, &attr_string(entry.path_name(), debug_str, debug_line_str)?);
+                    let file = self.file_id(&path, file_ids);
+
+                    ids.insert(row.file_index(), file);
+                    file
+                }
+            };
+
+            self.rows.push(Row {
+                addr: row.address() as u32,
+                file,
+                line: row.line().map_or(0, |line| line.get() as u32),
+                is_stmt: row.is_stmt(),
+                end: row.end_sequence(),
+            });
+        }
+
+        return Ok(end);
+    }
+
+    fn file_id(self: &mut Self, path: &str, file_ids: &mut HashMap<String, u32>) -> u32 {
+        return *file_ids.entry(path.to_string()).or_insert_with(|| {
+            self.files.push(path.to_string());
+            return self.files.len() as u32 - 1;
+        });
+    }
+
+    pub fn len(self: &Self) -> usize {
+        return self.ranges.len();
+    }
+
+    // the file & line addr is code for
+    pub fn lookup(self: &Self, addr: u32) -> Option<(&str, u32)> {
+        let idx = self.ranges.partition_point(|(start, _, _)| *start <= addr).checked_sub(1)?;
+        let (_, end, row) = self.ranges[idx];
+
+        if addr >= end || self.rows[row].line == 0 {
+            return None;
+        }
+
+        return Some((&self.files[self.rows[row].file as usize], self.rows[row].line));
+    }
+
+    // where a breakpoint on a line of `path` goes: the line it ends up on (the next one with code, if the line has none) &
+    // the address of each run of code for it - a loop's condition, say, can be in two places
+    pub fn addresses(self: &Self, path: &str, line: u32) -> Option<(u32, Vec<u32>)> {
+        let files: Vec<bool> = self.files.iter().map(|file| same_file(file, path)).collect();
+        let on_file = |row: &Row| !row.end && row.is_stmt && files[row.file as usize];
+
+        let line = self.rows.iter().filter(|row| on_file(row) && row.line >= line).map(|row| row.line).min()?;
+        let mut addrs = Vec::new();
+
+        for (idx, row) in self.rows.iter().enumerate() {
+            if !on_file(row) || row.line != line {
+                continue;
+            }
+
+            // the same line carrying on from the row before is the same run of code
+            let prev = idx.checked_sub(1).map(|prev| &self.rows[prev]).filter(|prev| !prev.end);
+            if prev.is_some_and(|prev| prev.file == row.file && prev.line == row.line) {
+                continue;
+            }
+
+            addrs.push(row.addr);
+        }
+
+        addrs.sort();
+        addrs.dedup();
+
+        return Some((line, addrs));
+    }
+}
+
+impl Lines {
+    // load the line table breakpoints & stepping use from here on. returns how many ranges of code it has lines for
+    pub fn load(self: &Self, path: &Path) -> Result<usize, String> {
+        let data = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let table = LineTable::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let len = table.len();
+
+        self.0.set(table).map_err(|_| "a line table is already loaded".to_string())?;
+        return Ok(len);
+    }
+
+    pub fn lookup(self: &Self, addr: u32) -> Option<(&str, u32)> {
+        return self.0.get()?.lookup(addr);
+    }
+
+    pub fn addresses(self: &Self, path: &str, line: u32) -> Option<(u32, Vec<u32>)> {
+        return self.0.get()?.addresses(path, line);
+    }
+
+    pub fn is_loaded(self: &Self) -> bool {
+        return self.0.get().is_some_and(|table| table.len() > 0);
+    }
+}
+
+// a debugger has the full path of the file it's showing, while the line table may only have it relative to where it was
+// built - so either can be the tail of the other
+fn same_file(a: &str, b: &str) -> bool {
+    let (a, b) = (Path::new(a), Path::new(b));
+    return a.ends_with(b) || b.ends_with(a);
+}
+
+fn join_path(dir: &str, path: &str) -> String {
+    return Path::new(dir).join(path).to_string_lossy().into_owned();
+}
+
+// a section's contents, or nothing if the ELF doesn't have it
+pub(crate) fn section<'a>(data: &'a [u8], name: &str) -> Result<Section<'a>, String> {
+    return Ok(EndianSlice::new(find_section(data, name)?.unwrap_or(&[]), LittleEndian));
+}
+
+// a directory or file name, which a version 5 table can keep in either string section
+fn attr_string(attr: AttributeValue<Section>, debug_str: &DebugStr<Section>, debug_line_str: &DebugLineStr<Section>) -> gimli::Result<String> {
+    let text = match attr {
+        AttributeValue::String(text) => text,
+        AttributeValue::DebugStrRef(offset) => debug_str.get_str(offset)?,
+        AttributeValue::DebugLineStrRef(offset) => debug_line_str.get_str(offset)?,
+        _ => return Ok(String::new()),
+    };
+
+    return Ok(text.to_string_lossy().into_owned());
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use gimli::{DW_FORM_data16, DW_FORM_line_strp, DW_FORM_string, DW_FORM_udata, DW_LNCT_MD5, DW_LNCT_directory_index, DW_LNCT_path};
+    use gimli::{DW_LNE_end_sequence, DW_LNE_set_address, DW_LNS_advance_line, DW_LNS_advance_pc, DW_LNS_copy, DW_LNS_set_file};
+
+    use super::*;
+
+    // the rows for two files: main.c lines 10, 11, 13, with util.h line 3 inlined in the middle of 11. files count from 1
+    // before version 5, & from 0 after
+    fn program(main: u8, util: u8) -> Vec<u8> {
+        return [
+            &[0x00, 5, DW_LNE_set_address.0, 0x00, 0x10, 0x00, 0x00][..],
+            &[DW_LNS_set_file.0, main, DW_LNS_advance_line.0, 9, DW_LNS_copy.0],                                // 0x1000 main.c:10
+            &[DW_LNS_advance_pc.0, 8, DW_LNS_advance_line.0, 1, DW_LNS_copy.0],                                 // 0x1008 main.c:11
+            &[DW_LNS_advance_pc.0, 4, DW_LNS_set_file.0, util, DW_LNS_advance_line.0, 0x78, DW_LNS_copy.0],     // 0x100c util.h:3
+            &[DW_LNS_advance_pc.0, 4, DW_LNS_set_file.0, main, DW_LNS_advance_line.0, 8, DW_LNS_copy.0],        // 0x1010 main.c:11
+            &[DW_LNS_advance_pc.0, 4, DW_LNS_advance_line.0, 2, DW_LNS_copy.0],                                 // 0x1014 main.c:13
+            &[DW_LNS_advance_pc.0, 4, 0x00, 1, DW_LNE_end_sequence.0],                                          // ends at 0x1018
+        ].concat();
+    }
+
+    // the header fields every version has, from the minimum instruction length on
+    fn header_fields(version: u16) -> Vec<u8> {
+        let mut fields = vec![1];                                   // minimum instruction length
+        if version >= 4 {
+            fields.push(1);                                         // maximum operations per instruction
+        }
+
+        fields.extend([
+            1,                                                      // default is_stmt
+            (-5i8) as u8,                                           // line base
+            14,                                                     // line range
+            13,                                                     // opcode base
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1,                     // standard opcode lengths
+        ]);
+        return fields;
+    }
+
+    fn unit(version: u16, prefix: &[u8], header: &[u8], program: &[u8]) -> Vec<u8> {
+        let mut unit = version.to_le_bytes().to_vec();
+        unit.extend(prefix);
+        unit.extend((header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend(program);
+
+        let mut data = (unit.len() as u32).to_le_bytes().to_vec();
+        data.extend(unit);
+        return data;
+    }
+
+    // a version 2 to 4 line program, with its directories & files inline in the header
+    fn line_program(version: u16) -> Vec<u8> {
+        let mut header = header_fields(version);
+        header.extend(b"src\0\0");
+        header.extend(b"main.c\0\x01\0\0util.h\0\x01\0\0\0");
+
+        return unit(version, &[], &header, &program(1, 2));
+    }
+
+    // a version 5 line program, whose directory & file entries say how they're stored: here the directories are
+    // inline, the file names are in .debug_line_str, & the files carry an MD5 the table doesn't need. returns
+    // .debug_line & .debug_line_str
+    fn line_program_v5() -> (Vec<u8>, Vec<u8>) {
+        let line_str = b"main.c\0util.h\0".to_vec();
+
+        let mut header = header_fields(5);
+
+        // directories: the compilation directory, then src
+        header.extend([1, DW_LNCT_path.0 as u8, DW_FORM_string.0 as u8, 2]);
+        header.extend(b"/build\0src\0");
+
+        // files: the primary source file, which version 5 lists as file 0, then util.h
+        header.extend([3, DW_LNCT_path.0 as u8, DW_FORM_line_strp.0 as u8, DW_LNCT_directory_index.0 as u8,
+            DW_FORM_udata.0 as u8, DW_LNCT_MD5.0 as u8, DW_FORM_data16.0 as u8, 2]);
+        header.extend(0u32.to_le_bytes());
+        header.push(1);
+        header.extend([0xAA;16]);
+        header.extend(7u32.to_le_bytes());
+        header.push(1);
+        header.extend([0xBB;16]);
+
+        // address & segment selector sizes come before the header length in version 5
+        return (unit(5, &[4, 0], &header, &program(0, 1)), line_str);
+    }
+
+    // just enough of an ELF for find_section: the header, the section names, & the sections
+    pub(crate) fn elf(sections: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut names = vec![0];
+        let mut contents = Vec::new();
+
+        for (name, data) in [(".shstrtab", &[][..])].iter().chain(sections) {
+            contents.push((names.len() as u32, *data));
+            names.extend(name.as_bytes());
+            names.push(0);
+        }
+
+        contents[0].1 = &names;
+
+        let mut elf = vec![0;0x34];
+        elf[..6].copy_from_slice(b"\x7fELF\x01\x01");
+
+        let mut headers = vec![0;40];
+
+        for (name, data) in contents {
+            let mut header = vec![0;40];
+            header[0..4].copy_from_slice(&name.to_le_bytes());
+            header[16..20].copy_from_slice(&(elf.len() as u32).to_le_bytes());
+            header[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            headers.extend(header);
+            elf.extend(data);
+        }
+
+        let shoff = elf.len() as u32;
+        elf[0x20..0x24].copy_from_slice(&shoff.to_le_bytes());
+        elf[0x30..0x32].copy_from_slice(&((headers.len() / 40) as u16).to_le_bytes());
+        elf[0x32..0x34].copy_from_slice(&1u16.to_le_bytes());
+        elf.extend(headers);
+        return elf;
+    }
+
+    fn table() -> LineTable {
+        return LineTable::parse(&elf(&[(".debug_line", &line_program(4))])).unwrap();
+    }
+
+    fn src(name: &str) -> String {
+        return join_path("src", name);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(table().len(), 5);
+
+        // no line table is no lines, but not an ELF at all is an error
+        assert_eq!(LineTable::parse(&elf(&[])).unwrap().len(), 0);
+        assert!(LineTable::parse(b"not an elf").is_err());
+    }
+
+    #[test]
+    fn versions() {
+        for version in 2..=4 {
+            let table = LineTable::parse(&elf(&[(".debug_line", &line_program(version))])).unwrap();
+            assert_eq!(table.lookup(0x100c), Some((src("util.h").as_str(), 3)), "version {}", version);
+        }
+
+        let (debug_line, line_str) = line_program_v5();
+        let table = LineTable::parse(&elf(&[(".debug_line", &debug_line), (".debug_line_str", &line_str)])).unwrap();
+
+        assert_eq!(table.len(), 5);
+        assert_eq!(table.lookup(0x1000), Some((src("main.c").as_str(), 10)));
+        assert_eq!(table.lookup(0x100c), Some((src("util.h").as_str(), 3)));
+        assert_eq!(table.addresses("main.c", 11), Some((11, vec![0x1008, 0x1010])));
+    }
+
+    #[test]
+    fn truncated() {
+        // cut short anywhere - in the unit length, the header, the file list, or the program - is an error, not a panic
+        // or a partial table
+        let data = line_program(4);
+
+        for len in 1..data.len() {
+            assert!(LineTable::parse(&elf(&[(".debug_line", &data[..len])])).is_err(), "cut to {} bytes", len);
+        }
+
+        // a string section offset past its end
+        let (debug_line, _) = line_program_v5();
+        assert!(LineTable::parse(&elf(&[(".debug_line", &debug_line), (".debug_line_str", b"main.c\0")])).is_err());
+    }
+
+    #[test]
+    fn lookup() {
+        let table = table();
+
+        assert_eq!(table.lookup(0x1000), Some((src("main.c").as_str(), 10)));
+        assert_eq!(table.lookup(0x1007), Some((src("main.c").as_str(), 10)));
+        assert_eq!(table.lookup(0x100c), Some((src("util.h").as_str(), 3)));
+        assert_eq!(table.lookup(0x1010), Some((src("main.c").as_str(), 11)));
+        assert_eq!(table.lookup(0x1017), Some((src("main.c").as_str(), 13)));
+
+        // before the sequence & at its end
+        assert_eq!(table.lookup(0xFFF), None);
+        assert_eq!(table.lookup(0x1018), None);
+    }
+
+    #[test]
+    fn addresses() {
+        let table = table();
+
+        // a line in two places, split by the inlined code
+        assert_eq!(table.addresses("main.c", 11), Some((11, vec![0x1008, 0x1010])));
+        assert_eq!(table.addresses("util.h", 3), Some((3, vec![0x100c])));
+
+        // a line without code moves on to the next one that has some - & a debugger's full path matches the table's
+        // relative one
+        assert_eq!(table.addresses("/home/dev/game/src/main.c", 12), Some((13, vec![0x1014])));
+        assert_eq!(table.addresses("main.c", 1), Some((10, vec![0x1000])));
+
+        assert_eq!(table.addresses("main.c", 14), None);
+        assert_eq!(table.addresses("other.c", 10), None);
+    }
+}
@@ -0,0 +1,794 @@
+use std::{collections::HashMap, fs, path::Path, sync::{Arc, OnceLock}};
+
+use gimli::{AttributeValue, BaseAddresses, CfaRule, CieOrFde, DebugFrame, DebuggingInformationEntry, Dwarf, DwAte, DwAt, DwTag};
+use gimli::{Encoding, Format, LittleEndian, Operation, Unit, UnitHeader, UnitType, UnwindContext, UnwindSection};
+
+use crate::{lines::{section, Section, ADDRESS_SIZE}, symbols::{check_elf, find_section}};
+
+// local variables & parameters from the DWARF debug info (.debug_info) of an ELF built with -g, so a debugger can show
+// them while the CPU is stopped in a function. gimli does the reading, so every unit version & both DWARF formats are
+// understood. a variable has to be where a single location expression puts it - in a register, at a fixed address, or at
+// an offset from a register or the frame base - which is how unoptimized code keeps them. anything cleverer shows up as
+// unavailable
+
+// how many bytes of a struct or array are shown, & how deep a chain of types is followed before giving up on it
+const MAX_SHOWN_BYTES: usize = 16;
+const MAX_TYPE_DEPTH: usize = 16;
+
+// what a location expression is read as. only the operations that read an address or a section offset care, & those
+// are the same size in every 32-bit unit
+const EXPR_ENCODING: Encoding = Encoding { format: Format::Dwarf32, version: 4, address_size: ADDRESS_SIZE };
+
+// a variable as a debugger shows it
+#[derive(Debug, PartialEq)]
+pub struct Local {
+    pub name: String,
+    pub ty: String,
+    pub value: String,
+}
+
+struct Variable {
+    name: Option<String>,
+    ty: Option<usize>,
+    // a location expression
+    location: Option<Vec<u8>>,
+    // the code it's in scope for, where that's narrower than its function's
+    scope: Option<(u32, u32)>,
+    // the declaration an out-of-line copy of an inline function's variable gets its name & type from
+    origin: Option<usize>,
+}
+
+struct Function {
+    low: u32,
+    high: u32,
+    frame_base: Option<Vec<u8>>,
+    vars: Vec<Variable>,
+}
+
+// the parts of a type DIE that matter for showing a value
+struct TypeInfo {
+    tag: DwTag,
+    name: Option<String>,
+    size: Option<u32>,
+    encoding: DwAte,
+    ty: Option<usize>,
+}
+
+pub struct DebugInfo {
+    // sorted by low address
+    functions: Vec<Function>,
+    // keyed by offset in .debug_info, which is how DIEs refer to each other
+    types: HashMap<usize, TypeInfo>,
+    origins: HashMap<usize, (Option<String>, Option<usize>)>,
+    // the call frame info the CFA is found from, checked when it's loaded
+    debug_frame: Vec<u8>,
+}
+
+// a System's variable info, for a debugger to show locals from
+#[derive(Clone, Default)]
+pub struct Locals(Arc<OnceLock<DebugInfo>>);
+
+// where the DIE being read sits: what encloses it, from the unit down
+enum Level {
+    Function(usize),
+    Block(Option<(u32, u32)>),
+    Other,
+}
+
+// where a variable's value is
+enum Place {
+    Reg(u16),
+    Mem(u32),
+}
+
+impl DebugInfo {
+    // an ELF without debug info has no variables in it, rather than being an error
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        check_elf(data)?;
+
+        let mut info = Self { functions: Vec::new(), types: HashMap::new(), origins: HashMap::new(), debug_frame: Vec::new() };
+
+        if find_section(data, ".debug_info")?.is_none() || find_section(data, ".debug_abbrev")?.is_none() {
+            return Ok(info);
+        }
+
+        let dwarf = Dwarf::load(|id| section(data, id.name()))?;
+        let mut units = dwarf.units();
+
+        while let Some(header) = units.next().map_err(|e| format!(".debug_info: {}", e))? {
+            let offset = header.offset().as_debug_info_offset().map_or(0, |offset| offset.0);
+            info.parse_unit(&dwarf, header).map_err(|e| format!(".debug_info @ {:#x}: {}", offset, e))?;
+        }
+
+        if let Some(data) = find_section(data, ".debug_frame")? {
+            check_frames(data).map_err(|e| format!(".debug_frame: {}", e))?;
+            info.debug_frame = data.to_vec();
+        }
+
+        // out-of-line copies of inline functions take their variables' names & types from the abstract ones
+        for var in info.functions.iter_mut().flat_map(|function| function.vars.iter_mut()) {
+            if let Some((name, ty)) = var.origin.and_then(|origin| info.origins.get(&origin)) {
+                var.name = var.name.take().or(name.clone());
+                var.ty = var.ty.or(*ty);
+            }
+        }
+
+        info.functions.sort_by_key(|function| function.low);
+
+        return Ok(info);
+    }
+
+    fn parse_unit(self: &mut Self, dwarf: &Dwarf<Section>, header: UnitHeader<Section>) -> gimli::Result<()> {
+        // type & split units don't have any code in them
+        if !matches!(header.type_(), UnitType::Compilation | UnitType::Partial) {
+            return Ok(());
+        }
+
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+        let mut levels: Vec<Level> = Vec::new();
+        let mut depth = 0;
+
+        while let Some((delta, entry)) = entries.next_dfs()? {
+            depth += delta;
+            levels.truncate(depth.max(0) as usize);
+
+            let die = entry.offset().to_debug_info_offset(&unit.header).map_or(0, |offset| offset.0);
+
+            let level = match entry.tag() {
+                gimli::DW_TAG_subprogram => {
+                    // a declaration, or a function that was only ever inlined, has no code of its own
+                    match code_range(dwarf, &unit, entry)? {
+                        Some((low, high)) => {
+                            let frame_base = expr(entry, gimli::DW_AT_frame_base)?;
+
+                            self.functions.push(Function { low, high, frame_base, vars: Vec::new() });
+                            Level::Function(self.functions.len() - 1)
+                        }
+                        _ => Level::Other,
+                    }
+                }
+                gimli::DW_TAG_lexical_block => {
+                    // a block in several pieces (gimli::DW_AT_ranges) is taken as covering the whole function
+                    Level::Block(code_range(dwarf, &unit, entry)?)
+                }
+                gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter => {
+                    let name = string(dwarf, &unit, entry)?;
+                    let ty = reference(&unit, entry, gimli::DW_AT_type)?;
+
+                    self.origins.insert(die, (name.clone(), ty));
+
+                    // only the variables of a function proper - not of an inlined call in it, or a declaration
+                    let function = levels.iter().rev().find(|level| !matches!(level, Level::Block(_)));
+                    if let Some(Level::Function(idx)) = function {
+                        let scope = levels.iter().rev().find_map(|level| match level {
+                            Level::Block(range) => *range,
+                            _ => None,
+                        });
+
+                        let location = expr(entry, gimli::DW_AT_location)?;
+                        let origin = reference(&unit, entry, gimli::DW_AT_abstract_origin)?;
+
+                        self.functions[*idx].vars.push(Variable { name, ty, location, scope, origin });
+                    }
+
+                    Level::Other
+                }
+                gimli::DW_TAG_base_type | gimli::DW_TAG_pointer_type | gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type |
+                gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type | gimli::DW_TAG_enumeration_type | gimli::DW_TAG_array_type => {
+                    self.types.insert(die, TypeInfo {
+                        tag: entry.tag(),
+                        name: string(dwarf, &unit, entry)?,
+                        size: entry.attr_value(gimli::DW_AT_byte_size)?.and_then(|size| size.udata_value()).map(|size| size as u32),
+                        encoding: match entry.attr_value(gimli::DW_AT_encoding)? {
+                            Some(AttributeValue::Encoding(encoding)) => encoding,
+                            _ => DwAte(0),
+                        },
+                        ty: reference(&unit, entry, gimli::DW_AT_type)?,
+                    });
+
+                    Level::Other
+                }
+                // the unit, inlined calls, & everything else
+                _ => Level::Other,
+            };
+
+            levels.push(level);
+        }
+
+        return Ok(());
+    }
+
+    // functions that have any variables
+    pub fn len(self: &Self) -> usize {
+        return self.functions.iter().filter(|function| !function.vars.is_empty()).count();
+    }
+
+    // whether pc is in a function the debug info knows, & so has variables to show
+    pub fn covers(self: &Self, pc: u32) -> bool {
+        return self.function(pc).is_some();
+    }
+
+    // the variables in scope at pc, with the values they have in the registers & memory given. None if pc isn't in a
+    // function the debug info knows
+    pub fn locals(self: &Self, pc: u32, regs: &[u32;16], read: impl Fn(u32, usize) -> Option<Vec<u8>>) -> Option<Vec<Local>> {
+        let function = self.function(pc)?;
+        let frame_base = function.frame_base.as_ref().and_then(|expr| self.frame_base(expr, pc, regs));
+
+        let mut locals = Vec::new();
+
+        for var in &function.vars {
+            if var.scope.is_some_and(|(low, high)| pc < low || pc >= high) {
+                continue;
+            }
+
+            let Some(name) = &var.name else {
+                continue;
+            };
+
+            let place = var.location.as_ref().and_then(|expr| place(expr, regs, frame_base));
+            let (size, ty) = match var.ty {
+                Some(ty) => (self.size_of(ty), self.type_name(ty, 0)),
+                None => (None, "?".to_string()),
+            };
+
+            let value = match place {
+                Some(place) => {
+                    let len = size.unwrap_or(4).min(MAX_SHOWN_BYTES as u32) as usize;
+                    let bytes = match place {
+                        Place::Reg(reg) => regs.get(reg as usize).map(|val| val.to_le_bytes()[..len.min(4)].to_vec()),
+                        Place::Mem(addr) => read(addr, len),
+                    };
+
+                    match bytes {
+                        Some(bytes) => self.format(var.ty, size, &bytes),
+                        None => "<unreadable>".to_string(),
+                    }
+                }
+                None => "<unavailable>".to_string(),
+            };
+
+            locals.push(Local { name: name.clone(), ty, value });
+        }
+
+        return Some(locals);
+    }
+
+    fn function(self: &Self, pc: u32) -> Option<&Function> {
+        let idx = self.functions.partition_point(|function| function.low <= pc).checked_sub(1)?;
+
+        // nested functions aren't a thing in C, but an empty one can share its address with the next
+        return self.functions[..=idx].iter().rev().find(|function| pc >= function.low && pc < function.high);
+    }
+
+    fn frame_base(self: &Self, expr: &[u8], pc: u32, regs: &[u32;16]) -> Option<u32> {
+        if matches!(operation(expr)?, Operation::CallFrameCFA) {
+            return self.cfa(pc, regs);
+        }
+
+        return match place(expr, regs, None)? {
+            // the register holds the frame base itself
+            Place::Reg(reg) => regs.get(reg as usize).copied(),
+            Place::Mem(addr) => Some(addr),
+        };
+    }
+
+    // the canonical frame address at pc - the stack pointer as it was at the call
+    fn cfa(self: &Self, pc: u32, regs: &[u32;16]) -> Option<u32> {
+        let debug_frame = debug_frame(&self.debug_frame);
+        let mut ctx = UnwindContext::new();
+        let row = debug_frame.unwind_info_for_address(&BaseAddresses::default(), &mut ctx, pc as u64, DebugFrame::cie_from_offset).ok()?;
+
+        return match row.cfa() {
+            CfaRule::RegisterAndOffset { register, offset } => Some((*regs.get(register.0 as usize)? as i64 + offset) as u32),
+            // a CFA worked out by an expression isn't followed
+            CfaRule::Expression(_) => None,
+        };
+    }
+
+    // typedefs & qualifiers don't change what a value looks like, so they're looked through
+    fn resolve(self: &Self, mut ty: usize) -> Option<&TypeInfo> {
+        for _ in 0..MAX_TYPE_DEPTH {
+            let info = self.types.get(&ty)?;
+
+            match info.tag {
+                gimli::DW_TAG_typedef | gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type => {
+                    ty = info.ty?;
+                }
+                _ => {
+                    return Some(info);
+                }
+            }
+        }
+
+        return None;
+    }
+
+    fn size_of(self: &Self, ty: usize) -> Option<u32> {
+        let info = self.resolve(ty)?;
+        return if info.tag == gimli::DW_TAG_pointer_type { Some(info.size.unwrap_or(4)) } else { info.size };
+    }
+
+    fn type_name(self: &Self, ty: usize, depth: usize) -> String {
+        let Some(info) = self.types.get(&ty).filter(|_| depth < MAX_TYPE_DEPTH) else {
+            return "?".to_string();
+        };
+
+        let inner = |what: &str| info.ty.map_or(what.to_string(), |ty| self.type_name(ty, depth + 1));
+        let name = info.name.clone().unwrap_or_default();
+
+        return match info.tag {
+            gimli::DW_TAG_pointer_type => format!("{} *", inner("void")),
+            gimli::DW_TAG_const_type => format!("const {}", inner("void")),
+            gimli::DW_TAG_volatile_type => format!("volatile {}", inner("void")),
+            gimli::DW_TAG_restrict_type => format!("{} restrict", inner("void")),
+            gimli::DW_TAG_array_type => format!("{}[]", inner("?")),
+            gimli::DW_TAG_structure_type => format!("struct {}", name).trim_end().to_string(),
+            gimli::DW_TAG_union_type => format!("union {}", name).trim_end().to_string(),
+            gimli::DW_TAG_enumeration_type => format!("enum {}", name).trim_end().to_string(),
+            _ if !name.is_empty() => name,
+            _ => "?".to_string(),
+        };
+    }
+
+    fn format(self: &Self, ty: Option<usize>, size: Option<u32>, bytes: &[u8]) -> String {
+        let info = ty.and_then(|ty| self.resolve(ty));
+        let (tag, encoding) = info.map_or((gimli::DW_TAG_base_type, DwAte(0)), |info| (info.tag, info.encoding));
+
+        let mut raw = [0;8];
+        let len = bytes.len().min(8);
+        raw[..len].copy_from_slice(&bytes[..len]);
+        let unsigned = u64::from_le_bytes(raw);
+        let signed = if len > 0 && len < 8 { ((unsigned << (64 - len * 8)) as i64) >> (64 - len * 8) } else { unsigned as i64 };
+
+        // anything bigger than a scalar is shown as its bytes
+        if size.is_some_and(|size| size as usize > bytes.len().min(8)) || !matches!(tag, gimli::DW_TAG_base_type | gimli::DW_TAG_pointer_type | gimli::DW_TAG_enumeration_type) {
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            let more = if size.is_some_and(|size| size as usize > bytes.len()) { " ..." } else { "" };
+            return format!("{{{}{}}}", hex.join(" "), more);
+        }
+
+        return match (tag, encoding) {
+            (gimli::DW_TAG_pointer_type, _) => format!("0x{:08x}", unsigned),
+            (_, gimli::DW_ATE_boolean) => (unsigned != 0).to_string(),
+            (_, gimli::DW_ATE_float) if len == 4 => f32::from_bits(unsigned as u32).to_string(),
+            (_, gimli::DW_ATE_float) if len == 8 => f64::from_bits(unsigned).to_string(),
+            (_, gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char) if (0x20..0x7F).contains(&unsigned) => {
+                format!("{} '{}'", if encoding == gimli::DW_ATE_signed_char { signed.to_string() } else { unsigned.to_string() }, unsigned as u8 as char)
+            }
+            (_, gimli::DW_ATE_signed | gimli::DW_ATE_signed_char) => signed.to_string(),
+            (gimli::DW_TAG_enumeration_type, _) => signed.to_string(),
+            _ => unsigned.to_string(),
+        };
+    }
+}
+
+type Entry<'abbrev, 'unit, 'a> = DebuggingInformationEntry<'abbrev, 'unit, Section<'a>>;
+
+fn string(dwarf: &Dwarf<Section>, unit: &Unit<Section>, entry: &Entry) -> gimli::Result<Option<String>> {
+    return match entry.attr_value(gimli::DW_AT_name)? {
+        Some(attr) => Ok(Some(dwarf.attr_string(unit, attr)?.to_string_lossy().into_owned())),
+        None => Ok(None),
+    };
+}
+
+// the DIE an attribute refers to, by its offset in .debug_info
+fn reference(unit: &Unit<Section>, entry: &Entry, attr: DwAt) -> gimli::Result<Option<usize>> {
+    return Ok(match entry.attr_value(attr)? {
+        Some(AttributeValue::UnitRef(offset)) => offset.to_debug_info_offset(&unit.header).map(|offset| offset.0),
+        Some(AttributeValue::DebugInfoRef(offset)) => Some(offset.0),
+        _ => None,
+    });
+}
+
+// a location expression - a location list is more than a single expression can say, so it's left out
+fn expr(entry: &Entry, attr: DwAt) -> gimli::Result<Option<Vec<u8>>> {
+    return Ok(entry.attr_value(attr)?.and_then(|attr| attr.exprloc_value()).map(|expr| expr.0.to_vec()));
+}
+
+// low_pc to high_pc - which, since version 4, can be a length instead of an address
+fn code_range(dwarf: &Dwarf<Section>, unit: &Unit<Section>, entry: &Entry) -> gimli::Result<Option<(u32, u32)>> {
+    let (Some(low), Some(high)) = (entry.attr_value(gimli::DW_AT_low_pc)?, entry.attr_value(gimli::DW_AT_high_pc)?) else {
+        return Ok(None);
+    };
+
+    let Some(low) = dwarf.attr_address(unit, low)? else {
+        return Ok(None);
+    };
+
+    let high = match high.udata_value() {
+        Some(len) => Some(low.wrapping_add(len)),
+        None => dwarf.attr_address(unit, high)?,
+    };
+
+    return Ok(high.map(|high| (low as u32, high as u32)));
+}
+
+// the one operation in an expression, or None if there's more than that
+fn operation(expr: &[u8]) -> Option<Operation<Section<'_>>> {
+    let mut r = Section::new(expr, LittleEndian);
+    let op = Operation::parse(&mut r, EXPR_ENCODING).ok()?;
+    return if r.is_empty() { Some(op) } else { None };
+}
+
+// where a location expression of a single operation puts a value
+fn place(expr: &[u8], regs: &[u32;16], frame_base: Option<u32>) -> Option<Place> {
+    return match operation(expr)? {
+        Operation::Address { address } => Some(Place::Mem(address as u32)),
+        Operation::Register { register } => Some(Place::Reg(register.0)),
+        Operation::RegisterOffset { register, offset, .. } => Some(Place::Mem((*regs.get(register.0 as usize)? as i64 + offset) as u32)),
+        Operation::FrameOffset { offset } => Some(Place::Mem((frame_base? as i64 + offset) as u32)),
+        _ => None,
+    };
+}
+
+fn debug_frame(data: &[u8]) -> DebugFrame<Section<'_>> {
+    let mut debug_frame = DebugFrame::new(data, LittleEndian);
+    debug_frame.set_address_size(ADDRESS_SIZE);
+    return debug_frame;
+}
+
+// every FDE has to parse, & find its CIE, for the CFA to be worked out from them later
+fn check_frames(data: &[u8]) -> gimli::Result<()> {
+    let debug_frame = debug_frame(data);
+    let bases = BaseAddresses::default();
+    let mut entries = debug_frame.entries(&bases);
+
+    while let Some(entry) = entries.next()? {
+        if let CieOrFde::Fde(fde) = entry {
+            fde.parse(DebugFrame::cie_from_offset)?;
+        }
+    }
+
+    return Ok(());
+}
+
+impl Locals {
+    // load the variables a debugger shows from here on. returns how many functions have any
+    pub fn load(self: &Self, path: &Path) -> Result<usize, String> {
+        let data = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let info = DebugInfo::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let len = info.len();
+
+        self.0.set(info).map_err(|_| "debug info is already loaded".to_string())?;
+        return Ok(len);
+    }
+
+    pub fn covers(self: &Self, pc: u32) -> bool {
+        return self.0.get().is_some_and(|info| info.covers(pc));
+    }
+
+    pub fn locals(self: &Self, pc: u32, regs: &[u32;16], read: impl Fn(u32, usize) -> Option<Vec<u8>>) -> Option<Vec<Local>> {
+        return self.0.get()?.locals(pc, regs, read);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gimli::{DwForm, DW_AT_addr_base, DW_AT_str_offsets_base, DW_CFA_advance_loc, DW_CFA_def_cfa, DW_CFA_def_cfa_offset, DW_OP_call_frame_cfa};
+    use gimli::{DW_FORM_addr, DW_FORM_addrx, DW_FORM_block1, DW_FORM_data1, DW_FORM_data4, DW_FORM_exprloc, DW_FORM_implicit_const};
+    use gimli::{DW_FORM_ref4, DW_FORM_sec_offset, DW_FORM_string, DW_FORM_strx1, DW_FORM_udata, DW_OP_fbreg, DW_OP_reg4};
+    use gimli::{DW_AT_byte_size, DW_AT_encoding, DW_AT_frame_base, DW_AT_high_pc, DW_AT_location, DW_AT_low_pc, DW_AT_name, DW_AT_type};
+    use gimli::{DW_ATE_signed, DW_ATE_signed_char, DW_ATE_unsigned_char, DW_TAG_base_type, DW_TAG_compile_unit, DW_TAG_formal_parameter};
+    use gimli::{DW_TAG_lexical_block, DW_TAG_pointer_type, DW_TAG_subprogram, DW_TAG_typedef, DW_TAG_variable};
+
+    use super::*;
+    use crate::lines::tests::elf;
+
+    // a unit being built, & the string & address sections a version 5 one indexes into
+    struct Fixture {
+        version: u16,
+        dies: Vec<u8>,
+        strs: Vec<u8>,
+        str_offsets: Vec<u8>,
+        addrs: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn header_len(self: &Self) -> usize {
+            return if self.version >= 5 { 12 } else { 11 };
+        }
+
+        // the offset of the next DIE, for a ref4 to it
+        fn at(self: &Self) -> [u8;4] {
+            return ((self.header_len() + self.dies.len()) as u32).to_le_bytes();
+        }
+
+        fn name(self: &mut Self, name: &str) {
+            if self.version >= 5 {
+                self.dies.push((self.str_offsets.len() / 4) as u8);
+                self.str_offsets.extend((self.strs.len() as u32).to_le_bytes());
+                self.strs.extend(name.as_bytes());
+                self.strs.push(0);
+            }
+            else {
+                self.dies.extend(name.as_bytes());
+                self.dies.push(0);
+            }
+        }
+
+        fn code(self: &mut Self, low: u32, len: u32) {
+            if self.version >= 5 {
+                self.dies.push((self.addrs.len() / 4) as u8);
+                self.addrs.extend(low.to_le_bytes());
+                self.dies.push(len as u8);
+            }
+            else {
+                self.dies.extend(low.to_le_bytes());
+                self.dies.extend(if self.version >= 4 { len } else { low + len }.to_le_bytes());
+            }
+        }
+
+        // a block1 & an exprloc of a short expression look the same
+        fn expr(self: &mut Self, ops: &[u8]) {
+            self.dies.push(ops.len() as u8);
+            self.dies.extend(ops);
+        }
+    }
+
+    // the forms each version's compilers use: strings in .debug_str_offsets & addresses in .debug_addr from version 5,
+    // high_pc a length from version 4, & expressions in blocks before version 4. version 5's pointer size is an
+    // implicit_const, which lives in the abbreviation rather than the DIE
+    fn abbrevs(version: u16) -> Vec<u8> {
+        let (name, addr, len, expr) = match version {
+            2 | 3 => (DW_FORM_string, DW_FORM_addr, DW_FORM_addr, DW_FORM_block1),
+            4 => (DW_FORM_string, DW_FORM_addr, DW_FORM_data4, DW_FORM_exprloc),
+            _ => (DW_FORM_strx1, DW_FORM_addrx, DW_FORM_udata, DW_FORM_exprloc),
+        };
+
+        let unit: &[(DwAt, DwForm)] = if version >= 5 {
+            &[(DW_AT_name, name), (DW_AT_str_offsets_base, DW_FORM_sec_offset), (DW_AT_addr_base, DW_FORM_sec_offset)]
+        }
+        else {
+            &[(DW_AT_name, name)]
+        };
+
+        let ptr_size = if version >= 5 { DW_FORM_implicit_const } else { DW_FORM_data1 };
+        let var = &[(DW_AT_name, name), (DW_AT_type, DW_FORM_ref4), (DW_AT_location, expr)][..];
+        let mut data = Vec::new();
+
+        for (code, tag, children, attrs) in [
+            (1, DW_TAG_compile_unit, true, unit),
+            (2, DW_TAG_base_type, false, &[(DW_AT_name, name), (DW_AT_byte_size, DW_FORM_data1), (DW_AT_encoding, DW_FORM_data1)][..]),
+            (3, DW_TAG_typedef, false, &[(DW_AT_name, name), (DW_AT_type, DW_FORM_ref4)]),
+            (4, DW_TAG_pointer_type, false, &[(DW_AT_byte_size, ptr_size), (DW_AT_type, DW_FORM_ref4)]),
+            (5, DW_TAG_subprogram, true, &[(DW_AT_name, name), (DW_AT_low_pc, addr), (DW_AT_high_pc, len), (DW_AT_frame_base, expr)]),
+            (6, DW_TAG_formal_parameter, false, var),
+            (7, DW_TAG_variable, false, var),
+            (8, DW_TAG_variable, false, &var[..2]),
+            (9, DW_TAG_lexical_block, true, &[(DW_AT_low_pc, addr), (DW_AT_high_pc, len)]),
+        ] {
+            data.extend([code, tag.0 as u8, children as u8]);
+            for (attr, form) in attrs {
+                data.extend([attr.0 as u8, form.0 as u8]);
+
+                // the only implicit_const is the pointer size
+                if *form == DW_FORM_implicit_const {
+                    data.push(4);
+                }
+            }
+            data.extend([0, 0]);
+        }
+
+        data.push(0);
+        return data;
+    }
+
+    // a unit with one function, 0x1000 to 0x1018:
+    //
+    //   void main(int count) { char *p; int gone; { uint8_t i; } }
+    //
+    // with p in r4, gone optimized away, & i only in scope from 0x1008 to 0x1010. returns .debug_info, & for version 5
+    // the .debug_str, .debug_str_offsets & .debug_addr it indexes into
+    fn debug_info(version: u16) -> Vec<(&'static str, Vec<u8>)> {
+        let mut unit = Fixture { version, dies: Vec::new(), strs: Vec::new(), str_offsets: Vec::new(), addrs: Vec::new() };
+
+        unit.dies.push(1);
+        unit.name("main.c");
+        if version >= 5 {
+            // the bases are past each section's 8 byte header
+            unit.dies.extend(8u32.to_le_bytes());
+            unit.dies.extend(8u32.to_le_bytes());
+        }
+
+        let int = unit.at();
+        unit.dies.push(2);
+        unit.name("int");
+        unit.dies.extend([4, DW_ATE_signed.0]);
+
+        let uchar = unit.at();
+        unit.dies.push(2);
+        unit.name("unsigned char");
+        unit.dies.extend([1, DW_ATE_unsigned_char.0]);
+
+        let uint8 = unit.at();
+        unit.dies.push(3);
+        unit.name("uint8_t");
+        unit.dies.extend(uchar);
+
+        let char = unit.at();
+        unit.dies.push(2);
+        unit.name("char");
+        unit.dies.extend([1, DW_ATE_signed_char.0]);
+
+        let char_ptr = unit.at();
+        unit.dies.push(4);
+        if version < 5 {
+            unit.dies.push(4);
+        }
+        unit.dies.extend(char);
+
+        unit.dies.push(5);
+        unit.name("main");
+        unit.code(0x1000, 0x18);
+        unit.expr(&[DW_OP_call_frame_cfa.0]);
+
+        unit.dies.push(6);
+        unit.name("count");
+        unit.dies.extend(int);
+        unit.expr(&[DW_OP_fbreg.0, 0x6c]);                 // cfa - 20
+
+        unit.dies.push(7);
+        unit.name("p");
+        unit.dies.extend(char_ptr);
+        unit.expr(&[DW_OP_reg4.0]);
+
+        unit.dies.push(8);
+        unit.name("gone");
+        unit.dies.extend(int);
+
+        unit.dies.push(9);
+        unit.code(0x1008, 8);
+
+        unit.dies.push(7);
+        unit.name("i");
+        unit.dies.extend(uint8);
+        unit.expr(&[DW_OP_fbreg.0, 0x6b]);                 // cfa - 21
+
+        // the end of the block, the function & the unit
+        unit.dies.extend([0, 0, 0]);
+
+        let mut header = version.to_le_bytes().to_vec();
+        if version >= 5 {
+            // a full compilation unit, then the address size before the abbreviations' offset
+            header.extend([gimli::DW_UT_compile.0, 4]);
+            header.extend(0u32.to_le_bytes());
+        }
+        else {
+            header.extend(0u32.to_le_bytes());
+            header.push(4);
+        }
+
+        let mut sections = vec![(".debug_info", entry([header, unit.dies].concat()))];
+
+        if version >= 5 {
+            // both index sections start with a header of their own: a version, & padding or the address size
+            sections.push((".debug_str", unit.strs));
+            sections.push((".debug_str_offsets", entry([&[5, 0, 0, 0][..], &unit.str_offsets].concat())));
+            sections.push((".debug_addr", entry([&[5, 0, 4, 0][..], &unit.addrs].concat())));
+        }
+
+        return sections;
+    }
+
+    // a unit or call frame entry: its contents after their length
+    fn entry(contents: Vec<u8>) -> Vec<u8> {
+        let mut data = (contents.len() as u32).to_le_bytes().to_vec();
+        data.extend(contents);
+        return data;
+    }
+
+    // the CFA is sp on entry, sp + 8 once the prologue has pushed two registers, & r11 + 4 once it's set up r11
+    fn debug_frame() -> Vec<u8> {
+        let cie = [
+            &0xFFFF_FFFFu32.to_le_bytes()[..],
+            &[3, 0, 2, 0x7c, 14],                                                   // version, augmentation, alignments, lr
+            &[DW_CFA_def_cfa.0, 13, 0],
+        ].concat();
+
+        let fde = [
+            &0u32.to_le_bytes()[..],
+            &0x1000u32.to_le_bytes(),
+            &0x18u32.to_le_bytes(),
+            &[DW_CFA_advance_loc.0 | 2, DW_CFA_def_cfa_offset.0, 8],                // from 0x1004
+            &[DW_CFA_advance_loc.0 | 2, DW_CFA_def_cfa.0, 11, 4],                   // from 0x1008
+        ].concat();
+
+        return [entry(cie), entry(fde)].concat();
+    }
+
+    fn sections(version: u16) -> Vec<(&'static str, Vec<u8>)> {
+        let mut sections = debug_info(version);
+        sections.push((".debug_abbrev", abbrevs(version)));
+        sections.push((".debug_frame", debug_frame()));
+        return sections;
+    }
+
+    fn parse_sections(sections: &[(&'static str, Vec<u8>)]) -> Result<DebugInfo, String> {
+        let sections: Vec<(&str, &[u8])> = sections.iter().map(|(name, data)| (*name, &data[..])).collect();
+        return DebugInfo::parse(&elf(&sections));
+    }
+
+    // the stack at 0x2000_0000: count at 0x2000_0fe8 & i at 0x2000_0fe7, from r11's CFA of 0x2000_0ffc - & a different
+    // count at 0x2000_0fd4, where sp's CFA of 0x2000_0fe8 has it before r11 is set up
+    fn locals_at(info: &DebugInfo, pc: u32) -> Option<Vec<Local>> {
+        let mut stack = vec![0u8;0x1000];
+        stack[0xfe8..0xfec].copy_from_slice(&(-3i32).to_le_bytes());
+        stack[0xfe7] = b'A';
+        stack[0xfd4..0xfd8].copy_from_slice(&7i32.to_le_bytes());
+
+        let mut regs = [0;16];
+        regs[4] = 0x1234;
+        regs[11] = 0x2000_0ff8;
+        regs[13] = 0x2000_0fe0;
+
+        let read = |addr: u32, len: usize| stack.get(addr.checked_sub(0x2000_0000)? as usize..).and_then(|rest| rest.get(..len)).map(<[u8]>::to_vec);
+        return info.locals(pc, &regs, read);
+    }
+
+    fn local(name: &str, ty: &str, value: &str) -> Local {
+        return Local { name: name.to_string(), ty: ty.to_string(), value: value.to_string() };
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_sections(&sections(4)).unwrap().len(), 1);
+
+        // no debug info is no variables, but not an ELF at all is an error
+        assert_eq!(DebugInfo::parse(&elf(&[])).unwrap().len(), 0);
+        assert!(DebugInfo::parse(b"not an elf").is_err());
+    }
+
+    #[test]
+    fn locals() {
+        let info = parse_sections(&sections(4)).unwrap();
+
+        assert_eq!(locals_at(&info, 0x100a), Some(vec![
+            local("count", "int", "-3"),
+            local("p", "char *", "0x00001234"),
+            local("gone", "int", "<unavailable>"),
+            local("i", "uint8_t", "65 'A'"),
+        ]));
+
+        // past i's block, & before the frame moves to r11 - when count is found from sp
+        assert_eq!(locals_at(&info, 0x1010).unwrap().len(), 3);
+        assert_eq!(locals_at(&info, 0x1004).unwrap()[0], local("count", "int", "7"));
+
+        assert_eq!(locals_at(&info, 0xFFF), None);
+        assert_eq!(locals_at(&info, 0x1018), None);
+    }
+
+    #[test]
+    fn versions() {
+        // the same unit, in each version's forms, has the same variables
+        let expected = locals_at(&parse_sections(&sections(4)).unwrap(), 0x100a);
+
+        for version in [2, 3, 5] {
+            let info = parse_sections(&sections(version)).unwrap();
+            assert_eq!(locals_at(&info, 0x100a), expected, "version {}", version);
+            assert_eq!(locals_at(&info, 0x1004).unwrap()[0], local("count", "int", "7"), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        // any section cut short - a unit, its abbreviations, an index section, or the call frame info - is an error, not
+        // a panic or some of the variables
+        for version in [4, 5] {
+            let sections = sections(version);
+
+            for (idx, (name, data)) in sections.iter().enumerate() {
+                for len in 1..data.len() {
+                    // the call frame info is still whole cut between its CIE & FDE, & the strings just lose ones nothing uses
+                    if (*name == ".debug_frame" && len == 16) || *name == ".debug_str" {
+                        continue;
+                    }
+
+                    let mut cut = sections.clone();
+                    cut[idx].1.truncate(len);
+                    assert!(parse_sections(&cut).is_err(), "version {} {} cut to {} bytes", version, name, len);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fmt, io::Write, sync::atomic::{AtomicU8, Ordering}};
+
+// diagnostics from the machine, each from a subsystem with a level of its own so one can be turned up (or down) without
+// drowning in the rest. levels can be changed at any time, from any thread - a message that's filtered out costs a load &
+// a compare, & its arguments aren't even formatted
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Subsystem {
+    Cpu,
+    Mem,
+    Vdp,
+    Uart,
+    Clock,
+    Hw,
+}
+
+pub const SUBSYSTEMS: [Subsystem;6] = [Subsystem::Cpu, Subsystem::Mem, Subsystem::Vdp, Subsystem::Uart, Subsystem::Clock, Subsystem::Hw];
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+pub const LEVELS: [Level;6] = [Level::Off, Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace];
+
+// what everything logs at until told otherwise - errors, warnings, & the odd note, but nothing per-event
+pub const DEFAULT_LEVEL: Level = Level::Info;
+
+static SUBSYSTEM_LEVELS: [AtomicU8;SUBSYSTEMS.len()] = [const { AtomicU8::new(DEFAULT_LEVEL as u8) };SUBSYSTEMS.len()];
+
+impl Subsystem {
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            Subsystem::Cpu => "cpu",
+            Subsystem::Mem => "mem",
+            Subsystem::Vdp => "vdp",
+            Subsystem::Uart => "uart",
+            Subsystem::Clock => "clock",
+            Subsystem::Hw => "hw",
+        };
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        return SUBSYSTEMS.iter().copied().find(|sub| sub.name() == s)
+            .ok_or_else(|| format!("unknown log subsystem '{}' (expected one of {})", s, SUBSYSTEMS.map(|sub| sub.name()).join(", ")));
+    }
+}
+
+impl Level {
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        return LEVELS.iter().copied().find(|level| level.name() == s)
+            .ok_or_else(|| format!("unknown log level '{}' (expected one of {})", s, LEVELS.map(|level| level.name()).join(", ")));
+    }
+}
+
+pub fn level(sub: Subsystem) -> Level {
+    return LEVELS[SUBSYSTEM_LEVELS[sub as usize].load(Ordering::Relaxed) as usize];
+}
+
+pub fn set_level(sub: Subsystem, level: Level) {
+    SUBSYSTEM_LEVELS[sub as usize].store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(sub: Subsystem, level: Level) -> bool {
+    return level != Level::Off && SUBSYSTEM_LEVELS[sub as usize].load(Ordering::Relaxed) >= level as u8;
+}
+
+// a level for one subsystem (`vdp=debug`) or for all of them (`warn`)
+pub fn parse_level(s: &str) -> Result<(Option<Subsystem>, Level), String> {
+    return match s.trim().split_once('=') {
+        Some((sub, level)) => Ok((Some(Subsystem::parse(sub.trim())?), Level::parse(level.trim())?)),
+        None => Ok((None, Level::parse(s.trim())?)),
+    };
+}
+
+// a comma separated list of levels, applied in order - so "warn,vdp=trace" quiets everything but the VDP
+pub fn parse_levels(spec: &str) -> Result<Vec<(Option<Subsystem>, Level)>, String> {
+    return spec.split(',').filter(|part| !part.trim().is_empty()).map(parse_level).collect();
+}
+
+pub fn apply_levels(levels: &[(Option<Subsystem>, Level)]) {
+    for (sub, level) in levels {
+        match sub {
+            Some(sub) => set_level(*sub, *level),
+            None => SUBSYSTEMS.iter().for_each(|sub| set_level(*sub, *level)),
+        }
+    }
+}
+
+// every subsystem's current level, as a spec parse_levels would take
+pub fn describe_levels() -> String {
+    return SUBSYSTEMS.map(|sub| format!("{}={}", sub.name(), level(sub).name())).join(",");
+}
+
+// use the log! macro rather than calling this, so filtered out messages aren't formatted
+pub fn write(sub: Subsystem, level: Level, args: fmt::Arguments) {
+    let mut out = std::io::stdout().lock();
+
+    let _ = match level {
+        Level::Info => writeln!(out, "{}: {}", sub.name(), args),
+        _ => writeln!(out, "{} {}: {}", sub.name(), level.name(), args),
+    };
+}
+
+// log!(Vdp, Warn, "command queue at {:#X} ...", addr)
+#[macro_export]
+macro_rules! log {
+    ($sub:ident, $level:ident, $($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Subsystem::$sub, $crate::log::Level::$level) {
+            $crate::log::write($crate::log::Subsystem::$sub, $crate::log::Level::$level, format_args!($($arg)+));
+        }
+    };
+}
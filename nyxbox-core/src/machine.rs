@@ -0,0 +1,1513 @@
+use std::{collections::HashMap, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, mpsc::Receiver, Arc, Mutex}, thread::{self, JoinHandle}, time::Duration};
+
+use rsevents::{AutoResetEvent, Awaitable, EventState, ManualResetEvent};
+use serde_json::json;
+use unicorn_engine::{ffi::uc_handle, ArmCpuModel, uc_error, HookType, Mode, Permission, RegisterARM, UcHookId, Unicorn};
+
+use crate::{bios::{Bios, BIOS_MEMCPY, BIOS_SLEEPFRAMES, BIOS_WAITVBLANK}, breakpoint::{BreakSpec, Breakpoint, DebugStop, DebugTraps, WatchKind}, buserr::{is_unmapped, BusError, UnmappedPolicy}, chrometrace::{ChromeTrace, TRACK_CPU, TRACK_INTERRUPTS}, excstats::{ExceptionStats, EXCP_FIQ, EXCP_IRQ, EXCP_SWI}, clock::Clock, coproc::{Coprocessor, CoprocessorRunContext}, crashdump::History, elf::{Executable, Segment}, fault::{enter_exception, register_dump, FaultMode, GuestException}, framebudget::FrameBudget, hwmodel::{HwLimits, Limit}, intc::{line_names, InterruptController, FIQ_VECTOR, IRQ_VECTOR}, mem::{MemoryMap, BOOT_ROM_BEGIN}, mailbox::{Mailbox, Side}, mpu::Mpu, peripheral::Peripheral, poison::PoisonMap, symbols::Symbols, timebase::{frame_to_ns, Timebase}, trace::{MemAccess, Tracer}, vdpport::VdpPort};
+
+// registers visible to host-side debugging tools, in display order
+pub const DEBUG_REGS: [(&str, RegisterARM); 17] = [
+    ("r0", RegisterARM::R0), ("r1", RegisterARM::R1), ("r2", RegisterARM::R2), ("r3", RegisterARM::R3),
+    ("r4", RegisterARM::R4), ("r5", RegisterARM::R5), ("r6", RegisterARM::R6), ("r7", RegisterARM::R7),
+    ("r8", RegisterARM::R8), ("r9", RegisterARM::R9), ("r10", RegisterARM::R10), ("r11", RegisterARM::R11),
+    ("r12", RegisterARM::R12), ("sp", RegisterARM::SP), ("lr", RegisterARM::LR), ("pc", RegisterARM::PC),
+    ("cpsr", RegisterARM::CPSR),
+];
+
+// SVC mode, IRQ + FIQ disabled, ARM state
+pub const CPSR_RESET: u64 = 0x1D3;
+
+// modes with an SP & LR of their own (system shares user's, & neither has an SPSR), in save state order: system, SVC,
+// abort, undefined, IRQ, FIQ
+const BANKED_MODES: [u64;6] = [CPSR_MODE_SYS, 0x13, 0x17, 0x1B, CPSR_MODE_IRQ, CPSR_MODE_FIQ];
+const HIGH_REGS: [RegisterARM;5] = [RegisterARM::R8, RegisterARM::R9, RegisterARM::R10, RegisterARM::R11, RegisterARM::R12];
+
+// SP, LR & SPSR for each banked mode, then FIQ's r8-r12 & system's
+pub const BANKED_REG_COUNT: usize = BANKED_MODES.len() * 3 + HIGH_REGS.len() * 2;
+
+pub const CPSR_MODE_MASK: u64   = 0x1F;
+pub const CPSR_MODE_FIQ: u64    = 0x11;
+pub const CPSR_MODE_IRQ: u64    = 0x12;
+pub const CPSR_MODE_SYS: u64    = 0x1F;
+pub const CPSR_T: u64           = 0x20;
+pub const CPSR_F: u64           = 0x40;
+pub const CPSR_I: u64           = 0x80;
+
+// instructions the CPU gets per frame unless told otherwise - about what an ARM11 at 600MHz retires, counting one per cycle
+pub const DEFAULT_CPU_BUDGET: u64 = 10_000_000;
+
+const WFI_ARM: u32      = 0x0320F003;
+const WFI_THUMB: u16    = 0xBF30;
+
+// how long pause() waits for the CPU thread to park. it only has to get out of emu_start, so this is only ever hit when
+// something on the CPU thread is stuck
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// the cores unicorn can run that share the ARM1176's exception model (so no M-profile parts). the console is an ARM1176 -
+// the others are for checking code against older or newer cores, & aren't otherwise modelled
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CpuModel {
+    /// ARMv6KZ, as in the console
+    #[default]
+    Arm1176,
+    /// ARMv6 without the security extensions
+    Arm1136,
+    /// ARMv6K
+    Arm11mpcore,
+    /// ARMv5TEJ
+    Arm926,
+    /// ARMv5TE
+    Arm946,
+    /// ARMv7-A
+    CortexA8,
+    /// ARMv7-A, multiprocessor
+    CortexA9,
+    /// ARMv7-A with virtualization & LPAE
+    CortexA15,
+}
+
+impl CpuModel {
+    pub fn unicorn_model(self: &Self) -> (Mode, ArmCpuModel) {
+        return match self {
+            CpuModel::Arm1176 => (Mode::ARM1176, ArmCpuModel::UC_CPU_ARM_1176),
+            CpuModel::Arm1136 => (Mode::ARM, ArmCpuModel::UC_CPU_ARM_1136),
+            CpuModel::Arm11mpcore => (Mode::ARM, ArmCpuModel::UC_CPU_ARM_11MPCORE),
+            CpuModel::Arm926 => (Mode::ARM926, ArmCpuModel::UC_CPU_ARM_926),
+            CpuModel::Arm946 => (Mode::ARM946, ArmCpuModel::UC_CPU_ARM_946),
+            CpuModel::CortexA8 => (Mode::ARM, ArmCpuModel::UC_CPU_ARM_CORTEX_A8),
+            CpuModel::CortexA9 => (Mode::ARM, ArmCpuModel::UC_CPU_ARM_CORTEX_A9),
+            CpuModel::CortexA15 => (Mode::ARM, ArmCpuModel::UC_CPU_ARM_CORTEX_A15),
+        };
+    }
+
+    // the name it has on the command line - stable across builds, unlike the discriminant, so movie config hashes use it
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            CpuModel::Arm1176 => "arm1176",
+            CpuModel::Arm1136 => "arm1136",
+            CpuModel::Arm11mpcore => "arm11mpcore",
+            CpuModel::Arm926 => "arm926",
+            CpuModel::Arm946 => "arm946",
+            CpuModel::CortexA8 => "cortex-a8",
+            CpuModel::CortexA9 => "cortex-a9",
+            CpuModel::CortexA15 => "cortex-a15",
+        };
+    }
+}
+
+pub struct Machine<'a> {
+    cpu: Unicorn<'a, ()>,
+    frame_budget: Option<Arc<FrameBudget>>,
+    exception_stats: Arc<ExceptionStats>,
+    bios: Arc<Bios>,
+    fault: Arc<Mutex<Option<String>>>,
+    intc: Option<Arc<InterruptController>>,
+    traps: Arc<DebugTraps>,
+    debug_stops: Receiver<DebugStop>,
+    // breakpoints & watchpoints by id, with a description, the hooks that implement them, & a breakpoint's hit state
+    trap_hooks: HashMap<u32, (String, Vec<UcHookId>, Option<Arc<Breakpoint>>)>,
+    next_trap_id: u32,
+    // instructions per frame (0 = unlimited), & roughly how many have run in total (once something has asked for them to be
+    // counted)
+    cpu_budget: u64,
+    executed: Arc<AtomicU64>,
+    counting: bool,
+    fault_mode: FaultMode,
+    // unmapped accesses are latched here, & with UnmappedPolicy::Abort always reach the guest as aborts
+    bus_error: Option<Arc<BusError>>,
+    unmapped_abort: bool,
+    // kept so regions loaded from a save state can be applied without a register write
+    mpu: Option<Arc<Mpu>>,
+    // deterministic mode: the instruction-driven timebase, & the clock whose interrupts the CPU thread raises itself
+    deterministic: Option<(Arc<Timebase>, Arc<Clock>)>,
+    // idle skip: the timebase a waiting CPU jumps ahead, & the clock it jumps to the next interrupt of
+    idle_skip: Option<(Arc<Timebase>, Arc<Clock>)>,
+    // the optional second core, which shares memory mapped after it's added
+    coprocessor: Option<Coprocessor<'a>>,
+    // spans for each run of the CPU & each wait in WFI, & instants where it takes an interrupt or exception
+    chrome_trace: Option<Arc<ChromeTrace>>,
+    // host-side SWI handlers by call number
+    swi_handlers: Arc<Mutex<HashMap<u8, SwiHandler>>>,
+    // what reset puts in PC, SP, & CPSR
+    boot_pc: u32,
+    boot_sp: u32,
+    boot_cpsr: u32,
+    // the layout memory & peripherals are mapped to, & the guest symbols faults & breakpoints are described with
+    map: MemoryMap,
+    symbols: Symbols,
+}
+
+pub struct MachineRunContext {
+    join_handle: JoinHandle<()>,
+    coprocessor: Option<CoprocessorRunContext>,
+    cpu_handle: usize,
+    cpu_signal: Arc<AutoResetEvent>,
+    stop_signal: Arc<AtomicBool>,
+    pause_signal: Arc<AtomicBool>,
+    resume_signal: Arc<AutoResetEvent>,
+    idle_signal: Arc<AutoResetEvent>,
+    // set while the CPU thread is parked (or gone), so pausing can wait until the CPU is actually still
+    parked_signal: Arc<ManualResetEvent>,
+    // set while the CPU is waiting in WFI (or a BIOS wait) for the frame signal or an interrupt
+    idle: Arc<AtomicBool>,
+    fault: Arc<Mutex<Option<String>>>,
+    frame_budget: Option<Arc<FrameBudget>>,
+    frame_signal: Arc<AtomicBool>,
+    intc: Option<Arc<InterruptController>>,
+    step_insn: Arc<AtomicBool>,
+    step_frame: Arc<AtomicBool>,
+    // a step_until in progress, & how many more instructions it can step
+    step_until: Arc<Mutex<Option<(StepDone, u32)>>>,
+}
+
+// MMIO registers are 32 bits wide. narrower reads return the addressed byte lane(s) of the register (little endian).
+// narrower writes are only accepted in lane 0 (zero-extended) - writes to any other lane are dropped
+pub fn mmio_read<T: Peripheral + ?Sized>(dev: &T, addr: u64, size: usize) -> u64 {
+    let local_addr = (addr & 0xFFFFFF) >> 2;
+    let lane = (addr & 3) * 8;
+    let val = (dev.read(local_addr as u32) >> lane) as u64;
+
+    return val & mmio_size_mask(size);
+}
+
+pub fn mmio_write<T: Peripheral + ?Sized>(dev: &T, addr: u64, size: usize, value: u64) {
+    if addr & 3 != 0 {
+        crate::log!(Mem, Warn, "dropped {}-byte MMIO write to unaligned register offset {:x}", size, addr);
+        return;
+    }
+
+    let local_addr = (addr & 0xFFFFFF) >> 2;
+    dev.write(local_addr as u32, (value & mmio_size_mask(size)) as u32);
+}
+
+fn mmio_size_mask(size: usize) -> u64 {
+    return match size {
+        1 => 0xFF,
+        2 => 0xFFFF,
+        _ => 0xFFFFFFFF,
+    };
+}
+
+// a host-side SWI handler. it gets the calling CPU, & returns true if the CPU should stop (e.g. to park it)
+pub type SwiHandler = Box<dyn Fn(&mut SwiCall) -> bool + Send + Sync>;
+
+// where a MachineRunContext::step_until is going: given the PC & SP after each instruction, true once it's there
+pub type StepDone = Box<dyn Fn(u32, u32) -> bool + Send>;
+
+// the CPU as an SWI handler sees it: arguments in r0-r3, results back in r0-r3, & guest memory to read from & write to
+pub struct SwiCall<'c, 'u> {
+    cpu: &'c mut Unicorn<'u, ()>,
+}
+
+impl <'c, 'u> SwiCall<'c, 'u> {
+    pub fn arg(self: &Self, n: usize) -> u32 {
+        return self.cpu.reg_read(SWI_ARG_REGS[n]).unwrap() as u32;
+    }
+
+    pub fn set_result(self: &mut Self, n: usize, val: u32) {
+        self.cpu.reg_write(SWI_ARG_REGS[n], val as u64).unwrap();
+    }
+
+    // false if any of it isn't mapped plain memory
+    pub fn read_memory(self: &Self, addr: u32, data: &mut [u8]) -> bool {
+        return self.cpu.mem_read(addr as u64, data).is_ok();
+    }
+
+    pub fn write_memory(self: &mut Self, addr: u32, data: &[u8]) -> bool {
+        return self.cpu.mem_write(addr as u64, data).is_ok();
+    }
+}
+
+const SWI_ARG_REGS: [RegisterARM;4] = [RegisterARM::R0, RegisterARM::R1, RegisterARM::R2, RegisterARM::R3];
+
+// BIOS_MEMCPY: a chunk at a time, so a huge copy doesn't need a huge host buffer. a chunk that isn't all plain memory
+// ends the copy there
+fn bios_memcpy(call: &mut SwiCall) -> bool {
+    let (dst, src, len) = (call.arg(0), call.arg(1), call.arg(2));
+    let mut buf = [0;4096];
+    let mut done = 0;
+
+    while done < len {
+        let chunk = (len - done).min(buf.len() as u32) as usize;
+
+        if !call.read_memory(src.wrapping_add(done), &mut buf[..chunk]) || !call.write_memory(dst.wrapping_add(done), &buf[..chunk]) {
+            break;
+        }
+
+        done += chunk as u32;
+    }
+
+    call.set_result(0, done);
+    return false;
+}
+
+// where emu_start should carry on from. unicorn takes the instruction set from bit 0 of the start address rather than the
+// T bit, so Thumb code has to be resumed at pc | 1 or it comes back in ARM state
+pub fn resume_addr(cpu: &Unicorn<'_, ()>) -> u64 {
+    let pc = cpu.pc_read().unwrap();
+
+    if cpu.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0 {
+        return pc | 1;
+    }
+
+    return pc;
+}
+
+// emu_start also returns when the guest executes WFI, which leaves the PC just past it
+pub fn after_wfi(cpu: &Unicorn<'_, ()>) -> bool {
+    let pc = cpu.pc_read().unwrap();
+    let mut insn = [0;4];
+
+    if cpu.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0 {
+        return cpu.mem_read(pc.wrapping_sub(2), &mut insn[..2]).is_ok() && u16::from_le_bytes([insn[0], insn[1]]) == WFI_THUMB;
+    }
+
+    return cpu.mem_read(pc.wrapping_sub(4), &mut insn).is_ok() && u32::from_le_bytes(insn) & 0x0FFFFFFF == WFI_ARM;
+}
+
+// every mode's banked registers, which DEBUG_REGS only shows the current mode's view of. unicorn only exposes the current
+// bank, so this visits each mode in turn & comes back to the one the CPU was in
+fn read_banked(cpu: &mut Unicorn<'_, ()>) -> Vec<u32> {
+    let cpsr = cpu.reg_read(RegisterARM::CPSR).unwrap();
+    let mut regs = Vec::with_capacity(BANKED_REG_COUNT);
+    let mut high = Vec::with_capacity(HIGH_REGS.len() * 2);
+
+    for mode in BANKED_MODES {
+        cpu.reg_write(RegisterARM::CPSR, (cpsr & !CPSR_MODE_MASK) | mode).unwrap();
+
+        for reg in [RegisterARM::SP, RegisterARM::LR, RegisterARM::SPSR] {
+            regs.push(cpu.reg_read(reg).unwrap() as u32);
+        }
+
+        if mode == CPSR_MODE_FIQ || mode == CPSR_MODE_SYS {
+            high.extend(HIGH_REGS.iter().map(|reg| cpu.reg_read(*reg).unwrap() as u32));
+        }
+    }
+
+    cpu.reg_write(RegisterARM::CPSR, cpsr).unwrap();
+
+    // FIQ's first
+    regs.extend_from_slice(&high[HIGH_REGS.len()..]);
+    regs.extend_from_slice(&high[..HIGH_REGS.len()]);
+    return regs;
+}
+
+fn write_banked(cpu: &mut Unicorn<'_, ()>, regs: &[u32]) {
+    let cpsr = cpu.reg_read(RegisterARM::CPSR).unwrap();
+    let high = &regs[BANKED_MODES.len() * 3..];
+
+    for (i, mode) in BANKED_MODES.into_iter().enumerate() {
+        cpu.reg_write(RegisterARM::CPSR, (cpsr & !CPSR_MODE_MASK) | mode).unwrap();
+
+        for (j, reg) in [RegisterARM::SP, RegisterARM::LR, RegisterARM::SPSR].into_iter().enumerate() {
+            cpu.reg_write(reg, regs[i * 3 + j] as u64).unwrap();
+        }
+
+        let bank: &[u32] = match mode {
+            CPSR_MODE_FIQ => &high[..HIGH_REGS.len()],
+            CPSR_MODE_SYS => &high[HIGH_REGS.len()..],
+            _ => &[],
+        };
+
+        for (reg, val) in HIGH_REGS.iter().zip(bank) {
+            cpu.reg_write(*reg, *val as u64).unwrap();
+        }
+    }
+
+    cpu.reg_write(RegisterARM::CPSR, cpsr).unwrap();
+}
+
+// take an IRQ exception the way the core does: switch to IRQ mode (banking SP & LR) with IRQs masked & in ARM state, save
+// the old CPSR in SPSR_irq, & jump to the vector. the handler returns with `subs pc, lr, #4`. an FIQ is the same, but in
+// FIQ mode (which banks r8-r12 too) with FIQs masked as well
+fn enter_interrupt(cpu: &mut Unicorn<'_, ()>, fiq: bool) {
+    let cpsr = cpu.reg_read(RegisterARM::CPSR).unwrap();
+    let ret = cpu.pc_read().unwrap();
+    let (mode, masks, vector) = if fiq { (CPSR_MODE_FIQ, CPSR_I | CPSR_F, FIQ_VECTOR) } else { (CPSR_MODE_IRQ, CPSR_I, IRQ_VECTOR) };
+
+    cpu.reg_write(RegisterARM::CPSR, (cpsr & !(CPSR_MODE_MASK | CPSR_T)) | mode | masks).unwrap();
+    cpu.reg_write(RegisterARM::SPSR, cpsr).unwrap();
+    cpu.reg_write(RegisterARM::LR, ret + 4).unwrap();
+    cpu.reg_write(RegisterARM::PC, vector as u64).unwrap();
+}
+
+impl <'a> Machine<'a> {
+    pub fn new(model: CpuModel, map: MemoryMap, symbols: Symbols) -> Self {
+        let (mode, uc_model) = model.unicorn_model();
+        let mut cpu = Unicorn::new(unicorn_engine::Arch::ARM, mode).unwrap();
+        cpu.ctl_set_cpu_model(uc_model as i32).unwrap();
+
+        let exception_stats = Arc::new(ExceptionStats::new());
+        let hook_stats = exception_stats.clone();
+
+        let bios = Arc::new(Bios::new());
+        let hook_bios = bios.clone();
+
+        let swi_handlers: Arc<Mutex<HashMap<u8, SwiHandler>>> = Arc::new(Mutex::new(HashMap::new()));
+        let hook_swi_handlers = swi_handlers.clone();
+
+        // dispatch SWIs to their handlers (the BIOS calls, & whatever else the frontend registers)
+        cpu.add_intr_hook(move |uc, intr| {
+            let mut swi_num = None;
+
+            if intr == EXCP_SWI {
+                // swi - the call number is the low byte of the immediate in both ARM & Thumb encodings
+                let thumb = uc.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0;
+                let addr = uc.pc_read().unwrap() - if thumb { 2 } else { 4 };
+                let mut insr = [0;4];
+                uc.mem_read(addr, &mut insr).unwrap();
+                swi_num = Some(insr[0]);
+
+                // a handler that parks the CPU stops it here, & the run loop waits that out
+                if let Some(handler) = hook_swi_handlers.lock().unwrap().get(&insr[0]) {
+                    hook_bios.record_call(insr[0]);
+
+                    if handler(&mut SwiCall { cpu: uc }) {
+                        uc.emu_stop().unwrap();
+                    }
+                }
+            }
+
+            crate::log!(Cpu, Trace, "exception {}{} @ pc {:08x}", intr, swi_num.map_or(String::new(), |num| format!(" (swi {:#x})", num)), uc.pc_read().unwrap_or(0));
+            hook_stats.record(intr, swi_num);
+        }).unwrap();
+
+        let (traps, debug_stops) = DebugTraps::new();
+
+        {
+            let mut handlers = swi_handlers.lock().unwrap();
+
+            let park_bios = bios.clone();
+            handlers.insert(BIOS_WAITVBLANK, Box::new(move |_| park_bios.park(BIOS_WAITVBLANK, 1)));
+
+            let park_bios = bios.clone();
+            handlers.insert(BIOS_SLEEPFRAMES, Box::new(move |call| park_bios.park(BIOS_SLEEPFRAMES, call.arg(0))));
+
+            handlers.insert(BIOS_MEMCPY, Box::new(bios_memcpy));
+        }
+
+        Self {
+            cpu: cpu,
+            frame_budget: None,
+            exception_stats,
+            bios,
+            fault: Arc::new(Mutex::new(None)),
+            intc: None,
+            traps: Arc::new(traps),
+            debug_stops,
+            trap_hooks: HashMap::new(),
+            next_trap_id: 1,
+            cpu_budget: 0,
+            executed: Arc::new(AtomicU64::new(0)),
+            counting: false,
+            boot_pc: BOOT_ROM_BEGIN as u32,
+            boot_sp: 0,
+            boot_cpsr: CPSR_RESET as u32,
+            swi_handlers,
+            fault_mode: FaultMode::Halt,
+            bus_error: None,
+            unmapped_abort: false,
+            mpu: None,
+            deterministic: None,
+            idle_skip: None,
+            coprocessor: None,
+            chrome_trace: None,
+            map,
+            symbols,
+        }
+    }
+
+    // memory is shared with the coprocessor, if there is one by now
+    pub fn map_memory(self: &mut Self, mem: &'a mut [u8], start_addr: u32, permission: Permission) {
+        unsafe {
+            self.cpu.mem_map_ptr(start_addr as u64, mem.len(), permission, mem.as_mut_ptr().cast()).unwrap();
+
+            if let Some(coprocessor) = &mut self.coprocessor {
+                coprocessor.map_shared(mem.as_mut_ptr(), mem.len(), start_addr, permission);
+            }
+        }
+    }
+
+    // a second core, talking to this one through the mailbox mapped at start_addr in both. add it before mapping memory,
+    // so it gets to share it
+    pub fn add_coprocessor(self: &mut Self, model: CpuModel, mailbox: Arc<Mailbox>, start_addr: u32, length: u32) {
+        self.map_peripheral(Arc::new(mailbox.port(Side::Main)), start_addr, length);
+        self.coprocessor = Some(Coprocessor::new(model, mailbox, start_addr, length, self.symbols.clone()));
+    }
+
+    pub fn map_peripheral<T>(self: &mut Self, device: Arc<T>, start_addr: u32, length: u32) where T : Peripheral + 'a {
+        let rd_dev = device.clone();
+        let wr_dev = device.clone();
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&*rd_dev, addr, size);
+        };
+
+        let wr = move |_uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            mmio_write(&*wr_dev, addr, size, value);
+        };
+
+        // add read/write hooks
+        self.cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+    }
+
+    // part of a peripheral's range, with register offsets counted from `offset` into it (see devmap.rs)
+    pub fn map_peripheral_range(self: &mut Self, device: Arc<dyn Peripheral>, start_addr: u32, length: u32, offset: u32) {
+        let rd_dev = device.clone();
+        let wr_dev = device.clone();
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&*rd_dev, addr + offset as u64, size);
+        };
+
+        let wr = move |_uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            mmio_write(&*wr_dev, addr + offset as u64, size, value);
+        };
+
+        self.cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+    }
+
+    // the first mapped region (memory or MMIO) that the given range overlaps, as first & last address
+    pub fn mapped_overlap(self: &Self, start_addr: u32, length: u32) -> Option<(u32, u32)> {
+        let end = start_addr as u64 + length as u64;
+
+        return self.cpu.mem_regions().unwrap().into_iter()
+            .find(|r| r.begin < end && (start_addr as u64) <= r.end)
+            .map(|r| (r.begin as u32, r.end as u32));
+    }
+
+    // the MPU needs the CPU itself, so it can't go through the generic peripheral path
+    pub fn map_mpu(self: &mut Self, mpu: Arc<Mpu>, start_addr: u32, length: u32) {
+        let rd_dev = mpu.clone();
+        let wr_dev = mpu.clone();
+        let fault_dev = mpu.clone();
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&*rd_dev, addr, size);
+        };
+
+        let wr = move |uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            mmio_write(&*wr_dev, addr, size, value);
+            wr_dev.apply_if_dirty(uc);
+        };
+
+        self.cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+
+        // latch the faulting access so it can be inspected - the access itself still stops emulation
+        self.cpu.add_mem_hook(HookType::MEM_PROT, 0, u64::MAX, move |_uc, mem_type, addr, _size, _value| {
+            fault_dev.record_fault(mem_type, addr);
+            return false;
+        }).unwrap();
+
+        self.mpu = Some(mpu);
+    }
+
+    // bring page permissions in line with MPU registers changed behind the guest's back (e.g. by a save state)
+    pub fn apply_mpu(self: &mut Self) {
+        if let Some(mpu) = &self.mpu {
+            mpu.apply_if_dirty(&mut self.cpu);
+        }
+    }
+
+    // the bus error block latches every access to unmapped memory, so it hooks the whole address space
+    pub fn map_bus_error(self: &mut Self, bus_error: Arc<BusError>, start_addr: u32, length: u32) {
+        self.map_peripheral(bus_error.clone(), start_addr, length);
+
+        let fault_dev = bus_error.clone();
+
+        // the access itself still stops emulation, unless open bus has filled the hole in
+        self.cpu.add_mem_hook(HookType::MEM_UNMAPPED, 0, u64::MAX, move |uc, mem_type, addr, _size, _value| {
+            fault_dev.record_fault(mem_type, addr, uc.pc_read().unwrap_or(0) as u32);
+            return false;
+        }).unwrap();
+
+        self.bus_error = Some(bus_error);
+    }
+
+    // open bus maps every hole left in the 4GiB address space, so call this after everything else has been mapped
+    pub fn set_unmapped_policy(self: &mut Self, policy: UnmappedPolicy) {
+        self.unmapped_abort = policy == UnmappedPolicy::Abort;
+
+        if policy != UnmappedPolicy::OpenBus {
+            return;
+        }
+
+        let bus_error = self.bus_error.clone().expect("open bus needs the bus error block mapped");
+        let mut regions = self.cpu.mem_regions().unwrap();
+        regions.sort_by_key(|r| r.begin);
+
+        let mut holes = Vec::new();
+        let mut next = 0u64;
+
+        for region in &regions {
+            if region.begin > next {
+                holes.push((next, region.begin - next));
+            }
+
+            next = next.max(region.end + 1);
+        }
+
+        if next < 1 << 32 {
+            holes.push((next, (1 << 32) - next));
+        }
+
+        for (base, size) in holes {
+            let rd_dev = bus_error.clone();
+            let wr_dev = bus_error.clone();
+
+            let rd = move |uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+                return rd_dev.on_read((base + addr) as u32, size as u32, uc.pc_read().unwrap_or(0) as u32) as u64;
+            };
+
+            let wr = move |uc: &mut Unicorn<'_, ()>, addr, _size, _value| {
+                wr_dev.on_write((base + addr) as u32, uc.pc_read().unwrap_or(0) as u32);
+            };
+
+            self.cpu.mmio_map(base, size as usize, Some(rd), Some(wr)).unwrap();
+        }
+    }
+
+    // strict mode needs the PC of each write to the VDP port, which only the CPU thread knows
+    pub fn map_vdp_port(self: &mut Self, vdp_port: Arc<VdpPort>, start_addr: u32, length: u32) {
+        if !vdp_port.is_strict() {
+            self.map_peripheral(vdp_port, start_addr, length);
+            return;
+        }
+
+        let rd_dev = vdp_port.clone();
+        let wr_dev = vdp_port.clone();
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&*rd_dev, addr, size);
+        };
+
+        let wr = move |uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            wr_dev.set_writer_pc(uc.pc_read().unwrap_or(0) as u32);
+            mmio_write(&*wr_dev, addr, size, value);
+        };
+
+        self.cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+    }
+
+    // poison tracking watches every guest access to the tracked RAM, & poisoning a block writes the canary into guest memory
+    pub fn map_poison(self: &mut Self, poison: Arc<PoisonMap>, start_addr: u32, length: u32) {
+        let rd_dev = poison.clone();
+        let wr_dev = poison.clone();
+        let read_dev = poison.clone();
+        let write_dev = poison.clone();
+
+        let rd = move |_uc: &mut Unicorn<'_, ()>, addr, size| -> u64 {
+            return mmio_read(&*rd_dev, addr, size);
+        };
+
+        let wr = move |uc: &mut Unicorn<'_, ()>, addr, size, value| {
+            mmio_write(&*wr_dev, addr, size, value);
+            wr_dev.apply_pending(uc);
+        };
+
+        self.cpu.mmio_map(start_addr as u64, length as usize, Some(rd), Some(wr)).unwrap();
+
+        let (base, size) = poison.range();
+        let end = base as u64 + size as u64 - 1;
+
+        self.cpu.add_mem_hook(HookType::MEM_READ, base as u64, end, move |uc, _mem_type, addr, size, _value| {
+            read_dev.on_read(uc.pc_read().unwrap_or(0) as u32, addr as u32, size as u32);
+            return true;
+        }).unwrap();
+
+        self.cpu.add_mem_hook(HookType::MEM_WRITE, base as u64, end, move |_uc, _mem_type, addr, size, _value| {
+            write_dev.on_write(addr as u32, size as u32);
+            return true;
+        }).unwrap();
+    }
+
+    // the CPU thread has to know about the interrupt controller to take the IRQs it raises
+    pub fn map_intc(self: &mut Self, intc: Arc<InterruptController>, start_addr: u32, length: u32) {
+        self.map_peripheral(intc.clone(), start_addr, length);
+        self.intc = Some(intc);
+    }
+
+    // the frame budget monitor has to hear about frame boundaries from the CPU thread, so it gets mapped here rather than as a plain peripheral
+    pub fn map_frame_budget(self: &mut Self, frame_budget: Arc<FrameBudget>, start_addr: u32, length: u32) {
+        self.map_peripheral(frame_budget.clone(), start_addr, length);
+        self.frame_budget = Some(frame_budget);
+    }
+
+    // accesses that don't line up with their own size are where the hardware model differs: developer mode splits them
+    // across byte lanes like any other narrow access, strict mode stops the CPU with a fault
+    pub fn set_hw_limits(self: &mut Self, hw: Arc<HwLimits>) {
+        let (mmio_begin, mmio_end) = self.map.mmio_range();
+
+        for hook_type in [HookType::MEM_READ, HookType::MEM_WRITE] {
+            let hw = hw.clone();
+            let fault = self.fault.clone();
+            let symbols = self.symbols.clone();
+
+            self.cpu.add_mem_hook(hook_type, mmio_begin as u64, mmio_end as u64 - 1, move |uc, _mem_type, addr, size, _value| {
+                if addr % size as u64 == 0 {
+                    return true;
+                }
+
+                let pc = uc.pc_read().unwrap_or(0);
+
+                if hw.exceeded(Limit::MmioAlignment, || format!("unaligned {}-byte access to {:08x} (pc {:08x})", size, addr, pc)) {
+                    *fault.lock().unwrap() = Some(format!("unaligned {}-byte MMIO access to {:08x} @ pc {}", size, addr, symbols.describe(pc as u32)));
+                    uc.emu_stop().unwrap();
+                }
+
+                return true;
+            }).unwrap();
+        }
+    }
+
+    // breakpoints & watchpoints are hooks on the CPU, which can't be added or removed under a running emu_start - only call
+    // these while no run context is active (see breakpoint.rs for how a hit stops the CPU)
+    pub fn add_breakpoint(self: &mut Self, spec: BreakSpec) -> u32 {
+        self.remove_spent_breakpoints();
+
+        let id = self.next_trap_id;
+        let addr = spec.addr;
+        let desc = spec.describe(&self.symbols);
+        let bp = Arc::new(Breakpoint::new(spec));
+        let hook_bp = bp.clone();
+        let traps = self.traps.clone();
+
+        let hook = self.cpu.add_code_hook(addr as u64, addr as u64, move |uc, pc, _size| {
+            if traps.on_breakpoint(id, &hook_bp, uc, pc as u32) {
+                uc.emu_stop().unwrap();
+            }
+        }).unwrap();
+
+        // code already translated wouldn't call the new hook
+        self.cpu.ctl_remove_cache(addr as u64, addr as u64 + 4).unwrap();
+
+        self.next_trap_id += 1;
+        self.trap_hooks.insert(id, (desc, vec![hook], Some(bp)));
+        return id;
+    }
+
+    pub fn add_watchpoint(self: &mut Self, addr: u32, len: u32, kind: WatchKind) -> u32 {
+        let id = self.next_trap_id;
+        let end = addr as u64 + len.max(1) as u64 - 1;
+        let mut hooks = Vec::new();
+
+        for (hook_type, write) in [(HookType::MEM_READ, false), (HookType::MEM_WRITE, true)] {
+            if (write && kind == WatchKind::Read) || (!write && kind == WatchKind::Write) {
+                continue;
+            }
+
+            let traps = self.traps.clone();
+
+            hooks.push(self.cpu.add_mem_hook(hook_type, addr as u64, end, move |uc, _mem_type, addr, size, _value| {
+                let pc = uc.pc_read().unwrap_or(0) as u32;
+                traps.on_hit(DebugStop::Watchpoint { id, pc, addr: addr as u32, size: size as u32, write });
+                uc.emu_stop().unwrap();
+                return true;
+            }).unwrap());
+        }
+
+        // memory hooks are compiled into the translated code too
+        self.flush_code_cache();
+
+        self.next_trap_id += 1;
+        self.trap_hooks.insert(id, (format!("watch {:08x}:{}:{}", addr, len, kind.name()), hooks, None));
+        return id;
+    }
+
+    // removes a breakpoint or watchpoint - false if there's no such id
+    pub fn remove_breakpoint(self: &mut Self, id: u32) -> bool {
+        self.remove_spent_breakpoints();
+
+        let Some((_, hooks, _)) = self.trap_hooks.remove(&id) else {
+            return false;
+        };
+
+        for hook in hooks {
+            self.cpu.remove_hook(hook).unwrap();
+        }
+
+        // drop translations that still call the removed hooks
+        self.flush_code_cache();
+        return true;
+    }
+
+    // a temporary breakpoint stops the CPU once & then does nothing, but its hook can only go while no run context is active -
+    // which is whenever breakpoints are next changed
+    fn remove_spent_breakpoints(self: &mut Self) {
+        let spent: Vec<u32> = self.trap_hooks.iter().filter(|(_, (_, _, bp))| bp.as_ref().is_some_and(|bp| bp.is_spent())).map(|(id, _)| *id).collect();
+
+        for id in spent {
+            for hook in self.trap_hooks.remove(&id).unwrap().1 {
+                self.cpu.remove_hook(hook).unwrap();
+            }
+        }
+    }
+
+    // everywhere code can run from: boot ROM, main RAM, & expansion RAM
+    fn flush_code_cache(self: &mut Self) {
+        self.cpu.ctl_remove_cache(BOOT_ROM_BEGIN as u64, self.map.memory_end() as u64).unwrap();
+    }
+
+    // (id, description, hits) for every breakpoint & watchpoint, in the order they were added. only breakpoints count hits,
+    // & a temporary one that's already stopped the CPU is gone
+    pub fn breakpoints(self: &Self) -> Vec<(u32, String, Option<u32>)> {
+        let mut list: Vec<(u32, String, Option<u32>)> = self.trap_hooks.iter()
+            .filter(|(_, (_, _, bp))| !bp.as_ref().is_some_and(|bp| bp.is_spent()))
+            .map(|(id, (desc, _, bp))| (*id, desc.clone(), bp.as_ref().map(|bp| bp.hits())))
+            .collect();
+
+        list.sort_by_key(|(id, _, _)| *id);
+        return list;
+    }
+
+    // the next breakpoint or watchpoint the CPU stopped at, if any - the CPU stays paused until resumed
+    pub fn poll_debug_stop(self: &Self) -> Option<DebugStop> {
+        return self.debug_stops.try_recv().ok();
+    }
+
+    // whether guest faults halt the CPU or reach the guest as aborts. takes effect the next time the CPU thread starts
+    pub fn set_fault_mode(self: &mut Self, mode: FaultMode) {
+        self.fault_mode = mode;
+    }
+
+    // call back on every guest read & write within start..=end, with the PC that made it. reads are reported before they
+    // happen, so the value is read out of memory - only meaningful for RAM & ROM, not MMIO
+    pub fn trace_memory<F>(self: &mut Self, start: u32, end: u32, callback: F) where F: Fn(MemAccess) + Send + Sync + 'static {
+        let callback = Arc::new(callback);
+
+        for (hook_type, write) in [(HookType::MEM_READ, false), (HookType::MEM_WRITE, true)] {
+            let callback = callback.clone();
+
+            self.cpu.add_mem_hook(hook_type, start as u64, end as u64, move |uc, _mem_type, addr, size, value| {
+                let value = if write {
+                    value as u32
+                }
+                else {
+                    let mut data = [0;4];
+                    let _ = uc.mem_read(addr, &mut data[..size.min(4)]);
+                    u32::from_le_bytes(data)
+                };
+
+                let mask = if size >= 4 { u32::MAX } else { (1 << (size * 8)) - 1 };
+
+                callback(MemAccess { pc: uc.pc_read().unwrap_or(0) as u32, addr: addr as u32, size: size as u32, value: value & mask, write });
+                return true;
+            }).unwrap();
+        }
+
+        // memory hooks are compiled into the translated code too
+        self.flush_code_cache();
+    }
+
+    // the running count of instructions executed (while there's a CPU budget, or after count_instructions) - deterministic
+    // mode's time source
+    pub fn instruction_counter(self: &Self) -> Arc<AtomicU64> {
+        return self.executed.clone();
+    }
+
+    // run off emulated time alone: the CPU stops at exactly the instruction each clock interrupt is due on, skips WFI
+    // straight to the next one, & only wakes early for the frame signal. everything else that raises interrupts has to
+    // do it between frames, while the CPU is idle (see the frontend's lockstep). needs a CPU budget & a timebase made with
+    // Timebase::deterministic
+    pub fn set_deterministic(self: &mut Self, timebase: Arc<Timebase>, clock: Arc<Clock>) {
+        self.deterministic = Some((timebase, clock));
+    }
+
+    // a CPU waiting in WFI with a timer interrupt due later in the frame jumps emulated time straight to it instead of
+    // waiting for the host clock to get there. deterministic mode already does this, off the instruction count
+    pub fn set_idle_skip(self: &mut Self, timebase: Arc<Timebase>, clock: Arc<Clock>) {
+        self.idle_skip = Some((timebase, clock));
+    }
+
+    // service SWI num on the host, replacing any handler it already had. the BIOS calls are registered from the start, but
+    // any of them can be overridden
+    pub fn register_swi(self: &mut Self, num: u8, handler: SwiHandler) {
+        self.swi_handlers.lock().unwrap().insert(num, handler);
+    }
+
+    // each frame signal gives the CPU this many instructions (0 = as many as it gets through before WFI). a guest that uses
+    // them all up waits for the next frame as if it had executed WFI, so one that never waits still runs at a steady
+    // emulated speed rather than however fast the host is. instructions are counted a block at a time, so a frame can
+    // overshoot its budget (or be cut off just short of it) - the difference carries into the next frame's budget, so it
+    // evens out over frames. time spent idle in WFI is lost, as on the hardware, & a frame cut short by an interrupt or a
+    // pause keeps what it had left. cycles are counted as one per instruction
+    pub fn set_cpu_budget(self: &mut Self, instructions_per_frame: u64) {
+        if instructions_per_frame != 0 {
+            self.count_instructions();
+        }
+
+        self.cpu_budget = instructions_per_frame;
+    }
+
+    // keep the instruction counter going, budget or not (benchmarks want it either way)
+    pub fn count_instructions(self: &mut Self) {
+        if self.counting {
+            return;
+        }
+
+        // a block hook is a lot cheaper than a per-instruction one, but it adds a whole block as it's entered - the count can
+        // be up to a block ahead of what's actually run, & a budget overshoots by as much (set_cpu_budget carries that
+        // into the next frame)
+        let executed = self.executed.clone();
+
+        self.cpu.add_block_hook(1, 0, move |uc, _addr, size| {
+            let insn_size = if uc.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0 { 2 } else { 4 };
+            executed.fetch_add((size / insn_size) as u64, Ordering::Relaxed);
+        }).unwrap();
+
+        self.flush_code_cache();
+        self.counting = true;
+    }
+
+    pub fn set_chrome_trace(self: &mut Self, trace: Arc<ChromeTrace>) {
+        self.chrome_trace = Some(trace);
+    }
+
+    // keep the address of every block the CPU starts executing, for crash dumps. blocks rather than instructions, as a
+    // per-instruction hook would slow everything down for the sake of something that's hopefully never needed
+    pub fn set_pc_history(self: &mut Self, history: Arc<History>) {
+        self.cpu.add_block_hook(1, 0, move |_uc, addr, _size| {
+            history.push(addr);
+        }).unwrap();
+
+        self.flush_code_cache();
+    }
+
+    // registers & memory of this machine's CPU, readable from anywhere for as long as the Machine lives - crash dumps use
+    // it from a panic hook, where there's no run context to ask
+    pub fn probe(self: &Self) -> CpuProbe {
+        return CpuProbe {
+            cpu_handle: self.cpu.get_handle() as usize,
+        };
+    }
+
+    // log every instruction executed within the ranges (start & end inclusive - everywhere, if there are none)
+    pub fn set_tracer(self: &mut Self, tracer: Arc<Tracer>, ranges: &[(u32, u32)]) {
+        // unicorn treats begin > end as "every address"
+        let ranges = if ranges.is_empty() { vec![(1, 0)] } else { ranges.iter().map(|(start, end)| (*start as u64, *end as u64)).collect() };
+
+        for (start, end) in ranges {
+            let tracer = tracer.clone();
+
+            self.cpu.add_code_hook(start, end, move |uc, addr, size| {
+                let thumb = uc.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0;
+                let mut insn = [0;4];
+
+                if uc.mem_read(addr, &mut insn[..size.min(4) as usize]).is_err() {
+                    return;
+                }
+
+                // a 32-bit Thumb instruction (a bl/blx pair) goes in as its two halves
+                if thumb {
+                    tracer.record(addr as u32, u16::from_le_bytes([insn[0], insn[1]]) as u32, true);
+
+                    if size == 4 {
+                        tracer.record(addr as u32 + 2, u16::from_le_bytes([insn[2], insn[3]]) as u32, true);
+                    }
+                }
+                else {
+                    tracer.record(addr as u32, u32::from_le_bytes(insn), false);
+                }
+            }).unwrap();
+        }
+
+        self.flush_code_cache();
+    }
+
+    pub fn exception_stats(self: &Self) -> Arc<ExceptionStats> {
+        return self.exception_stats.clone();
+    }
+
+    pub fn bios(self: &Self) -> Arc<Bios> {
+        return self.bios.clone();
+    }
+
+    // put the CPU back into its power-on state - by default that's the reset vector in ARM state, supervisor mode, with
+    // interrupts masked, but see set_boot_state
+    pub fn reset(self: &mut Self) {
+        for (_, reg) in DEBUG_REGS {
+            self.cpu.reg_write(reg, 0).unwrap();
+        }
+
+        // PC first: writing it picks the instruction set from bit 0, which the CPSR write then settles from the T bit
+        self.cpu.reg_write(RegisterARM::PC, self.boot_pc as u64).unwrap();
+        self.cpu.reg_write(RegisterARM::CPSR, self.boot_cpsr as u64).unwrap();
+        self.cpu.reg_write(RegisterARM::SP, self.boot_sp as u64).unwrap();
+
+        if let Some(coprocessor) = &mut self.coprocessor {
+            coprocessor.reset();
+        }
+    }
+
+    // where reset leaves the CPU, for images whose startup code isn't an ARM reset vector (e.g. Thumb-2 toolchain output).
+    // as with BX, bit 0 of pc selects Thumb state, which sets the T bit in cpsr. takes effect on the next reset
+    pub fn set_boot_state(self: &mut Self, pc: u32, sp: u32, cpsr: u32) {
+        let thumb = pc & 1 != 0 || cpsr & CPSR_T as u32 != 0;
+
+        self.boot_pc = pc & !1;
+        self.boot_sp = sp;
+        self.boot_cpsr = if thumb { cpsr | CPSR_T as u32 } else { cpsr & !(CPSR_T as u32) };
+    }
+
+    // load a full register set, in DEBUG_REGS order (e.g. from a save state). the CPSR goes in first as well as last, so SP
+    // & LR land in the bank of the mode they were saved in
+    pub fn set_registers(self: &mut Self, regs: &[u32]) {
+        if let Some(cpsr) = regs.get(DEBUG_REGS.len() - 1) {
+            self.cpu.reg_write(RegisterARM::CPSR, *cpsr as u64).unwrap();
+        }
+
+        for ((_, reg), val) in DEBUG_REGS.iter().zip(regs) {
+            self.cpu.reg_write(*reg, *val as u64).unwrap();
+        }
+    }
+
+    // every mode's SP, LR, SPSR, & r8-r12, as MachineRunContext::banked_registers gives them
+    pub fn set_banked_registers(self: &mut Self, regs: &[u32]) {
+        write_banked(&mut self.cpu, regs);
+    }
+
+    // write straight into mapped guest memory, e.g. to fill RAM before boot
+    pub fn write_memory(self: &mut Self, addr: u32, data: &[u8]) {
+        self.cpu.mem_write(addr as u64, data).unwrap();
+    }
+
+    // overwrite the boot ROM contents, zero-filling whatever the new image doesn't cover
+    pub fn load_rom(self: &mut Self, rom: &[u8]) {
+        let rom_size = self.map.boot_rom_size;
+        let mut image = vec![0;rom_size];
+        image[..rom.len()].copy_from_slice(rom);
+
+        self.cpu.mem_write(BOOT_ROM_BEGIN as u64, &image).unwrap();
+
+        // make sure we don't keep executing stale translated code
+        self.cpu.ctl_remove_cache(BOOT_ROM_BEGIN as u64, (BOOT_ROM_BEGIN + rom_size) as u64).unwrap();
+    }
+
+    // replace the boot ROM contents with an executable's ROM segments, & write its RAM segments in
+    pub fn load_elf(self: &mut Self, exe: &Executable) -> Result<(), String> {
+        self.load_rom(&[]);
+        return self.load_segments(&exe.segments);
+    }
+
+    // write data straight into memory, ROM included
+    pub fn load_segments(self: &mut Self, segments: &[Segment]) -> Result<(), String> {
+        for segment in segments {
+            self.cpu.mem_write(segment.addr as u64, &segment.data)
+                .map_err(|_| format!("segment at {:#x} ({} bytes) isn't in mapped memory", segment.addr, segment.data.len()))?;
+            self.cpu.ctl_remove_cache(segment.addr as u64, segment.addr as u64 + segment.data.len() as u64).unwrap();
+        }
+
+        return Ok(());
+    }
+
+    pub fn run(self: &Self) -> MachineRunContext {
+        return self.start(false);
+    }
+
+    // start the CPU thread already paused, so not a single instruction runs until it's resumed
+    pub fn run_paused(self: &Self) -> MachineRunContext {
+        return self.start(true);
+    }
+
+    fn start(self: &Self, paused: bool) -> MachineRunContext {
+        // this is an awful no good very bad way to do this tbh
+        // basically: turns underlying uc_handle into a usize, sends it to the thread, turns it back into a uc_handle, & makes a new Unicorn instance pointing to that handle
+
+        // that said, the underlying API is *supposed* to be thread safe, so this should be OK
+
+        let cpu_send = self.cpu.get_handle() as usize;
+        let cpu_signal = Arc::new(AutoResetEvent::new(EventState::Unset));
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let pause_signal = Arc::new(AtomicBool::new(paused));
+        let resume_signal = Arc::new(AutoResetEvent::new(EventState::Unset));
+        let idle_signal = Arc::new(AutoResetEvent::new(EventState::Unset));
+        let parked_signal = Arc::new(ManualResetEvent::new(EventState::Unset));
+        let idle = Arc::new(AtomicBool::new(false));
+        let ret_idle = idle.clone();
+
+        let ret_cpu_signal = cpu_signal.clone();
+        let ret_stop_signal = stop_signal.clone();
+        let ret_pause_signal = pause_signal.clone();
+        let ret_resume_signal = resume_signal.clone();
+        let ret_idle_signal = idle_signal.clone();
+        let ret_parked_signal = parked_signal.clone();
+        let fault = self.fault.clone();
+        let ret_fault = self.fault.clone();
+        *fault.lock().unwrap() = None;
+
+        let frame_budget = self.frame_budget.clone();
+        let ret_frame_budget = self.frame_budget.clone();
+        let bios = self.bios.clone();
+        let exception_stats = self.exception_stats.clone();
+        let traps = self.traps.clone();
+        let budget = self.cpu_budget;
+        let executed = self.executed.clone();
+        let fault_mode = self.fault_mode;
+        let unmapped_abort = self.unmapped_abort;
+        let deterministic = self.deterministic.clone();
+        let idle_skip = self.idle_skip.clone();
+        let chrome_trace = self.chrome_trace.clone();
+        let symbols = self.symbols.clone();
+
+        let step_insn = Arc::new(AtomicBool::new(false));
+        let step_frame = Arc::new(AtomicBool::new(false));
+        let ret_step_insn = step_insn.clone();
+        let ret_step_frame = step_frame.clone();
+        let step_until: Arc<Mutex<Option<(StepDone, u32)>>> = Arc::new(Mutex::new(None));
+        let ret_step_until = step_until.clone();
+
+        let intc = self.intc.clone();
+        let ret_intc = self.intc.clone();
+        let frame_signal = Arc::new(AtomicBool::new(false));
+        let ret_frame_signal = frame_signal.clone();
+        let irq_request = Arc::new(AtomicBool::new(false));
+        let notify_irq_request = irq_request.clone();
+        let notify_signal = cpu_signal.clone();
+
+        let join_handle = thread::spawn(move || {
+            let cpu_handle = cpu_send as uc_handle;
+            let mut cpu = unsafe { Unicorn::from_handle(cpu_handle).unwrap() };
+
+            while pause_signal.load(Ordering::Relaxed) && !stop_signal.load(Ordering::Relaxed) && !step_insn.load(Ordering::Relaxed) {
+                parked_signal.set();
+                resume_signal.wait();
+            }
+
+            if stop_signal.load(Ordering::Relaxed) {
+                parked_signal.set();
+                return;
+            }
+
+            parked_signal.reset();
+
+            // pick up wherever the CPU was left - the boot address after a reset, or wherever a restored state had it, in
+            // whichever instruction set it was in
+            let mut pc = resume_addr(&cpu);
+
+            if paused {
+                traps.skip_once(pc as u32 & !1);
+            }
+
+            // instructions left in this frame's budget - negative once it's overshot
+            let mut credit = budget as i64;
+
+            // run until WFI (or the budget runs out), then wait for signal to resume
+            loop {
+                // a single step runs one instruction & parks again
+                let stepping = step_insn.swap(false, Ordering::AcqRel);
+                let mut count = if stepping { 1 } else if budget != 0 { credit.max(1) as u64 } else { 0 };
+                let executed_before = executed.load(Ordering::Relaxed);
+                let mut at_deadline = false;
+
+                // deterministic mode stops where the next timer interrupt is due, so it lands on the same instruction
+                // every run
+                if let (Some((timebase, clock)), false) = (&deterministic, stepping) {
+                    if let Some(until) = clock.next_deadline_ns().and_then(|ns| timebase.instructions_until(ns)) {
+                        at_deadline = until.max(1) < count;
+                        count = count.min(until.max(1));
+                    }
+                }
+
+                let mut exception_taken = false;
+
+                // a guest fault either becomes an exception for the guest to handle, or ends the thread - but not the
+                // frontend, it's left for the user to reset or load a state
+                let run_start = chrome_trace.as_ref().map(|trace| trace.now());
+                let result = cpu.emu_start(pc, u64::MAX, 0, count as usize);
+
+                if let (Some(trace), Some(start)) = (&chrome_trace, run_start) {
+                    trace.span(TRACK_CPU, "cpu", "run", start, Some(json!({ "pc": format!("{:#010x}", pc), "budget": count })));
+                }
+
+                if let Err(e) = result {
+                    let fault_pc = cpu.pc_read().unwrap_or(0);
+                    let exception = GuestException::from_error(e).filter(|_| fault_mode == FaultMode::Abort || (unmapped_abort && is_unmapped(e)));
+
+                    crate::log!(Cpu, Error, "{:?} @ pc {}{}\n{}", e, symbols.describe(fault_pc as u32), exception.map_or(String::new(), |exc| format!(", taking {}", exc.name())), register_dump(&cpu, &symbols));
+
+                    match exception {
+                        Some(exception) => {
+                            enter_exception(&mut cpu, exception);
+                            exception_stats.record(exception.excp(), None);
+
+                            if let Some(trace) = &chrome_trace {
+                                trace.instant(TRACK_CPU, "cpu", exception.name(), Some(json!({ "pc": format!("{:#010x}", fault_pc) })));
+                            }
+                            exception_taken = true;
+                        }
+                        None => {
+                            *fault.lock().unwrap() = Some(format!("{:?} @ pc {}", e, symbols.describe(fault_pc as u32)));
+                            idle_signal.set();
+                            break;
+                        }
+                    }
+                }
+
+                // ...as does one a hook caught
+                if fault.lock().unwrap().is_some() {
+                    idle_signal.set();
+                    break;
+                }
+
+                if let Some((_, clock)) = &deterministic {
+                    clock.poll();
+                }
+
+                // a step_until that isn't there yet goes straight on to its next instruction, without a stop for each one
+                let mut step_on = false;
+
+                // a breakpoint or watchpoint parks the CPU just like a pause request - & ends a step_until there
+                if traps.take_hit() {
+                    pause_signal.store(true, Ordering::Relaxed);
+                    step_until.lock().unwrap().take();
+                }
+                else if stepping {
+                    let (pc, sp) = (cpu.pc_read().unwrap() as u32, cpu.reg_read(RegisterARM::SP).unwrap() as u32);
+                    let mut until = step_until.lock().unwrap();
+
+                    match until.as_mut() {
+                        Some((done, left)) if *left > 0 && !done(pc, sp) => {
+                            *left -= 1;
+                            step_on = true;
+                            step_insn.store(true, Ordering::Release);
+                        }
+                        _ => {
+                            *until = None;
+                            traps.report(DebugStop::Step { pc });
+                        }
+                    }
+                }
+
+                // stopped to take an interrupt (or having just taken an exception, or at a timer deadline) rather than by
+                // WFI - unless a BIOS call parked the CPU at the same time
+                let irq_stop = (irq_request.swap(false, Ordering::AcqRel) && !bios.is_parked()) || exception_taken ||
+                    (at_deadline && !bios.is_parked() && !after_wfi(&cpu));
+
+                // anything else that ended emu_start short of WFI means the frame's instructions are all used up
+                let ran = executed.load(Ordering::Relaxed) - executed_before;
+                let out_of_budget = budget != 0 && !stepping && !irq_stop && !pause_signal.load(Ordering::Relaxed) && !bios.is_parked() && !after_wfi(&cpu);
+                credit -= ran as i64;
+
+                // deterministic WFI: no host timer is going to come along & interrupt it, so skip ahead through the frame's
+                // timer deadlines until one of them gets the CPU an interrupt it wants
+                let mut timer_wake = false;
+
+                if let (Some((timebase, clock)), Some(intc)) = (&deterministic, &intc) {
+                    if !stepping && !irq_stop && !out_of_budget && !pause_signal.load(Ordering::Relaxed) && !bios.is_parked() {
+                        while intc.active() == 0 {
+                            let Some(skip) = clock.next_deadline_ns().and_then(|ns| timebase.instructions_until(ns)) else {
+                                break;
+                            };
+
+                            executed.fetch_add(skip, Ordering::Relaxed);
+                            credit -= skip as i64;
+                            clock.poll();
+                        }
+
+                        timer_wake = intc.active() != 0;
+                    }
+                }
+
+                // idle skip does the same against the host-driven timebase, as far as the end of the frame
+                if let (Some((timebase, clock)), Some(intc)) = (&idle_skip, &intc) {
+                    if !stepping && !irq_stop && !out_of_budget && !pause_signal.load(Ordering::Relaxed) && !bios.is_parked() {
+                        while intc.active() == 0 {
+                            let Some(ns) = clock.next_deadline_ns().filter(|ns| *ns < frame_to_ns(timebase.frame() + 1)) else {
+                                break;
+                            };
+
+                            timebase.skip_to(ns);
+                            clock.poll();
+                        }
+
+                        clock.time_skipped();
+                        timer_wake = intc.active() != 0;
+                    }
+                }
+
+                // if we were stopped by a pause request, we're not actually sitting in WFI
+                if !pause_signal.load(Ordering::Relaxed) && !irq_stop && !timer_wake {
+                    if let Some(frame_budget) = &frame_budget {
+                        frame_budget.end_frame();
+                    }
+
+                    idle_signal.set();
+
+                    // a frame step is over once the guest's frame is - it waits here, paused, rather than for the next one
+                    if step_frame.swap(false, Ordering::AcqRel) {
+                        pause_signal.store(true, Ordering::Relaxed);
+                        traps.report(DebugStop::FrameStep { pc: cpu.pc_read().unwrap() as u32 });
+                    }
+
+                    // WFI ends at the frame signal, or when an interrupt line wants the CPU. a BIOS sleep only ends at the frame
+                    // signal(s) it asked for, & each frame slept through still counts as idle for anything waiting on it
+                    let mut frame_wake = false;
+                    let wait_start = chrome_trace.as_ref().map(|trace| trace.now());
+                    let wait_name = if bios.is_parked() { "bios sleep" } else { "wfi" };
+                    idle.store(true, Ordering::Release);
+
+                    while !pause_signal.load(Ordering::Relaxed) && !stop_signal.load(Ordering::Relaxed) {
+                        cpu_signal.wait();
+
+                        if frame_signal.swap(false, Ordering::AcqRel) {
+                            if bios.sleep_more() {
+                                idle_signal.set();
+                                continue;
+                            }
+
+                            frame_wake = true;
+                            break;
+                        }
+
+                        // ...but a CPU that's out of instructions has to wait for the next frame to take it, & in deterministic
+                        // mode anything raised from outside the CPU thread is only taken at the next frame too
+                        if !bios.is_parked() && !out_of_budget && deterministic.is_none() && intc.as_ref().is_some_and(|intc| intc.active() != 0) {
+                            break;
+                        }
+                    }
+
+                    idle.store(false, Ordering::Release);
+                    bios.unpark();
+
+                    if let (Some(trace), Some(start)) = (&chrome_trace, wait_start) {
+                        let woken_by = if frame_wake { "frame" } else if pause_signal.load(Ordering::Relaxed) || stop_signal.load(Ordering::Relaxed) { "pause" } else { "interrupt" };
+                        trace.span(TRACK_CPU, "cpu", wait_name, start, Some(json!({ "woken_by": woken_by })));
+                    }
+
+                    // a frame that ran out of instructions carries what it was left with into the next - whatever the count was
+                    // off by, either way - & an idle one only what it overshot by
+                    if frame_wake && budget != 0 {
+                        credit = budget as i64 + if out_of_budget { credit } else { credit.min(0) };
+                    }
+
+                    // waking for an interrupt mid-frame carries on with the frame that was already running
+                    if let (Some(frame_budget), true) = (&frame_budget, frame_wake) {
+                        frame_budget.begin_frame();
+                    }
+                }
+
+                // park here until resumed (or asked to step)
+                let parked = pause_signal.load(Ordering::Relaxed) && !step_on;
+
+                while pause_signal.load(Ordering::Relaxed) && !stop_signal.load(Ordering::Relaxed) && !step_insn.load(Ordering::Relaxed) {
+                    parked_signal.set();
+                    resume_signal.wait();
+                }
+
+                if stop_signal.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                parked_signal.reset();
+
+                if let Some(intc) = &intc {
+                    let cpsr = cpu.reg_read(RegisterARM::CPSR).unwrap();
+                    let fiq = intc.active_fiq() != 0 && cpsr & CPSR_F == 0;
+
+                    if fiq || (intc.active_irq() != 0 && cpsr & CPSR_I == 0) {
+                        enter_interrupt(&mut cpu, fiq);
+                        exception_stats.record(if fiq { EXCP_FIQ } else { EXCP_IRQ }, None);
+
+                        if let Some(trace) = &chrome_trace {
+                            let lines = if fiq { intc.active_fiq() } else { intc.active_irq() };
+                            trace.instant(TRACK_INTERRUPTS, "cpu", if fiq { "take FIQ" } else { "take IRQ" }, Some(json!({ "lines": line_names(lines) })));
+                        }
+
+                        // whatever asked for it has been dealt with, so the next stop isn't mistaken for one
+                        irq_request.store(false, Ordering::Release);
+                    }
+                }
+
+                pc = resume_addr(&cpu);
+
+                if parked {
+                    traps.skip_once(pc as u32 & !1);
+                }
+            }
+
+            // a thread that's gone (faulted or stopped) is as still as a parked one - nobody should wait on it
+            parked_signal.set();
+        });
+
+        // an interrupt the CPU will want to take kicks it out of emu_start (or WFI), so it gets taken straight away
+        if let Some(intc) = &self.intc {
+            intc.set_notify(Some(Box::new(move || {
+                notify_irq_request.store(true, Ordering::Release);
+
+                // same trick as above - the handle stays owned by the Machine
+                let mut cpu = unsafe { Unicorn::<()>::from_handle(cpu_send as uc_handle).unwrap() };
+                cpu.emu_stop().unwrap();
+                notify_signal.set();
+            })));
+        }
+
+        return MachineRunContext {
+            join_handle,
+            coprocessor: self.coprocessor.as_ref().map(|coprocessor| coprocessor.start()),
+            cpu_handle: cpu_send,
+            cpu_signal: ret_cpu_signal,
+            stop_signal: ret_stop_signal,
+            pause_signal: ret_pause_signal,
+            resume_signal: ret_resume_signal,
+            idle_signal: ret_idle_signal,
+            parked_signal: ret_parked_signal,
+            idle: ret_idle,
+            fault: ret_fault,
+            frame_budget: ret_frame_budget,
+            frame_signal: ret_frame_signal,
+            intc: ret_intc,
+            step_insn: ret_step_insn,
+            step_frame: ret_step_frame,
+            step_until: ret_step_until,
+        };
+    }
+}
+
+impl MachineRunContext {
+    pub fn raise_signal(self: &Self) {
+        if let Some(frame_budget) = &self.frame_budget {
+            frame_budget.frame_signal();
+        }
+
+        self.frame_signal.store(true, Ordering::Release);
+        self.cpu_signal.set();
+    }
+
+    // block until the CPU has finished its frame & is sitting in WFI - for running in lockstep with the frontend.
+    // false if it didn't get there in time (a guest that never waits for interrupts)
+    pub fn wait_idle(self: &Self, timeout: Duration) -> bool {
+        return self.idle_signal.wait_for(timeout);
+    }
+
+    // kick the CPU out of emu_start (or out of WFI) & wait for it to park, so registers & memory hold still for whatever
+    // the caller wants to do with them. false if it didn't park in time - e.g. a host-side handler that's blocked
+    pub fn pause(self: &Self) -> bool {
+        self.step_until.lock().unwrap().take();
+        self.pause_signal.store(true, Ordering::Relaxed);
+        self.cpu().emu_stop().unwrap();
+        self.cpu_signal.set();
+
+        return self.parked_signal.wait_for(PAUSE_TIMEOUT);
+    }
+
+    // carry on from wherever the CPU was parked. a breakpoint it was parked on doesn't stop it again
+    pub fn resume(self: &Self) {
+        // until the CPU thread has woken up & left, a pause() straight after this mustn't take it for parked
+        if !self.join_handle.is_finished() {
+            self.parked_signal.reset();
+        }
+
+        self.step_until.lock().unwrap().take();
+        self.pause_signal.store(false, Ordering::Relaxed);
+        self.resume_signal.set();
+    }
+
+    // paused: run exactly one instruction, then park again (reported as DebugStop::Step)
+    pub fn step_instruction(self: &Self) {
+        if !self.is_paused() {
+            return;
+        }
+
+        // like resume, the CPU isn't parked again until it's done the step
+        self.parked_signal.reset();
+        self.step_insn.store(true, Ordering::Release);
+        self.resume_signal.set();
+    }
+
+    // paused: step an instruction at a time until `done` says the CPU has got where it's going, or `limit` instructions
+    // have gone by, then park again (reported as DebugStop::Step, like a single step). the CPU thread does the stepping,
+    // so this returns straight away - a breakpoint or watchpoint on the way stops it there, & a pause stops it wherever
+    // it's got to
+    pub fn step_until(self: &Self, done: StepDone, limit: u32) {
+        if !self.is_paused() {
+            return;
+        }
+
+        *self.step_until.lock().unwrap() = Some((done, limit));
+        self.step_instruction();
+    }
+
+    // paused: run until the guest next waits for vblank (WFI or a BIOS wait), then park again (DebugStop::FrameStep)
+    pub fn step_frame(self: &Self) {
+        if !self.is_paused() {
+            return;
+        }
+
+        self.step_frame.store(true, Ordering::Release);
+        self.resume();
+    }
+
+    pub fn is_paused(self: &Self) -> bool {
+        return self.pause_signal.load(Ordering::Relaxed);
+    }
+
+    // the guest is waiting for something - nothing happens until the next frame or interrupt
+    pub fn is_idle(self: &Self) -> bool {
+        return self.idle.load(Ordering::Acquire);
+    }
+
+    // why the CPU thread died, if it has - it won't run again until the machine is reset or a state is loaded
+    pub fn fault(self: &Self) -> Option<String> {
+        return self.fault.lock().unwrap().clone();
+    }
+
+    pub fn mem_read(self: &Self, addr: u32, len: usize) -> Result<Vec<u8>, uc_error> {
+        return self.cpu().mem_read_as_vec(addr as u64, len);
+    }
+
+    pub fn mem_write(self: &Self, addr: u32, data: &[u8]) -> Result<(), uc_error> {
+        return self.cpu().mem_write(addr as u64, data);
+    }
+
+    pub fn registers(self: &Self) -> Vec<(&'static str, u32)> {
+        let cpu = self.cpu();
+        return DEBUG_REGS.iter().map(|(name, reg)| (*name, cpu.reg_read(*reg).unwrap() as u32)).collect();
+    }
+
+    pub fn banked_registers(self: &Self) -> Vec<u32> {
+        return read_banked(&mut self.cpu());
+    }
+
+    pub fn stop(self: Self) {
+        if let Some(intc) = &self.intc {
+            intc.set_notify(None);
+        }
+
+        // set the stop signal, interrupt the CPU, & then wait for the thread to exit
+        self.stop_signal.store(true, Ordering::Relaxed);
+        self.cpu_signal.set();
+        self.resume_signal.set();
+        self.join_handle.join().unwrap();
+
+        if let Some(coprocessor) = self.coprocessor {
+            coprocessor.stop();
+        }
+    }
+
+    fn cpu(self: &Self) -> Unicorn<'static, ()> {
+        // same trick as in Machine::run - the underlying handle stays owned by the Machine
+        return unsafe { Unicorn::from_handle(self.cpu_handle as uc_handle).unwrap() };
+    }
+}
+
+// nothing stops the CPU while these read, so what they see can be torn - fine for a post-mortem, not for anything else
+pub struct CpuProbe {
+    cpu_handle: usize,
+}
+
+impl CpuProbe {
+    pub fn registers(self: &Self) -> Vec<(&'static str, u32)> {
+        let cpu = self.cpu();
+        return DEBUG_REGS.iter().map(|(name, reg)| (*name, cpu.reg_read(*reg).unwrap_or(0) as u32)).collect();
+    }
+
+    pub fn mem_read(self: &Self, addr: u32, len: usize) -> Result<Vec<u8>, uc_error> {
+        return self.cpu().mem_read_as_vec(addr as u64, len);
+    }
+
+    fn cpu(self: &Self) -> Unicorn<'static, ()> {
+        return unsafe { Unicorn::from_handle(self.cpu_handle as uc_handle).unwrap() };
+    }
+}
\ No newline at end of file
@@ -0,0 +1,407 @@
+use std::{collections::VecDeque, sync::{Arc, Condvar, Mutex}};
+
+use crate::{intc::IrqLine, peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}};
+
+pub const MAILBOX_MEM_SIZE: u32 = 4096;
+
+// words each side's inbox holds before the sender sees TXFULL
+pub const MAILBOX_FIFO_DEPTH: usize = 16;
+
+pub const MAILBOXSTATUSBIT_RXREADY: u32     = 1;
+pub const MAILBOXSTATUSBIT_TXFULL: u32      = 2;
+
+pub const MAILBOXCOPCTRLBIT_RUN: u32        = 1;
+
+pub const MAILBOXCOPSTATUSBIT_RUNNING: u32  = 1;
+pub const MAILBOXCOPSTATUSBIT_FAULT: u32    = 2;
+
+// which end of the mailbox a port is - both CPUs see it at the same address, each from their own side
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Main,
+    Coprocessor,
+}
+
+impl Side {
+    fn index(self: &Self) -> usize {
+        return match self {
+            Side::Main => 0,
+            Side::Coprocessor => 1,
+        };
+    }
+
+    fn other(self: &Self) -> Side {
+        return match self {
+            Side::Main => Side::Coprocessor,
+            Side::Coprocessor => Side::Main,
+        };
+    }
+}
+
+// how the main CPU & the coprocessor talk to each other. each side has an inbox of words & a set of doorbell bits the
+// other side can ring:
+//
+//   STATUS     (read only) RXREADY (own inbox has mail), TXFULL (the other side's inbox is full)
+//   TX         send a word to the other side - dropped if its inbox is full
+//   RX         take the next word from own inbox (0 when empty)
+//   DOORBELL   reads own pending doorbell bits, writing rings the other side's
+//   ACK        write 1s to clear those bits from own DOORBELL
+//
+// ringing the main CPU's doorbell raises IRQ_MAILBOX. the coprocessor takes no interrupts, but mail or a doorbell wakes
+// it from WFI. the main CPU also starts & stops the coprocessor:
+//
+//   COPCTRL    write RUN to start the coprocessor at COPENTRY with SP = COPSTACK, or 0 to halt it. reads back RUN
+//   COPENTRY   where it starts (bit 0 selects Thumb)
+//   COPSTACK   its stack pointer at start
+//   COPSTATUS  (read only) RUNNING, & FAULT if it stopped on a fault (also raises IRQ_MAILBOX). cleared by a start
+pub struct Mailbox {
+    state: PeripheralLock<MailboxState>,
+    // wakes the coprocessor thread, whether it's waiting to be started or sitting in WFI
+    cop_wake: Condvar,
+    // kicks a running coprocessor out of emu_start - set by the coprocessor while its thread is running
+    cop_stop: Mutex<Option<Box<dyn Fn() + Send + Sync>>>,
+    irq: IrqLine,
+}
+
+#[derive(Default)]
+struct MailboxState {
+    inbox: [VecDeque<u32>;2],
+    doorbell: [u32;2],
+    cop_entry: u32,
+    cop_stack: u32,
+    // what the main CPU last asked for, & whether the coprocessor is actually running
+    cop_run: bool,
+    cop_running: bool,
+    cop_fault: bool,
+    // bumped on every start, so a quick halt & restart isn't missed by a coprocessor that never saw the halt
+    cop_generation: u32,
+    shutdown: bool,
+}
+
+// a coprocessor start: entry point, stack pointer, & which start it was
+pub struct CopStart {
+    pub entry: u32,
+    pub stack: u32,
+    pub generation: u32,
+}
+
+impl Mailbox {
+    pub fn new(irq: IrqLine) -> Self {
+        Self {
+            state: PeripheralLock::new(MailboxState::default()),
+            cop_wake: Condvar::new(),
+            cop_stop: Mutex::new(None),
+            irq,
+        }
+    }
+
+    // one side's view of the registers, to map into that CPU
+    pub fn port(self: &Arc<Self>, side: Side) -> MailboxPort {
+        return MailboxPort {
+            mailbox: self.clone(),
+            side,
+        };
+    }
+
+    pub fn set_cop_stop(self: &Self, stop: Option<Box<dyn Fn() + Send + Sync>>) {
+        *self.cop_stop.lock().unwrap() = stop;
+    }
+
+    // machine reset: empty both inboxes & halt the coprocessor
+    pub fn reset(self: &Self) {
+        let mut state = self.state.lock();
+        *state = MailboxState { shutdown: state.shutdown, ..MailboxState::default() };
+    }
+
+    // coprocessor thread: wait for the main CPU to start it, or None once the machine is stopping
+    pub fn wait_start(self: &Self) -> Option<CopStart> {
+        let mut state = self.state.lock();
+
+        while !state.shutdown && !state.cop_run {
+            state = self.cop_wake.wait(state).unwrap();
+        }
+
+        if state.shutdown {
+            return None;
+        }
+
+        state.cop_running = true;
+
+        return Some(CopStart {
+            entry: state.cop_entry,
+            stack: state.cop_stack,
+            generation: state.cop_generation,
+        });
+    }
+
+    // coprocessor thread, in WFI: wait for mail or a doorbell. false if the coprocessor should stop instead
+    pub fn wait_wake(self: &Self, generation: u32) -> bool {
+        let mut state = self.state.lock();
+
+        loop {
+            if !Self::should_run(&state, generation) {
+                return false;
+            }
+
+            if !state.inbox[Side::Coprocessor.index()].is_empty() || state.doorbell[Side::Coprocessor.index()] != 0 {
+                return true;
+            }
+
+            state = self.cop_wake.wait(state).unwrap();
+        }
+    }
+
+    // coprocessor thread: whether the start it's running is still wanted
+    pub fn cop_should_run(self: &Self, generation: u32) -> bool {
+        return Self::should_run(&self.state.lock(), generation);
+    }
+
+    fn should_run(state: &MailboxState, generation: u32) -> bool {
+        return !state.shutdown && state.cop_run && state.cop_generation == generation;
+    }
+
+    // coprocessor thread: it's stopped, because it was halted or it faulted
+    pub fn cop_stopped(self: &Self, generation: u32, fault: bool) {
+        let mut state = self.state.lock();
+
+        // a start that came in meanwhile stands
+        if state.cop_generation == generation {
+            state.cop_run = false;
+        }
+
+        state.cop_running = false;
+        state.cop_fault = fault;
+        drop(state);
+
+        if fault {
+            self.irq.raise();
+        }
+    }
+
+    // the machine is stopping: the coprocessor thread should exit
+    pub fn shutdown(self: &Self) {
+        self.state.lock().shutdown = true;
+        self.wake_cop(true);
+    }
+
+    // ...& it's running again
+    pub fn restart(self: &Self) {
+        self.state.lock().shutdown = false;
+    }
+
+    // a coprocessor in the middle of emu_start only needs stopping if it's being halted - mail can wait for it to get round
+    // to checking
+    fn wake_cop(self: &Self, stop: bool) {
+        self.cop_wake.notify_all();
+
+        if let (Some(stop), true) = (self.cop_stop.lock().unwrap().as_ref(), stop) {
+            stop();
+        }
+    }
+
+    // only the mailbox's own state - the coprocessor's registers aren't captured, so it comes back from a load halted, as if
+    // after a reset, & the main CPU has to start it again
+    fn save_state(self: &Self) -> Vec<u8> {
+        let state = self.state.lock();
+        let mut w = StateWriter::new();
+
+        for side in [Side::Main, Side::Coprocessor] {
+            w.words(&state.inbox[side.index()].iter().copied().collect::<Vec<_>>());
+            w.u32(state.doorbell[side.index()]);
+        }
+
+        w.u32(state.cop_entry);
+        w.u32(state.cop_stack);
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let mut inbox: [VecDeque<u32>;2] = Default::default();
+        let mut doorbell = [0;2];
+
+        for side in [Side::Main, Side::Coprocessor] {
+            let words = r.words()?;
+
+            if words.len() > MAILBOX_FIFO_DEPTH {
+                return Err(format!("{} words queued in a {} word inbox", words.len(), MAILBOX_FIFO_DEPTH));
+            }
+
+            inbox[side.index()] = words.into();
+            doorbell[side.index()] = r.u32()?;
+        }
+
+        let cop_entry = r.u32()?;
+        let cop_stack = r.u32()?;
+        r.finish()?;
+
+        let mut state = self.state.lock();
+        let halt = state.cop_run;
+
+        *state = MailboxState {
+            inbox,
+            doorbell,
+            cop_entry,
+            cop_stack,
+            // a coprocessor that's still running a start from before the load should notice it's no longer wanted
+            cop_generation: state.cop_generation.wrapping_add(1),
+            cop_running: state.cop_running,
+            shutdown: state.shutdown,
+            ..MailboxState::default()
+        };
+        drop(state);
+
+        self.wake_cop(halt);
+        return Ok(());
+    }
+
+    fn read(self: &Self, side: Side, addr: u32) -> u32 {
+        let mut state = self.state.lock();
+        let own = side.index();
+
+        match addr {
+            0x00 => {
+                // STATUS
+                return
+                    if !state.inbox[own].is_empty() { MAILBOXSTATUSBIT_RXREADY } else { 0 } |
+                    if state.inbox[side.other().index()].len() >= MAILBOX_FIFO_DEPTH { MAILBOXSTATUSBIT_TXFULL } else { 0 };
+            }
+            0x02 => {
+                // RX
+                return state.inbox[own].pop_front().unwrap_or(0);
+            }
+            0x03 => {
+                // DOORBELL
+                return state.doorbell[own];
+            }
+            0x08 if side == Side::Main => {
+                // COPCTRL
+                return if state.cop_run { MAILBOXCOPCTRLBIT_RUN } else { 0 };
+            }
+            0x09 if side == Side::Main => {
+                // COPENTRY
+                return state.cop_entry;
+            }
+            0x0A if side == Side::Main => {
+                // COPSTACK
+                return state.cop_stack;
+            }
+            0x0B if side == Side::Main => {
+                // COPSTATUS
+                return
+                    if state.cop_running { MAILBOXCOPSTATUSBIT_RUNNING } else { 0 } |
+                    if state.cop_fault { MAILBOXCOPSTATUSBIT_FAULT } else { 0 };
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, side: Side, addr: u32, val: u32) {
+        let mut state = self.state.lock();
+        let other = side.other().index();
+
+        match addr {
+            0x01 => {
+                // TX
+                if state.inbox[other].len() < MAILBOX_FIFO_DEPTH {
+                    state.inbox[other].push_back(val);
+                }
+            }
+            0x03 => {
+                // DOORBELL
+                state.doorbell[other] |= val;
+
+                if side == Side::Coprocessor && val != 0 {
+                    drop(state);
+                    self.irq.raise();
+                    return;
+                }
+            }
+            0x04 => {
+                // ACK
+                state.doorbell[side.index()] &= !val;
+                return;
+            }
+            0x08 if side == Side::Main => {
+                // COPCTRL
+                let run = val & MAILBOXCOPCTRLBIT_RUN != 0;
+
+                if run && !state.cop_run {
+                    state.cop_generation = state.cop_generation.wrapping_add(1);
+                    state.cop_fault = false;
+                }
+
+                let halt = state.cop_run && !run;
+                state.cop_run = run;
+                drop(state);
+
+                self.wake_cop(halt);
+                return;
+            }
+            0x09 if side == Side::Main => {
+                // COPENTRY
+                state.cop_entry = val;
+                return;
+            }
+            0x0A if side == Side::Main => {
+                // COPSTACK
+                state.cop_stack = val;
+                return;
+            }
+            _ => {
+                return;
+            }
+        }
+
+        // mail or a doorbell the coprocessor might be waiting on
+        if side == Side::Main {
+            drop(state);
+            self.wake_cop(false);
+        }
+    }
+}
+
+pub struct MailboxPort {
+    mailbox: Arc<Mailbox>,
+    side: Side,
+}
+
+impl Peripheral for MailboxPort {
+    fn read(self: &Self, addr: u32) -> u32 {
+        return self.mailbox.read(self.side, addr);
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        self.mailbox.write(self.side, addr, val);
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        // both ports share the one lock, so only report it once
+        if self.side != Side::Main {
+            return Vec::new();
+        }
+
+        return vec![("mailbox", self.mailbox.state.stats())];
+    }
+
+    // likewise, the mailbox is saved once, from the main CPU's side
+    fn save_state(self: &Self) -> Vec<u8> {
+        if self.side != Side::Main {
+            return Vec::new();
+        }
+
+        return self.mailbox.save_state();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        if self.side != Side::Main {
+            return Ok(());
+        }
+
+        return self.mailbox.load_state(data);
+    }
+}
+
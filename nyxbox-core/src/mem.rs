@@ -0,0 +1,346 @@
+use std::{fs, path::Path};
+
+// 4MiB boot ROM
+pub const BOOT_ROM_SIZE: usize = 4 * 1024 * 1024;
+
+// 16MiB main ram
+pub const MAIN_RAM_SIZE: usize = 16 * 1024 * 1024;
+
+// optional 16MiB expansion ram - only mapped when enabled, so anything touching it on a base machine faults
+pub const EXPANSION_RAM_SIZE: usize = 16 * 1024 * 1024;
+
+pub const BOOT_ROM_BEGIN: usize = 0x0000000;
+// pub const BOOT_ROM_END: usize = BOOT_ROM_BEGIN + (BOOT_ROM_SIZE - 1);
+
+pub const MAIN_RAM_BEGIN: usize = 0x1000000;
+// pub const MAIN_RAM_END: usize = MAIN_RAM_BEGIN + (MAIN_RAM_SIZE - 1);
+
+pub const EXPANSION_RAM_BEGIN: usize = 0x2000000;
+
+pub const UART_BEGIN: usize = 0x6000000;
+pub const VDP_BEGIN: usize = 0x7000000;
+pub const CLOCK_BEGIN: usize = 0x8000000;
+pub const SYSINFO_BEGIN: usize = 0x9000000;
+pub const DEBUGPORT_BEGIN: usize = 0xA000000;
+pub const MPU_BEGIN: usize = 0xB000000;
+pub const FRAMEBUDGET_BEGIN: usize = 0xC000000;
+pub const GAMEPAD_BEGIN: usize = 0xD000000;
+pub const POISON_BEGIN: usize = 0xE000000;
+pub const INTC_BEGIN: usize = 0xF000000;
+pub const BUSERR_BEGIN: usize = 0x10000000;
+pub const MAILBOX_BEGIN: usize = 0x11000000;
+pub const WATCHDOG_BEGIN: usize = 0x12000000;
+
+// peripherals each get a 16MiB slot (their registers are addressed within it)
+pub const MMIO_SLOT_SIZE: usize = 16 * 1024 * 1024;
+
+// VRAM isn't visible to the CPU - this base is only used by host-side tools which want to address it alongside the rest of memory
+pub const VRAM_DEBUG_BEGIN: usize = 0x80000000;
+
+// memory is mapped in whole pages
+pub const PAGE_SIZE: usize = 4096;
+
+// the constants above are the standard machine's layout. a memory map file can resize memory & move peripherals around, so
+// anything that depends on the layout goes by the machine's map (System::map) rather than using them directly. the file is
+// the subset of TOML toml.rs reads, with any of:
+//
+//   [boot_rom]         size
+//   [main_ram]         base, size
+//   [expansion_ram]    base, size
+//   [peripherals]      uart, vdp, clock, sysinfo, debugport, mpu, framebudget, gamepad, poison, intc, buserr, mailbox,
+//                      watchdog - each a base address
+//
+// anything left out keeps its standard value. boot ROM can't move, as the CPU's reset & exception vectors are at 0
+#[derive(Clone, Copy, PartialEq)]
+pub struct MemoryMap {
+    pub boot_rom_size: usize,
+    pub main_ram_begin: usize,
+    pub main_ram_size: usize,
+    pub expansion_ram_begin: usize,
+    pub expansion_ram_size: usize,
+    pub uart_begin: usize,
+    pub vdp_begin: usize,
+    pub clock_begin: usize,
+    pub sysinfo_begin: usize,
+    pub debugport_begin: usize,
+    pub mpu_begin: usize,
+    pub framebudget_begin: usize,
+    pub gamepad_begin: usize,
+    pub poison_begin: usize,
+    pub intc_begin: usize,
+    pub buserr_begin: usize,
+    pub mailbox_begin: usize,
+    pub watchdog_begin: usize,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self {
+            boot_rom_size: BOOT_ROM_SIZE,
+            main_ram_begin: MAIN_RAM_BEGIN,
+            main_ram_size: MAIN_RAM_SIZE,
+            expansion_ram_begin: EXPANSION_RAM_BEGIN,
+            expansion_ram_size: EXPANSION_RAM_SIZE,
+            uart_begin: UART_BEGIN,
+            vdp_begin: VDP_BEGIN,
+            clock_begin: CLOCK_BEGIN,
+            sysinfo_begin: SYSINFO_BEGIN,
+            debugport_begin: DEBUGPORT_BEGIN,
+            mpu_begin: MPU_BEGIN,
+            framebudget_begin: FRAMEBUDGET_BEGIN,
+            gamepad_begin: GAMEPAD_BEGIN,
+            poison_begin: POISON_BEGIN,
+            intc_begin: INTC_BEGIN,
+            buserr_begin: BUSERR_BEGIN,
+            mailbox_begin: MAILBOX_BEGIN,
+            watchdog_begin: WATCHDOG_BEGIN,
+        }
+    }
+}
+
+impl MemoryMap {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        return Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    // a memory map file: the standard layout, with whatever the file sets moved or resized
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut map = Self::default();
+
+        for (table, keys) in &doc {
+            let Some(keys) = keys.as_table() else {
+                return Err(format!("{} isn't part of the memory map", table));
+            };
+
+            for (key, value) in keys {
+                let Some(field) = map.field(table, key) else {
+                    return Err(format!("{}.{} isn't part of the memory map", table, key));
+                };
+
+                let Some(val) = value.as_integer() else {
+                    return Err(format!("{}.{} has to be an integer, not of type {}", table, key, value.type_str()));
+                };
+
+                if val < 0 || val > u32::MAX as i64 {
+                    return Err(format!("{}.{} is outside the 32-bit address space", table, key));
+                }
+
+                *field = val as usize;
+            }
+        }
+
+        map.validate()?;
+        return Ok(map);
+    }
+
+    // no single read or watch can usefully cover more than this - anything longer runs off the end of memory
+    pub fn largest_region(self: &Self) -> usize {
+        return self.boot_rom_size.max(self.main_ram_size).max(self.expansion_ram_size);
+    }
+
+    fn field(self: &mut Self, table: &str, key: &str) -> Option<&mut usize> {
+        return match (table, key) {
+            ("boot_rom", "size") => Some(&mut self.boot_rom_size),
+            ("main_ram", "base") => Some(&mut self.main_ram_begin),
+            ("main_ram", "size") => Some(&mut self.main_ram_size),
+            ("expansion_ram", "base") => Some(&mut self.expansion_ram_begin),
+            ("expansion_ram", "size") => Some(&mut self.expansion_ram_size),
+            ("peripherals", name) => self.peripherals_mut().into_iter().find(|(n, _)| *n == name).map(|(_, base)| base),
+            _ => None,
+        };
+    }
+
+    pub fn peripherals(self: &Self) -> [(&'static str, usize);13] {
+        return [
+            ("uart", self.uart_begin), ("vdp", self.vdp_begin), ("clock", self.clock_begin), ("sysinfo", self.sysinfo_begin),
+            ("debugport", self.debugport_begin), ("mpu", self.mpu_begin), ("framebudget", self.framebudget_begin),
+            ("gamepad", self.gamepad_begin), ("poison", self.poison_begin), ("intc", self.intc_begin),
+            ("buserr", self.buserr_begin), ("mailbox", self.mailbox_begin), ("watchdog", self.watchdog_begin),
+        ];
+    }
+
+    fn peripherals_mut(self: &mut Self) -> [(&'static str, &mut usize);13] {
+        return [
+            ("uart", &mut self.uart_begin), ("vdp", &mut self.vdp_begin), ("clock", &mut self.clock_begin), ("sysinfo", &mut self.sysinfo_begin),
+            ("debugport", &mut self.debugport_begin), ("mpu", &mut self.mpu_begin), ("framebudget", &mut self.framebudget_begin),
+            ("gamepad", &mut self.gamepad_begin), ("poison", &mut self.poison_begin), ("intc", &mut self.intc_begin),
+            ("buserr", &mut self.buserr_begin), ("mailbox", &mut self.mailbox_begin), ("watchdog", &mut self.watchdog_begin),
+        ];
+    }
+
+    // everything has to be page aligned, peripherals slot aligned, below the VRAM debug window, & clear of everything else
+    pub fn validate(self: &Self) -> Result<(), String> {
+        let mut ranges = vec![
+            ("boot_rom", BOOT_ROM_BEGIN, self.boot_rom_size),
+            ("main_ram", self.main_ram_begin, self.main_ram_size),
+            ("expansion_ram", self.expansion_ram_begin, self.expansion_ram_size),
+        ];
+
+        for (name, base, size) in &ranges {
+            if *size == 0 || base % PAGE_SIZE != 0 || size % PAGE_SIZE != 0 {
+                return Err(format!("{} ({:08x}+{:x}) has to be a whole number of {} byte pages", name, base, size, PAGE_SIZE));
+            }
+        }
+
+        for (name, base) in self.peripherals() {
+            if base % MMIO_SLOT_SIZE != 0 {
+                return Err(format!("peripherals.{} ({:08x}) has to be a multiple of {:x}", name, base, MMIO_SLOT_SIZE));
+            }
+
+            ranges.push((name, base, MMIO_SLOT_SIZE));
+        }
+
+        for (idx, (name, base, size)) in ranges.iter().enumerate() {
+            if base + size > VRAM_DEBUG_BEGIN {
+                return Err(format!("{} ({:08x}-{:08x}) runs into the VRAM debug window at {:08x}", name, base, base + size - 1, VRAM_DEBUG_BEGIN));
+            }
+
+            if let Some((other, other_base, other_size)) = ranges[..idx].iter().find(|(_, other_base, other_size)| base < &(other_base + other_size) && other_base < &(base + size)) {
+                return Err(format!("{} ({:08x}-{:08x}) overlaps {} ({:08x}-{:08x})", name, base, base + size - 1, other, other_base, other_base + other_size - 1));
+            }
+        }
+
+        return Ok(());
+    }
+
+    // whether start..start+len falls entirely within one memory region (expansion RAM counts, fitted or not - writing to
+    // it when it isn't fails then)
+    pub fn in_memory(self: &Self, start: usize, len: usize) -> bool {
+        let regions = [
+            (BOOT_ROM_BEGIN, self.boot_rom_size),
+            (self.main_ram_begin, self.main_ram_size),
+            (self.expansion_ram_begin, self.expansion_ram_size),
+        ];
+
+        return regions.into_iter().any(|(base, size)| start >= base && start + len <= base + size);
+    }
+
+    // the end of whichever memory region is highest - code can run anywhere below it
+    pub fn memory_end(self: &Self) -> usize {
+        return (BOOT_ROM_BEGIN + self.boot_rom_size).max(self.main_ram_begin + self.main_ram_size).max(self.expansion_ram_begin + self.expansion_ram_size);
+    }
+
+    // the span the peripheral slots cover
+    pub fn mmio_range(self: &Self) -> (usize, usize) {
+        let bases = self.peripherals().map(|(_, base)| base);
+        return (*bases.iter().min().unwrap(), bases.iter().max().unwrap() + MMIO_SLOT_SIZE);
+    }
+
+    // for config hashes: empty for the standard peripheral layout, so a moved peripheral changes the hash but nothing else does
+    pub fn mmio_desc(self: &Self) -> String {
+        let standard = Self::default();
+
+        return self.peripherals().iter().zip(standard.peripherals())
+            .filter(|(ours, theirs)| ours.1 != theirs.1)
+            .map(|((name, base), _)| format!(";{}@{:x}", name, base))
+            .collect();
+    }
+}
+
+// a memory size for the command line: bytes (decimal or 0x hex), or a whole number of KiB/MiB with a K/M suffix
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let (digits, scale) = match s.strip_suffix(['K', 'k']) {
+        Some(digits) => (digits, 1024),
+        None => match s.strip_suffix(['M', 'm']) {
+            Some(digits) => (digits, 1024 * 1024),
+            None => (s, 1),
+        },
+    };
+
+    let res = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        usize::from_str_radix(hex, 16)
+    }
+    else {
+        digits.parse::<usize>()
+    };
+
+    let val = res.map_err(|e| format!("invalid size '{}': {}", s, e))?;
+    return val.checked_mul(scale).filter(|val| *val <= u32::MAX as usize).ok_or(format!("invalid size '{}': too large", s));
+}
+
+pub struct Memory {
+    pub boot_rom: Box<[u8]>,
+    pub main_ram: Box<[u8]>,
+    pub expansion_ram: Option<Box<[u8]>>,
+}
+
+impl Memory {
+    pub fn new(map: &MemoryMap, expansion: bool) -> Self {
+        Self {
+            boot_rom: vec![0;map.boot_rom_size].into_boxed_slice(),
+            main_ram: vec![0;map.main_ram_size].into_boxed_slice(),
+            expansion_ram: if expansion { Some(vec![0;map.expansion_ram_size].into_boxed_slice()) } else { None },
+        }
+    }
+
+    // total RAM visible to the guest
+    pub fn ram_size(self: &Self) -> usize {
+        return self.main_ram.len() + self.expansion_ram.as_ref().map_or(0, |ram| ram.len());
+    }
+
+    /*pub fn load_bootrom<T: Copy>(self: &Self, addr: u32) -> T {
+        let ptr: *const u8 = &self.boot_rom[(addr as usize) % BOOT_ROM_SIZE];
+        let ptr_t = ptr.cast::<T>();
+        unsafe { return *ptr_t; }
+    }
+
+    pub fn store_bootrom<T: Copy>(self: &mut Self, addr: u32, val: T) {
+        let ptr: *mut u8 = &mut self.boot_rom[(addr as usize) % BOOT_ROM_SIZE];
+        let ptr_t = ptr.cast::<T>();
+        unsafe { *ptr_t = val; }
+    }
+
+    pub fn load_main<T: Copy>(self: &Self, addr: u32) -> T {
+        let ptr: *const u8 = &self.main_ram[(addr as usize) % MAIN_RAM_SIZE];
+        let ptr_t = ptr.cast::<T>();
+        unsafe { return *ptr_t; }
+    }
+
+    pub fn store_main<T: Copy>(self: &mut Self, addr: u32, val: T) {
+        let ptr: *mut u8 = &mut self.main_ram[(addr as usize) % MAIN_RAM_SIZE];
+        let ptr_t = ptr.cast::<T>();
+        unsafe { *ptr_t = val; }
+    }*/
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_map_file_overrides_the_standard_layout() {
+        let map = MemoryMap::parse("
+            # a bigger main RAM, with expansion RAM moved out of its way, & the UART moved to a free slot
+            [main_ram]
+            size = 0x0200_0000
+
+            [expansion_ram]
+            base = 0x0400_0000
+
+            [peripherals]
+            uart = 0x1300_0000
+        ").unwrap();
+
+        assert_eq!(map.main_ram_size, 0x2000000);
+        assert_eq!(map.expansion_ram_begin, 0x4000000);
+        assert_eq!(map.uart_begin, 0x13000000);
+        assert_eq!(map.boot_rom_size, MemoryMap::default().boot_rom_size);
+    }
+
+    #[test]
+    fn memory_map_file_errors() {
+        for (text, expected) in [
+            ("[main_ram]\nsize = 0x2000000\nsize = 1", "duplicate key"),
+            ("[main_ram]\nbase = \"low\"", "main_ram.base has to be an integer, not of type string"),
+            ("[main_ram]\nwidth = 4", "main_ram.width isn't part of the memory map"),
+            ("size = 4", "size isn't part of the memory map"),
+            ("[peripherals]\nuart = -1", "peripherals.uart is outside the 32-bit address space"),
+            ("[peripherals]\nuart = 0x01000000", "overlaps main_ram"),
+        ] {
+            let err = MemoryMap::parse(text).err().unwrap_or_else(|| panic!("{:?} parsed", text));
+            assert!(err.contains(expected), "{:?}: {}", text, err);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::movie::frame_seed;
+
+// what RAM & VRAM hold at power-on. real DRAM comes up full of junk, so guest code that reads memory it never wrote can work
+// fine on a zeroed emulator & then break on hardware - the non-zero fills are there to shake that out. the random fills
+// derive from the machine seed, so they're reproducible with --seed (& during movie playback)
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MemoryFill {
+    /// All zero bytes
+    #[default]
+    Zero,
+    /// All 0xFF bytes
+    Ones,
+    /// Uniformly random bytes
+    Random,
+    /// Looks like DRAM straight after power-on: long runs of 0x00 or 0xFF with scattered flipped bits
+    Garbage,
+}
+
+impl MemoryFill {
+    // the name it has on the command line, for movie config hashes
+    pub fn name(self: &Self) -> &'static str {
+        return match self {
+            MemoryFill::Zero => "zero",
+            MemoryFill::Ones => "ones",
+            MemoryFill::Random => "random",
+            MemoryFill::Garbage => "garbage",
+        };
+    }
+}
+
+// bytes per run of 0x00/0xFF in the garbage fill
+const GARBAGE_RUN: usize = 256;
+
+// fill buf with the pattern. stream picks an independent random sequence, so different memories don't hold the same junk
+pub fn fill(pattern: MemoryFill, seed: u64, stream: u64, buf: &mut [u8]) {
+    let seed = seed ^ stream.wrapping_mul(0xD1B54A32D192ED03);
+
+    match pattern {
+        MemoryFill::Zero => {
+            buf.fill(0);
+        }
+        MemoryFill::Ones => {
+            buf.fill(0xFF);
+        }
+        MemoryFill::Random => {
+            for (idx, chunk) in buf.chunks_mut(8).enumerate() {
+                let bytes = frame_seed(seed, idx as u64).to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+        MemoryFill::Garbage => {
+            for (idx, chunk) in buf.chunks_mut(8).enumerate() {
+                let run = (idx * 8 / GARBAGE_RUN) as u64;
+                let base = if frame_seed(!seed, run) & 1 != 0 { u64::MAX } else { 0 };
+
+                // AND-ing four random words leaves each bit set with 1/16 odds - a few flips per word
+                let a = frame_seed(seed, idx as u64 * 2);
+                let b = frame_seed(seed, idx as u64 * 2 + 1);
+                let flips = a & b & a.rotate_left(32) & b.rotate_left(32);
+
+                let bytes = (base ^ flips).to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::{fs, io, path::Path};
+
+use crate::{machine::CpuModel, mem::{MemoryMap, BOOT_ROM_BEGIN}, memfill::MemoryFill, pacing::TIMESTEP, storage::fnv1a};
+
+// movie file layout: magic, version, metadata, then input events until EOF, all little endian:
+//   metadata: [emulator version len: u32][emulator version][config hash: u32][ROM hash: u32][seed: u64][RTC start: i64]
+//   event:    [frame: u64][kind: u8][len: u32][data]
+pub const MOVIE_MAGIC: &[u8;4] = b"NYXM";
+pub const MOVIE_VERSION: u32 = 2;
+
+pub const MOVIEEVENT_UART_INPUT: u8 = 1;
+// gamepad button state (u32 LE) as latched from this frame on - only written when it changes
+pub const MOVIEEVENT_PAD: u8 = 2;
+// no data - marks the frame the recording stopped at
+pub const MOVIEEVENT_END: u8 = 3;
+
+// everything that has to match for a recording to play back the same way
+#[derive(Clone, PartialEq)]
+pub struct MovieMeta {
+    pub emulator_version: String,
+    pub config_hash: u32,
+    pub rom_hash: u32,
+    pub seed: u64,
+    // the guest's RTC at emulated time zero (Clock::rtc_start). version 1 movies didn't keep it, & play back with whatever
+    // the RTC would otherwise start at
+    pub rtc_start: Option<i64>,
+}
+
+pub struct MovieEvent {
+    pub frame: u64,
+    pub kind: u8,
+    pub data: Vec<u8>,
+}
+
+pub struct Movie {
+    pub meta: MovieMeta,
+    events: Vec<MovieEvent>,
+    next: usize,
+}
+
+impl MovieMeta {
+    pub fn new(rom: &[u8], features: u32, cpu: MovieCpu, boot_fill: MemoryFill, map: &MemoryMap, seed: u64) -> Self {
+        Self {
+            emulator_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: config_hash(features, cpu, boot_fill, map),
+            rom_hash: fnv1a(rom),
+            seed,
+            rtc_start: None,
+        }
+    }
+
+    // a movie is only useful if it plays back against the same emulator, machine, & game it was recorded with - say exactly what differs
+    pub fn check_playback(self: &Self, current: &MovieMeta) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if self.emulator_version != current.emulator_version {
+            problems.push(format!("recorded with NyxBox {}, this is NyxBox {}", self.emulator_version, current.emulator_version));
+        }
+
+        if self.config_hash != current.config_hash {
+            problems.push(format!("recorded with a different machine configuration ({:08x}, current {:08x})", self.config_hash, current.config_hash));
+        }
+
+        if self.rom_hash != current.rom_hash {
+            problems.push(format!("recorded against a different ROM ({:08x}, loaded ROM is {:08x})", self.rom_hash, current.rom_hash));
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        return Err(format!("refusing to play movie, it would desync:\n  {}", problems.join("\n  ")));
+    }
+}
+
+impl Movie {
+    pub fn new(meta: MovieMeta) -> Self {
+        Self {
+            meta,
+            events: Vec::new(),
+            next: 0,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        return Self::from_bytes(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+
+    pub fn save(self: &Self, path: &Path) -> io::Result<()> {
+        return fs::write(path, self.to_bytes());
+    }
+
+    pub fn record(self: &mut Self, frame: u64, kind: u8, data: &[u8]) {
+        self.events.push(MovieEvent { frame, kind, data: data.to_vec() });
+    }
+
+    // next event due at or before the given frame, in recording order
+    pub fn next_event(self: &mut Self, frame: u64) -> Option<&MovieEvent> {
+        let ev = self.events.get(self.next).filter(|ev| ev.frame <= frame)?;
+        self.next += 1;
+        return Some(ev);
+    }
+
+    // frames the recording covers. movies recorded before MOVIEEVENT_END existed end at their last input event
+    pub fn length(self: &Self) -> u64 {
+        return self.events.iter().rev()
+            .find(|ev| ev.kind == MOVIEEVENT_END)
+            .or(self.events.last())
+            .map(|ev| ev.frame)
+            .unwrap_or(0);
+    }
+
+    pub fn to_bytes(self: &Self) -> Vec<u8> {
+        let mut out = MOVIE_MAGIC.to_vec();
+        out.extend_from_slice(&MOVIE_VERSION.to_le_bytes());
+
+        out.extend_from_slice(&(self.meta.emulator_version.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.meta.emulator_version.as_bytes());
+        out.extend_from_slice(&self.meta.config_hash.to_le_bytes());
+        out.extend_from_slice(&self.meta.rom_hash.to_le_bytes());
+        out.extend_from_slice(&self.meta.seed.to_le_bytes());
+        out.extend_from_slice(&self.meta.rtc_start.unwrap_or(0).to_le_bytes());
+
+        for ev in &self.events {
+            out.extend_from_slice(&ev.frame.to_le_bytes());
+            out.push(ev.kind);
+            out.extend_from_slice(&(ev.data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&ev.data);
+        }
+
+        return out;
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 8 || &data[0..4] != MOVIE_MAGIC {
+            return Err("not a NyxBox movie".to_string());
+        }
+
+        let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        if version > MOVIE_VERSION {
+            return Err(format!("movie version {} is newer than this build supports ({})", version, MOVIE_VERSION));
+        }
+
+        let mut pos = 8;
+        let truncated = || "movie is truncated".to_string();
+
+        let ver_len = u32::from_le_bytes(data.get(pos..pos + 4).ok_or_else(truncated)?.try_into().unwrap()) as usize;
+        pos += 4;
+        let emulator_version = String::from_utf8_lossy(data.get(pos..pos + ver_len).ok_or_else(truncated)?).into_owned();
+        pos += ver_len;
+
+        let fixed_len = if version >= 2 { 24 } else { 16 };
+        let fixed = data.get(pos..pos + fixed_len).ok_or_else(truncated)?;
+        let meta = MovieMeta {
+            emulator_version,
+            config_hash: u32::from_le_bytes(fixed[0..4].try_into().unwrap()),
+            rom_hash: u32::from_le_bytes(fixed[4..8].try_into().unwrap()),
+            seed: u64::from_le_bytes(fixed[8..16].try_into().unwrap()),
+            rtc_start: fixed.get(16..24).map(|rtc| i64::from_le_bytes(rtc.try_into().unwrap())),
+        };
+        pos += fixed_len;
+
+        let mut events = Vec::new();
+
+        while pos < data.len() {
+            let hdr = data.get(pos..pos + 13).ok_or_else(truncated)?;
+            let frame = u64::from_le_bytes(hdr[0..8].try_into().unwrap());
+            let kind = hdr[8];
+            let len = u32::from_le_bytes(hdr[9..13].try_into().unwrap()) as usize;
+            pos += 13;
+
+            let ev_data = data.get(pos..pos + len).ok_or_else(truncated)?;
+            pos += len;
+
+            events.push(MovieEvent { frame, kind, data: ev_data.to_vec() });
+        }
+
+        return Ok(Self {
+            meta,
+            events,
+            next: 0,
+        });
+    }
+}
+
+// the CPU settings a recording depends on: which core ran it, & how many instructions it got per frame (--cpu-budget or
+// --cpu-clock) - a different budget moves every input relative to the guest's code
+#[derive(Clone, Copy)]
+pub struct MovieCpu {
+    pub model: CpuModel,
+    pub budget: u64,
+}
+
+// identifies the parts of the machine which affect how a guest runs. the boot fill is in here because the fills that
+// aren't zero put different bytes in memory the guest hasn't written yet
+pub fn config_hash(features: u32, cpu: MovieCpu, boot_fill: MemoryFill, map: &MemoryMap) -> u32 {
+    let desc = format!("rom={:x}@{:x};ram={:x}@{:x};features={:x};timestep={};cpu={};budget={};fill={}{}",
+        map.boot_rom_size, BOOT_ROM_BEGIN, map.main_ram_size, map.main_ram_begin, features, TIMESTEP,
+        cpu.model.name(), cpu.budget, boot_fill.name(), map.mmio_desc());
+
+    return fnv1a(desc.as_bytes());
+}
+
+// splitmix64 - cheap, & good enough to turn (seed, frame) into an unrelated-looking value
+pub fn frame_seed(seed: u64, frame: u64) -> u64 {
+    let mut z = seed.wrapping_add(frame.wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
@@ -0,0 +1,275 @@
+use unicorn_engine::{MemType, Permission, Unicorn};
+
+use crate::{peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}};
+
+pub const MPU_MEM_SIZE: u32 = 4096;
+
+pub const MPU_REGION_COUNT: usize = 8;
+
+// regions are enforced with Unicorn page permissions, so everything is rounded out to whole pages
+pub const MPU_PAGE_SIZE: u32 = 4096;
+
+pub const MPUCTRLBIT_ENABLE: u32        = 1;
+
+pub const MPUATTRBIT_READ: u32          = 1;
+pub const MPUATTRBIT_WRITE: u32         = 2;
+pub const MPUATTRBIT_EXEC: u32          = 4;
+pub const MPUATTRBIT_ENABLE: u32        = 0x80000000;
+
+pub const MPUFAULTBIT_READ: u32         = 1;
+pub const MPUFAULTBIT_WRITE: u32        = 2;
+pub const MPUFAULTBIT_FETCH: u32        = 4;
+
+#[derive(Clone, Copy, Default)]
+struct MpuRegion {
+    base: u32,
+    size: u32,
+    attr: u32,
+}
+
+// a memory range the MPU is allowed to restrict, along with the permissions it was originally mapped with
+struct MpuArea {
+    base: u32,
+    size: u32,
+    perms: Permission,
+}
+
+// MPU-style protection for kernels that don't want full paging - regions overlay the default memory map, & higher numbered regions win where they overlap
+pub struct Mpu {
+    state: PeripheralLock<MpuState>,
+}
+
+struct MpuState {
+    enable: bool,
+    regions: [MpuRegion;MPU_REGION_COUNT],
+    areas: Vec<MpuArea>,
+    dirty: bool,
+    fault_addr: u32,
+    fault_status: u32,
+}
+
+impl MpuRegion {
+    fn perms(self: &Self) -> Permission {
+        let mut perms = Permission::NONE;
+
+        if self.attr & MPUATTRBIT_READ != 0 {
+            perms |= Permission::READ;
+        }
+
+        if self.attr & MPUATTRBIT_WRITE != 0 {
+            perms |= Permission::WRITE;
+        }
+
+        if self.attr & MPUATTRBIT_EXEC != 0 {
+            perms |= Permission::EXEC;
+        }
+
+        return perms;
+    }
+}
+
+impl Mpu {
+    pub fn new() -> Self {
+        Self {
+            state: PeripheralLock::new(MpuState::new()),
+        }
+    }
+
+    pub fn add_area(self: &Self, base: u32, size: u32, perms: Permission) {
+        self.state.lock().areas.push(MpuArea { base, size, perms });
+    }
+
+    pub fn record_fault(self: &Self, mem_type: MemType, addr: u64) {
+        let mut state = self.state.lock();
+
+        state.fault_addr = addr as u32;
+        state.fault_status |= match mem_type {
+            MemType::READ_PROT => MPUFAULTBIT_READ,
+            MemType::WRITE_PROT => MPUFAULTBIT_WRITE,
+            MemType::FETCH_PROT => MPUFAULTBIT_FETCH,
+            _ => 0,
+        };
+    }
+
+    // called from the MMIO write hook, after the register write has landed
+    pub fn apply_if_dirty<D>(self: &Self, uc: &mut Unicorn<D>) {
+        let mut state = self.state.lock();
+
+        if std::mem::replace(&mut state.dirty, false) {
+            state.apply(uc);
+        }
+    }
+}
+
+impl MpuState {
+    fn new() -> Self {
+        Self {
+            enable: false,
+            regions: [MpuRegion::default();MPU_REGION_COUNT],
+            areas: Vec::new(),
+            dirty: false,
+            fault_addr: 0,
+            fault_status: 0,
+        }
+    }
+
+    // rebuild page permissions from scratch - regions are few & only change on context switch, so there's no point being clever
+    fn apply<D>(self: &Self, uc: &mut Unicorn<D>) {
+        for area in &self.areas {
+            uc.mem_protect(area.base as u64, area.size as usize, area.perms).unwrap();
+        }
+
+        if self.enable {
+            for region in self.regions.iter().filter(|r| r.attr & MPUATTRBIT_ENABLE != 0 && r.size != 0) {
+                let start = region.base as u64;
+                let end = (start + region.size as u64).next_multiple_of(MPU_PAGE_SIZE as u64);
+
+                for area in &self.areas {
+                    let area_end = area.base as u64 + area.size as u64;
+                    let lo = start.max(area.base as u64);
+                    let hi = end.min(area_end);
+
+                    if lo < hi {
+                        // regions can restrict memory, but never grant more than it was mapped with (no writable ROM)
+                        uc.mem_protect(lo, (hi - lo) as usize, region.perms() & area.perms).unwrap();
+                    }
+                }
+            }
+        }
+
+        // don't keep running code translated under the old permissions
+        for area in &self.areas {
+            uc.ctl_remove_cache(area.base as u64, area.base as u64 + area.size as u64).unwrap();
+        }
+    }
+}
+
+impl Peripheral for Mpu {
+    fn read(self: &Self, addr: u32) -> u32 {
+        return self.state.lock().read(addr);
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        self.state.lock().write(addr, val);
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("mpu", self.state.stats())];
+    }
+
+    // loading only marks the regions dirty - Machine::apply_mpu puts them into effect
+    fn save_state(self: &Self) -> Vec<u8> {
+        let state = self.state.lock();
+        let mut w = StateWriter::new();
+
+        w.bool(state.enable);
+
+        for region in &state.regions {
+            w.u32(region.base);
+            w.u32(region.size);
+            w.u32(region.attr);
+        }
+
+        w.u32(state.fault_addr);
+        w.u32(state.fault_status);
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let enable = r.bool()?;
+        let mut regions = [MpuRegion::default();MPU_REGION_COUNT];
+
+        for region in &mut regions {
+            *region = MpuRegion { base: r.u32()?, size: r.u32()?, attr: r.u32()? };
+        }
+
+        let fault_addr = r.u32()?;
+        let fault_status = r.u32()?;
+        r.finish()?;
+
+        let mut state = self.state.lock();
+        state.enable = enable;
+        state.regions = regions;
+        state.fault_addr = fault_addr;
+        state.fault_status = fault_status;
+        state.dirty = true;
+
+        return Ok(());
+    }
+}
+
+impl MpuState {
+    fn read(self: &mut Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // CTRL
+                return if self.enable { MPUCTRLBIT_ENABLE } else { 0 };
+            }
+            0x01 => {
+                // REGIONS
+                return MPU_REGION_COUNT as u32;
+            }
+            0x02 => {
+                // FAULTADDR
+                return self.fault_addr;
+            }
+            0x03 => {
+                // FAULTSTATUS
+                return self.fault_status;
+            }
+            0x10..=0x2F => {
+                // RBASEn, RSIZEn, RATTRn, (reserved)
+                let region = &self.regions[((addr - 0x10) / 4) as usize];
+
+                return match (addr - 0x10) % 4 {
+                    0 => region.base,
+                    1 => region.size,
+                    2 => region.attr,
+                    _ => 0,
+                };
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &mut Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // CTRL
+                self.enable = val & MPUCTRLBIT_ENABLE != 0;
+                self.dirty = true;
+            }
+            0x03 => {
+                // FAULTSTATUS (write clears)
+                self.fault_status = 0;
+            }
+            0x10..=0x2F => {
+                // RBASEn, RSIZEn, RATTRn, (reserved)
+                let region = &mut self.regions[((addr - 0x10) / 4) as usize];
+
+                match (addr - 0x10) % 4 {
+                    0 => {
+                        region.base = val & !(MPU_PAGE_SIZE - 1);
+                    }
+                    1 => {
+                        region.size = val;
+                    }
+                    2 => {
+                        region.attr = val;
+                    }
+                    _ => {
+                        return;
+                    }
+                }
+
+                self.dirty = true;
+            }
+            _ => {
+            }
+        }
+    }
+}
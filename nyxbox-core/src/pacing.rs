@@ -0,0 +1,123 @@
+use std::{thread, time::Duration};
+
+use crate::timebase::FRAME_RATE;
+
+// emulated frame length in seconds
+pub const TIMESTEP: f64 = 1.0 / FRAME_RATE as f64;
+
+// speed multiplier while fast-forwarding
+pub const FAST_FORWARD_SPEED: f64 = 4.0;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum BackgroundMode {
+    /// Keep running at full speed when the window loses focus
+    #[default]
+    Run,
+    /// Keep emulating in real time, but stop presenting & sleep between frames
+    Throttle,
+    /// Pause the machine until the window regains focus
+    Pause,
+}
+
+// how presented frames line up with the host display
+#[derive(Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SyncMode {
+    /// Wait for the host display's vsync - frames get quantized to its refresh rate
+    #[default]
+    Vsync,
+    /// Present each frame as soon as it's emulated, for variable refresh rate displays to follow (falls back to vsync if the display can't present without waiting)
+    Adaptive,
+}
+
+// decides how many emulated frames to run per host frame, & which of them get presented.
+// emulation always advances in whole TIMESTEPs - when the host falls behind we catch up by running several frames back to
+// back & skipping presentation, rather than stretching guest time
+pub struct FramePacer {
+    accum: f64,
+    max_catchup: u32,
+    frame_skip: u32,
+    skipped: u32,
+    dropped: f64,
+    dropped_report: f64,
+    speed: f64,
+}
+
+impl FramePacer {
+    pub fn new(max_catchup: u32, frame_skip: u32) -> Self {
+        Self {
+            accum: 0.0,
+            max_catchup: max_catchup.max(1),
+            frame_skip,
+            skipped: 0,
+            dropped: 0.0,
+            dropped_report: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    // emulated seconds per host second (e.g. 4.0 to fast-forward). catch-up headroom scales with it, so running fast
+    // doesn't count as falling behind
+    pub fn set_speed(self: &mut Self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn speed(self: &Self) -> f64 {
+        return self.speed;
+    }
+
+    // returns the number of emulated frames to run for dt seconds of host time
+    pub fn advance(self: &mut Self, dt: f64) -> u32 {
+        self.accum += dt * self.speed;
+
+        let limit = self.max_catchup as f64 * self.speed.max(1.0) * TIMESTEP;
+        if self.accum > limit {
+            // too far behind to ever catch up - this time is lost, & the guest runs slow
+            self.dropped += self.accum - limit;
+            self.accum = limit;
+        }
+
+        let ticks = (self.accum / TIMESTEP) as u32;
+        self.accum -= ticks as f64 * TIMESTEP;
+
+        return ticks;
+    }
+
+    // while paused, host time doesn't count towards the next frame
+    pub fn hold(self: &mut Self) {
+        self.accum = 0.0;
+    }
+
+    // skip presenting while we're catching up, but never more than frame_skip presents in a row
+    pub fn should_present(self: &mut Self, ticks: u32) -> bool {
+        if ticks > 1 && self.skipped < self.frame_skip {
+            self.skipped += 1;
+            return false;
+        }
+
+        self.skipped = 0;
+        return true;
+    }
+
+    // seconds of guest time lost to slowdown since the last call, if any worth mentioning
+    pub fn take_slowdown(self: &mut Self) -> Option<f64> {
+        self.dropped_report += self.dropped;
+        self.dropped = 0.0;
+
+        if self.dropped_report >= 1.0 {
+            return Some(std::mem::replace(&mut self.dropped_report, 0.0));
+        }
+
+        return None;
+    }
+
+    // sleep until the next frame is due, for when nothing else (vsync) is pacing the loop
+    pub fn idle(self: &Self) {
+        let remaining = TIMESTEP - self.accum;
+
+        if remaining > 0.0 {
+            thread::sleep(Duration::from_secs_f64(remaining));
+        }
+    }
+}
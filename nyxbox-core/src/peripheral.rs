@@ -0,0 +1,88 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Mutex, MutexGuard, TryLockError}, time::Instant};
+
+// peripherals are shared between the CPU thread (MMIO hooks) & the frontend, so they take &Self & handle their own locking.
+// keep locks as fine-grained as the device allows - anything held across a host-side operation stalls the CPU
+pub trait Peripheral: Send + Sync {
+    fn read(self: &Self, addr: u32) -> u32;
+    fn write(self: &Self, addr: u32, val: u32);
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return Vec::new();
+    }
+
+    // save state support: the peripheral's guest-visible state in whatever layout it likes (savestate::StateWriter helps).
+    // the core only stores the bytes under the section tag the peripheral was listed with, so a peripheral with nothing
+    // worth keeping can leave these be
+    fn save_state(self: &Self) -> Vec<u8> {
+        return Vec::new();
+    }
+
+    // ...& back again. check the whole section before changing anything, so a damaged one leaves the peripheral as it was
+    fn load_state(self: &Self, _data: &[u8]) -> Result<(), String> {
+        return Ok(());
+    }
+
+    // emulated time has just been set to somewhere else entirely (restoring a save state, possibly to before now), from
+    // old_ns. anything kept relative to the timebase needs rebasing on the new time, whether or not a section of its own
+    // gets loaded after
+    fn time_jumped(self: &Self, _old_ns: u64) {
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LockStats {
+    pub acquisitions: u64,
+    pub contended: u64,
+    pub wait_ns: u64,
+}
+
+// a mutex that keeps track of how often it had to wait, & for how long
+pub struct PeripheralLock<T> {
+    inner: Mutex<T>,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    wait_ns: AtomicU64,
+}
+
+impl<T> PeripheralLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Mutex::new(value),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            wait_ns: AtomicU64::new(0),
+        }
+    }
+
+    pub fn lock(self: &Self) -> MutexGuard<'_, T> {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+
+        // uncontended fast path - only pay for timing when we actually have to wait
+        match self.inner.try_lock() {
+            Ok(guard) => {
+                return guard;
+            }
+            Err(TryLockError::Poisoned(e)) => {
+                panic!("peripheral lock poisoned: {}", e);
+            }
+            Err(TryLockError::WouldBlock) => {
+            }
+        }
+
+        let start = Instant::now();
+        let guard = self.inner.lock().unwrap();
+
+        self.contended.fetch_add(1, Ordering::Relaxed);
+        self.wait_ns.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+        return guard;
+    }
+
+    pub fn stats(self: &Self) -> LockStats {
+        return LockStats {
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+            wait_ns: self.wait_ns.load(Ordering::Relaxed),
+        };
+    }
+}
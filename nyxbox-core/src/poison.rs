@@ -0,0 +1,179 @@
+use std::{collections::HashSet, sync::atomic::{AtomicU64, Ordering}};
+
+use unicorn_engine::Unicorn;
+
+use crate::{peripheral::{LockStats, Peripheral, PeripheralLock}, symbols::Symbols};
+
+pub const POISON_MEM_SIZE: u32 = 4096;
+
+// poisoned memory is filled with this, so even a read that slips past detection (e.g. through a debugger) looks wrong
+pub const POISON_BYTE: u8 = 0xA5;
+
+pub const POISONCMD_POISON: u32     = 1;
+pub const POISONCMD_UNPOISON: u32   = 2;
+
+// a lightweight ASan for guest code: every byte of main RAM is tracked as valid or poisoned. RAM starts out poisoned, guest
+// writes make bytes valid, & an allocator can poison blocks again when it frees them (ADDR, LEN, then CMD). reading a poisoned
+// byte - from the CPU or through VDP DMA - is reported along with the guest PC, once per PC so a loop doesn't flood the log
+pub struct PoisonMap {
+    base: u32,
+    size: u32,
+    bits: Vec<AtomicU64>,
+    regs: PeripheralLock<PoisonRegs>,
+    reported: PeripheralLock<HashSet<u32>>,
+    hits: AtomicU64,
+    symbols: Symbols,
+}
+
+#[derive(Default)]
+struct PoisonRegs {
+    addr: u32,
+    len: u32,
+    pending: Option<(u32, u32)>,
+}
+
+impl PoisonMap {
+    // everything in the tracked range starts poisoned
+    pub fn new(base: u32, size: u32, symbols: Symbols) -> Self {
+        Self {
+            base,
+            size,
+            bits: (0..(size as usize).div_ceil(64)).map(|_| AtomicU64::new(u64::MAX)).collect(),
+            regs: PeripheralLock::new(PoisonRegs::default()),
+            reported: PeripheralLock::new(HashSet::new()),
+            hits: AtomicU64::new(0),
+            symbols,
+        }
+    }
+
+    pub fn range(self: &Self) -> (u32, u32) {
+        return (self.base, self.size);
+    }
+
+    // offsets within the tracked range covered by [addr, addr + len)
+    fn offsets(self: &Self, addr: u32, len: u32) -> std::ops::Range<usize> {
+        let start = addr.max(self.base).min(self.base + self.size) - self.base;
+        let end = (addr as u64 + len as u64).clamp(self.base as u64, (self.base + self.size) as u64) as u32 - self.base;
+        return start as usize..end.max(start) as usize;
+    }
+
+    fn set(self: &Self, addr: u32, len: u32, poisoned: bool) {
+        for offs in self.offsets(addr, len) {
+            let mask = 1 << (offs % 64);
+
+            if poisoned {
+                self.bits[offs / 64].fetch_or(mask, Ordering::Relaxed);
+            }
+            else {
+                self.bits[offs / 64].fetch_and(!mask, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn count_poisoned(self: &Self, addr: u32, len: u32) -> usize {
+        return self.offsets(addr, len).filter(|offs| self.bits[offs / 64].load(Ordering::Relaxed) & (1 << (offs % 64)) != 0).count();
+    }
+
+    // CPU thread: a guest write makes the bytes valid
+    pub fn on_write(self: &Self, addr: u32, size: u32) {
+        self.set(addr, size, false);
+    }
+
+    // CPU thread: about to read guest memory
+    pub fn on_read(self: &Self, pc: u32, addr: u32, size: u32) {
+        if self.count_poisoned(addr, size) != 0 {
+            self.report(pc, || format!("poison: {} byte read of poisoned memory at {}, pc {}", size, self.symbols.describe(addr), self.symbols.describe(pc)));
+        }
+    }
+
+    // frontend: the VDP is about to DMA out of guest memory
+    pub fn on_dma(self: &Self, src: u32, len: u32) {
+        let count = self.count_poisoned(src, len);
+        if count != 0 {
+            self.report(src, || format!("poison: VDP DMA of {} bytes from {:08x} reads {} poisoned bytes", len, src, count));
+        }
+    }
+
+    fn report<F: FnOnce() -> String>(self: &Self, key: u32, msg: F) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        if self.reported.lock().insert(key) {
+            crate::log!(Mem, Warn, "{}", msg());
+        }
+    }
+
+    pub fn hits(self: &Self) -> u64 {
+        return self.hits.load(Ordering::Relaxed);
+    }
+
+    // CPU thread, after a register write: poisoning also has to fill the canary into guest memory, which needs the CPU
+    pub fn apply_pending(self: &Self, uc: &mut Unicorn<'_, ()>) {
+        let Some((addr, len)) = self.regs.lock().pending.take() else {
+            return;
+        };
+
+        let range = self.offsets(addr, len);
+        self.set(addr, len, true);
+
+        if !range.is_empty() {
+            // nothing useful to do if the guest pointed this at something odd - the bits are still set
+            let _ = uc.mem_write((self.base as usize + range.start) as u64, &vec![POISON_BYTE;range.len()]);
+        }
+    }
+}
+
+impl Peripheral for PoisonMap {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // ADDR
+                return self.regs.lock().addr;
+            }
+            0x01 => {
+                // LEN
+                return self.regs.lock().len;
+            }
+            0x03 => {
+                // HITS - poisoned reads detected so far
+                return self.hits() as u32;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // ADDR
+                self.regs.lock().addr = val;
+            }
+            0x01 => {
+                // LEN
+                self.regs.lock().len = val;
+            }
+            0x02 => {
+                // CMD
+                let mut regs = self.regs.lock();
+
+                match val {
+                    POISONCMD_POISON => {
+                        regs.pending = Some((regs.addr, regs.len));
+                    }
+                    POISONCMD_UNPOISON => {
+                        self.set(regs.addr, regs.len, false);
+                    }
+                    _ => {
+                    }
+                }
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("poison.regs", self.regs.stats()), ("poison.reported", self.reported.stats())];
+    }
+}
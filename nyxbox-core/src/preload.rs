@@ -0,0 +1,308 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{elf::Segment, inspect::parse_addr, mem::MemoryMap};
+
+// a file to put in memory before boot, alongside the ROM - a boot stub, or assets the guest expects to find in RAM. flat
+// binaries need an address to go at; Intel HEX & S-record files carry their own
+#[derive(Clone)]
+pub struct Preload {
+    pub path: PathBuf,
+    pub addr: Option<u32>,
+}
+
+#[derive(PartialEq)]
+enum Format {
+    Raw,
+    IntelHex,
+    SRecord,
+}
+
+// `file@addr`, or just `file` for HEX & S-record files
+pub fn parse_preload(s: &str) -> Result<Preload, String> {
+    return match s.rsplit_once('@') {
+        Some((path, addr)) if !path.is_empty() => Ok(Preload { path: PathBuf::from(path), addr: Some(parse_addr(addr)?) }),
+        _ => Ok(Preload { path: PathBuf::from(s), addr: None }),
+    };
+}
+
+impl Preload {
+    pub fn read(self: &Self, map: &MemoryMap) -> Result<Vec<Segment>, String> {
+        let data = fs::read(&self.path).map_err(|e| format!("failed to read {}: {}", self.path.display(), e))?;
+        let err = |e: String| format!("{}: {}", self.path.display(), e);
+
+        let segments = match (format_of(&self.path, &data), self.addr) {
+            (Format::Raw, Some(addr)) => vec![Segment { addr, data }],
+            (Format::Raw, None) => return Err(err("a flat binary needs an address to load at (file@addr)".to_string())),
+            (_, Some(_)) => return Err(err("HEX & S-record files carry their own addresses - leave off the @addr".to_string())),
+            (Format::IntelHex, None) => parse_ihex(&data).map_err(err)?,
+            (Format::SRecord, None) => parse_srec(&data).map_err(err)?,
+        };
+
+        for segment in &segments {
+            if !map.in_memory(segment.addr as usize, segment.data.len()) {
+                return Err(err(format!("{:#x} ({} bytes) isn't within boot ROM or RAM", segment.addr, segment.data.len())));
+            }
+        }
+
+        return Ok(segments);
+    }
+}
+
+// by extension, or failing that by what the first line looks like
+fn format_of(path: &Path, data: &[u8]) -> Format {
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_ascii_lowercase();
+
+    match ext.as_str() {
+        "hex" | "ihex" | "ihx" => return Format::IntelHex,
+        "srec" | "s19" | "s28" | "s37" | "mot" => return Format::SRecord,
+        "bin" => return Format::Raw,
+        _ => {
+        }
+    }
+
+    return match data {
+        [b':', next, ..] if next.is_ascii_hexdigit() => Format::IntelHex,
+        [b'S', next, ..] if next.is_ascii_digit() => Format::SRecord,
+        _ => Format::Raw,
+    };
+}
+
+// a record's hex digits as bytes
+fn record_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err("not a whole number of hex bytes".to_string());
+    }
+
+    return Ok((0..hex.len()).step_by(2).map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap()).collect());
+}
+
+// data records come in small pieces, so runs of them are joined back up into one segment per contiguous block
+fn push_data(segments: &mut Vec<Segment>, addr: u32, data: &[u8]) {
+    if let Some(last) = segments.last_mut().filter(|last| last.addr as usize + last.data.len() == addr as usize) {
+        last.data.extend_from_slice(data);
+        return;
+    }
+
+    segments.push(Segment { addr, data: data.to_vec() });
+}
+
+// :LLAAAATT<data>CC lines. extended segment & linear address records set the upper address bits; start address records
+// are ignored (execution starts at the reset vector or --entry, as with any ROM)
+fn parse_ihex(data: &[u8]) -> Result<Vec<Segment>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "not a text file".to_string())?;
+    let mut segments = Vec::new();
+    let mut base: u32 = 0;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let err = |e: &str| format!("line {}: {}", idx + 1, e);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some(hex) = line.strip_prefix(':') else {
+            return Err(err("expected a record starting with ':'"));
+        };
+
+        let bytes = record_bytes(hex).map_err(|e| err(&e))?;
+
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(err("record length doesn't match its byte count"));
+        }
+
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(err("bad checksum"));
+        }
+
+        let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let payload = &bytes[4..bytes.len() - 1];
+
+        match bytes[3] {
+            0x00 => push_data(&mut segments, base.wrapping_add(addr), payload),
+            0x01 => return Ok(segments),
+            0x02 if payload.len() == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as u32) << 4,
+            0x04 if payload.len() == 2 => base = (u16::from_be_bytes([payload[0], payload[1]]) as u32) << 16,
+            0x03 | 0x05 => {
+            }
+            _ => return Err(err("unknown or malformed record")),
+        }
+    }
+
+    return Err("no end of file record".to_string());
+}
+
+// S<type><count><address><data><checksum> lines, with 2, 3, or 4 byte addresses for S1, S2, & S3 data records. headers,
+// counts, & start addresses are ignored
+fn parse_srec(data: &[u8]) -> Result<Vec<Segment>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "not a text file".to_string())?;
+    let mut segments = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let err = |e: &str| format!("line {}: {}", idx + 1, e);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some(kind) = line.strip_prefix('S').and_then(|rest| rest.chars().next()) else {
+            return Err(err("expected a record starting with 'S'"));
+        };
+
+        // checked before slicing past it, as it could be any character
+        if !kind.is_ascii_digit() {
+            return Err(err("unknown record type"));
+        }
+
+        let bytes = record_bytes(&line[2..]).map_err(|e| err(&e))?;
+
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(err("record length doesn't match its byte count"));
+        }
+
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0xFF {
+            return Err(err("bad checksum"));
+        }
+
+        let addr_len = match kind {
+            '1' => 2,
+            '2' => 3,
+            '3' => 4,
+            '0' | '5' | '6' | '7' | '8' | '9' => continue,
+            _ => return Err(err("unknown record type")),
+        };
+
+        if bytes.len() < addr_len + 2 {
+            return Err(err("record too short for its address"));
+        }
+
+        let addr = bytes[1..1 + addr_len].iter().fold(0u32, |addr, byte| (addr << 8) | *byte as u32);
+        push_data(&mut segments, addr, &bytes[1 + addr_len..bytes.len() - 1]);
+    }
+
+    return Ok(segments);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a record with its count & checksum filled in
+    fn ihex(kind: u8, addr: u16, payload: &[u8]) -> String {
+        let mut bytes = vec![payload.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+        bytes.extend_from_slice(payload);
+        bytes.push(bytes.iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte)));
+        return format!(":{}", bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>());
+    }
+
+    fn srec(kind: char, addr: &[u8], payload: &[u8]) -> String {
+        let mut bytes = vec![(addr.len() + payload.len() + 1) as u8];
+        bytes.extend_from_slice(addr);
+        bytes.extend_from_slice(payload);
+        bytes.push(!bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)));
+        return format!("S{}{}", kind, bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>());
+    }
+
+    fn segments(segments: &[Segment]) -> Vec<(u32, Vec<u8>)> {
+        return segments.iter().map(|segment| (segment.addr, segment.data.clone())).collect();
+    }
+
+    #[test]
+    fn ihex_joins_contiguous_records() {
+        let text = [ihex(0, 0x100, &[1, 2, 3, 4]), ihex(0, 0x104, &[5, 6]), ihex(0, 0x200, &[7]), ihex(1, 0, &[])].join("\n");
+        let parsed = parse_ihex(text.as_bytes()).unwrap();
+        assert_eq!(segments(&parsed), vec![(0x100, vec![1, 2, 3, 4, 5, 6]), (0x200, vec![7])]);
+    }
+
+    #[test]
+    fn ihex_extended_addresses() {
+        let text = [
+            ihex(4, 0, &[0x20, 0x00]),
+            ihex(0, 0x10, &[0xAA]),
+            ihex(2, 0, &[0x10, 0x00]),
+            ihex(0, 0x10, &[0xBB]),
+            // start addresses are ignored
+            ihex(5, 0, &[0, 0, 0, 0]),
+            ihex(1, 0, &[]),
+        ].join("\r\n");
+        let parsed = parse_ihex(text.as_bytes()).unwrap();
+        assert_eq!(segments(&parsed), vec![(0x2000_0010, vec![0xAA]), (0x1_0010, vec![0xBB])]);
+    }
+
+    #[test]
+    fn ihex_stops_at_eof_record() {
+        let text = [ihex(0, 0, &[1]), ihex(1, 0, &[]), "garbage".to_string()].join("\n");
+        assert_eq!(segments(&parse_ihex(text.as_bytes()).unwrap()), vec![(0, vec![1])]);
+
+        let err = parse_ihex(ihex(0, 0, &[1]).as_bytes()).err().unwrap();
+        assert_eq!(err, "no end of file record");
+    }
+
+    #[test]
+    fn ihex_rejects_bad_records() {
+        let mut bad_sum = ihex(0, 0, &[1, 2]);
+        bad_sum.replace_range(bad_sum.len() - 2.., "00");
+
+        let cases = [
+            (bad_sum, "bad checksum"),
+            (":0100000001".to_string(), "record length doesn't match its byte count"),
+            (":0".to_string(), "not a whole number of hex bytes"),
+            ("0000000000".to_string(), "expected a record starting with ':'"),
+            (ihex(6, 0, &[]), "unknown or malformed record"),
+            (ihex(4, 0, &[1]), "unknown or malformed record"),
+        ];
+
+        for (line, msg) in cases {
+            assert_eq!(parse_ihex(line.as_bytes()).err(), Some(format!("line 1: {}", msg)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn srec_address_sizes() {
+        let text = [
+            srec('0', &[0, 0], b"header"),
+            srec('1', &[0x12, 0x34], &[1, 2]),
+            srec('1', &[0x12, 0x36], &[3]),
+            srec('2', &[0x12, 0x34, 0x56], &[4]),
+            srec('3', &[0x20, 0x00, 0x00, 0x00], &[5, 6]),
+            srec('5', &[0, 4], &[]),
+            srec('7', &[0x20, 0, 0, 0], &[]),
+        ].join("\n");
+        let parsed = parse_srec(text.as_bytes()).unwrap();
+        assert_eq!(segments(&parsed), vec![(0x1234, vec![1, 2, 3]), (0x12_3456, vec![4]), (0x2000_0000, vec![5, 6])]);
+    }
+
+    #[test]
+    fn srec_rejects_bad_records() {
+        let mut bad_sum = srec('1', &[0, 0], &[1]);
+        bad_sum.replace_range(bad_sum.len() - 2.., "00");
+
+        let cases = [
+            (bad_sum, "bad checksum"),
+            ("S1050000".to_string(), "record length doesn't match its byte count"),
+            ("S1".to_string(), "record length doesn't match its byte count"),
+            ("X1030000FC".to_string(), "expected a record starting with 'S'"),
+            ("S".to_string(), "expected a record starting with 'S'"),
+            (srec('4', &[0, 0], &[]), "unknown record type"),
+            (srec('3', &[0, 0], &[]), "record too short for its address"),
+            // a multi-byte character where the type goes used to panic slicing after it
+            ("Sé0300FC".to_string(), "unknown record type"),
+            ("SA030000FC".to_string(), "unknown record type"),
+        ];
+
+        for (line, msg) in cases {
+            assert_eq!(parse_srec(line.as_bytes()).err(), Some(format!("line 1: {}", msg)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn detects_format() {
+        assert!(format_of(Path::new("a.hex"), b"") == Format::IntelHex);
+        assert!(format_of(Path::new("a.S19"), b"") == Format::SRecord);
+        assert!(format_of(Path::new("a.bin"), b":00000001FF") == Format::Raw);
+        assert!(format_of(Path::new("a"), b":00000001FF") == Format::IntelHex);
+        assert!(format_of(Path::new("a"), b"S00300FC") == Format::SRecord);
+        assert!(format_of(Path::new("a"), b"\x7fELF") == Format::Raw);
+    }
+}
@@ -0,0 +1,58 @@
+use sdl3::gpu::{CommandBuffer, Device};
+
+use crate::vdp::VDP;
+
+// takes the frame's command buffer (which already has the VDP's work recorded in it), gets the result in front of
+// whoever's watching, & submits it. frontends pick one of these rather than each growing their own presentation code
+pub trait PresentBackend {
+    fn present(self: &mut Self, frame: u64, vdp: &mut VDP, gfx_device: &Device, cmd_buffer: CommandBuffer) -> Result<(), String>;
+}
+
+// just submits the VDP's work
+pub struct NullPresenter;
+
+impl PresentBackend for NullPresenter {
+    fn present(self: &mut Self, _frame: u64, _vdp: &mut VDP, _gfx_device: &Device, cmd_buffer: CommandBuffer) -> Result<(), String> {
+        return cmd_buffer.submit().map_err(|e| e.to_string());
+    }
+}
+
+// hands every Nth new frame's pixels (RGBA8, tightly packed) to a callback - an embedder's, to upload into its own texture,
+// or a frontend's, to filter & write out. reading the frame back waits on the GPU, so frontends that can draw straight from
+// VRAM (see VDP::vram_buffer) should
+pub struct CallbackPresenter<F: FnMut(u64, u32, u32, Vec<u8>) -> Result<(), String>> {
+    callback: F,
+    interval: u64,
+    last_frame: Option<u64>,
+}
+
+impl<F: FnMut(u64, u32, u32, Vec<u8>) -> Result<(), String>> CallbackPresenter<F> {
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            interval: 1,
+            last_frame: None,
+        }
+    }
+
+    // only every Nth emulated frame is read back & handed over
+    pub fn with_interval(mut self, interval: u64) -> Self {
+        self.interval = interval.max(1);
+        return self;
+    }
+}
+
+impl<F: FnMut(u64, u32, u32, Vec<u8>) -> Result<(), String>> PresentBackend for CallbackPresenter<F> {
+    fn present(self: &mut Self, frame: u64, vdp: &mut VDP, gfx_device: &Device, cmd_buffer: CommandBuffer) -> Result<(), String> {
+        cmd_buffer.submit().map_err(|e| e.to_string())?;
+
+        if self.last_frame == Some(frame) || frame % self.interval != 0 {
+            return Ok(());
+        }
+
+        self.last_frame = Some(frame);
+
+        let (width, height, rgba) = vdp.read_framebuffer(gfx_device)?;
+        return (self.callback)(frame, width, height, rgba);
+    }
+}
@@ -0,0 +1,117 @@
+// host-side rendering overrides for looking into a guest's scenes - the guest doesn't know or care which one is active.
+// wireframe replaces each triangle with its outline, & overdraw counts how many triangles cover each pixel, presenting
+// (& screenshotting) the counts as a heat map in place of the framebuffer
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RenderDebugMode {
+    /// Render normally
+    #[default]
+    None,
+    /// Draw triangle outlines only
+    Wireframe,
+    /// Show per-pixel overdraw as a heat map
+    Overdraw,
+}
+
+// must match the DEBUGMODE_* defines in draw_tri_list.glsl & draw_lines.glsl
+impl RenderDebugMode {
+    pub fn shader_mode(self: &Self) -> u32 {
+        match self {
+            RenderDebugMode::None => 0,
+            RenderDebugMode::Wireframe => 1,
+            RenderDebugMode::Overdraw => 2,
+        }
+    }
+
+    // for the hotkey, which cycles through them
+    pub fn next(self: &Self) -> Self {
+        match self {
+            RenderDebugMode::None => RenderDebugMode::Wireframe,
+            RenderDebugMode::Wireframe => RenderDebugMode::Overdraw,
+            RenderDebugMode::Overdraw => RenderDebugMode::None,
+        }
+    }
+
+    pub fn name(self: &Self) -> &'static str {
+        match self {
+            RenderDebugMode::None => "none",
+            RenderDebugMode::Wireframe => "wireframe",
+            RenderDebugMode::Overdraw => "overdraw",
+        }
+    }
+}
+
+// heat map colors by overdraw count: nothing, then blue -> green -> yellow -> orange -> red, & white for anything past that
+const HEAT_COLORS: [[u8;4];7] = [
+    [0x00, 0x00, 0x00, 0xFF],
+    [0x20, 0x40, 0xFF, 0xFF],
+    [0x20, 0xC0, 0x40, 0xFF],
+    [0xFF, 0xE0, 0x20, 0xFF],
+    [0xFF, 0x80, 0x10, 0xFF],
+    [0xFF, 0x20, 0x10, 0xFF],
+    [0xFF, 0xFF, 0xFF, 0xFF],
+];
+
+pub fn heat_map(counts: &[u32]) -> Vec<u8> {
+    return counts.iter().flat_map(|c| HEAT_COLORS[(*c as usize).min(HEAT_COLORS.len() - 1)]).collect();
+}
+
+// graphics debug: run only some of each frame's draw commands, to pin down which one is responsible for a bad frame.
+// draws are numbered from 0 in the order the VDP executes them each frame - everything else (register writes, vertex
+// processing) still runs, so skipped draws don't change the state later ones see
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawIsolation {
+    #[default]
+    Off,
+    // draws 0 through N
+    UpTo(u32),
+    // draw N alone
+    Only(u32),
+}
+
+impl DrawIsolation {
+    pub fn allows(self: &Self, draw: u32) -> bool {
+        match self {
+            DrawIsolation::Off => true,
+            DrawIsolation::UpTo(n) => draw <= *n,
+            DrawIsolation::Only(n) => draw == *n,
+        }
+    }
+
+    pub fn parse(mode: &str, index: u32) -> Result<Self, String> {
+        match mode {
+            "off" => Ok(DrawIsolation::Off),
+            "upto" => Ok(DrawIsolation::UpTo(index)),
+            "only" => Ok(DrawIsolation::Only(index)),
+            _ => Err(format!("unknown isolation mode '{}' (expected off, upto, or only)", mode)),
+        }
+    }
+
+    // for the hotkey, which cycles off -> up to -> only, keeping the index
+    pub fn next(self: &Self, index: u32) -> Self {
+        match self {
+            DrawIsolation::Off => DrawIsolation::UpTo(index),
+            DrawIsolation::UpTo(n) => DrawIsolation::Only(*n),
+            DrawIsolation::Only(_) => DrawIsolation::Off,
+        }
+    }
+
+    // move the index, staying within the draws the last frame actually had
+    pub fn step(self: &Self, delta: i32, draws: u32) -> Self {
+        let move_index = |n: u32| (n as i64 + delta as i64).clamp(0, draws.saturating_sub(1) as i64) as u32;
+
+        match self {
+            DrawIsolation::Off => DrawIsolation::Off,
+            DrawIsolation::UpTo(n) => DrawIsolation::UpTo(move_index(*n)),
+            DrawIsolation::Only(n) => DrawIsolation::Only(move_index(*n)),
+        }
+    }
+
+    pub fn describe(self: &Self, draws: u32) -> String {
+        match self {
+            DrawIsolation::Off => "off".to_string(),
+            DrawIsolation::UpTo(n) => format!("draws 0-{} of {}", n, draws),
+            DrawIsolation::Only(n) => format!("draw {} of {}", n, draws),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+
+use crate::savestate::SaveState;
+
+// a run of at least this many unchanged bytes ends a literal in a delta - shorter ones cost less to copy than to skip
+const MIN_SKIP: usize = 8;
+
+// rewinding: a save state every `interval` frames, in a ring of `capacity` of them. whole states are mostly RAM & VRAM that
+// barely change from one to the next, so only the newest is kept in full - each older one is stored as the bytes it differs
+// from the state after it by. dropping the oldest is then free, & stepping back decodes just the one delta
+pub struct Rewind {
+    interval: u64,
+    capacity: usize,
+    next_capture: u64,
+    newest: Option<(u64, Vec<u8>)>,
+    // oldest first, each a delta against the state after it (the last against newest)
+    older: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl Rewind {
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            next_capture: 0,
+            newest: None,
+            older: VecDeque::new(),
+        }
+    }
+
+    // whether a state should be captured at this frame
+    pub fn due(self: &Self, frame: u64) -> bool {
+        return frame >= self.next_capture;
+    }
+
+    pub fn push(self: &mut Self, frame: u64, state: &SaveState) {
+        let data = state.to_bytes();
+
+        if let Some((prev_frame, prev)) = self.newest.take() {
+            self.older.push_back((prev_frame, encode(&prev, &data)));
+        }
+
+        while self.older.len() + 1 > self.capacity {
+            self.older.pop_front();
+        }
+
+        self.newest = Some((frame, data));
+        self.next_capture = frame + self.interval;
+    }
+
+    // the newest state from before `frame` - far enough before it that stepping back is noticeable, if there's a choice.
+    // it's taken out of the buffer, so the next step goes further back
+    pub fn step_back(self: &mut Self, frame: u64) -> Option<(u64, SaveState)> {
+        loop {
+            let (newest_frame, newest) = self.newest.take()?;
+            let older = self.older.pop_back().map(|(older_frame, delta)| (older_frame, decode(&delta, &newest)));
+
+            if newest_frame + self.interval / 2 <= frame || older.is_none() {
+                self.newest = older;
+                self.next_capture = newest_frame + self.interval;
+
+                // the ring only ever holds states this build made, so this can't fail short of a bug
+                return Some((newest_frame, SaveState::from_bytes(&newest).unwrap()));
+            }
+
+            self.newest = older;
+        }
+    }
+
+    // everything captured so far describes a machine that's no longer there (a reset, or another state loaded)
+    pub fn clear(self: &mut Self, frame: u64) {
+        self.newest = None;
+        self.older.clear();
+        self.next_capture = frame;
+    }
+
+    // states held, & the bytes they take up
+    pub fn usage(self: &Self) -> (usize, usize) {
+        let count = self.older.len() + self.newest.is_some() as usize;
+        let bytes = self.older.iter().chain(self.newest.iter()).map(|(_, data)| data.len()).sum();
+        return (count, bytes);
+    }
+}
+
+// a delta that turns `base` back into `target`: target's length, then [unchanged bytes: u32][changed bytes: u32][target ^
+// base for each] until target runs out. base is taken as zero past its end
+fn encode(target: &[u8], base: &[u8]) -> Vec<u8> {
+    let xor = |i: usize| target[i] ^ base.get(i).copied().unwrap_or(0);
+    let same = |i: usize| (i..(i + MIN_SKIP).min(target.len())).all(|j| xor(j) == 0);
+
+    let mut out = (target.len() as u32).to_le_bytes().to_vec();
+    let mut i = 0;
+
+    while i < target.len() {
+        let skip_start = i;
+
+        while i < target.len() && xor(i) == 0 {
+            i += 1;
+        }
+
+        let lit_start = i;
+
+        while i < target.len() && !same(i) {
+            i += 1;
+        }
+
+        out.extend_from_slice(&((lit_start - skip_start) as u32).to_le_bytes());
+        out.extend_from_slice(&((i - lit_start) as u32).to_le_bytes());
+        out.extend((lit_start..i).map(xor));
+    }
+
+    return out;
+}
+
+fn decode(delta: &[u8], base: &[u8]) -> Vec<u8> {
+    let word = |pos: usize| u32::from_le_bytes(delta[pos..pos + 4].try_into().unwrap()) as usize;
+    let base_at = |i: usize| base.get(i).copied().unwrap_or(0);
+
+    let mut out = Vec::with_capacity(word(0));
+    let mut pos = 4;
+
+    while pos < delta.len() {
+        let skip = word(pos);
+        let len = word(pos + 4);
+        pos += 8;
+
+        let start = out.len();
+        out.extend((start..start + skip).map(base_at));
+
+        let start = out.len();
+        out.extend(delta[pos..pos + len].iter().enumerate().map(|(j, b)| b ^ base_at(start + j)));
+        pos += len;
+    }
+
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deterministic filler, so a failure reproduces
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut x = seed.max(1);
+        return (0..len).map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        }).collect();
+    }
+
+    fn round_trip(target: &[u8], base: &[u8]) {
+        let delta = encode(target, base);
+        assert_eq!(decode(&delta, base), target);
+    }
+
+    #[test]
+    fn round_trips_same_length() {
+        let base = noise(4096, 1);
+        let mut target = base.clone();
+
+        round_trip(&target, &base);
+
+        target[0] ^= 1;
+        target[100..140].copy_from_slice(&noise(40, 2));
+        target[4095] ^= 0x80;
+        round_trip(&target, &base);
+        round_trip(&noise(4096, 3), &base);
+    }
+
+    #[test]
+    fn round_trips_growing_target() {
+        let base = noise(1000, 4);
+
+        let mut target = base.clone();
+        target.extend(noise(500, 5));
+        round_trip(&target, &base);
+
+        // zeroes past the end of base count as unchanged
+        let mut target = base.clone();
+        target.extend(vec![0; 500]);
+        round_trip(&target, &base);
+
+        round_trip(&noise(1000, 6), &[]);
+    }
+
+    #[test]
+    fn round_trips_shrinking_target() {
+        let base = noise(1000, 7);
+
+        round_trip(&base[..600], &base);
+        round_trip(&noise(600, 8), &base);
+        round_trip(&[], &base);
+    }
+
+    #[test]
+    fn splits_literals_at_min_skip() {
+        let base = vec![0u8; 64];
+
+        // a gap one byte short of MIN_SKIP stays inside the literal (& the unchanged tail is a record of its own)
+        let mut target = base.clone();
+        target[10] = 1;
+        target[10 + MIN_SKIP] = 1;
+        let delta = encode(&target, &base);
+        assert_eq!(delta.len(), 4 + 8 + MIN_SKIP + 1 + 8);
+        assert_eq!(decode(&delta, &base), target);
+
+        // a gap of exactly MIN_SKIP ends it
+        let mut target = base.clone();
+        target[10] = 1;
+        target[11 + MIN_SKIP] = 1;
+        let delta = encode(&target, &base);
+        assert_eq!(delta.len(), 4 + 8 + 1 + 8 + 1 + 8);
+        assert_eq!(decode(&delta, &base), target);
+
+        // a changed run right at the end, shorter than MIN_SKIP
+        let mut target = base.clone();
+        target[60..].fill(0xFF);
+        round_trip(&target, &base);
+    }
+
+    fn state(seed: u32, len: usize) -> SaveState {
+        let mut state = SaveState::new();
+        state.set_section(*b"TEST", noise(len, seed));
+        return state;
+    }
+
+    #[test]
+    fn steps_back_through_deltas() {
+        let mut rewind = Rewind::new(10, 8);
+
+        for i in 0..5 {
+            assert!(rewind.due(i * 10));
+            rewind.push(i * 10, &state(i as u32 + 1, 1000 + i as usize * 100));
+        }
+
+        assert_eq!(rewind.usage().0, 5);
+
+        // too close to the newest to count as a step, so it goes one further
+        let (frame, got) = rewind.step_back(42).unwrap();
+        assert_eq!(frame, 30);
+        assert_eq!(got.section(b"TEST").unwrap(), state(4, 1300).section(b"TEST").unwrap());
+
+        for i in (0..3).rev() {
+            let (frame, got) = rewind.step_back(frame).unwrap();
+            assert_eq!(frame, i * 10);
+            assert_eq!(got.section(b"TEST").unwrap(), state(i as u32 + 1, 1000 + i as usize * 100).section(b"TEST").unwrap());
+        }
+
+        assert!(rewind.step_back(0).is_none());
+    }
+
+    #[test]
+    fn drops_oldest_past_capacity() {
+        let mut rewind = Rewind::new(1, 3);
+
+        for i in 0..6 {
+            rewind.push(i, &state(i as u32 + 1, 256));
+        }
+
+        assert_eq!(rewind.usage().0, 3);
+
+        let frames: Vec<u64> = std::iter::from_fn(|| rewind.step_back(u64::MAX).map(|(frame, _)| frame)).collect();
+        assert_eq!(frames, vec![5, 4, 3]);
+    }
+}
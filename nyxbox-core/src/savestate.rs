@@ -0,0 +1,384 @@
+use std::{collections::BTreeMap, fs, io, path::Path, sync::Arc};
+
+use sdl3::gpu::Device;
+
+use crate::{machine::{Machine, MachineRunContext, BANKED_REG_COUNT, DEBUG_REGS}, mem::{MemoryMap, BOOT_ROM_BEGIN}, peripheral::Peripheral, timebase::Timebase, vdp::{INTERNALREG_COUNT, VDP, VRAM_SIZE}};
+
+// save state file layout: magic, version, then a list of tagged sections ([tag: 4 bytes][len: u32][data]), all little endian
+// sections are independent so tools can pull out just the parts they care about, and unknown sections are simply skipped
+pub const STATE_MAGIC: &[u8;4] = b"NYXS";
+pub const STATE_VERSION: u32 = 2;
+// oldest layout this build can still read. states from before it are refused up front rather than half-loaded
+pub const STATE_VERSION_MIN: u32 = 1;
+
+pub const SECTION_CPU: [u8;4]       = *b"CPU ";
+pub const SECTION_ROM: [u8;4]       = *b"ROM ";
+pub const SECTION_RAM: [u8;4]       = *b"RAM ";
+pub const SECTION_XRAM: [u8;4]      = *b"XRAM";
+pub const SECTION_VRAM: [u8;4]      = *b"VRAM";
+pub const SECTION_VDP_REGS: [u8;4]  = *b"VREG";
+// [frame: u64][emulated time in ns: u64]
+pub const SECTION_TIME: [u8;4]      = *b"TIME";
+
+// version 2 onwards. a version 1 state has none of these, & loading one leaves the parts they cover as they were
+//
+// SP, LR, & SPSR of each mode, then FIQ's own r8-r12 & everyone else's (see machine::BANKED_MODES)
+pub const SECTION_CPU_BANKS: [u8;4] = *b"BANK";
+// the VDP's command FIFO, pending tokens, & display/error state (VDP::save_state)
+pub const SECTION_VDP_CORE: [u8;4]  = *b"VDPC";
+// peripherals, each in its own layout (see their Peripheral::save_state)
+pub const SECTION_VDP_PORT: [u8;4]  = *b"VPRT";
+pub const SECTION_INTC: [u8;4]      = *b"INTC";
+pub const SECTION_CLOCK: [u8;4]     = *b"CLCK";
+pub const SECTION_UART: [u8;4]      = *b"UART";
+pub const SECTION_GAMEPAD: [u8;4]   = *b"PAD ";
+pub const SECTION_MPU: [u8;4]       = *b"MPU ";
+pub const SECTION_BUSERR: [u8;4]    = *b"BERR";
+pub const SECTION_WATCHDOG: [u8;4]  = *b"WDOG";
+pub const SECTION_MAILBOX: [u8;4]   = *b"MBOX";
+
+// crash dumps only (see crashdump.rs): the report as text, & the most recent block addresses, oldest first
+pub const SECTION_CRASH_REPORT: [u8;4] = *b"CRSH";
+pub const SECTION_PC_HISTORY: [u8;4]   = *b"PCHI";
+
+// the peripherals a save state covers, & the section each goes in
+pub type Snapshots = Vec<([u8;4], Arc<dyn Peripheral>)>;
+
+// section contents are built up from little endian fields, in whatever order the owner likes
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+        }
+    }
+
+    pub fn u32(self: &mut Self, val: u32) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn u64(self: &mut Self, val: u64) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn bool(self: &mut Self, val: bool) {
+        self.data.push(val as u8);
+    }
+
+    // length-prefixed
+    pub fn bytes(self: &mut Self, data: &[u8]) {
+        self.u32(data.len() as u32);
+        self.data.extend_from_slice(data);
+    }
+
+    pub fn words(self: &mut Self, words: &[u32]) {
+        self.u32(words.len() as u32);
+
+        for word in words {
+            self.u32(*word);
+        }
+    }
+
+    pub fn finish(self: Self) -> Vec<u8> {
+        return self.data;
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl <'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+        }
+    }
+
+    fn take(self: &mut Self, len: usize) -> Result<&'a [u8], String> {
+        let data = self.pos.checked_add(len).and_then(|end| self.data.get(self.pos..end)).ok_or("section is truncated".to_string())?;
+        self.pos += len;
+        return Ok(data);
+    }
+
+    pub fn u32(self: &mut Self) -> Result<u32, String> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    pub fn u64(self: &mut Self) -> Result<u64, String> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    pub fn bool(self: &mut Self) -> Result<bool, String> {
+        return Ok(self.take(1)?[0] != 0);
+    }
+
+    pub fn bytes(self: &mut Self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        return self.take(len);
+    }
+
+    pub fn words(self: &mut Self) -> Result<Vec<u32>, String> {
+        let len = self.u32()? as usize;
+        return Ok(self.take(len.checked_mul(4).ok_or("section is truncated".to_string())?)?.chunks_exact(4).map(|c| u32::from_le_bytes(c.try_into().unwrap())).collect());
+    }
+
+    // for fields added to the end of a section since it was first saved
+    pub fn at_end(self: &Self) -> bool {
+        return self.pos == self.data.len();
+    }
+
+    // a section with anything left over isn't the layout the reader expected
+    pub fn finish(self: Self) -> Result<(), String> {
+        if self.pos != self.data.len() {
+            return Err(format!("section has {} unexpected trailing bytes", self.data.len() - self.pos));
+        }
+
+        return Ok(());
+    }
+}
+
+pub struct SaveState {
+    pub version: u32,
+    sections: BTreeMap<[u8;4], Vec<u8>>,
+}
+
+impl SaveState {
+    pub fn new() -> Self {
+        Self {
+            version: STATE_VERSION,
+            sections: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        return Self::from_bytes(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+
+    pub fn save(self: &Self, path: &Path) -> io::Result<()> {
+        return fs::write(path, self.to_bytes());
+    }
+
+    pub fn set_section(self: &mut Self, tag: [u8;4], data: Vec<u8>) {
+        self.sections.insert(tag, data);
+    }
+
+    pub fn set_section_words(self: &mut Self, tag: [u8;4], words: &[u32]) {
+        self.set_section(tag, words.iter().flat_map(|w| w.to_le_bytes()).collect());
+    }
+
+    pub fn section(self: &Self, tag: &[u8;4]) -> Option<&[u8]> {
+        return self.sections.get(tag).map(Vec::as_slice);
+    }
+
+    pub fn section_words(self: &Self, tag: &[u8;4]) -> Option<Vec<u32>> {
+        return self.section(tag).map(|data| data.chunks_exact(4).map(|c| u32::from_le_bytes(c.try_into().unwrap())).collect());
+    }
+
+    pub fn to_bytes(self: &Self) -> Vec<u8> {
+        let mut out = STATE_MAGIC.to_vec();
+        out.extend_from_slice(&self.version.to_le_bytes());
+
+        for (tag, data) in &self.sections {
+            out.extend_from_slice(tag);
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(data);
+        }
+
+        return out;
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 8 || &data[0..4] != STATE_MAGIC {
+            return Err("not a NyxBox save state".to_string());
+        }
+
+        let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+        if version > STATE_VERSION {
+            return Err(format!("save state version {} is newer than this build supports ({})", version, STATE_VERSION));
+        }
+
+        if version < STATE_VERSION_MIN {
+            return Err(format!("save state version {} is older than this build supports ({} or later)", version, STATE_VERSION_MIN));
+        }
+
+        let mut sections = BTreeMap::new();
+        let mut pos = 8;
+
+        while pos < data.len() {
+            let Some(hdr) = data.get(pos..pos + 8) else {
+                return Err(format!("truncated section header at offset {}", pos));
+            };
+
+            let tag: [u8;4] = hdr[0..4].try_into().unwrap();
+            let len = u32::from_le_bytes(hdr[4..8].try_into().unwrap()) as usize;
+            pos += 8;
+
+            let Some(section) = pos.checked_add(len).and_then(|end| data.get(pos..end)) else {
+                return Err(format!("section '{}' is truncated", String::from_utf8_lossy(&tag)));
+            };
+
+            if sections.insert(tag, section.to_vec()).is_some() {
+                return Err(format!("section '{}' appears more than once", String::from_utf8_lossy(&tag)));
+            }
+
+            pos += len;
+        }
+
+        let state = Self {
+            version,
+            sections,
+        };
+
+        state.validate()?;
+        return Ok(state);
+    }
+
+    // known sections have a fixed size - anything else means the file is damaged, & nothing should try to use it. memory
+    // sections are sized by the memory map, so only check_restorable can tell whether those fit
+    pub fn validate(self: &Self) -> Result<(), String> {
+        let sizes = [
+            (SECTION_CPU, DEBUG_REGS.len() * 4),
+            (SECTION_VRAM, VRAM_SIZE as usize),
+            (SECTION_VDP_REGS, INTERNALREG_COUNT * 4),
+            (SECTION_TIME, 16),
+            (SECTION_CPU_BANKS, BANKED_REG_COUNT * 4),
+        ];
+
+        for (tag, size) in sizes {
+            if let Some(data) = self.section(&tag) {
+                if data.len() != size {
+                    return Err(format!("section '{}' is {} bytes, expected {}", String::from_utf8_lossy(&tag), data.len(), size));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+// snapshot the machine as it stands. the CPU should be paused so memory, registers, & peripherals agree with each other
+pub fn capture_state(run_ctx: &MachineRunContext, vdp: &mut VDP, gfx_device: &Device, timebase: &Timebase, snapshots: &Snapshots, map: &MemoryMap, expansion_ram: bool) -> Result<SaveState, String> {
+    let mut state = SaveState::new();
+
+    let mut time = timebase.frame().to_le_bytes().to_vec();
+    time.extend_from_slice(&timebase.now_ns().to_le_bytes());
+    state.set_section(SECTION_TIME, time);
+
+    let cpu: Vec<u32> = run_ctx.registers().into_iter().map(|(_, val)| val).collect();
+    state.set_section_words(SECTION_CPU, &cpu);
+    state.set_section_words(SECTION_CPU_BANKS, &run_ctx.banked_registers());
+
+    let mut regions = vec![(SECTION_ROM, BOOT_ROM_BEGIN, map.boot_rom_size), (SECTION_RAM, map.main_ram_begin, map.main_ram_size)];
+    if expansion_ram {
+        regions.push((SECTION_XRAM, map.expansion_ram_begin, map.expansion_ram_size));
+    }
+
+    for (tag, base, len) in regions {
+        let data = run_ctx.mem_read(base as u32, len).map_err(|e| format!("failed to read guest memory at {:08x}: {:?}", base, e))?;
+        state.set_section(tag, data);
+    }
+
+    state.set_section(SECTION_VRAM, vdp.read_vram(gfx_device));
+    state.set_section_words(SECTION_VDP_REGS, vdp.internal_regs());
+    state.set_section(SECTION_VDP_CORE, vdp.save_state());
+
+    // a peripheral with nothing to save doesn't get a section
+    for (tag, peripheral) in snapshots {
+        let data = peripheral.save_state();
+
+        if !data.is_empty() {
+            state.set_section(*tag, data);
+        }
+    }
+
+    return Ok(state);
+}
+
+// everything restore_state needs from a state, checked up front so a state that can't be restored is refused before any of
+// the machine has been overwritten
+fn check_restorable(state: &SaveState, map: &MemoryMap, expansion_ram: bool) -> Result<(), String> {
+    for tag in [SECTION_CPU, SECTION_ROM, SECTION_RAM, SECTION_VRAM, SECTION_VDP_REGS] {
+        if state.section(&tag).is_none() {
+            return Err(format!("save state has no '{}' section", String::from_utf8_lossy(&tag)));
+        }
+    }
+
+    for (tag, size) in [(SECTION_ROM, map.boot_rom_size), (SECTION_RAM, map.main_ram_size), (SECTION_XRAM, map.expansion_ram_size)] {
+        if let Some(data) = state.section(&tag) {
+            if data.len() != size {
+                return Err(format!("section '{}' is {} bytes, but this memory map has {}", String::from_utf8_lossy(&tag), data.len(), size));
+            }
+        }
+    }
+
+    match (state.section(&SECTION_XRAM).is_some(), expansion_ram) {
+        (true, false) => return Err("save state was made with expansion RAM - run with --expansion-ram to load it".to_string()),
+        (false, true) => return Err("save state was made without expansion RAM".to_string()),
+        _ => {}
+    }
+
+    return Ok(());
+}
+
+pub fn load_restorable(path: &Path, map: &MemoryMap, expansion_ram: bool) -> Result<SaveState, String> {
+    let state = SaveState::load(path).map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+    check_restorable(&state, map, expansion_ram)?;
+    return Ok(state);
+}
+
+// put the machine back the way a state had it. the CPU thread must be stopped, & the state must have passed
+// check_restorable. returns the frame to carry on from. a peripheral section that doesn't load is reported & skipped, so
+// that peripheral carries on as it was
+pub fn restore_state(state: &SaveState, machine: &mut Machine, vdp: &mut VDP, gfx_device: &Device, timebase: &Timebase, snapshots: &Snapshots,
+    map: &MemoryMap) -> u64 {
+    // emulated time first, as the clock & watchdog count from it
+    let frame = match state.section(&SECTION_TIME) {
+        Some(time) => u64::from_le_bytes(time[0..8].try_into().unwrap()),
+        None => timebase.frame(),
+    };
+
+    let old_ns = timebase.now_ns();
+    timebase.set_frame(frame);
+
+    for (_, peripheral) in snapshots {
+        peripheral.time_jumped(old_ns);
+    }
+
+    if let Some(banks) = state.section_words(&SECTION_CPU_BANKS) {
+        machine.set_banked_registers(&banks);
+    }
+
+    machine.set_registers(&state.section_words(&SECTION_CPU).unwrap());
+    machine.load_rom(state.section(&SECTION_ROM).unwrap());
+    machine.write_memory(map.main_ram_begin as u32, state.section(&SECTION_RAM).unwrap());
+
+    if let Some(xram) = state.section(&SECTION_XRAM) {
+        machine.write_memory(map.expansion_ram_begin as u32, xram);
+    }
+
+    let cmd_buf = gfx_device.acquire_command_buffer().unwrap();
+    vdp.upload(&state.section_words(&SECTION_VRAM).unwrap(), 0, gfx_device, &cmd_buf);
+    cmd_buf.submit().unwrap();
+
+    vdp.set_internal_regs(&state.section_words(&SECTION_VDP_REGS).unwrap());
+
+    let sections = state.section(&SECTION_VDP_CORE).map(|data| (SECTION_VDP_CORE, vdp.load_state(data))).into_iter()
+        .chain(snapshots.iter().filter_map(|(tag, peripheral)| state.section(tag).map(|data| (*tag, peripheral.load_state(data)))));
+
+    for (tag, res) in sections {
+        if let Err(e) = res {
+            println!("save state: skipped section '{}': {}", String::from_utf8_lossy(&tag), e);
+        }
+    }
+
+    machine.apply_mpu();
+
+    return frame;
+}
@@ -0,0 +1,120 @@
+use std::{fs::File, io::{self, BufReader, BufWriter}, path::Path};
+
+use sdl3::gpu::Device;
+
+use crate::{capture::CaptureWriter, storage::fnv1a, vdp::{VDP, INTERNALREG_FBADDR, INTERNALREG_FBDIM}};
+
+// write tightly packed RGBA8 pixels out as a PNG
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(rgba).map_err(io::Error::other)?;
+
+    return Ok(());
+}
+
+// any 8 or 16 bit PNG, as tightly packed RGBA8 (grey & palette images expanded, 16 bit channels cut down to 8)
+pub fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), String> {
+    let err = |e: String| format!("failed to read {}: {}", path.display(), e);
+
+    let file = File::open(path).map_err(|e| err(e.to_string()))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(|e| err(e.to_string()))?;
+    let mut buf = vec![0;reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| err(e.to_string()))?;
+    let pixels = &buf[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|p| [*p, *p, *p, 0xFF]).collect(),
+        png::ColorType::Indexed => return Err(err("palette wasn't expanded".to_string())),
+    };
+
+    return Ok((info.width, info.height, rgba));
+}
+
+// identifies a framebuffer's exact contents, dimensions included
+pub fn framebuffer_hash(width: u32, height: u32, rgba: &[u8]) -> u32 {
+    let mut data = Vec::with_capacity(8 + rgba.len());
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(rgba);
+
+    return fnv1a(&data);
+}
+
+// how one RGBA8 image differs from another of the same size
+pub struct ImageDiff {
+    // pixels with any channel off by more than the tolerance
+    pub pixels: usize,
+    // the largest difference in any one channel, tolerated or not
+    pub max_delta: u8,
+    pub first: Option<(u32, u32)>,
+    // the differing pixels in red over a dimmed copy of the expected image, for looking at
+    pub image: Vec<u8>,
+}
+
+pub fn diff_images(width: u32, expected: &[u8], actual: &[u8], tolerance: u8) -> ImageDiff {
+    let mut diff = ImageDiff { pixels: 0, max_delta: 0, first: None, image: Vec::with_capacity(expected.len()) };
+
+    for (idx, (want, got)) in expected.chunks_exact(4).zip(actual.chunks_exact(4)).enumerate() {
+        let delta = want.iter().zip(got).map(|(a, b)| a.abs_diff(*b)).max().unwrap();
+        diff.max_delta = diff.max_delta.max(delta);
+
+        if delta > tolerance {
+            diff.pixels += 1;
+            diff.first.get_or_insert((idx as u32 % width, idx as u32 / width));
+            diff.image.extend_from_slice(&[0xFF, 0x00, 0x00, 0xFF]);
+        }
+        else {
+            let grey = ((want[0] as u32 + want[1] as u32 + want[2] as u32) / 12) as u8;
+            diff.image.extend_from_slice(&[grey, grey, grey, 0xFF]);
+        }
+    }
+
+    return diff;
+}
+
+// read the VDP's current framebuffer back from the GPU & save it (as shown, so a render debug view gets saved as-is)
+pub fn save_framebuffer(vdp: &mut VDP, gfx_device: &Device, path: &Path) -> Result<(), String> {
+    let (width, height, rgba) = vdp.read_framebuffer(gfx_device)?;
+
+    return write_png(path, width, height, &rgba).map_err(|e| format!("failed to write {}: {}", path.display(), e));
+}
+
+// same, but leave the encoding to the capture workers
+// reads the framebuffer back (waiting on the GPU) & queues the PNG. returns false if the capture queue dropped it
+pub fn queue_framebuffer(vdp: &mut VDP, gfx_device: &Device, capture: &CaptureWriter, path: &Path) -> Result<bool, String> {
+    let (width, height, rgba) = vdp.read_framebuffer(gfx_device)?;
+    return Ok(capture.write_png(path.to_path_buf(), width, height, rgba));
+}
+
+// framebuffer is FBDIM (w | h << 16) pixels of packed RGBA8 starting at word address FBADDR
+pub fn framebuffer_rgba(vdp_regs: &[u32], vram: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let (Some(fb_dim), Some(fb_addr)) = (vdp_regs.get(INTERNALREG_FBDIM as usize), vdp_regs.get(INTERNALREG_FBADDR as usize)) else {
+        return Err("VDP registers are missing".to_string());
+    };
+
+    let fb_addr = *fb_addr as usize * 4;
+    let width = fb_dim & 0xFFFF;
+    let height = fb_dim >> 16;
+    let len = width as usize * height as usize * 4;
+
+    if width == 0 || height == 0 {
+        return Err("framebuffer dimensions are not set".to_string());
+    }
+
+    let Some(pixels) = vram.get(fb_addr..fb_addr + len) else {
+        return Err(format!("framebuffer at {:08x} ({}x{}) lies outside VRAM", fb_addr, width, height));
+    };
+
+    return Ok((width, height, pixels.to_vec()));
+}
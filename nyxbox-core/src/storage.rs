@@ -0,0 +1,182 @@
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}};
+
+use serde_json::{json, Value};
+
+// how persistent per-game data (save RAM, RTC, states, etc) gets laid out on disk
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum StorageLayout {
+    // everything for a game packed into a single <game>.nyxsav file
+    File,
+    // one file per entry under <game>/
+    #[default]
+    Directory,
+    // like Directory, but every write is atomic (write + rename) and tracked in an index.json, so sync tools never see half-written or opaque files
+    Synced,
+}
+
+pub struct SaveStore {
+    layout: StorageLayout,
+    root: PathBuf,
+    game_id: String,
+}
+
+const CONTAINER_MAGIC: &[u8;4] = b"NXSV";
+const INDEX_FILE: &str = "index.json";
+
+impl SaveStore {
+    pub fn new(root: &Path, game_id: &str, layout: StorageLayout) -> Self {
+        Self {
+            layout,
+            root: root.to_path_buf(),
+            game_id: sanitize(game_id),
+        }
+    }
+
+    pub fn game_dir(self: &Self) -> PathBuf {
+        return self.root.join(&self.game_id);
+    }
+
+    pub fn read(self: &Self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match self.layout {
+            StorageLayout::File => {
+                return Ok(self.read_container()?.remove(name));
+            }
+            StorageLayout::Directory | StorageLayout::Synced => {
+                return match fs::read(self.game_dir().join(name)) {
+                    Ok(v) => Ok(Some(v)),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e),
+                };
+            }
+        }
+    }
+
+    pub fn write(self: &Self, name: &str, data: &[u8]) -> io::Result<()> {
+        match self.layout {
+            StorageLayout::File => {
+                let mut entries = self.read_container()?;
+                entries.insert(name.to_string(), data.to_vec());
+                fs::create_dir_all(&self.root)?;
+                return write_atomic(&self.container_path(), &encode_container(&entries));
+            }
+            StorageLayout::Directory => {
+                fs::create_dir_all(self.game_dir())?;
+                return fs::write(self.game_dir().join(name), data);
+            }
+            StorageLayout::Synced => {
+                fs::create_dir_all(self.game_dir())?;
+                write_atomic(&self.game_dir().join(name), data)?;
+                return self.update_index(name, data);
+            }
+        }
+    }
+
+    fn container_path(self: &Self) -> PathBuf {
+        return self.root.join(format!("{}.nyxsav", self.game_id));
+    }
+
+    fn read_container(self: &Self) -> io::Result<BTreeMap<String, Vec<u8>>> {
+        return match fs::read(self.container_path()) {
+            Ok(v) => decode_container(&v),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e),
+        };
+    }
+
+    fn update_index(self: &Self, name: &str, data: &[u8]) -> io::Result<()> {
+        let index_path = self.game_dir().join(INDEX_FILE);
+
+        let mut index = fs::read(&index_path).ok()
+            .and_then(|v| serde_json::from_slice::<Value>(&v).ok())
+            .filter(Value::is_object)
+            .unwrap_or(json!({}));
+
+        index["game"] = json!(self.game_id);
+        index["entries"][name] = json!({
+            "size": data.len(),
+            "hash": format!("{:08x}", fnv1a(data)),
+            "modified": chrono::Utc::now().to_rfc3339(),
+        });
+
+        return write_atomic(&index_path, serde_json::to_string_pretty(&index).unwrap().as_bytes());
+    }
+}
+
+// memory card images are the single-file (.nyxsav) containers - these let offline tools get at individual entries
+pub fn read_card(path: &Path) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    return decode_container(&fs::read(path)?);
+}
+
+pub fn write_card(path: &Path, entries: &BTreeMap<String, Vec<u8>>) -> io::Result<()> {
+    return write_atomic(path, &encode_container(entries));
+}
+
+// derive a stable per-game id from the ROM path (an image with no ROM file, only --bios or --load, just gets "test")
+pub fn game_id_for(rom: Option<&Path>) -> String {
+    return rom.and_then(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or("test".to_string());
+}
+
+fn sanitize(name: &str) -> String {
+    return name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' }).collect();
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    fs::write(&tmp, data)?;
+    return fs::rename(&tmp, path);
+}
+
+pub fn fnv1a(data: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811C9DC5;
+    for b in data {
+        hash ^= *b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    return hash;
+}
+
+// container format: magic, then repeated [name len: u32][name][data len: u32][data], all little endian
+fn encode_container(entries: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut out = CONTAINER_MAGIC.to_vec();
+
+    for (name, data) in entries {
+        out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+
+    return out;
+}
+
+fn decode_container(data: &[u8]) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "corrupt save container");
+
+    if data.len() < 4 || &data[0..4] != CONTAINER_MAGIC {
+        return Err(bad());
+    }
+
+    let mut entries = BTreeMap::new();
+    let mut pos = 4;
+
+    let take = |len: usize, pos: &mut usize| -> io::Result<&[u8]> {
+        let chunk = data.get(*pos..*pos + len).ok_or_else(bad)?;
+        *pos += len;
+        return Ok(chunk);
+    };
+
+    while pos < data.len() {
+        let name_len = u32::from_le_bytes(take(4, &mut pos)?.try_into().unwrap()) as usize;
+        let name = String::from_utf8_lossy(take(name_len, &mut pos)?).to_string();
+        let data_len = u32::from_le_bytes(take(4, &mut pos)?.try_into().unwrap()) as usize;
+        let entry = take(data_len, &mut pos)?.to_vec();
+        entries.insert(name, entry);
+    }
+
+    return Ok(entries);
+}
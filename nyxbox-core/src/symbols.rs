@@ -0,0 +1,182 @@
+use std::{fs, path::Path, sync::{Arc, OnceLock}};
+
+// guest symbols from an ELF built alongside the ROM, so diagnostics can say "main+0x1c" instead of a bare address. only
+// 32-bit little endian ELFs (what an ARM toolchain produces) are understood, & only the static symbol table is read -
+// a stripped image has nothing to offer
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8    = 1;
+const ELFDATA2LSB: u8   = 1;
+
+const SHT_SYMTAB: u32   = 2;
+const SECTION_LEN: usize = 40;
+const SYMBOL_LEN: usize = 16;
+
+const STT_NOTYPE: u8    = 0;
+const STT_OBJECT: u8    = 1;
+const STT_FUNC: u8      = 2;
+
+const SHN_UNDEF: u16    = 0;
+
+struct Symbol {
+    addr: u32,
+    size: u32,
+    name: String,
+}
+
+pub struct SymbolTable {
+    // sorted by address
+    symbols: Vec<Symbol>,
+}
+
+// a System's symbols. every part of the machine that reports addresses holds a clone, & they all see the table once it's
+// loaded
+#[derive(Clone, Default)]
+pub struct Symbols(Arc<OnceLock<SymbolTable>>);
+
+impl SymbolTable {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        check_elf(data)?;
+
+        let shnum = read_u16(data, 0x30)? as usize;
+        let mut symbols = Vec::new();
+
+        for idx in 0..shnum {
+            let header = section_header(data, idx)?;
+
+            if read_u32(header, 4)? != SHT_SYMTAB {
+                continue;
+            }
+
+            let table = file_range(data, read_u32(header, 16)?, read_u32(header, 20)?)?;
+            let strtab_header = section_header(data, read_u32(header, 24)? as usize)?;
+            let strtab = file_range(data, read_u32(strtab_header, 16)?, read_u32(strtab_header, 20)?)?;
+
+            for sym in table.chunks_exact(SYMBOL_LEN) {
+                let kind = sym[12] & 0xF;
+
+                if read_u16(sym, 14)? == SHN_UNDEF || ![STT_NOTYPE, STT_OBJECT, STT_FUNC].contains(&kind) {
+                    continue;
+                }
+
+                let name = read_str(strtab, read_u32(sym, 0)? as usize);
+
+                // $a, $t & $d only mark where ARM code, Thumb code & data start
+                if name.is_empty() || name.starts_with('$') {
+                    continue;
+                }
+
+                // a Thumb function's address has bit 0 set
+                let addr = read_u32(sym, 4)? & if kind == STT_FUNC { !1 } else { !0 };
+
+                symbols.push(Symbol { addr, size: read_u32(sym, 8)?, name: name.to_string() });
+            }
+        }
+
+        // sized symbols first at the same address, so they win over a bare label sitting on top of them
+        symbols.sort_by_key(|sym| (sym.addr, sym.size == 0));
+        symbols.dedup_by_key(|sym| sym.addr);
+
+        return Ok(Self { symbols });
+    }
+
+    pub fn len(self: &Self) -> usize {
+        return self.symbols.len();
+    }
+
+    // the symbol addr falls in, & how far into it. a symbol without a size covers everything up to the next one
+    pub fn lookup(self: &Self, addr: u32) -> Option<(&str, u32)> {
+        let idx = self.symbols.partition_point(|sym| sym.addr <= addr).checked_sub(1)?;
+        let sym = &self.symbols[idx];
+
+        if sym.size != 0 && addr - sym.addr >= sym.size {
+            return None;
+        }
+
+        return Some((&sym.name, addr - sym.addr));
+    }
+}
+
+impl Symbols {
+    // load the symbols every diagnostic will use from here on. returns how many there were
+    pub fn load(self: &Self, path: &Path) -> Result<usize, String> {
+        let data = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let table = SymbolTable::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let len = table.len();
+
+        self.0.set(table).map_err(|_| "symbols are already loaded".to_string())?;
+        return Ok(len);
+    }
+
+    pub fn lookup(self: &Self, addr: u32) -> Option<(&str, u32)> {
+        return self.0.get()?.lookup(addr);
+    }
+
+    // "main+0x1c", or just "main" at its start
+    pub fn name(self: &Self, addr: u32) -> Option<String> {
+        let (name, offs) = self.lookup(addr)?;
+        return Some(if offs == 0 { name.to_string() } else { format!("{}+{:#x}", name, offs) });
+    }
+
+    // an address for a diagnostic: "0100021c <main+0x1c>" when there's a symbol for it, otherwise just the hex
+    pub fn describe(self: &Self, addr: u32) -> String {
+        return match self.name(addr) {
+            Some(name) => format!("{:08x} <{}>", addr, name),
+            None => format!("{:08x}", addr),
+        };
+    }
+}
+
+// the ELFs this can read - the line table (lines.rs) & variables (locals.rs) come from the same one
+pub(crate) fn check_elf(data: &[u8]) -> Result<(), String> {
+    if data.len() < 0x34 || &data[..4] != ELF_MAGIC {
+        return Err("not an ELF file".to_string());
+    }
+
+    if data[4] != ELFCLASS32 || data[5] != ELFDATA2LSB {
+        return Err("only 32-bit little endian ELFs are supported".to_string());
+    }
+
+    return Ok(());
+}
+
+fn section_header(data: &[u8], idx: usize) -> Result<&[u8], String> {
+    let start = read_u32(data, 0x20)? as usize + idx * SECTION_LEN;
+    return data.get(start..start + SECTION_LEN).ok_or(format!("section header {} is past the end of the file", idx));
+}
+
+// the contents of the section called `name`, if there is one
+pub(crate) fn find_section<'a>(data: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, String> {
+    let shnum = read_u16(data, 0x30)? as usize;
+    let names_header = section_header(data, read_u16(data, 0x32)? as usize)?;
+    let names = file_range(data, read_u32(names_header, 16)?, read_u32(names_header, 20)?)?;
+
+    for idx in 0..shnum {
+        let header = section_header(data, idx)?;
+
+        if read_str(names, read_u32(header, 0)? as usize) == name {
+            return file_range(data, read_u32(header, 16)?, read_u32(header, 20)?).map(Some);
+        }
+    }
+
+    return Ok(None);
+}
+
+fn file_range(data: &[u8], offset: u32, size: u32) -> Result<&[u8], String> {
+    return data.get(offset as usize..offset as usize + size as usize).ok_or("section is past the end of the file".to_string());
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    let bytes = data.get(offset..offset + 2).ok_or("truncated ELF".to_string())?;
+    return Ok(u16::from_le_bytes(bytes.try_into().unwrap()));
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    let bytes = data.get(offset..offset + 4).ok_or("truncated ELF".to_string())?;
+    return Ok(u32::from_le_bytes(bytes.try_into().unwrap()));
+}
+
+pub(crate) fn read_str(data: &[u8], offset: usize) -> &str {
+    let bytes = data.get(offset..).unwrap_or(&[]);
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    return std::str::from_utf8(&bytes[..len]).unwrap_or("");
+}
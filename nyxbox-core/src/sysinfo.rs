@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::{mem::MemoryMap, movie::frame_seed, peripheral::Peripheral, timebase::Timebase};
+
+pub const SYSINFO_MEM_SIZE: u32 = 4096;
+
+// "NYXB" - lets guest code probe for the register block before trusting anything else in it
+pub const SYSINFO_ID: u32 = 0x4E595842;
+
+pub const FEATUREBIT_UART: u32              = 1;
+pub const FEATUREBIT_CLOCK: u32             = 2;
+pub const FEATUREBIT_VDP: u32               = 4;
+pub const FEATUREBIT_DETERMINISTIC: u32     = 8;
+pub const FEATUREBIT_DEBUGPORT: u32         = 16;
+pub const FEATUREBIT_MPU: u32               = 32;
+pub const FEATUREBIT_FRAMEBUDGET: u32       = 64;
+pub const FEATUREBIT_EXPANSIONRAM: u32      = 128;
+pub const FEATUREBIT_GAMEPAD: u32           = 256;
+pub const FEATUREBIT_POISON: u32            = 512;
+pub const FEATUREBIT_STRICTHW: u32          = 1024;
+pub const FEATUREBIT_INTC: u32              = 2048;
+pub const FEATUREBIT_BUSERR: u32            = 4096;
+pub const FEATUREBIT_COPROCESSOR: u32       = 8192;
+pub const FEATUREBIT_WATCHDOG: u32          = 16384;
+
+// read-only block describing the emulator build & which optional hardware is present
+pub struct SysInfo {
+    features: u32,
+    seed: u64,
+    ram_size: u32,
+    main_ram_size: u32,
+    rom_size: u32,
+    cpu_budget: u32,
+    timebase: Arc<Timebase>,
+}
+
+impl SysInfo {
+    pub fn new(features: u32, seed: u64, map: &MemoryMap, ram_size: u32, cpu_budget: u32, timebase: Arc<Timebase>) -> Self {
+        Self {
+            features,
+            seed,
+            ram_size,
+            main_ram_size: map.main_ram_size as u32,
+            rom_size: map.boot_rom_size as u32,
+            cpu_budget,
+            timebase,
+        }
+    }
+
+    pub fn version() -> u32 {
+        let major: u32 = env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap();
+        let minor: u32 = env!("CARGO_PKG_VERSION_MINOR").parse().unwrap();
+        let patch: u32 = env!("CARGO_PKG_VERSION_PATCH").parse().unwrap();
+
+        return (major << 16) | ((minor & 0xFF) << 8) | (patch & 0xFF);
+    }
+}
+
+impl Peripheral for SysInfo {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // ID
+                return SYSINFO_ID;
+            }
+            0x01 => {
+                // VERSION
+                return Self::version();
+            }
+            0x02 => {
+                // FEATURES
+                return self.features;
+            }
+            0x03 => {
+                // SEEDLO
+                return self.seed as u32;
+            }
+            0x04 => {
+                // SEEDHI
+                return (self.seed >> 32) as u32;
+            }
+            0x05 => {
+                // FRAME
+                return self.timebase.frame() as u32;
+            }
+            0x06 => {
+                // FRAMESEED - differs every frame, but is reproducible given the same seed (e.g. when replaying a movie)
+                return frame_seed(self.seed, self.timebase.frame()) as u32;
+            }
+            0x07 => {
+                // RAMSIZE - total bytes of RAM, including any expansion
+                return self.ram_size;
+            }
+            0x08 => {
+                // CPUBUDGET - instructions the CPU gets per frame (0 = unlimited), i.e. the clock speed it's emulated at
+                return self.cpu_budget;
+            }
+            0x09 => {
+                // MAINRAMSIZE - bytes of main RAM alone, i.e. how far it runs from its base
+                return self.main_ram_size;
+            }
+            0x0A => {
+                // ROMSIZE - bytes of boot ROM
+                return self.rom_size;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, _addr: u32, _val: u32) {
+    }
+}
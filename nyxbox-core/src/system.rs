@@ -0,0 +1,465 @@
+use std::{io::Write, sync::Arc, time::Duration};
+
+use sdl3::gpu::{CommandBuffer, Device};
+use unicorn_engine::Permission;
+
+use crate::{bios::BIOS_PUTC, buserr::{BusError, UnmappedPolicy, BUSERR_MEM_SIZE}, chrometrace::{ChromeTrace, TRACK_FRONTEND}, clock::{Clock, CLOCK_MEM_SIZE}, debugport::{DebugPort, DEBUGPORT_MEM_SIZE}, devmap::DeviceMap, fault::FaultMode, framebudget::{FrameBudget, FRAMEBUDGET_MEM_SIZE}, gamepad::{Gamepad, GAMEPAD_MEM_SIZE}, hwmodel::{HardwareModel, HwLimits}, intc::{InterruptController, INTC_MEM_SIZE, IRQ_MAILBOX, IRQ_TIMER, IRQ_UART_RX, IRQ_VBLANK, IRQ_VDP, IRQ_WATCHDOG}, lines::Lines, locals::Locals, machine::{CpuModel, Machine, MachineRunContext}, mailbox::{Mailbox, Side, MAILBOX_MEM_SIZE}, mem::{Memory, MemoryMap, BOOT_ROM_BEGIN}, memfill::{self, MemoryFill}, mpu::{Mpu, MPU_MEM_SIZE}, peripheral::Peripheral, poison::{PoisonMap, POISON_BYTE, POISON_MEM_SIZE}, renderdebug::RenderDebugMode, savestate::{self, SaveState, Snapshots, SECTION_BUSERR, SECTION_CLOCK, SECTION_GAMEPAD, SECTION_INTC, SECTION_MAILBOX, SECTION_MPU, SECTION_UART, SECTION_VDP_PORT, SECTION_WATCHDOG}, symbols::Symbols, sysinfo::{self, SysInfo, SYSINFO_MEM_SIZE}, timebase::Timebase, uart::{UART, UART_MEM_SIZE}, vdp::{VDP, VRAM_SIZE}, vdpcheck::StrictLog, vdpport::{VdpPort, VDPPORT_MEM_SIZE}, watchdog::{Watchdog, WATCHDOG_MEM_SIZE}};
+
+// the whole console, assembled: the CPU & memory map with every peripheral wired up, the VDP, & what save states need to
+// capture. a frontend builds one from a SystemConfig, starts it with power_on, & then calls step_frame once per emulated
+// frame - input, presenting, & anything else the user sees stay with the frontend. like Machine, the CPU thread's run
+// context belongs to whoever started it, so anything that has to restart the CPU takes it & hands back the new one
+
+// how long to wait at a time for a CPU that has to finish its frame before going on - a debugger stopping it mid-frame
+// gives up on the wait rather than hanging the frontend
+const FRAME_DONE_POLL: Duration = Duration::from_millis(100);
+
+// how many instructions a source line step goes through looking for the next line before stopping wherever it's got to -
+// stepping into code without line info (a library built without -g, say), or a line that loops forever, would otherwise
+// never end. they're stepped on the CPU thread, so this is a few seconds at most
+const STEP_LINE_LIMIT: u32 = 1_000_000;
+
+// everything about the machine that's fixed once it's built
+#[derive(Clone)]
+pub struct SystemConfig {
+    // where memory & the peripherals are (see mem.rs) - Memory has to be built to the same one
+    pub memory_map: MemoryMap,
+    pub cpu_model: CpuModel,
+    // instructions per frame (0 = unlimited)
+    pub cpu_budget: u64,
+    pub deterministic: bool,
+    pub idle_skip: bool,
+    pub expansion_ram: bool,
+    pub coprocessor: bool,
+    pub poison: bool,
+    pub hw_model: HardwareModel,
+    // the guest's default per-frame CPU budget, in microseconds of emulated time (0 = only flag missed frames)
+    pub frame_budget: u32,
+    pub open_bus_value: u32,
+    pub fault_mode: FaultMode,
+    pub unmapped: UnmappedPolicy,
+    pub boot_fill: MemoryFill,
+    pub vdp_strict: bool,
+    pub vdp_unchecked: bool,
+    pub render_debug: RenderDebugMode,
+}
+
+impl SystemConfig {
+    // what SysInfo reports to the guest. a movie is tied to these, so they're known before the machine is built
+    pub fn features(self: &Self) -> u32 {
+        return sysinfo::FEATUREBIT_UART | sysinfo::FEATUREBIT_CLOCK | sysinfo::FEATUREBIT_VDP | sysinfo::FEATUREBIT_DEBUGPORT | sysinfo::FEATUREBIT_MPU | sysinfo::FEATUREBIT_GAMEPAD | sysinfo::FEATUREBIT_INTC | sysinfo::FEATUREBIT_BUSERR | sysinfo::FEATUREBIT_WATCHDOG |
+            if self.deterministic { sysinfo::FEATUREBIT_DETERMINISTIC } else { sysinfo::FEATUREBIT_FRAMEBUDGET } |
+            if self.expansion_ram { sysinfo::FEATUREBIT_EXPANSIONRAM } else { 0 } |
+            if self.poison { sysinfo::FEATUREBIT_POISON } else { 0 } |
+            if self.coprocessor { sysinfo::FEATUREBIT_COPROCESSOR } else { 0 } |
+            if HwLimits::new(self.hw_model).is_strict() { sysinfo::FEATUREBIT_STRICTHW } else { 0 };
+    }
+}
+
+// what changes for the guest at the start of the next frame
+#[derive(Default)]
+pub struct FrameInput {
+    // the buttons held from now on, if they're to change
+    pub pad: Option<u32>,
+    pub uart: Vec<u8>,
+}
+
+// the frames run ahead are thrown away by putting this back
+pub struct RunAhead {
+    state: SaveState,
+    debug_events: usize,
+}
+
+pub struct System<'a> {
+    pub machine: Machine<'a>,
+    pub vdp: VDP,
+    pub frame: u64,
+    pub hw: Arc<HwLimits>,
+    pub timebase: Arc<Timebase>,
+    pub intc: Arc<InterruptController>,
+    pub uart: Arc<UART>,
+    pub clock: Arc<Clock>,
+    pub vdp_port: Arc<VdpPort>,
+    pub debugport: Arc<DebugPort>,
+    pub mpu: Arc<Mpu>,
+    pub frame_budget: Arc<FrameBudget>,
+    pub gamepad: Arc<Gamepad>,
+    pub bus_error: Arc<BusError>,
+    pub watchdog: Arc<Watchdog>,
+    pub sysinfo: Arc<SysInfo>,
+    pub mailbox: Option<Arc<Mailbox>>,
+    pub poison: Option<Arc<PoisonMap>>,
+    // every peripheral, for lock stats
+    pub peripherals: Vec<Arc<dyn Peripheral>>,
+    // what save states capture beyond the CPU, memory, & VDP
+    pub snapshots: Snapshots,
+    pub map: MemoryMap,
+    // guest debug info, empty until something is loaded into it. everything that reports guest addresses shares these
+    pub symbols: Symbols,
+    pub lines: Lines,
+    pub locals: Locals,
+    chrome_trace: Option<Arc<ChromeTrace>>,
+    config: SystemConfig,
+}
+
+impl <'a> System<'a> {
+    // builds the machine around `mem`, with the UART's output going to `uart_out`. nothing is loaded & the CPU isn't running
+    // yet - CPU hooks (breakpoints, tracing) go on `machine` now, then the image, then power_on
+    pub fn new<W: Write + Send + 'static>(config: &SystemConfig, seed: u64, mem: &'a mut Memory, gfx_device: &Device, uart_out: W) -> Result<Self, String> {
+        let ram_size = mem.ram_size();
+        let map = &config.memory_map;
+        let symbols = Symbols::default();
+
+        let mut machine = Machine::new(config.cpu_model, *map, symbols.clone());
+        let intc = Arc::new(InterruptController::new());
+
+        // the coprocessor shares system memory, so it has to be there before that's mapped
+        let mailbox = config.coprocessor.then(|| Arc::new(Mailbox::new(intc.line(IRQ_MAILBOX))));
+
+        if let Some(mailbox) = &mailbox {
+            machine.add_coprocessor(config.cpu_model, mailbox.clone(), map.mailbox_begin as u32, MAILBOX_MEM_SIZE);
+        }
+
+        // map system memory
+        let Memory { boot_rom, main_ram, expansion_ram } = mem;
+
+        machine.map_memory(boot_rom, BOOT_ROM_BEGIN as u32, Permission::READ | Permission::EXEC);
+        machine.map_memory(main_ram, map.main_ram_begin as u32, Permission::ALL);
+
+        if let Some(expansion_ram) = expansion_ram {
+            machine.map_memory(expansion_ram, map.expansion_ram_begin as u32, Permission::ALL);
+        }
+
+        // every limit that differs between the strict & developer hardware models goes through this
+        let hw = Arc::new(HwLimits::new(config.hw_model));
+        machine.set_hw_limits(hw.clone());
+
+        // map peripherals. the ones that are only MMIO go in the device map, which is mapped once they're all in - that's
+        // the place to add new hardware
+        let mut devices = DeviceMap::new();
+
+        machine.map_intc(intc.clone(), map.intc_begin as u32, INTC_MEM_SIZE);
+
+        let uart = Arc::new(UART::new(uart_out, hw.clone(), intc.line(IRQ_UART_RX)));
+        // emulated time, shared by everything that needs to know what time it is
+        let timebase = Arc::new(if config.deterministic { Timebase::deterministic(machine.instruction_counter(), config.cpu_budget) } else { Timebase::new() });
+
+        let clock = Arc::new(Clock::new(timebase.clone(), intc.line(IRQ_TIMER)));
+
+        if config.deterministic {
+            machine.set_deterministic(timebase.clone(), clock.clone());
+        }
+        else {
+            clock.start_timer();
+
+            if config.idle_skip {
+                machine.set_idle_skip(timebase.clone(), clock.clone());
+            }
+        }
+
+        devices.add("uart", uart.clone(), map.uart_begin as u32, UART_MEM_SIZE, 0)?;
+
+        // BIOS putc goes out the same way as a TX register write
+        let putc_uart = uart.clone();
+        machine.register_swi(BIOS_PUTC, Box::new(move |call| {
+            putc_uart.write(0x01, call.arg(0));
+            return false;
+        }));
+        devices.add("clock", clock.clone(), map.clock_begin as u32, CLOCK_MEM_SIZE, 0)?;
+
+        let poison = config.poison.then(|| Arc::new(PoisonMap::new(map.main_ram_begin as u32, map.main_ram_size as u32, symbols.clone())));
+
+        let vdp_port = Arc::new(VdpPort::new(*map, poison.clone(), config.vdp_strict.then(|| StrictLog::new(symbols.clone())), hw.clone(), intc.line(IRQ_VDP)));
+        machine.map_vdp_port(vdp_port.clone(), map.vdp_begin as u32, VDPPORT_MEM_SIZE);
+
+        let debugport = Arc::new(DebugPort::new());
+        devices.add("debugport", debugport.clone(), map.debugport_begin as u32, DEBUGPORT_MEM_SIZE, 0)?;
+
+        let mpu = Arc::new(Mpu::new());
+        mpu.add_area(BOOT_ROM_BEGIN as u32, map.boot_rom_size as u32, Permission::READ | Permission::EXEC);
+        mpu.add_area(map.main_ram_begin as u32, map.main_ram_size as u32, Permission::ALL);
+        if config.expansion_ram {
+            mpu.add_area(map.expansion_ram_begin as u32, map.expansion_ram_size as u32, Permission::ALL);
+        }
+        machine.map_mpu(mpu.clone(), map.mpu_begin as u32, MPU_MEM_SIZE);
+
+        let frame_budget = Arc::new(FrameBudget::new(config.frame_budget, timebase.clone()));
+        machine.map_frame_budget(frame_budget.clone(), map.framebudget_begin as u32, FRAMEBUDGET_MEM_SIZE);
+
+        let gamepad = Arc::new(Gamepad::new());
+        devices.add("gamepad", gamepad.clone(), map.gamepad_begin as u32, GAMEPAD_MEM_SIZE, 0)?;
+
+        if let Some(poison) = &poison {
+            machine.map_poison(poison.clone(), map.poison_begin as u32, POISON_MEM_SIZE);
+        }
+
+        let bus_error = Arc::new(BusError::new(config.open_bus_value));
+        machine.map_bus_error(bus_error.clone(), map.buserr_begin as u32, BUSERR_MEM_SIZE);
+
+        let watchdog = Arc::new(Watchdog::new(timebase.clone(), intc.line(IRQ_WATCHDOG)));
+        devices.add("watchdog", watchdog.clone(), map.watchdog_begin as u32, WATCHDOG_MEM_SIZE, 0)?;
+
+        let sysinfo = Arc::new(SysInfo::new(config.features(), seed, map, ram_size as u32, config.cpu_budget.min(u32::MAX as u64) as u32, timebase.clone()));
+        devices.add("sysinfo", sysinfo.clone(), map.sysinfo_begin as u32, SYSINFO_MEM_SIZE, 0)?;
+
+        devices.map(&mut machine)?;
+
+        machine.set_cpu_budget(config.cpu_budget);
+        machine.set_fault_mode(config.fault_mode);
+        machine.set_unmapped_policy(config.unmapped);
+
+        // power-on memory contents
+        if config.boot_fill != MemoryFill::Zero {
+            let mut ram = vec![0;map.main_ram_size];
+            memfill::fill(config.boot_fill, seed, 0, &mut ram);
+            machine.write_memory(map.main_ram_begin as u32, &ram);
+
+            if config.expansion_ram {
+                let mut xram = vec![0;map.expansion_ram_size];
+                memfill::fill(config.boot_fill, seed, 1, &mut xram);
+                machine.write_memory(map.expansion_ram_begin as u32, &xram);
+            }
+        }
+
+        // poisoned RAM holds the canary, so a read that does slip through still stands out
+        if poison.is_some() {
+            machine.write_memory(map.main_ram_begin as u32, &vec![POISON_BYTE;map.main_ram_size]);
+        }
+
+        let mut peripherals: Vec<Arc<dyn Peripheral>> = vec![intc.clone(), vdp_port.clone(), mpu.clone(), frame_budget.clone(), bus_error.clone()];
+        peripherals.extend(devices.peripherals());
+
+        if let Some(poison) = &poison {
+            peripherals.push(poison.clone());
+        }
+
+        let mut snapshots: Snapshots = vec![
+            (SECTION_VDP_PORT, vdp_port.clone()),
+            (SECTION_INTC, intc.clone()),
+            (SECTION_CLOCK, clock.clone()),
+            (SECTION_UART, uart.clone()),
+            (SECTION_GAMEPAD, gamepad.clone()),
+            (SECTION_MPU, mpu.clone()),
+            (SECTION_BUSERR, bus_error.clone()),
+            (SECTION_WATCHDOG, watchdog.clone()),
+        ];
+
+        if let Some(mailbox) = &mailbox {
+            snapshots.push((SECTION_MAILBOX, Arc::new(mailbox.port(Side::Main))));
+        }
+
+        // set up VDP
+        let mut vdp = VDP::new(gfx_device);
+        vdp.set_unchecked(config.vdp_unchecked);
+        vdp.set_render_debug(config.render_debug);
+        vdp.set_strict(config.vdp_strict);
+        vdp.set_deterministic(config.deterministic);
+
+        // GPU buffers don't start out zeroed, so VRAM gets filled whatever the setting
+        let cmd_buffer = gfx_device.acquire_command_buffer().map_err(|e| e.to_string())?;
+
+        let mut vram = vec![0;VRAM_SIZE as usize];
+        memfill::fill(config.boot_fill, seed, 2, &mut vram);
+        vdp.upload(&vram.chunks_exact(4).map(|w| u32::from_le_bytes(w.try_into().unwrap())).collect::<Vec<u32>>(), 0, gfx_device, &cmd_buffer);
+        cmd_buffer.submit().map_err(|e| e.to_string())?;
+
+        // the fill isn't guest traffic, keep it out of the guest-visible DMA stats
+        vdp.take_stats();
+
+        return Ok(Self {
+            machine,
+            vdp,
+            frame: 0,
+            hw,
+            timebase,
+            intc,
+            uart,
+            clock,
+            vdp_port,
+            debugport,
+            mpu,
+            frame_budget,
+            gamepad,
+            bus_error,
+            watchdog,
+            sysinfo,
+            mailbox,
+            poison,
+            peripherals,
+            snapshots,
+            map: *map,
+            symbols,
+            lines: Lines::default(),
+            locals: Locals::default(),
+            chrome_trace: None,
+            config: config.clone(),
+        });
+    }
+
+    // CPU spans go in with the rest - like any CPU hook, before power_on
+    pub fn set_chrome_trace(self: &mut Self, trace: Arc<ChromeTrace>) {
+        self.machine.set_chrome_trace(trace.clone());
+        self.intc.set_chrome_trace(trace.clone());
+        self.vdp.set_chrome_trace(trace.clone());
+        self.chrome_trace = Some(trace);
+    }
+
+    // resets the CPU into whatever image was loaded last & starts it. after a stop, this is how a new ROM gets booted
+    pub fn power_on(self: &mut Self) -> MachineRunContext {
+        self.machine.reset();
+        return self.machine.run();
+    }
+
+    // the same, but with the CPU parked at the reset vector until something resumes it
+    pub fn power_on_paused(self: &mut Self) -> MachineRunContext {
+        self.machine.reset();
+        return self.machine.run_paused();
+    }
+
+    // breakpoints are CPU hooks, which can only change with the CPU thread stopped - so the run is restarted around the
+    // change, left paused if it was (or if the guest is halted, so a dead CPU doesn't come back to life)
+    pub fn change_machine<T>(self: &mut Self, run_ctx: MachineRunContext, halted: bool, change: impl FnOnce(&mut Machine<'a>) -> T) -> (MachineRunContext, T) {
+        let paused = run_ctx.is_paused() || halted;
+        run_ctx.stop();
+
+        let res = change(&mut self.machine);
+
+        return (if paused { self.machine.run_paused() } else { self.machine.run() }, res);
+    }
+
+    // the CPU has to be paused, so everything in the state agrees
+    pub fn save_state(self: &mut Self, run_ctx: &MachineRunContext, gfx_device: &Device) -> Result<SaveState, String> {
+        return savestate::capture_state(run_ctx, &mut self.vdp, gfx_device, &self.timebase, &self.snapshots, &self.map, self.config.expansion_ram);
+    }
+
+    // picks up at the frame the state was taken on, paused there if asked
+    pub fn load_state(self: &mut Self, run_ctx: MachineRunContext, state: &SaveState, gfx_device: &Device, paused: bool) -> MachineRunContext {
+        run_ctx.stop();
+        self.frame = savestate::restore_state(state, &mut self.machine, &mut self.vdp, gfx_device, &self.timebase, &self.snapshots, &self.map);
+
+        return if paused { self.machine.run_paused() } else { self.machine.run() };
+    }
+
+    // the end of an emulated frame: the VDP runs what the guest queued during it, the input goes in, emulated time moves on,
+    // & the frame signal & vblank wake the CPU for the next one. returns true if the watchdog expired, which the frontend
+    // answers with a reset once the frame is done
+    pub fn step_frame(self: &mut Self, run_ctx: &MachineRunContext, gfx_device: &Device, cmd_buf: &CommandBuffer, input: FrameInput) -> bool {
+        // deterministic runs are always in lockstep, however long the frame takes - with a CPU budget it does end
+        if self.config.deterministic {
+            wait_frame_done(run_ctx);
+        }
+
+        let vdp_span = self.chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "frontend", format!("vdp frame {}", self.frame)));
+        self.vdp.begin_frame(self.frame, gfx_device, cmd_buf);
+        self.vdp_port.service(&mut self.vdp, run_ctx, gfx_device, cmd_buf);
+        self.vdp.end_frame(gfx_device, cmd_buf);
+        drop(vdp_span);
+
+        if let Some(buttons) = input.pad {
+            self.gamepad.latch(buttons);
+        }
+
+        self.frame += 1;
+        self.timebase.set_frame(self.frame);
+        self.clock.frame_advanced();
+
+        let watchdog = self.watchdog.poll();
+
+        // deterministic mode only gets here with the CPU idle, so UART input reaches the guest at a frame boundary rather
+        // than wherever the CPU happened to be when it arrived
+        if !input.uart.is_empty() {
+            self.uart.push_input(&input.uart);
+        }
+
+        // the frame signal wakes the CPU from WFI whether or not the guest takes the vblank IRQ. a deterministic CPU
+        // only wakes for the frame signal, so the IRQ has to be pending before it arrives
+        if self.config.deterministic {
+            self.intc.raise(IRQ_VBLANK);
+            run_ctx.raise_signal();
+        }
+        else {
+            run_ctx.raise_signal();
+            self.intc.raise(IRQ_VBLANK);
+        }
+
+        return watchdog;
+    }
+
+    // run-ahead: with the CPU parked at the end of the frame, keep the state, service the frames after it as if they'd been
+    // reached (with the input just latched) into `cmd_buf`, & leave the last one to be presented. the frame's own command
+    // buffer has to have been submitted already, so the state sees its draws in VRAM. None if the CPU couldn't be parked
+    pub fn run_ahead(self: &mut Self, run_ctx: &MachineRunContext, frames: u32, gfx_device: &Device, cmd_buf: &CommandBuffer) -> Result<Option<RunAhead>, String> {
+        wait_frame_done(run_ctx);
+
+        let state = if run_ctx.pause() { Some(self.save_state(run_ctx, gfx_device)) } else { None };
+        run_ctx.resume();
+
+        let Some(state) = state.transpose()? else {
+            return Ok(None);
+        };
+
+        let kept = RunAhead { state, debug_events: self.debugport.pending() };
+        self.uart.set_discard_tx(true);
+
+        for ahead in 0..frames as u64 {
+            // the CPU has already done the first frame's work, waiting on the state being kept
+            if ahead > 0 {
+                self.timebase.set_frame(self.frame + ahead);
+                self.clock.frame_advanced();
+                self.intc.raise(IRQ_VBLANK);
+                run_ctx.raise_signal();
+
+                wait_frame_done(run_ctx);
+            }
+
+            self.vdp.begin_frame(self.frame + ahead, gfx_device, cmd_buf);
+            self.vdp_port.service(&mut self.vdp, run_ctx, gfx_device, cmd_buf);
+            self.vdp.end_frame(gfx_device, cmd_buf);
+        }
+
+        return Ok(Some(kept));
+    }
+
+    // nothing the frames run ahead did happened - faults, exits & breakpoints included. they'll happen for real soon enough
+    pub fn end_run_ahead(self: &mut Self, run_ctx: MachineRunContext, kept: RunAhead, gfx_device: &Device) -> MachineRunContext {
+        run_ctx.stop();
+        savestate::restore_state(&kept.state, &mut self.machine, &mut self.vdp, gfx_device, &self.timebase, &self.snapshots, &self.map);
+        let run_ctx = self.machine.run();
+
+        self.uart.set_discard_tx(false);
+        self.debugport.truncate(kept.debug_events);
+
+        while self.machine.poll_debug_stop().is_some() {
+        }
+
+        return run_ctx;
+    }
+
+    // paused: step until the CPU reaches code for another source line, going over calls if `over` - it's back to the
+    // same stack depth, or shallower, once they've returned. the CPU thread does the stepping & the stop comes back like
+    // any other, as a step or a breakpoint or watchpoint on the way. with no line info for where the CPU is, it's a single
+    // instruction step
+    pub fn step_line(self: &Self, run_ctx: &MachineRunContext, over: bool) {
+        let (start_pc, start_sp) = pc_sp(run_ctx);
+
+        let Some((file, line)) = self.lines.lookup(start_pc) else {
+            run_ctx.step_instruction();
+            return;
+        };
+
+        let (lines, here) = (self.lines.clone(), (file.to_string(), line));
+        run_ctx.step_until(Box::new(move |pc, sp| lines.lookup(pc).is_some_and(|(file, line)| (file, line) != (here.0.as_str(), here.1)) && (!over || sp >= start_sp)), STEP_LINE_LIMIT);
+    }
+}
+
+fn pc_sp(run_ctx: &MachineRunContext) -> (u32, u32) {
+    let regs = run_ctx.registers();
+    let reg = |name: &str| regs.iter().find(|(reg, _)| *reg == name).map_or(0, |(_, val)| *val);
+    return (reg("pc"), reg("sp"));
+}
+
+// waits for the CPU to finish its frame's work - unless a debugger or a fault stops it first
+fn wait_frame_done(run_ctx: &MachineRunContext) {
+    while !run_ctx.wait_idle(FRAME_DONE_POLL) && !run_ctx.is_paused() && run_ctx.fault().is_none() {
+    }
+}
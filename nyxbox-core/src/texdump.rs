@@ -0,0 +1,106 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use serde_json::{json, Value};
+
+use crate::{screenshot, vdp::{TextureBinding, TEXFORMAT_RGB565, TEXFORMAT_RGBA8888, TUCONF_FORMAT_MASK, TUCONF_FORMAT_SHIFT, TUCONF_HEIGHT_SHIFT, TUCONF_SIZE_MASK, TUCONF_WIDTH_SHIFT, VRAM_SIZE}};
+
+// everything the draws of one frame had bound goes into <capture dir>/textures/frame<N>/: a PNG per texture
+// (tex<unit>_<addr>.png) & textures.json listing each one's unit, address, format, size, & how many draws used it.
+// a format the dumper can't decode gets its raw VRAM bytes written out instead (.bin)
+pub const TEXTURE_DIR: &str = "textures";
+
+pub fn format_name(format: u32) -> String {
+    match format {
+        TEXFORMAT_RGBA8888 => "rgba8888".to_string(),
+        TEXFORMAT_RGB565 => "rgb565".to_string(),
+        _ => format!("unknown({})", format),
+    }
+}
+
+fn bits_per_texel(format: u32) -> u32 {
+    match format {
+        TEXFORMAT_RGB565 => 16,
+        _ => 32,
+    }
+}
+
+fn texture_size(conf: u32) -> (u32, u32) {
+    return (1 << ((conf >> TUCONF_WIDTH_SHIFT) & TUCONF_SIZE_MASK), 1 << ((conf >> TUCONF_HEIGHT_SHIFT) & TUCONF_SIZE_MASK));
+}
+
+// texels are packed into words from the low bits up, starting at the texture's word address
+fn decode(format: u32, data: &[u8], texels: usize) -> Option<Vec<u8>> {
+    match format {
+        TEXFORMAT_RGBA8888 => {
+            return Some(data[..texels * 4].to_vec());
+        }
+        TEXFORMAT_RGB565 => {
+            return Some(data[..texels * 2].chunks_exact(2).flat_map(|px| {
+                let px = u16::from_le_bytes([px[0], px[1]]) as u32;
+                let (r, g, b) = (px & 0x1F, (px >> 5) & 0x3F, px >> 11);
+                [(r * 255 / 31) as u8, (g * 255 / 63) as u8, (b * 255 / 31) as u8, 0xFF]
+            }).collect());
+        }
+        _ => {
+            return None;
+        }
+    }
+}
+
+// write out a frame's worth of bindings against a snapshot of VRAM. returns the directory written to
+pub fn dump_textures(capture_dir: &Path, frame: u64, bindings: &[TextureBinding], vram: &[u8]) -> Result<PathBuf, String> {
+    let dir = capture_dir.join(TEXTURE_DIR).join(format!("frame{:08}", frame));
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+
+    let mut manifest = Vec::new();
+
+    for binding in bindings {
+        let format = (binding.conf >> TUCONF_FORMAT_SHIFT) & TUCONF_FORMAT_MASK;
+        let (width, height) = texture_size(binding.conf);
+        let texels = (width * height) as usize;
+
+        let start = binding.addr as u64 * 4;
+        let len = (texels as u64 * bits_per_texel(format) as u64).div_ceil(8);
+
+        let mut entry = json!({
+            "unit": binding.unit,
+            "addr": format!("{:08x}", binding.addr),
+            "format": format_name(format),
+            "width": width,
+            "height": height,
+            "draws": binding.draws,
+        });
+
+        // a bad binding is worth listing - it may well be the reason the dump was taken
+        if start + len > VRAM_SIZE as u64 {
+            entry["error"] = Value::from("texture runs past the end of VRAM");
+            manifest.push(entry);
+            continue;
+        }
+
+        let data = &vram[start as usize..(start + len) as usize];
+        let name = format!("tex{}_{:08x}", binding.unit, binding.addr);
+
+        let file = match decode(format, data, texels) {
+            Some(rgba) => {
+                let path = dir.join(format!("{}.png", name));
+                screenshot::write_png(&path, width, height, &rgba).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+                path
+            }
+            None => {
+                let path = dir.join(format!("{}.bin", name));
+                fs::write(&path, data).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+                path
+            }
+        };
+
+        entry["file"] = Value::from(file.file_name().unwrap().to_string_lossy().to_string());
+        manifest.push(entry);
+    }
+
+    let manifest_path = dir.join("textures.json");
+    let text = serde_json::to_string_pretty(&Value::from(manifest)).unwrap();
+    fs::write(&manifest_path, text).map_err(|e| format!("failed to write {}: {}", manifest_path.display(), e))?;
+
+    return Ok(dir);
+}
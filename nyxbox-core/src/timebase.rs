@@ -0,0 +1,142 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Instant};
+
+// emulated time, in integer nanoseconds since power-on, shared by every peripheral that needs to know "now" so they all
+// agree on it. it advances in whole frames as the frontend runs them: a frame boundary is always exactly
+// frame * NS_PER_SEC / FRAME_RATE, so time never drifts against the frame count, & a slow or paused host slows emulated
+// time down with it instead of the guest seeing time jump. within a frame, time follows the host clock but never reaches
+// the next boundary until the frontend gets there
+//
+// in deterministic mode the host clock is out of it entirely: time within a frame is how far the CPU has got through its
+// instruction budget, so the guest sees exactly the same times however fast or slow the host runs it
+pub const NS_PER_SEC: u64   = 1_000_000_000;
+pub const NS_PER_US: u64    = 1_000;
+pub const FRAME_RATE: u64   = 60;
+
+pub const fn frame_to_ns(frame: u64) -> u64 {
+    return (frame as u128 * NS_PER_SEC as u128 / FRAME_RATE as u128) as u64;
+}
+
+pub const fn ns_to_us(ns: u64) -> u64 {
+    return ns / NS_PER_US;
+}
+
+pub const fn ns_to_secs(ns: u64) -> u64 {
+    return ns / NS_PER_SEC;
+}
+
+// where the current frame started, by each clock. kept together under one lock so a reader never pairs one frame with
+// another's start, which would have time jump backwards mid-frame
+#[derive(Clone, Copy)]
+struct FrameStart {
+    frame: u64,
+    // host time (ns since host_base) the frame started at
+    host_ns: u64,
+    // deterministic mode: the CPU's instruction count at the start of the frame
+    insns: u64,
+}
+
+pub struct Timebase {
+    host_base: Instant,
+    start: Mutex<FrameStart>,
+    // deterministic mode: the CPU's running instruction count & instructions per frame
+    insn_clock: Option<(Arc<AtomicU64>, u64)>,
+}
+
+impl Timebase {
+    pub fn new() -> Self {
+        Self {
+            host_base: Instant::now(),
+            start: Mutex::new(FrameStart { frame: 0, host_ns: 0, insns: 0 }),
+            insn_clock: None,
+        }
+    }
+
+    // time driven by the CPU's instruction count instead of the host clock (the budget can't be unlimited)
+    pub fn deterministic(executed: Arc<AtomicU64>, insns_per_frame: u64) -> Self {
+        Self {
+            insn_clock: Some((executed, insns_per_frame.max(1))),
+            ..Self::new()
+        }
+    }
+
+    pub fn is_deterministic(self: &Self) -> bool {
+        return self.insn_clock.is_some();
+    }
+
+    // deterministic mode: instructions the CPU has to run from here to reach ns, or None if that's not in this frame
+    pub fn instructions_until(self: &Self, ns: u64) -> Option<u64> {
+        let (executed, per_frame) = self.insn_clock.as_ref()?;
+        let frame_start = *self.start.lock().unwrap();
+        let frame = frame_start.frame;
+        let start = frame_to_ns(frame);
+        let len = frame_to_ns(frame + 1) - start;
+
+        if ns >= start + len {
+            return None;
+        }
+
+        // the first instruction count at which now_ns() has reached ns
+        let target = ((ns.saturating_sub(start) as u128 * *per_frame as u128).div_ceil(len as u128)) as u64;
+        let ran = executed.load(Ordering::Relaxed).saturating_sub(frame_start.insns);
+
+        return Some(target.saturating_sub(ran));
+    }
+
+    fn host_ns(self: &Self) -> u64 {
+        return self.host_base.elapsed().as_nanos() as u64;
+    }
+
+    // frontend: the next emulated frame is starting
+    pub fn advance_frame(self: &Self) {
+        let mut start = self.start.lock().unwrap();
+        *start = self.frame_start(start.frame + 1);
+    }
+
+    // jump straight to the start of a frame (e.g. restoring a save state)
+    pub fn set_frame(self: &Self, frame: u64) {
+        let mut start = self.start.lock().unwrap();
+        *start = self.frame_start(frame);
+    }
+
+    fn frame_start(self: &Self, frame: u64) -> FrameStart {
+        let insns = self.insn_clock.as_ref().map_or(0, |(executed, _)| executed.load(Ordering::Relaxed));
+        return FrameStart { frame, host_ns: self.host_ns(), insns };
+    }
+
+    // jump emulated time forward to ns, but no further than the end of the current frame - for skipping an idle CPU ahead
+    // to its next timer. never goes backwards, & doesn't apply to deterministic time
+    pub fn skip_to(self: &Self, ns: u64) {
+        let mut start = self.start.lock().unwrap();
+        let frame_start = frame_to_ns(start.frame);
+        let into_frame = ns.min(frame_to_ns(start.frame + 1) - 1).saturating_sub(frame_start);
+
+        start.host_ns = start.host_ns.min(self.host_ns().saturating_sub(into_frame));
+    }
+
+    pub fn frame(self: &Self) -> u64 {
+        return self.start.lock().unwrap().frame;
+    }
+
+    pub fn now_ns(self: &Self) -> u64 {
+        let frame_start = *self.start.lock().unwrap();
+        let start = frame_to_ns(frame_start.frame);
+        let len = frame_to_ns(frame_start.frame + 1) - start;
+        let into_frame = match &self.insn_clock {
+            Some((executed, per_frame)) => {
+                let ran = executed.load(Ordering::Relaxed).saturating_sub(frame_start.insns);
+                (ran as u128 * len as u128 / *per_frame as u128) as u64
+            }
+            None => self.host_ns().saturating_sub(frame_start.host_ns),
+        };
+
+        return start + into_frame.min(len - 1);
+    }
+
+    pub fn now_us(self: &Self) -> u64 {
+        return ns_to_us(self.now_ns());
+    }
+
+    pub fn now_secs(self: &Self) -> u64 {
+        return ns_to_secs(self.now_ns());
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs::File, io::{BufWriter, Write}, path::Path, sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, Receiver, SyncSender}, Arc, Mutex}, thread::{self, JoinHandle}};
+
+use crate::{disasm, inspect::parse_addr, symbols::Symbols};
+
+// instructions are buffered per batch & handed to a writer thread in a bounded queue. the CPU thread only copies out the
+// PC & opcode - disassembly, formatting, & file IO all happen on the writer, which is what keeps tracing usable. if the
+// writer falls behind, the CPU waits for room rather than leaving holes in the trace
+const BATCH_LEN: usize      = 4096;
+const QUEUE_BATCHES: usize  = 64;
+
+#[derive(Clone, Copy)]
+enum TraceEntry {
+    Insn { pc: u32, opcode: u32, thumb: bool },
+    Mem(MemAccess),
+}
+
+// one guest data access, as memory access hooks see it. a read's value is what memory held as it was read
+#[derive(Clone, Copy)]
+pub struct MemAccess {
+    pub pc: u32,
+    pub addr: u32,
+    pub size: u32,
+    pub value: u32,
+    pub write: bool,
+}
+
+// a --trace-range argument: START-END, both inclusive (e.g. "0x1000-0x1fff")
+pub fn parse_range(s: &str) -> Result<(u32, u32), String> {
+    let (start, end) = s.split_once('-').ok_or(format!("expected START-END, got '{}'", s))?;
+    let (start, end) = (parse_addr(start)?, parse_addr(end)?);
+
+    if end < start {
+        return Err(format!("range {} ends before it starts", s));
+    }
+
+    return Ok((start, end));
+}
+
+pub struct Tracer {
+    batch: Mutex<Vec<TraceEntry>>,
+    sender: Mutex<Option<SyncSender<Vec<TraceEntry>>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    traced: Arc<AtomicU64>,
+}
+
+impl Tracer {
+    pub fn create(path: &Path, symbols: Symbols) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_BATCHES);
+        let traced = Arc::new(AtomicU64::new(0));
+        let writer_traced = traced.clone();
+
+        let writer = thread::Builder::new()
+            .name("trace".to_string())
+            .spawn(move || writer(BufWriter::new(file), receiver, writer_traced, symbols))
+            .unwrap();
+
+        return Ok(Self {
+            batch: Mutex::new(Vec::with_capacity(BATCH_LEN)),
+            sender: Mutex::new(Some(sender)),
+            writer: Mutex::new(Some(writer)),
+            traced,
+        });
+    }
+
+    // CPU thread, from the trace code hook
+    pub fn record(self: &Self, pc: u32, opcode: u32, thumb: bool) {
+        self.push(TraceEntry::Insn { pc, opcode, thumb });
+    }
+
+    // CPU thread, from a memory trace hook
+    pub fn record_mem(self: &Self, access: MemAccess) {
+        self.push(TraceEntry::Mem(access));
+    }
+
+    fn push(self: &Self, entry: TraceEntry) {
+        let mut batch = self.batch.lock().unwrap();
+        batch.push(entry);
+
+        if batch.len() == BATCH_LEN {
+            let full = std::mem::replace(&mut *batch, Vec::with_capacity(BATCH_LEN));
+            drop(batch);
+            self.send(full);
+        }
+    }
+
+    fn send(self: &Self, batch: Vec<TraceEntry>) {
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(batch);
+        }
+    }
+
+    // write out everything recorded so far & close the file - anything executed afterwards isn't traced. returns how many
+    // entries (instructions & memory accesses) ended up in the trace
+    pub fn finish(self: &Self) -> u64 {
+        let rest = std::mem::take(&mut *self.batch.lock().unwrap());
+        self.send(rest);
+        self.sender.lock().unwrap().take();
+
+        if let Some(writer) = self.writer.lock().unwrap().take() {
+            let _ = writer.join();
+        }
+
+        return self.traced.load(Ordering::Relaxed);
+    }
+}
+
+fn writer(mut out: BufWriter<File>, receiver: Receiver<Vec<TraceEntry>>, traced: Arc<AtomicU64>, symbols: Symbols) {
+    // with symbols loaded, a "name:" line marks each time execution moves into a different function
+    let mut symbol = None;
+
+    for batch in receiver {
+        for entry in &batch {
+            if let TraceEntry::Insn { pc, .. } = *entry {
+                let name = symbols.lookup(pc).map(|(name, _)| name);
+
+                if name.is_some() && name != symbol {
+                    if let Err(e) = writeln!(out, "{}:", name.unwrap()) {
+                        println!("trace: write failed, stopping: {}", e);
+                        return;
+                    }
+                }
+
+                symbol = name;
+            }
+
+            let res = match *entry {
+                TraceEntry::Insn { pc, opcode, thumb: true } => writeln!(out, "{:08x}  {:04x}      {}", pc, opcode, disasm::disassemble(pc, opcode, true)),
+                TraceEntry::Insn { pc, opcode, thumb: false } => writeln!(out, "{:08x}  {:08x}  {}", pc, opcode, disasm::disassemble(pc, opcode, false)),
+                // e.g. "00001234  w4 01000100 = 0000002a"
+                TraceEntry::Mem(access) => writeln!(out, "{:08x}  {}{} {:08x} = {:0width$x}", access.pc, if access.write { 'w' } else { 'r' }, access.size,
+                    access.addr, access.value, width = access.size as usize * 2),
+            };
+
+            if let Err(e) = res {
+                println!("trace: write failed, stopping: {}", e);
+                return;
+            }
+        }
+
+        traced.fetch_add(batch.len() as u64, Ordering::Relaxed);
+    }
+
+    let _ = out.flush();
+}
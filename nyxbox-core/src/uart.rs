@@ -0,0 +1,244 @@
+use std::{collections::VecDeque, io::Write, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::{self, JoinHandle}};
+
+use rsevents::{AutoResetEvent, Awaitable, EventState};
+
+use crate::{hwmodel::{HwLimits, Limit}, intc::IrqLine, peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}};
+
+pub const UART_MEM_SIZE: u32 = 4096;
+
+// bytes the guest can queue before TX reports full
+pub const UART_TX_FIFO_SIZE: usize = 4096;
+
+// most recent TX bytes kept around for failure reports
+pub const UART_TX_HISTORY_SIZE: usize = 4096;
+
+pub const UARTSTATUSBIT_RESET: u32      = 1;
+pub const UARTSTATUSBIT_TXEMPTY: u32    = 2;
+pub const UARTSTATUSBIT_TXFULL: u32     = 4;
+pub const UARTSTATUSBIT_RXEMPTY: u32    = 8;
+
+// state shared between the peripheral & the host-side thread which drains TX into the sink
+struct TxShared {
+    fifo: PeripheralLock<Vec<u8>>,
+    busy: AtomicBool,
+    stop: AtomicBool,
+    ready: AutoResetEvent,
+    space: AutoResetEvent,
+}
+
+// RX & TX are locked separately, so the frontend feeding input never waits on a guest writing output (or vice versa).
+// TX is buffered & written out by a dedicated thread, so a slow sink doesn't stall the CPU
+pub struct UART {
+    rx: PeripheralLock<VecDeque<u8>>,
+    tx: Arc<TxShared>,
+    tx_history: PeripheralLock<VecDeque<u8>>,
+    flusher: Option<JoinHandle<()>>,
+    discard_tx: AtomicBool,
+    hw: Arc<HwLimits>,
+    rx_irq: IrqLine,
+}
+
+impl UART {
+    pub fn new<W: Write + Send + 'static>(out_buffer: W, hw: Arc<HwLimits>, rx_irq: IrqLine) -> Self {
+        let tx = Arc::new(TxShared {
+            fifo: PeripheralLock::new(Vec::with_capacity(UART_TX_FIFO_SIZE)),
+            busy: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            ready: AutoResetEvent::new(EventState::Unset),
+            space: AutoResetEvent::new(EventState::Unset),
+        });
+
+        let flusher_tx = tx.clone();
+        let flusher = thread::spawn(move || {
+            Self::flush_thread(flusher_tx, out_buffer);
+        });
+
+        Self {
+            rx: PeripheralLock::new(VecDeque::new()),
+            tx,
+            tx_history: PeripheralLock::new(VecDeque::with_capacity(UART_TX_HISTORY_SIZE)),
+            flusher: Some(flusher),
+            discard_tx: AtomicBool::new(false),
+            hw,
+            rx_irq,
+        }
+    }
+
+    pub fn push_input(self: &Self, input: &[u8]) {
+        crate::log!(Uart, Debug, "{} bytes received", input.len());
+        self.rx.lock().extend(input);
+
+        if !input.is_empty() {
+            self.rx_irq.raise();
+        }
+    }
+
+    // the last UART_TX_HISTORY_SIZE bytes the guest wrote
+    pub fn tx_history(self: &Self) -> Vec<u8> {
+        return self.tx_history.lock().iter().copied().collect();
+    }
+
+    // run-ahead frames are thrown away, so what they write mustn't reach the sink (or the history) - it's taken as sent
+    pub fn set_discard_tx(self: &Self, discard: bool) {
+        self.discard_tx.store(discard, Ordering::Release);
+    }
+
+    // block until everything the guest has written so far has reached the sink
+    pub fn flush(self: &Self) {
+        while self.tx.busy.load(Ordering::Acquire) || !self.tx.fifo.lock().is_empty() {
+            self.tx.ready.set();
+            self.tx.space.wait();
+        }
+    }
+
+    fn flush_thread<W: Write>(tx: Arc<TxShared>, mut out: W) {
+        let mut pending = Vec::with_capacity(UART_TX_FIFO_SIZE);
+
+        loop {
+            tx.ready.wait();
+
+            // swap buffers rather than copying out of the FIFO - the guest can keep writing while we're in the sink
+            {
+                let mut fifo = tx.fifo.lock();
+                std::mem::swap(&mut *fifo, &mut pending);
+                tx.busy.store(!pending.is_empty(), Ordering::Release);
+            }
+
+            if !pending.is_empty() {
+                // nowhere to report a broken sink to, so output is just dropped
+                let _ = out.write_all(&pending);
+                let _ = out.flush();
+                pending.clear();
+            }
+
+            tx.busy.store(false, Ordering::Release);
+            tx.space.set();
+
+            if tx.stop.load(Ordering::Acquire) && tx.fifo.lock().is_empty() {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for UART {
+    fn drop(self: &mut Self) {
+        self.tx.stop.store(true, Ordering::Release);
+        self.tx.ready.set();
+
+        if let Some(flusher) = self.flusher.take() {
+            let _ = flusher.join();
+        }
+    }
+}
+
+impl Peripheral for UART {
+    fn read(self: &Self, addr: u32) -> u32 {
+        match addr {
+            0x00 => {
+                // STATUS
+                let tx_len = self.tx.fifo.lock().len();
+
+                return
+                    if tx_len == 0 && !self.tx.busy.load(Ordering::Acquire) { UARTSTATUSBIT_TXEMPTY } else { 0 } |
+                    if tx_len >= UART_TX_FIFO_SIZE { UARTSTATUSBIT_TXFULL } else { 0 } |
+                    if self.rx.lock().len() == 0 { UARTSTATUSBIT_RXEMPTY } else { 0 };
+            }
+            0x02 => {
+                // RX
+                if let Some(v) = self.rx.lock().pop_front() {
+                    return v as u32;
+                }
+                else {
+                    return 0;
+                }
+            }
+            0x03 => {
+                // TXLEVEL
+                return self.tx.fifo.lock().len() as u32;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        match addr {
+            0x00 => {
+                // STATUS
+
+                // reset
+                if (val & UARTSTATUSBIT_RESET) != 0 {
+                    crate::log!(Uart, Debug, "reset");
+                    self.rx.lock().clear();
+                    self.tx.ready.set();
+                }
+            }
+            0x01 => {
+                // TX
+                let b = (val & 0xFF) as u8;
+                crate::log!(Uart, Trace, "sent {:#04x} {:?}", b, b as char);
+
+                if self.discard_tx.load(Ordering::Acquire) {
+                    return;
+                }
+
+                // guests are expected to poll TXFULL - if they don't, the hardware model decides whether the byte is lost
+                // or the CPU stalls until there's room
+                let mut reported = false;
+
+                loop {
+                    let mut fifo = self.tx.fifo.lock();
+
+                    if fifo.len() < UART_TX_FIFO_SIZE {
+                        fifo.push(b);
+                        break;
+                    }
+
+                    if !reported {
+                        reported = true;
+
+                        if self.hw.exceeded(Limit::UartTxFifo, || "TX written while TXFULL was set".to_string()) {
+                            return;
+                        }
+                    }
+
+                    drop(fifo);
+                    self.tx.ready.set();
+                    self.tx.space.wait();
+                }
+
+                self.tx.ready.set();
+
+                let mut history = self.tx_history.lock();
+                if history.len() == UART_TX_HISTORY_SIZE {
+                    history.pop_front();
+                }
+                history.push_back(b);
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("uart.rx", self.rx.stats()), ("uart.tx", self.tx.fifo.stats()), ("uart.tx_history", self.tx_history.stats())];
+    }
+
+    // only unread input - TX has already gone (or is on its way) to the sink, & output can't be taken back
+    fn save_state(self: &Self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.bytes(&self.rx.lock().iter().copied().collect::<Vec<_>>());
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let rx = r.bytes()?;
+        r.finish()?;
+
+        *self.rx.lock() = rx.iter().copied().collect();
+        return Ok(());
+    }
+}
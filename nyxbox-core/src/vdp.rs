@@ -0,0 +1,1141 @@
+use std::{collections::VecDeque, fs, sync::Arc};
+
+use sdl3::gpu::{Buffer, BufferMemMap, BufferRegion, BufferUsageFlags, CommandBuffer, ComputePipeline, Device, ShaderFormat, StorageBufferReadWriteBinding, TransferBuffer, TransferBufferLocation, TransferBufferUsage};
+
+use crate::{chrometrace::{ChromeTrace, TRACK_FRONTEND}, crashdump::History, vdpqueue::{vram_run, QueueFault, QueueReader, MAX_LIST_COUNT, MAX_QUEUE_DISPATCHES}, screenshot::framebuffer_rgba, savestate::{StateReader, StateWriter}, vdpcheck, renderdebug::{self, DrawIsolation, RenderDebugMode}, vucapture::{VuCapture, VuVertex, VU_OUTPUT_WORDS}};
+
+pub const REG_STATUS: usize         = 0;
+pub const REG_CMDPORT: usize        = 1;
+pub const REG_DISPLAYMODE: usize    = 2;
+
+pub const STATUSBIT_RESET: u32              = 1;
+pub const STATUSBIT_CMDFIFOEMPTY: u32       = 2;
+pub const STATUSBIT_CMDFIFOFULL: u32        = 4;
+
+pub const STATUSBIT_ERR_MASK: u32           = 0x18;
+pub const STATUSBIT_ERR_ADDR: u32           = 0x8;
+pub const STATUSBIT_ERR_CMD: u32            = 0x10;
+
+pub const DISPLAYBIT_CABLE_MASK: u32        = 0b11;
+pub const DISPLAYBIT_CABLE_VGA: u32         = 0;
+pub const DISPLAYBIT_CABLE_COMPOSITE: u32   = 1;
+pub const DISPLAYBIT_CABLE_SVIDEO: u32      = 2;
+pub const DISPLAYBIT_CABLE_COMPONENT: u32   = 3;
+pub const DISPLAYBIT_ENABLE: u32            = 4;
+pub const DISPLAYBIT_INTERLACE: u32         = 8;
+
+pub const INTERNALREG_FBDIM: u32            = 0;
+pub const INTERNALREG_FBADDR: u32           = 1;
+pub const INTERNALREG_DBADDR: u32           = 2;
+pub const INTERNALREG_VUSTRIDE: u32         = 3;
+pub const INTERNALREG_VULAYOUT0: u32        = 4;
+pub const INTERNALREG_VUCDATA0: u32         = 12;
+pub const INTERNALREG_VUPROGADDR: u32       = 76;
+pub const INTERNALREG_FOGENCOL: u32         = 77;
+pub const INTERNALREG_FOGTBL0: u32          = 78;
+pub const INTERNALREG_CLIPXY: u32           = 142;
+pub const INTERNALREG_CLIPWH: u32           = 143;
+pub const INTERNALREG_VPXY: u32             = 144;
+pub const INTERNALREG_VPWH: u32             = 145;
+pub const INTERNALREG_DEPTH: u32            = 146;
+pub const INTERNALREG_BLEND: u32            = 147;
+pub const INTERNALREG_CULL: u32             = 148;
+pub const INTERNALREG_TUCONF: u32           = 149;
+pub const INTERNALREG_TU0ADDR: u32          = 150;
+pub const INTERNALREG_TU1ADDR: u32          = 151;
+pub const INTERNALREG_TCOMBINE: u32         = 152;
+
+pub const INTERNALREG_COUNT: usize          = 256;
+
+// TUCONF holds both texture units' config - unit 0 in the low 16 bits, unit 1 in the high 16. per unit:
+// bit 0 enable, bits 1-3 format, bits 4-7 log2 width, bits 8-11 log2 height
+pub const TEXTURE_UNITS: usize              = 2;
+pub const TUCONFBIT_ENABLE: u32             = 1;
+pub const TUCONF_FORMAT_SHIFT: u32          = 1;
+pub const TUCONF_FORMAT_MASK: u32           = 0x7;
+pub const TUCONF_WIDTH_SHIFT: u32           = 4;
+pub const TUCONF_HEIGHT_SHIFT: u32          = 8;
+pub const TUCONF_SIZE_MASK: u32             = 0xF;
+
+pub const TEXFORMAT_RGBA8888: u32           = 0;
+pub const TEXFORMAT_RGB565: u32             = 1;
+
+// DEPTH: bit 0 enables the depth test, bits 1-3 pick how a triangle's depth is compared with the buffer's (passing if
+// it's <func> what's there), & bit 4 lets passing pixels write their depth. with the test off the depth buffer is neither
+// read nor written, so the reset value of 0 draws everything in order. depths are window space (NDC z mapped to 0-1),
+// one float per pixel at DBADDR
+pub const DEPTHBIT_ENABLE: u32              = 1;
+pub const DEPTH_FUNC_SHIFT: u32             = 1;
+pub const DEPTH_FUNC_MASK: u32              = 0x7;
+pub const DEPTHBIT_WRITE: u32               = 0x10;
+
+pub const DEPTHFUNC_NEVER: u32              = 0;
+pub const DEPTHFUNC_LESS: u32               = 1;
+pub const DEPTHFUNC_EQUAL: u32              = 2;
+pub const DEPTHFUNC_LEQUAL: u32             = 3;
+pub const DEPTHFUNC_GREATER: u32            = 4;
+pub const DEPTHFUNC_NOTEQUAL: u32           = 5;
+pub const DEPTHFUNC_GEQUAL: u32             = 6;
+pub const DEPTHFUNC_ALWAYS: u32             = 7;
+
+// BLEND: bits 0-2 pick how a triangle's color (src) combines with the framebuffer's (dst), per channel from 0 to 1 &
+// clamped back into range:
+//   opaque     src
+//   alpha      src * src.a + dst * (1 - src.a), alpha src.a + dst.a * (1 - src.a)
+//   add        dst + src * src.a
+//   subtract   dst - src * src.a
+//   multiply   dst * src
+// all but alpha leave the framebuffer's alpha as it was. the other values are reserved (& draw opaque)
+pub const BLEND_MODE_MASK: u32              = 0x7;
+
+pub const BLENDMODE_OPAQUE: u32             = 0;
+pub const BLENDMODE_ALPHA: u32              = 1;
+pub const BLENDMODE_ADD: u32                = 2;
+pub const BLENDMODE_SUBTRACT: u32           = 3;
+pub const BLENDMODE_MULTIPLY: u32           = 4;
+
+// CLIPXY & CLIPWH: the scissor rectangle, x & width in the low 16 bits & y & height in the high 16. pixels outside it
+// are left alone by triangles & lines. a CLIPWH of 0 (as after reset) turns clipping off rather than clipping everything,
+// & the render debug modes ignore it so they show all the geometry
+pub const CLIPXY_X_MASK: u32                = 0xFFFF;
+pub const CLIPXY_Y_SHIFT: u32               = 16;
+pub const CLIPWH_WIDTH_MASK: u32            = 0xFFFF;
+pub const CLIPWH_HEIGHT_SHIFT: u32          = 16;
+
+pub const SHADER_VU: &str                   = "content/shaders/vu.spv";
+pub const SHADER_DRAW_TRI_LIST: &str        = "content/shaders/draw_tri_list.spv";
+pub const SHADER_DRAW_LINES: &str           = "content/shaders/draw_lines.spv";
+pub const SHADER_FILL: &str                 = "content/shaders/fill.spv";
+
+// every shader the VDP needs at startup
+pub const SHADER_PATHS: &[&str] = &[
+    SHADER_VU,
+    SHADER_DRAW_TRI_LIST,
+    SHADER_DRAW_LINES,
+    SHADER_FILL,
+];
+
+// 8MiB VRAM
+pub const VRAM_SIZE: u32 = 1024 * 1024 * 8;
+
+// VRAM size in words is a power of two, so the unchecked path can wrap addresses with a mask instead of testing them
+const VRAM_WORD_MASK: u32 = (VRAM_SIZE / 4) - 1;
+
+// the command processor is written once & instantiated for each of these. the validating path catches bad queues (out of range addresses, runaway queues)
+// and reports them through the status register, while the unchecked path just wraps addresses into VRAM & trusts the guest - meant for shipping titles that are known-good
+pub trait ValidationMode {
+    const CHECKED: bool;
+
+    // translate a guest VRAM word address, or return None if it's out of range
+    fn vram_addr(addr: u32) -> Option<usize>;
+}
+
+pub struct Validating;
+pub struct Unchecked;
+
+impl ValidationMode for Validating {
+    const CHECKED: bool = true;
+
+    #[inline(always)]
+    fn vram_addr(addr: u32) -> Option<usize> {
+        return if addr < VRAM_SIZE / 4 { Some(addr as usize) } else { None };
+    }
+}
+
+impl ValidationMode for Unchecked {
+    const CHECKED: bool = false;
+
+    #[inline(always)]
+    fn vram_addr(addr: u32) -> Option<usize> {
+        return Some((addr & VRAM_WORD_MASK) as usize);
+    }
+}
+
+#[repr(C)]
+struct VertexUnitUBO {
+    src_addr: u32,
+    dst_addr: u32,
+}
+
+// how the triangle & line shaders walk their vertices (see shaders-src/draw_tri_list.glsl & draw_lines.glsl)
+const DRAW_TOPOLOGY_LIST: u32               = 0;
+const DRAW_TOPOLOGY_STRIP: u32              = 1;
+
+// the triangle shader draws a tile of TRI_TILE_SIZE x TRI_TILE_SIZE pixels per work group (one triangle per work group in
+// the render debug modes)
+const TRI_TILE_SIZE: u32                    = 8;
+
+// invocations per work group in the fill shader, & what it sets each word to (see shaders-src/fill.glsl)
+const FILL_THREADS: u32                     = 64;
+const FILLMODE_VALUE: u32                   = 0;
+const FILLMODE_COPY: u32                    = 1;
+
+#[repr(C)]
+struct FillUBO {
+    addr: u32,
+    count: u32,
+    value: u32,
+    src: u32,
+    mode: u32,
+}
+
+#[repr(C)]
+struct DrawListUBO {
+    addr: u32,
+    debug_mode: u32,
+    topology: u32,
+    // primitives in the list
+    count: u32,
+}
+
+// work done since the last time these were taken (the port takes them once per frame). pixels is the exception: the draw
+// shaders count them on the GPU, & they're read back a frame late so nothing waits on it - it's the frame before's count
+#[derive(Clone, Copy, Default)]
+pub struct VdpStats {
+    pub commands: u32,
+    pub primitives: u32,
+    pub vertices: u32,
+    pub dma_bytes: u32,
+    pub pixels: u32,
+    // compute dispatches issued to the GPU (vertex lists & draws) - the host's side of things, not visible to the guest
+    pub dispatches: u32,
+}
+
+// a texture some draw in a captured frame had bound: which unit, its word address, & that unit's 16 bits of TUCONF
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TextureBinding {
+    pub unit: usize,
+    pub addr: u32,
+    pub conf: u32,
+    pub draws: u32,
+}
+
+pub enum ErrorMode {
+    None,
+    AddressError,
+    CmdError,
+}
+
+pub enum DisplayCable {
+    VGA,
+    Composite,
+    SVideo,
+    Component
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Topology {
+    TriangleList,
+    TriangleStrip,
+    LineList,
+    LineStrip,
+}
+
+// a decoded command queue entry (see vdpqueue)
+pub enum VDPCommand {
+    WriteInternalRegister { reg: usize, val: u32 },
+    ProcessVertexList { count: u32, src: u32, dst: u32 },
+    DrawList { topology: Topology, count: u32, addr: u32 },
+    ClearColor { color: u32 },
+    ClearDepth { depth: f32 },
+    SwapBuffers { copy_target: Option<u32> },
+    EndOfQueue { token: u32 },
+}
+
+pub struct VDP {
+    internal_reg: [u32;INTERNALREG_COUNT],
+    reset_state: bool,
+    cmd_fifo: VecDeque<u32>,
+    last_cmd_tok: VecDeque<u32>,
+    cable_type: DisplayCable,
+    display_enable: bool,
+    display_interlace: bool,
+    err_mode: ErrorMode,
+    vram: Buffer,
+    vram_transfer: TransferBuffer,
+    vram_readback: TransferBuffer,
+    overdraw: Buffer,
+    overdraw_zero: TransferBuffer,
+    // the draw shaders' count of pixels written this frame, & where end_frame copies it for begin_frame to pick up
+    pixels: Buffer,
+    pixels_readback: TransferBuffer,
+    pixels_copied: bool,
+    deterministic: bool,
+    render_debug: RenderDebugMode,
+    draw_isolation: DrawIsolation,
+    frame_draws: u32,
+    last_frame_draws: u32,
+    frame: u64,
+    frame_vertex_lists: u32,
+    vu_capture_list: Option<u32>,
+    vu_capture: Option<(VuCapture, TransferBuffer)>,
+    regmem: Buffer,
+    regmem_transfer: TransferBuffer,
+    regmem_dirty: bool,
+    unchecked: bool,
+    strict: bool,
+    violations: Vec<(u32, String)>,
+    stats: VdpStats,
+    texture_capture: Option<Vec<TextureBinding>>,
+    // (VRAM address << 32 | header) of each command processed, for crash dumps
+    cmd_history: Option<Arc<History>>,
+    chrome_trace: Option<Arc<ChromeTrace>>,
+    vu_pipeline: ComputePipeline,
+    draw_tri_list_pipeline: ComputePipeline,
+    draw_lines_pipeline: ComputePipeline,
+    fill_pipeline: ComputePipeline,
+    // the framebuffer (word address, FBDIM) the last swap latched for display - none until the guest's first swap, in
+    // which case the display follows FBADDR & FBDIM as they are
+    display: Option<(u32, u32)>,
+}
+
+impl VDP {
+    pub fn new(graphics_device: &Device) -> VDP {
+        let vram = graphics_device.create_buffer()
+            .with_size(VRAM_SIZE)
+            .with_usage(BufferUsageFlags::ComputeStorageRead | BufferUsageFlags::ComputeStorageWrite | BufferUsageFlags::GraphicsStorageRead)
+            .build()
+            .unwrap();
+
+        let vram_transfer = graphics_device.create_transfer_buffer()
+            .with_size(VRAM_SIZE)
+            .with_usage(TransferBufferUsage::Upload)
+            .build()
+            .unwrap();
+
+        let vram_readback = graphics_device.create_transfer_buffer()
+            .with_size(VRAM_SIZE)
+            .with_usage(TransferBufferUsage::Download)
+            .build()
+            .unwrap();
+
+        // one count per framebuffer pixel, & the framebuffer can't be bigger than VRAM
+        let overdraw = graphics_device.create_buffer()
+            .with_size(VRAM_SIZE)
+            .with_usage(BufferUsageFlags::ComputeStorageRead | BufferUsageFlags::ComputeStorageWrite)
+            .build()
+            .unwrap();
+
+        let mut overdraw_zero = graphics_device.create_transfer_buffer()
+            .with_size(VRAM_SIZE)
+            .with_usage(TransferBufferUsage::Upload)
+            .build()
+            .unwrap();
+
+        let mut zero: BufferMemMap<'_, u8> = overdraw_zero.map::<u8>(graphics_device, false);
+        zero.mem_mut().fill(0);
+        drop(zero);
+
+        let pixels = graphics_device.create_buffer()
+            .with_size(4)
+            .with_usage(BufferUsageFlags::ComputeStorageRead | BufferUsageFlags::ComputeStorageWrite)
+            .build()
+            .unwrap();
+
+        let pixels_readback = graphics_device.create_transfer_buffer()
+            .with_size(4)
+            .with_usage(TransferBufferUsage::Download)
+            .build()
+            .unwrap();
+
+        let regmem = graphics_device.create_buffer()
+            .with_size((INTERNALREG_COUNT * 4) as u32)
+            .with_usage(sdl3::gpu::BufferUsageFlags::ComputeStorageRead)
+            .build()
+            .unwrap();
+
+        let regmem_transfer = graphics_device.create_transfer_buffer()
+            .with_size((INTERNALREG_COUNT * 4) as u32)
+            .with_usage(sdl3::gpu::TransferBufferUsage::Upload)
+            .build()
+            .unwrap();
+
+        // load compute shaders
+        let vu_shader = fs::read(SHADER_VU).unwrap();
+        let vu_pipeline = graphics_device.create_compute_pipeline()
+            .with_code(ShaderFormat::SpirV, &vu_shader)
+            .with_entrypoint("main")
+            .with_readonly_storage_buffers(1)
+            .with_readwrite_storage_buffers(1)
+            .with_uniform_buffers(1)
+            .with_thread_count(1, 1, 1)
+            .build().unwrap();
+
+        let draw_tri_list_shader = fs::read(SHADER_DRAW_TRI_LIST).unwrap();
+        let draw_tri_list_pipeline = graphics_device.create_compute_pipeline()
+            .with_code(ShaderFormat::SpirV, &draw_tri_list_shader)
+            .with_entrypoint("main")
+            .with_readonly_storage_buffers(1)
+            .with_readwrite_storage_buffers(3)
+            .with_uniform_buffers(1)
+            .with_thread_count(TRI_TILE_SIZE, TRI_TILE_SIZE, 1)
+            .build().unwrap();
+
+        let draw_lines_shader = fs::read(SHADER_DRAW_LINES).unwrap();
+        let draw_lines_pipeline = graphics_device.create_compute_pipeline()
+            .with_code(ShaderFormat::SpirV, &draw_lines_shader)
+            .with_entrypoint("main")
+            .with_readonly_storage_buffers(1)
+            .with_readwrite_storage_buffers(3)
+            .with_uniform_buffers(1)
+            .with_thread_count(1, 1, 1)
+            .build().unwrap();
+
+        let fill_shader = fs::read(SHADER_FILL).unwrap();
+        let fill_pipeline = graphics_device.create_compute_pipeline()
+            .with_code(ShaderFormat::SpirV, &fill_shader)
+            .with_entrypoint("main")
+            .with_readwrite_storage_buffers(1)
+            .with_uniform_buffers(1)
+            .with_thread_count(FILL_THREADS, 1, 1)
+            .build().unwrap();
+
+        VDP {
+            internal_reg: [0;256],
+            reset_state: false,
+            cmd_fifo: VecDeque::new(),
+            last_cmd_tok: VecDeque::new(),
+            cable_type: DisplayCable::VGA,
+            display_enable: false,
+            display_interlace: false,
+            err_mode: ErrorMode::None,
+            vram,
+            vram_transfer,
+            vram_readback,
+            overdraw,
+            overdraw_zero,
+            pixels,
+            pixels_readback,
+            pixels_copied: false,
+            deterministic: false,
+            render_debug: RenderDebugMode::None,
+            draw_isolation: DrawIsolation::Off,
+            frame_draws: 0,
+            last_frame_draws: 0,
+            frame: 0,
+            frame_vertex_lists: 0,
+            vu_capture_list: None,
+            vu_capture: None,
+            regmem,
+            regmem_transfer,
+            regmem_dirty: true,
+            unchecked: false,
+            strict: false,
+            violations: Vec::new(),
+            stats: VdpStats::default(),
+            texture_capture: None,
+            cmd_history: None,
+            chrome_trace: None,
+            vu_pipeline,
+            draw_tri_list_pipeline,
+            draw_lines_pipeline,
+            fill_pipeline,
+            display: None,
+        }
+    }
+
+    pub fn set_cmd_history(self: &mut Self, history: Arc<History>) {
+        self.cmd_history = Some(history);
+    }
+
+    // a span for every command queue & each command in it
+    pub fn set_chrome_trace(self: &mut Self, trace: Arc<ChromeTrace>) {
+        self.chrome_trace = Some(trace);
+    }
+
+    // switch the command processor between the validating & unchecked paths
+    pub fn set_unchecked(self: &mut Self, unchecked: bool) {
+        self.unchecked = unchecked;
+    }
+
+    // check internal register writes against the documented invariants, collecting violations for take_violations
+    pub fn set_strict(self: &mut Self, strict: bool) {
+        self.strict = strict;
+    }
+
+    // deterministic runs go without the pixel count, as when it's read back depends on the GPU (see end_frame)
+    pub fn set_deterministic(self: &mut Self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    // (command queue address, what was wrong) for each violation since the last call
+    pub fn take_violations(self: &mut Self) -> Vec<(u32, String)> {
+        return std::mem::take(&mut self.violations);
+    }
+
+    pub fn set_cable(self: &mut Self, cable: DisplayCable) {
+        self.cable_type = cable;
+    }
+
+    pub fn get_reg(self: &mut Self, reg: usize) -> u32 {
+        if reg == REG_STATUS {
+            return
+                if self.reset_state { STATUSBIT_RESET } else { 0 } |
+                if self.cmd_fifo.len() == 0 { STATUSBIT_CMDFIFOEMPTY } else { 0 } |
+                match self.err_mode {
+                    ErrorMode::None => 0,
+                    ErrorMode::AddressError => STATUSBIT_ERR_ADDR,
+                    ErrorMode::CmdError => STATUSBIT_ERR_CMD,
+                };
+        }
+        else if reg == REG_CMDPORT {
+            return self.last_cmd_tok.pop_front().unwrap_or(0);
+        }
+        else if reg == REG_DISPLAYMODE {
+            return
+                match self.cable_type {
+                    DisplayCable::VGA => DISPLAYBIT_CABLE_VGA,
+                    DisplayCable::Composite => DISPLAYBIT_CABLE_COMPOSITE,
+                    DisplayCable::SVideo => DISPLAYBIT_CABLE_SVIDEO,
+                    DisplayCable::Component => DISPLAYBIT_CABLE_COMPONENT
+                } |
+                if self.display_enable { DISPLAYBIT_ENABLE } else { 0 } |
+                if self.display_interlace { DISPLAYBIT_INTERLACE } else { 0 };
+        }
+        else {
+            return 0;
+        }
+    }
+
+    pub fn set_reg(self: &mut Self, reg: usize, value: u32) {
+        if reg == REG_STATUS {
+            if value & STATUSBIT_RESET == 0 {
+                self.reset_state = true;
+            }
+        }
+        else if reg == REG_CMDPORT {
+            // value is address of command queue in main RAM
+            self.cmd_fifo.push_back(value);
+        }
+        else if reg == REG_DISPLAYMODE {
+            self.display_enable = (value & DISPLAYBIT_ENABLE) != 0;
+            self.display_interlace = (value & DISPLAYBIT_INTERLACE) != 0;
+        }
+    }
+
+    pub fn tick(self: &mut Self, graphics_device: &Device, cmd_buffer: &CommandBuffer) {
+        // execute commands
+        let cmds = self.cmd_fifo.drain(0..).collect::<Vec<u32>>();
+        for cmd_addr in cmds {
+            if self.unchecked {
+                self.exec_cmd_queue::<Unchecked>(cmd_addr, graphics_device, &cmd_buffer);
+            }
+            else {
+                self.exec_cmd_queue::<Validating>(cmd_addr, graphics_device, &cmd_buffer);
+            }
+        }
+    }
+
+    // dst_addr is a word address. the shadow copy is updated immediately (the command processor reads from it), while the GPU copy is updated in command buffer order
+    pub fn upload(self: &mut Self, mem: &[u32], dst_addr: u32, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        let mut vram: BufferMemMap<'_, u32> = self.vram_transfer.map::<u32>(gfx_device, false);
+        vram.mem_mut()[dst_addr as usize..][..mem.len()].copy_from_slice(mem);
+        drop(vram);
+
+        self.stats.dma_bytes = self.stats.dma_bytes.wrapping_add((mem.len() * 4) as u32);
+
+        // stage through a buffer of its own - the shadow may be written again before this command buffer executes, & the GPU has to see the data as of *now*
+        let mut staging = gfx_device.create_transfer_buffer()
+            .with_size((mem.len() * 4) as u32)
+            .with_usage(TransferBufferUsage::Upload)
+            .build()
+            .unwrap();
+
+        let mut staging_mem: BufferMemMap<'_, u32> = staging.map::<u32>(gfx_device, false);
+        staging_mem.mem_mut().copy_from_slice(mem);
+        drop(staging_mem);
+
+        let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).unwrap();
+        copy_pass.upload_to_gpu_buffer(
+        TransferBufferLocation::new()
+            .with_transfer_buffer(&staging)
+            .with_offset(0), 
+        BufferRegion::new()
+            .with_buffer(&self.vram)
+            .with_offset(dst_addr * 4)
+            .with_size((mem.len() * 4) as u32),
+        false);
+        gfx_device.end_copy_pass(copy_pass);
+    }
+
+    // bounds-checked upload on behalf of the guest - flags an address error instead of panicking
+    pub fn dma(self: &mut Self, mem: &[u32], dst_addr: u32, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        if dst_addr as u64 + mem.len() as u64 > (VRAM_SIZE / 4) as u64 {
+            self.err_mode = ErrorMode::AddressError;
+            return;
+        }
+
+        if !mem.is_empty() {
+            self.upload(mem, dst_addr, gfx_device, cmd_buffer);
+        }
+    }
+
+    pub fn set_error(self: &mut Self, err: ErrorMode) {
+        self.err_mode = err;
+    }
+
+    pub fn take_tokens(self: &mut Self) -> Vec<u32> {
+        return self.last_cmd_tok.drain(..).collect();
+    }
+
+    pub fn take_stats(self: &mut Self) -> VdpStats {
+        return std::mem::take(&mut self.stats);
+    }
+
+    // start noting every texture the draws bind, until take_texture_capture
+    pub fn begin_texture_capture(self: &mut Self) {
+        self.texture_capture = Some(Vec::new());
+    }
+
+    pub fn take_texture_capture(self: &mut Self) -> Option<Vec<TextureBinding>> {
+        return self.texture_capture.take();
+    }
+
+    fn capture_draw_textures(texture_capture: &mut Option<Vec<TextureBinding>>, internal_reg: &[u32]) {
+        let Some(bindings) = texture_capture else {
+            return;
+        };
+
+        for unit in 0..TEXTURE_UNITS {
+            let conf = (internal_reg[INTERNALREG_TUCONF as usize] >> (unit * 16)) & 0xFFFF;
+            let addr = internal_reg[INTERNALREG_TU0ADDR as usize + unit];
+
+            if conf & TUCONFBIT_ENABLE == 0 {
+                continue;
+            }
+
+            match bindings.iter_mut().find(|b| b.unit == unit && b.addr == addr && b.conf == conf) {
+                Some(binding) => binding.draws += 1,
+                None => bindings.push(TextureBinding { unit, addr, conf, draws: 1 }),
+            }
+        }
+    }
+
+    pub fn internal_regs(self: &Self) -> &[u32] {
+        return &self.internal_reg;
+    }
+
+    // restoring a save state: the GPU's copy is refreshed before the next command queue runs
+    pub fn set_internal_regs(self: &mut Self, regs: &[u32]) {
+        self.internal_reg.copy_from_slice(regs);
+        self.regmem_dirty = true;
+    }
+
+    // what the guest can see of the VDP besides VRAM & the internal registers: queued command queues, tokens it hasn't read,
+    // & the display & error state
+    pub fn save_state(self: &Self) -> Vec<u8> {
+        let mut out = StateWriter::new();
+
+        out.bool(self.reset_state);
+        out.words(&self.cmd_fifo.iter().copied().collect::<Vec<u32>>());
+        out.words(&self.last_cmd_tok.iter().copied().collect::<Vec<u32>>());
+        out.bool(self.display_enable);
+        out.bool(self.display_interlace);
+        out.u32(match self.err_mode {
+            ErrorMode::None => 0,
+            ErrorMode::AddressError => 1,
+            ErrorMode::CmdError => 2,
+        });
+        out.words(&self.display.map_or(Vec::new(), |(addr, dims)| vec![addr, dims]));
+
+        return out.finish();
+    }
+
+    pub fn load_state(self: &mut Self, data: &[u8]) -> Result<(), String> {
+        let mut state = StateReader::new(data);
+
+        let reset_state = state.bool()?;
+        let cmd_fifo = state.words()?;
+        let last_cmd_tok = state.words()?;
+        let display_enable = state.bool()?;
+        let display_interlace = state.bool()?;
+        let err_mode = match state.u32()? {
+            0 => ErrorMode::None,
+            1 => ErrorMode::AddressError,
+            2 => ErrorMode::CmdError,
+            mode => return Err(format!("unknown error mode {}", mode)),
+        };
+        // states from before swaps existed end here
+        let display = if state.at_end() { Vec::new() } else { state.words()? };
+        let display = match display[..] {
+            [] => None,
+            [addr, dims] => Some((addr, dims)),
+            _ => return Err(format!("display has {} words rather than 2", display.len())),
+        };
+        state.finish()?;
+
+        self.reset_state = reset_state;
+        self.cmd_fifo = cmd_fifo.into();
+        self.last_cmd_tok = last_cmd_tok.into();
+        self.display_enable = display_enable;
+        self.display_interlace = display_interlace;
+        self.display = display;
+        self.err_mode = err_mode;
+        return Ok(());
+    }
+
+    // copy the current contents of VRAM back to the host. this stalls until the GPU is idle, so it's only meant for debug tooling (screenshots, dumps), not per-frame use
+    pub fn read_vram(self: &mut Self, gfx_device: &Device) -> Vec<u8> {
+        return Self::download(&self.vram, &mut self.vram_readback, gfx_device);
+    }
+
+    fn download(buffer: &Buffer, readback: &mut TransferBuffer, gfx_device: &Device) -> Vec<u8> {
+        let cmd_buffer = gfx_device.acquire_command_buffer().unwrap();
+
+        let copy_pass = gfx_device.begin_copy_pass(&cmd_buffer).unwrap();
+        copy_pass.download_from_gpu_buffer(
+        BufferRegion::new()
+            .with_buffer(buffer)
+            .with_offset(0)
+            .with_size(VRAM_SIZE),
+        TransferBufferLocation::new()
+            .with_transfer_buffer(readback)
+            .with_offset(0));
+        gfx_device.end_copy_pass(copy_pass);
+
+        cmd_buffer.submit().unwrap();
+        gfx_device.wait_idle().unwrap();
+
+        let mem: BufferMemMap<'_, u8> = readback.map::<u8>(gfx_device, false);
+        return mem.mem().to_vec();
+    }
+
+    pub fn set_render_debug(self: &mut Self, mode: RenderDebugMode) {
+        self.render_debug = mode;
+    }
+
+    pub fn render_debug(self: &Self) -> RenderDebugMode {
+        return self.render_debug;
+    }
+
+    pub fn set_draw_isolation(self: &mut Self, isolation: DrawIsolation) {
+        self.draw_isolation = isolation;
+    }
+
+    pub fn draw_isolation(self: &Self) -> DrawIsolation {
+        return self.draw_isolation;
+    }
+
+    // draw commands executed in the last whole frame (the range draw isolation can pick from)
+    pub fn last_frame_draws(self: &Self) -> u32 {
+        return self.last_frame_draws;
+    }
+
+    // copy out the vertex unit's output for the given vertex list (numbered per frame, like draws) the next time a frame has it
+    pub fn capture_vu_output(self: &mut Self, list: u32) {
+        self.vu_capture_list = Some(list);
+    }
+
+    // forget a capture_vu_output request that hasn't been met yet
+    pub fn cancel_vu_capture(self: &mut Self) {
+        self.vu_capture_list = None;
+    }
+
+    // the requested VU output, once the frame it was in has been submitted. waits for the GPU to finish with it
+    pub fn take_vu_capture(self: &mut Self, gfx_device: &Device) -> Option<VuCapture> {
+        let (mut capture, mut readback) = self.vu_capture.take()?;
+
+        gfx_device.wait_idle().unwrap();
+
+        let mem: BufferMemMap<'_, u32> = readback.map::<u32>(gfx_device, false);
+        capture.vertices = mem.mem().chunks_exact(VU_OUTPUT_WORDS).map(VuVertex::decode).collect();
+
+        return Some(capture);
+    }
+
+    // call before servicing each frame - draw & vertex list numbering, overdraw counts, & the pixel count start over every
+    // frame
+    pub fn begin_frame(self: &mut Self, frame: u64, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        self.last_frame_draws = self.frame_draws;
+        self.frame_draws = 0;
+        self.frame_vertex_lists = 0;
+        self.frame = frame;
+
+        // the last frame's pixel count, copied out when it ended. this doesn't wait for the GPU to get that far, so if it
+        // hasn't yet, the count is from a frame or two before instead
+        if self.pixels_copied {
+            let mem: BufferMemMap<'_, u32> = self.pixels_readback.map::<u32>(gfx_device, false);
+            self.stats.pixels = mem.mem()[0];
+        }
+
+        let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).unwrap();
+        copy_pass.upload_to_gpu_buffer(TransferBufferLocation::new().with_transfer_buffer(&self.overdraw_zero),
+            BufferRegion::new().with_buffer(&self.pixels).with_size(4), false);
+
+        if self.render_debug == RenderDebugMode::Overdraw {
+            copy_pass.upload_to_gpu_buffer(TransferBufferLocation::new().with_transfer_buffer(&self.overdraw_zero),
+                BufferRegion::new().with_buffer(&self.overdraw).with_size(VRAM_SIZE), false);
+        }
+
+        gfx_device.end_copy_pass(copy_pass);
+    }
+
+    // call after servicing each frame - copies the frame's pixel count out for the next begin_frame. not in deterministic
+    // runs: which frame's count begin_frame would see depends on how far the GPU has got, so the guest is left with 0
+    pub fn end_frame(self: &mut Self, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        if self.deterministic {
+            return;
+        }
+
+        let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).unwrap();
+        copy_pass.download_from_gpu_buffer(BufferRegion::new().with_buffer(&self.pixels).with_size(4),
+            TransferBufferLocation::new().with_transfer_buffer(&self.pixels_readback));
+        gfx_device.end_copy_pass(copy_pass);
+
+        self.pixels_copied = true;
+    }
+
+    // the framebuffer on display: (word address, FBDIM)
+    pub fn display_framebuffer(self: &Self) -> (u32, u32) {
+        return self.display.unwrap_or((self.internal_reg[INTERNALREG_FBADDR as usize], self.internal_reg[INTERNALREG_FBDIM as usize]));
+    }
+
+    // the internal registers with FBADDR & FBDIM describing the framebuffer on display, for screenshot::framebuffer_rgba
+    pub fn display_regs(self: &Self) -> [u32;INTERNALREG_COUNT] {
+        let (fb_addr, fb_dim) = self.display_framebuffer();
+        let mut regs = self.internal_reg;
+        regs[INTERNALREG_FBADDR as usize] = fb_addr;
+        regs[INTERNALREG_FBDIM as usize] = fb_dim;
+        return regs;
+    }
+
+    // for presenting straight out of VRAM, without a readback
+    pub fn vram_buffer(self: &Self) -> &Buffer {
+        return &self.vram;
+    }
+
+    // the framebuffer as it should be shown (RGBA8) - the overdraw heat map instead while that's on. stalls like read_vram
+    pub fn read_framebuffer(self: &mut Self, gfx_device: &Device) -> Result<(u32, u32, Vec<u8>), String> {
+        if self.render_debug != RenderDebugMode::Overdraw {
+            let regs = self.display_regs();
+            let vram = self.read_vram(gfx_device);
+            return framebuffer_rgba(&regs, &vram);
+        }
+
+        let fb_dim = self.display_framebuffer().1;
+        let (width, height) = (fb_dim & 0xFFFF, fb_dim >> 16);
+
+        if width == 0 || height == 0 || width * height > VRAM_SIZE / 4 {
+            return Err(format!("framebuffer dimensions ({}x{}) are not usable", width, height));
+        }
+
+        let counts = Self::download(&self.overdraw, &mut self.vram_readback, gfx_device);
+        let counts: Vec<u32> = counts[..(width * height * 4) as usize].chunks_exact(4).map(|c| u32::from_le_bytes(c.try_into().unwrap())).collect();
+
+        return Ok((width, height, renderdebug::heat_map(&counts)));
+    }
+
+    fn reset(self: &mut Self) {
+        for r in &mut self.internal_reg {
+            *r = 0;
+        }
+        self.regmem_dirty = true;
+        self.cmd_fifo.clear();
+        self.last_cmd_tok.clear();
+        self.display_enable = false;
+        self.display_interlace = false;
+        self.display = None;
+        self.reset_state = false;
+        self.err_mode = ErrorMode::None;
+    }
+
+    fn flush_regmem(regmem_transfer: &mut TransferBuffer, regmem: &Buffer, internal_reg: &[u32], gfx_device: &Device, cmd_buffer: &CommandBuffer, regmem_dirty: &mut bool) {
+        if *regmem_dirty {
+            let mut transfer = regmem_transfer.map::<u32>(gfx_device, true);
+            transfer.mem_mut().copy_from_slice(internal_reg);
+            drop(transfer);
+
+            let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).unwrap();
+            copy_pass.upload_to_gpu_buffer(TransferBufferLocation::new().with_transfer_buffer(&regmem_transfer),
+                BufferRegion::new().with_buffer(&regmem).with_size(regmem.len()), true);
+            gfx_device.end_copy_pass(copy_pass);
+
+            *regmem_dirty = false;
+        }
+    }
+
+    // set a run of VRAM words to a value or copy one over them (see FillUBO), on the GPU & in order with the draws around it
+    fn fill_vram(fill_pipeline: &ComputePipeline, vram: &Buffer, ubo: FillUBO, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        let compute_pass = gfx_device.begin_compute_pass(cmd_buffer, &[], &[
+            StorageBufferReadWriteBinding::new().with_buffer(vram).with_cycle(false)
+        ]).unwrap();
+        {
+            compute_pass.bind_compute_pipeline(fill_pipeline);
+            cmd_buffer.push_compute_uniform_data(0, &ubo);
+            compute_pass.dispatch(ubo.count.div_ceil(FILL_THREADS), 1, 1);
+        }
+        gfx_device.end_compute_pass(compute_pass);
+    }
+
+    // flag the error & (when validating) report where the queue went wrong
+    fn queue_fault<V: ValidationMode>(self: &mut Self, queue_addr: u32, fault: QueueFault) {
+        match fault {
+            QueueFault::Address { addr, what } => {
+                self.err_mode = ErrorMode::AddressError;
+                if V::CHECKED {
+                    crate::log!(Vdp, Warn, "command queue at {:#X} faulted at {:#X}: {}", queue_addr, addr, what);
+                }
+            }
+            QueueFault::UnknownOpcode { addr, op } => {
+                self.err_mode = ErrorMode::CmdError;
+                if V::CHECKED {
+                    crate::log!(Vdp, Warn, "command queue at {:#X} has unknown opcode {:#X} at {:#X}", queue_addr, op, addr);
+                }
+            }
+            QueueFault::TooManyDispatches { addr } => {
+                self.err_mode = ErrorMode::CmdError;
+                if V::CHECKED {
+                    crate::log!(Vdp, Warn, "command queue at {:#X} gave up at {:#X}: more than {} draws, vertex lists, clears & swaps", queue_addr, addr, MAX_QUEUE_DISPATCHES);
+                }
+            }
+            QueueFault::ListTooLong { addr, count } => {
+                self.err_mode = ErrorMode::CmdError;
+                if V::CHECKED {
+                    crate::log!(Vdp, Warn, "command queue at {:#X} has a list of {} at {:#X}, more than {}", queue_addr, count, addr, MAX_LIST_COUNT);
+                }
+            }
+        }
+    }
+
+    fn exec_cmd_queue<V: ValidationMode>(self: &mut Self, queue_addr: u32, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        // command buffers reside in VRAM - lucky for us, we basically maintain a full copy of the VRAM state in a transfer buffer
+        let mem: BufferMemMap<'_, u32> = self.vram_transfer.map::<u32>(gfx_device, false);
+        let mut reader = QueueReader::<V>::new(mem.mem(), queue_addr);
+        let _queue_span = self.chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "vdp", format!("queue {:#X}", queue_addr)));
+
+        loop {
+            let (cmd_addr, hdr, cmd) = match reader.next_cmd() {
+                Ok(v) => v,
+                Err(fault) => {
+                    drop(mem);
+                    self.queue_fault::<V>(queue_addr, fault);
+                    return;
+                }
+            };
+
+            if let Some(history) = &self.cmd_history {
+                history.push((cmd_addr as u64) << 32 | hdr as u64);
+            }
+
+            crate::log!(Vdp, Trace, "{:#X}: {} ({:08x})", cmd_addr, cmd_name(hdr & 0xFF), hdr);
+            let _cmd_span = self.chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "vdp", cmd_name(hdr & 0xFF)));
+
+            if !matches!(cmd, VDPCommand::EndOfQueue { .. }) {
+                self.stats.commands = self.stats.commands.wrapping_add(1);
+            }
+
+            match cmd {
+                VDPCommand::WriteInternalRegister { reg, val } => {
+                    self.internal_reg[reg] = val;
+                    self.regmem_dirty = true;
+
+                    if self.strict {
+                        if let Some(msg) = vdpcheck::check_internal_reg(reg as u32, val) {
+                            self.violations.push((queue_addr, msg));
+                        }
+                    }
+                }
+                VDPCommand::ProcessVertexList { count, src, dst } => {
+                    self.stats.vertices = self.stats.vertices.wrapping_add(count);
+
+                    Self::flush_regmem(&mut self.regmem_transfer, &self.regmem, &self.internal_reg, gfx_device, cmd_buffer, &mut self.regmem_dirty);
+
+                    let compute_pass = gfx_device.begin_compute_pass(cmd_buffer, &[], &[
+                        StorageBufferReadWriteBinding::new().with_buffer(&self.vram).with_cycle(false)
+                    ]).unwrap();
+                    {
+                        compute_pass.bind_compute_pipeline(&self.vu_pipeline);
+                        compute_pass.bind_compute_storage_buffers(0, &[&self.regmem]);
+
+                        let ubo = VertexUnitUBO {
+                            src_addr: src,
+                            dst_addr: dst
+                        };
+                        cmd_buffer.push_compute_uniform_data(0, &ubo);
+
+                        compute_pass.dispatch(count, 1, 1);
+                        self.stats.dispatches = self.stats.dispatches.wrapping_add(1);
+                    }
+                    gfx_device.end_compute_pass(compute_pass);
+
+                    let list = self.frame_vertex_lists;
+                    self.frame_vertex_lists += 1;
+
+                    if self.vu_capture_list == Some(list) && self.vu_capture.is_none() {
+                        // queue the copy right behind the dispatch, before anything else can touch the output. the decoder
+                        // has already cut the list down to what fits in VRAM
+                        let start = dst as usize;
+                        let words = count as usize * VU_OUTPUT_WORDS;
+
+                        if words >= VU_OUTPUT_WORDS {
+                            let readback = gfx_device.create_transfer_buffer()
+                                .with_size((words * 4) as u32)
+                                .with_usage(TransferBufferUsage::Download)
+                                .build()
+                                .unwrap();
+
+                            let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).unwrap();
+                            copy_pass.download_from_gpu_buffer(
+                            BufferRegion::new()
+                                .with_buffer(&self.vram)
+                                .with_offset((start * 4) as u32)
+                                .with_size((words * 4) as u32),
+                            TransferBufferLocation::new()
+                                .with_transfer_buffer(&readback)
+                                .with_offset(0));
+                            gfx_device.end_copy_pass(copy_pass);
+
+                            let capture = VuCapture { frame: self.frame, list, src, dst, vertices: Vec::new() };
+                            self.vu_capture = Some((capture, readback));
+                            self.vu_capture_list = None;
+                        }
+                    }
+                }
+                VDPCommand::DrawList { topology, count, addr } => {
+                    // a list's count is of primitives, a strip's of vertices - each one past the first two adds a triangle, &
+                    // each one past the first a line
+                    let (primitives, shader_topology) = match topology {
+                        Topology::TriangleList | Topology::LineList => (count, DRAW_TOPOLOGY_LIST),
+                        Topology::TriangleStrip => (count.saturating_sub(2), DRAW_TOPOLOGY_STRIP),
+                        Topology::LineStrip => (count.saturating_sub(1), DRAW_TOPOLOGY_STRIP),
+                    };
+
+                    self.stats.primitives = self.stats.primitives.wrapping_add(primitives);
+
+                    let draw = self.frame_draws;
+                    self.frame_draws += 1;
+
+                    if !self.draw_isolation.allows(draw) {
+                        continue;
+                    }
+
+                    Self::capture_draw_textures(&mut self.texture_capture, &self.internal_reg);
+
+                    // the framebuffer has to fit in VRAM for triangles & lines alike - as does the depth buffer, if the depth
+                    // test is going to use it (lines don't)
+                    let lines = matches!(topology, Topology::LineList | Topology::LineStrip);
+                    let dims = self.internal_reg[INTERNALREG_FBDIM as usize];
+                    let (width, height) = (dims & 0xFFFF, dims >> 16);
+                    let fb_addr = self.internal_reg[INTERNALREG_FBADDR as usize];
+                    let dbaddr = self.internal_reg[INTERNALREG_DBADDR as usize];
+
+                    if vram_run::<V>(fb_addr, width * height).is_none() {
+                        drop(mem);
+                        self.queue_fault::<V>(queue_addr, QueueFault::Address { addr: fb_addr, what: "framebuffer out of range" });
+                        return;
+                    }
+
+                    if !lines && self.internal_reg[INTERNALREG_DEPTH as usize] & DEPTHBIT_ENABLE != 0 && vram_run::<V>(dbaddr, width * height).is_none() {
+                        drop(mem);
+                        self.queue_fault::<V>(queue_addr, QueueFault::Address { addr: dbaddr, what: "depth buffer out of range" });
+                        return;
+                    }
+
+                    Self::flush_regmem(&mut self.regmem_transfer, &self.regmem, &self.internal_reg, gfx_device, cmd_buffer, &mut self.regmem_dirty);
+
+                    let compute_pass = gfx_device.begin_compute_pass(cmd_buffer, &[], &[
+                        StorageBufferReadWriteBinding::new().with_buffer(&self.vram).with_cycle(false),
+                        StorageBufferReadWriteBinding::new().with_buffer(&self.overdraw).with_cycle(false),
+                        StorageBufferReadWriteBinding::new().with_buffer(&self.pixels).with_cycle(false)
+                    ]).unwrap();
+                    {
+                        compute_pass.bind_compute_pipeline(if lines { &self.draw_lines_pipeline } else { &self.draw_tri_list_pipeline });
+                        compute_pass.bind_compute_storage_buffers(0, &[&self.regmem]);
+
+                        let ubo = DrawListUBO {
+                            addr,
+                            debug_mode: self.render_debug.shader_mode(),
+                            topology: shader_topology,
+                            count: primitives,
+                        };
+                        cmd_buffer.push_compute_uniform_data(0, &ubo);
+
+                        if lines || self.render_debug != RenderDebugMode::None {
+                            compute_pass.dispatch(primitives, 1, 1);
+                        }
+                        else {
+                            compute_pass.dispatch(width.div_ceil(TRI_TILE_SIZE), height.div_ceil(TRI_TILE_SIZE), 1);
+                        }
+                        self.stats.dispatches = self.stats.dispatches.wrapping_add(1);
+                    }
+                    gfx_device.end_compute_pass(compute_pass);
+                }
+                VDPCommand::ClearColor { .. } => {
+                }
+                VDPCommand::ClearDepth { depth } => {
+                    // the depth buffer is one float per pixel at DBADDR, the size of the framebuffer
+                    let dims = self.internal_reg[INTERNALREG_FBDIM as usize];
+                    let dbaddr = self.internal_reg[INTERNALREG_DBADDR as usize];
+
+                    let Some((start, words)) = vram_run::<V>(dbaddr, (dims & 0xFFFF) * (dims >> 16)) else {
+                        drop(mem);
+                        self.queue_fault::<V>(queue_addr, QueueFault::Address { addr: dbaddr, what: "depth buffer out of range" });
+                        return;
+                    };
+
+                    if words != 0 {
+                        let ubo = FillUBO { addr: start, count: words, value: depth.to_bits(), src: 0, mode: FILLMODE_VALUE };
+                        Self::fill_vram(&self.fill_pipeline, &self.vram, ubo, gfx_device, cmd_buffer);
+                        self.stats.dispatches = self.stats.dispatches.wrapping_add(1);
+                    }
+                }
+                VDPCommand::SwapBuffers { copy_target } => {
+                    // the display shows whichever framebuffer the last swap latched - FBADDR as it is now, or a copy of it
+                    // made at the copy target, which leaves the guest free to draw the next frame over the same FBADDR
+                    let dims = self.internal_reg[INTERNALREG_FBDIM as usize];
+                    let fb_addr = self.internal_reg[INTERNALREG_FBADDR as usize];
+                    let words = (dims & 0xFFFF) * (dims >> 16);
+
+                    let display = match copy_target {
+                        None => fb_addr,
+                        Some(target) => {
+                            let Some((src, src_words)) = vram_run::<V>(fb_addr, words) else {
+                                drop(mem);
+                                self.queue_fault::<V>(queue_addr, QueueFault::Address { addr: fb_addr, what: "framebuffer out of range" });
+                                return;
+                            };
+
+                            let Some((dst, dst_words)) = vram_run::<V>(target, words) else {
+                                drop(mem);
+                                self.queue_fault::<V>(queue_addr, QueueFault::Address { addr: target, what: "swap copy target out of range" });
+                                return;
+                            };
+
+                            if src_words.min(dst_words) != 0 {
+                                let ubo = FillUBO { addr: dst, count: src_words.min(dst_words), value: 0, src, mode: FILLMODE_COPY };
+                                Self::fill_vram(&self.fill_pipeline, &self.vram, ubo, gfx_device, cmd_buffer);
+                                self.stats.dispatches = self.stats.dispatches.wrapping_add(1);
+                            }
+
+                            dst
+                        }
+                    };
+
+                    crate::log!(Vdp, Debug, "swap: displaying {}x{} @ {:#X}", dims & 0xFFFF, dims >> 16, display);
+                    self.display = Some((display, dims));
+                }
+                VDPCommand::EndOfQueue { token } => {
+                    crate::log!(Vdp, Debug, "command queue at {:#X} ended at {:#X}, token {:#X}", queue_addr, cmd_addr, token);
+                    self.last_cmd_tok.push_back(token);
+                    return;
+                }
+            }
+        }
+    }
+}
+// what a command queue opcode does, for diagnostics
+pub fn cmd_name(op: u32) -> &'static str {
+    return match op {
+        0 => "write internal register",
+        1 => "process vertex list",
+        2 => "draw triangle list",
+        3 => "draw triangle strip",
+        4 => "draw line list",
+        5 => "draw line strip",
+        6 => "clear color",
+        7 => "clear depth",
+        8 => "swap buffers",
+        0xFF => "end of queue",
+        _ => "unknown",
+    };
+}
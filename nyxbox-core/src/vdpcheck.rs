@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use crate::{symbols::Symbols, vdpport, vdp::{self, TEXFORMAT_RGB565, TEXFORMAT_RGBA8888, TEXTURE_UNITS, TUCONFBIT_ENABLE, TUCONF_FORMAT_MASK, TUCONF_FORMAT_SHIFT, VRAM_SIZE}};
+
+// strict mode (--vdp-strict): every write to a VDP port register or internal register is checked against the rules below,
+// & breaking one gets logged with the guest PC of the MMIO write responsible. internal registers are written by command
+// queues, so those violations are pinned on the CMDPORT write that submitted the queue. nothing is changed or rejected -
+// the write still goes through exactly as it would without strict mode
+//
+// port registers:
+//   CMDPORT       queue address inside VRAM
+//   DISPLAYMODE   only ENABLE & INTERLACE may be set
+//   DMASRC        word-aligned
+//   DMADST        inside VRAM
+//   DMACTRL       no transfer started with a zero DMALEN
+//
+// internal registers:
+//   FBDIM         width & height each 1 to FB_MAX_DIM
+//   FBADDR, DBADDR, VUPROGADDR, TU0ADDR, TU1ADDR   inside VRAM
+//   VULAYOUT0-7   slot type 0 to VU_SLOT_TYPE_MAX (FLOAT1 .. SNORM4)
+//   TUCONF        enabled units use a known texture format
+//   DEPTH         only ENABLE, the compare function & WRITE may be set
+//   BLEND         a known blend mode, & no other bits set
+pub const FB_MAX_DIM: u32       = 1024;
+pub const VU_SLOT_TYPE_MAX: u32 = 5;
+
+const VRAM_WORDS: u32 = VRAM_SIZE / 4;
+
+pub fn check_port_write(addr: u32, val: u32, dma_len: u32) -> Option<String> {
+    match addr {
+        0x01 if val >= VRAM_WORDS => Some(format!("CMDPORT: command queue at {:#X} is outside VRAM", val)),
+        0x02 if val & !(vdp::DISPLAYBIT_ENABLE | vdp::DISPLAYBIT_INTERLACE) != 0 => Some(format!("DISPLAYMODE: reserved bits set in {:#X}", val)),
+        0x03 if val % 4 != 0 => Some(format!("DMASRC: {:08x} is not word-aligned", val)),
+        0x04 if val >= VRAM_WORDS => Some(format!("DMADST: {:#X} is outside VRAM", val)),
+        0x06 if val & vdpport::DMACTRLBIT_START != 0 && dma_len == 0 => Some("DMACTRL: transfer started with DMALEN = 0".to_string()),
+        _ => None,
+    }
+}
+
+pub fn check_internal_reg(reg: u32, val: u32) -> Option<String> {
+    match reg {
+        vdp::INTERNALREG_FBDIM => {
+            let (width, height) = (val & 0xFFFF, val >> 16);
+
+            if width == 0 || height == 0 || width > FB_MAX_DIM || height > FB_MAX_DIM {
+                return Some(format!("FBDIM: {}x{} is not a legal framebuffer size (1-{} each way)", width, height, FB_MAX_DIM));
+            }
+        }
+        vdp::INTERNALREG_FBADDR | vdp::INTERNALREG_DBADDR | vdp::INTERNALREG_VUPROGADDR | vdp::INTERNALREG_TU0ADDR | vdp::INTERNALREG_TU1ADDR => {
+            if val >= VRAM_WORDS {
+                return Some(format!("{}: {:#X} is outside VRAM", reg_name(reg), val));
+            }
+        }
+        _ if (vdp::INTERNALREG_VULAYOUT0..vdp::INTERNALREG_VULAYOUT0 + 8).contains(&reg) => {
+            if val & 7 > VU_SLOT_TYPE_MAX {
+                return Some(format!("VULAYOUT{}: unknown slot type {}", reg - vdp::INTERNALREG_VULAYOUT0, val & 7));
+            }
+        }
+        vdp::INTERNALREG_DEPTH => {
+            if val & !(vdp::DEPTHBIT_ENABLE | (vdp::DEPTH_FUNC_MASK << vdp::DEPTH_FUNC_SHIFT) | vdp::DEPTHBIT_WRITE) != 0 {
+                return Some(format!("DEPTH: reserved bits set in {:#X}", val));
+            }
+        }
+        vdp::INTERNALREG_BLEND => {
+            if val > vdp::BLENDMODE_MULTIPLY {
+                return Some(format!("BLEND: {:#X} is not a known blend mode", val));
+            }
+        }
+        vdp::INTERNALREG_TUCONF => {
+            for unit in 0..TEXTURE_UNITS {
+                let conf = (val >> (unit * 16)) & 0xFFFF;
+                let format = (conf >> TUCONF_FORMAT_SHIFT) & TUCONF_FORMAT_MASK;
+
+                if conf & TUCONFBIT_ENABLE != 0 && format != TEXFORMAT_RGBA8888 && format != TEXFORMAT_RGB565 {
+                    return Some(format!("TUCONF: texture unit {} uses unknown format {}", unit, format));
+                }
+            }
+        }
+        _ => {
+        }
+    }
+
+    return None;
+}
+
+fn reg_name(reg: u32) -> &'static str {
+    match reg {
+        vdp::INTERNALREG_FBADDR => "FBADDR",
+        vdp::INTERNALREG_DBADDR => "DBADDR",
+        vdp::INTERNALREG_VUPROGADDR => "VUPROGADDR",
+        vdp::INTERNALREG_TU0ADDR => "TU0ADDR",
+        vdp::INTERNALREG_TU1ADDR => "TU1ADDR",
+        _ => "?",
+    }
+}
+
+// logs each distinct violation from each PC once, so a guest repeating the same bad write every frame doesn't bury the log
+pub struct StrictLog {
+    seen: HashSet<(u32, String)>,
+    count: u64,
+    symbols: Symbols,
+}
+
+impl StrictLog {
+    pub fn new(symbols: Symbols) -> Self {
+        Self {
+            seen: HashSet::new(),
+            count: 0,
+            symbols,
+        }
+    }
+
+    pub fn report(self: &mut Self, pc: u32, msg: String) {
+        self.count += 1;
+
+        if self.seen.insert((pc, msg.clone())) {
+            crate::log!(Vdp, Warn, "strict: {} (pc {})", msg, self.symbols.describe(pc));
+        }
+    }
+
+    pub fn count(self: &Self) -> u64 {
+        return self.count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vdp::*;
+
+    #[test]
+    fn legal_internal_reg_writes_pass() {
+        let cases = [
+            (INTERNALREG_FBDIM, 320 | (240 << 16)),
+            (INTERNALREG_FBDIM, FB_MAX_DIM | (FB_MAX_DIM << 16)),
+            (INTERNALREG_FBDIM, 1 | (1 << 16)),
+            (INTERNALREG_FBADDR, 0),
+            (INTERNALREG_DBADDR, VRAM_WORDS - 1),
+            (INTERNALREG_VUPROGADDR, 0x1000),
+            (INTERNALREG_TU0ADDR, 0x2000),
+            (INTERNALREG_TU1ADDR, VRAM_WORDS - 1),
+            (INTERNALREG_VULAYOUT0, 0),
+            (INTERNALREG_VULAYOUT0 + 7, VU_SLOT_TYPE_MAX),
+            (INTERNALREG_DEPTH, 0),
+            (INTERNALREG_DEPTH, DEPTHBIT_ENABLE | (DEPTHFUNC_ALWAYS << DEPTH_FUNC_SHIFT) | DEPTHBIT_WRITE),
+            (INTERNALREG_BLEND, BLENDMODE_OPAQUE),
+            (INTERNALREG_BLEND, BLENDMODE_MULTIPLY),
+            (INTERNALREG_TUCONF, 0),
+            (INTERNALREG_TUCONF, TUCONFBIT_ENABLE | ((TUCONFBIT_ENABLE | (TEXFORMAT_RGB565 << TUCONF_FORMAT_SHIFT)) << 16)),
+            // a disabled unit's format isn't looked at
+            (INTERNALREG_TUCONF, 7 << TUCONF_FORMAT_SHIFT),
+            // registers without invariants take anything
+            (INTERNALREG_VPXY, 0xFFFFFFFF),
+            (INTERNALREG_CLIPWH, 0xFFFFFFFF),
+        ];
+
+        for (reg, val) in cases {
+            assert_eq!(check_internal_reg(reg, val), None, "register {} = {:#X}", reg, val);
+        }
+    }
+
+    #[test]
+    fn each_internal_reg_invariant_is_reported() {
+        let cases = [
+            (INTERNALREG_FBDIM, 240 << 16, "FBDIM: 0x240"),
+            (INTERNALREG_FBDIM, 320, "FBDIM: 320x0"),
+            (INTERNALREG_FBDIM, (FB_MAX_DIM + 1) | (240 << 16), "FBDIM: 1025x240"),
+            (INTERNALREG_FBDIM, 320 | ((FB_MAX_DIM + 1) << 16), "FBDIM: 320x1025"),
+            (INTERNALREG_FBADDR, VRAM_WORDS, "FBADDR: 0x200000 is outside VRAM"),
+            (INTERNALREG_DBADDR, u32::MAX, "DBADDR: 0xFFFFFFFF is outside VRAM"),
+            (INTERNALREG_VUPROGADDR, VRAM_WORDS, "VUPROGADDR: 0x200000 is outside VRAM"),
+            (INTERNALREG_TU0ADDR, VRAM_WORDS, "TU0ADDR: 0x200000 is outside VRAM"),
+            (INTERNALREG_TU1ADDR, VRAM_WORDS, "TU1ADDR: 0x200000 is outside VRAM"),
+            (INTERNALREG_VULAYOUT0 + 3, VU_SLOT_TYPE_MAX + 1, "VULAYOUT3: unknown slot type 6"),
+            (INTERNALREG_DEPTH, 0x20, "DEPTH: reserved bits set in 0x20"),
+            (INTERNALREG_DEPTH, DEPTHBIT_ENABLE | 0x80000000, "DEPTH: reserved bits set in 0x80000001"),
+            (INTERNALREG_BLEND, BLENDMODE_MULTIPLY + 1, "BLEND: 0x5 is not a known blend mode"),
+            (INTERNALREG_BLEND, 0x100, "BLEND: 0x100 is not a known blend mode"),
+            (INTERNALREG_TUCONF, TUCONFBIT_ENABLE | (2 << TUCONF_FORMAT_SHIFT), "TUCONF: texture unit 0 uses unknown format 2"),
+            (INTERNALREG_TUCONF, (TUCONFBIT_ENABLE | (7 << TUCONF_FORMAT_SHIFT)) << 16, "TUCONF: texture unit 1 uses unknown format 7"),
+        ];
+
+        for (reg, val, msg) in cases {
+            let err = check_internal_reg(reg, val);
+            assert!(err.as_deref().is_some_and(|err| err.starts_with(msg)), "register {} = {:#X}: expected '{}', got {:?}", reg, val, msg, err);
+        }
+    }
+
+    #[test]
+    fn each_port_write_invariant_is_reported() {
+        let cases = [
+            (0x01, VRAM_WORDS, 0, Some("CMDPORT")),
+            (0x01, VRAM_WORDS - 1, 0, None),
+            (0x02, vdp::DISPLAYBIT_ENABLE | vdp::DISPLAYBIT_INTERLACE, 0, None),
+            (0x02, 0x10, 0, Some("DISPLAYMODE")),
+            (0x03, 0x1002, 0, Some("DMASRC")),
+            (0x03, 0x1004, 0, None),
+            (0x04, VRAM_WORDS, 0, Some("DMADST")),
+            (0x06, vdpport::DMACTRLBIT_START, 0, Some("DMACTRL")),
+            (0x06, vdpport::DMACTRLBIT_START, 16, None),
+            (0x06, 0, 0, None),
+        ];
+
+        for (addr, val, dma_len, reg) in cases {
+            let err = check_port_write(addr, val, dma_len);
+            assert_eq!(err.as_deref().map(|err| err.split(':').next().unwrap()), reg, "port {:#X} = {:#X}: got {:?}", addr, val, err);
+        }
+    }
+}
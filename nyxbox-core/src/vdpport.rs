@@ -0,0 +1,473 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicU32, Ordering}, Arc}};
+
+use sdl3::gpu::{CommandBuffer, Device};
+
+use crate::{hwmodel::{HwLimits, Limit, CMD_FIFO_DEPTH, DMA_BYTES_PER_FRAME}, intc::IrqLine, machine::MachineRunContext, mem::MemoryMap, poison::PoisonMap, peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}, vdp::{self, VdpStats, VDP}, vdpcheck::{self, StrictLog}};
+
+pub const VDPPORT_MEM_SIZE: u32 = 4096;
+
+// sync status bits, alongside the VDP's own STATUS bits
+pub const STATUSBIT_DMABUSY: u32            = 0x20;
+pub const STATUSBIT_UPLOADPENDING: u32      = 0x40;
+pub const STATUSBIT_DRAWBUSY: u32           = 0x80;
+pub const STATUSBIT_FENCEPENDING: u32       = 0x100;
+
+pub const DMACTRLBIT_START: u32             = 1;
+
+// STATCMDS, STATPRIMS, STATVERTS, STATDMABYTES & STATPIXELS are read-only & report the previous frame's work: commands processed
+// (not counting end of queue), primitives drawn, vertices transformed, bytes moved into VRAM by DMA, & pixels written by draws
+// (a pixel drawn over counts again). the GPU counts the pixels, & they're read back without waiting on it, so STATPIXELS is a
+// frame behind the rest - & always 0 in deterministic runs, where it couldn't be the same from one run to the next
+
+// coherency rules the guest can rely on:
+//
+// - VDP register writes, command queue submissions, DMA transfers, & fences take effect in the order the CPU issued them
+// - DMA copies main RAM into VRAM. once DMABUSY clears, the source may be reused. once UPLOADPENDING clears, the data is
+//   visible to every command queue submitted afterwards (both the command processor & the GPU)
+// - a command queue submitted *before* a DMA never observes that DMA's data, even if it hasn't executed yet
+// - GPU writes (vertex unit output, rendering) are never visible to the command processor - command queues must be uploaded by DMA
+// - DRAWBUSY is set while submitted command queues haven't been confirmed complete. writing FENCE requests confirmation:
+//   once FENCE reads back the written value (& FENCEPENDING clears), all prior work has landed in VRAM
+enum PortOp {
+    // pc is the guest PC of the write (strict mode only)
+    SetReg { reg: usize, val: u32, pc: u32 },
+    Dma { src: u32, dst: u32, len: u32 },
+    Fence { token: u32, draw_seq: u32 },
+}
+
+struct VdpPortState {
+    ops: VecDeque<PortOp>,
+    status: u32,
+    display_mode: u32,
+    tokens: VecDeque<u32>,
+    dma_src: u32,
+    dma_dst: u32,
+    dma_len: u32,
+    dma_pending: u32,
+    draw_seq: u32,
+    draw_seq_done: u32,
+    fence_pending: Option<(u32, u32)>,
+    fence_done: u32,
+    last_stats: VdpStats,
+}
+
+impl VdpPortState {
+    // command queue submissions waiting for the next service, i.e. what's sitting in the hardware's command FIFO
+    fn queued_cmds(self: &Self) -> usize {
+        return self.ops.iter().filter(|op| matches!(op, PortOp::SetReg { reg: vdp::REG_CMDPORT, .. })).count();
+    }
+}
+
+// the guest's view of the VDP. the VDP itself lives on the frontend thread (it owns GPU resources), so accesses are queued
+// here & serviced once per tick
+pub struct VdpPort {
+    state: PeripheralLock<VdpPortState>,
+    map: MemoryMap,
+    poison: Option<Arc<PoisonMap>>,
+    strict: Option<PeripheralLock<StrictLog>>,
+    writer_pc: AtomicU32,
+    hw: Arc<HwLimits>,
+    irq: IrqLine,
+}
+
+impl VdpPort {
+    // with a poison map, DMA sources get checked for uninitialized or freed guest memory. strict mode checks every register
+    // write against the VDP's invariants (see vdpcheck.rs). the hardware model decides whether the command FIFO & DMA
+    // bandwidth limits are enforced. the IRQ line is raised when a command queue token arrives or a fence completes
+    pub fn new(map: MemoryMap, poison: Option<Arc<PoisonMap>>, strict: Option<StrictLog>, hw: Arc<HwLimits>, irq: IrqLine) -> Self {
+        Self {
+            state: PeripheralLock::new(VdpPortState {
+                ops: VecDeque::new(),
+                status: vdp::STATUSBIT_CMDFIFOEMPTY,
+                display_mode: 0,
+                tokens: VecDeque::new(),
+                dma_src: 0,
+                dma_dst: 0,
+                dma_len: 0,
+                dma_pending: 0,
+                draw_seq: 0,
+                draw_seq_done: 0,
+                fence_pending: None,
+                fence_done: 0,
+                last_stats: VdpStats::default(),
+            }),
+            map,
+            poison,
+            strict: strict.map(PeripheralLock::new),
+            writer_pc: AtomicU32::new(0),
+            hw,
+            irq,
+        }
+    }
+
+    pub fn is_strict(self: &Self) -> bool {
+        return self.strict.is_some();
+    }
+
+    // CPU thread, strict mode: the PC of the MMIO write about to be made
+    pub fn set_writer_pc(self: &Self, pc: u32) {
+        self.writer_pc.store(pc, Ordering::Relaxed);
+    }
+
+    // strict mode violations so far
+    pub fn strict_violations(self: &Self) -> u64 {
+        return self.strict.as_ref().map(|log| log.lock().count()).unwrap_or(0);
+    }
+
+    // apply queued guest accesses to the VDP, then run its command queues
+    pub fn service(self: &Self, vdp: &mut VDP, run_ctx: &MachineRunContext, gfx_device: &Device, cmd_buffer: &CommandBuffer) {
+        let mut ops = std::mem::take(&mut self.state.lock().ops);
+
+        // which guest write submitted each command queue, so strict mode can pin violations on it
+        let mut queue_pcs = Vec::new();
+        let mut dma_bytes = 0;
+
+        while let Some(op) = ops.pop_front() {
+            match op {
+                PortOp::SetReg { reg, val, pc } => {
+                    if reg == vdp::REG_CMDPORT {
+                        queue_pcs.push((val, pc));
+                    }
+
+                    vdp.set_reg(reg, val);
+                }
+                PortOp::Dma { src, dst, len } => {
+                    // out of bandwidth for this frame: this DMA & everything queued after it wait for the next one, so
+                    // ordering still holds
+                    if dma_bytes >= DMA_BYTES_PER_FRAME && self.hw.exceeded(Limit::DmaBandwidth, || format!("more than {} bytes of DMA in one frame", DMA_BYTES_PER_FRAME)) {
+                        let mut state = self.state.lock();
+                        ops.push_front(PortOp::Dma { src, dst, len });
+
+                        while let Some(op) = ops.pop_back() {
+                            state.ops.push_front(op);
+                        }
+                        break;
+                    }
+
+                    dma_bytes = dma_bytes.saturating_add(len.saturating_mul(4));
+
+                    // anything queued before the DMA must execute against the old contents
+                    vdp.tick(gfx_device, cmd_buffer);
+
+                    // DMALEN is whatever the guest wrote, so the ranges are checked before anything is read - a wild
+                    // length would otherwise have the host allocate gigabytes for a read that then fails anyway
+                    let bytes = len as usize * 4;
+
+                    if !self.map.in_memory(src as usize, bytes) || dst as u64 + len as u64 > (vdp::VRAM_SIZE / 4) as u64 {
+                        vdp.set_error(vdp::ErrorMode::AddressError);
+                        self.state.lock().dma_pending -= 1;
+                        continue;
+                    }
+
+                    if let Some(poison) = &self.poison {
+                        poison.on_dma(src, len.saturating_mul(4));
+                    }
+
+                    match run_ctx.mem_read(src, bytes) {
+                        Ok(data) => {
+                            let words: Vec<u32> = data.chunks_exact(4).map(|w| u32::from_le_bytes(w.try_into().unwrap())).collect();
+                            vdp.dma(&words, dst, gfx_device, cmd_buffer);
+                        }
+                        Err(_) => {
+                            vdp.set_error(vdp::ErrorMode::AddressError);
+                        }
+                    }
+
+                    self.state.lock().dma_pending -= 1;
+                }
+                PortOp::Fence { token, draw_seq } => {
+                    self.state.lock().fence_pending = Some((token, draw_seq));
+                }
+            }
+        }
+
+        vdp.tick(gfx_device, cmd_buffer);
+
+        if let Some(strict) = &self.strict {
+            let mut log = strict.lock();
+
+            for (queue_addr, msg) in vdp.take_violations() {
+                let pc = queue_pcs.iter().rev().find(|(addr, _)| *addr == queue_addr).map(|(_, pc)| *pc).unwrap_or(0);
+                log.report(pc, format!("{} (command queue at {:#X})", msg, queue_addr));
+            }
+        }
+
+        let mut state = self.state.lock();
+        state.status = vdp.get_reg(vdp::REG_STATUS);
+        state.display_mode = vdp.get_reg(vdp::REG_DISPLAYMODE);
+        let tokens = vdp.take_tokens();
+        let raise = !tokens.is_empty();
+        state.tokens.extend(tokens);
+
+        // serviced once per frame, so this is exactly the last frame's work
+        state.last_stats = vdp.take_stats();
+        drop(state);
+
+        if raise {
+            self.irq.raise();
+        }
+    }
+
+    // the VDP's work in the frame last serviced
+    pub fn last_stats(self: &Self) -> VdpStats {
+        return self.state.lock().last_stats;
+    }
+
+    pub fn fence_pending(self: &Self) -> bool {
+        return self.state.lock().fence_pending.is_some();
+    }
+
+    // called once the command buffer containing the fenced work has been submitted & the GPU has gone idle
+    pub fn complete_fence(self: &Self) {
+        let mut state = self.state.lock();
+
+        if let Some((token, draw_seq)) = state.fence_pending.take() {
+            state.fence_done = token;
+            state.draw_seq_done = draw_seq;
+            drop(state);
+
+            self.irq.raise();
+        }
+    }
+}
+
+impl Peripheral for VdpPort {
+    fn read(self: &Self, addr: u32) -> u32 {
+        let mut state = self.state.lock();
+
+        match addr {
+            0x00 => {
+                // STATUS
+                let cmd_queued = state.ops.iter().any(|op| matches!(op, PortOp::SetReg { reg: vdp::REG_CMDPORT, .. }));
+                let fence_queued = state.ops.iter().any(|op| matches!(op, PortOp::Fence { .. }));
+
+                let mut status = state.status;
+
+                if cmd_queued {
+                    status &= !vdp::STATUSBIT_CMDFIFOEMPTY;
+                }
+
+                if self.hw.is_strict() && state.queued_cmds() >= CMD_FIFO_DEPTH {
+                    status |= vdp::STATUSBIT_CMDFIFOFULL;
+                }
+
+                return status |
+                    if state.dma_pending != 0 { STATUSBIT_DMABUSY | STATUSBIT_UPLOADPENDING } else { 0 } |
+                    if state.draw_seq != state.draw_seq_done { STATUSBIT_DRAWBUSY } else { 0 } |
+                    if state.fence_pending.is_some() || fence_queued { STATUSBIT_FENCEPENDING } else { 0 };
+            }
+            0x01 => {
+                // CMDPORT
+                return state.tokens.pop_front().unwrap_or(0);
+            }
+            0x02 => {
+                // DISPLAYMODE
+                return state.display_mode;
+            }
+            0x03 => {
+                // DMASRC
+                return state.dma_src;
+            }
+            0x04 => {
+                // DMADST
+                return state.dma_dst;
+            }
+            0x05 => {
+                // DMALEN
+                return state.dma_len;
+            }
+            0x06 => {
+                // DMACTRL
+                return if state.dma_pending != 0 { DMACTRLBIT_START } else { 0 };
+            }
+            0x07 => {
+                // FENCE
+                return state.fence_done;
+            }
+            0x08 => {
+                // STATCMDS
+                return state.last_stats.commands;
+            }
+            0x09 => {
+                // STATPRIMS
+                return state.last_stats.primitives;
+            }
+            0x0A => {
+                // STATVERTS
+                return state.last_stats.vertices;
+            }
+            0x0B => {
+                // STATDMABYTES
+                return state.last_stats.dma_bytes;
+            }
+            0x0C => {
+                // STATPIXELS
+                return state.last_stats.pixels;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        let mut state = self.state.lock();
+        let pc = self.writer_pc.load(Ordering::Relaxed);
+
+        if let Some(strict) = &self.strict {
+            if let Some(msg) = vdpcheck::check_port_write(addr, val, state.dma_len) {
+                strict.lock().report(pc, msg);
+            }
+        }
+
+        match addr {
+            0x00 => {
+                // STATUS
+                state.ops.push_back(PortOp::SetReg { reg: vdp::REG_STATUS, val, pc });
+            }
+            0x01 => {
+                // CMDPORT
+                let queued = state.queued_cmds();
+
+                // the submission is lost, as it would be with the hardware's FIFO full
+                if queued >= CMD_FIFO_DEPTH && self.hw.exceeded(Limit::CmdFifo, || format!("{} command queues submitted in one frame (pc {:08x})", queued + 1, pc)) {
+                    return;
+                }
+
+                state.ops.push_back(PortOp::SetReg { reg: vdp::REG_CMDPORT, val, pc });
+                state.draw_seq = state.draw_seq.wrapping_add(1);
+            }
+            0x02 => {
+                // DISPLAYMODE
+                state.ops.push_back(PortOp::SetReg { reg: vdp::REG_DISPLAYMODE, val, pc });
+            }
+            0x03 => {
+                // DMASRC
+                state.dma_src = val;
+            }
+            0x04 => {
+                // DMADST
+                state.dma_dst = val;
+            }
+            0x05 => {
+                // DMALEN
+                state.dma_len = val;
+            }
+            0x06 => {
+                // DMACTRL
+                if val & DMACTRLBIT_START != 0 {
+                    let (src, dst, len) = (state.dma_src, state.dma_dst, state.dma_len);
+                    state.ops.push_back(PortOp::Dma { src, dst, len });
+                    state.dma_pending += 1;
+                }
+            }
+            0x07 => {
+                // FENCE
+                let draw_seq = state.draw_seq;
+                state.ops.push_back(PortOp::Fence { token: val, draw_seq });
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        let mut stats = vec![("vdpport", self.state.stats())];
+
+        if let Some(strict) = &self.strict {
+            stats.push(("vdpport.strict", strict.stats()));
+        }
+
+        return stats;
+    }
+
+    // guest accesses not yet serviced go in too, so a state saved mid-frame picks up where it left off
+    fn save_state(self: &Self) -> Vec<u8> {
+        let state = self.state.lock();
+        let mut out = StateWriter::new();
+
+        out.u32(state.ops.len() as u32);
+
+        for op in &state.ops {
+            match op {
+                PortOp::SetReg { reg, val, pc } => {
+                    out.u32(0);
+                    out.words(&[*reg as u32, *val, *pc]);
+                }
+                PortOp::Dma { src, dst, len } => {
+                    out.u32(1);
+                    out.words(&[*src, *dst, *len]);
+                }
+                PortOp::Fence { token, draw_seq } => {
+                    out.u32(2);
+                    out.words(&[*token, *draw_seq]);
+                }
+            }
+        }
+
+        out.u32(state.status);
+        out.u32(state.display_mode);
+        out.words(&state.tokens.iter().copied().collect::<Vec<u32>>());
+        out.words(&[state.dma_src, state.dma_dst, state.dma_len, state.dma_pending, state.draw_seq, state.draw_seq_done, state.fence_done]);
+        out.bool(state.fence_pending.is_some());
+        out.words(&state.fence_pending.map_or(vec![0, 0], |(token, draw_seq)| vec![token, draw_seq]));
+        out.words(&[state.last_stats.commands, state.last_stats.primitives, state.last_stats.vertices, state.last_stats.dma_bytes, state.last_stats.pixels]);
+
+        return out.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut state = StateReader::new(data);
+        let mut ops = VecDeque::new();
+
+        for _ in 0..state.u32()? {
+            let kind = state.u32()?;
+            let args = state.words()?;
+
+            ops.push_back(match (kind, args.as_slice()) {
+                (0, [reg, val, pc]) => PortOp::SetReg { reg: *reg as usize, val: *val, pc: *pc },
+                (1, [src, dst, len]) => PortOp::Dma { src: *src, dst: *dst, len: *len },
+                (2, [token, draw_seq]) => PortOp::Fence { token: *token, draw_seq: *draw_seq },
+                _ => return Err(format!("bad queued access (kind {})", kind)),
+            });
+        }
+
+        let status = state.u32()?;
+        let display_mode = state.u32()?;
+        let tokens = state.words()?;
+        let regs = state.words()?;
+        let fence_pending = state.bool()?;
+        let fence = state.words()?;
+        let stats = state.words()?;
+        state.finish()?;
+
+        let ([dma_src, dma_dst, dma_len, dma_pending, draw_seq, draw_seq_done, fence_done], [fence_token, fence_seq]) = (regs.as_slice(), fence.as_slice()) else {
+            return Err("wrong number of registers".to_string());
+        };
+
+        // states from before STATPIXELS have no pixel count
+        let (commands, primitives, vertices, dma_bytes, pixels) = match stats[..] {
+            [commands, primitives, vertices, dma_bytes] => (commands, primitives, vertices, dma_bytes, 0),
+            [commands, primitives, vertices, dma_bytes, pixels] => (commands, primitives, vertices, dma_bytes, pixels),
+            _ => return Err("wrong number of registers".to_string()),
+        };
+
+        *self.state.lock() = VdpPortState {
+            ops,
+            status,
+            display_mode,
+            tokens: tokens.into(),
+            dma_src: *dma_src,
+            dma_dst: *dma_dst,
+            dma_len: *dma_len,
+            dma_pending: *dma_pending,
+            draw_seq: *draw_seq,
+            draw_seq_done: *draw_seq_done,
+            fence_pending: fence_pending.then_some((*fence_token, *fence_seq)),
+            fence_done: *fence_done,
+            last_stats: VdpStats { commands, primitives, vertices, dma_bytes, pixels, dispatches: 0 },
+        };
+
+        return Ok(());
+    }
+}
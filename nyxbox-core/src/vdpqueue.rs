@@ -0,0 +1,166 @@
+use std::marker::PhantomData;
+
+use crate::{vdp::{Topology, VDPCommand, ValidationMode, VRAM_SIZE}, vucapture::VU_OUTPUT_WORDS};
+
+// a queue can't be longer than VRAM without going round it again - only possible on the unchecked path, where addresses
+// wrap, & the guest forgot to end the queue (or jumped back into it by overwriting it)
+pub const MAX_QUEUE_WORDS: usize = (VRAM_SIZE / 4) as usize;
+
+// commands that put work on the GPU (draws, vertex lists, clears, swaps) one queue can have - a few frames' worth for any
+// real game. the word limit alone would let a queue of back to back dispatches stall the GPU for seconds
+pub const MAX_QUEUE_DISPATCHES: usize = 16384;
+
+// the most a vertex list or draw's count can be. every tile of the framebuffer walks a draw's whole list of triangles, so
+// this is what bounds the work of a single dispatch - & vertex lists & line draws are one work group per item, which
+// the GPU can't go past 65535 of in a dispatch anyway
+pub const MAX_LIST_COUNT: u32 = 65535;
+
+// where a run of `words` words from word address `addr` lies in VRAM, & how much of it does. validating, the whole run
+// has to be in VRAM - unchecked, the start wraps like any other address & the run is cut short at the end of VRAM
+pub fn vram_run<V: ValidationMode>(addr: u32, words: u32) -> Option<(u32, u32)> {
+    let start = V::vram_addr(addr)? as u32;
+
+    if V::CHECKED && start as u64 + words as u64 > (VRAM_SIZE / 4) as u64 {
+        return None;
+    }
+
+    return Some((start, words.min(VRAM_SIZE / 4 - start)));
+}
+
+// why the command processor gave up on a queue
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QueueFault {
+    // a word or pointer outside VRAM, or a queue that never ended
+    Address { addr: u32, what: &'static str },
+    UnknownOpcode { addr: u32, op: u32 },
+    // past MAX_QUEUE_DISPATCHES
+    TooManyDispatches { addr: u32 },
+    // a vertex list or draw with a count past MAX_LIST_COUNT
+    ListTooLong { addr: u32, count: u32 },
+}
+
+// the command processor's front end: walks a command queue in a VRAM image & decodes it, with nothing of the GPU. the
+// VDP runs what comes out against the GPU's copy of VRAM, & the fuzz target (nyxbox-core/fuzz) against arbitrary VRAM
+// contents - every address it hands out has been through V::vram_addr, & every read is bounds checked against the image,
+// so nothing a guest writes can make it index out of bounds or loop forever
+pub struct QueueReader<'v, V: ValidationMode> {
+    vram: &'v [u32],
+    addr: u32,
+    words: usize,
+    dispatches: usize,
+    mode: PhantomData<V>,
+}
+
+impl<'v, V: ValidationMode> QueueReader<'v, V> {
+    // addr is the word address of the queue's first command
+    pub fn new(vram: &'v [u32], addr: u32) -> Self {
+        Self {
+            vram,
+            addr,
+            words: 0,
+            dispatches: 0,
+            mode: PhantomData,
+        }
+    }
+
+    fn word(self: &mut Self) -> Result<u32, QueueFault> {
+        if self.words == MAX_QUEUE_WORDS {
+            return Err(QueueFault::Address { addr: self.addr, what: "queue never ended" });
+        }
+
+        let Some(word) = V::vram_addr(self.addr).and_then(|idx| self.vram.get(idx)) else {
+            return Err(QueueFault::Address { addr: self.addr, what: "queue ran past the end of VRAM" });
+        };
+
+        self.addr = self.addr.wrapping_add(1);
+        self.words += 1;
+        return Ok(*word);
+    }
+
+    // a pointer handed on to a shader, as a VRAM word address - out of range is a fault when validating, & wraps into VRAM
+    // when not, so the shaders never see anything past the end of it either way
+    fn ptr(self: &mut Self, what: &'static str) -> Result<u32, QueueFault> {
+        let ptr = self.word()?;
+
+        let Some(addr) = V::vram_addr(ptr) else {
+            return Err(QueueFault::Address { addr: ptr, what });
+        };
+
+        return Ok(addr as u32);
+    }
+
+    // how many of `vertices` vertices from word address addr are in VRAM. validating, they all have to be - unchecked, the
+    // list is cut short at the end of VRAM, like any other run of words the guest points at
+    fn vertices_in_vram(addr: u32, vertices: u32, what: &'static str) -> Result<u32, QueueFault> {
+        let Some((_, words)) = vram_run::<V>(addr, vertices * VU_OUTPUT_WORDS as u32) else {
+            return Err(QueueFault::Address { addr, what });
+        };
+
+        return Ok(words / VU_OUTPUT_WORDS as u32);
+    }
+
+    // a draw, with its count cut down to the primitives that are in VRAM. a list's count is of primitives, a strip's of
+    // vertices
+    fn draw(self: &mut Self, cmd_addr: u32, topology: Topology, count: u32, what: &'static str) -> Result<VDPCommand, QueueFault> {
+        let addr = self.ptr(what)?;
+
+        if count > MAX_LIST_COUNT {
+            return Err(QueueFault::ListTooLong { addr: cmd_addr, count });
+        }
+
+        let per_primitive = match topology {
+            Topology::TriangleList => 3,
+            Topology::LineList => 2,
+            Topology::TriangleStrip | Topology::LineStrip => 1,
+        };
+
+        let count = Self::vertices_in_vram(addr, count * per_primitive, what)? / per_primitive;
+        return Ok(VDPCommand::DrawList { topology, count, addr });
+    }
+
+    // the next command, with the address & header word it was decoded from. after an end of queue, the reader carries on
+    // with whatever follows, so the caller stops there
+    pub fn next_cmd(self: &mut Self) -> Result<(u32, u32, VDPCommand), QueueFault> {
+        let cmd_addr = self.addr;
+        let hdr = self.word()?;
+        let op = hdr & 0xFF;
+        let count = hdr >> 8;
+
+        let cmd = match op {
+            0 => VDPCommand::WriteInternalRegister { reg: ((hdr >> 8) & 0xFF) as usize, val: self.word()? },
+            1 => {
+                let src = self.ptr("vertex list source out of range")?;
+                let dst = self.ptr("vertex list destination out of range")?;
+
+                if count > MAX_LIST_COUNT {
+                    return Err(QueueFault::ListTooLong { addr: cmd_addr, count });
+                }
+
+                // the output is VU_OUTPUT_WORDS per vertex - where the inputs are depends on VUSTRIDE & the layout, so the
+                // vertex unit checks those itself
+                let count = Self::vertices_in_vram(dst, count, "vertex list destination out of range")?;
+                VDPCommand::ProcessVertexList { count, src, dst }
+            }
+            2 => self.draw(cmd_addr, Topology::TriangleList, count, "triangle list out of range")?,
+            3 => self.draw(cmd_addr, Topology::TriangleStrip, count, "triangle strip out of range")?,
+            4 => self.draw(cmd_addr, Topology::LineList, count, "line list out of range")?,
+            5 => self.draw(cmd_addr, Topology::LineStrip, count, "line strip out of range")?,
+            6 => VDPCommand::ClearColor { color: self.word()? },
+            7 => VDPCommand::ClearDepth { depth: f32::from_bits(self.word()?) },
+            // bit 8 of the header says a copy target follows
+            8 => VDPCommand::SwapBuffers { copy_target: if hdr & 0x100 != 0 { Some(self.ptr("swap copy target out of range")?) } else { None } },
+            0xFF => VDPCommand::EndOfQueue { token: count },
+            _ => return Err(QueueFault::UnknownOpcode { addr: cmd_addr, op }),
+        };
+
+        if !matches!(cmd, VDPCommand::WriteInternalRegister { .. } | VDPCommand::EndOfQueue { .. }) {
+            if self.dispatches == MAX_QUEUE_DISPATCHES {
+                return Err(QueueFault::TooManyDispatches { addr: cmd_addr });
+            }
+
+            self.dispatches += 1;
+        }
+
+        return Ok((cmd_addr, hdr, cmd));
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::{json, Value};
+
+// the vertex unit's output for one vertex list, copied out of VRAM right after the list ran (so later lists reusing the same
+// output buffer don't get in the way). lists are numbered from 0 in the order the VDP executes them each frame
+pub struct VuCapture {
+    pub frame: u64,
+    pub list: u32,
+    pub src: u32,
+    pub dst: u32,
+    pub vertices: Vec<VuVertex>,
+}
+
+// one output vertex - 10 words: position, texcoord 0, texcoord 1, color 0, color 1 (see vu.glsl)
+pub const VU_OUTPUT_WORDS: usize = 10;
+
+pub struct VuVertex {
+    pub position: [f32;4],
+    pub texcoord0: [f32;2],
+    pub texcoord1: [f32;2],
+    pub color0: u32,
+    pub color1: u32,
+}
+
+impl VuVertex {
+    pub fn decode(words: &[u32]) -> Self {
+        let f = |idx: usize| f32::from_bits(words[idx]);
+
+        return Self {
+            position: [f(0), f(1), f(2), f(3)],
+            texcoord0: [f(4), f(5)],
+            texcoord1: [f(6), f(7)],
+            color0: words[8],
+            color1: words[9],
+        };
+    }
+}
+
+// colors are shown as RRGGBBAA, whatever order they're packed in
+fn color_hex(col: u32) -> String {
+    return format!("{:08x}", col.swap_bytes());
+}
+
+impl VuCapture {
+    pub fn table(self: &Self) -> String {
+        let mut out = format!("vertex list {} @ frame {}: {} vertices, {:08x} -> {:08x}\n", self.list, self.frame, self.vertices.len(), self.src, self.dst);
+        out.push_str(&format!("{:>5}  {:>10} {:>10} {:>10} {:>10}  {:>9} {:>9}  {:>9} {:>9}  {:>8} {:>8}\n",
+            "#", "x", "y", "z", "w", "u0", "v0", "u1", "v1", "color0", "color1"));
+
+        for (idx, v) in self.vertices.iter().enumerate() {
+            out.push_str(&format!("{:>5}  {:>10.4} {:>10.4} {:>10.4} {:>10.4}  {:>9.4} {:>9.4}  {:>9.4} {:>9.4}  {} {}\n",
+                idx, v.position[0], v.position[1], v.position[2], v.position[3],
+                v.texcoord0[0], v.texcoord0[1], v.texcoord1[0], v.texcoord1[1],
+                color_hex(v.color0), color_hex(v.color1)));
+        }
+
+        return out;
+    }
+
+    pub fn to_json(self: &Self) -> Value {
+        let vertices: Vec<Value> = self.vertices.iter().map(|v| json!({
+            "position": v.position,
+            "texcoord0": v.texcoord0,
+            "texcoord1": v.texcoord1,
+            "color0": color_hex(v.color0),
+            "color1": color_hex(v.color1),
+        })).collect();
+
+        return json!({
+            "frame": self.frame,
+            "list": self.list,
+            "src": format!("{:08x}", self.src),
+            "dst": format!("{:08x}", self.dst),
+            "vertices": vertices,
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use crate::{intc::IrqLine, peripheral::{LockStats, Peripheral, PeripheralLock}, savestate::{StateReader, StateWriter}, timebase::Timebase};
+
+pub const WATCHDOG_MEM_SIZE: u32 = 4096;
+
+pub const WATCHDOGCTRLBIT_ENABLE: u32       = 1;
+pub const WATCHDOGCTRLBIT_IRQ: u32          = 2;
+
+pub const WATCHDOGSTATUSBIT_WARNED: u32     = 1;
+pub const WATCHDOGSTATUSBIT_RESET: u32      = 2;
+
+// only this value written to KICK counts, so code that's run off into the weeds is unlikely to keep the watchdog fed
+pub const WATCHDOG_KICK_KEY: u32 = 0x6B69636B;
+
+// resets the machine if the guest stops kicking it. once enabled it counts down PERIOD emulated microseconds, & every kick
+// starts the count again:
+//
+//   CTRL       ENABLE starts the countdown. with IRQ set as well, the first expiry only raises IRQ_WATCHDOG as a warning &
+//              counts down again - it's the next one that resets
+//   PERIOD     countdown length in microseconds. writing it restarts the count
+//   KICK       write WATCHDOG_KICK_KEY to restart the count (& call off a warning)
+//   REMAIN     (read only) microseconds until the next expiry, 0 while disabled
+//   STATUS     WARNED (the IRQ stage has fired), RESET (the last reset was the watchdog's) - write 1s to clear
+//
+// the watchdog isn't reset along with the machine (that's how the guest can see RESET), but its reset does disable it.
+// expiry is checked at frame boundaries, so it lands up to a frame late - which keeps it on emulated time, & deterministic
+pub struct Watchdog {
+    state: PeripheralLock<WatchdogState>,
+    timebase: Arc<Timebase>,
+    irq: IrqLine,
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    enabled: bool,
+    irq: bool,
+    period: u32,
+    // emulated microsecond the count runs out at
+    deadline: u64,
+    warned: bool,
+    status: u32,
+}
+
+impl Watchdog {
+    pub fn new(timebase: Arc<Timebase>, irq: IrqLine) -> Self {
+        Self {
+            state: PeripheralLock::new(WatchdogState::default()),
+            timebase,
+            irq,
+        }
+    }
+
+    // frontend, at each frame boundary: true if the watchdog has run out & the machine should be reset
+    pub fn poll(self: &Self) -> bool {
+        let mut state = self.state.lock();
+        let now = self.timebase.now_us();
+
+        if !state.enabled || state.period == 0 || now < state.deadline {
+            return false;
+        }
+
+        if state.irq && !state.warned {
+            state.warned = true;
+            state.status |= WATCHDOGSTATUSBIT_WARNED;
+            state.deadline = now + state.period as u64;
+            drop(state);
+
+            self.irq.raise();
+            return false;
+        }
+
+        state.enabled = false;
+        state.warned = false;
+        state.status |= WATCHDOGSTATUSBIT_RESET;
+        return true;
+    }
+
+    fn restart(self: &Self, state: &mut WatchdogState) {
+        state.deadline = self.timebase.now_us() + state.period as u64;
+        state.warned = false;
+    }
+}
+
+impl Peripheral for Watchdog {
+    fn read(self: &Self, addr: u32) -> u32 {
+        let state = self.state.lock();
+
+        match addr {
+            0x00 => {
+                // CTRL
+                return
+                    if state.enabled { WATCHDOGCTRLBIT_ENABLE } else { 0 } |
+                    if state.irq { WATCHDOGCTRLBIT_IRQ } else { 0 };
+            }
+            0x01 => {
+                // PERIOD
+                return state.period;
+            }
+            0x03 => {
+                // REMAIN
+                if !state.enabled {
+                    return 0;
+                }
+
+                return state.deadline.saturating_sub(self.timebase.now_us()).min(u32::MAX as u64) as u32;
+            }
+            0x04 => {
+                // STATUS
+                return state.status;
+            }
+            _ => {
+                return 0;
+            }
+        }
+    }
+
+    fn write(self: &Self, addr: u32, val: u32) {
+        let mut state = self.state.lock();
+
+        match addr {
+            0x00 => {
+                // CTRL
+                let enable = val & WATCHDOGCTRLBIT_ENABLE != 0;
+
+                if enable && !state.enabled {
+                    self.restart(&mut state);
+                }
+
+                state.enabled = enable;
+                state.irq = val & WATCHDOGCTRLBIT_IRQ != 0;
+            }
+            0x01 => {
+                // PERIOD
+                state.period = val;
+                self.restart(&mut state);
+            }
+            0x02 => {
+                // KICK
+                if val == WATCHDOG_KICK_KEY {
+                    self.restart(&mut state);
+                }
+            }
+            0x04 => {
+                // STATUS
+                state.status &= !val;
+            }
+            _ => {
+            }
+        }
+    }
+
+    fn lock_stats(self: &Self) -> Vec<(&'static str, LockStats)> {
+        return vec![("watchdog", self.state.stats())];
+    }
+
+    // the deadline is kept as time remaining, so it carries over to wherever emulated time is on load
+    fn save_state(self: &Self) -> Vec<u8> {
+        let state = self.state.lock();
+        let mut w = StateWriter::new();
+
+        w.bool(state.enabled);
+        w.bool(state.irq);
+        w.u32(state.period);
+        w.u64(state.deadline.saturating_sub(self.timebase.now_us()));
+        w.bool(state.warned);
+        w.u32(state.status);
+
+        return w.finish();
+    }
+
+    fn load_state(self: &Self, data: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(data);
+        let enabled = r.bool()?;
+        let irq = r.bool()?;
+        let period = r.u32()?;
+        let remain = r.u64()?;
+        let warned = r.bool()?;
+        let status = r.u32()?;
+        r.finish()?;
+
+        *self.state.lock() = WatchdogState {
+            enabled,
+            irq,
+            period,
+            deadline: self.timebase.now_us() + remain,
+            warned,
+            status,
+        };
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,10 @@
+use std::{env, fs, path::PathBuf};
+
+// put the linker script somewhere the linker will find it, for this crate & anything depending on it
+fn main() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::copy("link.x", out.join("link.x")).unwrap();
+
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=link.x");
+}
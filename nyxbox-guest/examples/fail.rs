@@ -0,0 +1,15 @@
+#![no_std]
+#![no_main]
+
+// a failing assert: the emulator captures the machine to captures/failures/<message>/ & the run exits with code 101
+
+use nyxbox_guest::{entry, println, sysinfo};
+
+fn main() -> ! {
+    println!("checking RAM size");
+    assert_eq!(sysinfo::ram_size(), 0, "ram size mismatch");
+
+    nyxbox_guest::exit(0);
+}
+
+entry!(main);
@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+use nyxbox_guest::{bios, entry, gamepad, println, regs, sysinfo};
+
+fn main() -> ! {
+    println!("hello from rust on NyxBox");
+    println!("version {}, features {:#x}, {} KiB RAM", sysinfo::version(), sysinfo::features(), sysinfo::ram_size() / 1024);
+
+    let mut last = 0;
+
+    loop {
+        bios::wait_vblank();
+
+        let buttons = gamepad::buttons();
+        if buttons & !last & regs::gamepad::BUTTON_START != 0 {
+            println!("start pressed on frame {}", sysinfo::frame());
+        }
+
+        last = buttons;
+    }
+}
+
+entry!(main);
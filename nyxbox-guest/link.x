@@ -0,0 +1,48 @@
+/* NyxBox guest memory layout - see nyxbox-guest/src/regs.rs (MAIN_RAM_BASE etc) */
+MEMORY
+{
+    ROM : ORIGIN = 0x00000000, LENGTH = 4M
+    RAM : ORIGIN = 0x01000000, LENGTH = 16M
+}
+
+ENTRY(_start);
+
+/* the stacks grow down from the top of main RAM: the IRQ handler's first, then the program's */
+_irq_stack_top = ORIGIN(RAM) + LENGTH(RAM);
+_stack_top = _irq_stack_top - 4K;
+
+SECTIONS
+{
+    /* the CPU starts executing at address 0, so the startup code has to come first */
+    .text : {
+        KEEP(*(.text.boot));
+        *(.text .text.*);
+    } > ROM
+
+    .rodata : ALIGN(4) {
+        *(.rodata .rodata.*);
+    } > ROM
+
+    /* initialized data lives in ROM & is copied into RAM by _start */
+    .data : ALIGN(4) {
+        __sdata = .;
+        *(.data .data.*);
+        . = ALIGN(4);
+        __edata = .;
+    } > RAM AT > ROM
+
+    __sidata = LOADADDR(.data);
+
+    .bss (NOLOAD) : ALIGN(4) {
+        __sbss = .;
+        *(.bss .bss.*);
+        *(COMMON);
+        . = ALIGN(4);
+        __ebss = .;
+    } > RAM
+
+    /DISCARD/ : {
+        *(.ARM.exidx .ARM.exidx.*);
+        *(.ARM.extab .ARM.extab.*);
+    }
+}
@@ -0,0 +1,37 @@
+use core::arch::asm;
+
+use crate::regs::bios;
+
+// BIOS calls are serviced by the emulator itself, so they cost nothing while the CPU is parked. prefer these to looping on
+// wait_for_frame - they can't wake early, & the emulator can report how long the guest spent waiting in them
+
+// park until the next frame - the end of one main loop iteration
+pub fn wait_vblank() {
+    unsafe {
+        asm!("svc #{num}", num = const bios::WAITVBLANK, options(nostack, preserves_flags));
+    }
+}
+
+// park until the given number of frames have started (0 returns straight away)
+pub fn sleep_frames(frames: u32) {
+    unsafe {
+        asm!("svc #{num}", num = const bios::SLEEPFRAMES, in("r0") frames, options(nostack, preserves_flags));
+    }
+}
+
+// write a byte to the UART, as if it went to the TX register
+pub fn putc(c: u8) {
+    unsafe {
+        asm!("svc #{num}", num = const bios::PUTC, in("r0") c as u32, options(nostack, preserves_flags));
+    }
+}
+
+// copy len bytes from src to dst, which mustn't overlap. returns how many were copied - fewer than len if either range
+// runs into something that isn't plain memory (e.g. MMIO)
+pub unsafe fn memcpy(dst: *mut u8, src: *const u8, len: usize) -> usize {
+    let copied: u32;
+
+    asm!("svc #{num}", num = const bios::MEMCPY, inout("r0") dst as u32 => copied, in("r1") src as u32, in("r2") len as u32, options(nostack, preserves_flags));
+
+    return copied as usize;
+}
@@ -0,0 +1,59 @@
+use core::fmt;
+
+use crate::{mmio, regs::debugport};
+
+// writes a request's name into the debug port, a byte at a time (the port keeps the first debugport::NAME_MAX)
+struct NameWriter;
+
+impl fmt::Write for NameWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            unsafe {
+                mmio::write(debugport::NAME, b as u32);
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+fn command(cmd: u32, name: fmt::Arguments) {
+    use fmt::Write;
+
+    let _ = NameWriter.write_fmt(name);
+
+    unsafe {
+        mmio::write(debugport::CMD, cmd);
+    }
+}
+
+// save the framebuffer to <capture dir>/<name>.png once this frame is done
+pub fn screenshot(name: &str) {
+    command(debugport::CMD_SCREENSHOT, format_args!("{}", name));
+}
+
+// log a named marker to <capture dir>/markers.txt
+pub fn marker(name: &str) {
+    command(debugport::CMD_MARKER, format_args!("{}", name));
+}
+
+// report a failed assert. the emulator snapshots the machine (save state, screenshot, recent UART output) into
+// <capture dir>/failures/<message>/ once this frame is done
+pub fn assert_failed(message: fmt::Arguments) {
+    command(debugport::CMD_ASSERT, message);
+}
+
+// end the run - the emulator exits with this code once the current frame is done (& so does nyxbox-runner)
+pub fn exit(code: i32) -> ! {
+    exit_with_result(code, format_args!(""));
+}
+
+// end the run, with a one-line result (e.g. "12 passed, 0 failed") for the emulator to print alongside the exit code
+pub fn exit_with_result(code: i32, result: fmt::Arguments) -> ! {
+    unsafe {
+        mmio::write(debugport::EXITCODE, code as u32);
+    }
+
+    command(debugport::CMD_EXIT, result);
+    crate::halt();
+}
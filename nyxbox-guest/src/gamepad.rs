@@ -0,0 +1,10 @@
+use crate::{mmio, regs::gamepad};
+
+// gamepad::BUTTON_* flags for the buttons held this frame
+pub fn buttons() -> u32 {
+    return unsafe { mmio::read(gamepad::BUTTONS) };
+}
+
+pub fn held(button: u32) -> bool {
+    return buttons() & button != 0;
+}
@@ -0,0 +1,53 @@
+use core::{arch::asm, sync::atomic::{AtomicUsize, Ordering}};
+
+use crate::{mmio, regs::intc};
+
+// the program's IRQ handler, as a fn(u32) address - 0 until one's installed
+static HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+// install the handler for IRQs. it's passed the intc::IRQ_* lines that were active, which are acknowledged once it returns
+pub fn set_handler(handler: fn(u32)) {
+    HANDLER.store(handler as usize, Ordering::Release);
+}
+
+// let these intc::IRQ_* lines interrupt the CPU
+pub fn enable(lines: u32) {
+    unsafe {
+        mmio::write(intc::ENABLE, mmio::read(intc::ENABLE) | lines);
+    }
+}
+
+pub fn disable(lines: u32) {
+    unsafe {
+        mmio::write(intc::ENABLE, mmio::read(intc::ENABLE) & !lines);
+    }
+}
+
+// clear the CPSR's I bit, so enabled lines actually interrupt
+pub fn enable_cpu() {
+    unsafe {
+        asm!("cpsie i", options(nomem, nostack));
+    }
+}
+
+pub fn disable_cpu() {
+    unsafe {
+        asm!("cpsid i", options(nomem, nostack));
+    }
+}
+
+// called in IRQ mode by the vector in lib.rs
+#[no_mangle]
+extern "C" fn __nyxbox_irq() {
+    let active = unsafe { mmio::read(intc::ACTIVE) };
+    let handler = HANDLER.load(Ordering::Acquire);
+
+    if handler != 0 {
+        let handler: fn(u32) = unsafe { core::mem::transmute(handler) };
+        handler(active);
+    }
+
+    unsafe {
+        mmio::write(intc::ACK, active);
+    }
+}
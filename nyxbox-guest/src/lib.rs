@@ -0,0 +1,119 @@
+#![no_std]
+
+// runtime for NyxBox guest programs written in no_std rust: startup code, MMIO register bindings, UART printing, & a panic
+// handler that reports over the UART. a program is just:
+//
+//   #![no_std]
+//   #![no_main]
+//
+//   use nyxbox_guest::{entry, println};
+//
+//   fn main() -> ! {
+//       println!("hello from rust");
+//       nyxbox_guest::halt();
+//   }
+//
+//   entry!(main);
+//
+// `cargo run --example hello` from this directory builds it & boots it in the emulator (see .cargo/config.toml)
+
+use core::arch::{asm, global_asm};
+
+pub mod regs;
+pub mod mmio;
+pub mod uart;
+pub mod sysinfo;
+pub mod gamepad;
+pub mod debug;
+pub mod poison;
+pub mod bios;
+pub mod intc;
+
+pub use debug::exit;
+
+// the CPU comes out of reset at address 0 in SVC mode with interrupts off, & the exception vectors follow it. set up the IRQ
+// & SVC stacks, copy initialized data out of ROM, zero bss, then hand off to the program's entry point
+global_asm!(
+    ".section .text.boot, \"ax\"",
+    ".global _start",
+    ".arm",
+    "_start:",
+    "    b 0f",
+    "    b .",
+    "    b .",
+    "    b .",
+    "    b .",
+    "    b .",
+    "    b __nyxbox_irq_entry",
+    "    b .",
+
+    "0:  msr cpsr_c, #0xd2",
+    "    ldr sp, =_irq_stack_top",
+    "    msr cpsr_c, #0xd3",
+    "    ldr sp, =_stack_top",
+
+    "    ldr r0, =__sidata",
+    "    ldr r1, =__sdata",
+    "    ldr r2, =__edata",
+    "1:  cmp r1, r2",
+    "    ldrlo r3, [r0], #4",
+    "    strlo r3, [r1], #4",
+    "    blo 1b",
+
+    "    ldr r1, =__sbss",
+    "    ldr r2, =__ebss",
+    "    mov r3, #0",
+    "2:  cmp r1, r2",
+    "    strlo r3, [r1], #4",
+    "    blo 2b",
+
+    "    bl __nyxbox_main",
+    "3:  wfi",
+    "    b 3b",
+
+    // save what the AAPCS lets the handler clobber, & return to the interrupted instruction with its CPSR restored
+    "__nyxbox_irq_entry:",
+    "    sub lr, lr, #4",
+    "    stmfd sp!, {{r0-r3, r12, lr}}",
+    "    bl __nyxbox_irq",
+    "    ldmfd sp!, {{r0-r3, r12, pc}}^",
+);
+
+// declare the program's entry point, a `fn() -> !`
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[no_mangle]
+        pub extern "C" fn __nyxbox_main() -> ! {
+            let main: fn() -> ! = $main;
+            main()
+        }
+    };
+}
+
+// sleep until the emulator's next frame signal (or an enabled interrupt, once they're on)
+pub fn wait_for_frame() {
+    unsafe {
+        asm!("wfi", options(nomem, nostack, preserves_flags));
+    }
+}
+
+// park the CPU for good
+pub fn halt() -> ! {
+    loop {
+        wait_for_frame();
+    }
+}
+
+#[cfg(feature = "panic-handler")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    use core::fmt::Write;
+
+    // print first, so the full message is in the failure report's UART tail (the assert name gets truncated)
+    let _ = writeln!(uart::Uart, "panic: {}", info);
+    debug::assert_failed(format_args!("{}", info.message()));
+
+    // same exit code as a panicking host program
+    exit(101);
+}
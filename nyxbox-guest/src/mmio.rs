@@ -0,0 +1,11 @@
+// volatile access to the console's 32-bit MMIO registers. addresses are the absolute byte addresses in `regs`
+
+#[inline(always)]
+pub unsafe fn read(addr: usize) -> u32 {
+    return core::ptr::read_volatile(addr as *const u32);
+}
+
+#[inline(always)]
+pub unsafe fn write(addr: usize, val: u32) {
+    core::ptr::write_volatile(addr as *mut u32, val);
+}
@@ -0,0 +1,39 @@
+use crate::{mmio, regs::{poison, sysinfo}, sysinfo::has_feature};
+
+// the poison port only exists when the emulator runs with --poison, so everything here checks for it first & is a no-op
+// otherwise - an allocator can call these unconditionally
+pub fn enabled() -> bool {
+    return has_feature(sysinfo::FEATUREBIT_POISON);
+}
+
+fn command(cmd: u32, ptr: *const u8, len: usize) {
+    if !enabled() {
+        return;
+    }
+
+    unsafe {
+        mmio::write(poison::ADDR, ptr as u32);
+        mmio::write(poison::LEN, len as u32);
+        mmio::write(poison::CMD, cmd);
+    }
+}
+
+// mark a block as invalid (e.g. on free) & fill it with poison::FILL_BYTE. reading it before it's written again gets
+// reported with the PC of the read
+pub fn poison(ptr: *const u8, len: usize) {
+    command(poison::CMD_POISON, ptr, len);
+}
+
+// mark a block as valid without writing it (e.g. memory the host filled in some other way)
+pub fn unpoison(ptr: *const u8, len: usize) {
+    command(poison::CMD_UNPOISON, ptr, len);
+}
+
+// reads of poisoned memory the emulator has caught so far
+pub fn hits() -> u32 {
+    if !enabled() {
+        return 0;
+    }
+
+    return unsafe { mmio::read(poison::HITS) };
+}
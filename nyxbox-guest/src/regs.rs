@@ -0,0 +1,244 @@
+// generated by `nyxbox gen-regs` from the emulator's own constants - regenerate rather than editing by hand
+#![allow(dead_code)]
+
+pub const BOOT_ROM_BASE: usize = 0x0;
+pub const BOOT_ROM_SIZE: usize = 0x400000;
+pub const MAIN_RAM_BASE: usize = 0x1000000;
+pub const MAIN_RAM_SIZE: usize = 0x1000000;
+pub const EXPANSION_RAM_BASE: usize = 0x2000000;
+pub const EXPANSION_RAM_SIZE: usize = 0x1000000;
+
+pub mod uart {
+    pub const BASE: usize = 0x6000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const TX: usize = BASE + 0x4;
+    pub const RX: usize = BASE + 0x8;
+    pub const TXLEVEL: usize = BASE + 0xC;
+    pub const STATUSBIT_RESET: u32 = 0x1;
+    pub const STATUSBIT_TXEMPTY: u32 = 0x2;
+    pub const STATUSBIT_TXFULL: u32 = 0x4;
+    pub const STATUSBIT_RXEMPTY: u32 = 0x8;
+}
+
+pub mod vdp {
+    pub const BASE: usize = 0x7000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const CMDPORT: usize = BASE + 0x4;
+    pub const DISPLAYMODE: usize = BASE + 0x8;
+    pub const DMASRC: usize = BASE + 0xC;
+    pub const DMADST: usize = BASE + 0x10;
+    pub const DMALEN: usize = BASE + 0x14;
+    pub const DMACTRL: usize = BASE + 0x18;
+    pub const FENCE: usize = BASE + 0x1C;
+    pub const STATCMDS: usize = BASE + 0x20;
+    pub const STATPRIMS: usize = BASE + 0x24;
+    pub const STATVERTS: usize = BASE + 0x28;
+    pub const STATDMABYTES: usize = BASE + 0x2C;
+    // approximate: counted on the GPU & read back without waiting on it, so it can lag a frame behind the other STAT registers. always 0 when sysinfo FEATUREBIT_DETERMINISTIC is set - don't base level of detail on it alone
+    pub const STATPIXELS: usize = BASE + 0x30;
+    pub const STATUSBIT_RESET: u32 = 0x1;
+    pub const STATUSBIT_CMDFIFOEMPTY: u32 = 0x2;
+    pub const STATUSBIT_CMDFIFOFULL: u32 = 0x4;
+    pub const STATUSBIT_ERR_MASK: u32 = 0x18;
+    pub const STATUSBIT_ERR_ADDR: u32 = 0x8;
+    pub const STATUSBIT_ERR_CMD: u32 = 0x10;
+    pub const STATUSBIT_DMABUSY: u32 = 0x20;
+    pub const STATUSBIT_UPLOADPENDING: u32 = 0x40;
+    pub const STATUSBIT_DRAWBUSY: u32 = 0x80;
+    pub const STATUSBIT_FENCEPENDING: u32 = 0x100;
+    pub const DISPLAYBIT_CABLE_MASK: u32 = 0x3;
+    pub const DISPLAYBIT_ENABLE: u32 = 0x4;
+    pub const DISPLAYBIT_INTERLACE: u32 = 0x8;
+    pub const DMACTRLBIT_START: u32 = 0x1;
+    pub const VRAM_SIZE: u32 = 0x800000;
+}
+
+pub mod clock {
+    pub const BASE: usize = 0x8000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const DT: usize = BASE + 0x4;
+    pub const CTR0LO: usize = BASE + 0x8;
+    pub const CTR0HI: usize = BASE + 0xC;
+    pub const CTR1LO: usize = BASE + 0x10;
+    pub const CTR1HI: usize = BASE + 0x14;
+    pub const CTR0P: usize = BASE + 0x18;
+    pub const CTR1P: usize = BASE + 0x1C;
+    pub const IRQ: usize = BASE + 0x20;
+    pub const IRQBIT_CTR0: u32 = 0x1;
+    pub const IRQBIT_CTR1: u32 = 0x2;
+}
+
+pub mod sysinfo {
+    pub const BASE: usize = 0x9000000;
+    pub const ID: usize = BASE + 0x0;
+    pub const VERSION: usize = BASE + 0x4;
+    pub const FEATURES: usize = BASE + 0x8;
+    pub const SEEDLO: usize = BASE + 0xC;
+    pub const SEEDHI: usize = BASE + 0x10;
+    pub const FRAME: usize = BASE + 0x14;
+    pub const FRAMESEED: usize = BASE + 0x18;
+    pub const RAMSIZE: usize = BASE + 0x1C;
+    pub const CPUBUDGET: usize = BASE + 0x20;
+    pub const MAINRAMSIZE: usize = BASE + 0x24;
+    pub const ROMSIZE: usize = BASE + 0x28;
+    pub const ID_VALUE: u32 = 0x4E595842;
+    pub const FEATUREBIT_UART: u32 = 0x1;
+    pub const FEATUREBIT_CLOCK: u32 = 0x2;
+    pub const FEATUREBIT_VDP: u32 = 0x4;
+    pub const FEATUREBIT_DETERMINISTIC: u32 = 0x8;
+    pub const FEATUREBIT_DEBUGPORT: u32 = 0x10;
+    pub const FEATUREBIT_MPU: u32 = 0x20;
+    pub const FEATUREBIT_FRAMEBUDGET: u32 = 0x40;
+    pub const FEATUREBIT_EXPANSIONRAM: u32 = 0x80;
+    pub const FEATUREBIT_GAMEPAD: u32 = 0x100;
+    pub const FEATUREBIT_POISON: u32 = 0x200;
+    pub const FEATUREBIT_STRICTHW: u32 = 0x400;
+    pub const FEATUREBIT_INTC: u32 = 0x800;
+    pub const FEATUREBIT_BUSERR: u32 = 0x1000;
+    pub const FEATUREBIT_COPROCESSOR: u32 = 0x2000;
+    pub const FEATUREBIT_WATCHDOG: u32 = 0x4000;
+}
+
+pub mod debugport {
+    pub const BASE: usize = 0xA000000;
+    pub const NAME: usize = BASE + 0x0;
+    pub const CMD: usize = BASE + 0x4;
+    pub const PENDING: usize = BASE + 0x8;
+    pub const SERVICED: usize = BASE + 0xC;
+    pub const EXITCODE: usize = BASE + 0x10;
+    pub const CMD_SCREENSHOT: u32 = 0x1;
+    pub const CMD_MARKER: u32 = 0x2;
+    pub const CMD_EXIT: u32 = 0x3;
+    pub const CMD_ASSERT: u32 = 0x4;
+    pub const NAME_MAX: u32 = 0x40;
+}
+
+pub mod mpu {
+    pub const BASE: usize = 0xB000000;
+    pub const CTRL: usize = BASE + 0x0;
+    pub const REGIONS: usize = BASE + 0x4;
+    pub const FAULTADDR: usize = BASE + 0x8;
+    pub const FAULTSTATUS: usize = BASE + 0xC;
+    pub const RBASE0: usize = BASE + 0x40;
+    pub const RSIZE0: usize = BASE + 0x44;
+    pub const RATTR0: usize = BASE + 0x48;
+    pub const REGION_STRIDE: u32 = 0x4;
+    pub const REGION_COUNT: u32 = 0x8;
+    pub const PAGE_SIZE: u32 = 0x1000;
+    pub const CTRLBIT_ENABLE: u32 = 0x1;
+    pub const ATTRBIT_READ: u32 = 0x1;
+    pub const ATTRBIT_WRITE: u32 = 0x2;
+    pub const ATTRBIT_EXEC: u32 = 0x4;
+    pub const ATTRBIT_ENABLE: u32 = 0x80000000;
+    pub const FAULTBIT_READ: u32 = 0x1;
+    pub const FAULTBIT_WRITE: u32 = 0x2;
+    pub const FAULTBIT_FETCH: u32 = 0x4;
+}
+
+pub mod framebudget {
+    pub const BASE: usize = 0xC000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const OVERRUNS: usize = BASE + 0x4;
+    pub const BUDGET: usize = BASE + 0x8;
+    pub const LASTFRAME: usize = BASE + 0xC;
+    pub const STATUSBIT_OVERRUN: u32 = 0x1;
+}
+
+pub mod gamepad {
+    pub const BASE: usize = 0xD000000;
+    pub const BUTTONS: usize = BASE + 0x0;
+    pub const BUTTON_UP: u32 = 0x1;
+    pub const BUTTON_DOWN: u32 = 0x2;
+    pub const BUTTON_LEFT: u32 = 0x4;
+    pub const BUTTON_RIGHT: u32 = 0x8;
+    pub const BUTTON_A: u32 = 0x10;
+    pub const BUTTON_B: u32 = 0x20;
+    pub const BUTTON_X: u32 = 0x40;
+    pub const BUTTON_Y: u32 = 0x80;
+    pub const BUTTON_L: u32 = 0x100;
+    pub const BUTTON_R: u32 = 0x200;
+    pub const BUTTON_START: u32 = 0x400;
+    pub const BUTTON_SELECT: u32 = 0x800;
+}
+
+pub mod poison {
+    pub const BASE: usize = 0xE000000;
+    pub const ADDR: usize = BASE + 0x0;
+    pub const LEN: usize = BASE + 0x4;
+    pub const CMD: usize = BASE + 0x8;
+    pub const HITS: usize = BASE + 0xC;
+    pub const CMD_POISON: u32 = 0x1;
+    pub const CMD_UNPOISON: u32 = 0x2;
+    pub const FILL_BYTE: u32 = 0xA5;
+}
+
+pub mod intc {
+    pub const BASE: usize = 0xF000000;
+    pub const PENDING: usize = BASE + 0x0;
+    pub const ENABLE: usize = BASE + 0x4;
+    pub const ACTIVE: usize = BASE + 0x8;
+    pub const ACK: usize = BASE + 0xC;
+    pub const RAISE: usize = BASE + 0x10;
+    pub const FIQSEL: usize = BASE + 0x14;
+    pub const FIQACTIVE: usize = BASE + 0x18;
+    pub const IRQ_VBLANK: u32 = 0x1;
+    pub const IRQ_VDP: u32 = 0x2;
+    pub const IRQ_UART_RX: u32 = 0x4;
+    pub const IRQ_TIMER: u32 = 0x8;
+    pub const IRQ_MAILBOX: u32 = 0x10;
+    pub const IRQ_WATCHDOG: u32 = 0x20;
+    pub const VECTOR: u32 = 0x18;
+    pub const FIQ_VECTOR: u32 = 0x1C;
+}
+
+pub mod buserr {
+    pub const BASE: usize = 0x10000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const ADDR: usize = BASE + 0x4;
+    pub const PC: usize = BASE + 0x8;
+    pub const COUNT: usize = BASE + 0xC;
+    pub const OPENBUS: usize = BASE + 0x10;
+    pub const STATUSBIT_READ: u32 = 0x1;
+    pub const STATUSBIT_WRITE: u32 = 0x2;
+    pub const STATUSBIT_FETCH: u32 = 0x4;
+}
+
+pub mod mailbox {
+    pub const BASE: usize = 0x11000000;
+    pub const STATUS: usize = BASE + 0x0;
+    pub const TX: usize = BASE + 0x4;
+    pub const RX: usize = BASE + 0x8;
+    pub const DOORBELL: usize = BASE + 0xC;
+    pub const ACK: usize = BASE + 0x10;
+    pub const COPCTRL: usize = BASE + 0x20;
+    pub const COPENTRY: usize = BASE + 0x24;
+    pub const COPSTACK: usize = BASE + 0x28;
+    pub const COPSTATUS: usize = BASE + 0x2C;
+    pub const FIFO_DEPTH: u32 = 0x10;
+    pub const STATUSBIT_RXREADY: u32 = 0x1;
+    pub const STATUSBIT_TXFULL: u32 = 0x2;
+    pub const COPCTRLBIT_RUN: u32 = 0x1;
+    pub const COPSTATUSBIT_RUNNING: u32 = 0x1;
+    pub const COPSTATUSBIT_FAULT: u32 = 0x2;
+}
+
+pub mod watchdog {
+    pub const BASE: usize = 0x12000000;
+    pub const CTRL: usize = BASE + 0x0;
+    pub const PERIOD: usize = BASE + 0x4;
+    pub const KICK: usize = BASE + 0x8;
+    pub const REMAIN: usize = BASE + 0xC;
+    pub const STATUS: usize = BASE + 0x10;
+    pub const CTRLBIT_ENABLE: u32 = 0x1;
+    pub const CTRLBIT_IRQ: u32 = 0x2;
+    pub const STATUSBIT_WARNED: u32 = 0x1;
+    pub const STATUSBIT_RESET: u32 = 0x2;
+    pub const KICK_KEY: u32 = 0x6B69636B;
+}
+
+pub mod bios {
+    pub const WAITVBLANK: u32 = 0x1;
+    pub const SLEEPFRAMES: u32 = 0x2;
+    pub const PUTC: u32 = 0x3;
+    pub const MEMCPY: u32 = 0x4;
+}
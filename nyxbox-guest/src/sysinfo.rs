@@ -0,0 +1,52 @@
+use crate::{mmio, regs::sysinfo};
+
+// true if the system info block is there at all (every NyxBox has one, but it's the cheapest sanity check there is)
+pub fn present() -> bool {
+    return unsafe { mmio::read(sysinfo::ID) } == sysinfo::ID_VALUE;
+}
+
+pub fn version() -> u32 {
+    return unsafe { mmio::read(sysinfo::VERSION) };
+}
+
+// sysinfo::FEATUREBIT_* flags for the peripherals this machine has
+pub fn features() -> u32 {
+    return unsafe { mmio::read(sysinfo::FEATURES) };
+}
+
+pub fn has_feature(bit: u32) -> bool {
+    return features() & bit != 0;
+}
+
+// per-boot random seed (fixed in deterministic mode)
+pub fn seed() -> u64 {
+    unsafe {
+        let lo = mmio::read(sysinfo::SEEDLO) as u64;
+        let hi = mmio::read(sysinfo::SEEDHI) as u64;
+        return (hi << 32) | lo;
+    }
+}
+
+pub fn frame() -> u32 {
+    return unsafe { mmio::read(sysinfo::FRAME) };
+}
+
+// bytes of RAM, including expansion RAM if fitted
+pub fn ram_size() -> u32 {
+    return unsafe { mmio::read(sysinfo::RAMSIZE) };
+}
+
+// bytes of main RAM alone, from regs::MAIN_RAM_BASE (ram_size() also counts expansion RAM, which is mapped elsewhere)
+pub fn main_ram_size() -> u32 {
+    return unsafe { mmio::read(sysinfo::MAINRAMSIZE) };
+}
+
+// bytes of boot ROM
+pub fn rom_size() -> u32 {
+    return unsafe { mmio::read(sysinfo::ROMSIZE) };
+}
+
+// instructions the CPU gets per frame (0 = unlimited) - what to size per-frame work against
+pub fn cpu_budget() -> u32 {
+    return unsafe { mmio::read(sysinfo::CPUBUDGET) };
+}
@@ -0,0 +1,63 @@
+use core::fmt;
+
+use crate::{mmio, regs::uart};
+
+// the serial port. the emulator forwards TX to the host terminal (& terminal input to RX with --uart-stdin)
+pub struct Uart;
+
+impl Uart {
+    // blocks while the TX FIFO is full
+    pub fn write_byte(b: u8) {
+        unsafe {
+            while mmio::read(uart::STATUS) & uart::STATUSBIT_TXFULL != 0 {}
+            mmio::write(uart::TX, b as u32);
+        }
+    }
+
+    pub fn write_bytes(bytes: &[u8]) {
+        for b in bytes {
+            Self::write_byte(*b);
+        }
+    }
+
+    // next received byte, if any
+    pub fn read_byte() -> Option<u8> {
+        unsafe {
+            if mmio::read(uart::STATUS) & uart::STATUSBIT_RXEMPTY != 0 {
+                return None;
+            }
+
+            return Some(mmio::read(uart::RX) as u8);
+        }
+    }
+}
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        Self::write_bytes(s.as_bytes());
+        return Ok(());
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use fmt::Write;
+    let _ = Uart.write_fmt(args);
+}
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::uart::_print(format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::uart::_print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
@@ -0,0 +1,114 @@
+use clap::ValueEnum;
+
+// photosensitivity: how hard to damp sudden full-screen brightness changes in guest output
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FlashReduction {
+    /// Show guest output as-is
+    #[default]
+    Off,
+    /// Damp large full-screen flashes
+    Low,
+    /// Damp any noticeable full-screen brightness change
+    High,
+}
+
+impl FlashReduction {
+    // largest change in average brightness (0..1) allowed between presented frames
+    fn max_step(self: &Self) -> Option<f32> {
+        return match self {
+            FlashReduction::Off => None,
+            FlashReduction::Low => Some(0.2),
+            FlashReduction::High => Some(0.08),
+        };
+    }
+}
+
+// how on-screen messages are drawn over the guest's frame
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OsdTheme {
+    /// Light text on a translucent dark box
+    #[default]
+    Default,
+    /// White text on an opaque black box with a white border
+    HighContrast,
+}
+
+pub struct OsdColors {
+    pub text: [u8; 3],
+    pub background: [u8; 3],
+    // how much of the guest's frame the background covers (0..1)
+    pub background_alpha: f32,
+    pub border: Option<[u8; 3]>,
+}
+
+impl OsdTheme {
+    pub fn colors(self: &Self) -> OsdColors {
+        return match self {
+            OsdTheme::Default => OsdColors { text: [224, 224, 224], background: [0, 0, 0], background_alpha: 0.6, border: None },
+            OsdTheme::HighContrast => OsdColors { text: [255, 255, 255], background: [0, 0, 0], background_alpha: 1.0, border: Some([255, 255, 255]) },
+        };
+    }
+}
+
+// limits how fast the average brightness of presented frames can change. when a frame jumps too far from the last one shown,
+// it's blended with the last one just enough to keep the change under the limit, so a flash ramps in over a few frames instead
+pub struct FlashFilter {
+    level: FlashReduction,
+    prev: Vec<u8>,
+    prev_luma: f32,
+}
+
+impl FlashFilter {
+    pub fn new(level: FlashReduction) -> Self {
+        Self {
+            level,
+            prev: Vec::new(),
+            prev_luma: 0.0,
+        }
+    }
+
+    // whether apply does anything - presenters that can skip the readback otherwise check this
+    pub fn is_active(self: &Self) -> bool {
+        return self.level.max_step().is_some();
+    }
+
+    // filter a tightly packed RGBA8 frame in place
+    pub fn apply(self: &mut Self, rgba: &mut [u8]) {
+        let Some(max_step) = self.level.max_step() else {
+            return;
+        };
+
+        let luma = average_luma(rgba);
+
+        // first frame, or the resolution changed - nothing to compare against
+        if self.prev.len() == rgba.len() {
+            let delta = (luma - self.prev_luma).abs();
+
+            if delta > max_step {
+                // keep t of the new frame, so the brightness moves by about max_step
+                let t = max_step / delta;
+
+                for (px, prev) in rgba.iter_mut().zip(&self.prev) {
+                    *px = (*prev as f32 + (*px as f32 - *prev as f32) * t).round() as u8;
+                }
+            }
+        }
+
+        self.prev.clear();
+        self.prev.extend_from_slice(rgba);
+        self.prev_luma = average_luma(rgba);
+    }
+}
+
+fn average_luma(rgba: &[u8]) -> f32 {
+    let count = rgba.len() / 4;
+    if count == 0 {
+        return 0.0;
+    }
+
+    let sum: f64 = rgba.chunks_exact(4)
+        .map(|px| 0.2126 * px[0] as f64 + 0.7152 * px[1] as f64 + 0.0722 * px[2] as f64)
+        .sum();
+
+    return (sum / count as f64 / 255.0) as f32;
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use nyxbox_core::vdp::VdpStats;
+
+// frame time percentiles a benchmark reports
+pub const BENCH_PERCENTILES: [f64;4] = [50.0, 90.0, 99.0, 100.0];
+
+// what `nyxbox run --bench` measures: the guest is run in lockstep with the frontend as fast as the host allows for a
+// fixed (host) time, & each frame's host time & VDP work is kept so the report can give percentiles rather than just an
+// average - a scheduler change that evens out frame times shows up even if the total doesn't move
+pub struct Bench {
+    duration: Duration,
+    start: Instant,
+    last_frame: Instant,
+    start_instructions: u64,
+    frame_times: Vec<f64>,
+    // VDP work, summed over every frame: commands, primitives, vertices, DMA bytes, & GPU dispatches
+    vdp: [u64;5],
+}
+
+impl Bench {
+    pub fn new(secs: f64, instructions: u64) -> Self {
+        let now = Instant::now();
+
+        Self {
+            duration: Duration::from_secs_f64(secs.max(0.0)),
+            start: now,
+            last_frame: now,
+            start_instructions: instructions,
+            frame_times: Vec::new(),
+            vdp: [0;5],
+        }
+    }
+
+    // once per emulated frame, when its GPU work is done
+    pub fn frame(self: &mut Self, stats: VdpStats) {
+        let now = Instant::now();
+        self.frame_times.push((now - self.last_frame).as_secs_f64() * 1000.0);
+        self.last_frame = now;
+
+        for (total, count) in self.vdp.iter_mut().zip([stats.commands, stats.primitives, stats.vertices, stats.dma_bytes, stats.dispatches]) {
+            *total += count as u64;
+        }
+    }
+
+    pub fn done(self: &Self) -> bool {
+        return self.start.elapsed() >= self.duration;
+    }
+
+    pub fn report(self: &Self, instructions: u64) -> Value {
+        let secs = self.start.elapsed().as_secs_f64();
+        let frames = self.frame_times.len().max(1) as f64;
+        let executed = instructions.saturating_sub(self.start_instructions);
+
+        let mut sorted = self.frame_times.clone();
+        sorted.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+
+            // nearest rank
+            let rank = ((p / 100.0 * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+            return sorted[rank - 1];
+        };
+
+        let mut frame_ms = serde_json::Map::new();
+        frame_ms.insert("mean".to_string(), json!(self.frame_times.iter().sum::<f64>() / frames));
+
+        for p in BENCH_PERCENTILES {
+            let name = if p == 100.0 { "max".to_string() } else { format!("p{}", p) };
+            frame_ms.insert(name, json!(percentile(p)));
+        }
+
+        let [commands, primitives, vertices, dma_bytes, dispatches] = self.vdp;
+
+        return json!({
+            "seconds": secs,
+            "frames": self.frame_times.len(),
+            "fps": self.frame_times.len() as f64 / secs,
+            "instructions": executed,
+            "mips": executed as f64 / secs / 1_000_000.0,
+            "vdp_per_frame": {
+                "commands": commands as f64 / frames,
+                "primitives": primitives as f64 / frames,
+                "vertices": vertices as f64 / frames,
+                "dma_bytes": dma_bytes as f64 / frames,
+                "dispatches": dispatches as f64 / frames,
+            },
+            "gpu_dispatches": dispatches,
+            "frame_ms": frame_ms,
+        });
+    }
+}
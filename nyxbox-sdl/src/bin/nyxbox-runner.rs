@@ -0,0 +1,67 @@
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+use clap::Parser;
+
+// cargo target runner for guest crates (see nyxbox-guest/.cargo/config.toml): boots the ELF cargo just built with
+// `nyxbox run` with the terminal wired to the UART, & exits with whatever code the guest reported through the debug port
+#[derive(Parser)]
+#[command(version, about = "Run a guest ELF in NyxBox (for use as a cargo target runner)")]
+struct Args {
+    /// Don't show a window (also enabled by setting NYXBOX_HEADLESS)
+    #[arg(long)]
+    headless: bool,
+
+    /// Guest ELF executable
+    elf: PathBuf,
+
+    /// Extra arguments passed through to `nyxbox run`
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    emu_args: Vec<String>,
+}
+
+// the emulator loads shaders & other content relative to its checkout (the workspace root, above this crate), so it has
+// to run from there
+fn nyxbox_dir() -> PathBuf {
+    return env::var_os("NYXBOX_DIR").map(PathBuf::from).unwrap_or(Path::new(env!("CARGO_MANIFEST_DIR")).join(".."));
+}
+
+fn run(args: &Args) -> Result<i32, String> {
+    // the emulator runs from its checkout, so the path has to survive the change of directory
+    let elf = fs::canonicalize(&args.elf).map_err(|e| format!("{}: {}", args.elf.display(), e))?;
+
+    let exe = env::current_exe().map_err(|e| e.to_string())?;
+    let emu = exe.parent().unwrap_or(Path::new(".")).join(format!("nyxbox{}", env::consts::EXE_SUFFIX));
+
+    let headless = args.headless || env::var_os("NYXBOX_HEADLESS").is_some_and(|v| !v.is_empty() && v != "0");
+
+    let mut cmd = Command::new(&emu);
+    cmd.current_dir(nyxbox_dir())
+        .arg("run")
+        .arg(&elf)
+        .arg("--uart-stdin");
+
+    if headless {
+        cmd.arg("--headless");
+    }
+
+    cmd.args(&args.emu_args);
+
+    let status = cmd.status().map_err(|e| format!("failed to start {}: {}", emu.display(), e))?;
+
+    // killed by a signal
+    return Ok(status.code().unwrap_or(1));
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(code) => {
+            std::process::exit(code);
+        }
+        Err(e) => {
+            eprintln!("nyxbox-runner: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}};
+
+use clap::Subcommand;
+
+use nyxbox_core::storage::{self, fnv1a};
+
+// offline tools for memory card images (the single-file save layout), so individual saves can be backed up or shared
+#[derive(Subcommand)]
+pub enum CardCommand {
+    /// List the save slots in a memory card image
+    List { card: PathBuf },
+    /// Copy one save slot out of a memory card image
+    Export { card: PathBuf, slot: String, out: PathBuf },
+    /// Copy a file into a memory card image as a save slot, creating the image if needed
+    Import {
+        card: PathBuf,
+        slot: String,
+        file: PathBuf,
+
+        /// Replace the slot if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn fail(msg: String) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+fn load_card(path: &Path, create: bool) -> BTreeMap<String, Vec<u8>> {
+    return match storage::read_card(path) {
+        Ok(entries) => entries,
+        Err(e) if create && e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => fail(format!("failed to load {}: {}", path.display(), e)),
+    };
+}
+
+pub fn card_cmd(cmd: &CardCommand) {
+    match cmd {
+        CardCommand::List { card } => {
+            let entries = load_card(card, false);
+
+            if entries.is_empty() {
+                println!("{} is empty", card.display());
+                return;
+            }
+
+            println!("{:<32} {:>10} {:>8}", "slot", "size", "hash");
+            for (name, data) in &entries {
+                println!("{:<32} {:>10} {:08x}", name, data.len(), fnv1a(data));
+            }
+        }
+        CardCommand::Export { card, slot, out } => {
+            let entries = load_card(card, false);
+
+            let Some(data) = entries.get(slot) else {
+                fail(format!("{} has no slot '{}'", card.display(), slot));
+            };
+
+            fs::write(out, data).unwrap_or_else(|e| fail(format!("failed to write {}: {}", out.display(), e)));
+        }
+        CardCommand::Import { card, slot, file, force } => {
+            let mut entries = load_card(card, true);
+
+            if entries.contains_key(slot) && !force {
+                fail(format!("{} already has a slot '{}' (use --force to replace it)", card.display(), slot));
+            }
+
+            let data = fs::read(file).unwrap_or_else(|e| fail(format!("failed to read {}: {}", file.display(), e)));
+            entries.insert(slot.clone(), data);
+
+            storage::write_card(card, &entries).unwrap_or_else(|e| fail(format!("failed to write {}: {}", card.display(), e)));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{env, ffi::OsString, fs, path::{Path, PathBuf}};
+
+use toml::Value;
+
+// per-user defaults for `nyxbox run`: the file --config names, or else nyxbox.toml in the working directory, or else in the
+// user's config directory ($XDG_CONFIG_HOME/nyxbox, ~/.config/nyxbox, or %APPDATA%\nyxbox). every setting is the run flag
+// of the same name, so the file's settings go in ahead of the command line's flags & anything given there wins. e.g.
+//
+//   [video]
+//   scale = 2
+//   sync = "adaptive"
+//
+//   [paths]
+//   save_dir = "saves"      # relative paths are relative to the file
+//
+// a boolean flag set here can't be turned back off from the command line - leave it out of the file instead
+pub const CONFIG_FILE: &str = "nyxbox.toml";
+
+// a file to use instead of looking for one, or empty for none (the test & perf runners set this, so a user's settings
+// don't change their results)
+pub const CONFIG_ENV: &str = "NYXBOX_CONFIG";
+
+// what can be set, by section. per-run things (the ROM, movies, breakpoints & the like) are left out on purpose
+const SETTINGS: &[(&str, &[&str])] = &[
+    ("video", &["scale", "fullscreen", "present", "present_interval", "sync", "background", "flash_reduction", "ui_scale", "osd_theme", "frame_skip", "max_catchup", "render_debug"]),
+    ("input", &["hotkeys", "turbo", "turbo_rate"]),
+    ("paths", &["save_dir", "save_layout", "capture_dir"]),
+    ("system", &["lang", "log", "memory_map", "expansion_ram", "boot_fill", "hw_model", "cpu_model", "guest_faults", "unmapped"]),
+];
+
+// settings that name a file or directory
+const PATH_SETTINGS: &[&str] = &["hotkeys", "save_dir", "capture_dir", "memory_map"];
+
+// the file to use: one named by --config, then by the environment, then the first nyxbox.toml found
+pub fn find(flag: Option<PathBuf>) -> Option<PathBuf> {
+    if flag.is_some() {
+        return flag;
+    }
+
+    if let Some(path) = env::var_os(CONFIG_ENV) {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+
+    let local = PathBuf::from(CONFIG_FILE);
+
+    if local.is_file() {
+        return Some(local);
+    }
+
+    let path = user_dir()?.join(CONFIG_FILE);
+    return path.is_file().then_some(path);
+}
+
+// `--config PATH` (or `--config=PATH`) among the run command's arguments. it has to be picked out before clap parses them,
+// since the file's settings go in ahead of them
+pub fn flag(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().take_while(|arg| *arg != "--");
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    return None;
+}
+
+// where per-user files go: $XDG_CONFIG_HOME/nyxbox, ~/.config/nyxbox, or %APPDATA%\nyxbox. it may not exist yet
+pub fn user_dir() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+    return Some(dir.join("nyxbox"));
+}
+
+// the file's settings as `nyxbox run` flags
+pub fn run_args(path: &Path) -> Result<Vec<OsString>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut args = Vec::new();
+
+    let err = |e: String| format!("{}: {}", path.display(), e);
+
+    let doc: toml::Table = text.parse().map_err(|e: toml::de::Error| err(e.to_string()))?;
+
+    for (section, keys) in &doc {
+        let sections = || SETTINGS.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join("], [");
+
+        let Some(keys) = keys.as_table() else {
+            return Err(err(format!("settings go in one of [{}], not before the first section ({})", sections(), section)));
+        };
+
+        let Some((_, settings)) = SETTINGS.iter().find(|(name, _)| name == section) else {
+            return Err(err(format!("settings go in one of [{}], not [{}]", sections(), section)));
+        };
+
+        for (key, value) in keys {
+            if !settings.contains(&key.as_str()) {
+                return Err(err(format!("{} isn't a [{}] setting", key, section)));
+            }
+
+            let flag = format!("--{}", key.replace('_', "-"));
+
+            match value {
+                Value::Boolean(true) => args.push(OsString::from(flag)),
+                Value::Boolean(false) => {
+                }
+                Value::Integer(val) => {
+                    args.push(OsString::from(flag));
+                    args.push(OsString::from(val.to_string()));
+                }
+                Value::Float(val) => {
+                    args.push(OsString::from(flag));
+                    args.push(OsString::from(val.to_string()));
+                }
+                Value::String(val) if PATH_SETTINGS.contains(&key.as_str()) => {
+                    args.push(OsString::from(flag));
+                    args.push(dir.join(val).into_os_string());
+                }
+                Value::String(val) => {
+                    args.push(OsString::from(flag));
+                    args.push(OsString::from(val));
+                }
+                other => {
+                    return Err(err(format!("{} can't be of type {}", key, other.type_str())));
+                }
+            }
+        }
+    }
+
+    return Ok(args);
+}
@@ -0,0 +1,384 @@
+use std::{io::{self, BufRead, BufReader, Write}, net::{TcpListener, TcpStream}, sync::mpsc::{self, Receiver, Sender}, thread};
+
+use serde_json::{json, Value};
+
+use nyxbox_core::{breakpoint::{BreakSpec, Cond, WatchKind}, inspect::{parse_addr, parse_hex_pattern}, log::{self, Level, Subsystem}, mem::MemoryMap};
+
+use crate::input::parse_buttons;
+
+// JSON-RPC 2.0 error codes
+pub const ERR_PARSE: i64            = -32700;
+pub const ERR_INVALID_REQUEST: i64  = -32600;
+pub const ERR_METHOD_NOT_FOUND: i64 = -32601;
+pub const ERR_INVALID_PARAMS: i64   = -32602;
+pub const ERR_EXEC: i64             = -32000;
+
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    Step,
+    // to the next source line, going over calls if `over` - an instruction, without line info
+    StepLine { over: bool },
+    FrameStep,
+    Status,
+    // the guest memory peek & poke can reach
+    Regions,
+    Peek { addr: u32, len: usize },
+    Poke { addr: u32, data: Vec<u8> },
+    Input { data: Vec<u8> },
+    // gamepad buttons to hold down & let go of (masks of gamepad::BUTTON_*)
+    Pad { press: u32, release: u32 },
+    Reset,
+    SaveState { path: Option<String> },
+    LoadState { path: String },
+    Screenshot { path: String },
+    Registers,
+    Exceptions,
+    DumpTextures,
+    IsolateDraws { mode: String, index: u32 },
+    CaptureVu { list: u32 },
+    AddBreakpoint { spec: BreakSpec },
+    AddWatchpoint { addr: u32, len: u32, kind: WatchKind },
+    RemoveBreakpoint { id: u32 },
+    Breakpoints,
+    LogLevels { levels: Vec<(Option<Subsystem>, Level)> },
+}
+
+pub struct ControlRequest {
+    pub cmd: ControlCommand,
+    reply: Sender<Result<Value, String>>,
+}
+
+// collects requests from remote frontends (JSON-RPC, DAP) & hands them to the main loop, which owns the machine. lengths
+// in requests are checked against the machine's memory map before they get that far
+pub struct ControlServer {
+    tx: Sender<ControlRequest>,
+    rx: Receiver<ControlRequest>,
+    map: MemoryMap,
+}
+
+impl ControlRequest {
+    pub fn new(cmd: ControlCommand) -> (Self, Receiver<Result<Value, String>>) {
+        let (reply, reply_rx) = mpsc::channel();
+        return (Self { cmd, reply }, reply_rx);
+    }
+
+    pub fn reply(self: Self, result: Result<Value, String>) {
+        // client may have hung up in the meantime, that's fine
+        let _ = self.reply.send(result);
+    }
+}
+
+impl ControlServer {
+    pub fn new(map: MemoryMap) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        return Self {
+            tx,
+            rx,
+            map,
+        };
+    }
+
+    pub fn sender(self: &Self) -> Sender<ControlRequest> {
+        return self.tx.clone();
+    }
+
+    // accepts newline-delimited JSON-RPC requests on a local socket
+    pub fn listen_jsonrpc(self: &Self, addr: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let tx = self.sender();
+        let map = self.map;
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let _ = Self::handle_client(stream, tx, &map);
+                });
+            }
+        });
+
+        return Ok(());
+    }
+
+    pub fn poll(self: &Self) -> Option<ControlRequest> {
+        return self.rx.try_recv().ok();
+    }
+
+    fn handle_client(stream: TcpStream, tx: Sender<ControlRequest>, map: &MemoryMap) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let reader = BufReader::new(stream);
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(req) => Self::dispatch(&req, &tx, map),
+                Err(e) => Some(error_response(Value::Null, ERR_PARSE, &e.to_string())),
+            };
+
+            // notifications (no id) don't get a response
+            if let Some(response) = response {
+                writeln!(writer, "{}", response)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn dispatch(req: &Value, tx: &Sender<ControlRequest>, map: &MemoryMap) -> Option<Value> {
+        let id = req.get("id").cloned();
+        let reply_id = id.clone().unwrap_or(Value::Null);
+
+        if req.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Some(error_response(reply_id, ERR_INVALID_REQUEST, "expected jsonrpc 2.0 request"));
+        }
+
+        let Some(method) = req.get("method").and_then(Value::as_str) else {
+            return Some(error_response(reply_id, ERR_INVALID_REQUEST, "missing method"));
+        };
+
+        let params = req.get("params").cloned().unwrap_or(Value::Null);
+
+        let cmd = match parse_command(method, &params, map) {
+            Ok(v) => v,
+            Err((code, msg)) => return Some(error_response(reply_id, code, &msg)),
+        };
+
+        let (ctl_req, reply_rx) = ControlRequest::new(cmd);
+        if tx.send(ctl_req).is_err() {
+            return Some(error_response(reply_id, ERR_EXEC, "emulator is shutting down"));
+        }
+
+        let result = reply_rx.recv().unwrap_or(Err("emulator is shutting down".to_string()));
+
+        if id.is_none() {
+            return None;
+        }
+
+        return Some(match result {
+            Ok(v) => json!({ "jsonrpc": "2.0", "id": reply_id, "result": v }),
+            Err(e) => error_response(reply_id, ERR_EXEC, &e),
+        });
+    }
+}
+
+// the other end of listen_jsonrpc, for tools that drive a running instance - one request at a time, waiting for each reply
+pub struct ControlClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl ControlClient {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        let writer = TcpStream::connect(addr)?;
+        let reader = BufReader::new(writer.try_clone()?);
+
+        return Ok(Self {
+            reader,
+            writer,
+            next_id: 1,
+        });
+    }
+
+    pub fn call(self: &mut Self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let req = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.writer, "{}", req).map_err(|e| format!("failed to send '{}': {}", method, e))?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(|e| format!("failed to read '{}' reply: {}", method, e))? == 0 {
+            return Err(format!("connection closed before '{}' replied", method));
+        }
+
+        let mut reply = serde_json::from_str::<Value>(&line).map_err(|e| format!("bad '{}' reply: {}", method, e))?;
+
+        if let Some(err) = reply.get("error") {
+            return Err(format!("'{}' failed: {}", method, err.get("message").and_then(Value::as_str).unwrap_or("unknown error")));
+        }
+
+        return Ok(reply.get_mut("result").map(Value::take).unwrap_or(Value::Null));
+    }
+}
+
+fn error_response(id: Value, code: i64, msg: &str) -> Value {
+    return json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": msg } });
+}
+
+fn param_addr(params: &Value, name: &str) -> Result<u32, (i64, String)> {
+    return match params.get(name) {
+        Some(Value::Number(n)) => n.as_u64().filter(|v| *v <= u32::MAX as u64).map(|v| v as u32)
+            .ok_or((ERR_INVALID_PARAMS, format!("'{}' must be a 32-bit address", name))),
+        Some(Value::String(s)) => parse_addr(s).map_err(|e| (ERR_INVALID_PARAMS, e)),
+        _ => Err((ERR_INVALID_PARAMS, format!("missing '{}'", name))),
+    };
+}
+
+// a length in bytes, which is no use past the largest memory region & would just make the main loop allocate that much
+fn param_len(params: &Value, name: &str, default: u64, map: &MemoryMap) -> Result<usize, (i64, String)> {
+    let len = params.get(name).and_then(Value::as_u64).unwrap_or(default);
+    let max = map.largest_region();
+
+    if len > max as u64 {
+        return Err((ERR_INVALID_PARAMS, format!("'{}' can be at most {} bytes", name, max)));
+    }
+
+    return Ok(len as usize);
+}
+
+fn param_str<'a>(params: &'a Value, name: &str) -> Result<&'a str, (i64, String)> {
+    return params.get(name).and_then(Value::as_str).ok_or((ERR_INVALID_PARAMS, format!("missing '{}'", name)));
+}
+
+fn parse_command(method: &str, params: &Value, map: &MemoryMap) -> Result<ControlCommand, (i64, String)> {
+    return match method {
+        "pause" => Ok(ControlCommand::Pause),
+        "resume" => Ok(ControlCommand::Resume),
+        "step" => Ok(ControlCommand::Step),
+        "step_line" => Ok(ControlCommand::StepLine { over: params.get("over").and_then(Value::as_bool).unwrap_or(false) }),
+        "frame_step" => Ok(ControlCommand::FrameStep),
+        "status" => Ok(ControlCommand::Status),
+        "regions" => Ok(ControlCommand::Regions),
+        "peek" => {
+            let addr = param_addr(params, "addr")?;
+            let len = param_len(params, "len", 4, map)?;
+            Ok(ControlCommand::Peek { addr, len })
+        }
+        "poke" => {
+            let addr = param_addr(params, "addr")?;
+            let data = parse_hex_pattern(param_str(params, "data")?).map_err(|e| (ERR_INVALID_PARAMS, e))?;
+            Ok(ControlCommand::Poke { addr, data })
+        }
+        "input" => {
+            let data = if let Some(text) = params.get("text").and_then(Value::as_str) {
+                text.as_bytes().to_vec()
+            }
+            else {
+                parse_hex_pattern(param_str(params, "bytes")?).map_err(|e| (ERR_INVALID_PARAMS, e))?
+            };
+            Ok(ControlCommand::Input { data })
+        }
+        "pad" => {
+            // comma separated button names, e.g. { "press": "a,start" } - either may be left out
+            let press = parse_buttons(params.get("press").and_then(Value::as_str).unwrap_or("")).map_err(|e| (ERR_INVALID_PARAMS, e))?;
+            let release = parse_buttons(params.get("release").and_then(Value::as_str).unwrap_or("")).map_err(|e| (ERR_INVALID_PARAMS, e))?;
+            Ok(ControlCommand::Pad { press, release })
+        }
+        "reset" => Ok(ControlCommand::Reset),
+        // with no path, goes in the capture directory like the save state hotkey's
+        "save_state" => Ok(ControlCommand::SaveState { path: params.get("path").and_then(Value::as_str).map(str::to_string) }),
+        "load_state" => Ok(ControlCommand::LoadState { path: param_str(params, "path")?.to_string() }),
+        "screenshot" => Ok(ControlCommand::Screenshot { path: param_str(params, "path")?.to_string() }),
+        "registers" => Ok(ControlCommand::Registers),
+        "exceptions" => Ok(ControlCommand::Exceptions),
+        "dump_textures" => Ok(ControlCommand::DumpTextures),
+        "capture_vu" => Ok(ControlCommand::CaptureVu { list: params.get("list").and_then(Value::as_u64).unwrap_or(0) as u32 }),
+        "isolate_draws" => {
+            let mode = param_str(params, "mode")?.to_string();
+            let index = params.get("index").and_then(Value::as_u64).unwrap_or(0) as u32;
+            Ok(ControlCommand::IsolateDraws { mode, index })
+        }
+        "add_breakpoint" => {
+            let cond = match params.get("cond").and_then(Value::as_str) {
+                Some(cond) => Some((cond.to_string(), Cond::parse(cond).map_err(|e| (ERR_INVALID_PARAMS, e))?)),
+                None => None,
+            };
+
+            Ok(ControlCommand::AddBreakpoint { spec: BreakSpec {
+                addr: param_addr(params, "addr")?,
+                ignore: params.get("after").and_then(Value::as_u64).unwrap_or(0) as u32,
+                temporary: params.get("once").and_then(Value::as_bool).unwrap_or(false),
+                cond,
+            } })
+        }
+        "add_watchpoint" => {
+            let addr = param_addr(params, "addr")?;
+            let len = param_len(params, "len", 4, map)? as u32;
+            let kind = WatchKind::parse(params.get("kind").and_then(Value::as_str).unwrap_or("rw")).map_err(|e| (ERR_INVALID_PARAMS, e))?;
+            Ok(ControlCommand::AddWatchpoint { addr, len: len.max(1), kind })
+        }
+        "remove_breakpoint" => {
+            let id = params.get("id").and_then(Value::as_u64).ok_or((ERR_INVALID_PARAMS, "missing 'id'".to_string()))? as u32;
+            Ok(ControlCommand::RemoveBreakpoint { id })
+        }
+        "breakpoints" => Ok(ControlCommand::Breakpoints),
+        "log_levels" => {
+            // with no levels given, just reports the current ones
+            let spec = params.get("levels").and_then(Value::as_str).unwrap_or("");
+            Ok(ControlCommand::LogLevels { levels: log::parse_levels(spec).map_err(|e| (ERR_INVALID_PARAMS, e))? })
+        }
+        _ => Err((ERR_METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+    };
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    return data.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use nyxbox_core::gamepad::{BUTTON_A, BUTTON_START};
+
+    use super::*;
+
+    fn parse(method: &str, params: Value) -> Result<ControlCommand, (i64, String)> {
+        return parse_command(method, &params, &MemoryMap::default());
+    }
+
+    #[test]
+    fn pad() {
+        let Ok(ControlCommand::Pad { press, release }) = parse("pad", json!({ "press": "a, Start" })) else {
+            panic!("pad didn't parse");
+        };
+        assert_eq!((press, release), (BUTTON_A | BUTTON_START, 0));
+
+        let Ok(ControlCommand::Pad { press, release }) = parse("pad", json!({ "release": "start" })) else {
+            panic!("pad didn't parse");
+        };
+        assert_eq!((press, release), (0, BUTTON_START));
+
+        assert!(matches!(parse("pad", json!({ "press": "a,turbo" })), Err((ERR_INVALID_PARAMS, _))));
+    }
+
+    #[test]
+    fn input() {
+        let Ok(ControlCommand::Input { data }) = parse("input", json!({ "text": "hi" })) else {
+            panic!("input didn't parse");
+        };
+        assert_eq!(data, b"hi");
+
+        let Ok(ControlCommand::Input { data }) = parse("input", json!({ "bytes": "0d0a" })) else {
+            panic!("input didn't parse");
+        };
+        assert_eq!(data, [0x0D, 0x0A]);
+
+        assert!(matches!(parse("input", json!({})), Err((ERR_INVALID_PARAMS, _))));
+    }
+
+    #[test]
+    fn breakpoints() {
+        let Ok(ControlCommand::AddBreakpoint { spec }) = parse("add_breakpoint", json!({ "addr": "0x1000", "after": 2, "once": true })) else {
+            panic!("add_breakpoint didn't parse");
+        };
+        assert_eq!((spec.addr, spec.ignore, spec.temporary), (0x1000, 2, true));
+
+        assert!(matches!(parse("add_breakpoint", json!({})), Err((ERR_INVALID_PARAMS, _))));
+        assert!(matches!(parse("remove_breakpoint", json!({ "id": 3 })), Ok(ControlCommand::RemoveBreakpoint { id: 3 })));
+    }
+
+    #[test]
+    fn unknown_method() {
+        assert!(matches!(parse("warp", Value::Null), Err((ERR_METHOD_NOT_FOUND, _))));
+    }
+}
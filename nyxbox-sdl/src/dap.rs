@@ -0,0 +1,713 @@
+use std::{collections::HashMap, env, fs, io::{self, BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, path::Path, process::{self, Child, Stdio}, sync::{mpsc::Sender, Arc, Mutex, Weak}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use serde_json::{json, Value};
+
+use nyxbox_core::{breakpoint::{BreakSpec, Cond, DebugStop}, elf, lines::Lines, locals::{Local, Locals}, machine::DEBUG_REGS, symbols::Symbols};
+
+use crate::{control::{ControlCommand, ControlRequest}, events::{EventSubscriber, MachineEvent}};
+
+// we only model the one CPU as a single debuggee thread
+const THREAD_ID: i64 = 1;
+const REGISTERS_REF: i64 = 1;
+const LOCALS_REF: i64 = 2;
+
+// the most a client can send in one message, or read from memory in one request - plenty for anything a debugger does,
+// & it keeps a bad Content-Length or count from being taken as an allocation size
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+type Clients = Arc<Mutex<Vec<Weak<Mutex<DapWriter>>>>>;
+
+// minimal Debug Adapter Protocol server - lets VS Code (or any DAP client) attach via a "debugServer" port
+// requests are translated into control commands & serviced by the main loop just like JSON-RPC requests. the stops the
+// clients need to hear about come back through the returned subscriber, which goes on the event bus. frames, breakpoints by
+// line, & variables are looked up in the running System's debug info
+pub fn listen(addr: &str, tx: Sender<ControlRequest>, symbols: Symbols, lines: Lines, locals: Locals) -> io::Result<DapStops> {
+    let listener = TcpListener::bind(addr)?;
+    let clients = Clients::default();
+    let sessions = clients.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+
+            let tx = tx.clone();
+            let sessions = sessions.clone();
+            let (symbols, lines, locals) = (symbols.clone(), lines.clone(), locals.clone());
+
+            thread::spawn(move || {
+                let Ok(mut session) = DapSession::new(stream, tx, &sessions, symbols, lines, locals) else {
+                    return;
+                };
+
+                let _ = session.run();
+
+                // breakpoints a client set shouldn't outlive it, however it went away
+                session.clear_breakpoints();
+            });
+        }
+    });
+
+    return Ok(DapStops { clients });
+}
+
+// tells every attached client when the CPU stops by itself - at a breakpoint or watchpoint, or the end of a step. steps
+// run on the CPU thread, so a client's step request is answered straight away & its stop comes through here later
+pub struct DapStops {
+    clients: Clients,
+}
+
+impl EventSubscriber for DapStops {
+    fn on_event(self: &mut Self, _frame: u64, ev: &MachineEvent) {
+        let MachineEvent::DebugStop { stop, desc } = ev else {
+            return;
+        };
+
+        let reason = match stop {
+            DebugStop::Breakpoint { .. } => "breakpoint",
+            DebugStop::Watchpoint { .. } => "data breakpoint",
+            DebugStop::Step { .. } | DebugStop::FrameStep { .. } => "step",
+        };
+
+        let body = json!({ "reason": reason, "description": desc, "threadId": THREAD_ID, "allThreadsStopped": true });
+
+        // clients that have gone are dropped on the way
+        self.clients.lock().unwrap().retain(|client| match client.upgrade() {
+            Some(writer) => writer.lock().unwrap().stopped(body.clone()).is_ok(),
+            None => false,
+        });
+    }
+}
+
+// the sending half of a session, shared with DapStops - events & responses have to take turns, & share the sequence
+struct DapWriter {
+    stream: Box<dyn Write + Send>,
+    seq: i64,
+    // stops that came in while a request was being handled, which wait for its response - a step's stop mustn't get
+    // to the client ahead of the step's own response
+    held: Option<Vec<Value>>,
+}
+
+impl DapWriter {
+    fn stopped(self: &mut Self, body: Value) -> io::Result<()> {
+        if let Some(held) = &mut self.held {
+            held.push(body);
+            return Ok(());
+        }
+
+        return self.event("stopped", body);
+    }
+
+    fn hold(self: &mut Self) {
+        self.held.get_or_insert_with(Vec::new);
+    }
+
+    fn release(self: &mut Self) -> io::Result<()> {
+        for body in self.held.take().unwrap_or_default() {
+            self.event("stopped", body)?;
+        }
+
+        return Ok(());
+    }
+
+    fn event(self: &mut Self, event: &str, body: Value) -> io::Result<()> {
+        return self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn send(self: &mut Self, mut msg: Value) -> io::Result<()> {
+        msg["seq"] = json!(self.seq);
+        self.seq += 1;
+
+        return write_message(&mut self.stream, &msg);
+    }
+}
+
+struct DapSession {
+    reader: BufReader<TcpStream>,
+    writer: Arc<Mutex<DapWriter>>,
+    tx: Sender<ControlRequest>,
+    // the breakpoints installed for each source file, which a setBreakpoints for the file replaces
+    breakpoints: HashMap<String, Vec<u32>>,
+    symbols: Symbols,
+    lines: Lines,
+    locals: Locals,
+}
+
+impl DapSession {
+    fn new(stream: TcpStream, tx: Sender<ControlRequest>, clients: &Clients, symbols: Symbols, lines: Lines, locals: Locals) -> io::Result<Self> {
+        let writer = Arc::new(Mutex::new(DapWriter { stream: Box::new(stream.try_clone()?), seq: 1, held: None }));
+        clients.lock().unwrap().push(Arc::downgrade(&writer));
+
+        return Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            tx,
+            breakpoints: HashMap::new(),
+            symbols,
+            lines,
+            locals,
+        });
+    }
+
+    fn run(self: &mut Self) -> io::Result<()> {
+        while let Some(msg) = read_message(&mut self.reader)? {
+            if msg.get("type").and_then(Value::as_str) != Some("request") {
+                continue;
+            }
+
+            let command = msg.get("command").and_then(Value::as_str).unwrap_or("").to_string();
+            let args = msg.get("arguments").cloned().unwrap_or(Value::Null);
+            let req_seq = msg.get("seq").and_then(Value::as_i64).unwrap_or(0);
+
+            self.writer.lock().unwrap().hold();
+
+            let result = self.handle(&command, &args);
+            let ok = result.is_ok();
+
+            match result {
+                Ok(body) => self.send(json!({
+                    "type": "response", "request_seq": req_seq, "success": true, "command": command, "body": body
+                }))?,
+                Err(e) => self.send(json!({
+                    "type": "response", "request_seq": req_seq, "success": false, "command": command, "message": e
+                }))?,
+            }
+
+            // some requests have follow-up events which must come after the response - & only once they've worked, a pause
+            // that timed out hasn't stopped anything
+            match command.as_str() {
+                "initialize" if ok => {
+                    self.event("initialized", json!({}))?;
+                }
+                "pause" if ok => {
+                    self.event("stopped", json!({ "reason": "pause", "threadId": THREAD_ID, "allThreadsStopped": true }))?;
+                }
+                "disconnect" => {
+                    return Ok(());
+                }
+                _ => {
+                }
+            }
+
+            self.writer.lock().unwrap().release()?;
+        }
+
+        return Ok(());
+    }
+
+    fn handle(self: &mut Self, command: &str, args: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => {
+                return Ok(capabilities());
+            }
+            "launch" => {
+                // the machine is already running by the time a client can connect here - launching is `nyxbox dap`'s job
+                return Err("this instance is already running - attach to it, or launch through `nyxbox dap`".to_string());
+            }
+            "attach" | "configurationDone" => {
+                return Ok(Value::Null);
+            }
+            "setBreakpoints" => {
+                let path = args.get("source").and_then(|source| source.get("path")).and_then(Value::as_str).ok_or("missing source path")?.to_string();
+
+                // each request has every breakpoint the file is to have, so the ones it had before go
+                for id in self.breakpoints.remove(&path).unwrap_or_default() {
+                    let _ = self.request(ControlCommand::RemoveBreakpoint { id });
+                }
+
+                let mut ids = Vec::new();
+                let mut bps = Vec::new();
+
+                for bp in args.get("breakpoints").and_then(Value::as_array).map_or(&[][..], Vec::as_slice) {
+                    let line = bp.get("line").and_then(Value::as_u64).unwrap_or(0) as u32;
+
+                    bps.push(match self.add_line_breakpoint(&path, line, bp.get("condition").and_then(Value::as_str)) {
+                        Ok((line, bp_ids)) => {
+                            ids.extend(&bp_ids);
+                            json!({ "verified": true, "id": bp_ids[0], "line": line })
+                        }
+                        Err(e) => json!({ "verified": false, "line": line, "message": e }),
+                    });
+                }
+
+                self.breakpoints.insert(path, ids);
+
+                return Ok(json!({ "breakpoints": bps }));
+            }
+            "threads" => {
+                return Ok(json!({ "threads": [ { "id": THREAD_ID, "name": "CPU" } ] }));
+            }
+            "stackTrace" => {
+                let pc = self.registers()?.get("pc").and_then(Value::as_u64).unwrap_or(0) as u32;
+
+                let mut frame = json!({
+                    "id": 0,
+                    "name": self.symbols.name(pc).unwrap_or_else(|| format!("{:08x}", pc)),
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": format!("0x{:08x}", pc),
+                });
+
+                if let Some((path, line)) = self.lines.lookup(pc) {
+                    let name = Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+                    frame["source"] = json!({ "name": name, "path": path });
+                    frame["line"] = json!(line);
+                }
+
+                return Ok(json!({ "stackFrames": [ frame ], "totalFrames": 1 }));
+            }
+            "scopes" => {
+                let pc = self.registers()?.get("pc").and_then(Value::as_u64).unwrap_or(0) as u32;
+                let mut scopes = vec![json!({ "name": "Registers", "presentationHint": "registers", "variablesReference": REGISTERS_REF, "expensive": false })];
+
+                // only inside a function the debug info has variables for
+                if self.locals.covers(pc) {
+                    scopes.insert(0, json!({ "name": "Locals", "presentationHint": "locals", "variablesReference": LOCALS_REF, "expensive": false }));
+                }
+
+                return Ok(json!({ "scopes": scopes }));
+            }
+            "variables" => {
+                let vars: Vec<Value> = match args.get("variablesReference").and_then(Value::as_i64) {
+                    Some(REGISTERS_REF) => {
+                        let regs = self.registers()?;
+                        regs.as_object().map(|m| m.iter().map(|(name, val)| json!({
+                            "name": name,
+                            "value": format!("0x{:08x}", val.as_u64().unwrap_or(0)),
+                            "variablesReference": 0,
+                        })).collect()).unwrap_or_default()
+                    }
+                    Some(LOCALS_REF) => {
+                        self.locals()?.unwrap_or_default().into_iter().map(|local| json!({
+                            "name": local.name,
+                            "type": local.ty,
+                            "value": local.value,
+                            "variablesReference": 0,
+                        })).collect()
+                    }
+                    _ => Vec::new(),
+                };
+
+                return Ok(json!({ "variables": vars }));
+            }
+            "readMemory" => {
+                let addr = parse_mem_ref(args)?;
+                let len = args.get("count").and_then(Value::as_u64).unwrap_or(0) as usize;
+
+                if len > MAX_MESSAGE_LEN {
+                    return Err(format!("can't read more than {} bytes at once", MAX_MESSAGE_LEN));
+                }
+
+                let res = self.request(ControlCommand::Peek { addr, len })?;
+                let hex = res.get("data").and_then(Value::as_str).unwrap_or("");
+
+                return Ok(json!({ "address": format!("0x{:08x}", addr), "data": hex_to_base64(hex) }));
+            }
+            "writeMemory" => {
+                let addr = parse_mem_ref(args)?;
+                let data = base64_to_bytes(args.get("data").and_then(Value::as_str).unwrap_or(""))?;
+                let len = data.len();
+                self.request(ControlCommand::Poke { addr, data })?;
+
+                return Ok(json!({ "bytesWritten": len }));
+            }
+            "pause" => {
+                self.request(ControlCommand::Pause)?;
+                return Ok(Value::Null);
+            }
+            "next" | "stepIn" => {
+                // by source line, or by instruction where there's no line info
+                self.request(ControlCommand::StepLine { over: command == "next" })?;
+                return Ok(Value::Null);
+            }
+            "continue" => {
+                self.request(ControlCommand::Resume)?;
+                return Ok(json!({ "allThreadsContinued": true }));
+            }
+            "disconnect" => {
+                // don't leave the machine frozen (or about to be) once the debugger goes away
+                self.clear_breakpoints();
+                self.request(ControlCommand::Resume)?;
+                return Ok(Value::Null);
+            }
+            _ => {
+                return Err(format!("unsupported request '{}'", command));
+            }
+        }
+    }
+
+    fn request(self: &Self, cmd: ControlCommand) -> Result<Value, String> {
+        let (req, reply) = ControlRequest::new(cmd);
+        self.tx.send(req).map_err(|_| "emulator is shutting down".to_string())?;
+        return reply.recv().unwrap_or(Err("emulator is shutting down".to_string()));
+    }
+
+    fn registers(self: &Self) -> Result<Value, String> {
+        return self.request(ControlCommand::Registers);
+    }
+
+    // the variables of the function the CPU is stopped in, read through peeks
+    fn locals(self: &Self) -> Result<Option<Vec<Local>>, String> {
+        let named = self.registers()?;
+        let regs: [u32;16] = std::array::from_fn(|idx| named.get(DEBUG_REGS[idx].0).and_then(Value::as_u64).unwrap_or(0) as u32);
+
+        return Ok(self.locals.locals(regs[15], &regs, |addr, len| {
+            let res = self.request(ControlCommand::Peek { addr, len }).ok()?;
+            return nyxbox_core::inspect::parse_hex_pattern(res.get("data")?.as_str()?).ok();
+        }));
+    }
+
+    // a breakpoint at each place the line's code is - the line being where it ended up, the next with code if it has none
+    fn add_line_breakpoint(self: &Self, path: &str, line: u32, cond: Option<&str>) -> Result<(u32, Vec<u32>), String> {
+        if !self.lines.is_loaded() {
+            return Err("no line info - pass the ROM's ELF, built with -g, as --symbols".to_string());
+        }
+
+        let cond = match cond.filter(|cond| !cond.is_empty()) {
+            Some(cond) => Some((cond.to_string(), Cond::parse(cond)?)),
+            None => None,
+        };
+
+        let (line, addrs) = self.lines.addresses(path, line).filter(|(_, addrs)| !addrs.is_empty()).ok_or("no code for this line".to_string())?;
+        let mut ids = Vec::new();
+
+        for addr in addrs {
+            let spec = BreakSpec { addr, ignore: 0, temporary: false, cond: cond.clone() };
+            let res = self.request(ControlCommand::AddBreakpoint { spec })?;
+            ids.push(res.get("id").and_then(Value::as_u64).ok_or("breakpoint wasn't given an id".to_string())? as u32);
+        }
+
+        return Ok((line, ids));
+    }
+
+    fn clear_breakpoints(self: &mut Self) {
+        for id in std::mem::take(&mut self.breakpoints).into_values().flatten() {
+            let _ = self.request(ControlCommand::RemoveBreakpoint { id });
+        }
+    }
+
+    fn event(self: &mut Self, event: &str, body: Value) -> io::Result<()> {
+        return self.writer.lock().unwrap().event(event, body);
+    }
+
+    fn send(self: &mut Self, msg: Value) -> io::Result<()> {
+        return self.writer.lock().unwrap().send(msg);
+    }
+}
+
+// `nyxbox dap`: a debug adapter on stdin & stdout, for clients that start one per session & launch the program through it.
+// initialize is answered here, & launch starts `nyxbox run` on the program - paused, listening for DAP on a local port -
+// & connects to it. from then on messages go straight between the two, so the session is the same as one attached to
+// the instance's --dap port
+pub fn adapter_cmd() {
+    if let Err(e) = adapter() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+// the launched instance, & the requests the adapter sent it itself - whose responses the client mustn't see. these get
+// negative sequence numbers, so they can't be mistaken for any of the client's
+struct Launched {
+    stream: TcpStream,
+    child: Child,
+    stop_on_entry: bool,
+    replies: Option<JoinHandle<()>>,
+}
+
+const ADAPTER_INITIALIZE_SEQ: i64 = -1;
+const ADAPTER_CONTINUE_SEQ: i64 = -2;
+
+// how long a launched instance gets to open its DAP port
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn adapter() -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let client = Arc::new(Mutex::new(DapWriter { stream: Box::new(io::stdout()), seq: 1, held: None }));
+
+    let mut init_args = Value::Null;
+    let mut launched: Option<Launched> = None;
+
+    while let Some(msg) = read_message(&mut input)? {
+        if msg.get("type").and_then(Value::as_str) != Some("request") {
+            continue;
+        }
+
+        let command = msg.get("command").and_then(Value::as_str).unwrap_or("");
+        let args = msg.get("arguments").cloned().unwrap_or(Value::Null);
+        let req_seq = msg.get("seq").and_then(Value::as_i64).unwrap_or(0);
+
+        let respond = |result: Result<Value, String>| {
+            let mut client = client.lock().unwrap();
+            return match result {
+                Ok(body) => client.send(json!({ "type": "response", "request_seq": req_seq, "success": true, "command": command, "body": body })),
+                Err(e) => client.send(json!({ "type": "response", "request_seq": req_seq, "success": false, "command": command, "message": e })),
+            };
+        };
+
+        let Some(instance) = &mut launched else {
+            match command {
+                "initialize" => {
+                    // passed on to the instance once there is one - its initialized event is the client's cue to go on
+                    init_args = args;
+                    respond(Ok(capabilities()))?;
+                }
+                "launch" => {
+                    match launch(&args) {
+                        Ok(mut instance) => {
+                            respond(Ok(Value::Null))?;
+                            instance.replies = Some(forward_replies(instance.stream.try_clone()?, client.clone()));
+                            forward_output(&mut instance.child, client.clone());
+
+                            write_message(&mut instance.stream, &json!({ "type": "request", "seq": ADAPTER_INITIALIZE_SEQ, "command": "initialize", "arguments": init_args }))?;
+                            launched = Some(instance);
+                        }
+                        Err(e) => {
+                            respond(Err(e))?;
+                        }
+                    }
+                }
+                "disconnect" => {
+                    respond(Ok(Value::Null))?;
+                    return Ok(());
+                }
+                _ => {
+                    respond(Err(format!("'{}' needs a launched program first", command)))?;
+                }
+            }
+
+            continue;
+        };
+
+        match command {
+            "configurationDone" => {
+                // the breakpoints are in, so the instance can start - or stay where it is, & say so
+                respond(Ok(Value::Null))?;
+
+                if instance.stop_on_entry {
+                    client.lock().unwrap().event("stopped", json!({ "reason": "entry", "threadId": THREAD_ID, "allThreadsStopped": true }))?;
+                }
+                else {
+                    write_message(&mut instance.stream, &json!({ "type": "request", "seq": ADAPTER_CONTINUE_SEQ, "command": "continue" }))?;
+                }
+            }
+            "disconnect" => {
+                // its answer gets to the client before the connection closes
+                write_message(&mut instance.stream, &msg)?;
+                if let Some(replies) = instance.replies.take() {
+                    let _ = replies.join();
+                }
+
+                // the instance was started for this session, so it goes with it unless the client wants it left running
+                if args.get("terminateDebuggee").and_then(Value::as_bool) != Some(false) {
+                    let _ = instance.child.kill();
+                    let _ = instance.child.wait();
+                }
+
+                return Ok(());
+            }
+            _ => {
+                write_message(&mut instance.stream, &msg)?;
+            }
+        }
+    }
+
+    // the client went away without a disconnect
+    if let Some(mut instance) = launched {
+        let _ = instance.child.kill();
+        let _ = instance.child.wait();
+    }
+
+    return Ok(());
+}
+
+// launch arguments: "program" (the ROM or ELF), & optionally "symbols" (defaults to the program if it's an ELF), "args"
+// (more `nyxbox run` flags), "cwd" & "stopOnEntry"
+fn launch(args: &Value) -> Result<Launched, String> {
+    let program = args.get("program").and_then(Value::as_str).ok_or("missing program to launch")?;
+    let extra: Vec<&str> = args.get("args").and_then(Value::as_array).map_or(Vec::new(), |args| args.iter().filter_map(Value::as_str).collect());
+
+    let symbols = match args.get("symbols").and_then(Value::as_str) {
+        Some(path) => Some(path),
+        None if fs::read(program).is_ok_and(|data| elf::is_elf(&data)) => Some(program),
+        None => None,
+    };
+
+    // a free port for it to listen on - closed again straight away, so there's a small chance something else takes it
+    let addr = TcpListener::bind("127.0.0.1:0").and_then(|listener| listener.local_addr()).map_err(|e| format!("no free port for the debugger: {}", e))?;
+
+    let exe = env::current_exe().map_err(|e| format!("can't find the nyxbox executable: {}", e))?;
+    let mut cmd = process::Command::new(exe);
+    cmd.arg("run").arg("--paused").arg("--dap").arg(addr.to_string());
+
+    if let Some(path) = symbols {
+        cmd.arg("--symbols").arg(path);
+    }
+
+    if let Some(cwd) = args.get("cwd").and_then(Value::as_str) {
+        cmd.current_dir(cwd);
+    }
+
+    // stdout is the client's channel, so the instance's output goes to it as output events instead
+    cmd.args(extra).arg(program).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().map_err(|e| format!("failed to start nyxbox: {}", e))?;
+    let start = Instant::now();
+
+    loop {
+        if let Ok(stream) = TcpStream::connect(addr) {
+            return Ok(Launched { stream, child, stop_on_entry: args.get("stopOnEntry").and_then(Value::as_bool).unwrap_or(false), replies: None });
+        }
+
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("nyxbox exited ({}) before the debugger could connect", status));
+        }
+
+        if start.elapsed() > LAUNCH_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("nyxbox didn't open its debugger port within {} seconds", LAUNCH_TIMEOUT.as_secs()));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// everything the instance sends goes on to the client, bar the replies to the adapter's own requests. when it closes
+// the connection the session is over
+fn forward_replies(stream: TcpStream, client: Arc<Mutex<DapWriter>>) -> JoinHandle<()> {
+    return thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+
+        while let Ok(Some(msg)) = read_message(&mut reader) {
+            if msg.get("type").and_then(Value::as_str) == Some("response") && msg.get("request_seq").and_then(Value::as_i64).is_some_and(|seq| seq < 0) {
+                continue;
+            }
+
+            if client.lock().unwrap().send(msg).is_err() {
+                return;
+            }
+        }
+
+        let _ = client.lock().unwrap().event("terminated", json!({}));
+    });
+}
+
+fn forward_output(child: &mut Child, client: Arc<Mutex<DapWriter>>) {
+    let pipes: [(Option<Box<dyn Read + Send>>, &str); 2] = [
+        (child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>), "stdout"),
+        (child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>), "stderr"),
+    ];
+
+    for (pipe, category) in pipes {
+        let Some(pipe) = pipe else {
+            continue;
+        };
+
+        let client = client.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(pipe).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+
+                let _ = client.lock().unwrap().event("output", json!({ "category": category, "output": line + "\n" }));
+            }
+        });
+    }
+}
+
+fn capabilities() -> Value {
+    return json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsReadMemoryRequest": true,
+        "supportsWriteMemoryRequest": true,
+    });
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_len = None;
+
+    // headers, terminated by an empty line
+    loop {
+        let mut line = String::new();
+        if input.take(MAX_MESSAGE_LEN as u64).read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(v) = line.strip_prefix("Content-Length:") {
+            content_len = v.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(content_len) = content_len else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"));
+    };
+
+    if content_len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("message of {} bytes is over the {} byte limit", content_len, MAX_MESSAGE_LEN)));
+    }
+
+    let mut body = vec![0;content_len];
+    input.read_exact(&mut body)?;
+
+    return serde_json::from_slice(&body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+}
+
+fn write_message(out: &mut impl Write, msg: &Value) -> io::Result<()> {
+    let body = msg.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    return out.flush();
+}
+
+fn parse_mem_ref(args: &Value) -> Result<u32, String> {
+    let mem_ref = args.get("memoryReference").and_then(Value::as_str).ok_or("missing memoryReference")?;
+    let base = nyxbox_core::inspect::parse_addr(mem_ref)?;
+    let offset = args.get("offset").and_then(Value::as_i64).unwrap_or(0);
+    return Ok((base as i64 + offset) as u32);
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn hex_to_base64(hex: &str) -> String {
+    let bytes = nyxbox_core::inspect::parse_hex_pattern(hex).unwrap_or_default();
+    let mut out = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+
+        out.push(BASE64_CHARS[(n >> 18) as usize & 63] as char);
+        out.push(BASE64_CHARS[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { BASE64_CHARS[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { BASE64_CHARS[n as usize & 63] as char } else { '=' });
+    }
+
+    return out;
+}
+
+fn base64_to_bytes(s: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes().filter(|c| *c != b'=') {
+        let v = BASE64_CHARS.iter().position(|x| *x == c).ok_or("invalid base64 data")? as u32;
+        acc = (acc << 6) | v;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    return Ok(out);
+}
@@ -0,0 +1,119 @@
+use std::{fmt::Display, fs};
+
+// SPIR-V module magic number (little endian)
+const SPIRV_MAGIC: u32 = 0x07230203;
+
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<&'static str>,
+}
+
+// collects the results of startup validation so we can report everything that's wrong at once, instead of dying on the first unwrap
+pub struct StartupReport {
+    results: Vec<CheckResult>,
+}
+
+impl StartupReport {
+    pub fn new() -> Self {
+        Self {
+            results: Vec::new(),
+        }
+    }
+
+    pub fn push(self: &mut Self, name: &'static str, status: CheckStatus, detail: String, hint: Option<&'static str>) {
+        self.results.push(CheckResult { name, status, detail, hint });
+    }
+
+    // a failed requirement is fatal - startup stops once all checks have run
+    pub fn require<T, E: Display>(self: &mut Self, name: &'static str, res: Result<T, E>, hint: &'static str) -> Option<T> {
+        match res {
+            Ok(v) => {
+                self.push(name, CheckStatus::Ok, "ok".to_string(), None);
+                return Some(v);
+            }
+            Err(e) => {
+                self.push(name, CheckStatus::Fail, e.to_string(), Some(hint));
+                return None;
+            }
+        }
+    }
+
+    // a failed optional check just means running with that feature disabled
+    pub fn optional<T, E: Display>(self: &mut Self, name: &'static str, res: Result<T, E>, hint: &'static str) -> Option<T> {
+        match res {
+            Ok(v) => {
+                self.push(name, CheckStatus::Ok, "ok".to_string(), None);
+                return Some(v);
+            }
+            Err(e) => {
+                self.push(name, CheckStatus::Warn, e.to_string(), Some(hint));
+                return None;
+            }
+        }
+    }
+
+    pub fn check_shaders(self: &mut Self, paths: &[&str]) {
+        for path in paths {
+            match validate_spirv(path) {
+                Ok(()) => {
+                    self.push("shader", CheckStatus::Ok, path.to_string(), None);
+                }
+                Err(e) => {
+                    self.push("shader", CheckStatus::Fail, format!("{}: {}", path, e),
+                        Some("run ./build-shaders.sh to rebuild the compute shaders, or `git lfs pull` if the repository was cloned without LFS"));
+                }
+            }
+        }
+    }
+
+    pub fn has_failures(self: &Self) -> bool {
+        return self.results.iter().any(|r| matches!(r.status, CheckStatus::Fail));
+    }
+
+    pub fn has_problems(self: &Self) -> bool {
+        return self.results.iter().any(|r| !matches!(r.status, CheckStatus::Ok));
+    }
+
+    pub fn print(self: &Self, include_ok: bool) {
+        for r in &self.results {
+            let tag = match r.status {
+                CheckStatus::Ok => " ok ",
+                CheckStatus::Warn => "warn",
+                CheckStatus::Fail => "FAIL",
+            };
+
+            if !include_ok && matches!(r.status, CheckStatus::Ok) {
+                continue;
+            }
+
+            eprintln!("[{}] {}: {}", tag, r.name, r.detail);
+
+            if let Some(hint) = r.hint {
+                eprintln!("       hint: {}", hint);
+            }
+        }
+    }
+}
+
+fn validate_spirv(path: &str) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+
+    // a common failure mode: the repo was cloned without git-lfs, so we've got a text pointer instead of a binary
+    if data.starts_with(b"version https://git-lfs") {
+        return Err("file is a Git LFS pointer, not a compiled shader".to_string());
+    }
+
+    if data.len() < 20 || data.len() % 4 != 0 || u32::from_le_bytes(data[0..4].try_into().unwrap()) != SPIRV_MAGIC {
+        return Err("not a valid SPIR-V module".to_string());
+    }
+
+    return Ok(());
+}
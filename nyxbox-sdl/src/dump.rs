@@ -0,0 +1,164 @@
+use std::{fs, io, path::PathBuf};
+
+use clap::Args;
+use serde_json::{json, Value};
+
+use nyxbox_core::{inspect::{hexdump, parse_hex_pattern, AddressSpace}, mem::{MemoryMap, BOOT_ROM_BEGIN, VRAM_DEBUG_BEGIN}, savestate::SaveState};
+
+use crate::control::ControlClient;
+
+// how much one peek asks for - a whole region in one go would be a reply tens of megabytes long
+const PEEK_CHUNK: u64 = 64 * 1024;
+
+// where `nyxbox dump` & `nyxbox find` get the memory they look at
+#[derive(Args)]
+pub struct ImageArgs {
+    /// Save state to take ROM, RAM, and VRAM images from
+    #[arg(long)]
+    pub state: Option<PathBuf>,
+
+    /// Boot ROM image to load at the boot ROM base address
+    #[arg(long)]
+    pub rom: Option<PathBuf>,
+
+    /// Main RAM image to load at the main RAM base address
+    #[arg(long)]
+    pub ram: Option<PathBuf>,
+
+    /// VRAM image to load at the VRAM debug base address
+    #[arg(long)]
+    pub vram: Option<PathBuf>,
+
+    /// Read ROM & RAM from a running instance's control socket (its --control address) instead. It's paused while
+    /// they're read. VRAM isn't visible to the CPU, so isn't included
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["state", "rom", "ram", "vram"])]
+    pub connect: Option<String>,
+}
+
+impl ImageArgs {
+    pub fn load(self: &Self) -> Result<AddressSpace, String> {
+        if let Some(addr) = &self.connect {
+            return load_remote(addr);
+        }
+
+        return self.load_images().map_err(|e| format!("failed to load memory image: {}", e));
+    }
+
+    fn load_images(self: &Self) -> io::Result<AddressSpace> {
+        let mut space = AddressSpace::new();
+        let map = MemoryMap::default();
+
+        if let Some(path) = &self.state {
+            space.add_state(&SaveState::load(path)?, &map);
+        }
+
+        if let Some(path) = &self.rom {
+            space.add_region("rom", BOOT_ROM_BEGIN as u32, fs::read(path)?);
+        }
+
+        if let Some(path) = &self.ram {
+            space.add_region("ram", map.main_ram_begin as u32, fs::read(path)?);
+        }
+
+        if let Some(path) = &self.vram {
+            space.add_region("vram", VRAM_DEBUG_BEGIN as u32, fs::read(path)?);
+        }
+
+        return Ok(space);
+    }
+}
+
+// pauses the instance at addr for as long as it takes to peek all of its memory, so the regions are all from the same moment
+fn load_remote(addr: &str) -> Result<AddressSpace, String> {
+    let mut client = ControlClient::connect(addr).map_err(|e| format!("failed to connect to {}: {}", addr, e))?;
+
+    let was_paused = client.call("status", Value::Null)?.get("paused").and_then(Value::as_bool).unwrap_or(false);
+    client.call("pause", Value::Null)?;
+
+    let res = read_regions(&mut client);
+
+    // leave it as it was found, even when the read failed
+    if !was_paused {
+        let resumed = client.call("resume", Value::Null);
+        return res.and_then(|space| resumed.map(|_| space));
+    }
+
+    return res;
+}
+
+fn read_regions(client: &mut ControlClient) -> Result<AddressSpace, String> {
+    let mut space = AddressSpace::new();
+    let regions = client.call("regions", Value::Null)?;
+
+    for region in regions.get("regions").and_then(Value::as_array).map_or(&[][..], Vec::as_slice) {
+        let name = match region.get("name").and_then(Value::as_str) {
+            Some("rom") => "rom",
+            Some("ram") => "ram",
+            Some("xram") => "xram",
+            _ => continue,
+        };
+
+        let base = region.get("base").and_then(Value::as_u64).unwrap_or(0);
+        let size = region.get("size").and_then(Value::as_u64).unwrap_or(0);
+        let mut data = Vec::with_capacity(size as usize);
+
+        for offs in (0..size).step_by(PEEK_CHUNK as usize) {
+            let reply = client.call("peek", json!({ "addr": base + offs, "len": PEEK_CHUNK.min(size - offs) }))?;
+            data.extend(parse_hex_pattern(reply.get("data").and_then(Value::as_str).unwrap_or(""))?);
+        }
+
+        space.add_region(name, base as u32, data);
+    }
+
+    return Ok(space);
+}
+
+fn load_or_exit(images: &ImageArgs) -> AddressSpace {
+    let space = match images.load() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if space.is_empty() {
+        eprintln!("no memory images given (use --state, --rom, --ram, --vram, or --connect)");
+        std::process::exit(1);
+    }
+
+    return space;
+}
+
+pub fn dump_cmd(images: &ImageArgs, addr: u32, len: usize) {
+    let space = load_or_exit(images);
+
+    let Some(data) = space.read(addr, len) else {
+        eprintln!("address {:08x} is not inside any loaded region", addr);
+        std::process::exit(1);
+    };
+
+    hexdump(&mut io::stdout().lock(), addr, data).unwrap();
+}
+
+pub fn find_cmd(images: &ImageArgs, pattern: &str, text: bool) {
+    let space = load_or_exit(images);
+
+    let pattern = if text {
+        pattern.as_bytes().to_vec()
+    }
+    else {
+        match parse_hex_pattern(pattern) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    // one match per line so output can be piped straight into other tools
+    for (addr, region) in space.find(&pattern) {
+        println!("{:08x} {}", addr, region);
+    }
+}
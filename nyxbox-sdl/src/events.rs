@@ -0,0 +1,125 @@
+use std::{fmt, path::PathBuf};
+
+use sdl3::video::Window;
+
+use nyxbox_core::breakpoint::DebugStop;
+
+use crate::lang::tr;
+
+// things happening to the machine that frontend features might care about. the main loop publishes these & subscribers
+// react, so adding something like an OSD or a scripting hook doesn't mean threading more calls through the main loop. so
+// far that's the event log & the window title - captures, frame hooks & control clients are still driven from the main
+// loop directly, as they need the machine & VDP rather than just hearing what happened
+pub enum MachineEvent {
+    // the CPU thread was started, either at boot or after a reload
+    Started,
+    // the CPU was parked (by the user, a debugger, a remote client, or losing focus)
+    Paused,
+    Resumed,
+    // the CPU hit a breakpoint or watchpoint, or finished a step, & is paused there. desc says where, with the guest's symbols
+    DebugStop { stop: DebugStop, desc: String },
+    // a ROM image was loaded into boot ROM (None when --bios or --load make up the image)
+    RomLoaded { path: Option<PathBuf> },
+    // the frame signal was raised, waking the CPU for the given frame
+    VBlank { frame: u64 },
+    // the guest asked to end the run, with a result string if it gave one
+    GuestExit { code: i32, result: String },
+    // the guest crashed, or exited with a window up, & emulation has stopped until the machine is reset or a state loaded
+    Halted { reason: String },
+    // the guest stopped kicking the watchdog, & the machine is being reset
+    WatchdogReset,
+    // the machine is shutting down
+    Stopped,
+}
+
+impl fmt::Display for MachineEvent {
+    fn fmt(self: &Self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            MachineEvent::Started => write!(f, "started"),
+            MachineEvent::Paused => write!(f, "paused"),
+            MachineEvent::Resumed => write!(f, "resumed"),
+            MachineEvent::DebugStop { desc, .. } => write!(f, "stopped: {}", desc),
+            MachineEvent::RomLoaded { path: Some(path) } => write!(f, "rom loaded: {}", path.display()),
+            MachineEvent::RomLoaded { path: None } => write!(f, "rom loaded: --bios/--load image"),
+            MachineEvent::VBlank { frame } => write!(f, "vblank {}", frame),
+            MachineEvent::GuestExit { code, result } if result.is_empty() => write!(f, "guest exit {}", code),
+            MachineEvent::GuestExit { code, result } => write!(f, "guest exit {}: {}", code, result),
+            MachineEvent::Halted { reason } => write!(f, "halted: {}", reason),
+            MachineEvent::WatchdogReset => write!(f, "watchdog reset"),
+            MachineEvent::Stopped => write!(f, "stopped"),
+        };
+    }
+}
+
+pub trait EventSubscriber {
+    fn on_event(self: &mut Self, frame: u64, ev: &MachineEvent);
+}
+
+// subscribers are called synchronously, in the order they subscribed, on the frontend thread
+pub struct EventBus {
+    subscribers: Vec<Box<dyn EventSubscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(self: &mut Self, subscriber: Box<dyn EventSubscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn publish(self: &mut Self, frame: u64, ev: MachineEvent) {
+        for subscriber in &mut self.subscribers {
+            subscriber.on_event(frame, &ev);
+        }
+    }
+}
+
+// prints events as they happen (--log-events)
+pub struct EventLog;
+
+impl EventSubscriber for EventLog {
+    fn on_event(self: &mut Self, frame: u64, ev: &MachineEvent) {
+        // one of these a frame would drown everything else out
+        if let MachineEvent::VBlank { .. } = ev {
+            return;
+        }
+
+        println!("[{:>8}] {}", frame, ev);
+    }
+}
+
+// says in the title bar why a halted guest isn't running, until it starts again
+pub struct WindowTitle {
+    window: Window,
+    halted: bool,
+}
+
+impl WindowTitle {
+    pub fn new(window: Window) -> Self {
+        Self {
+            window,
+            halted: false,
+        }
+    }
+}
+
+impl EventSubscriber for WindowTitle {
+    fn on_event(self: &mut Self, _frame: u64, ev: &MachineEvent) {
+        match ev {
+            MachineEvent::Halted { reason } => {
+                let _ = self.window.set_title(&format!("{} - {}", tr!("window_title"), reason));
+                self.halted = true;
+            }
+            MachineEvent::Started if self.halted => {
+                let _ = self.window.set_title(tr!("window_title"));
+                self.halted = false;
+            }
+            _ => {
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use clap::Subcommand;
+
+use nyxbox_core::{machine::DEBUG_REGS, timebase::NS_PER_SEC, savestate::{SaveState, SECTION_CPU, SECTION_CRASH_REPORT, SECTION_RAM, SECTION_ROM, SECTION_TIME, SECTION_VDP_REGS, SECTION_VRAM}, screenshot::{self, framebuffer_rgba}, vdp::INTERNALREG_COUNT};
+
+use crate::statecheck;
+
+// offline tools for pulling data back out of a save state, without booting the emulator
+#[derive(Subcommand)]
+pub enum StateCommand {
+    /// Write the main RAM image to a file
+    Ram { state: PathBuf, out: PathBuf },
+    /// Write the boot ROM image to a file
+    Rom { state: PathBuf, out: PathBuf },
+    /// Write the VRAM image to a file
+    Vram { state: PathBuf, out: PathBuf },
+    /// Write the current framebuffer (FBADDR/FBDIM) out as a PNG
+    Screenshot { state: PathBuf, out: PathBuf },
+    /// Print CPU and VDP register values
+    Regs { state: PathBuf },
+    /// Print the report from a crash dump
+    Crash { dump: PathBuf },
+    /// Check that every state in a compatibility corpus loads, or fails the way its corpus.txt says it should
+    Check {
+        /// Corpus directory (save states plus corpus.txt)
+        #[arg(default_value = "tests/savestates")]
+        corpus: PathBuf,
+    },
+}
+
+fn fail(msg: String) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+fn load_state(path: &Path) -> SaveState {
+    return SaveState::load(path).unwrap_or_else(|e| fail(format!("failed to load {}: {}", path.display(), e)));
+}
+
+fn require_section<'a>(state: &'a SaveState, tag: &[u8;4]) -> &'a [u8] {
+    return state.section(tag).unwrap_or_else(|| fail(format!("save state has no '{}' section", String::from_utf8_lossy(tag))));
+}
+
+fn write_out(path: &Path, data: &[u8]) {
+    fs::write(path, data).unwrap_or_else(|e| fail(format!("failed to write {}: {}", path.display(), e)));
+}
+
+pub fn state_cmd(cmd: &StateCommand) {
+    match cmd {
+        StateCommand::Ram { state, out } => {
+            write_out(out, require_section(&load_state(state), &SECTION_RAM));
+        }
+        StateCommand::Rom { state, out } => {
+            write_out(out, require_section(&load_state(state), &SECTION_ROM));
+        }
+        StateCommand::Vram { state, out } => {
+            write_out(out, require_section(&load_state(state), &SECTION_VRAM));
+        }
+        StateCommand::Screenshot { state, out } => {
+            let state = load_state(state);
+            let regs = state.section_words(&SECTION_VDP_REGS).unwrap_or_else(|| fail("save state has no VDP registers".to_string()));
+            let vram = require_section(&state, &SECTION_VRAM);
+
+            let (width, height, rgba) = framebuffer_rgba(&regs, vram).unwrap_or_else(|e| fail(e));
+            screenshot::write_png(out, width, height, &rgba).unwrap_or_else(|e| fail(format!("failed to write {}: {}", out.display(), e)));
+        }
+        StateCommand::Regs { state } => {
+            let state = load_state(state);
+
+            println!("version {}", state.version);
+
+            if let Some(time) = state.section(&SECTION_TIME) {
+                let frame = u64::from_le_bytes(time[0..8].try_into().unwrap());
+                let ns = u64::from_le_bytes(time[8..16].try_into().unwrap());
+                println!("time: frame {}, {}.{:09}s", frame, ns / NS_PER_SEC, ns % NS_PER_SEC);
+            }
+
+            if let Some(cpu) = state.section_words(&SECTION_CPU) {
+                println!("cpu:");
+                for ((name, _), val) in DEBUG_REGS.iter().zip(cpu) {
+                    println!("  {:<5} {:08x}", name, val);
+                }
+            }
+
+            if let Some(vdp) = state.section_words(&SECTION_VDP_REGS) {
+                // most of the register file is usually zero, only print what's been set
+                println!("vdp:");
+                for (idx, val) in vdp.iter().enumerate().take(INTERNALREG_COUNT).filter(|(_, v)| **v != 0) {
+                    println!("  [{:3}] {:08x}", idx, val);
+                }
+            }
+        }
+        StateCommand::Crash { dump } => {
+            print!("{}", String::from_utf8_lossy(require_section(&load_state(dump), &SECTION_CRASH_REPORT)));
+        }
+        StateCommand::Check { corpus } => {
+            statecheck::check_corpus(corpus);
+        }
+    }
+}
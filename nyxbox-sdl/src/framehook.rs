@@ -0,0 +1,333 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use sdl3::gpu::Device;
+
+use nyxbox_core::{inspect::{parse_addr, parse_hex_pattern}, machine::MachineRunContext, screenshot::{diff_images, framebuffer_hash, framebuffer_rgba, read_png, write_png}, uart::UART, vdp::VDP};
+
+pub enum HookAction {
+    Continue,
+    Exit(i32),
+}
+
+// invoked by the frontend after every presented frame
+pub trait FrameHook {
+    fn on_frame(self: &mut Self, ctx: &mut FrameContext) -> HookAction;
+}
+
+// what a frame hook gets to look at. the framebuffer is only read back from the GPU when a hook actually asks for it
+pub struct FrameContext<'a> {
+    pub frame: u64,
+    vdp: &'a mut VDP,
+    gfx_device: &'a Device,
+    run_ctx: &'a MachineRunContext,
+    uart: &'a UART,
+    framebuffer: Option<Result<(u32, u32, Vec<u8>), String>>,
+}
+
+impl<'a> FrameContext<'a> {
+    pub fn new(frame: u64, vdp: &'a mut VDP, gfx_device: &'a Device, run_ctx: &'a MachineRunContext, uart: &'a UART) -> Self {
+        Self {
+            frame,
+            vdp,
+            gfx_device,
+            run_ctx,
+            uart,
+            framebuffer: None,
+        }
+    }
+
+    pub fn framebuffer(self: &mut Self) -> Result<&(u32, u32, Vec<u8>), String> {
+        if self.framebuffer.is_none() {
+            let vram = self.vdp.read_vram(self.gfx_device);
+            self.framebuffer = Some(framebuffer_rgba(&self.vdp.display_regs(), &vram));
+        }
+
+        return self.framebuffer.as_ref().unwrap().as_ref().map_err(|e| e.clone());
+    }
+
+    pub fn pixel(self: &mut Self, x: u32, y: u32) -> Result<u32, String> {
+        let (width, height, rgba) = self.framebuffer()?;
+
+        if x >= *width || y >= *height {
+            return Err(format!("pixel ({}, {}) is outside the {}x{} framebuffer", x, y, width, height));
+        }
+
+        let offs = ((y * width + x) * 4) as usize;
+        return Ok(u32::from_be_bytes(rgba[offs..offs + 4].try_into().unwrap()));
+    }
+
+    pub fn mem_read(self: &Self, addr: u32, len: usize) -> Result<Vec<u8>, String> {
+        return self.run_ctx.mem_read(addr, len).map_err(|e| format!("read of {:08x} failed: {:?}", addr, e));
+    }
+
+    // the most recent bytes the guest has sent over the UART (see UART_TX_HISTORY_SIZE)
+    pub fn uart_output(self: &Self) -> Vec<u8> {
+        return self.uart.tx_history();
+    }
+}
+
+enum Check {
+    // pixel value is RGBA, as it appears in the framebuffer
+    Pixel { x: u32, y: u32, value: u32 },
+    Mem { addr: u32, data: Vec<u8> },
+    // somewhere in the UART output so far
+    Uart { text: Vec<u8> },
+    Exit,
+}
+
+// assertion-style visual tests, one check per line:
+//
+//   # comment
+//   300 pixel 10 10 ff0000ff
+//   300 mem 0x1000000 deadbeef
+//   300 uart "hello\n"
+//   600 exit
+//
+// a check runs on the first presented frame at or after the frame it names. uart text is quoted, with \n, \r, \t, \\, \"
+// & \xNN escapes (a # has to be written \x23, as it starts a comment)
+pub struct AssertScript {
+    checks: Vec<(u64, Check)>,
+    next: usize,
+    failures: u32,
+}
+
+impl AssertScript {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        return Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut checks = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let check = Self::parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            checks.push(check);
+        }
+
+        // stable, so checks on the same frame keep their script order
+        checks.sort_by_key(|(frame, _)| *frame);
+
+        return Ok(Self {
+            checks,
+            next: 0,
+            failures: 0,
+        });
+    }
+
+    fn parse_line(line: &str) -> Result<(u64, Check), String> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+
+        let frame = args[0].parse::<u64>().map_err(|_| format!("invalid frame number '{}'", args[0]))?;
+
+        // the text is the rest of the line, spaces & all
+        if args.get(1) == Some(&"uart") {
+            let text = parse_quoted(line[line.find("uart").unwrap() + 4..].trim())?;
+
+            if text.is_empty() {
+                return Err("uart text can't be empty".to_string());
+            }
+
+            return Ok((frame, Check::Uart { text }));
+        }
+
+        let check = match &args[1..] {
+            ["pixel", x, y, value] => {
+                Check::Pixel {
+                    x: x.parse().map_err(|_| format!("invalid x coordinate '{}'", x))?,
+                    y: y.parse().map_err(|_| format!("invalid y coordinate '{}'", y))?,
+                    value: u32::from_str_radix(value, 16).map_err(|_| format!("invalid RGBA value '{}'", value))?,
+                }
+            }
+            ["mem", addr, data] => {
+                Check::Mem {
+                    addr: parse_addr(addr)?,
+                    data: parse_hex_pattern(data)?,
+                }
+            }
+            ["exit"] => Check::Exit,
+            _ => {
+                return Err(format!("unrecognized check '{}'", line));
+            }
+        };
+
+        return Ok((frame, check));
+    }
+}
+
+impl FrameHook for AssertScript {
+    fn on_frame(self: &mut Self, ctx: &mut FrameContext) -> HookAction {
+        while let Some((frame, check)) = self.checks.get(self.next) {
+            if *frame > ctx.frame {
+                break;
+            }
+
+            self.next += 1;
+
+            let res = match check {
+                Check::Pixel { x, y, value } => {
+                    ctx.pixel(*x, *y).and_then(|actual| if actual == *value {
+                        Ok(())
+                    }
+                    else {
+                        Err(format!("pixel ({}, {}) is {:08x}, expected {:08x}", x, y, actual, value))
+                    })
+                }
+                Check::Mem { addr, data } => {
+                    ctx.mem_read(*addr, data.len()).and_then(|actual| if actual == *data {
+                        Ok(())
+                    }
+                    else {
+                        Err(format!("memory at {:08x} is {}, expected {}", addr, crate::control::to_hex(&actual), crate::control::to_hex(data)))
+                    })
+                }
+                Check::Uart { text } => {
+                    let output = ctx.uart_output();
+
+                    if output.windows(text.len()).any(|window| window == text.as_slice()) {
+                        Ok(())
+                    }
+                    else {
+                        Err(format!("UART output doesn't contain \"{}\" (got \"{}\")", text.escape_ascii(), output.escape_ascii()))
+                    }
+                }
+                Check::Exit => {
+                    println!("assertions: {} failed", self.failures);
+                    return HookAction::Exit(if self.failures == 0 { 0 } else { 1 });
+                }
+            };
+
+            if let Err(e) = res {
+                println!("frame {}: {}", ctx.frame, e);
+                self.failures += 1;
+            }
+        }
+
+        return HookAction::Continue;
+    }
+}
+
+// a "quoted" string with C-style escapes, as bytes
+fn parse_quoted(s: &str) -> Result<Vec<u8>, String> {
+    let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).filter(|_| s.len() >= 2) else {
+        return Err(format!("expected quoted text, not '{}'", s));
+    };
+
+    let mut out = Vec::new();
+    let mut bytes = inner.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+
+        match bytes.next() {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'"') => out.push(b'"'),
+            Some(b'x') => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                let val = std::str::from_utf8(&hex).ok().filter(|hex| hex.len() == 2).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                out.push(val.ok_or(format!("bad \\x escape in {}", s))?);
+            }
+            _ => return Err(format!("unknown escape in {}", s)),
+        }
+    }
+
+    return Ok(out);
+}
+
+// what a golden test compares the framebuffer with
+pub enum Golden {
+    Image(PathBuf),
+    Hash(u32),
+}
+
+// golden-image regression test: once the given frame has been presented, compare the framebuffer with a stored image (to
+// within a per-channel tolerance) or hash, & end the run - 0 if it matches, 1 if not. a mismatched image leaves the actual
+// framebuffer & a diff next to each other in the output directory. updating writes the golden image instead
+pub struct GoldenCheck {
+    frame: u64,
+    golden: Golden,
+    tolerance: u8,
+    update: bool,
+    out_dir: PathBuf,
+}
+
+impl GoldenCheck {
+    pub fn new(frame: u64, golden: Golden, tolerance: u8, update: bool, out_dir: PathBuf) -> Self {
+        Self {
+            frame,
+            golden,
+            tolerance,
+            update,
+            out_dir,
+        }
+    }
+
+    fn check(self: &Self, ctx: &mut FrameContext) -> Result<(), String> {
+        let (width, height, rgba) = ctx.framebuffer()?.clone();
+        let hash = framebuffer_hash(width, height, &rgba);
+
+        println!("golden: frame {} is {}x{}, hash {:08x}", ctx.frame, width, height, hash);
+
+        let path = match &self.golden {
+            Golden::Hash(expected) if hash == *expected => return Ok(()),
+            Golden::Hash(expected) => return Err(format!("hash is {:08x}, expected {:08x}", hash, expected)),
+            Golden::Image(path) => path,
+        };
+
+        if self.update {
+            write_png(path, width, height, &rgba).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+            println!("golden: wrote {}", path.display());
+            return Ok(());
+        }
+
+        let (expected_width, expected_height, expected) = read_png(path)?;
+
+        if (expected_width, expected_height) != (width, height) {
+            return Err(format!("framebuffer is {}x{}, but {} is {}x{}", width, height, path.display(), expected_width, expected_height));
+        }
+
+        let diff = diff_images(width, &expected, &rgba, self.tolerance);
+
+        let Some((x, y)) = diff.first else {
+            return Ok(());
+        };
+
+        let stem = path.file_stem().map_or("golden".into(), |stem| stem.to_string_lossy());
+        let actual_path = self.out_dir.join(format!("{}.actual.png", stem));
+        let diff_path = self.out_dir.join(format!("{}.diff.png", stem));
+
+        fs::create_dir_all(&self.out_dir).map_err(|e| format!("failed to create {}: {}", self.out_dir.display(), e))?;
+        write_png(&actual_path, width, height, &rgba).map_err(|e| format!("failed to write {}: {}", actual_path.display(), e))?;
+        write_png(&diff_path, width, height, &diff.image).map_err(|e| format!("failed to write {}: {}", diff_path.display(), e))?;
+
+        return Err(format!("{} pixels differ from {} (first at ({}, {}), largest channel difference {}) - see {}",
+            diff.pixels, path.display(), x, y, diff.max_delta, diff_path.display()));
+    }
+}
+
+impl FrameHook for GoldenCheck {
+    fn on_frame(self: &mut Self, ctx: &mut FrameContext) -> HookAction {
+        if ctx.frame < self.frame {
+            return HookAction::Continue;
+        }
+
+        return match self.check(ctx) {
+            Ok(()) => HookAction::Exit(0),
+            Err(e) => {
+                println!("golden: {}", e);
+                HookAction::Exit(1)
+            }
+        };
+    }
+}
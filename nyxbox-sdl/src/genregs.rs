@@ -0,0 +1,293 @@
+use std::{fmt::Write, fs, path::PathBuf};
+
+use clap::Args;
+
+use nyxbox_core::{bios, buserr, clock, debugport, framebudget, gamepad, intc, mailbox, mem, mpu, poison, sysinfo, uart, vdp, vdpport, watchdog};
+
+#[derive(Args)]
+pub struct GenRegsArgs {
+    /// Write the bindings here instead of stdout (e.g. nyxbox-guest/src/regs.rs)
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Generate addresses for this memory map instead of the standard layout
+    #[arg(long)]
+    memory_map: Option<PathBuf>,
+}
+
+// one peripheral's register block: register names by word index, & the bit/value constants that go with it. the base
+// address comes from the memory map, by name
+struct Block {
+    name: &'static str,
+    regs: &'static [(&'static str, u32)],
+    consts: &'static [(&'static str, u32)],
+    // comments written above a register's constant, for behaviour a guest can't see from the name alone
+    notes: &'static [(&'static str, &'static str)],
+}
+
+// register indices aren't named constants on the emulator side (peripherals just match on them), so they're listed here -
+// keep these in step with the peripherals' read/write matches
+const BLOCKS: &[Block] = &[
+    Block {
+        name: "uart",
+        regs: &[("STATUS", 0), ("TX", 1), ("RX", 2), ("TXLEVEL", 3)],
+        consts: &[
+            ("STATUSBIT_RESET", uart::UARTSTATUSBIT_RESET),
+            ("STATUSBIT_TXEMPTY", uart::UARTSTATUSBIT_TXEMPTY),
+            ("STATUSBIT_TXFULL", uart::UARTSTATUSBIT_TXFULL),
+            ("STATUSBIT_RXEMPTY", uart::UARTSTATUSBIT_RXEMPTY),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "vdp",
+        regs: &[
+            ("STATUS", 0), ("CMDPORT", 1), ("DISPLAYMODE", 2), ("DMASRC", 3), ("DMADST", 4), ("DMALEN", 5), ("DMACTRL", 6), ("FENCE", 7),
+            ("STATCMDS", 8), ("STATPRIMS", 9), ("STATVERTS", 10), ("STATDMABYTES", 11), ("STATPIXELS", 12),
+        ],
+        consts: &[
+            ("STATUSBIT_RESET", vdp::STATUSBIT_RESET),
+            ("STATUSBIT_CMDFIFOEMPTY", vdp::STATUSBIT_CMDFIFOEMPTY),
+            ("STATUSBIT_CMDFIFOFULL", vdp::STATUSBIT_CMDFIFOFULL),
+            ("STATUSBIT_ERR_MASK", vdp::STATUSBIT_ERR_MASK),
+            ("STATUSBIT_ERR_ADDR", vdp::STATUSBIT_ERR_ADDR),
+            ("STATUSBIT_ERR_CMD", vdp::STATUSBIT_ERR_CMD),
+            ("STATUSBIT_DMABUSY", vdpport::STATUSBIT_DMABUSY),
+            ("STATUSBIT_UPLOADPENDING", vdpport::STATUSBIT_UPLOADPENDING),
+            ("STATUSBIT_DRAWBUSY", vdpport::STATUSBIT_DRAWBUSY),
+            ("STATUSBIT_FENCEPENDING", vdpport::STATUSBIT_FENCEPENDING),
+            ("DISPLAYBIT_CABLE_MASK", vdp::DISPLAYBIT_CABLE_MASK),
+            ("DISPLAYBIT_ENABLE", vdp::DISPLAYBIT_ENABLE),
+            ("DISPLAYBIT_INTERLACE", vdp::DISPLAYBIT_INTERLACE),
+            ("DMACTRLBIT_START", vdpport::DMACTRLBIT_START),
+            ("VRAM_SIZE", vdp::VRAM_SIZE),
+        ],
+        notes: &[
+            ("STATPIXELS", "approximate: counted on the GPU & read back without waiting on it, so it can lag a frame behind the other STAT registers. always 0 when sysinfo FEATUREBIT_DETERMINISTIC is set - don't base level of detail on it alone"),
+        ],
+    },
+    Block {
+        name: "clock",
+        regs: &[("STATUS", 0), ("DT", 1), ("CTR0LO", 2), ("CTR0HI", 3), ("CTR1LO", 4), ("CTR1HI", 5), ("CTR0P", 6), ("CTR1P", 7), ("IRQ", 8)],
+        consts: &[
+            ("IRQBIT_CTR0", clock::CLOCKIRQ_CTR0),
+            ("IRQBIT_CTR1", clock::CLOCKIRQ_CTR1),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "sysinfo",
+        regs: &[("ID", 0), ("VERSION", 1), ("FEATURES", 2), ("SEEDLO", 3), ("SEEDHI", 4), ("FRAME", 5), ("FRAMESEED", 6), ("RAMSIZE", 7), ("CPUBUDGET", 8), ("MAINRAMSIZE", 9), ("ROMSIZE", 10)],
+        consts: &[
+            ("ID_VALUE", sysinfo::SYSINFO_ID),
+            ("FEATUREBIT_UART", sysinfo::FEATUREBIT_UART),
+            ("FEATUREBIT_CLOCK", sysinfo::FEATUREBIT_CLOCK),
+            ("FEATUREBIT_VDP", sysinfo::FEATUREBIT_VDP),
+            ("FEATUREBIT_DETERMINISTIC", sysinfo::FEATUREBIT_DETERMINISTIC),
+            ("FEATUREBIT_DEBUGPORT", sysinfo::FEATUREBIT_DEBUGPORT),
+            ("FEATUREBIT_MPU", sysinfo::FEATUREBIT_MPU),
+            ("FEATUREBIT_FRAMEBUDGET", sysinfo::FEATUREBIT_FRAMEBUDGET),
+            ("FEATUREBIT_EXPANSIONRAM", sysinfo::FEATUREBIT_EXPANSIONRAM),
+            ("FEATUREBIT_GAMEPAD", sysinfo::FEATUREBIT_GAMEPAD),
+            ("FEATUREBIT_POISON", sysinfo::FEATUREBIT_POISON),
+            ("FEATUREBIT_STRICTHW", sysinfo::FEATUREBIT_STRICTHW),
+            ("FEATUREBIT_INTC", sysinfo::FEATUREBIT_INTC),
+            ("FEATUREBIT_BUSERR", sysinfo::FEATUREBIT_BUSERR),
+            ("FEATUREBIT_COPROCESSOR", sysinfo::FEATUREBIT_COPROCESSOR),
+            ("FEATUREBIT_WATCHDOG", sysinfo::FEATUREBIT_WATCHDOG),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "debugport",
+        regs: &[("NAME", 0), ("CMD", 1), ("PENDING", 2), ("SERVICED", 3), ("EXITCODE", 4)],
+        consts: &[
+            ("CMD_SCREENSHOT", debugport::DEBUGCMD_SCREENSHOT),
+            ("CMD_MARKER", debugport::DEBUGCMD_MARKER),
+            ("CMD_EXIT", debugport::DEBUGCMD_EXIT),
+            ("CMD_ASSERT", debugport::DEBUGCMD_ASSERT),
+            ("NAME_MAX", debugport::DEBUGPORT_NAME_MAX as u32),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "mpu",
+        regs: &[("CTRL", 0), ("REGIONS", 1), ("FAULTADDR", 2), ("FAULTSTATUS", 3), ("RBASE0", 0x10), ("RSIZE0", 0x11), ("RATTR0", 0x12)],
+        consts: &[
+            ("REGION_STRIDE", 4),
+            ("REGION_COUNT", mpu::MPU_REGION_COUNT as u32),
+            ("PAGE_SIZE", mpu::MPU_PAGE_SIZE),
+            ("CTRLBIT_ENABLE", mpu::MPUCTRLBIT_ENABLE),
+            ("ATTRBIT_READ", mpu::MPUATTRBIT_READ),
+            ("ATTRBIT_WRITE", mpu::MPUATTRBIT_WRITE),
+            ("ATTRBIT_EXEC", mpu::MPUATTRBIT_EXEC),
+            ("ATTRBIT_ENABLE", mpu::MPUATTRBIT_ENABLE),
+            ("FAULTBIT_READ", mpu::MPUFAULTBIT_READ),
+            ("FAULTBIT_WRITE", mpu::MPUFAULTBIT_WRITE),
+            ("FAULTBIT_FETCH", mpu::MPUFAULTBIT_FETCH),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "framebudget",
+        regs: &[("STATUS", 0), ("OVERRUNS", 1), ("BUDGET", 2), ("LASTFRAME", 3)],
+        consts: &[
+            ("STATUSBIT_OVERRUN", framebudget::FRAMEBUDGETBIT_OVERRUN),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "gamepad",
+        regs: &[("BUTTONS", 0)],
+        consts: &[
+            ("BUTTON_UP", gamepad::BUTTON_UP),
+            ("BUTTON_DOWN", gamepad::BUTTON_DOWN),
+            ("BUTTON_LEFT", gamepad::BUTTON_LEFT),
+            ("BUTTON_RIGHT", gamepad::BUTTON_RIGHT),
+            ("BUTTON_A", gamepad::BUTTON_A),
+            ("BUTTON_B", gamepad::BUTTON_B),
+            ("BUTTON_X", gamepad::BUTTON_X),
+            ("BUTTON_Y", gamepad::BUTTON_Y),
+            ("BUTTON_L", gamepad::BUTTON_L),
+            ("BUTTON_R", gamepad::BUTTON_R),
+            ("BUTTON_START", gamepad::BUTTON_START),
+            ("BUTTON_SELECT", gamepad::BUTTON_SELECT),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "poison",
+        regs: &[("ADDR", 0), ("LEN", 1), ("CMD", 2), ("HITS", 3)],
+        consts: &[
+            ("CMD_POISON", poison::POISONCMD_POISON),
+            ("CMD_UNPOISON", poison::POISONCMD_UNPOISON),
+            ("FILL_BYTE", poison::POISON_BYTE as u32),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "intc",
+        regs: &[("PENDING", 0), ("ENABLE", 1), ("ACTIVE", 2), ("ACK", 3), ("RAISE", 4), ("FIQSEL", 5), ("FIQACTIVE", 6)],
+        consts: &[
+            ("IRQ_VBLANK", intc::IRQ_VBLANK),
+            ("IRQ_VDP", intc::IRQ_VDP),
+            ("IRQ_UART_RX", intc::IRQ_UART_RX),
+            ("IRQ_TIMER", intc::IRQ_TIMER),
+            ("IRQ_MAILBOX", intc::IRQ_MAILBOX),
+            ("IRQ_WATCHDOG", intc::IRQ_WATCHDOG),
+            ("VECTOR", intc::IRQ_VECTOR),
+            ("FIQ_VECTOR", intc::FIQ_VECTOR),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "buserr",
+        regs: &[("STATUS", 0), ("ADDR", 1), ("PC", 2), ("COUNT", 3), ("OPENBUS", 4)],
+        consts: &[
+            ("STATUSBIT_READ", buserr::BUSERRBIT_READ),
+            ("STATUSBIT_WRITE", buserr::BUSERRBIT_WRITE),
+            ("STATUSBIT_FETCH", buserr::BUSERRBIT_FETCH),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "mailbox",
+        regs: &[
+            ("STATUS", 0), ("TX", 1), ("RX", 2), ("DOORBELL", 3), ("ACK", 4),
+            ("COPCTRL", 8), ("COPENTRY", 9), ("COPSTACK", 10), ("COPSTATUS", 11),
+        ],
+        consts: &[
+            ("FIFO_DEPTH", mailbox::MAILBOX_FIFO_DEPTH as u32),
+            ("STATUSBIT_RXREADY", mailbox::MAILBOXSTATUSBIT_RXREADY),
+            ("STATUSBIT_TXFULL", mailbox::MAILBOXSTATUSBIT_TXFULL),
+            ("COPCTRLBIT_RUN", mailbox::MAILBOXCOPCTRLBIT_RUN),
+            ("COPSTATUSBIT_RUNNING", mailbox::MAILBOXCOPSTATUSBIT_RUNNING),
+            ("COPSTATUSBIT_FAULT", mailbox::MAILBOXCOPSTATUSBIT_FAULT),
+        ],
+        notes: &[],
+    },
+    Block {
+        name: "watchdog",
+        regs: &[("CTRL", 0), ("PERIOD", 1), ("KICK", 2), ("REMAIN", 3), ("STATUS", 4)],
+        consts: &[
+            ("CTRLBIT_ENABLE", watchdog::WATCHDOGCTRLBIT_ENABLE),
+            ("CTRLBIT_IRQ", watchdog::WATCHDOGCTRLBIT_IRQ),
+            ("STATUSBIT_WARNED", watchdog::WATCHDOGSTATUSBIT_WARNED),
+            ("STATUSBIT_RESET", watchdog::WATCHDOGSTATUSBIT_RESET),
+            ("KICK_KEY", watchdog::WATCHDOG_KICK_KEY),
+        ],
+        notes: &[],
+    },
+];
+
+// rust source for the guest SDK's register bindings. register constants are absolute byte addresses
+pub fn rust_bindings(map: &mem::MemoryMap) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "// generated by `nyxbox gen-regs` from the emulator's own constants - regenerate rather than editing by hand").unwrap();
+    writeln!(out, "#![allow(dead_code)]").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub const BOOT_ROM_BASE: usize = {:#X};", mem::BOOT_ROM_BEGIN).unwrap();
+    writeln!(out, "pub const BOOT_ROM_SIZE: usize = {:#X};", map.boot_rom_size).unwrap();
+    writeln!(out, "pub const MAIN_RAM_BASE: usize = {:#X};", map.main_ram_begin).unwrap();
+    writeln!(out, "pub const MAIN_RAM_SIZE: usize = {:#X};", map.main_ram_size).unwrap();
+    writeln!(out, "pub const EXPANSION_RAM_BASE: usize = {:#X};", map.expansion_ram_begin).unwrap();
+    writeln!(out, "pub const EXPANSION_RAM_SIZE: usize = {:#X};", map.expansion_ram_size).unwrap();
+
+    for block in BLOCKS {
+        let (_, base) = map.peripherals().into_iter().find(|(name, _)| *name == block.name).unwrap();
+
+        writeln!(out).unwrap();
+        writeln!(out, "pub mod {} {{", block.name).unwrap();
+        writeln!(out, "    pub const BASE: usize = {:#X};", base).unwrap();
+
+        for (name, idx) in block.regs {
+            for (_, note) in block.notes.iter().filter(|(reg, _)| reg == name) {
+                writeln!(out, "    // {}", note).unwrap();
+            }
+
+            writeln!(out, "    pub const {}: usize = BASE + {:#X};", name, idx * 4).unwrap();
+        }
+
+        for (name, val) in block.consts {
+            writeln!(out, "    pub const {}: u32 = {:#X};", name, val).unwrap();
+        }
+
+        writeln!(out, "}}").unwrap();
+    }
+
+    // BIOS calls are SWI numbers rather than registers
+    writeln!(out).unwrap();
+    writeln!(out, "pub mod bios {{").unwrap();
+
+    for (num, name) in bios::BIOS_CALL_NAMES {
+        writeln!(out, "    pub const {}: u32 = {:#X};", name.to_uppercase(), num).unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+
+    return out;
+}
+
+pub fn gen_regs_cmd(args: &GenRegsArgs) {
+    let map = match &args.memory_map {
+        Some(path) => mem::MemoryMap::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => mem::MemoryMap::default(),
+    };
+
+    let src = rust_bindings(&map);
+
+    match &args.out {
+        Some(path) => {
+            if let Err(e) = fs::write(path, src) {
+                eprintln!("failed to write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        None => {
+            print!("{}", src);
+        }
+    }
+}
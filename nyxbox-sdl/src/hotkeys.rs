@@ -0,0 +1,280 @@
+use std::{fmt, fs, path::{Path, PathBuf}};
+
+use sdl3::{gamepad::Button, keyboard::{Keycode, Mod}};
+
+use crate::config;
+
+// frontend hotkeys live in one remappable table rather than in key checks scattered through the event loop. bindings are kept
+// in a plain text file (created with the defaults on first run, in the user's config directory unless --hotkeys names
+// one), one action per line:
+//
+//   # comment
+//   pause = ctrl+P, pad:back+start
+//   screenshot = F12
+//   save_state =
+//
+// an action can have any number of bindings, separated by commas - leave it empty to unbind it. keyboard bindings are an SDL
+// key name with any of ctrl/shift/alt in front, & must match the held modifiers exactly. controller bindings are "pad:"
+// followed by buttons that all have to be held together - they fire when the last one goes down, & the buttons still
+// reach the guest as usual
+pub const DEFAULT_HOTKEY_FILE: &str = "hotkeys.txt";
+
+// the bindings file to use without --hotkeys: one in the working directory, if there is one (where older builds wrote it),
+// or else the one next to nyxbox.toml in the user's config directory. None if there's no config directory, which leaves
+// the defaults in place rather than dropping a file wherever the emulator was started from
+pub fn default_path() -> Option<PathBuf> {
+    let local = PathBuf::from(DEFAULT_HOTKEY_FILE);
+
+    if local.is_file() {
+        return Some(local);
+    }
+
+    return config::user_dir().map(|dir| dir.join(DEFAULT_HOTKEY_FILE));
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Pause,
+    StepInstruction,
+    StepFrame,
+    FastForward,
+    Screenshot,
+    SaveState,
+    LoadState,
+    Rewind,
+    Reset,
+    ReloadRom,
+    DumpTextures,
+    RenderDebug,
+    IsolateDraws,
+    IsolatePrev,
+    IsolateNext,
+    CaptureVu,
+    Quit,
+    MacroRecord(usize),
+    MacroPlay(usize),
+}
+
+const ACTIONS: &[(&str, HotkeyAction, &str)] = &[
+    ("pause",           HotkeyAction::Pause,            "ctrl+P, pad:back+start"),
+    ("step",            HotkeyAction::StepInstruction,  "F10"),
+    ("frame_step",      HotkeyAction::StepFrame,        "F11"),
+    ("fast_forward",    HotkeyAction::FastForward,      "ctrl+F"),
+    ("screenshot",      HotkeyAction::Screenshot,       "F12"),
+    ("save_state",      HotkeyAction::SaveState,        "F5"),
+    ("load_state",      HotkeyAction::LoadState,        "F9"),
+    ("rewind",          HotkeyAction::Rewind,           "Backspace"),
+    ("reset",           HotkeyAction::Reset,            "ctrl+R"),
+    ("reload_rom",      HotkeyAction::ReloadRom,        "ctrl+shift+R"),
+    ("dump_textures",   HotkeyAction::DumpTextures,     "ctrl+T"),
+    ("render_debug",    HotkeyAction::RenderDebug,      "ctrl+W"),
+    ("isolate_draws",   HotkeyAction::IsolateDraws,     "ctrl+I"),
+    ("isolate_prev",    HotkeyAction::IsolatePrev,      "ctrl+PageUp"),
+    ("isolate_next",    HotkeyAction::IsolateNext,      "ctrl+PageDown"),
+    ("capture_vu",      HotkeyAction::CaptureVu,        "ctrl+U"),
+    ("quit",            HotkeyAction::Quit,             "ctrl+Q"),
+    ("macro_record_1",  HotkeyAction::MacroRecord(0),   "ctrl+F1"),
+    ("macro_record_2",  HotkeyAction::MacroRecord(1),   "ctrl+F2"),
+    ("macro_record_3",  HotkeyAction::MacroRecord(2),   "ctrl+F3"),
+    ("macro_record_4",  HotkeyAction::MacroRecord(3),   "ctrl+F4"),
+    ("macro_play_1",    HotkeyAction::MacroPlay(0),     "F1"),
+    ("macro_play_2",    HotkeyAction::MacroPlay(1),     "F2"),
+    ("macro_play_3",    HotkeyAction::MacroPlay(2),     "F3"),
+    ("macro_play_4",    HotkeyAction::MacroPlay(3),     "F4"),
+];
+
+const PAD_BUTTON_NAMES: &[(&str, Button)] = &[
+    ("south", Button::South), ("east", Button::East), ("west", Button::West), ("north", Button::North),
+    ("back", Button::Back), ("guide", Button::Guide), ("start", Button::Start),
+    ("leftstick", Button::LeftStick), ("rightstick", Button::RightStick),
+    ("leftshoulder", Button::LeftShoulder), ("rightshoulder", Button::RightShoulder),
+    ("dpup", Button::DPadUp), ("dpdown", Button::DPadDown), ("dpleft", Button::DPadLeft), ("dpright", Button::DPadRight),
+];
+
+const MODBIT_CTRL: u8   = 1;
+const MODBIT_SHIFT: u8  = 2;
+const MODBIT_ALT: u8    = 4;
+
+const MOD_NAMES: &[(&str, u8)] = &[("ctrl", MODBIT_CTRL), ("shift", MODBIT_SHIFT), ("alt", MODBIT_ALT)];
+
+// left & right modifiers count the same, & lock keys don't count at all
+fn mod_bits(keymod: Mod) -> u8 {
+    return
+        if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) { MODBIT_CTRL } else { 0 } |
+        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) { MODBIT_SHIFT } else { 0 } |
+        if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) { MODBIT_ALT } else { 0 };
+}
+
+#[derive(Clone, PartialEq)]
+enum Trigger {
+    Key { mods: u8, key: Keycode },
+    Pad(Vec<Button>),
+}
+
+impl Trigger {
+    fn parse(text: &str) -> Result<Self, String> {
+        if let Some(buttons) = text.strip_prefix("pad:") {
+            let mut combo = Vec::new();
+
+            for name in buttons.split('+').map(str::trim) {
+                let Some((_, button)) = PAD_BUTTON_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) else {
+                    return Err(format!("unknown controller button '{}'", name));
+                };
+                combo.push(*button);
+            }
+
+            return Ok(Trigger::Pad(combo));
+        }
+
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or("");
+        let mut mods = 0;
+
+        for name in parts {
+            let Some((_, bit)) = MOD_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) else {
+                return Err(format!("unknown modifier '{}'", name));
+            };
+            mods |= bit;
+        }
+
+        let Some(key) = Keycode::from_name(key_name) else {
+            return Err(format!("unknown key '{}'", key_name));
+        };
+
+        return Ok(Trigger::Key { mods, key });
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(self: &Self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Key { mods, key } => {
+                for (name, bit) in MOD_NAMES {
+                    if mods & bit != 0 {
+                        write!(f, "{}+", name)?;
+                    }
+                }
+
+                return write!(f, "{}", key.name());
+            }
+            Trigger::Pad(combo) => {
+                let names: Vec<&str> = combo.iter()
+                    .map(|button| PAD_BUTTON_NAMES.iter().find(|(_, b)| b == button).map(|(n, _)| *n).unwrap_or("?"))
+                    .collect();
+
+                return write!(f, "pad:{}", names.join("+"));
+            }
+        }
+    }
+}
+
+pub struct HotkeyMap {
+    bindings: Vec<(HotkeyAction, Trigger)>,
+    pad_held: Vec<Button>,
+}
+
+impl HotkeyMap {
+    pub fn defaults() -> Self {
+        let mut map = Self {
+            bindings: Vec::new(),
+            pad_held: Vec::new(),
+        };
+
+        for (_, action, bindings) in ACTIONS {
+            map.bind(*action, bindings).unwrap();
+        }
+
+        return map;
+    }
+
+    // load bindings, writing out the defaults if there's no file yet so there's something to edit.
+    // actions the file doesn't mention keep their default bindings
+    pub fn load_or_create(path: &Path) -> Result<Self, String> {
+        let mut map = Self::defaults();
+
+        if !path.exists() {
+            map.save(path)?;
+            return Ok(map);
+        }
+
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, bindings)) = line.split_once('=') else {
+                return Err(format!("{}:{}: expected 'action = binding, ...'", path.display(), idx + 1));
+            };
+
+            let Some((_, action, _)) = ACTIONS.iter().find(|(n, _, _)| *n == name.trim()) else {
+                return Err(format!("{}:{}: unknown action '{}'", path.display(), idx + 1, name.trim()));
+            };
+
+            map.bindings.retain(|(a, _)| a != action);
+            map.bind(*action, bindings).map_err(|e| format!("{}:{}: {}", path.display(), idx + 1, e))?;
+        }
+
+        return Ok(map);
+    }
+
+    pub fn save(self: &Self, path: &Path) -> Result<(), String> {
+        let mut text = String::from("# frontend hotkeys - see nyxbox-sdl/src/hotkeys.rs for the format\n");
+
+        for (name, action, _) in ACTIONS {
+            let bindings: Vec<String> = self.bindings.iter().filter(|(a, _)| a == action).map(|(_, t)| t.to_string()).collect();
+            text.push_str(&format!("{} = {}\n", name, bindings.join(", ")));
+        }
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        }
+
+        return fs::write(path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e));
+    }
+
+    fn bind(self: &mut Self, action: HotkeyAction, bindings: &str) -> Result<(), String> {
+        for binding in bindings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            self.bindings.push((action, Trigger::parse(binding)?));
+        }
+
+        return Ok(());
+    }
+
+    // the bindings for an action as the user would type them, e.g. "ctrl+P or pad:back+start"
+    pub fn describe(self: &Self, action: HotkeyAction) -> String {
+        let bindings: Vec<String> = self.bindings.iter().filter(|(a, _)| *a == action).map(|(_, t)| t.to_string()).collect();
+        return if bindings.is_empty() { "(unbound)".to_string() } else { bindings.join(" or ") };
+    }
+
+    // a key that triggers a hotkey is swallowed - it doesn't go on to the guest
+    pub fn key_down(self: &Self, key: Keycode, keymod: Mod) -> Option<HotkeyAction> {
+        let mods = mod_bits(keymod);
+
+        return self.bindings.iter()
+            .find(|(_, t)| *t == Trigger::Key { mods, key })
+            .map(|(a, _)| *a);
+    }
+
+    // fires the biggest combo that this press completes
+    pub fn button_down(self: &mut Self, button: Button) -> Option<HotkeyAction> {
+        if !self.pad_held.contains(&button) {
+            self.pad_held.push(button);
+        }
+
+        return self.bindings.iter()
+            .filter_map(|(a, t)| match t {
+                Trigger::Pad(combo) if combo.contains(&button) && combo.iter().all(|b| self.pad_held.contains(b)) => Some((a, combo.len())),
+                _ => None,
+            })
+            .max_by_key(|(_, len)| *len)
+            .map(|(a, _)| *a);
+    }
+
+    pub fn button_up(self: &mut Self, button: Button) {
+        self.pad_held.retain(|b| *b != button);
+    }
+}
@@ -0,0 +1,167 @@
+use sdl3::{gamepad::Button, keyboard::{Keycode, Mod}};
+
+use nyxbox_core::gamepad::*;
+
+use crate::lang::tr;
+
+pub const MACRO_SLOTS: usize = 4;
+
+// longest macro, in frames - macros are meant for short combos, not whole play sessions
+pub const MACRO_MAX_FRAMES: usize = 600;
+
+fn key_button(key: Keycode) -> Option<u32> {
+    return match key {
+        Keycode::Up => Some(BUTTON_UP),
+        Keycode::Down => Some(BUTTON_DOWN),
+        Keycode::Left => Some(BUTTON_LEFT),
+        Keycode::Right => Some(BUTTON_RIGHT),
+        Keycode::Z => Some(BUTTON_A),
+        Keycode::X => Some(BUTTON_B),
+        Keycode::A => Some(BUTTON_X),
+        Keycode::S => Some(BUTTON_Y),
+        Keycode::Q => Some(BUTTON_L),
+        Keycode::W => Some(BUTTON_R),
+        Keycode::Return => Some(BUTTON_START),
+        Keycode::Tab => Some(BUTTON_SELECT),
+        _ => None,
+    };
+}
+
+fn pad_button(button: Button) -> Option<u32> {
+    return match button {
+        Button::DPadUp => Some(BUTTON_UP),
+        Button::DPadDown => Some(BUTTON_DOWN),
+        Button::DPadLeft => Some(BUTTON_LEFT),
+        Button::DPadRight => Some(BUTTON_RIGHT),
+        Button::South => Some(BUTTON_A),
+        Button::East => Some(BUTTON_B),
+        Button::West => Some(BUTTON_X),
+        Button::North => Some(BUTTON_Y),
+        Button::LeftShoulder => Some(BUTTON_L),
+        Button::RightShoulder => Some(BUTTON_R),
+        Button::Start => Some(BUTTON_START),
+        Button::Back => Some(BUTTON_SELECT),
+        _ => None,
+    };
+}
+
+// parse a comma separated list of button names (e.g. "a,b") into a mask
+pub fn parse_buttons(list: &str) -> Result<u32, String> {
+    let mut mask = 0;
+
+    for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((_, bit)) = BUTTON_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) else {
+            return Err(format!("unknown button '{}'", name));
+        };
+        mask |= bit;
+    }
+
+    return Ok(mask);
+}
+
+// host-side input processing that happens before the gamepad latch: turbo & macros get expanded here, so the guest (& any movie
+// being recorded) only ever sees plain per-frame button states
+//
+// - shift + a button's key toggles turbo for that button
+// - macros are recorded & played back into one of MACRO_SLOTS slots, driven by hotkeys (ctrl + F1-F4 & F1-F4 by default)
+pub struct InputLayer {
+    held: u32,
+    // buttons held down over the control socket, apart from the keyboard & pads so neither lets go of the other's
+    remote: u32,
+    turbo: u32,
+    turbo_rate: u32,
+    macros: [Vec<u32>;MACRO_SLOTS],
+    recording: Option<usize>,
+    playing: Option<(usize, usize)>,
+}
+
+impl InputLayer {
+    pub fn new(turbo: u32, turbo_rate: u32) -> Self {
+        Self {
+            held: 0,
+            remote: 0,
+            turbo,
+            turbo_rate: turbo_rate.max(1),
+            macros: Default::default(),
+            recording: None,
+            playing: None,
+        }
+    }
+
+    // start recording into a slot, or finish recording if it's already the one being recorded
+    pub fn toggle_macro_recording(self: &mut Self, slot: usize) {
+        if self.recording == Some(slot) {
+            self.recording = None;
+            println!("{}", tr!("macro_recorded", slot + 1, self.macros[slot].len()));
+        }
+        else {
+            self.recording = Some(slot);
+            self.macros[slot].clear();
+            println!("{}", tr!("macro_recording", slot + 1));
+        }
+    }
+
+    pub fn play_macro(self: &mut Self, slot: usize) {
+        if !self.macros[slot].is_empty() && self.recording.is_none() {
+            self.playing = Some((slot, 0));
+        }
+    }
+
+    pub fn key_down(self: &mut Self, key: Keycode, keymod: Mod) {
+        if let Some(button) = key_button(key) {
+            if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                self.turbo ^= button;
+            }
+            self.held |= button;
+        }
+    }
+
+    pub fn key_up(self: &mut Self, key: Keycode) {
+        if let Some(button) = key_button(key) {
+            self.held &= !button;
+        }
+    }
+
+    pub fn button_down(self: &mut Self, button: Button) {
+        if let Some(button) = pad_button(button) {
+            self.held |= button;
+        }
+    }
+
+    pub fn button_up(self: &mut Self, button: Button) {
+        if let Some(button) = pad_button(button) {
+            self.held &= !button;
+        }
+    }
+
+    // press & release buttons on behalf of a remote client, which the guest sees from the next frame like any other
+    // input. returns the buttons the client now holds
+    pub fn remote_buttons(self: &mut Self, press: u32, release: u32) -> u32 {
+        self.remote = (self.remote | press) & !release;
+        return self.remote;
+    }
+
+    // produce the button state for the next emulated frame
+    pub fn frame(self: &mut Self, frame: u64) -> u32 {
+        let held = self.held | self.remote;
+        let mut buttons = held;
+
+        // turbo buttons are forced off for every other turbo_rate frames while held
+        if (frame / self.turbo_rate as u64) % 2 == 1 {
+            buttons &= !self.turbo;
+        }
+
+        if let Some((slot, pos)) = self.playing {
+            buttons |= self.macros[slot][pos];
+            self.playing = if pos + 1 < self.macros[slot].len() { Some((slot, pos + 1)) } else { None };
+        }
+
+        if let Some(slot) = self.recording {
+            if self.macros[slot].len() < MACRO_MAX_FRAMES {
+                self.macros[slot].push(held);
+            }
+        }
+
+        return buttons;
+    }
+}
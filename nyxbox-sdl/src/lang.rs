@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fmt::Display, fs, path::{Path, PathBuf}, sync::OnceLock};
+
+// frontend strings are looked up by key. english is built in, & other languages are plain text files under content/lang/<code>.txt:
+//
+//   # comment
+//   startup_failed = Startprüfungen fehlgeschlagen, beende
+//
+// each {} in a string is replaced by the next argument, in order. any key a language file leaves out falls back to english
+pub const LANG_DIR: &str = "content/lang";
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("window_title",            "NyxBox"),
+    ("sdl_init_failed",         "failed to initialize SDL: {}"),
+    ("startup_problems",        "startup checks found problems:"),
+    ("startup_failed",          "startup checks failed, exiting"),
+    ("hint_no_display",         "no usable display - on Linux check that DISPLAY or WAYLAND_DISPLAY is set, or force a driver with SDL_VIDEO_DRIVER"),
+    ("hint_no_window",          "window creation failed - check your display server"),
+    ("hint_no_gpu",             "the VDP needs a Vulkan-capable GPU with compute shader support - try updating your graphics drivers"),
+    ("hint_no_audio",           "no audio device available, continuing without sound"),
+    ("hint_no_gamepad",         "gamepad support unavailable, continuing with keyboard only"),
+    ("no_controllers",          "no controllers connected"),
+    ("hint_no_controllers",     "plug in a controller to use it with the emulator"),
+    ("gamepad_open_failed",     "couldn't open gamepad {}: {}"),
+    ("screenshot_saved",        "screenshot @ frame {}: {}"),
+    ("screenshot_dropped",      "screenshot @ frame {} dropped - the capture queue is full: {}"),
+    ("marker_logged",           "marker @ frame {}: {}"),
+    ("pause_timeout",           "the CPU didn't stop in time - it may still be running"),
+    ("state_saved",             "save state @ frame {}: {}"),
+    ("capture_summary",         "captures: {} written, {} dropped (encoders fell behind), {} failed"),
+    ("vu_capture_stopped",      "the machine is paused or halted, so no frame will run to capture VU output from"),
+    ("vu_capture_timeout",      "no vertex list with that number in the last {} frames"),
+    ("vu_capture_reset",        "the machine was reset or a state was loaded before the VU output was captured"),
+    ("textures_dumped",         "textures @ frame {}: {} dumped to {}"),
+    ("render_debug",            "render debug view: {}"),
+    ("draw_isolation",          "draw isolation: {}"),
+    ("osd_paused",              "paused"),
+    ("osd_resumed",             "resumed"),
+    ("osd_speed",               "speed: {}x"),
+    ("assert_captured",         "assert failed @ frame {}: {} (captured to {})"),
+    ("assert_capture_failed",   "assert failed: {} (capture failed: {})"),
+    ("poison_summary",          "poison: {} read(s) of poisoned memory"),
+    ("buserr_summary",          "bus: {} unmapped access(es), last to {} @ pc {}"),
+    ("vdp_strict_summary",      "VDP strict: {} violation(s)"),
+    ("hw_limit_summary",        "hardware limits exceeded: {}"),
+    ("trace_summary",           "trace: {} entries written to {}"),
+    ("mem_trace_no_output",     "--trace-mem needs somewhere to write to: --trace or --mem-trace-out"),
+    ("movie_load_failed",       "failed to load movie {}: {}"),
+    ("deterministic_needs_budget", "--deterministic needs a CPU budget (--cpu-budget or --cpu-clock other than 0)"),
+    ("movie_save_failed",       "failed to save movie {}: {}"),
+    ("movie_not_deterministic", "recording without --deterministic - the movie may not play back the same way"),
+    ("rtc_load_failed",         "failed to load RTC state: {}"),
+    ("rtc_save_failed",         "failed to save RTC state: {}"),
+    ("control_listen_failed",   "failed to open control socket on {}: {}"),
+    ("dap_listen_failed",       "failed to open debug adapter socket on {}: {}"),
+    ("symbols_loaded",          "symbols: {} loaded from {}"),
+    ("symbols_load_failed",     "symbols: {}, continuing without"),
+    ("lines_loaded",            "line info: {} code ranges loaded from {}"),
+    ("lines_load_failed",       "line info: {}, continuing without"),
+    ("locals_loaded",           "debug info: variables of {} functions loaded from {}"),
+    ("locals_load_failed",      "debug info: {}, continuing without variables"),
+    ("rom_reloading",           "{} changed, reloading"),
+    ("rom_reload_no_file",      "nothing to reload - the ROM didn't come from a file"),
+    ("rom_loading",             "loading {}"),
+    ("machine_reset",           "machine reset"),
+    ("watchdog_reset",          "watchdog expired @ frame {}, resetting"),
+    ("state_loaded",            "save state loaded, continuing from frame {}: {}"),
+    ("no_state_to_load",        "no save state to load in {}"),
+    ("rewound",                 "rewound to frame {} ({} earlier state(s) left, {}MiB)"),
+    ("rewind_empty",            "nothing left to rewind to"),
+    ("rewind_off",              "rewind is off - run with --rewind SECONDS to use it"),
+    ("rewind_movie",            "can't rewind while a movie is recording or playing"),
+    ("guest_fault",             "guest crashed: {}"),
+    ("crash_dumped",            "crash dump written to {}"),
+    ("crash_dump_failed",       "crash dump failed: {}"),
+    ("guest_exited",            "guest exited with code {}"),
+    ("guest_result",            "guest result: {}"),
+    ("guest_halted",            "{} - {} to reset, {} to load the last save state, or drop a ROM file on the window to open it"),
+    ("debug_stop",              "stopped @ frame {}: {} ({} to resume)"),
+    ("guest_halted_title",      "NyxBox - guest stopped"),
+    ("slowdown",                "host can't keep up, guest fell {}s behind real time"),
+    ("adaptive_sync_unavailable", "display can't present without waiting for vsync, falling back to --sync vsync"),
+    ("present_failed",          "present failed: {}"),
+    ("macro_recording",         "recording macro {}"),
+    ("macro_recorded",          "macro {} recorded ({} frames)"),
+    ("lang_load_failed",        "failed to load language '{}': {}"),
+];
+
+static STRINGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// pick the language from an explicit setting, falling back to the host locale (e.g. LANG=de_DE.UTF-8 -> "de")
+pub fn init(lang: Option<&str>) {
+    let (code, explicit) = match lang {
+        Some(code) => (code.to_string(), true),
+        None => (host_language().unwrap_or("en".to_string()), false),
+    };
+
+    let mut strings: HashMap<String, String> = ENGLISH.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+    if code != "en" {
+        match load_file(&lang_path(&code)) {
+            Ok(overrides) => {
+                strings.extend(overrides);
+            }
+            Err(e) => {
+                // a missing file for the host locale just means nobody has translated it yet
+                if explicit {
+                    eprintln!("{}", format_str(&strings["lang_load_failed"], &[&code, &e]));
+                }
+            }
+        }
+    }
+
+    let _ = STRINGS.set(strings);
+}
+
+pub fn lang_path(code: &str) -> PathBuf {
+    return Path::new(LANG_DIR).join(format!("{}.txt", code));
+}
+
+pub fn lookup(key: &str) -> &'static str {
+    let strings = STRINGS.get_or_init(|| ENGLISH.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect());
+
+    return match strings.get(key) {
+        Some(s) => s.as_str(),
+        None => {
+            debug_assert!(false, "missing string '{}'", key);
+            ""
+        }
+    };
+}
+
+pub fn format(key: &str, args: &[&dyn Display]) -> String {
+    return format_str(lookup(key), args);
+}
+
+fn format_str(fmt: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut rest = fmt;
+
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        rest = &rest[pos + 2..];
+    }
+
+    out.push_str(rest);
+    return out;
+}
+
+fn host_language() -> Option<String> {
+    let locale = std::env::var("LC_ALL").ok().filter(|v| !v.is_empty())
+        .or_else(|| std::env::var("LANG").ok())?;
+
+    let code: String = locale.chars().take_while(|c| c.is_ascii_alphabetic()).collect::<String>().to_lowercase();
+    return if code.is_empty() || code == "c" || code == "posix" { None } else { Some(code) };
+}
+
+fn load_file(path: &Path) -> Result<HashMap<String, String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut strings = HashMap::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, val)) = line.split_once('=') else {
+            return Err(format!("{}:{}: expected 'key = text'", path.display(), idx + 1));
+        };
+
+        strings.insert(key.trim().to_string(), val.trim().to_string());
+    }
+
+    return Ok(strings);
+}
+
+// tr!("key") or tr!("key", arg, ...)
+macro_rules! tr {
+    ($key:expr) => {
+        crate::lang::lookup($key)
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        crate::lang::format($key, &[$(&$arg),+])
+    };
+}
+
+pub(crate) use tr;
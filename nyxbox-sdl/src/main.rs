@@ -0,0 +1,1573 @@
+use std::{collections::HashMap, env, ffi::OsString, fs::{self, OpenOptions}, io::{self, BufRead, Write}, path::{Path, PathBuf}, sync::{atomic::Ordering, mpsc, Arc}, thread, time::{Duration, Instant}};
+
+use clap::{error::ErrorKind, Args, Parser, Subcommand};
+use control::ControlServer;
+use debugport::DebugEvent;
+use diagnostics::{CheckStatus, StartupReport};
+use accessibility::{FlashReduction, OsdTheme};
+use card::CardCommand;
+use extract::StateCommand;
+use genregs::GenRegsArgs;
+use events::{EventBus, EventLog, MachineEvent, WindowTitle};
+use excstats::ExceptionMonitor;
+use memfill::MemoryFill;
+use input::InputLayer;
+use hotkeys::{HotkeyAction, HotkeyMap};
+use hwmodel::HardwareModel;
+use breakpoint::{BreakSpec, WatchKind};
+use buserr::UnmappedPolicy;
+use trace::Tracer;
+use chrometrace::{ChromeTrace, TRACK_FRONTEND};
+use fault::FaultMode;
+use framehook::{AssertScript, FrameContext, FrameHook, Golden, GoldenCheck, HookAction};
+use dump::ImageArgs;
+use lang::tr;
+use rewind::Rewind;
+use crashdump::{History, CRASH_DIR, PC_HISTORY_LEN, VDP_CMD_HISTORY_LEN};
+use storage::{SaveStore, StorageLayout};
+use system::{System, SystemConfig};
+use elf::Segment;
+use preload::Preload;
+use machine::{CpuModel, Machine, CPSR_RESET, DEFAULT_CPU_BUDGET};
+use capture::{CaptureOverflow, CaptureWriter};
+use movie::{Movie, MovieCpu, MovieMeta, MOVIEEVENT_END};
+use renderdebug::RenderDebugMode;
+use pacing::{BackgroundMode, FramePacer, SyncMode, TIMESTEP};
+use present::{PresentMode, WindowPresenter};
+use osd::Osd;
+use mem::{Memory, MemoryMap, BOOT_ROM_BEGIN};
+use sdl3::{gpu::{self, Device, ShaderFormat, SwapchainComposition}};
+use vdp::VDP;
+use testrunner::TestArgs;
+use timebase::FRAME_RATE;
+use perf::{PerfArgs, PerfReport, PERF_IDLE_TIMEOUT};
+use bench::Bench;
+use watch::FileWatcher;
+use session::Session;
+
+extern crate sdl3;
+extern crate unicorn_engine;
+
+use nyxbox_core::{mem, machine, inspect, storage, screenshot, pacing, movie, vdp, debugport, failcapture, excstats, memfill, texdump, renderdebug, timebase, capture, hwmodel, breakpoint, trace, chrometrace, fault, buserr, rewind, crashdump, elf, preload, log, system};
+use nyxbox_core::present::{NullPresenter, PresentBackend};
+
+mod control;
+mod dap;
+mod watch;
+mod extract;
+mod diagnostics;
+mod framehook;
+mod testrunner;
+mod present;
+mod card;
+mod dump;
+mod lang;
+mod accessibility;
+mod osd;
+mod input;
+mod hotkeys;
+mod genregs;
+mod events;
+mod perf;
+mod bench;
+mod statecheck;
+mod config;
+mod session;
+
+#[derive(Parser)]
+#[command(version, about, args_override_self = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Boot ROM image or ARM ELF executable to run. Can only be left out for --monitor, or when --bios or --load make up
+    /// the image
+    #[arg(required_unless_present_any = ["monitor", "bios", "load"])]
+    rom: Option<PathBuf>,
+
+    /// Also put a file in memory before boot: a flat binary as file@addr, or an Intel HEX or S-record file at the addresses
+    /// it gives. Can be repeated; later files overwrite earlier ones where they overlap
+    #[arg(long, value_parser = preload::parse_preload)]
+    load: Vec<Preload>,
+
+    /// Boot the built-in monitor (memory peek/poke, disassembler) instead of a ROM, with the terminal connected to the UART
+    #[arg(long, conflicts_with = "rom")]
+    monitor: bool,
+
+    /// Firmware image to put in boot ROM & start from at reset. The ROM, if given, then has to be an ELF executable - its
+    /// segments go in around the firmware, which is left to start it
+    #[arg(long, value_name = "PATH", conflicts_with = "monitor")]
+    bios: Option<PathBuf>,
+
+    /// Settings file to use instead of looking for nyxbox.toml in the working & user config directories
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Forward terminal input to the guest UART, a line at a time
+    #[arg(long)]
+    uart_stdin: bool,
+
+    /// Reload the machine whenever the ROM file changes
+    #[arg(long, requires = "rom")]
+    watch: bool,
+
+    /// Root directory for persistent per-game data
+    #[arg(long, default_value = "saves")]
+    save_dir: PathBuf,
+
+    /// How persistent data is laid out under the save directory
+    #[arg(long, value_enum, default_value_t)]
+    save_layout: StorageLayout,
+
+    /// Listen for JSON-RPC control commands on this address (e.g. 127.0.0.1:5050)
+    #[arg(long)]
+    control: Option<String>,
+
+    /// Listen for Debug Adapter Protocol clients on this address (e.g. 127.0.0.1:4711)
+    #[arg(long)]
+    dap: Option<String>,
+
+    /// Start with the CPU paused, until something resumes it - e.g. a debugger, once it has set its breakpoints
+    #[arg(long)]
+    paused: bool,
+
+    /// Directory for guest-requested screenshots and markers (defaults to "captures")
+    #[arg(long)]
+    capture_dir: Option<PathBuf>,
+
+    /// Don't write a crash dump (to <capture dir>/crashes) when the guest or the emulator crashes, & don't keep the recent
+    /// CPU & VDP history one needs
+    #[arg(long)]
+    no_crash_dumps: bool,
+
+    /// Run frame-by-frame assertions from a script (e.g. "300 pixel 10 10 ff0000ff"), exiting non-zero if any fail
+    #[arg(long)]
+    assert_script: Option<PathBuf>,
+
+    /// Golden-image test: compare the framebuffer at --golden-frame with this PNG & exit, 0 if it matches & 1 if not. A
+    /// mismatch writes the actual frame & a diff image to <capture dir>/golden
+    #[arg(long, requires = "golden_frame", conflicts_with = "golden_hash")]
+    golden: Option<PathBuf>,
+
+    /// Golden test against a framebuffer hash (as printed by any golden run) instead of an image
+    #[arg(long, requires = "golden_frame", value_parser = |s: &str| u32::from_str_radix(s, 16))]
+    golden_hash: Option<u32>,
+
+    /// Frame to run the golden test at
+    #[arg(long)]
+    golden_frame: Option<u64>,
+
+    /// How far a pixel's channels may each be off before it counts as different
+    #[arg(long, default_value_t = 0)]
+    golden_tolerance: u8,
+
+    /// Write the framebuffer to --golden instead of comparing against it
+    #[arg(long, requires = "golden")]
+    update_golden: bool,
+
+    /// Where rendered frames go
+    #[arg(long, value_enum, default_value_t)]
+    present: PresentMode,
+
+    /// Run without a window or swapchain, with the GPU rendering offscreen (e.g. on CI). Frames are only kept with --present
+    /// offscreen
+    #[arg(long)]
+    headless: bool,
+
+    /// Window size, as a multiple of 320x240
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..=16))]
+    scale: u32,
+
+    /// Start with the window fullscreen
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// With --present offscreen, write every Nth frame
+    #[arg(long, default_value_t = 60)]
+    present_interval: u64,
+
+    /// Worker threads encoding captured frames & screenshots
+    #[arg(long, default_value_t = 2)]
+    capture_threads: usize,
+
+    /// Captures that may wait for a worker before the overflow policy applies
+    #[arg(long, default_value_t = 8)]
+    capture_queue: usize,
+
+    /// What to do with new captures while the capture queue is full
+    #[arg(long, value_enum, default_value_t)]
+    capture_overflow: CaptureOverflow,
+
+    /// Damp sudden full-screen brightness changes in presented frames (photosensitivity)
+    #[arg(long, value_enum, default_value_t)]
+    flash_reduction: FlashReduction,
+
+    /// Size of on-screen messages, as a multiple of their normal size in guest pixels
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    ui_scale: u32,
+
+    /// How on-screen messages are drawn over the guest's frame
+    #[arg(long, value_enum, default_value_t)]
+    osd_theme: OsdTheme,
+
+    /// Most emulated frames to run per host frame when catching up - beyond this the guest slows down
+    #[arg(long, default_value_t = 4)]
+    max_catchup: u32,
+
+    /// Most presents in a row that may be skipped while catching up
+    #[arg(long, default_value_t = 0)]
+    frame_skip: u32,
+
+    /// How presented frames are synchronized with the display
+    #[arg(long, value_enum, default_value_t)]
+    sync: SyncMode,
+
+    /// What to do while the window is unfocused
+    #[arg(long, value_enum, default_value_t)]
+    background: BackgroundMode,
+
+    /// While the guest waits in WFI, skip emulated time ahead to its next timer interrupt, & sleep the host until the next
+    /// frame is due when nothing else (vsync) would. Deterministic runs always skip ahead
+    #[arg(long)]
+    idle_skip: bool,
+
+    /// Default per-frame CPU budget in microseconds of emulated time before the guest is told it overran (0 = only flag missed frames)
+    #[arg(long, default_value_t = 0)]
+    frame_budget: u32,
+
+    /// Diagnostic levels, for all subsystems or one (cpu, mem, vdp, uart, clock, hw): off, error, warn, info, debug, or
+    /// trace, e.g. "warn,vdp=debug" (applied in order; info by default)
+    #[arg(long, value_name = "LEVELS", value_delimiter = ',', value_parser = log::parse_level)]
+    log: Vec<(Option<log::Subsystem>, log::Level)>,
+
+    /// Fit the 16MiB expansion RAM (mapped at 0x2000000)
+    #[arg(long)]
+    expansion_ram: bool,
+
+    /// Lay memory & peripherals out as described in this TOML file instead of the standard layout
+    #[arg(long)]
+    memory_map: Option<PathBuf>,
+
+    /// Main RAM size, in bytes or with a K/M suffix (default 16M, overrides --memory-map)
+    #[arg(long, value_parser = mem::parse_size)]
+    ram_size: Option<usize>,
+
+    /// Boot ROM size, in bytes or with a K/M suffix (default 4M, overrides --memory-map)
+    #[arg(long, value_parser = mem::parse_size)]
+    rom_size: Option<usize>,
+
+    /// What RAM & VRAM hold at power-on (random patterns derive from --seed)
+    #[arg(long, value_enum, default_value_t)]
+    boot_fill: MemoryFill,
+
+    /// Poison main RAM until the guest writes it, & report reads of poisoned memory with the guest PC. Guest allocators can re-poison freed blocks through the poison port
+    #[arg(long, conflicts_with = "boot_fill")]
+    poison: bool,
+
+    /// Render debug view to start in (the render_debug hotkey cycles through them)
+    #[arg(long, value_enum, default_value_t)]
+    render_debug: RenderDebugMode,
+
+    /// Check VDP register writes against the documented invariants & log violations with the guest PC responsible
+    #[arg(long)]
+    vdp_strict: bool,
+
+    /// How strictly the machine holds the guest to the hardware's limits (command FIFO depth, DMA bandwidth, UART FIFO, MMIO alignment)
+    #[arg(long, value_enum, default_value_t)]
+    hw_model: HardwareModel,
+
+    /// Skip VDP command queue validation for speed (bad queues wrap around VRAM instead of raising an address error)
+    #[arg(long)]
+    vdp_unchecked: bool,
+
+    /// Buttons that start with turbo enabled (comma separated, e.g. "a,b"). Shift + a button's key toggles turbo at runtime
+    #[arg(long, default_value = "")]
+    turbo: String,
+
+    /// Frames per turbo half-cycle (a turbo button is pressed for N frames, then released for N)
+    #[arg(long, default_value_t = 2)]
+    turbo_rate: u32,
+
+    /// Keep this many seconds of save states for the rewind hotkey to step back through (0 = off)
+    #[arg(long, default_value_t = 0)]
+    rewind: u64,
+
+    /// Frames between rewind states. Smaller steps cost more memory & host time
+    #[arg(long, default_value_t = 30)]
+    rewind_interval: u64,
+
+    /// Hotkey bindings file (created with the defaults if it doesn't exist). Defaults to hotkeys.txt in the working directory if there is one, or else in the user config directory
+    #[arg(long)]
+    hotkeys: Option<PathBuf>,
+
+    /// Seed exposed to the guest through the system info block (defaults to the current time)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Record input to a movie file
+    #[arg(long, conflicts_with = "play")]
+    record: Option<PathBuf>,
+
+    /// Play back input from a movie file
+    #[arg(long)]
+    play: Option<PathBuf>,
+
+    /// Replay the --play movie as fast as possible with the CPU & frontend in lockstep, exit at its end, & write per-subsystem timings here as JSON
+    #[arg(long, requires = "play")]
+    perf_report: Option<PathBuf>,
+
+    /// Run the ROM as fast as possible with the CPU & frontend in lockstep for this many seconds, then exit & print emulated
+    /// instructions/s, VDP work per frame, GPU dispatches, & host frame time percentiles as JSON
+    #[arg(long, value_name = "SECONDS", conflicts_with = "perf_report")]
+    bench: Option<f64>,
+
+    /// Frontend language (loads content/lang/<LANG>.txt; defaults to the host locale, falling back to English)
+    #[arg(long)]
+    lang: Option<String>,
+
+    /// Print peripheral lock contention statistics on exit
+    #[arg(long)]
+    lock_stats: bool,
+
+    /// Print machine events (start, pause, ROM loads, guest exit) as they happen
+    #[arg(long)]
+    log_events: bool,
+
+    /// Print BIOS call counts & how long each kept the CPU parked, on exit
+    #[arg(long)]
+    bios_stats: bool,
+
+    /// Print guest exception rates (SWIs by number, IRQs, aborts, undefined instructions) every second, & totals on exit
+    #[arg(long)]
+    exception_stats: bool,
+
+    /// Pause before the instruction at this address runs: ADDR [after N] [once] [if COND], e.g. "0x1000400 if r0 == 5"
+    /// (repeatable). after N lets N hits through first, once removes it after it stops, & COND compares registers (r0-r12,
+    /// sp, lr, pc, cpsr), memory ([ADDR], half[ADDR], byte[ADDR]), & numbers with == != < <= > >= && ||. Resume with the
+    /// pause hotkey or a remote client
+    #[arg(long = "break", value_parser = breakpoint::parse_break)]
+    breakpoint: Vec<BreakSpec>,
+
+    /// Pause after the guest accesses this memory: ADDR[:LEN[:r|w|rw]], LEN defaulting to 4 & the kind to rw (repeatable)
+    #[arg(long, value_parser = breakpoint::parse_watch)]
+    watchpoint: Vec<(u32, u32, WatchKind)>,
+
+    /// Instructions the CPU may run per frame before it has to wait for the next one, WFI or not (0 = unlimited)
+    #[arg(long, default_value_t = DEFAULT_CPU_BUDGET)]
+    cpu_budget: u64,
+
+    /// Emulated CPU clock in MHz, as an alternative to --cpu-budget (one instruction per cycle)
+    #[arg(long, conflicts_with = "cpu_budget")]
+    cpu_clock: Option<u32>,
+
+    /// Derive every guest-visible time from the CPU's instruction count instead of the host clock, so the same ROM & inputs
+    /// always behave the same (needs a CPU budget; fixes the seed & RTC unless --seed is given)
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Run this many frames ahead with the latest input & show the last of them, putting the machine back afterwards, so
+    /// the guest's response to input reaches the screen that many frames sooner. Each frame then costs that many more to
+    /// emulate, plus a save state round trip (needs --deterministic)
+    #[arg(long, default_value_t = 0, requires = "deterministic")]
+    run_ahead: u32,
+
+    /// CPU core to emulate
+    #[arg(long, value_enum, default_value_t)]
+    cpu_model: CpuModel,
+
+    /// Fit a second core of the same model as a coprocessor, sharing memory with the main CPU & talking to it through the
+    /// mailbox. It runs freely rather than to the CPU budget, so it can't be used in deterministic runs
+    #[arg(long, conflicts_with = "deterministic")]
+    coprocessor: bool,
+
+    /// What a guest bad memory access or undefined instruction does
+    #[arg(long, value_enum, default_value_t)]
+    guest_faults: FaultMode,
+
+    /// What an access to an address nothing is mapped at does. Either way it's latched in the bus error registers
+    #[arg(long, value_enum, default_value_t)]
+    unmapped: UnmappedPolicy,
+
+    /// What open-bus reads return, per byte lane, with --unmapped open-bus
+    #[arg(long, value_parser = inspect::parse_addr, default_value_t = 0)]
+    open_bus_value: u32,
+
+    /// Start executing here on reset instead of at the reset vector (or an ELF's entry point). Set bit 0 to start in Thumb state
+    #[arg(long, value_parser = inspect::parse_addr)]
+    entry: Option<u32>,
+
+    /// Stack pointer on reset
+    #[arg(long, value_parser = inspect::parse_addr, default_value_t = 0)]
+    initial_sp: u32,
+
+    /// CPSR on reset (mode, interrupt masks, & T bit)
+    #[arg(long, value_parser = inspect::parse_addr, default_value_t = CPSR_RESET as u32)]
+    initial_cpsr: u32,
+
+    /// ELF with the ROM's symbols, so traces, fault dumps, & other diagnostics show function names (& its line info, if
+    /// built with -g, for --dap source breakpoints)
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+
+    /// Log the PC, opcode, & disassembly of every executed instruction to this file
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// Only trace instructions in this address range: START-END, inclusive (repeatable)
+    #[arg(long, requires = "trace", value_parser = trace::parse_range)]
+    trace_range: Vec<(u32, u32)>,
+
+    /// Log every guest read & write in this address range (START-END, inclusive, repeatable) with its value, size, & PC.
+    /// Goes into the --trace file, interleaved with the instructions, or to --mem-trace-out
+    #[arg(long, value_parser = trace::parse_range)]
+    trace_mem: Vec<(u32, u32)>,
+
+    /// Write memory access tracing to this file rather than the instruction trace
+    #[arg(long)]
+    mem_trace_out: Option<PathBuf>,
+
+    /// Write a timeline of CPU slices, WFI waits, VDP command queues, GPU submission, & interrupts to this file on exit, for
+    /// chrome://tracing or Perfetto
+    #[arg(long, value_name = "PATH")]
+    chrome_trace: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the emulator (default)
+    Run(RunArgs),
+    /// Hexdump a range of the unified guest address space
+    Dump {
+        #[command(flatten)]
+        images: ImageArgs,
+
+        /// Start address (decimal or 0x-prefixed hex)
+        #[arg(value_parser = inspect::parse_addr)]
+        addr: u32,
+
+        /// Number of bytes to dump
+        #[arg(default_value_t = 256)]
+        len: usize,
+    },
+    /// Search the unified guest address space for a byte pattern
+    Find {
+        #[command(flatten)]
+        images: ImageArgs,
+
+        /// Hex byte pattern (e.g. "deadbeef" or "de ad be ef")
+        pattern: String,
+
+        /// Treat the pattern as literal text instead of hex bytes
+        #[arg(long)]
+        text: bool,
+    },
+    /// Extract data from a save state
+    #[command(subcommand)]
+    State(StateCommand),
+    /// Run a directory of guest tests in parallel emulator instances
+    Test(TestArgs),
+    /// Manage save slots in a memory card image
+    #[command(subcommand)]
+    Card(CardCommand),
+    /// Generate the guest SDK's MMIO register bindings from the emulator's constants
+    GenRegs(GenRegsArgs),
+    /// Replay a directory of input movies headlessly & fail if any got slower than the stored baseline
+    Perf(PerfArgs),
+    /// Debug adapter on stdin & stdout, for DAP clients that start one to launch the program (like VS Code's "launch"
+    /// configurations) rather than attaching to a --dap port
+    Dap,
+}
+
+fn handle_debug_event(ev: DebugEvent, frame: u64, capture_dir: &Path, capture: &CaptureWriter, vdp: &mut VDP, gfx_device: &Device) -> Result<(), String> {
+    fs::create_dir_all(capture_dir).map_err(|e| format!("failed to create {}: {}", capture_dir.display(), e))?;
+
+    match ev {
+        DebugEvent::Screenshot { name } => {
+            let name = failcapture::sanitize_name(&name);
+            let name = if name.is_empty() { format!("frame{:08}", frame) } else { name };
+
+            let path = capture_dir.join(format!("{}.png", name));
+            if screenshot::queue_framebuffer(vdp, gfx_device, capture, &path)? {
+                println!("{}", tr!("screenshot_saved", frame, path.display()));
+            }
+            else {
+                println!("{}", tr!("screenshot_dropped", frame, path.display()));
+            }
+        }
+        DebugEvent::Marker { name } => {
+            let path = capture_dir.join("markers.txt");
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)
+                .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+
+            writeln!(file, "{}\t{}", frame, name).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+
+            println!("{}", tr!("marker_logged", frame, name));
+        }
+        DebugEvent::Exit { .. } | DebugEvent::Assert { .. } => {
+            // handled by the frontend loop, which owns the machine
+        }
+    }
+
+    return Ok(());
+}
+
+pub fn main() {
+    let mut argv: Vec<OsString> = env::args_os().collect();
+
+    // a bare `nyxbox` is `nyxbox run`, parsed like any other so it gets the same defaults
+    if argv.len() == 1 {
+        argv.push(OsString::from("run"));
+    }
+
+    // config file settings go in ahead of the run command's own flags, so the command line overrides them
+    if argv[1] == "run" {
+        if let Some(path) = config::find(config::flag(&argv[2..])) {
+            let args = config::run_args(&path).and_then(|args| {
+                // checked on their own first, so a bad value is blamed on the file. the ROM comes from the command line,
+                // so it's no fault of the file's that it's missing
+                return match Cli::try_parse_from(["nyxbox", "run"].map(OsString::from).into_iter().chain(args.iter().cloned())) {
+                    Ok(_) => Ok(args),
+                    Err(e) if e.kind() == ErrorKind::MissingRequiredArgument => Ok(args),
+                    Err(e) => Err(format!("{}: {}", path.display(), e.to_string().lines().next().unwrap_or(""))),
+                };
+            });
+
+            match args {
+                Ok(args) => {
+                    argv.splice(2..2, args);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    let cli = Cli::parse_from(argv);
+
+    match cli.command {
+        Command::Run(args) => {
+            run(&args);
+        }
+        Command::Dump { images, addr, len } => {
+            dump::dump_cmd(&images, addr, len);
+        }
+        Command::Find { images, pattern, text } => {
+            dump::find_cmd(&images, &pattern, text);
+        }
+        Command::State(cmd) => {
+            extract::state_cmd(&cmd);
+        }
+        Command::Test(args) => {
+            testrunner::test_cmd(&args);
+        }
+        Command::Card(cmd) => {
+            card::card_cmd(&cmd);
+        }
+        Command::GenRegs(args) => {
+            genregs::gen_regs_cmd(&args);
+        }
+        Command::Perf(args) => {
+            perf::perf_cmd(&args);
+        }
+        Command::Dap => {
+            dap::adapter_cmd();
+        }
+    }
+}
+
+// monitor guest program, built from guest-src/monitor.s by build-monitor.sh
+const MONITOR_ROM: &str = "content/monitor.bin";
+
+// read terminal input on a background thread, so the frontend can pick it up without blocking
+fn spawn_stdin_reader() -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+
+            match stdin.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => {
+                    break;
+                }
+                Ok(_) => {
+                    if tx.send(line.clone()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    return rx;
+}
+
+// what --scale multiplies
+const WINDOW_BASE_SIZE: (u32, u32) = (320, 240);
+
+// persistent storage entry names
+const SAVE_RTC: &str = "rtc.bin";
+
+// how many frames a control client's VU capture waits for its vertex list to turn up before giving up - lists are
+// numbered per frame, so one that isn't there by now most likely doesn't exist
+const VU_CAPTURE_TIMEOUT_FRAMES: u64 = 120;
+
+fn read_rom(path: &Path, map: &MemoryMap) -> Result<Vec<u8>, String> {
+    let rom = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+    if elf::is_elf(&rom) {
+        elf::parse(&rom, map).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    else if rom.len() > map.boot_rom_size {
+        return Err(format!("{} is too large for boot ROM ({} bytes, max {})", path.display(), rom.len(), map.boot_rom_size));
+    }
+
+    return Ok(rom);
+}
+
+// a flat image goes at the bottom of boot ROM & starts at the reset vector, an ELF's segments go wherever they're linked
+// & it starts at its entry point - unless --entry says otherwise. with a --bios image, that's what goes in boot ROM & starts,
+// & an ELF's segments go in around it. --load files go in on top. takes effect on the next reset
+fn load_image(machine: &mut Machine<'_>, map: &MemoryMap, args: &RunArgs, bios: Option<&[u8]>, data: &[u8], preloads: &[Segment]) -> Result<(), String> {
+    let entry = if let Some(bios) = bios {
+        machine.load_rom(bios);
+
+        if elf::is_elf(data) {
+            machine.load_segments(&elf::parse(data, map)?.segments)?;
+        }
+        else if !data.is_empty() {
+            return Err("a flat ROM image needs boot ROM to itself - use an ELF executable with --bios".to_string());
+        }
+
+        BOOT_ROM_BEGIN as u32
+    }
+    else if elf::is_elf(data) {
+        let exe = elf::parse(data, map)?;
+        machine.load_elf(&exe)?;
+        exe.entry
+    }
+    else {
+        machine.load_rom(data);
+        BOOT_ROM_BEGIN as u32
+    };
+
+    machine.load_segments(preloads)?;
+
+    machine.set_boot_state(args.entry.unwrap_or(entry), args.initial_sp, args.initial_cpsr);
+    return Ok(());
+}
+
+fn run(args: &RunArgs) {
+    lang::init(args.lang.as_deref());
+    log::apply_levels(&args.log);
+
+    // everything sized or placed by the memory map is built to this one, so it has to be settled first
+    let memory_map = args.memory_map.as_deref().map_or(Ok(MemoryMap::default()), MemoryMap::load).and_then(|mut map| {
+        map.main_ram_size = args.ram_size.unwrap_or(map.main_ram_size);
+        map.boot_rom_size = args.rom_size.unwrap_or(map.boot_rom_size);
+        map.validate()?;
+        return Ok(map);
+    }).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let sdl_context = sdl3::init().unwrap_or_else(|e| {
+        eprintln!("{}", tr!("sdl_init_failed", e));
+        std::process::exit(1);
+    });
+
+    // validate the host environment up front, so the user gets a list of what's wrong & how to fix it rather than a panic
+    let mut report = StartupReport::new();
+
+    // a headless run has no window to present to, so frames go nowhere unless they're being written out
+    let present = if args.headless && args.present == PresentMode::Window { PresentMode::None } else { args.present };
+
+    let video_sys = if args.headless { None } else { report.require("video", sdl_context.video(), tr!("hint_no_display")) };
+
+    // otherwise the GPU device still needs a window to claim, even when nothing gets shown in it
+    let window = video_sys.as_ref().and_then(|video_sys| {
+        let (width, height) = WINDOW_BASE_SIZE;
+        let mut builder = video_sys.window(tr!("window_title"), width * args.scale, height * args.scale);
+        builder.position_centered();
+
+        if args.present != PresentMode::Window {
+            builder.hidden();
+        }
+        else if args.fullscreen {
+            builder.fullscreen();
+        }
+
+        report.require("window", builder.build(), tr!("hint_no_window"))
+    });
+
+    let graphics_device = if args.headless {
+        report.require("GPU device", Device::new(ShaderFormat::SpirV, false), tr!("hint_no_gpu"))
+    }
+    else {
+        window.as_ref().and_then(|window| report.require("GPU device",
+            Device::new(ShaderFormat::SpirV, false).and_then(|d| d.with_window(window)),
+            tr!("hint_no_gpu")))
+    };
+
+    report.check_shaders(vdp::SHADER_PATHS);
+
+    if args.present == PresentMode::Window {
+        report.check_shaders(present::SHADER_PATHS);
+    }
+
+    report.optional("audio", sdl_context.audio(), tr!("hint_no_audio"));
+
+    // kept for the whole run - SDL only sends button events for gamepads that are open, through a live subsystem
+    let gamepad_sys = report.optional("gamepad", sdl_context.gamepad(), tr!("hint_no_gamepad"));
+
+    if let Some(gamepad_sys) = &gamepad_sys {
+        if gamepad_sys.gamepads().map(|v| v.is_empty()).unwrap_or(true) {
+            report.push("gamepad", CheckStatus::Warn, tr!("no_controllers").to_string(), Some(tr!("hint_no_controllers")));
+        }
+    }
+
+    // nothing is printed when every check passed
+    if report.has_problems() {
+        eprintln!("{}", tr!("startup_problems"));
+        report.print(false);
+    }
+
+    if report.has_failures() {
+        eprintln!("{}", tr!("startup_failed"));
+        std::process::exit(1);
+    }
+
+    let graphics_device = graphics_device.unwrap();
+
+    // adaptive sync: the swapchain stops waiting on vblank & the frame pacer times presents instead, so a VRR display
+    // refreshes whenever a frame is ready. immediate is preferred as it never holds a frame back - mailbox still works,
+    // but may show a frame up to one refresh late
+    let adaptive_sync = args.sync == SyncMode::Adaptive && present == PresentMode::Window && {
+        let window = window.as_ref().unwrap();
+        let mode = [gpu::PresentMode::Immediate, gpu::PresentMode::Mailbox].into_iter()
+            .find(|mode| graphics_device.window_supports_present_mode(window, *mode));
+
+        match mode {
+            Some(mode) if graphics_device.set_swapchain_parameters(window, SwapchainComposition::Sdr, mode) => true,
+            _ => {
+                println!("{}", tr!("adaptive_sync_unavailable"));
+                false
+            }
+        }
+    };
+
+    let mut event_pump = sdl_context.event_pump().unwrap();
+
+    let mut mem = Memory::new(&memory_map, args.expansion_ram);
+
+    // the monitor is just another ROM, but it's only useful if you can type at it
+    let rom_path = if args.monitor { Some(PathBuf::from(MONITOR_ROM)) } else { args.rom.clone() };
+
+    let rom = match &rom_path {
+        Some(path) => read_rom(path, &memory_map).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        // a BIOS or --load files make up the whole image by themselves
+        None => Vec::new(),
+    };
+
+    let bios = args.bios.as_ref().map(|path| match read_rom(path, &memory_map) {
+        Ok(bios) if elf::is_elf(&bios) => {
+            eprintln!("{}: the BIOS has to be a flat boot ROM image", path.display());
+            std::process::exit(1);
+        }
+        Ok(bios) => bios,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    });
+
+    // read once up front - reloading the ROM puts the same contents back
+    let preloads: Vec<Segment> = args.load.iter()
+        .map(|preload| preload.read(&memory_map))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+        .into_iter().flatten().collect();
+
+    let cpu_budget = args.cpu_clock.map_or(args.cpu_budget, |mhz| (mhz as f64 * 1e6 * TIMESTEP) as u64);
+
+    if args.deterministic && cpu_budget == 0 {
+        eprintln!("{}", tr!("deterministic_needs_budget"));
+        std::process::exit(1);
+    }
+
+    let config = SystemConfig {
+        memory_map,
+        cpu_model: args.cpu_model,
+        cpu_budget,
+        deterministic: args.deterministic,
+        idle_skip: args.idle_skip,
+        expansion_ram: args.expansion_ram,
+        coprocessor: args.coprocessor,
+        poison: args.poison,
+        hw_model: args.hw_model,
+        frame_budget: args.frame_budget,
+        open_bus_value: args.open_bus_value,
+        fault_mode: args.guest_faults,
+        unmapped: args.unmapped,
+        boot_fill: args.boot_fill,
+        vdp_strict: args.vdp_strict,
+        vdp_unchecked: args.vdp_unchecked,
+        render_debug: args.render_debug,
+    };
+    let features = config.features();
+
+    // movies carry their own seed, & have to match the machine they're played on
+    let movie_cpu = MovieCpu { model: args.cpu_model, budget: cpu_budget };
+    let playback = args.play.as_ref().map(|path| {
+        let movie = Movie::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", tr!("movie_load_failed", path.display(), e));
+            std::process::exit(1);
+        });
+
+        if let Err(e) = movie.meta.check_playback(&MovieMeta::new(&rom, features, movie_cpu, args.boot_fill, &memory_map, movie.meta.seed)) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+
+        movie
+    });
+
+    let seed = match &playback {
+        Some(movie) => movie.meta.seed,
+        None => args.seed.unwrap_or(if args.deterministic { 0 } else { chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64 }),
+    };
+
+    let mut recording = args.record.as_ref().map(|_| Movie::new(MovieMeta::new(&rom, features, movie_cpu, args.boot_fill, &memory_map, seed)));
+
+    // without a deterministic CPU, when an input lands depends on how fast the host ran
+    if recording.is_some() && !args.deterministic {
+        println!("{}", tr!("movie_not_deterministic"));
+    }
+
+    let mut system = System::new(&config, seed, &mut mem, &graphics_device, io::stdout()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    if let Some(path) = &args.symbols {
+        match system.symbols.load(path) {
+            Ok(count) => println!("{}", tr!("symbols_loaded", count, path.display())),
+            Err(e) => println!("{}", tr!("symbols_load_failed", e)),
+        }
+
+        // source lines & variables, for a debug adapter to put breakpoints on & show - an image built without -g just
+        // doesn't have any
+        match system.lines.load(path) {
+            Ok(0) => {
+            }
+            Ok(count) => println!("{}", tr!("lines_loaded", count, path.display())),
+            Err(e) => println!("{}", tr!("lines_load_failed", e)),
+        }
+
+        match system.locals.load(path) {
+            Ok(0) => {
+            }
+            Ok(count) => println!("{}", tr!("locals_loaded", count, path.display())),
+            Err(e) => println!("{}", tr!("locals_load_failed", e)),
+        }
+    }
+
+    // restore persistent state
+    let save_store = SaveStore::new(&args.save_dir, &storage::game_id_for(rom_path.as_deref()), args.save_layout);
+
+    // a deterministic RTC always starts from the epoch
+    match save_store.read(SAVE_RTC).map(|data| data.filter(|_| !args.deterministic)) {
+        Ok(Some(data)) if data.len() == 8 => {
+            system.clock.set_rtc_host_offset(i64::from_le_bytes(data.try_into().unwrap()));
+        }
+        Ok(_) => {
+        }
+        Err(e) => {
+            println!("{}", tr!("rtc_load_failed", e));
+        }
+    }
+
+    // a movie plays back against the RTC it was recorded with, so it sees the same dates
+    if let Some(start) = playback.as_ref().and_then(|movie| movie.meta.rtc_start) {
+        system.clock.set_rtc_start(start);
+    }
+
+    if let Some(movie) = &mut recording {
+        movie.meta.rtc_start = Some(system.clock.rtc_start());
+    }
+
+    // frontend features hang off the event bus rather than the main loop
+    let mut events = EventBus::new();
+
+    if args.log_events {
+        events.subscribe(Box::new(EventLog));
+    }
+
+    if let Some(window) = &window {
+        events.subscribe(Box::new(WindowTitle::new(window.clone())));
+    }
+
+    let exception_stats = system.machine.exception_stats();
+    let bios_stats = system.machine.bios();
+
+    for spec in &args.breakpoint {
+        system.machine.add_breakpoint(spec.clone());
+    }
+
+    for (addr, len, kind) in &args.watchpoint {
+        system.machine.add_watchpoint(*addr, *len, *kind);
+    }
+
+    let tracer = args.trace.as_ref().map(|path| Arc::new(Tracer::create(path, system.symbols.clone()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })));
+
+    if let Some(tracer) = &tracer {
+        system.machine.set_tracer(tracer.clone(), &args.trace_range);
+    }
+
+    let mem_tracer = match &args.mem_trace_out {
+        Some(path) => Some(Arc::new(Tracer::create(path, system.symbols.clone()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }))),
+        None => tracer.clone(),
+    };
+
+    if !args.trace_mem.is_empty() {
+        let Some(mem_tracer) = &mem_tracer else {
+            eprintln!("{}", tr!("mem_trace_no_output"));
+            std::process::exit(1);
+        };
+
+        for (start, end) in &args.trace_mem {
+            let mem_tracer = mem_tracer.clone();
+            system.machine.trace_memory(*start, *end, move |access| mem_tracer.record_mem(access));
+        }
+    }
+
+    let chrome_trace = args.chrome_trace.as_ref().map(|_| Arc::new(ChromeTrace::new()));
+
+    if let Some(chrome_trace) = &chrome_trace {
+        system.set_chrome_trace(chrome_trace.clone());
+    }
+
+    if args.bench.is_some() {
+        system.machine.count_instructions();
+    }
+
+    // after the power-on fill, so an ELF's initialized data isn't overwritten
+    if let Err(e) = load_image(&mut system.machine, &system.map, args, bios.as_deref(), &rom, &preloads) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let capture_dir = args.capture_dir.clone().unwrap_or(PathBuf::from("captures"));
+
+    // the history a crash dump reports is kept all along, as there's no knowing when it'll be needed
+    if !args.no_crash_dumps {
+        let pcs = Arc::new(History::new(PC_HISTORY_LEN));
+        let vdp_cmds = Arc::new(History::new(VDP_CMD_HISTORY_LEN));
+
+        system.machine.set_pc_history(pcs.clone());
+        system.vdp.set_cmd_history(vdp_cmds.clone());
+        crashdump::install(&capture_dir.join(CRASH_DIR), pcs, vdp_cmds, system.machine.probe(), system.timebase.clone(),
+            system.map, system.symbols.clone(), args.expansion_ram);
+    }
+
+    // start running the CPU
+    let mut run_ctx = if args.paused { system.power_on_paused() } else { system.power_on() };
+
+    events.publish(0, MachineEvent::RomLoaded { path: rom_path.clone() });
+
+    if args.paused {
+        events.publish(0, MachineEvent::Paused);
+    }
+    events.publish(0, MachineEvent::Started);
+
+    let mut rom_watcher = rom_path.as_ref().filter(|_| args.watch).map(|path| FileWatcher::new(path));
+
+    let control = ControlServer::new(system.map);
+
+    if let Some(addr) = &args.control {
+        control.listen_jsonrpc(addr).unwrap_or_else(|e| {
+            eprintln!("{}", tr!("control_listen_failed", addr, e));
+            std::process::exit(1);
+        });
+    }
+
+    if let Some(addr) = &args.dap {
+        let stops = dap::listen(addr, control.sender(), system.symbols.clone(), system.lines.clone(), system.locals.clone()).unwrap_or_else(|e| {
+            eprintln!("{}", tr!("dap_listen_failed", addr, e));
+            std::process::exit(1);
+        });
+        events.subscribe(Box::new(stops));
+    }
+
+    let mut prev_tick = sdl3::timer::performance_counter();
+
+    let capture = Arc::new(CaptureWriter::new(args.capture_threads, args.capture_queue, args.capture_overflow));
+
+    // hotkey feedback goes to the terminal, & over the frame when there's a window
+    let osd = Osd::new(args.ui_scale, args.osd_theme);
+
+    let mut presenter: Box<dyn PresentBackend> = match present {
+        PresentMode::Window => Box::new(WindowPresenter::new(window.as_ref().unwrap(), &graphics_device).with_flash_reduction(args.flash_reduction).with_osd(osd.clone())),
+        PresentMode::Offscreen => Box::new(present::offscreen_presenter(capture_dir.join("frames"), args.present_interval, capture.clone(), args.flash_reduction)),
+        PresentMode::None => Box::new(NullPresenter),
+    };
+
+    let mut frame_hooks: Vec<Box<dyn FrameHook>> = Vec::new();
+    let mut last_hook_frame = 0;
+    let mut exit_code = 0;
+
+    let golden = match (&args.golden, args.golden_hash) {
+        (Some(path), _) => Some(Golden::Image(path.clone())),
+        (None, Some(hash)) => Some(Golden::Hash(hash)),
+        (None, None) => None,
+    };
+
+    if let (Some(golden), Some(frame)) = (golden, args.golden_frame) {
+        frame_hooks.push(Box::new(GoldenCheck::new(frame, golden, args.golden_tolerance, args.update_golden, capture_dir.join("golden"))));
+    }
+
+    if let Some(path) = &args.assert_script {
+        match AssertScript::load(path) {
+            Ok(script) => {
+                frame_hooks.push(Box::new(script));
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let turbo = input::parse_buttons(&args.turbo).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let stdin_input = if args.uart_stdin || args.monitor { Some(spawn_stdin_reader()) } else { None };
+
+    let hotkeys = match args.hotkeys.clone().or_else(hotkeys::default_path) {
+        Some(path) => HotkeyMap::load_or_create(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => HotkeyMap::defaults(),
+    };
+
+    let mut session = Session {
+        args,
+        gfx_device: &graphics_device,
+        capture_dir: capture_dir.clone(),
+        capture: capture.clone(),
+        events,
+        osd,
+        input: InputLayer::new(turbo, args.turbo_rate),
+        hotkeys,
+        pacer: FramePacer::new(args.max_catchup, args.frame_skip),
+        exception_monitor: ExceptionMonitor::new(),
+        rewind: (args.rewind > 0).then(|| Rewind::new(args.rewind_interval, (args.rewind * FRAME_RATE / args.rewind_interval.max(1)) as usize + 1)),
+        recording,
+        playback,
+        halted: None,
+        reboot: None,
+        rom,
+        loaded_rom_path: rom_path.clone(),
+        vu_waiters: Vec::new(),
+        texture_dump_armed: false,
+        focused: true,
+        background_paused: false,
+        uart_pending: Vec::new(),
+        last_stop: None,
+        last_buttons: 0,
+        gamepad_sys,
+        open_gamepads: HashMap::new(),
+    };
+
+    // every gamepad connected now, & any plugged in later
+    for id in session.gamepad_sys.as_ref().and_then(|gamepad_sys| gamepad_sys.gamepads().ok()).unwrap_or_default() {
+        session.open_gamepad(id);
+    }
+
+    let mut captured_textures = None;
+
+    // perf runs replay a movie one frame per loop, as fast as the host allows, & stop where the recording did
+    let perf_end = match (&args.perf_report, &session.playback) {
+        (Some(_), Some(movie)) => Some(movie.length()),
+        _ => None,
+    };
+    let perf_start = Instant::now();
+
+    // benchmarks run in lockstep too, for a set time instead of to the end of a movie
+    let lockstep = perf_end.is_some() || args.bench.is_some();
+    let instructions = system.machine.instruction_counter();
+    let mut bench = args.bench.map(|secs| Bench::new(secs, instructions.load(Ordering::Relaxed)));
+    let mut perf_vdp = Duration::ZERO;
+    let mut perf_present = Duration::ZERO;
+    let mut perf_gpu = Duration::ZERO;
+
+    'running: loop {
+        let Some(actions) = session.poll_events(&mut event_pump, &system, &run_ctx) else {
+            break 'running;
+        };
+
+        for action in actions {
+            if action == HotkeyAction::Quit {
+                break 'running;
+            }
+
+            run_ctx = session.hotkey(action, &mut system, run_ctx);
+        }
+
+        // reload the machine if the guest binary was rebuilt
+        if let Some(watcher) = &mut rom_watcher {
+            if watcher.poll() {
+                match read_rom(watcher.path(), &system.map) {
+                    Ok(data) => {
+                        println!("{}", tr!("rom_reloading", watcher.path().display()));
+                        session.reboot = Some((data, Some(watcher.path().to_path_buf())));
+                    }
+                    Err(e) => {
+                        println!("{}", e);
+                    }
+                }
+            }
+        }
+
+        if let Some((data, path)) = session.reboot.take() {
+            run_ctx.stop();
+
+            if let Err(e) = load_image(&mut system.machine, &system.map, args, bios.as_deref(), &data, &preloads) {
+                println!("{}", e);
+            }
+
+            run_ctx = system.power_on();
+
+            // a ROM dropped on the window is the one being worked on now, so that's the one to watch
+            if let Some(watcher) = rom_watcher.as_mut().filter(|watcher| path.as_deref().is_some_and(|path| path != watcher.path())) {
+                *watcher = FileWatcher::new(path.as_deref().unwrap());
+            }
+
+            session.rom = data;
+            session.loaded_rom_path = path.clone();
+            exit_code = 0;
+
+            session.events.publish(system.frame, MachineEvent::RomLoaded { path });
+            session.restarted(&mut system);
+
+            if let Some(rewind) = &mut session.rewind {
+                rewind.clear(system.frame);
+            }
+        }
+
+        // rewind states are taken between frames, like the save state hotkey's. a halted guest has nothing new to keep
+        if let Some(rewind) = session.rewind.as_mut().filter(|rewind| rewind.due(system.frame) && session.halted.is_none() && !run_ctx.is_paused()) {
+            if run_ctx.pause() {
+                match system.save_state(&run_ctx, &graphics_device) {
+                    Ok(state) => rewind.push(system.frame, &state),
+                    Err(e) => println!("{}", e),
+                }
+            }
+
+            run_ctx.resume();
+        }
+
+        // terminal input goes to the UART just like input sent over the control socket
+        if let Some(stdin_input) = &stdin_input {
+            while let Ok(data) = stdin_input.try_recv() {
+                let _ = session.uart_input(&mut system, &data);
+            }
+        }
+
+        // service remote control requests
+        while let Some(req) = control.poll() {
+            run_ctx = session.control_request(req, &mut system, run_ctx);
+
+            // a reset happens at the top of the next iteration - anything the client sends after it's been acknowledged
+            // should see the machine that's been reset, so it has to wait until then
+            if session.reboot.is_some() {
+                break;
+            }
+        }
+
+        let cur_tick = sdl3::timer::performance_counter();
+        let delta_tick = cur_tick - prev_tick;
+        let dt = delta_tick as f64 / sdl3::timer::performance_frequency() as f64;
+        prev_tick = cur_tick;
+
+        let ticks = if run_ctx.is_paused() || session.halted.is_some() {
+            session.pacer.hold();
+            0
+        }
+        else if lockstep {
+            1
+        }
+        else {
+            session.pacer.advance(dt)
+        };
+
+        if session.exception_monitor.update(&exception_stats) && args.exception_stats && session.exception_monitor.rate().total() != 0 {
+            println!("exceptions/s: {}", session.exception_monitor.rate().summary());
+        }
+
+        if let Some(lost) = session.pacer.take_slowdown() {
+            println!("{}", tr!("slowdown", format!("{:.1}", lost)));
+        }
+
+        let cmd_buf = graphics_device.acquire_command_buffer().unwrap();
+
+        for _ in 0..ticks {
+            if session.texture_dump_armed {
+                system.vdp.begin_texture_capture();
+                session.texture_dump_armed = false;
+            }
+
+            // in lockstep the VDP only sees the guest's work once the whole frame's worth has been queued, whatever the host's speed
+            if lockstep && !run_ctx.wait_idle(PERF_IDLE_TIMEOUT) {
+                println!("perf: guest didn't reach WFI within {}s @ frame {}, timings may vary between runs", PERF_IDLE_TIMEOUT.as_secs(), system.frame);
+            }
+
+            let frame_input = session.frame_input(system.frame);
+
+            // update VDP & move on to the next frame
+            let vdp_start = Instant::now();
+            let watchdog_expired = system.step_frame(&run_ctx, &graphics_device, &cmd_buf, frame_input);
+            perf_vdp += vdp_start.elapsed();
+
+            if let Some(bindings) = system.vdp.take_texture_capture() {
+                captured_textures = Some((system.frame, bindings));
+            }
+
+            // a watchdog reset is the same as the user's, once this frame is done
+            if watchdog_expired {
+                println!("{}", tr!("watchdog_reset", system.frame));
+                session.events.publish(system.frame, MachineEvent::WatchdogReset);
+                session.reboot = Some((session.rom.clone(), session.loaded_rom_path.clone()));
+            }
+
+            session.events.publish(system.frame, MachineEvent::VBlank { frame: system.frame });
+        }
+
+        // run-ahead presents a few frames further on than the machine really is, & puts the state back once that's submitted
+        let mut run_ahead = None;
+
+        let cmd_buf = if args.run_ahead > 0 && ticks > 0 && session.halted.is_none() && !run_ctx.is_paused() && !lockstep {
+            // the state has to see this frame's draws in VRAM
+            cmd_buf.submit().unwrap();
+
+            let cmd_buf = graphics_device.acquire_command_buffer().unwrap();
+
+            match system.run_ahead(&run_ctx, args.run_ahead, &graphics_device, &cmd_buf) {
+                Ok(kept) => run_ahead = kept,
+                Err(e) => println!("{}", e),
+            }
+
+            cmd_buf
+        }
+        else {
+            cmd_buf
+        };
+
+        let throttled = !session.focused && args.background == BackgroundMode::Throttle;
+        let present_start = Instant::now();
+
+        if throttled || !session.pacer.should_present(ticks) {
+            // still have to submit the VDP's work
+            let _span = chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "gpu", "submit"));
+            cmd_buf.submit().unwrap();
+        }
+        else {
+            let _span = chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "gpu", "submit & present"));
+
+            if let Err(e) = presenter.present(system.frame, &mut system.vdp, &graphics_device, cmd_buf) {
+                println!("{}", tr!("present_failed", e));
+            }
+        }
+
+        perf_present += present_start.elapsed();
+
+        if let Some(kept) = run_ahead.take() {
+            run_ctx = system.end_run_ahead(run_ctx, kept, &graphics_device);
+        }
+
+        // GPU time would otherwise land on whichever later frame happens to block on it
+        if lockstep {
+            let gpu_start = Instant::now();
+            let gpu_span = chrome_trace.as_ref().map(|trace| trace.begin(TRACK_FRONTEND, "gpu", "gpu wait"));
+            graphics_device.wait_idle().unwrap();
+            drop(gpu_span);
+            perf_gpu += gpu_start.elapsed();
+
+            if perf_end.is_some_and(|end| system.frame >= end) {
+                break 'running;
+            }
+
+            if let Some(bench) = &mut bench {
+                if ticks > 0 {
+                    bench.frame(system.vdp_port.last_stats());
+                }
+
+                if bench.done() {
+                    break 'running;
+                }
+            }
+        }
+
+        // nothing else is holding the loop to the emulated frame rate - & without a window there's nothing at all, so an idle
+        // guest would otherwise have the loop spinning until its next frame
+        let idle_wait = args.idle_skip && present != PresentMode::Window && !lockstep && run_ctx.is_idle();
+
+        if throttled || adaptive_sync || idle_wait {
+            session.pacer.idle();
+        }
+
+        // fences only complete once everything before them has actually executed
+        if system.vdp_port.fence_pending() {
+            graphics_device.wait_idle().unwrap();
+            system.vdp_port.complete_fence();
+        }
+
+        if let Some(capture) = system.vdp.take_vu_capture(&graphics_device) {
+            print!("{}", capture.table());
+
+            for (req, _) in session.vu_waiters.drain(..) {
+                req.reply(Ok(capture.to_json()));
+            }
+        }
+
+        // no frames run while the machine's stopped, so nothing would ever answer these
+        if run_ctx.is_paused() || session.halted.is_some() {
+            session.fail_vu_waiters(&mut system.vdp, tr!("vu_capture_stopped"));
+        }
+        else if session.vu_waiters.first().is_some_and(|(_, asked)| system.frame.saturating_sub(*asked) >= VU_CAPTURE_TIMEOUT_FRAMES) {
+            session.fail_vu_waiters(&mut system.vdp, &tr!("vu_capture_timeout", VU_CAPTURE_TIMEOUT_FRAMES));
+        }
+
+        // the frame's draws have been submitted, so VRAM now holds what they sampled
+        if let Some((dump_frame, bindings)) = captured_textures.take() {
+            let vram = system.vdp.read_vram(&graphics_device);
+
+            match texdump::dump_textures(&capture_dir, dump_frame, &bindings, &vram) {
+                Ok(dir) => println!("{}", tr!("textures_dumped", dump_frame, bindings.len(), dir.display())),
+                Err(e) => println!("{}", e),
+            }
+        }
+
+        // service guest screenshot, marker, & assert requests now that the frame has been submitted
+        loop {
+            let Some(ev) = system.debugport.take_event() else {
+                break;
+            };
+
+            if let DebugEvent::Exit { code, result } = ev {
+                if !result.is_empty() {
+                    println!("{}", tr!("guest_result", result));
+                }
+
+                session.events.publish(system.frame, MachineEvent::GuestExit { code, result });
+                exit_code = code;
+
+                if present != PresentMode::Window {
+                    break 'running;
+                }
+
+                run_ctx.pause();
+                session.halt(system.frame, tr!("guest_exited", code));
+                break;
+            }
+
+            // snapshot everything needed to reproduce a failed guest assert, with the CPU held still so it all agrees
+            if let DebugEvent::Assert { message } = &ev {
+                let was_paused = run_ctx.is_paused();
+
+                if !run_ctx.pause() {
+                    println!("{}", tr!("pause_timeout"));
+                }
+
+                system.uart.flush();
+
+                let dir = failcapture::failure_dir(&capture_dir, message);
+                match failcapture::capture_failure(&dir, message, system.frame, &run_ctx, &mut system.vdp, &graphics_device, &system.timebase, &system.snapshots, &system.uart, &system.map, args.expansion_ram) {
+                    Ok(()) => {
+                        println!("{}", tr!("assert_captured", system.frame, message, dir.display()));
+                    }
+                    Err(e) => {
+                        println!("{}", tr!("assert_capture_failed", message, e));
+                    }
+                }
+
+                if !was_paused {
+                    run_ctx.resume();
+                }
+
+                continue;
+            }
+
+            if let Err(e) = handle_debug_event(ev, system.frame, &capture_dir, &capture, &mut system.vdp, &graphics_device) {
+                println!("{}", e);
+            }
+        }
+
+        // the CPU parks itself at a breakpoint or watchpoint - say where
+        while let Some(stop) = system.machine.poll_debug_stop() {
+            let desc = stop.describe(&system.symbols);
+            println!("{}", tr!("debug_stop", system.frame, desc, session.hotkeys.describe(HotkeyAction::Pause)));
+            session.last_stop = Some(desc.clone());
+            session.events.publish(system.frame, MachineEvent::DebugStop { stop, desc });
+        }
+
+        // a crashed guest takes the CPU thread down with it
+        if session.halted.is_none() {
+            if let Some(fault) = run_ctx.fault() {
+                let reason = tr!("guest_fault", fault);
+
+                if !args.no_crash_dumps {
+                    match crashdump::guest_dump(&fault, system.frame, &run_ctx, &mut system.vdp, &graphics_device, &system.timebase, &system.snapshots, &system.uart, args.expansion_ram) {
+                        Ok(path) => println!("{}", tr!("crash_dumped", path.display())),
+                        Err(e) => println!("{}", tr!("crash_dump_failed", e)),
+                    }
+                }
+
+                if present != PresentMode::Window {
+                    println!("{}", reason);
+                    exit_code = 1;
+                    break 'running;
+                }
+
+                session.halt(system.frame, reason);
+            }
+        }
+
+        // run frame hooks once per newly presented frame
+        if system.frame != last_hook_frame {
+            last_hook_frame = system.frame;
+
+            let mut ctx = FrameContext::new(system.frame, &mut system.vdp, &graphics_device, &run_ctx, &system.uart);
+            for hook in &mut frame_hooks {
+                if let HookAction::Exit(code) = hook.on_frame(&mut ctx) {
+                    exit_code = code;
+                    break 'running;
+                }
+            }
+        }
+    }
+
+    run_ctx.stop();
+    system.uart.flush();
+
+    // whatever is still queued gets written before exiting
+    capture.finish();
+
+    let (written, dropped, failed) = capture.summary();
+    if dropped != 0 || failed != 0 {
+        println!("{}", tr!("capture_summary", written, dropped, failed));
+    }
+
+    session.events.publish(system.frame, MachineEvent::Stopped);
+
+    if let Some(path) = &args.perf_report {
+        let mut report = PerfReport::new(system.frame);
+        report.add("total", perf_start.elapsed().as_secs_f64());
+        report.add("cpu", system.frame_budget.total_us() as f64 / 1_000_000.0);
+        report.add("vdp", perf_vdp.as_secs_f64());
+        report.add("present", perf_present.as_secs_f64());
+        report.add("gpu", perf_gpu.as_secs_f64());
+
+        if let Err(e) = report.save(path) {
+            println!("{}", e);
+            exit_code = 1;
+        }
+    }
+
+    if let Some(bench) = &bench {
+        println!("{}", bench.report(instructions.load(Ordering::Relaxed)));
+    }
+
+    if let (Some(movie), Some(path)) = (&mut session.recording, &args.record) {
+        movie.record(system.frame, MOVIEEVENT_END, &[]);
+
+        if let Err(e) = movie.save(path) {
+            println!("{}", tr!("movie_save_failed", path.display(), e));
+        }
+    }
+
+    system.clock.stop_timer();
+
+    // persist state for next boot - a deterministic RTC has nothing to carry over, & a movie's belongs to the movie
+    if !args.deterministic && session.playback.is_none() {
+        if let Err(e) = save_store.write(SAVE_RTC, &system.clock.rtc_host_offset().to_le_bytes()) {
+            println!("{}", tr!("rtc_save_failed", e));
+        }
+    }
+
+    if args.exception_stats {
+        println!("exceptions: {}", exception_stats.snapshot().summary());
+    }
+
+    if args.bios_stats {
+        println!("bios: {}", bios_stats.summary());
+    }
+
+    if let Some(poison) = &system.poison {
+        println!("{}", tr!("poison_summary", poison.hits()));
+    }
+
+    if system.bus_error.count() != 0 {
+        let (addr, pc) = system.bus_error.last();
+        println!("{}", tr!("buserr_summary", system.bus_error.count(), system.symbols.describe(addr), system.symbols.describe(pc)));
+    }
+
+    if args.vdp_strict {
+        println!("{}", tr!("vdp_strict_summary", system.vdp_port.strict_violations()));
+    }
+
+    if let Some(summary) = system.hw.summary() {
+        println!("{}", tr!("hw_limit_summary", summary));
+    }
+
+    if let (Some(tracer), Some(path)) = (&tracer, &args.trace) {
+        println!("{}", tr!("trace_summary", tracer.finish(), path.display()));
+    }
+
+    if let (Some(tracer), Some(path)) = (&mem_tracer, &args.mem_trace_out) {
+        println!("{}", tr!("trace_summary", tracer.finish(), path.display()));
+    }
+
+    if let (Some(chrome_trace), Some(path)) = (&chrome_trace, &args.chrome_trace) {
+        match chrome_trace.save(path) {
+            Ok(events) => println!("{}", tr!("trace_summary", events, path.display())),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    if args.lock_stats {
+        println!("{:<20} {:>12} {:>10} {:>12}", "lock", "acquisitions", "contended", "wait (us)");
+
+        for (name, stats) in system.peripherals.iter().flat_map(|p| p.lock_stats()) {
+            println!("{:<20} {:>12} {:>10} {:>12}", name, stats.acquisitions, stats.contended, stats.wait_ns / 1000);
+        }
+    }
+
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::{cell::RefCell, convert::Infallible, fmt::Display, rc::Rc, time::{Duration, Instant}};
+
+use embedded_graphics::{mono_font::{iso_8859_1::FONT_6X10, MonoTextStyle}, pixelcolor::Rgb888, prelude::*, text::{Baseline, Text}};
+
+use crate::accessibility::OsdTheme;
+
+// how long a message stays up
+const MESSAGE_TIME: Duration = Duration::from_secs(3);
+
+// short status messages (state saved, render debug view changed & the like) drawn over the top left of the window's frame,
+// in guest pixels, so they scale up with it. handles are cheap to clone: the run loop shows messages through one & the
+// window presenter draws through another
+#[derive(Clone)]
+pub struct Osd {
+    message: Rc<RefCell<Option<(String, Instant)>>>,
+    scale: u32,
+    theme: OsdTheme,
+}
+
+impl Osd {
+    pub fn new(scale: u32, theme: OsdTheme) -> Self {
+        Self {
+            message: Rc::new(RefCell::new(None)),
+            scale: scale.max(1),
+            theme,
+        }
+    }
+
+    // shows a message over the frame, replacing any already up (nothing shows without a window to show it in)
+    pub fn show(self: &Self, message: impl Display) {
+        *self.message.borrow_mut() = Some((message.to_string(), Instant::now()));
+    }
+
+    // prints a message, & shows it over the frame too
+    pub fn notify(self: &Self, message: impl Display) {
+        println!("{}", message);
+        self.show(message);
+    }
+
+    pub fn is_visible(self: &Self) -> bool {
+        return self.message.borrow().as_ref().is_some_and(|(_, shown)| shown.elapsed() < MESSAGE_TIME);
+    }
+
+    // draws the current message, if any, into a tightly packed RGBA8 frame
+    pub fn draw(self: &Self, rgba: &mut [u8], width: u32, height: u32) {
+        if let Some((text, shown)) = self.message.borrow().as_ref() {
+            if shown.elapsed() < MESSAGE_TIME {
+                draw_message(text, rgba, width, height, self.scale, self.theme);
+            }
+        }
+    }
+}
+
+// one line in a box, cut short if the frame's too narrow for all of it
+fn draw_message(text: &str, rgba: &mut [u8], width: u32, height: u32, scale: u32, theme: OsdTheme) {
+    let glyph = FONT_6X10.character_size;
+    let margin = 4 * scale;
+    let padding = 2 * scale;
+
+    let fits = (width.saturating_sub(2 * (margin + padding)) / (glyph.width * scale)) as usize;
+    let text: String = text.chars().take(fits).collect();
+    let chars = text.chars().count() as u32;
+
+    if chars == 0 || 2 * (margin + padding) + glyph.height * scale > height {
+        return;
+    }
+
+    let box_width = chars * glyph.width * scale + 2 * padding;
+    let box_height = glyph.height * scale + 2 * padding;
+    let colors = theme.colors();
+
+    if let Some(border) = colors.border {
+        fill_rect(rgba, width, height, (margin - scale, margin - scale), (box_width + 2 * scale, box_height + 2 * scale), border, 1.0);
+    }
+
+    fill_rect(rgba, width, height, (margin, margin), (box_width, box_height), colors.background, colors.background_alpha);
+
+    let mut canvas = Canvas {
+        rgba,
+        width,
+        height,
+        scale,
+        origin: (margin + padding, margin + padding),
+    };
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb888::new(colors.text[0], colors.text[1], colors.text[2]));
+    let _ = Text::with_baseline(&text, Point::zero(), style, Baseline::Top).draw(&mut canvas);
+}
+
+// blends a solid color over part of the frame, clipped to it
+fn fill_rect(rgba: &mut [u8], width: u32, height: u32, (x, y): (u32, u32), (w, h): (u32, u32), color: [u8; 3], alpha: f32) {
+    for py in y..(y + h).min(height) {
+        for px in x..(x + w).min(width) {
+            let i = ((py * width + px) * 4) as usize;
+
+            for (c, src) in rgba[i..(i + 3)].iter_mut().zip(color) {
+                *c = (*c as f32 + (src as f32 - *c as f32) * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+// the frame as somewhere for the font to draw, each font pixel scale x scale frame pixels
+struct Canvas<'a> {
+    rgba: &'a mut [u8],
+    width: u32,
+    height: u32,
+    scale: u32,
+    origin: (u32, u32),
+}
+
+impl<'a> OriginDimensions for Canvas<'a> {
+    fn size(self: &Self) -> Size {
+        return Size::new(self.width / self.scale, self.height / self.scale);
+    }
+}
+
+impl<'a> DrawTarget for Canvas<'a> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I: IntoIterator<Item = Pixel<Rgb888>>>(self: &mut Self, pixels: I) -> Result<(), Infallible> {
+        for Pixel(p, color) in pixels {
+            if p.x < 0 || p.y < 0 {
+                continue;
+            }
+
+            let at = (self.origin.0 + p.x as u32 * self.scale, self.origin.1 + p.y as u32 * self.scale);
+            fill_rect(self.rgba, self.width, self.height, at, (self.scale, self.scale), [color.r(), color.g(), color.b()], 1.0);
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, gray: u8) -> Vec<u8> {
+        return [gray, gray, gray, 255].repeat((width * height) as usize);
+    }
+
+    fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+        let i = ((y * width + x) * 4) as usize;
+        return [rgba[i], rgba[i + 1], rgba[i + 2]];
+    }
+
+    #[test]
+    fn high_contrast_box_is_opaque_with_a_border() {
+        let mut rgba = frame(320, 240, 128);
+        draw_message("I", &mut rgba, 320, 240, 1, OsdTheme::HighContrast);
+
+        // border just outside the box, opaque background just inside it, & the guest's frame untouched past both
+        assert_eq!(pixel(&rgba, 320, 3, 3), [255, 255, 255]);
+        assert_eq!(pixel(&rgba, 320, 4, 4), [0, 0, 0]);
+        assert_eq!(pixel(&rgba, 320, 100, 100), [128, 128, 128]);
+
+        // the glyph is drawn somewhere in the box, in the text color
+        let lit = (6..12).flat_map(|x| (6..16).map(move |y| (x, y))).filter(|&(x, y)| pixel(&rgba, 320, x, y) == [255, 255, 255]).count();
+        assert!(lit > 0);
+    }
+
+    #[test]
+    fn scale_grows_the_box() {
+        let mut small = frame(320, 240, 128);
+        let mut large = frame(320, 240, 128);
+        draw_message("hello", &mut small, 320, 240, 1, OsdTheme::Default);
+        draw_message("hello", &mut large, 320, 240, 2, OsdTheme::Default);
+
+        // the default theme only darkens the frame behind the text
+        let darkened = |rgba: &[u8]| (0..240).flat_map(|y| (0..320).map(move |x| (x, y))).filter(|&(x, y)| pixel(rgba, 320, x, y)[0] < 128).count();
+        assert!(darkened(&large) > 3 * darkened(&small));
+    }
+
+    #[test]
+    fn nothing_drawn_when_the_frame_is_too_small() {
+        let mut rgba = frame(8, 8, 128);
+        draw_message("hello", &mut rgba, 8, 8, 1, OsdTheme::HighContrast);
+        assert_eq!(rgba, frame(8, 8, 128));
+    }
+}
@@ -0,0 +1,329 @@
+use std::{env, fs, path::{Path, PathBuf}, process::{Command, Stdio}, time::{Duration, Instant}};
+
+use clap::Args;
+use serde_json::{json, Map, Value};
+
+use crate::config;
+
+// a perf case is a ROM image with an input movie of the same name next to it. each case is replayed headlessly with the
+// frontend & CPU in lockstep, so a run does the same work every time no matter how fast the host is, & the time spent in
+// each part of the emulator is compared against a stored baseline
+pub const PERF_ROM_EXT: &str = "bin";
+pub const PERF_MOVIE_EXT: &str = "nyxm";
+
+// subsystems timed by a perf run: the whole run, the guest CPU, VDP command processing, presenting, & waiting on the GPU
+pub const PERF_SUBSYSTEMS: [&str;5] = ["total", "cpu", "vdp", "present", "gpu"];
+
+// how long a perf run waits for the guest to finish a frame before carrying on without it
+pub const PERF_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// timings below this many microseconds per frame are too noisy to compare
+const PERF_MIN_US_PER_FRAME: f64 = 50.0;
+
+#[derive(Args)]
+pub struct PerfArgs {
+    /// Directory containing perf ROMs (*.bin) and the input movies to replay on them (*.nyxm)
+    dir: PathBuf,
+
+    /// Baseline timings to compare against (defaults to <DIR>/baseline.json)
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Slowdown allowed before a subsystem counts as regressed (0.15 = 15%)
+    #[arg(long, default_value_t = 0.15)]
+    tolerance: f64,
+
+    /// Write this run's timings as the new baseline instead of comparing
+    #[arg(long)]
+    update_baseline: bool,
+
+    /// Times to replay each case - the fastest time for each subsystem is kept
+    #[arg(long, default_value_t = 3)]
+    runs: u32,
+
+    /// Root for per-case state (saves, captures, reports) - each case gets its own subdirectory
+    #[arg(long, default_value = "perf-out")]
+    work_dir: PathBuf,
+}
+
+// what one replay measured, written by `nyxbox run --perf-report`
+pub struct PerfReport {
+    pub frames: u64,
+    pub times: Vec<(String, f64)>,
+}
+
+impl PerfReport {
+    pub fn new(frames: u64) -> Self {
+        Self {
+            frames,
+            times: Vec::new(),
+        }
+    }
+
+    pub fn add(self: &mut Self, subsystem: &str, secs: f64) {
+        self.times.push((subsystem.to_string(), secs));
+    }
+
+    pub fn get(self: &Self, subsystem: &str) -> Option<f64> {
+        return self.times.iter().find(|(name, _)| name == subsystem).map(|(_, secs)| *secs);
+    }
+
+    pub fn us_per_frame(self: &Self, subsystem: &str) -> Option<f64> {
+        return self.get(subsystem).map(|secs| secs * 1_000_000.0 / self.frames.max(1) as f64);
+    }
+
+    // keep the fastest time seen for each subsystem
+    pub fn merge_best(self: &mut Self, other: &PerfReport) {
+        for (name, secs) in &other.times {
+            match self.times.iter_mut().find(|(n, _)| n == name) {
+                Some((_, best)) => *best = best.min(*secs),
+                None => self.times.push((name.clone(), *secs)),
+            }
+        }
+    }
+
+    pub fn to_json(self: &Self) -> Value {
+        let mut times = Map::new();
+
+        for (name, secs) in &self.times {
+            times.insert(name.clone(), json!({
+                "seconds": secs,
+                "us_per_frame": self.us_per_frame(name).unwrap(),
+            }));
+        }
+
+        return json!({
+            "frames": self.frames,
+            "times": times,
+        });
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let frames = value["frames"].as_u64().ok_or("perf report has no frame count")?;
+        let times = value["times"].as_object().ok_or("perf report has no times")?;
+
+        let mut report = Self::new(frames);
+        for (name, time) in times {
+            let secs = time["seconds"].as_f64().ok_or_else(|| format!("perf report has no time for {}", name))?;
+            report.add(name, secs);
+        }
+
+        return Ok(report);
+    }
+
+    pub fn save(self: &Self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.to_json()).unwrap();
+        return fs::write(path, text).map_err(|e| format!("failed to write {}: {}", path.display(), e));
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let value: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Self::from_json(&value);
+    }
+}
+
+// per-frame times are compared, so a movie that's been re-recorded to a different length can still be checked
+pub fn compare(current: &PerfReport, baseline: &PerfReport, tolerance: f64) -> Vec<String> {
+    let mut regressions = Vec::new();
+
+    for name in PERF_SUBSYSTEMS {
+        let (Some(cur), Some(base)) = (current.us_per_frame(name), baseline.us_per_frame(name)) else {
+            continue;
+        };
+
+        if base < PERF_MIN_US_PER_FRAME {
+            continue;
+        }
+
+        if cur > base * (1.0 + tolerance) {
+            regressions.push(format!("{}: {:.1}us/frame, baseline {:.1}us/frame (+{:.1}%)", name, cur, base, (cur / base - 1.0) * 100.0));
+        }
+    }
+
+    return regressions;
+}
+
+struct PerfCase {
+    name: String,
+    rom: PathBuf,
+    movie: PathBuf,
+}
+
+fn collect_cases(dir: &Path) -> Result<Vec<PerfCase>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+    let mut cases = Vec::new();
+
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+
+        if path.extension().and_then(|v| v.to_str()) != Some(PERF_ROM_EXT) {
+            continue;
+        }
+
+        let movie = path.with_extension(PERF_MOVIE_EXT);
+        if !movie.exists() {
+            println!("skipping {}: no {} movie", path.display(), PERF_MOVIE_EXT);
+            continue;
+        }
+
+        cases.push(PerfCase {
+            name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            rom: path,
+            movie,
+        });
+    }
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    return Ok(cases);
+}
+
+// cases run one at a time - instances running side by side would only be measuring each other
+fn run_case(exe: &Path, case: &PerfCase, run: u32, args: &PerfArgs) -> Result<PerfReport, String> {
+    let out_dir = args.work_dir.join(&case.name);
+    fs::create_dir_all(&out_dir).map_err(|e| format!("failed to create {}: {}", out_dir.display(), e))?;
+
+    let log_path = out_dir.join("output.log");
+    let log = fs::File::create(&log_path).map_err(|e| format!("failed to create log: {}", e))?;
+    let report_path = out_dir.join(format!("run{}.json", run));
+
+    let status = Command::new(exe)
+        .arg("run")
+        .arg(&case.rom)
+        .env(config::CONFIG_ENV, "")
+        .arg("--play").arg(&case.movie)
+        .arg("--present").arg("none")
+        .arg("--perf-report").arg(&report_path)
+        .arg("--save-dir").arg(out_dir.join("saves"))
+        .arg("--capture-dir").arg(out_dir.join("captures"))
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().unwrap())
+        .stderr(log)
+        .status()
+        .map_err(|e| format!("failed to start emulator: {}", e))?;
+
+    if !status.success() {
+        return Err(format!("{} (see {})", status, log_path.display()));
+    }
+
+    return PerfReport::load(&report_path);
+}
+
+pub fn perf_cmd(args: &PerfArgs) {
+    let cases = match collect_cases(&args.dir) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let exe = env::current_exe().unwrap();
+    let baseline_path = args.baseline.clone().unwrap_or(args.dir.join("baseline.json"));
+
+    // baseline file: { "<case>": <report>, ... }
+    let baseline: Map<String, Value> = if args.update_baseline {
+        Map::new()
+    }
+    else {
+        match fs::read_to_string(&baseline_path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("{}: {}", baseline_path.display(), e);
+                    std::process::exit(1);
+                }
+            },
+            Err(e) => {
+                eprintln!("failed to read {} (run with --update-baseline to create it): {}", baseline_path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let mut results = Map::new();
+    let mut failed = Vec::new();
+
+    println!("running {} perf cases, {} run(s) each", cases.len(), args.runs);
+
+    for case in &cases {
+        let start = Instant::now();
+        let mut best: Option<PerfReport> = None;
+
+        for run in 0..args.runs.max(1) {
+            match run_case(&exe, case, run, args) {
+                Ok(report) => match &mut best {
+                    Some(best) => best.merge_best(&report),
+                    None => best = Some(report),
+                },
+                Err(e) => {
+                    println!("FAIL {}: {}", case.name, e);
+                    failed.push(case.name.clone());
+                    best = None;
+                    break;
+                }
+            }
+        }
+
+        let Some(report) = best else {
+            continue;
+        };
+
+        let summary = PERF_SUBSYSTEMS.iter()
+            .filter_map(|name| report.us_per_frame(name).map(|us| format!("{} {:.1}", name, us)))
+            .collect::<Vec<_>>().join(", ");
+
+        if args.update_baseline {
+            println!("{} ({:.2}s): {} frames, us/frame: {}", case.name, start.elapsed().as_secs_f64(), report.frames, summary);
+        }
+        else {
+            let regressions = match baseline.get(&case.name).map(PerfReport::from_json) {
+                Some(Ok(base)) => compare(&report, &base, args.tolerance),
+                Some(Err(e)) => vec![e],
+                None => {
+                    println!("NEW  {}: no baseline, us/frame: {}", case.name, summary);
+                    Vec::new()
+                }
+            };
+
+            if regressions.is_empty() {
+                println!("PASS {} ({:.2}s): us/frame: {}", case.name, start.elapsed().as_secs_f64(), summary);
+            }
+            else {
+                println!("FAIL {} ({:.2}s):", case.name, start.elapsed().as_secs_f64());
+                for r in &regressions {
+                    println!("  {}", r);
+                }
+                failed.push(case.name.clone());
+            }
+        }
+
+        results.insert(case.name.clone(), report.to_json());
+    }
+
+    if args.update_baseline {
+        let text = serde_json::to_string_pretty(&Value::from(results)).unwrap();
+        if let Err(e) = fs::write(&baseline_path, text) {
+            eprintln!("failed to write {}: {}", baseline_path.display(), e);
+            std::process::exit(1);
+        }
+
+        println!("baseline written to {}", baseline_path.display());
+    }
+    else {
+        let results_path = args.work_dir.join("results.json");
+        let text = serde_json::to_string_pretty(&Value::from(results)).unwrap();
+        if let Err(e) = fs::create_dir_all(&args.work_dir).and_then(|_| fs::write(&results_path, text)) {
+            println!("failed to write {}: {}", results_path.display(), e);
+        }
+    }
+
+    println!("{} passed, {} failed", cases.len() - failed.len(), failed.len());
+    for name in &failed {
+        println!("  {}", name);
+    }
+
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use clap::ValueEnum;
+use sdl3::{gpu::{Buffer, BufferMemMap, BufferRegion, BufferUsageFlags, ColorTargetDescription, ColorTargetInfo, CommandBuffer, Device, GraphicsPipeline, GraphicsPipelineTargetInfo, LoadOp, PrimitiveType, ShaderFormat, ShaderStage, StoreOp, TransferBuffer, TransferBufferLocation, TransferBufferUsage}, pixels::Color, video::Window};
+
+use nyxbox_core::{capture::CaptureWriter, present::{CallbackPresenter, PresentBackend}, renderdebug::RenderDebugMode, vdp::{VDP, VRAM_SIZE}};
+
+use crate::{accessibility::{FlashFilter, FlashReduction}, osd::Osd};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PresentMode {
+    /// Present to the SDL window
+    #[default]
+    Window,
+    /// Write frames out as PNGs
+    Offscreen,
+    /// Render frames but don't show or save them (e.g. for guest programs that only talk over the UART)
+    None,
+}
+
+pub const SHADER_PRESENT_VERT: &str = "content/shaders/present_vert.spv";
+pub const SHADER_PRESENT_FRAG: &str = "content/shaders/present_frag.spv";
+
+// the shaders the window presenter needs
+pub const SHADER_PATHS: &[&str] = &[
+    SHADER_PRESENT_VERT,
+    SHADER_PRESENT_FRAG,
+];
+
+#[repr(C)]
+struct PresentUBO {
+    fb_addr: u32,
+    fb_dim: u32,
+    target_width: u32,
+    target_height: u32,
+}
+
+// draws the framebuffer on display straight out of VRAM into the swapchain texture, with a fullscreen triangle whose
+// fragment shader does the scaling & unpacking - no readback, & no texture to keep in step with VRAM. with flash
+// reduction or the overdraw heat map on, or an OSD message up, the frame is read back instead, filtered (& the message
+// drawn over it) on the CPU, & drawn from a buffer of its own
+pub struct WindowPresenter<'a> {
+    window: &'a Window,
+    pipeline: GraphicsPipeline,
+    flash_filter: FlashFilter,
+    osd: Option<Osd>,
+    filtered: Option<(Buffer, TransferBuffer)>,
+}
+
+impl<'a> WindowPresenter<'a> {
+    pub fn new(window: &'a Window, gfx_device: &Device) -> Self {
+        let vert_shader = gfx_device.create_shader()
+            .with_code(ShaderFormat::SpirV, &fs::read(SHADER_PRESENT_VERT).unwrap(), ShaderStage::Vertex)
+            .with_entrypoint("main")
+            .build().unwrap();
+
+        let frag_shader = gfx_device.create_shader()
+            .with_code(ShaderFormat::SpirV, &fs::read(SHADER_PRESENT_FRAG).unwrap(), ShaderStage::Fragment)
+            .with_entrypoint("main")
+            .with_storage_buffers(1)
+            .with_uniform_buffers(1)
+            .build().unwrap();
+
+        let pipeline = gfx_device.create_graphics_pipeline()
+            .with_vertex_shader(&vert_shader)
+            .with_fragment_shader(&frag_shader)
+            .with_primitive_type(PrimitiveType::TriangleList)
+            .with_target_info(GraphicsPipelineTargetInfo::new()
+                .with_color_target_descriptions(&[ColorTargetDescription::new().with_format(gfx_device.get_swapchain_texture_format(window))]))
+            .build().unwrap();
+
+        Self {
+            window,
+            pipeline,
+            flash_filter: FlashFilter::new(FlashReduction::Off),
+            osd: None,
+            filtered: None,
+        }
+    }
+
+    pub fn with_flash_reduction(mut self, level: FlashReduction) -> Self {
+        self.flash_filter = FlashFilter::new(level);
+        return self;
+    }
+
+    pub fn with_osd(mut self, osd: Osd) -> Self {
+        self.osd = Some(osd);
+        return self;
+    }
+
+    // reads the frame back (stalling like the other presenters), filters it, & uploads it into the filtered buffer, which
+    // gets the same layout as a framebuffer at word 0. returns the FBDIM to present it with
+    fn upload_filtered(self: &mut Self, vdp: &mut VDP, gfx_device: &Device, cmd_buffer: &CommandBuffer) -> Result<u32, String> {
+        // no usable framebuffer yet (e.g. still booting) - black, like the unfiltered path shows, rather than an error
+        // every frame
+        let Ok((width, height, mut rgba)) = vdp.read_framebuffer(gfx_device) else {
+            return Ok(0);
+        };
+        self.flash_filter.apply(&mut rgba);
+
+        // after the flash filter, so messages come & go at once rather than fading
+        if let Some(osd) = &self.osd {
+            osd.draw(&mut rgba, width, height);
+        }
+
+        // the framebuffer can't be bigger than VRAM, so neither can this
+        let (buffer, transfer) = self.filtered.get_or_insert_with(|| {
+            let buffer = gfx_device.create_buffer()
+                .with_size(VRAM_SIZE)
+                .with_usage(BufferUsageFlags::GraphicsStorageRead)
+                .build()
+                .unwrap();
+
+            let transfer = gfx_device.create_transfer_buffer()
+                .with_size(VRAM_SIZE)
+                .with_usage(TransferBufferUsage::Upload)
+                .build()
+                .unwrap();
+
+            return (buffer, transfer);
+        });
+
+        let mut mem: BufferMemMap<'_, u8> = transfer.map::<u8>(gfx_device, true);
+        mem.mem_mut()[..rgba.len()].copy_from_slice(&rgba);
+        drop(mem);
+
+        let copy_pass = gfx_device.begin_copy_pass(cmd_buffer).map_err(|e| e.to_string())?;
+        copy_pass.upload_to_gpu_buffer(TransferBufferLocation::new().with_transfer_buffer(transfer),
+            BufferRegion::new().with_buffer(buffer).with_size(rgba.len() as u32), true);
+        gfx_device.end_copy_pass(copy_pass);
+
+        return Ok(width | height << 16);
+    }
+}
+
+impl<'a> PresentBackend for WindowPresenter<'a> {
+    fn present(self: &mut Self, _frame: u64, vdp: &mut VDP, gfx_device: &Device, mut cmd_buffer: CommandBuffer) -> Result<(), String> {
+        let filtering = self.flash_filter.is_active() || vdp.render_debug() == RenderDebugMode::Overdraw || self.osd.as_ref().is_some_and(|osd| osd.is_visible());
+
+        // the VDP's work has to have finished before its output can be read back, so it goes in first & the frame is
+        // drawn from a fresh command buffer
+        let filtered_dim = if filtering {
+            cmd_buffer.submit().map_err(|e| e.to_string())?;
+            cmd_buffer = gfx_device.acquire_command_buffer().map_err(|e| e.to_string())?;
+            Some(self.upload_filtered(vdp, gfx_device, &cmd_buffer)?)
+        }
+        else {
+            None
+        };
+
+        if let Ok(swap_target) = cmd_buffer.wait_and_acquire_swapchain_texture(self.window) {
+            let (fb_addr, fb_dim) = match filtered_dim {
+                Some(fb_dim) => (0, fb_dim),
+                None => vdp.display_framebuffer(),
+            };
+            let source = match &self.filtered {
+                Some((buffer, _)) if filtered_dim.is_some() => buffer,
+                _ => vdp.vram_buffer(),
+            };
+            let ubo = PresentUBO {
+                fb_addr,
+                fb_dim,
+                target_width: swap_target.width(),
+                target_height: swap_target.height(),
+            };
+
+            let targets = [
+                ColorTargetInfo::default()
+                    .with_texture(&swap_target)
+                    .with_clear_color(Color::RGB(0, 0, 0))
+                    .with_load_op(LoadOp::Clear)
+                    .with_store_op(StoreOp::Store)
+            ];
+            let render_pass = gfx_device.begin_render_pass(&cmd_buffer, &targets, None).map_err(|e| e.to_string())?;
+            {
+                render_pass.bind_graphics_pipeline(&self.pipeline);
+                render_pass.bind_fragment_storage_buffers(0, &[source]);
+                cmd_buffer.push_fragment_uniform_data(0, &ubo);
+                render_pass.draw_primitives(3, 1, 0, 0);
+            }
+            gfx_device.end_render_pass(render_pass);
+        }
+
+        return cmd_buffer.submit().map_err(|e| e.to_string());
+    }
+}
+
+// writes every Nth emulated frame to a numbered PNG - for headless runs & CI artifacts. encoding happens on the capture
+// workers, so only the readback is paid for here
+pub fn offscreen_presenter(dir: PathBuf, interval: u64, capture: Arc<CaptureWriter>, flash_reduction: FlashReduction) -> CallbackPresenter<impl FnMut(u64, u32, u32, Vec<u8>) -> Result<(), String>> {
+    let mut flash_filter = FlashFilter::new(flash_reduction);
+
+    let write_frame = move |frame: u64, width: u32, height: u32, mut rgba: Vec<u8>| {
+        flash_filter.apply(&mut rgba);
+
+        fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+
+        // a dropped frame is counted in the capture summary
+        capture.write_png(dir.join(format!("frame{:08}.png", frame)), width, height, rgba);
+        return Ok(());
+    };
+
+    return CallbackPresenter::new(write_frame).with_interval(interval);
+}
@@ -0,0 +1,590 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::Arc};
+
+use serde_json::json;
+
+use nyxbox_core::{capture::CaptureWriter, debugport::DebugEvent, log, machine::MachineRunContext, mem::BOOT_ROM_BEGIN, movie::{Movie, MOVIEEVENT_PAD, MOVIEEVENT_UART_INPUT}};
+use nyxbox_core::{excstats::ExceptionMonitor, pacing::{BackgroundMode, FramePacer, FAST_FORWARD_SPEED}, renderdebug::DrawIsolation, rewind::Rewind, savestate, screenshot};
+use nyxbox_core::{system::{FrameInput, System}, vdp::VDP};
+use sdl3::{event::{Event, WindowEvent}, gamepad::Gamepad, gpu::Device, EventPump, GamepadSubsystem};
+
+use crate::{control::{self, ControlCommand, ControlRequest}, events::{EventBus, MachineEvent}, hotkeys::{HotkeyAction, HotkeyMap}, input::InputLayer, lang::tr, osd::Osd};
+use crate::{handle_debug_event, read_rom, RunArgs};
+
+// the frontend's state from one frame to the next: what the window, gamepads, hotkeys & control clients act on. the System
+// & its run context stay with the main loop, which hands them to each of these - a run context that gets replaced (by a
+// load, a reset or a breakpoint change) is handed back
+pub struct Session<'a> {
+    pub args: &'a RunArgs,
+    pub gfx_device: &'a Device,
+    pub capture_dir: PathBuf,
+    pub capture: Arc<CaptureWriter>,
+    pub events: EventBus,
+    pub osd: Osd,
+    pub input: InputLayer,
+    pub hotkeys: HotkeyMap,
+    pub pacer: FramePacer,
+    pub exception_monitor: ExceptionMonitor,
+    pub rewind: Option<Rewind>,
+    pub recording: Option<Movie>,
+    pub playback: Option<Movie>,
+    // set when the guest crashes, or exits while in a window - rather than taking the frontend down with it, emulation
+    // stops until the user resets, loads a state, or drops another ROM onto the window
+    pub halted: Option<String>,
+    // the ROM to boot next, & where it came from (None when --bios or --load make up the image)
+    pub reboot: Option<(Vec<u8>, Option<PathBuf>)>,
+    pub rom: Vec<u8>,
+    pub loaded_rom_path: Option<PathBuf>,
+    // control clients waiting on a VU output capture, with the frame each one asked on
+    pub vu_waiters: Vec<(ControlRequest, u64)>,
+    // texture dumps cover one whole frame, so a request waits for the next one to start
+    pub texture_dump_armed: bool,
+    pub focused: bool,
+    pub background_paused: bool,
+    // deterministic mode's live UART input, waiting for the next frame boundary
+    pub uart_pending: Vec<u8>,
+    // what the CPU last stopped at, for status requests while it's still paused there
+    pub last_stop: Option<String>,
+    // the pad as last latched, so a movie only records changes
+    pub last_buttons: u32,
+    pub gamepad_sys: Option<GamepadSubsystem>,
+    // every gamepad connected, by joystick id - opened at startup & as they're plugged in (ControllerDeviceAdded)
+    pub open_gamepads: HashMap<u32, Gamepad>,
+}
+
+impl <'a> Session<'a> {
+    // window, keyboard & gamepad events since the last frame. returns the hotkeys pressed, or None once the window's closed
+    pub fn poll_events(self: &mut Self, event_pump: &mut EventPump, system: &System<'_>, run_ctx: &MachineRunContext) -> Option<Vec<HotkeyAction>> {
+        let mut actions = Vec::new();
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => {
+                    return None;
+                }
+                Event::KeyDown { keycode: Some(key), keymod, repeat: false, .. } => {
+                    match self.hotkeys.key_down(key, keymod) {
+                        Some(action) => actions.push(action),
+                        None => self.input.key_down(key, keymod),
+                    }
+                }
+                Event::KeyUp { keycode: Some(key), .. } => {
+                    self.input.key_up(key);
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    self.open_gamepad(which);
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.open_gamepads.remove(&which);
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    actions.extend(self.hotkeys.button_down(button));
+                    self.input.button_down(button);
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    self.hotkeys.button_up(button);
+                    self.input.button_up(button);
+                }
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                    self.focused = false;
+
+                    if self.args.background == BackgroundMode::Pause && !run_ctx.is_paused() {
+                        run_ctx.pause();
+                        self.background_paused = true;
+                        self.events.publish(system.frame, MachineEvent::Paused);
+                    }
+                }
+                Event::DropFile { filename, .. } => {
+                    let path = PathBuf::from(filename);
+
+                    match read_rom(&path, &system.map) {
+                        Ok(data) => {
+                            println!("{}", tr!("rom_loading", path.display()));
+                            self.reboot = Some((data, Some(path)));
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                        }
+                    }
+                }
+                Event::Window { win_event: WindowEvent::FocusGained, .. } => {
+                    self.focused = true;
+
+                    if self.background_paused {
+                        run_ctx.resume();
+                        self.background_paused = false;
+                        self.events.publish(system.frame, MachineEvent::Resumed);
+                    }
+                }
+                _ => {
+                }
+            }
+        }
+
+        return Some(actions);
+    }
+
+    // everything but Quit, which is the main loop's to act on
+    pub fn hotkey(self: &mut Self, action: HotkeyAction, system: &mut System<'_>, mut run_ctx: MachineRunContext) -> MachineRunContext {
+        match action {
+            HotkeyAction::Pause => {
+                if run_ctx.is_paused() {
+                    run_ctx.resume();
+                    self.events.publish(system.frame, MachineEvent::Resumed);
+                    self.osd.show(tr!("osd_resumed"));
+                }
+                else {
+                    run_ctx.pause();
+                    self.events.publish(system.frame, MachineEvent::Paused);
+                    self.osd.show(tr!("osd_paused"));
+                }
+                self.background_paused = false;
+            }
+            // from running, the step keys pause first - from paused, they step
+            HotkeyAction::StepInstruction | HotkeyAction::StepFrame => {
+                if !run_ctx.is_paused() {
+                    run_ctx.pause();
+                    self.events.publish(system.frame, MachineEvent::Paused);
+                }
+                else if action == HotkeyAction::StepInstruction {
+                    run_ctx.step_instruction();
+                }
+                else {
+                    run_ctx.step_frame();
+                }
+                self.background_paused = false;
+            }
+            HotkeyAction::FastForward => {
+                self.pacer.set_speed(if self.pacer.speed() == 1.0 { FAST_FORWARD_SPEED } else { 1.0 });
+                self.osd.show(tr!("osd_speed", self.pacer.speed()));
+            }
+            HotkeyAction::Screenshot => {
+                if let Err(e) = handle_debug_event(DebugEvent::Screenshot { name: String::new() }, system.frame, &self.capture_dir, &self.capture, &mut system.vdp, self.gfx_device) {
+                    self.osd.notify(e);
+                }
+            }
+            HotkeyAction::SaveState => {
+                let path = self.capture_dir.join(format!("state{:08}.nyxs", system.frame));
+
+                match self.save_state(system, &run_ctx, &path) {
+                    Ok(()) => self.osd.notify(tr!("state_saved", system.frame, path.display())),
+                    Err(e) => self.osd.notify(e),
+                }
+            }
+            HotkeyAction::LoadState => {
+                let res = latest_state(&self.capture_dir)
+                    .ok_or(tr!("no_state_to_load", self.capture_dir.display()))
+                    .and_then(|path| savestate::load_restorable(&path, &system.map, self.args.expansion_ram).map(|state| (path, state)));
+
+                match res {
+                    Ok((path, state)) => {
+                        run_ctx = system.load_state(run_ctx, &state, self.gfx_device, false);
+
+                        if let Some(rewind) = &mut self.rewind {
+                            rewind.clear(system.frame);
+                        }
+
+                        self.osd.notify(tr!("state_loaded", system.frame, path.display()));
+                        self.restarted(system);
+                    }
+                    Err(e) => {
+                        self.osd.notify(e);
+                    }
+                }
+            }
+            HotkeyAction::Rewind => {
+                // a movie's input is tied to the frames it was recorded on, so it can't go back in time with the machine
+                if self.recording.is_some() || self.playback.is_some() {
+                    self.osd.notify(tr!("rewind_movie"));
+                }
+                else if let Some(rewind) = &mut self.rewind {
+                    match rewind.step_back(system.frame) {
+                        Some((_, state)) => {
+                            let was_paused = run_ctx.is_paused();
+
+                            run_ctx = system.load_state(run_ctx, &state, self.gfx_device, was_paused);
+
+                            let (count, bytes) = rewind.usage();
+                            self.osd.notify(tr!("rewound", system.frame, count, bytes / (1024 * 1024)));
+                            self.restarted(system);
+                        }
+                        None => {
+                            self.osd.notify(tr!("rewind_empty"));
+                        }
+                    }
+                }
+                else {
+                    self.osd.notify(tr!("rewind_off"));
+                }
+            }
+            HotkeyAction::Reset => {
+                self.osd.notify(tr!("machine_reset"));
+                self.reboot = Some((self.rom.clone(), self.loaded_rom_path.clone()));
+            }
+            HotkeyAction::ReloadRom => {
+                // the same as a reset, but with whatever's on disk now - for when --watch isn't on
+                match &self.loaded_rom_path {
+                    Some(path) => match read_rom(path, &system.map) {
+                        Ok(data) => {
+                            self.osd.notify(tr!("rom_loading", path.display()));
+                            self.reboot = Some((data, Some(path.clone())));
+                        }
+                        Err(e) => {
+                            self.osd.notify(e);
+                        }
+                    },
+                    None => {
+                        self.osd.notify(tr!("rom_reload_no_file"));
+                    }
+                }
+            }
+            HotkeyAction::DumpTextures => {
+                self.texture_dump_armed = true;
+            }
+            HotkeyAction::IsolateDraws | HotkeyAction::IsolatePrev | HotkeyAction::IsolateNext => {
+                let isolation = system.vdp.draw_isolation();
+                let draws = system.vdp.last_frame_draws();
+
+                system.vdp.set_draw_isolation(match action {
+                    HotkeyAction::IsolatePrev => isolation.step(-1, draws),
+                    HotkeyAction::IsolateNext => isolation.step(1, draws),
+                    _ => isolation.next(0),
+                });
+                self.osd.notify(tr!("draw_isolation", system.vdp.draw_isolation().describe(draws)));
+            }
+            HotkeyAction::CaptureVu => {
+                // follows the isolated draw, if any, as vertex lists usually pair up with draws
+                let list = match system.vdp.draw_isolation() {
+                    DrawIsolation::UpTo(n) | DrawIsolation::Only(n) => n,
+                    DrawIsolation::Off => 0,
+                };
+                system.vdp.capture_vu_output(list);
+            }
+            HotkeyAction::RenderDebug => {
+                system.vdp.set_render_debug(system.vdp.render_debug().next());
+                self.osd.notify(tr!("render_debug", system.vdp.render_debug().name()));
+            }
+            HotkeyAction::Quit => {
+            }
+            HotkeyAction::MacroRecord(slot) => {
+                self.input.toggle_macro_recording(slot);
+            }
+            HotkeyAction::MacroPlay(slot) => {
+                self.input.play_macro(slot);
+            }
+        }
+
+        return run_ctx;
+    }
+
+    // answers a remote control request - bar a VU capture, which is answered once the capture is done, or it's given up on
+    pub fn control_request(self: &mut Self, req: ControlRequest, system: &mut System<'_>, mut run_ctx: MachineRunContext) -> MachineRunContext {
+        let mut deferred = false;
+
+        let result = match &req.cmd {
+            ControlCommand::Pause => {
+                let parked = run_ctx.pause();
+                self.events.publish(system.frame, MachineEvent::Paused);
+
+                if parked { Ok(json!(null)) } else { Err(tr!("pause_timeout").to_string()) }
+            }
+            ControlCommand::Resume => {
+                run_ctx.resume();
+                self.events.publish(system.frame, MachineEvent::Resumed);
+                Ok(json!(null))
+            }
+            ControlCommand::Step => {
+                if run_ctx.is_paused() {
+                    run_ctx.step_instruction();
+                    Ok(json!(null))
+                }
+                else {
+                    Err("the CPU has to be paused to step".to_string())
+                }
+            }
+            ControlCommand::StepLine { over } => {
+                // the stop it ends on is reported like any other, once the CPU thread gets there
+                if run_ctx.is_paused() {
+                    system.step_line(&run_ctx, *over);
+                    Ok(json!(null))
+                }
+                else {
+                    Err("the CPU has to be paused to step".to_string())
+                }
+            }
+            ControlCommand::FrameStep => {
+                if run_ctx.is_paused() {
+                    run_ctx.step_frame();
+                    Ok(json!(null))
+                }
+                else {
+                    Err("the CPU has to be paused to step".to_string())
+                }
+            }
+            ControlCommand::Status => {
+                let stop = self.last_stop.as_ref().filter(|_| run_ctx.is_paused());
+                Ok(json!({ "paused": run_ctx.is_paused(), "halted": self.halted, "stop": stop }))
+            }
+            ControlCommand::AddBreakpoint { spec } => {
+                let (ctx, id) = system.change_machine(run_ctx, self.halted.is_some(), |machine| machine.add_breakpoint(spec.clone()));
+                run_ctx = ctx;
+                Ok(json!({ "id": id }))
+            }
+            ControlCommand::AddWatchpoint { addr, len, kind } => {
+                let (ctx, id) = system.change_machine(run_ctx, self.halted.is_some(), |machine| machine.add_watchpoint(*addr, *len, *kind));
+                run_ctx = ctx;
+                Ok(json!({ "id": id }))
+            }
+            ControlCommand::RemoveBreakpoint { id } => {
+                let (ctx, removed) = system.change_machine(run_ctx, self.halted.is_some(), |machine| machine.remove_breakpoint(*id));
+                run_ctx = ctx;
+
+                if removed { Ok(json!(null)) } else { Err(format!("no breakpoint or watchpoint {}", id)) }
+            }
+            ControlCommand::LogLevels { levels } => {
+                log::apply_levels(levels);
+                Ok(json!({ "levels": log::describe_levels() }))
+            }
+            ControlCommand::Breakpoints => {
+                Ok(system.machine.breakpoints().into_iter().map(|(id, desc, hits)| json!({ "id": id, "desc": desc, "hits": hits })).collect())
+            }
+            ControlCommand::Regions => {
+                let map = &system.map;
+                let mut regions = vec![
+                    json!({ "name": "rom", "base": BOOT_ROM_BEGIN, "size": map.boot_rom_size }),
+                    json!({ "name": "ram", "base": map.main_ram_begin, "size": map.main_ram_size }),
+                ];
+
+                if self.args.expansion_ram {
+                    regions.push(json!({ "name": "xram", "base": map.expansion_ram_begin, "size": map.expansion_ram_size }));
+                }
+
+                Ok(json!({ "regions": regions }))
+            }
+            ControlCommand::Peek { addr, len } => {
+                run_ctx.mem_read(*addr, *len)
+                    .map(|data| json!({ "data": control::to_hex(&data) }))
+                    .map_err(|e| format!("read failed: {:?}", e))
+            }
+            ControlCommand::Poke { addr, data } => {
+                run_ctx.mem_write(*addr, data)
+                    .map(|_| json!(null))
+                    .map_err(|e| format!("write failed: {:?}", e))
+            }
+            ControlCommand::Input { data } => {
+                self.uart_input(system, data).map(|_| json!(null))
+            }
+            ControlCommand::Pad { press, release } => {
+                // latched with the rest of the pad at the start of the next frame, where it's recorded to any movie
+                if self.playback.is_some() {
+                    Err("live input is disabled during movie playback".to_string())
+                }
+                else {
+                    Ok(json!({ "buttons": self.input.remote_buttons(*press, *release) }))
+                }
+            }
+            ControlCommand::Reset => {
+                println!("{}", tr!("machine_reset"));
+                self.reboot = Some((self.rom.clone(), self.loaded_rom_path.clone()));
+                Ok(json!(null))
+            }
+            ControlCommand::SaveState { path } => {
+                let path = path.as_ref().map_or(self.capture_dir.join(format!("state{:08}.nyxs", system.frame)), PathBuf::from);
+
+                self.save_state(system, &run_ctx, &path)
+                    .map(|_| json!({ "frame": system.frame, "path": path.display().to_string() }))
+            }
+            ControlCommand::LoadState { path } => {
+                match savestate::load_restorable(Path::new(path), &system.map, self.args.expansion_ram) {
+                    Ok(state) => {
+                        run_ctx = system.load_state(run_ctx, &state, self.gfx_device, false);
+
+                        if let Some(rewind) = &mut self.rewind {
+                            rewind.clear(system.frame);
+                        }
+
+                        self.restarted(system);
+
+                        Ok(json!({ "frame": system.frame }))
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            ControlCommand::Screenshot { path } => {
+                screenshot::save_framebuffer(&mut system.vdp, self.gfx_device, Path::new(path))
+                    .map(|_| json!(null))
+            }
+            ControlCommand::Registers => {
+                Ok(run_ctx.registers().into_iter().map(|(name, val)| (name.to_string(), json!(val))).collect())
+            }
+            ControlCommand::Exceptions => {
+                let total = system.machine.exception_stats().snapshot().to_json();
+                Ok(json!({ "total": total, "per_second": self.exception_monitor.rate().to_json(), "bios": system.machine.bios().to_json() }))
+            }
+            ControlCommand::DumpTextures => {
+                self.texture_dump_armed = true;
+                Ok(json!(null))
+            }
+            ControlCommand::CaptureVu { list } => {
+                if run_ctx.is_paused() || self.halted.is_some() {
+                    Err(tr!("vu_capture_stopped").to_string())
+                }
+                else {
+                    system.vdp.capture_vu_output(*list);
+                    deferred = true;
+                    Ok(json!(null))
+                }
+            }
+            ControlCommand::IsolateDraws { mode, index } => {
+                DrawIsolation::parse(mode, *index).map(|isolation| {
+                    system.vdp.set_draw_isolation(isolation);
+                    json!({ "draws": system.vdp.last_frame_draws() })
+                })
+            }
+        };
+
+        if deferred {
+            self.vu_waiters.push((req, system.frame));
+        }
+        else {
+            req.reply(result);
+        }
+
+        return run_ctx;
+    }
+
+    // live UART input, from the terminal or a control client - held back to the next frame in deterministic mode, & refused
+    // while a movie drives the machine
+    pub fn uart_input(self: &mut Self, system: &mut System<'_>, data: &[u8]) -> Result<(), String> {
+        if self.playback.is_some() {
+            return Err("live input is disabled during movie playback".to_string());
+        }
+
+        if self.args.deterministic {
+            self.uart_pending.extend(data);
+            return Ok(());
+        }
+
+        if let Some(movie) = &mut self.recording {
+            movie.record(system.frame, MOVIEEVENT_UART_INPUT, data);
+        }
+
+        system.uart.push_input(data);
+        return Ok(());
+    }
+
+    // input for the coming frame. during playback the movie drives the pad instead
+    pub fn frame_input(self: &mut Self, frame: u64) -> FrameInput {
+        let mut frame_input = FrameInput::default();
+
+        if self.playback.is_none() {
+            let buttons = self.input.frame(frame);
+
+            if buttons != self.last_buttons {
+                if let Some(movie) = &mut self.recording {
+                    movie.record(frame, MOVIEEVENT_PAD, &buttons.to_le_bytes());
+                }
+                self.last_buttons = buttons;
+            }
+
+            frame_input.pad = Some(buttons);
+        }
+
+        if let Some(movie) = &mut self.playback {
+            while let Some(ev) = movie.next_event(frame + 1) {
+                if ev.kind == MOVIEEVENT_UART_INPUT {
+                    frame_input.uart.extend(&ev.data);
+                }
+                else if ev.kind == MOVIEEVENT_PAD && ev.data.len() == 4 {
+                    frame_input.pad = Some(u32::from_le_bytes(ev.data[..].try_into().unwrap()));
+                }
+            }
+        }
+
+        // deterministic mode holds live input back to here, so it's only seen from the next frame on
+        if !self.uart_pending.is_empty() {
+            if let Some(movie) = &mut self.recording {
+                movie.record(frame + 1, MOVIEEVENT_UART_INPUT, &self.uart_pending);
+            }
+
+            frame_input.uart.append(&mut self.uart_pending);
+        }
+
+        return frame_input;
+    }
+
+    // the machine picks up somewhere new - a state, a rewind or a reboot - so whatever it was doing before is over
+    pub fn restarted(self: &mut Self, system: &mut System<'_>) {
+        self.events.publish(system.frame, MachineEvent::Started);
+        self.fail_vu_waiters(&mut system.vdp, tr!("vu_capture_reset"));
+        self.halted = None;
+    }
+
+    // a dead guest leaves the window up on its last frame, with what happened & how to recover on the console (& in the
+    // title bar, which WindowTitle sees to)
+    pub fn halt(self: &mut Self, frame: u64, reason: String) {
+        println!("{}", tr!("guest_halted", reason, self.hotkeys.describe(HotkeyAction::Reset), self.hotkeys.describe(HotkeyAction::LoadState)));
+        self.events.publish(frame, MachineEvent::Halted { reason: reason.clone() });
+        self.halted = Some(reason);
+    }
+
+    // answers every control client still waiting on a VU capture with an error, & drops the capture they were waiting on
+    pub fn fail_vu_waiters(self: &mut Self, vdp: &mut VDP, reason: &str) {
+        if self.vu_waiters.is_empty() {
+            return;
+        }
+
+        vdp.cancel_vu_capture();
+
+        for (req, _) in self.vu_waiters.drain(..) {
+            req.reply(Err(reason.to_string()));
+        }
+    }
+
+    // a CPU that never parked is still running, so anything saved then would be torn. it carries on afterwards unless it
+    // was already paused
+    fn save_state(self: &Self, system: &mut System<'_>, run_ctx: &MachineRunContext, path: &Path) -> Result<(), String> {
+        let was_paused = run_ctx.is_paused();
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+        let res = if run_ctx.pause() { Ok(()) } else { Err(tr!("pause_timeout").to_string()) }
+            .and_then(|_| fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e)))
+            .and_then(|_| system.save_state(run_ctx, self.gfx_device))
+            .and_then(|state| state.save(path).map_err(|e| format!("failed to write {}: {}", path.display(), e)));
+
+        if !was_paused {
+            run_ctx.resume();
+        }
+
+        return res;
+    }
+
+    // an unopened gamepad never sends button events - the handle has to stay alive for as long as its input is wanted
+    pub fn open_gamepad(self: &mut Self, id: u32) {
+        let Some(gamepad_sys) = &self.gamepad_sys else {
+            return;
+        };
+
+        if self.open_gamepads.contains_key(&id) {
+            return;
+        }
+
+        match gamepad_sys.open(id) {
+            Ok(gamepad) => {
+                self.open_gamepads.insert(id, gamepad);
+            }
+            Err(e) => {
+                eprintln!("{}", tr!("gamepad_open_failed", id, e));
+            }
+        }
+    }
+}
+
+// Load State picks up the most recently written save state in the capture directory
+fn latest_state(dir: &Path) -> Option<PathBuf> {
+    return fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "nyxs"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path);
+}
@@ -0,0 +1,117 @@
+use std::{fs, io::{self, Read}, path::{Path, PathBuf}};
+use flate2::read::GzDecoder;
+
+use nyxbox_core::savestate::SaveState;
+
+// the compatibility corpus is a directory of save states written by earlier builds, plus a corpus.txt saying what loading
+// each one should do:
+//
+//   # comment
+//   v1_regs_only.nyxs       ok
+//   v3_future.nyxs          error newer than this build supports
+//
+// "ok" states must load & pass validation. "error" states must be refused with a message containing the given text, so a
+// state from an unsupported version always gets a clear version error rather than whatever the parser trips over first.
+// when the state format changes, add a state written by the last release before bumping STATE_VERSION
+// an entry ending in .gz is gunzipped before loading - the full states are mostly empty RAM & VRAM, so they're kept
+// compressed
+pub const CORPUS_MANIFEST: &str = "corpus.txt";
+
+enum Expect {
+    Ok,
+    Error(String),
+}
+
+fn load_manifest(dir: &Path) -> Result<Vec<(PathBuf, Expect)>, String> {
+    let path = dir.join(CORPUS_MANIFEST);
+    let text = fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let mut entries = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((file, rest)) = line.split_once(char::is_whitespace) else {
+            return Err(format!("{}:{}: expected '<file> ok' or '<file> error <message>'", path.display(), idx + 1));
+        };
+
+        let rest = rest.trim();
+        let (kind, msg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+        let expect = match kind {
+            "ok" => Expect::Ok,
+            "error" => Expect::Error(msg.trim().to_string()),
+            _ => return Err(format!("{}:{}: unknown expectation '{}'", path.display(), idx + 1, kind)),
+        };
+
+        entries.push((dir.join(file), expect));
+    }
+
+    return Ok(entries);
+}
+
+fn read_state(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut state = Vec::new();
+        GzDecoder::new(&data[..]).read_to_end(&mut state)?;
+        return Ok(state);
+    }
+
+    return Ok(data);
+}
+
+pub fn check_corpus(dir: &Path) {
+    let entries = match load_manifest(dir) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut failed = 0;
+
+    for (path, expect) in &entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let res = match read_state(path) {
+            Ok(data) => SaveState::from_bytes(&data),
+            Err(e) => {
+                println!("FAIL {}: {}", name, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        match (res, expect) {
+            (Ok(state), Expect::Ok) => {
+                println!("PASS {} (version {})", name, state.version);
+            }
+            (Err(e), Expect::Error(msg)) if e.contains(msg.as_str()) => {
+                println!("PASS {} ({})", name, e);
+            }
+            (Err(e), Expect::Ok) => {
+                println!("FAIL {}: {}", name, e);
+                failed += 1;
+            }
+            (Err(e), Expect::Error(msg)) => {
+                println!("FAIL {}: expected an error containing '{}', got '{}'", name, msg, e);
+                failed += 1;
+            }
+            (Ok(_), Expect::Error(msg)) => {
+                println!("FAIL {}: loaded, but should have failed with '{}'", name, msg);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", entries.len() - failed, failed);
+
+    if failed != 0 {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{collections::VecDeque, env, fs, path::{Path, PathBuf}, process::{Command, Stdio}, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use clap::Args;
+
+use crate::config;
+
+// a guest test is a ROM image with an assertion script of the same name next to it, & optionally a file of extra `run`
+// arguments (e.g. "--hw-model strict") for tests that need a particular machine configuration
+pub const TEST_ROM_EXT: &str = "bin";
+pub const TEST_SCRIPT_EXT: &str = "assert";
+pub const TEST_ARGS_EXT: &str = "args";
+
+#[derive(Args)]
+pub struct TestArgs {
+    /// Directory containing test ROMs (*.bin) and their assertion scripts (*.assert)
+    dir: PathBuf,
+
+    /// Number of instances to run in parallel (defaults to the number of CPUs)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Seconds before a test is killed and counted as a failure
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+
+    /// Root for per-test state (saves, captures) - each test gets its own subdirectory
+    #[arg(long, default_value = "test-out")]
+    work_dir: PathBuf,
+}
+
+struct TestCase {
+    name: String,
+    rom: PathBuf,
+    script: PathBuf,
+    extra_args: Vec<String>,
+}
+
+enum TestResult {
+    Pass,
+    Fail(String),
+}
+
+fn collect_tests(dir: &Path) -> Result<Vec<TestCase>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+    let mut tests = Vec::new();
+
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+
+        if path.extension().and_then(|v| v.to_str()) != Some(TEST_ROM_EXT) {
+            continue;
+        }
+
+        let script = path.with_extension(TEST_SCRIPT_EXT);
+        if !script.exists() {
+            println!("skipping {}: no {} script", path.display(), TEST_SCRIPT_EXT);
+            continue;
+        }
+
+        let args_path = path.with_extension(TEST_ARGS_EXT);
+        let extra_args = if args_path.exists() {
+            let text = fs::read_to_string(&args_path).map_err(|e| format!("failed to read {}: {}", args_path.display(), e))?;
+            text.split_whitespace().map(str::to_string).collect()
+        }
+        else {
+            Vec::new()
+        };
+
+        tests.push(TestCase {
+            name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            rom: path,
+            script,
+            extra_args,
+        });
+    }
+
+    tests.sort_by(|a, b| a.name.cmp(&b.name));
+    return Ok(tests);
+}
+
+// each test runs as its own headless emulator process, so instances can't trample each other's state (& a test run doesn't
+// need a display)
+fn run_test(exe: &Path, test: &TestCase, args: &TestArgs) -> TestResult {
+    let out_dir = args.work_dir.join(&test.name);
+    if let Err(e) = fs::create_dir_all(&out_dir) {
+        return TestResult::Fail(format!("failed to create {}: {}", out_dir.display(), e));
+    }
+
+    let log = match fs::File::create(out_dir.join("output.log")) {
+        Ok(v) => v,
+        Err(e) => return TestResult::Fail(format!("failed to create log: {}", e)),
+    };
+
+    let child = Command::new(exe)
+        .arg("run")
+        .arg(&test.rom)
+        .env(config::CONFIG_ENV, "")
+        .arg("--headless")
+        .arg("--assert-script").arg(&test.script)
+        .arg("--save-dir").arg(out_dir.join("saves"))
+        .arg("--capture-dir").arg(out_dir.join("captures"))
+        .args(&test.extra_args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().unwrap())
+        .stderr(log)
+        .spawn();
+
+    let mut child = match child {
+        Ok(v) => v,
+        Err(e) => return TestResult::Fail(format!("failed to start emulator: {}", e)),
+    };
+
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                return TestResult::Pass;
+            }
+            Ok(Some(status)) => {
+                return TestResult::Fail(format!("{} (see {})", status, out_dir.join("output.log").display()));
+            }
+            Ok(None) => {
+            }
+            Err(e) => {
+                return TestResult::Fail(e.to_string());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return TestResult::Fail(format!("timed out after {}s", args.timeout));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+pub fn test_cmd(args: &TestArgs) {
+    let tests = match collect_tests(&args.dir) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let exe = env::current_exe().unwrap();
+    let jobs = args.jobs.unwrap_or(thread::available_parallelism().map(|v| v.get()).unwrap_or(1)).max(1);
+
+    let total = tests.len();
+    let queue = Arc::new(Mutex::new(tests.into_iter().collect::<VecDeque<_>>()));
+    let failed = Arc::new(Mutex::new(Vec::new()));
+
+    println!("running {} tests with {} workers", total, jobs);
+
+    thread::scope(|s| {
+        for _ in 0..jobs {
+            let queue = queue.clone();
+            let failed = failed.clone();
+            let exe = &exe;
+
+            s.spawn(move || {
+                loop {
+                    let Some(test) = queue.lock().unwrap().pop_front() else {
+                        break;
+                    };
+
+                    let start = Instant::now();
+                    let res = run_test(exe, &test, args);
+                    let elapsed = start.elapsed().as_secs_f64();
+
+                    match res {
+                        TestResult::Pass => {
+                            println!("PASS {} ({:.2}s)", test.name, elapsed);
+                        }
+                        TestResult::Fail(e) => {
+                            println!("FAIL {} ({:.2}s): {}", test.name, elapsed, e);
+                            failed.lock().unwrap().push(test.name);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.lock().unwrap().clone();
+    failed.sort();
+
+    println!("{} passed, {} failed", total - failed.len(), failed.len());
+    for name in &failed {
+        println!("  {}", name);
+    }
+
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,54 @@
+use std::{fs, path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// polls a file's modification time - a change is only reported once the file has stopped changing for a full poll interval,
+// so we don't pick up a binary while the toolchain is still halfway through writing it
+pub struct FileWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    pending: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            last_modified: Self::modified_time(path),
+            pending: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn path(self: &Self) -> &Path {
+        return &self.path;
+    }
+
+    pub fn poll(self: &mut Self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = Self::modified_time(&self.path);
+
+        if modified.is_none() || modified == self.last_modified {
+            self.pending = None;
+            return false;
+        }
+
+        if self.pending == modified {
+            self.pending = None;
+            self.last_modified = modified;
+            return true;
+        }
+
+        self.pending = modified;
+        return false;
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        return fs::metadata(path).and_then(|m| m.modified()).ok();
+    }
+}
@@ -0,0 +1,150 @@
+use std::{fs, path::{Path, PathBuf}, process::Command};
+
+use sdl3::gpu::{Device, ShaderFormat};
+
+// runs the guest test ROMs in tests/roms through `nyxbox test`, so `cargo test` covers the CPU & peripherals end to end.
+// the ROMs are assembled from source first (with llvm-mc & llvm-objcopy, like build-test-roms.sh), into a scratch
+// directory rather than the source tree. without the tools, prebuilt .bin files next to the sources are used instead
+//
+// the emulator needs a Vulkan-capable GPU & the compiled shaders in content/shaders to run at all. on a machine without
+// either (a CI runner with no GPU, a checkout without the shaders) the test fails & says why, unless NYXBOX_SKIP_GPU_TESTS
+// is set - a skip has to be asked for, so a broken setup can't pass as a green run
+
+const ASSEMBLER: &str = "llvm-mc";
+const OBJCOPY: &str = "llvm-objcopy";
+
+const SKIP_VAR: &str = "NYXBOX_SKIP_GPU_TESTS";
+
+// the emulator loads its shaders, monitor ROM & the like relative to the checkout, so it runs from there
+fn workspace_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+}
+
+// why the emulator can't start here, if it can't
+fn missing_prerequisite() -> Option<String> {
+    for path in nyxbox_core::vdp::SHADER_PATHS {
+        match fs::read(workspace_dir().join(path)) {
+            Err(e) => return Some(format!("{} can't be read ({}) - run build-shaders.sh", path, e)),
+            Ok(data) if data.starts_with(b"version https://git-lfs") => return Some(format!("{} is a git LFS pointer - run build-shaders.sh", path)),
+            Ok(_) => {}
+        }
+    }
+
+    let sdl = match sdl3::init() {
+        Ok(sdl) => sdl,
+        Err(e) => return Some(format!("SDL failed to initialize ({})", e)),
+    };
+
+    let gpu = Device::new(ShaderFormat::SpirV, false).err().map(|e| format!("no Vulkan-capable GPU ({})", e));
+    drop(sdl);
+
+    return gpu;
+}
+
+fn roms_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("tests").join("roms");
+}
+
+fn assemble(src: &Path, out_dir: &Path) -> Result<PathBuf, String> {
+    let obj = out_dir.join(src.file_name().unwrap()).with_extension("o");
+    let bin = obj.with_extension("bin");
+
+    let status = Command::new(ASSEMBLER)
+        .args(["-triple=armv6-none-eabi", "-mcpu=arm1176jzf-s", "-filetype=obj"])
+        .arg("-I").arg(roms_dir())
+        .arg("-I").arg(workspace_dir().join("guest-src"))
+        .arg(src)
+        .arg("-o").arg(&obj)
+        .status()
+        .map_err(|e| format!("failed to run {}: {}", ASSEMBLER, e))?;
+
+    if !status.success() {
+        return Err(format!("{} failed on {} ({})", ASSEMBLER, src.display(), status));
+    }
+
+    let status = Command::new(OBJCOPY)
+        .args(["-O", "binary"])
+        .arg(&obj)
+        .arg(&bin)
+        .status()
+        .map_err(|e| format!("failed to run {}: {}", OBJCOPY, e))?;
+
+    if !status.success() {
+        return Err(format!("{} failed on {} ({})", OBJCOPY, obj.display(), status));
+    }
+
+    let _ = fs::remove_file(&obj);
+    return Ok(bin);
+}
+
+// every test's ROM, assertion script, & extra arguments, gathered in one directory for the runner
+fn stage_roms(out_dir: &Path) -> Vec<String> {
+    let _ = fs::remove_dir_all(out_dir);
+    fs::create_dir_all(out_dir).unwrap();
+
+    let mut sources: Vec<PathBuf> = fs::read_dir(roms_dir()).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("s"))
+        .collect();
+    sources.sort();
+
+    let mut names = Vec::new();
+
+    for src in &sources {
+        let name = src.file_stem().unwrap().to_string_lossy().into_owned();
+
+        if let Err(e) = assemble(src, out_dir) {
+            let prebuilt = src.with_extension("bin");
+            assert!(prebuilt.exists(), "{} (& no prebuilt {} - run build-test-roms.sh)", e, prebuilt.display());
+            fs::copy(&prebuilt, out_dir.join(prebuilt.file_name().unwrap())).unwrap();
+        }
+
+        for ext in ["assert", "args"] {
+            let path = src.with_extension(ext);
+
+            if path.exists() {
+                fs::copy(&path, out_dir.join(path.file_name().unwrap())).unwrap();
+            }
+        }
+
+        names.push(name);
+    }
+
+    return names;
+}
+
+#[test]
+fn guest_roms() {
+    if let Some(why) = missing_prerequisite() {
+        if std::env::var_os(SKIP_VAR).is_some() {
+            println!("skipping the guest ROM tests ({} is set): {}", SKIP_VAR, why);
+            return;
+        }
+
+        panic!("the guest ROM tests can't run: {} (set {} to skip them on machines without a GPU)", why, SKIP_VAR);
+    }
+
+    let scratch = Path::new(env!("CARGO_TARGET_TMPDIR")).join("guest-roms");
+    let roms = scratch.join("roms");
+    let names = stage_roms(&roms);
+
+    assert!(!names.is_empty(), "no test ROM sources in {}", roms_dir().display());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nyxbox"))
+        .current_dir(workspace_dir())
+        .arg("test")
+        .arg(&roms)
+        .arg("--work-dir").arg(scratch.join("out"))
+        .output()
+        .expect("failed to run nyxbox");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    print!("{}", stdout);
+
+    // a ROM without a script would be skipped rather than failed, which shouldn't pass unnoticed
+    for name in &names {
+        assert!(stdout.contains(&format!("PASS {} ", name)) || stdout.contains(&format!("FAIL {} ", name)), "{} didn't run", name);
+    }
+
+    assert!(output.status.success(), "guest ROM tests failed (logs in {})\n{}", scratch.join("out").display(), String::from_utf8_lossy(&output.stderr));
+}
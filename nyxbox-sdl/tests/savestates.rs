@@ -0,0 +1,23 @@
+use std::{path::Path, process::Command};
+
+// runs `nyxbox state check` over the save state compatibility corpus in tests/savestates, so `cargo test` catches a
+// format change that breaks loading states from earlier builds. it only parses the states, so unlike the guest ROM tests
+// it needs no GPU
+
+#[test]
+fn savestate_corpus() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nyxbox"))
+        .current_dir(&workspace)
+        .args(["state", "check"])
+        .arg(Path::new("tests").join("savestates"))
+        .output()
+        .expect("failed to run nyxbox");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    print!("{}", stdout);
+
+    assert!(stdout.contains(" passed, 0 failed"), "the save state corpus didn't run\n{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success(), "save states in the corpus loaded differently than corpus.txt expects\n{}", String::from_utf8_lossy(&output.stderr));
+}
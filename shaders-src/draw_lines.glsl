@@ -0,0 +1,274 @@
+#version 450
+layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+#define REG_FBDIM                0
+#define REG_FBADDR               1
+#define REG_DBADDR               2
+#define REG_VUSTRIDE             3
+#define REG_VULAYOUT0            4
+#define REG_VUCDATA0             12
+#define REG_VUPROGADDR           76
+#define REG_FOGENCOL             77
+#define REG_FOGTBL0              78
+#define REG_CLIPXY               142
+#define REG_CLIPWH               143
+#define REG_VPXY                 144
+#define REG_VPWH                 145
+#define REG_DEPTH                146
+#define REG_BLEND                147
+#define REG_CULL                 148
+#define REG_TUCONF               149
+#define REG_TU0ADDR              150
+#define REG_TU1ADDR              151
+#define REG_TCOMBINE             152
+
+// host-side render debug modes (see src/renderdebug.rs)
+#define DEBUGMODE_NONE           0
+#define DEBUGMODE_WIREFRAME      1
+#define DEBUGMODE_OVERDRAW       2
+
+// how vertices make up lines (see src/vdp.rs)
+#define TOPOLOGY_LIST            0
+#define TOPOLOGY_STRIP           1
+
+layout(std430, set = 0, binding = 0) readonly buffer Params {
+    uint data[256];
+} params;
+
+layout(std430, set = 1, binding = 0) buffer VRAM {
+    uint data[];
+} vram;
+
+// per-pixel count of lines covering it, indexed like the framebuffer (only written in overdraw mode)
+layout(std430, set = 1, binding = 1) buffer Overdraw {
+    uint count[];
+} overdraw;
+
+// pixels written this frame, summed over every draw for the STATPIXELS register (see src/vdp.rs)
+layout(std430, set = 1, binding = 2) buffer Pixels {
+    uint count;
+} pixels;
+
+layout(std140, set = 2, binding = 0) uniform UBO {
+    uint addr;
+    uint debugMode;
+    uint topology;
+} ubo;
+
+struct VertexData {
+    vec4 position;
+    vec4 color0;
+};
+
+vec4 loadVec4(uint addr) {
+    vec4 outdata;
+    outdata.x = uintBitsToFloat(vram.data[addr]);
+    outdata.y = uintBitsToFloat(vram.data[addr + 1]);
+    outdata.z = uintBitsToFloat(vram.data[addr + 2]);
+    outdata.w = uintBitsToFloat(vram.data[addr + 3]);
+    return outdata;
+}
+
+vec4 loadUNorm4(uint addr) {
+    uint val = vram.data[addr];
+    vec4 outdata;
+    outdata.x = float(bitfieldExtract(val, 0, 8)) / 255.0;
+    outdata.y = float(bitfieldExtract(val, 8, 8)) / 255.0;
+    outdata.z = float(bitfieldExtract(val, 16, 8)) / 255.0;
+    outdata.w = float(bitfieldExtract(val, 24, 8)) / 255.0;
+    return outdata;
+}
+
+// whether all 10 words of a vertex are in VRAM - the command processor checks a list's extent, this keeps a bad one
+// from reading past the end of the buffer anyway
+bool vertexInVram(uint addr) {
+    return addr < vram.data.length() && vram.data.length() - addr >= 10;
+}
+
+// lines only use a vertex's position & first color, but take the same 10 word vertices as triangles. a vertex outside
+// VRAM loads as all zeroes, but main skips its line first
+VertexData loadVertex(uint addr) {
+    VertexData vdata;
+
+    if (!vertexInVram(addr)) {
+        vdata.position = vec4(0.0, 0.0, 0.0, 1.0);
+        vdata.color0 = vec4(0.0);
+        return vdata;
+    }
+
+    vdata.position = loadVec4(addr);
+    vdata.color0 = loadUNorm4(addr + 8);
+    return vdata;
+}
+
+// screen space, still as floats - a vertex with w near zero lands far off screen, & has to be clipped before it's
+// turned into pixel coordinates
+vec2 ndcToScreen(vec2 ndc, vec4 vp) {
+    ndc = ndc * 0.5 + 0.5;
+    return (ndc * vp.zw) + vp.xy;
+}
+
+// the pixels a line may touch, as inclusive bounds: the framebuffer, cut down to the scissor rectangle if there is one
+// (see src/vdp.rs) - the debug modes show everything
+ivec4 clipRect(uvec2 fb_wh) {
+    uint clip_xy = params.data[REG_CLIPXY];
+    uint clip_wh = params.data[REG_CLIPWH];
+
+    if (ubo.debugMode != DEBUGMODE_NONE || clip_wh == 0) {
+        return ivec4(0, 0, ivec2(fb_wh) - 1);
+    }
+
+    ivec2 lo = ivec2(clip_xy & 0xFFFF, clip_xy >> 16);
+    ivec2 hi = lo + ivec2(clip_wh & 0xFFFF, clip_wh >> 16) - 1;
+    return ivec4(lo, min(hi, ivec2(fb_wh) - 1));
+}
+
+// Liang-Barsky: the part of the segment from a to b inside [lo, hi], as the fractions t0 to t1 of the way along it. false
+// if none of it is
+bool clipSegment(vec2 a, vec2 b, vec2 lo, vec2 hi, out float t0, out float t1) {
+    t0 = 0.0;
+    t1 = 1.0;
+
+    vec2 d = b - a;
+    float p[4] = float[4](-d.x, d.x, -d.y, d.y);
+    float q[4] = float[4](a.x - lo.x, hi.x - a.x, a.y - lo.y, hi.y - a.y);
+
+    for (int i = 0; i < 4; i++) {
+        if (p[i] == 0.0) {
+            if (q[i] < 0.0) {
+                return false;
+            }
+        }
+        else if (p[i] < 0.0) {
+            t0 = max(t0, q[i] / p[i]);
+        }
+        else {
+            t1 = min(t1, q[i] / p[i]);
+        }
+    }
+
+    return t0 <= t1;
+}
+
+// returns whether the pixel was drawn (or counted, in overdraw mode)
+bool plot(uint fbAddr, uvec2 fbDim, ivec4 clip, ivec2 p, vec4 col) {
+    // the segment was clipped to this already, but rounding can put an end a pixel over
+    if (any(lessThan(p, clip.xy)) || any(greaterThan(p, clip.zw))) {
+        return false;
+    }
+
+    uint idx = uint(p.y) * fbDim.x + uint(p.x);
+
+    if (ubo.debugMode == DEBUGMODE_OVERDRAW) {
+        if (idx < overdraw.count.length()) {
+            atomicAdd(overdraw.count[idx], 1);
+            return true;
+        }
+        return false;
+    }
+
+    // the command processor checks the framebuffer is in VRAM before drawing, but never write outside it regardless
+    if (fbAddr >= vram.data.length() || vram.data.length() - fbAddr <= idx) {
+        return false;
+    }
+
+    vram.data[fbAddr + idx] = ubo.debugMode == DEBUGMODE_WIREFRAME ? 0xFFFFFFFF : packUnorm4x8(col);
+    return true;
+}
+
+void main() {
+    // each work group processes one line of input
+
+    // NOTE: vertex size is 10 words, as for triangles
+    // - 4 words for position
+    // - 4 words for texcoord 0 UV + texcoord 1 UV (unused)
+    // - 2 words for col + ocol (ocol unused)
+
+    // a list has two vertices of its own per line, while a strip's lines each start where the last one ended
+    uint base_addr = ubo.addr + (gl_WorkGroupID.x * (ubo.topology == TOPOLOGY_STRIP ? 10 : 20));
+
+    if (!vertexInVram(base_addr) || !vertexInVram(base_addr + 10)) {
+        return;
+    }
+
+    VertexData v0 = loadVertex(base_addr);
+    VertexData v1 = loadVertex(base_addr + 10);
+
+    // clip space to NDC
+    v0.position /= v0.position.w;
+    v1.position /= v1.position.w;
+
+    // get viewport
+    uint vp_xy = params.data[REG_VPXY];
+    uint vp_wh = params.data[REG_VPWH];
+    vec4 vp = vec4(
+        vp_xy & 0xFFFF,
+        vp_xy >> 16,
+        vp_wh & 0xFFFF,
+        vp_wh >> 16
+    );
+
+    vec2 sa = ndcToScreen(v0.position.xy, vp);
+    vec2 sb = ndcToScreen(v1.position.xy, vp);
+
+    uint fb_addr = params.data[REG_FBADDR];
+    uint fb_dim = params.data[REG_FBDIM];
+    uvec2 fb_wh = uvec2(
+        fb_dim & 0xFFFF,
+        fb_dim >> 16
+    );
+    ivec4 clip = clipRect(fb_wh);
+
+    if (any(isnan(vec4(sa, sb))) || any(isinf(vec4(sa, sb))) || clip.x > clip.z || clip.y > clip.w) {
+        return;
+    }
+
+    // cut the line down to the pixels it can touch before stepping along it, so a wild vertex can't have a work group
+    // stepping through millions of pixels it'll never plot. a pixel covers [x, x + 1), hence the far edges
+    float t0, t1;
+    if (!clipSegment(sa, sb, vec2(clip.xy), vec2(clip.zw + 1), t0, t1)) {
+        return;
+    }
+
+    ivec2 a = ivec2(mix(sa, sb, t0));
+    ivec2 b = ivec2(mix(sa, sb, t1));
+    vec4 c0 = mix(v0.color0, v1.color0, t0);
+    vec4 c1 = mix(v0.color0, v1.color0, t1);
+
+    // width 1 Bresenham, both ends included - a strip's joints are drawn by the lines either side of them. color is
+    // interpolated along the line's major axis
+    int dx = abs(b.x - a.x);
+    int dy = -abs(b.y - a.y);
+    int sx = a.x < b.x ? 1 : -1;
+    int sy = a.y < b.y ? 1 : -1;
+    int err = dx + dy;
+    int steps = max(dx, -dy);
+
+    ivec2 p = a;
+    uint drawn = 0;
+
+    for (int i = 0; i <= steps; i++) {
+        float t = steps == 0 ? 0.0 : float(i) / float(steps);
+
+        if (plot(fb_addr, fb_wh, clip, p, mix(c0, c1, t))) {
+            drawn++;
+        }
+
+        int e2 = 2 * err;
+
+        if (e2 >= dy) {
+            err += dy;
+            p.x += sx;
+        }
+
+        if (e2 <= dx) {
+            err += dx;
+            p.y += sy;
+        }
+    }
+
+    // one atomic per line rather than per pixel
+    if (drawn != 0) {
+        atomicAdd(pixels.count, drawn);
+    }
+}
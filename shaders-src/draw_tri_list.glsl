@@ -1,5 +1,10 @@
 #version 450
-layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+// a work group is a TILE_SIZE x TILE_SIZE tile of the framebuffer, one invocation per pixel (see main)
+#define TILE_SIZE                8
+#define TILE_PIXELS              (TILE_SIZE * TILE_SIZE)
+
+layout (local_size_x = TILE_SIZE, local_size_y = TILE_SIZE, local_size_z = 1) in;
 
 #define REG_FBDIM                0
 #define REG_FBADDR               1
@@ -22,6 +27,39 @@ layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
 #define REG_TU1ADDR              151
 #define REG_TCOMBINE             152
 
+// host-side render debug modes (see src/renderdebug.rs)
+#define DEBUGMODE_NONE           0
+#define DEBUGMODE_WIREFRAME      1
+#define DEBUGMODE_OVERDRAW       2
+
+// how vertices make up triangles (see src/vdp.rs)
+#define TOPOLOGY_LIST            0
+#define TOPOLOGY_STRIP           1
+
+// the DEPTH register: bit 0 enables the test, bits 1-3 pick the compare & bit 4 enables writes (see src/vdp.rs)
+#define DEPTHBIT_ENABLE          1
+#define DEPTH_FUNC_SHIFT         1
+#define DEPTH_FUNC_MASK          7
+#define DEPTHBIT_WRITE           16
+
+#define DEPTHFUNC_NEVER          0
+#define DEPTHFUNC_LESS           1
+#define DEPTHFUNC_EQUAL          2
+#define DEPTHFUNC_LEQUAL         3
+#define DEPTHFUNC_GREATER        4
+#define DEPTHFUNC_NOTEQUAL       5
+#define DEPTHFUNC_GEQUAL         6
+#define DEPTHFUNC_ALWAYS         7
+
+// the BLEND register: bits 0-2 pick how a triangle's color combines with the framebuffer's (see src/vdp.rs)
+#define BLEND_MODE_MASK          7
+
+#define BLENDMODE_OPAQUE         0
+#define BLENDMODE_ALPHA          1
+#define BLENDMODE_ADD            2
+#define BLENDMODE_SUBTRACT       3
+#define BLENDMODE_MULTIPLY       4
+
 layout(std430, set = 0, binding = 0) readonly buffer Params {
     uint data[256];
 } params;
@@ -30,8 +68,21 @@ layout(std430, set = 1, binding = 0) buffer VRAM {
     uint data[];
 } vram;
 
+// per-pixel count of triangles covering it, indexed like the framebuffer (only written in overdraw mode)
+layout(std430, set = 1, binding = 1) buffer Overdraw {
+    uint count[];
+} overdraw;
+
+// pixels written this frame, summed over every draw for the STATPIXELS register (see src/vdp.rs)
+layout(std430, set = 1, binding = 2) buffer Pixels {
+    uint count;
+} pixels;
+
 layout(std140, set = 2, binding = 0) uniform UBO {
     uint addr;
+    uint debugMode;
+    uint topology;
+    uint count;
 } ubo;
 
 struct VertexData {
@@ -70,8 +121,25 @@ vec4 loadUNorm4(uint addr) {
     return outdata;
 }
 
+// whether all 10 words of a vertex are in VRAM - the command processor checks a list's extent, this keeps a bad one
+// from reading past the end of the buffer anyway
+bool vertexInVram(uint addr) {
+    return addr < vram.data.length() && vram.data.length() - addr >= 10;
+}
+
+// a vertex outside VRAM loads as all zeroes, but the callers skip its primitive first
 VertexData loadVertex(uint addr) {
     VertexData vdata;
+
+    if (!vertexInVram(addr)) {
+        vdata.position = vec4(0.0, 0.0, 0.0, 1.0);
+        vdata.texcoord0 = vec2(0.0);
+        vdata.texcoord1 = vec2(0.0);
+        vdata.color0 = vec4(0.0);
+        vdata.color1 = vec4(0.0);
+        return vdata;
+    }
+
     vdata.position = loadVec4(addr);
     vdata.texcoord0 = loadVec2(addr + 4);
     vdata.texcoord1 = loadVec2(addr + 6);
@@ -90,49 +158,372 @@ void setColor(uint fbAddr, uvec2 fbDim, uvec2 coord, uint col) {
     vram.data[pxAddr] = col;
 }
 
-void main() {
-    // each work group processes one triangle of input
+// returns the number of pixels drawn
+uint drawLine(uint fbAddr, uvec2 fbDim, ivec2 a, ivec2 b, uint col) {
+    ivec2 d = b - a;
+    int steps = max(abs(d.x), abs(d.y));
+    uint drawn = 0;
 
+    for (int i = 0; i <= steps; i++) {
+        ivec2 p = steps == 0 ? a : a + (d * i) / steps;
+
+        if (p.x >= 0 && p.y >= 0 && p.x < int(fbDim.x) && p.y < int(fbDim.y)) {
+            setColor(fbAddr, fbDim, uvec2(p), col);
+            drawn++;
+        }
+    }
+
+    return drawn;
+}
+
+float edge(vec2 a, vec2 b, vec2 p) {
+    return (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+}
+
+// bump the overdraw count of every pixel whose center the triangle covers (either winding). returns the number of them
+uint countCoverage(uvec2 fbDim, ivec2 v0, ivec2 v1, ivec2 v2) {
+    float area = edge(vec2(v0), vec2(v1), vec2(v2));
+    if (area == 0.0) {
+        return 0;
+    }
+
+    ivec2 lo = max(min(min(v0, v1), v2), ivec2(0));
+    ivec2 hi = min(max(max(v0, v1), v2), ivec2(fbDim) - 1);
+    uint covered = 0;
+
+    for (int y = lo.y; y <= hi.y; y++) {
+        for (int x = lo.x; x <= hi.x; x++) {
+            vec2 p = vec2(x, y) + 0.5;
+            vec3 w = vec3(edge(vec2(v1), vec2(v2), p), edge(vec2(v2), vec2(v0), p), edge(vec2(v0), vec2(v1), p)) * sign(area);
+
+            uint idx = uint(y) * fbDim.x + uint(x);
+            if (all(greaterThanEqual(w, vec3(0.0))) && idx < overdraw.count.length()) {
+                atomicAdd(overdraw.count[idx], 1);
+                covered++;
+            }
+        }
+    }
+
+    return covered;
+}
+
+// the three vertices of a triangle, in the order they're to be drawn
+void triangleAddrs(uint tri, out uint v0_addr, out uint v1_addr, out uint v2_addr) {
     // NOTE: vertex size is 10 words
     // - 4 words for position
     // - 4 words for texcoord 0 UV + texcoord 1 UV
     // - 2 words for col + ocol
 
-    uint base_addr = ubo.addr + (gl_WorkGroupID.x * 30);
-    VertexData v0 = loadVertex(base_addr);
-    VertexData v1 = loadVertex(base_addr + 10);
-    VertexData v2 = loadVertex(base_addr + 20);
-
-    // clip space to NDC
-    v0.position /= v0.position.w;
-    v1.position /= v1.position.w;
-    v2.position /= v2.position.w;
-
-    // NDC to screen coords
+    // a list has three vertices of its own per triangle, while a strip's triangles each start one vertex further on &
+    // share the two before it. every other strip triangle has its winding swapped back, so they all face the same way
+    if (ubo.topology == TOPOLOGY_STRIP) {
+        v0_addr = ubo.addr + (tri * 10);
+        bool odd = (tri & 1) != 0;
+        v1_addr = v0_addr + (odd ? 20 : 10);
+        v2_addr = v0_addr + (odd ? 10 : 20);
+    }
+    else {
+        v0_addr = ubo.addr + (tri * 30);
+        v1_addr = v0_addr + 10;
+        v2_addr = v0_addr + 20;
+    }
+}
 
-    // get viewport
+vec4 viewport() {
     uint vp_xy = params.data[REG_VPXY];
     uint vp_wh = params.data[REG_VPWH];
-    vec4 vp = vec4(
+    return vec4(
         vp_xy & 0xFFFF,
         vp_xy >> 16,
         vp_wh & 0xFFFF,
         vp_wh >> 16
     );
+}
 
-    ivec2 v0_scr = ndcToScreen(v0.position.xy, vp);
-    ivec2 v1_scr = ndcToScreen(v1.position.xy, vp);
-    ivec2 v2_scr = ndcToScreen(v2.position.xy, vp);
+// the scissor rectangle as inclusive bounds, or the whole framebuffer without one (see src/vdp.rs)
+ivec4 clipRect(uvec2 fb_wh) {
+    uint clip_xy = params.data[REG_CLIPXY];
+    uint clip_wh = params.data[REG_CLIPWH];
 
-    // plot points to framebuffer
-    uint fb_addr = params.data[REG_FBADDR];
+    if (clip_wh == 0) {
+        return ivec4(0, 0, ivec2(fb_wh) - 1);
+    }
+
+    ivec2 lo = ivec2(clip_xy & 0xFFFF, clip_xy >> 16);
+    ivec2 hi = lo + ivec2(clip_wh & 0xFFFF, clip_wh >> 16) - 1;
+    return ivec4(lo, min(hi, ivec2(fb_wh) - 1));
+}
+
+uvec2 framebufferDim() {
     uint fb_dim = params.data[REG_FBDIM];
-    uvec2 fb_wh = uvec2(
+    return uvec2(
         fb_dim & 0xFFFF,
         fb_dim >> 16
     );
+}
+
+// one triangle per work group, drawn by its first invocation - the debug modes only. these count what they draw as the
+// pixels written
+void drawDebug() {
+    if (gl_LocalInvocationIndex != 0) {
+        return;
+    }
 
-    setColor(fb_addr, fb_wh, uvec2(v0_scr), 0xFF0000FF);
-    setColor(fb_addr, fb_wh, uvec2(v1_scr), 0xFF00FF00);
-    setColor(fb_addr, fb_wh, uvec2(v2_scr), 0xFFFF0000);
-}
\ No newline at end of file
+    uint v0_addr, v1_addr, v2_addr;
+    triangleAddrs(gl_WorkGroupID.x, v0_addr, v1_addr, v2_addr);
+
+    if (!vertexInVram(v0_addr) || !vertexInVram(v1_addr) || !vertexInVram(v2_addr)) {
+        return;
+    }
+
+    VertexData v0 = loadVertex(v0_addr);
+    VertexData v1 = loadVertex(v1_addr);
+    VertexData v2 = loadVertex(v2_addr);
+
+    // clip space to NDC
+    v0.position /= v0.position.w;
+    v1.position /= v1.position.w;
+    v2.position /= v2.position.w;
+
+    // NDC to screen coords
+    vec4 vp = viewport();
+    ivec2 v0_scr = ndcToScreen(v0.position.xy, vp);
+    ivec2 v1_scr = ndcToScreen(v1.position.xy, vp);
+    ivec2 v2_scr = ndcToScreen(v2.position.xy, vp);
+
+    uint fb_addr = params.data[REG_FBADDR];
+    uvec2 fb_wh = framebufferDim();
+
+    uint drawn;
+
+    if (ubo.debugMode == DEBUGMODE_WIREFRAME) {
+        drawn = drawLine(fb_addr, fb_wh, v0_scr, v1_scr, 0xFFFFFFFF);
+        drawn += drawLine(fb_addr, fb_wh, v1_scr, v2_scr, 0xFFFFFFFF);
+        drawn += drawLine(fb_addr, fb_wh, v2_scr, v0_scr, 0xFFFFFFFF);
+    }
+    else {
+        drawn = countCoverage(fb_wh, v0_scr, v1_scr, v2_scr);
+    }
+
+    if (drawn != 0) {
+        atomicAdd(pixels.count, drawn);
+    }
+}
+
+bool depthPasses(uint func, float z, float stored) {
+    switch (func) {
+        case DEPTHFUNC_NEVER: return false;
+        case DEPTHFUNC_LESS: return z < stored;
+        case DEPTHFUNC_EQUAL: return z == stored;
+        case DEPTHFUNC_LEQUAL: return z <= stored;
+        case DEPTHFUNC_GREATER: return z > stored;
+        case DEPTHFUNC_NOTEQUAL: return z != stored;
+        case DEPTHFUNC_GEQUAL: return z >= stored;
+        default: return true;
+    }
+}
+
+// src is the triangle's color & dst what's in the framebuffer. all but alpha blending leave the framebuffer's alpha be,
+// & additive & subtractive scale the triangle's color by its alpha first, so they can be faded out
+vec4 blend(uint mode, vec4 src, vec4 dst) {
+    switch (mode) {
+        case BLENDMODE_ALPHA: return vec4(mix(dst.rgb, src.rgb, src.a), src.a + dst.a * (1.0 - src.a));
+        case BLENDMODE_ADD: return vec4(dst.rgb + src.rgb * src.a, dst.a);
+        case BLENDMODE_SUBTRACT: return vec4(dst.rgb - src.rgb * src.a, dst.a);
+        case BLENDMODE_MULTIPLY: return vec4(dst.rgb * src.rgb, dst.a);
+        default: return src;
+    }
+}
+
+// an edge owns the pixels exactly on it if it's a top or left edge, so triangles sharing an edge don't both draw them
+// (for the winding triangles are set up with, where the inside of every edge is positive)
+bool topLeft(vec2 a, vec2 b) {
+    vec2 d = b - a;
+    return d.y < 0.0 || (d.y == 0.0 && d.x > 0.0);
+}
+
+// a batch of triangles, set up by one invocation each & then walked in order by every pixel of the tile
+struct Triangle {
+    vec2 p0;
+    vec2 p1;
+    vec2 p2;
+    // window space depth (0 to 1) at each vertex
+    vec3 z;
+    vec4 color0[3];
+    // screen space bounds, inclusive, already clipped to the framebuffer & scissor - empty if there's nothing to draw
+    ivec4 bounds;
+    float area;
+};
+
+shared Triangle batch[TILE_PIXELS];
+
+Triangle setupTriangle(uint tri, ivec4 clip, vec4 vp) {
+    Triangle t;
+    t.bounds = ivec4(0, 0, -1, -1);
+
+    uint v0_addr, v1_addr, v2_addr;
+    triangleAddrs(tri, v0_addr, v1_addr, v2_addr);
+
+    if (!vertexInVram(v0_addr) || !vertexInVram(v1_addr) || !vertexInVram(v2_addr)) {
+        return t;
+    }
+
+    VertexData v0 = loadVertex(v0_addr);
+    VertexData v1 = loadVertex(v1_addr);
+    VertexData v2 = loadVertex(v2_addr);
+
+    // clip space to NDC
+    v0.position /= v0.position.w;
+    v1.position /= v1.position.w;
+    v2.position /= v2.position.w;
+
+    ivec2 s0 = ndcToScreen(v0.position.xy, vp);
+    ivec2 s1 = ndcToScreen(v1.position.xy, vp);
+    ivec2 s2 = ndcToScreen(v2.position.xy, vp);
+
+    float area = edge(vec2(s0), vec2(s1), vec2(s2));
+    if (area == 0.0) {
+        return t;
+    }
+
+    // either winding is drawn - swap the other round so the inside of every edge is positive
+    if (area < 0.0) {
+        ivec2 s = s1;
+        s1 = s2;
+        s2 = s;
+
+        VertexData v = v1;
+        v1 = v2;
+        v2 = v;
+
+        area = -area;
+    }
+
+    t.p0 = vec2(s0);
+    t.p1 = vec2(s1);
+    t.p2 = vec2(s2);
+    t.z = vec3(v0.position.z, v1.position.z, v2.position.z) * 0.5 + 0.5;
+    t.color0[0] = v0.color0;
+    t.color0[1] = v1.color0;
+    t.color0[2] = v2.color0;
+    t.area = area;
+    t.bounds = ivec4(max(min(min(s0, s1), s2), clip.xy), min(max(max(s0, s1), s2), clip.zw));
+
+    return t;
+}
+
+void main() {
+    if (ubo.debugMode != DEBUGMODE_NONE) {
+        drawDebug();
+        return;
+    }
+
+    // each work group draws every triangle of the list over its own tile of the framebuffer, & each invocation owns one
+    // pixel of that tile. no two invocations ever touch the same pixel, so the depth test & writes need no atomics, &
+    // each pixel sees the triangles strictly in list order - later triangles land on top of earlier ones at equal
+    // depth, the same on every GPU
+    uint fb_addr = params.data[REG_FBADDR];
+    uint db_addr = params.data[REG_DBADDR];
+    uvec2 fb_wh = framebufferDim();
+    vec4 vp = viewport();
+    ivec4 clip = clipRect(fb_wh);
+
+    uint depth_reg = params.data[REG_DEPTH];
+    bool depth_test = (depth_reg & DEPTHBIT_ENABLE) != 0;
+    bool depth_write = depth_test && (depth_reg & DEPTHBIT_WRITE) != 0;
+    uint depth_func = (depth_reg >> DEPTH_FUNC_SHIFT) & DEPTH_FUNC_MASK;
+    uint blend_mode = params.data[REG_BLEND] & BLEND_MODE_MASK;
+
+    ivec2 tile_lo = ivec2(gl_WorkGroupID.xy * TILE_SIZE);
+    ivec2 tile_hi = tile_lo + TILE_SIZE - 1;
+    ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+
+    // pixels past the edge of the framebuffer still help set up triangles, but don't draw
+    uint idx = uint(px.y) * fb_wh.x + uint(px.x);
+    bool in_fb = px.x < int(fb_wh.x) && px.y < int(fb_wh.y) && fb_addr + idx < vram.data.length();
+    bool in_db = in_fb && db_addr + idx < vram.data.length();
+
+    // the pixel's depth & color are kept here while the triangles go by, & written back once at the end. blending
+    // reads the color back as 8 bits per channel after every triangle, just like it would from the framebuffer
+    float depth = (depth_test && in_db) ? uintBitsToFloat(vram.data[db_addr + idx]) : 0.0;
+    uint color = (blend_mode != BLENDMODE_OPAQUE && in_fb) ? vram.data[fb_addr + idx] : 0;
+    bool color_written = false;
+    bool depth_written = false;
+    // every triangle that lands on the pixel counts as a pixel written, even though only the last is written back
+    uint drawn = 0;
+
+    vec2 p = vec2(px) + 0.5;
+
+    for (uint first = 0; first < ubo.count; first += uint(TILE_PIXELS)) {
+        uint tri = first + gl_LocalInvocationIndex;
+
+        if (tri < ubo.count) {
+            batch[gl_LocalInvocationIndex] = setupTriangle(tri, clip, vp);
+        }
+
+        barrier();
+
+        uint batch_count = min(ubo.count - first, uint(TILE_PIXELS));
+
+        for (uint i = 0; i < batch_count; i++) {
+            ivec4 bounds = batch[i].bounds;
+
+            // the whole tile can skip a triangle that misses it
+            if (bounds.x > tile_hi.x || bounds.y > tile_hi.y || bounds.z < tile_lo.x || bounds.w < tile_lo.y) {
+                continue;
+            }
+
+            // & a pixel outside its bounds, which keeps it inside the scissor rectangle
+            if (!in_fb || (depth_test && !in_db) || any(lessThan(px, bounds.xy)) || any(greaterThan(px, bounds.zw))) {
+                continue;
+            }
+
+            Triangle t = batch[i];
+            vec3 w = vec3(edge(t.p1, t.p2, p), edge(t.p2, t.p0, p), edge(t.p0, t.p1, p));
+
+            bool inside =
+                (w.x > 0.0 || (w.x == 0.0 && topLeft(t.p1, t.p2))) &&
+                (w.y > 0.0 || (w.y == 0.0 && topLeft(t.p2, t.p0))) &&
+                (w.z > 0.0 || (w.z == 0.0 && topLeft(t.p0, t.p1)));
+
+            if (!inside) {
+                continue;
+            }
+
+            // screen space interpolation - depth is linear in screen space after the divide, & color is left affine
+            vec3 bary = w / t.area;
+            float z = dot(bary, t.z);
+
+            if (depth_test) {
+                if (!depthPasses(depth_func, z, depth)) {
+                    continue;
+                }
+
+                if (depth_write) {
+                    depth = z;
+                    depth_written = true;
+                }
+            }
+
+            vec4 src = t.color0[0] * bary.x + t.color0[1] * bary.y + t.color0[2] * bary.z;
+            color = packUnorm4x8(blend(blend_mode, src, unpackUnorm4x8(color)));
+            color_written = true;
+            drawn++;
+        }
+
+        // the batch is about to be overwritten by the next one
+        barrier();
+    }
+
+    if (color_written) {
+        vram.data[fb_addr + idx] = color;
+    }
+
+    if (depth_written) {
+        vram.data[db_addr + idx] = floatBitsToUint(depth);
+    }
+
+    if (drawn != 0) {
+        atomicAdd(pixels.count, drawn);
+    }
+}
@@ -0,0 +1,45 @@
+#version 450
+layout (local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+// what each word is set to (see src/vdp.rs)
+#define FILLMODE_VALUE           0
+#define FILLMODE_COPY            1
+
+layout(std430, set = 1, binding = 0) buffer VRAM {
+    uint data[];
+} vram;
+
+layout(std140, set = 2, binding = 0) uniform UBO {
+    uint addr;
+    uint count;
+    uint value;
+    uint src;
+    uint mode;
+} ubo;
+
+void main() {
+    // each invocation sets one word of a run of VRAM - to a value (clearing the depth buffer & such), or to the word at the
+    // same offset from src (copying a framebuffer). a copy onto an overlapping run is left to whichever order the words
+    // happen to be written in
+    uint idx = gl_GlobalInvocationID.x;
+
+    if (idx >= ubo.count) {
+        return;
+    }
+
+    uint addr = ubo.addr + idx;
+    uint src = ubo.src + idx;
+
+    if (addr >= vram.data.length()) {
+        return;
+    }
+
+    if (ubo.mode == FILLMODE_COPY) {
+        if (src < vram.data.length()) {
+            vram.data[addr] = vram.data[src];
+        }
+    }
+    else {
+        vram.data[addr] = ubo.value;
+    }
+}
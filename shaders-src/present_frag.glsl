@@ -0,0 +1,41 @@
+#version 450
+
+layout(location = 0) out vec4 outColor;
+
+layout(std430, set = 2, binding = 0) readonly buffer VRAM {
+    uint data[];
+} vram;
+
+// the displayed framebuffer (word address & FBDIM layout, w | h << 16) & the size of the window's swapchain texture
+layout(std140, set = 3, binding = 0) uniform UBO {
+    uint fbAddr;
+    uint fbDim;
+    uint targetWidth;
+    uint targetHeight;
+} ubo;
+
+void main() {
+    vec2 fb = vec2(ubo.fbDim & 0xFFFF, ubo.fbDim >> 16);
+    vec2 target = vec2(ubo.targetWidth, ubo.targetHeight);
+
+    outColor = vec4(0.0, 0.0, 0.0, 1.0);
+
+    if (fb.x == 0.0 || fb.y == 0.0) {
+        return;
+    }
+
+    // scaled up as far as it'll go with its aspect ratio kept, nearest neighbor, & centered with bars either side
+    float scale = min(target.x / fb.x, target.y / fb.y);
+    vec2 origin = floor((target - fb * scale) * 0.5);
+    vec2 p = floor((gl_FragCoord.xy - origin) / scale);
+
+    if (any(lessThan(p, vec2(0.0))) || any(greaterThanEqual(p, fb))) {
+        return;
+    }
+
+    uint addr = ubo.fbAddr + uint(p.y) * uint(fb.x) + uint(p.x);
+
+    if (addr < vram.data.length()) {
+        outColor = vec4(unpackUnorm4x8(vram.data[addr]).rgb, 1.0);
+    }
+}
@@ -35,6 +35,11 @@ layout(std140, set = 2, binding = 0) uniform UBO {
     uint dst_addr;
 } ubo;
 
+// a word of VRAM, or 0 past the end of it - a vertex's inputs can be anywhere the stride & layout put them
+uint load_word(uint addr) {
+    return addr < vram.data.length() ? vram.data[addr] : 0;
+}
+
 vec4 load_vtx_slot(uint base_addr, uint slotlayout) {
     // lower 3 bits of layout identifies slot type
     uint param_type = slotlayout & 7;
@@ -47,33 +52,33 @@ vec4 load_vtx_slot(uint base_addr, uint slotlayout) {
     switch (param_type) {
         case 0: {
             // FLOAT1
-            outdata.x = uintBitsToFloat(vram.data[slot_addr]);
+            outdata.x = uintBitsToFloat(load_word(slot_addr));
             break;
         }
         case 1: {
             // FLOAT2
-            outdata.x = uintBitsToFloat(vram.data[slot_addr]);
-            outdata.y = uintBitsToFloat(vram.data[slot_addr + 1]);
+            outdata.x = uintBitsToFloat(load_word(slot_addr));
+            outdata.y = uintBitsToFloat(load_word(slot_addr + 1));
             break;
         }
         case 2: {
             // FLOAT3
-            outdata.x = uintBitsToFloat(vram.data[slot_addr]);
-            outdata.y = uintBitsToFloat(vram.data[slot_addr + 1]);
-            outdata.z = uintBitsToFloat(vram.data[slot_addr + 2]);
+            outdata.x = uintBitsToFloat(load_word(slot_addr));
+            outdata.y = uintBitsToFloat(load_word(slot_addr + 1));
+            outdata.z = uintBitsToFloat(load_word(slot_addr + 2));
             break;
         }
         case 3: {
             // FLOAT4
-            outdata.x = uintBitsToFloat(vram.data[slot_addr]);
-            outdata.y = uintBitsToFloat(vram.data[slot_addr + 1]);
-            outdata.z = uintBitsToFloat(vram.data[slot_addr + 2]);
-            outdata.w = uintBitsToFloat(vram.data[slot_addr + 3]);
+            outdata.x = uintBitsToFloat(load_word(slot_addr));
+            outdata.y = uintBitsToFloat(load_word(slot_addr + 1));
+            outdata.z = uintBitsToFloat(load_word(slot_addr + 2));
+            outdata.w = uintBitsToFloat(load_word(slot_addr + 3));
             break;
         }
         case 4: {
             // UNORM4
-            uint val = vram.data[slot_addr];
+            uint val = load_word(slot_addr);
             outdata.x = float(bitfieldExtract(val, 0, 8)) / 255.0;
             outdata.y = float(bitfieldExtract(val, 8, 8)) / 255.0;
             outdata.z = float(bitfieldExtract(val, 16, 8)) / 255.0;
@@ -82,7 +87,7 @@ vec4 load_vtx_slot(uint base_addr, uint slotlayout) {
         }
         case 5: {
             // SNORM4
-            int val = int(vram.data[slot_addr]);
+            int val = int(load_word(slot_addr));
             outdata.x = float(bitfieldExtract(val, 0, 8)) / 128.0;
             outdata.y = float(bitfieldExtract(val, 8, 8)) / 128.0;
             outdata.z = float(bitfieldExtract(val, 16, 8)) / 128.0;
@@ -111,6 +116,11 @@ void main() {
     uint in_addr = ubo.src_addr + (gl_WorkGroupID.x * stride);
     uint out_addr = ubo.dst_addr + (gl_WorkGroupID.x * 10);
 
+    // the command processor keeps the output in VRAM, but a vertex that would write past the end of it is dropped here too
+    if (out_addr >= vram.data.length() || vram.data.length() - out_addr < 10) {
+        return;
+    }
+
     // load inputs
     for (uint j = 0; j < 8; j++) {
         uint vlayout = params.data[REG_VULAYOUT0 + j];
@@ -119,7 +129,7 @@ void main() {
 
     // process vertex
     for (int j = 0; j < 64; j++) {
-        uint instr = vram.data[vuprog + j];
+        uint instr = load_word(vuprog + j);
 
         uint op = instr & 0x3F;
         uint dst = (instr >> 6) & 0xF;
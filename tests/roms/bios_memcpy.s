@@ -0,0 +1,42 @@
+@ BIOS memcpy copies from ROM to RAM & returns the byte count, & stops short at memory it can't copy
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =(MAIN_RAM + 0x100)
+
+    mov r0, r4
+    ldr r1, =pattern
+    mov r2, #12
+    svc #BIOS_MEMCPY
+    cmp r0, #12
+    bne fail_count
+
+    ldr r0, [r4]
+    ldr r1, =0x11223344
+    cmp r0, r1
+    bne fail_data
+    ldr r0, [r4, #8]
+    ldr r1, =0x99AABBCC
+    cmp r0, r1
+    bne fail_data
+
+    @ nothing is mapped at the top of the address space
+    mov r0, r4
+    ldr r1, =0xFFFFF000
+    mov r2, #16
+    svc #BIOS_MEMCPY
+    cmp r0, #0
+    bne fail_unmapped
+
+    test_pass
+fail_count:
+    test_fail 0xBAD1
+fail_data:
+    test_fail 0xBAD2
+fail_unmapped:
+    test_fail 0xBAD3
+
+pattern:
+    .word 0x11223344, 0x55667788, 0x99AABBCC
@@ -0,0 +1,72 @@
+@ a clock counter with a period raises the timer IRQ every time it crosses a multiple of it, many times per frame
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    b start
+    .org 0x18
+    b irq
+
+start:
+    msr cpsr_c, #0xd2
+    ldr sp, =(MAIN_RAM + 0x8000)
+    msr cpsr_c, #0xd3
+    ldr sp, =(MAIN_RAM + 0x10000)
+
+    @ r5 counts timer IRQs, r6 counts frames seen while waiting for them
+    mov r5, #0
+    mov r6, #0
+
+    ldr r4, =INTC
+    mov r0, #IRQ_TIMER
+    str r0, [r4, #INTC_ENABLE]
+
+    @ counter 0 interrupts every millisecond
+    ldr r4, =CLOCK
+    ldr r0, =1000
+    str r0, [r4, #CLOCK_CTR0P]
+    mov r0, #(CLOCK_CTR0EN | CLOCK_CTR0RESET | CLOCK_CTR0INTR)
+    str r0, [r4, #CLOCK_STATUS]
+
+    ldr r7, =SYSINFO
+    ldr r8, [r7, #SYSINFO_FRAME]
+
+    cpsie i
+wait:
+    wfi
+    cmp r5, #50
+    blo wait
+
+    cpsid i
+    mov r0, #0
+    str r0, [r4, #CLOCK_STATUS]
+
+    @ 50ms of 1ms periods shouldn't take anywhere near 30 frames
+    ldr r0, [r7, #SYSINFO_FRAME]
+    sub r0, r0, r8
+    cmp r0, #20
+    bhs fail_slow
+
+    test_pass
+fail_slow:
+    test_fail 0xBAD1
+fail_irq:
+    test_fail 0xBAD2
+
+irq:
+    sub lr, lr, #4
+    stmfd sp!, {r0-r1, lr}
+
+    ldr r0, =CLOCK
+    ldr r1, [r0, #CLOCK_IRQ]
+    tst r1, #CLOCK_IRQ_CTR0
+    beq fail_irq
+    str r1, [r0, #CLOCK_IRQ]
+
+    ldr r0, =INTC
+    mov r1, #IRQ_TIMER
+    str r1, [r0, #INTC_ACK]
+
+    add r5, r5, #1
+    ldmfd sp!, {r0-r1, pc}^
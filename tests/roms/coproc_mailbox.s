@@ -0,0 +1,70 @@
+@ with --coprocessor, the main CPU starts the second core on code in ROM, posts it a word, & gets back that word plus
+@ one along with a doorbell. the coprocessor waits in WFI for its mail, so this also checks mail wakes it
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =MAILBOX
+
+    ldr r0, =coproc
+    str r0, [r4, #MAILBOX_COPENTRY]
+    ldr r0, =(MAIN_RAM + 0x10000)
+    str r0, [r4, #MAILBOX_COPSTACK]
+    mov r0, #1
+    str r0, [r4, #MAILBOX_COPCTRL]
+
+    mov r0, #41
+    str r0, [r4, #MAILBOX_TX]
+
+    @ wait for the answer (the coprocessor runs on its own thread, so give it a while)
+    ldr r2, =10000000
+wait:
+    ldr r1, [r4, #MAILBOX_STATUS]
+    tst r1, #MAILBOX_RXREADY
+    bne answered
+    subs r2, r2, #1
+    bne wait
+    test_fail 0xBAD1
+
+answered:
+    ldr r1, [r4, #MAILBOX_RX]
+    cmp r1, #42
+    bne fail_value
+
+    @ the doorbell is rung after the mail is sent
+    ldr r2, =10000000
+wait_doorbell:
+    ldr r1, [r4, #MAILBOX_DOORBELL]
+    cmp r1, #2
+    beq rung
+    subs r2, r2, #1
+    bne wait_doorbell
+    test_fail 0xBAD3
+
+rung:
+    str r1, [r4, #MAILBOX_ACK]
+    ldr r1, [r4, #MAILBOX_DOORBELL]
+    cmp r1, #0
+    bne fail_ack
+
+    test_pass
+fail_value:
+    test_fail 0xBAD2
+fail_ack:
+    test_fail 0xBAD4
+
+@ runs on the coprocessor: echo mail back plus one, then ring doorbell bit 1
+coproc:
+    ldr r4, =MAILBOX
+1:  ldr r1, [r4, #MAILBOX_STATUS]
+    tst r1, #MAILBOX_RXREADY
+    bne 2f
+    wfi
+    b 1b
+2:  ldr r1, [r4, #MAILBOX_RX]
+    add r1, r1, #1
+    str r1, [r4, #MAILBOX_TX]
+    mov r1, #2
+    str r1, [r4, #MAILBOX_DOORBELL]
+    b 1b
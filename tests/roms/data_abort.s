@@ -0,0 +1,50 @@
+@ with --guest-faults abort, a load from unmapped memory takes a data abort: ABT mode, LR 8 past the faulting load, &
+@ a handler that can return past it
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    b start
+    .org 0x10
+    b dabort
+
+start:
+    msr cpsr_c, #0xd7
+    ldr sp, =(MAIN_RAM + 0x8000)
+    msr cpsr_c, #0xd3
+    ldr sp, =(MAIN_RAM + 0x10000)
+
+    @ r5 counts aborts, r6 is what the handler saw in LR
+    mov r5, #0
+    mov r6, #0
+
+    ldr r0, =0xFFFFF000
+faulting:
+    ldr r1, [r0]
+    cmp r5, #1
+    bne fail_count
+
+    ldr r0, =(faulting + 8)
+    cmp r6, r0
+    bne fail_lr
+
+    @ back in SVC mode afterwards
+    mrs r0, cpsr
+    and r0, r0, #0x1F
+    cmp r0, #0x13
+    bne fail_mode
+
+    test_pass
+fail_count:
+    test_fail 0xBAD1
+fail_lr:
+    test_fail 0xBAD2
+fail_mode:
+    test_fail 0xBAD3
+
+@ skip the faulting instruction rather than retrying it
+dabort:
+    add r5, r5, #1
+    mov r6, lr
+    subs pc, lr, #4
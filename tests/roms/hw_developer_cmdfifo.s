@@ -0,0 +1,37 @@
+@ developer hardware model: the VDP command FIFO never fills, however many queues go in one frame
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =SYSINFO
+    ldr r0, [r4, #SYSINFO_FEATURES]
+    tst r0, #FEATURE_STRICTHW
+    bne fail_model
+
+    ldr r4, =VDP
+
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0x000042FF
+    str r1, [r0]
+
+    vdp_dma (MAIN_RAM + 0x100), 0x100, 1
+
+    wfi
+
+    mov r5, #32
+    mov r0, #0x100
+submit:
+    str r0, [r4, #VDP_CMDPORT]
+    subs r5, r5, #1
+    bne submit
+
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #VDP_CMDFIFOFULL
+    bne fail_full
+
+    test_pass
+fail_model:
+    test_fail 0xBAD1
+fail_full:
+    test_fail 0xBAD2
@@ -0,0 +1,55 @@
+@ strict hardware model: the VDP command FIFO holds 16 queues a frame - CMDFIFOFULL reports it, & it drains by the next frame
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =SYSINFO
+    ldr r0, [r4, #SYSINFO_FEATURES]
+    tst r0, #FEATURE_STRICTHW
+    beq fail_model
+
+    ldr r4, =VDP
+
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0x000042FF
+    str r1, [r0]
+
+    vdp_dma (MAIN_RAM + 0x100), 0x100, 1
+
+    @ start submitting right after a frame boundary, so the whole burst lands in one frame
+    wfi
+
+    mov r5, #15
+    mov r0, #0x100
+submit:
+    str r0, [r4, #VDP_CMDPORT]
+    subs r5, r5, #1
+    bne submit
+
+    @ 15 queued - still room for one more
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #VDP_CMDFIFOFULL
+    bne fail_early
+
+    str r0, [r4, #VDP_CMDPORT]
+
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #VDP_CMDFIFOFULL
+    beq fail_full
+
+    @ the next frame's service empties it again
+    wfi
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #VDP_CMDFIFOFULL
+    bne fail_drain
+
+    test_pass
+fail_model:
+    test_fail 0xBAD1
+fail_early:
+    test_fail 0xBAD2
+fail_full:
+    test_fail 0xBAD3
+fail_drain:
+    test_fail 0xBAD4
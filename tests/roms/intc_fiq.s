@@ -0,0 +1,77 @@
+@ a line routed to FIQ is delivered through the FIQ vector, in FIQ mode with its own r8-r12, & not as an IRQ - & it's
+@ taken even while an IRQ is masked
+    .include "common.inc"
+
+    .equ FIQ_COUNT,         (MAIN_RAM + 0x100)
+
+    .text
+    .global _start
+_start:
+    b start
+    .org 0x18
+    b irq
+    .org 0x1C
+    b fiq
+
+start:
+    ldr r0, =FIQ_COUNT
+    mov r1, #0
+    str r1, [r0]
+
+    ldr r4, =INTC
+    mov r0, #IRQ_VDP
+    str r0, [r4, #INTC_FIQSEL]
+    str r0, [r4, #INTC_ENABLE]
+
+    @ with FIQs still masked, a raised line only shows in FIQACTIVE
+    str r0, [r4, #INTC_RAISE]
+    ldr r1, [r4, #INTC_ACTIVE]
+    cmp r1, #0
+    bne fail_active
+    ldr r1, [r4, #INTC_FIQACTIVE]
+    cmp r1, #IRQ_VDP
+    bne fail_active
+    str r0, [r4, #INTC_ACK]
+
+    @ unmask FIQ only, & raise it again. r8 is banked, so the handler can't touch ours
+    ldr r8, =0x1234
+    cpsie f
+    str r0, [r4, #INTC_RAISE]
+    nop
+    nop
+
+    ldr r0, =FIQ_COUNT
+    ldr r1, [r0]
+    cmp r1, #1
+    bne fail_count
+
+    ldr r1, =0x1234
+    cmp r8, r1
+    bne fail_banked
+
+    test_pass
+fail_active:
+    test_fail 0xBAD1
+fail_count:
+    test_fail 0xBAD2
+fail_banked:
+    test_fail 0xBAD3
+fail_irq:
+    test_fail 0xBAD4
+
+irq:
+    b fail_irq
+
+fiq:
+    sub lr, lr, #4
+
+    ldr r8, =INTC
+    ldr r9, [r8, #INTC_FIQACTIVE]
+    str r9, [r8, #INTC_ACK]
+
+    ldr r8, =FIQ_COUNT
+    ldr r9, [r8]
+    add r9, r9, #1
+    str r9, [r8]
+
+    movs pc, lr
@@ -0,0 +1,73 @@
+@ the vblank line interrupts the CPU through the low IRQ vector once it's enabled & the CPSR's I bit is clear
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    b start
+    .org 0x18
+    b irq
+
+start:
+    ldr r4, =SYSINFO
+    ldr r0, [r4, #SYSINFO_FEATURES]
+    tst r0, #FEATURE_INTC
+    beq fail_feature
+
+    @ IRQ mode stack, then back to SVC
+    msr cpsr_c, #0xd2
+    ldr sp, =(MAIN_RAM + 0x8000)
+    msr cpsr_c, #0xd3
+    ldr sp, =(MAIN_RAM + 0x10000)
+
+    @ r5 counts IRQs taken
+    mov r5, #0
+
+    ldr r4, =INTC
+    mov r0, #IRQ_VBLANK
+    str r0, [r4, #INTC_ENABLE]
+
+    @ a pending line wakes WFI, but isn't taken while the I bit is set
+    wfi
+    cmp r5, #0
+    bne fail_masked
+    ldr r0, [r4, #INTC_ACTIVE]
+    tst r0, #IRQ_VBLANK
+    beq fail_pending
+
+    cpsie i
+wait:
+    wfi
+    cmp r5, #3
+    blo wait
+
+    cpsid i
+    mov r0, #0
+    str r0, [r4, #INTC_ENABLE]
+    test_pass
+fail_feature:
+    test_fail 0xBAD1
+fail_masked:
+    test_fail 0xBAD2
+fail_pending:
+    test_fail 0xBAD3
+fail_irq:
+    test_fail 0xBAD4
+
+irq:
+    sub lr, lr, #4
+    stmfd sp!, {r0-r1, lr}
+
+    @ has to be IRQ mode, with vblank the line that's active
+    mrs r0, cpsr
+    and r0, r0, #0x1f
+    cmp r0, #0x12
+    bne fail_irq
+    ldr r0, =INTC
+    ldr r1, [r0, #INTC_ACTIVE]
+    tst r1, #IRQ_VBLANK
+    beq fail_irq
+
+    str r1, [r0, #INTC_ACK]
+    add r5, r5, #1
+    ldmfd sp!, {r0-r1, pc}^
@@ -0,0 +1,61 @@
+@ main RAM is little endian: byte & halfword accesses see the expected lanes of a word, & narrow stores compose into words
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =(MAIN_RAM + 0x100)
+    ldr r0, =0x11223344
+    str r0, [r4]
+
+    ldrb r0, [r4]
+    cmp r0, #0x44
+    bne fail_byte
+    ldrb r0, [r4, #1]
+    cmp r0, #0x33
+    bne fail_byte
+    ldrb r0, [r4, #2]
+    cmp r0, #0x22
+    bne fail_byte
+    ldrb r0, [r4, #3]
+    cmp r0, #0x11
+    bne fail_byte
+
+    ldrh r0, [r4]
+    ldr r1, =0x3344
+    cmp r0, r1
+    bne fail_half
+    ldrh r0, [r4, #2]
+    ldr r1, =0x1122
+    cmp r0, r1
+    bne fail_half
+
+    @ signed loads sign-extend
+    mov r0, #0x80
+    strb r0, [r4, #4]
+    ldrsb r0, [r4, #4]
+    mvn r1, #0x7F
+    cmp r0, r1
+    bne fail_signed
+
+    @ narrow stores land in the right lanes
+    ldr r0, =0xAABB
+    strh r0, [r4, #8]
+    mov r0, #0xCC
+    strb r0, [r4, #10]
+    mov r0, #0xDD
+    strb r0, [r4, #11]
+    ldr r0, [r4, #8]
+    ldr r1, =0xDDCCAABB
+    cmp r0, r1
+    bne fail_store
+
+    test_pass
+fail_byte:
+    test_fail 0xBAD1
+fail_half:
+    test_fail 0xBAD2
+fail_signed:
+    test_fail 0xBAD3
+fail_store:
+    test_fail 0xBAD4
@@ -0,0 +1,46 @@
+@ the ARM1176 handles unaligned word & halfword accesses to normal memory without faulting or rotating (SCTLR.U set, A clear)
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =(MAIN_RAM + 0x100)
+    ldr r0, =0x03020100
+    str r0, [r4]
+    ldr r0, =0x07060504
+    str r0, [r4, #4]
+
+    ldr r0, [r4, #1]
+    ldr r1, =0x04030201
+    cmp r0, r1
+    bne fail_word
+
+    ldr r0, [r4, #3]
+    ldr r1, =0x06050403
+    cmp r0, r1
+    bne fail_word
+
+    ldrh r0, [r4, #1]
+    ldr r1, =0x0201
+    cmp r0, r1
+    bne fail_half
+
+    @ unaligned store only touches the four addressed bytes
+    ldr r0, =0xA1B2C3D4
+    str r0, [r4, #1]
+    ldr r0, [r4]
+    ldr r1, =0xB2C3D400
+    cmp r0, r1
+    bne fail_store
+    ldr r0, [r4, #4]
+    ldr r1, =0x070605A1
+    cmp r0, r1
+    bne fail_store
+
+    test_pass
+fail_word:
+    test_fail 0xBAD1
+fail_half:
+    test_fail 0xBAD2
+fail_store:
+    test_fail 0xBAD3
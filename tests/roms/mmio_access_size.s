@@ -0,0 +1,56 @@
+@ MMIO registers are 32 bits: narrow reads return byte lanes, narrow writes are accepted in lane 0 only
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    @ SYSINFO ID reads as 'NYXB' (0x4E595842) at every access size
+    ldr r4, =SYSINFO
+    ldr r0, [r4]
+    ldr r1, =0x4E595842
+    cmp r0, r1
+    bne fail_read
+
+    ldrb r0, [r4]
+    cmp r0, #0x42
+    bne fail_read
+    ldrb r0, [r4, #1]
+    cmp r0, #0x58
+    bne fail_read
+    ldrb r0, [r4, #3]
+    cmp r0, #0x4E
+    bne fail_read
+    ldrh r0, [r4, #2]
+    ldr r1, =0x4E59
+    cmp r0, r1
+    bne fail_read
+
+    @ MPU region 0 size register (offset 0x44) takes a halfword write in lane 0, zero-extended
+    ldr r4, =MPU
+    ldr r0, =0xFFFFFFFF
+    str r0, [r4, #0x44]
+    ldr r0, =0x5678
+    strh r0, [r4, #0x44]
+    ldr r0, [r4, #0x44]
+    ldr r1, =0x5678
+    cmp r0, r1
+    bne fail_write
+
+    @ ...& a byte write to lane 1 is dropped
+    mov r0, #0x12
+    strb r0, [r4, #0x45]
+    ldr r0, [r4, #0x44]
+    ldr r1, =0x5678
+    cmp r0, r1
+    bne fail_drop
+
+    mov r0, #0
+    str r0, [r4, #0x44]
+
+    test_pass
+fail_read:
+    test_fail 0xBAD1
+fail_write:
+    test_fail 0xBAD2
+fail_drop:
+    test_fail 0xBAD3
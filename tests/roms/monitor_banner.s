@@ -0,0 +1,3 @@
+@ the monitor (guest-src/monitor.s, which build-monitor.sh turns into content/monitor.bin) boots & greets the UART. it's
+@ included from its own source, so this is the same program `nyxbox run --monitor` runs
+    .include "monitor.s"
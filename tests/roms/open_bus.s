@@ -0,0 +1,55 @@
+@ with --unmapped open-bus, reads from unmapped memory return the open-bus value (in the lanes they cover) & writes are
+@ dropped - the CPU carries on, & the bus error registers record what happened
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =BUSERR
+    ldr r0, =0xFFFFF000
+
+    ldr r1, [r0]
+    ldr r2, =0xDEADBEEF
+    cmp r1, r2
+    bne fail_word
+
+    ldrb r1, [r0, #1]
+    cmp r1, #0xBE
+    bne fail_byte
+
+    @ nothing there to write to, & nothing changes
+    mov r1, #0
+    str r1, [r0]
+    ldr r1, [r0]
+    cmp r1, r2
+    bne fail_word
+
+    ldr r1, [r4, #BUSERR_STATUS]
+    cmp r1, #(BUSERR_READ | BUSERR_WRITE)
+    bne fail_status
+
+    ldr r1, [r4, #BUSERR_ADDR]
+    cmp r1, r0
+    bne fail_addr
+
+    ldr r1, [r4, #BUSERR_COUNT]
+    cmp r1, #4
+    bne fail_count
+
+    @ a write clears the status
+    str r1, [r4, #BUSERR_STATUS]
+    ldr r1, [r4, #BUSERR_STATUS]
+    cmp r1, #0
+    bne fail_status
+
+    test_pass
+fail_word:
+    test_fail 0xBAD1
+fail_byte:
+    test_fail 0xBAD2
+fail_status:
+    test_fail 0xBAD3
+fail_addr:
+    test_fail 0xBAD4
+fail_count:
+    test_fail 0xBAD5
@@ -0,0 +1,35 @@
+@ booting straight into Thumb (--entry 0x1): the CPU starts in Thumb state & stays there across BIOS calls, which park it
+@ & resume it every frame
+    .include "common.inc"
+
+    .text
+    .thumb
+    .global _start
+_start:
+    ldr r4, =SYSINFO
+    ldr r5, [r4, #SYSINFO_FRAME]
+
+    @ sleep through ten frames a frame at a time - each resume has to come back in Thumb state to get round the loop
+    movs r6, #0
+1:  svc #BIOS_WAITVBLANK
+    adds r6, #1
+    cmp r6, #10
+    bne 1b
+
+    ldr r0, [r4, #SYSINFO_FRAME]
+    subs r0, r0, r5
+    cmp r0, #10
+    blo fail
+
+    ldr r0, =RESULT
+    ldr r1, =TEST_PASS
+    str r1, [r0]
+2:  svc #BIOS_WAITVBLANK
+    b 2b
+
+fail:
+    ldr r0, =RESULT
+    ldr r1, =0xBAD1
+    str r1, [r0]
+3:  svc #BIOS_WAITVBLANK
+    b 3b
@@ -0,0 +1,29 @@
+@ bytes written to the UART data register come out in order, & TX never reports full for a short message
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =UART
+    adr r5, message
+
+next:
+    ldrb r0, [r5], #1
+    cmp r0, #0
+    beq done
+
+    ldr r1, [r4, #UART_STATUS]
+    tst r1, #UART_TXFULL
+    bne fail_full
+
+    str r0, [r4, #UART_DATA]
+    b next
+
+done:
+    test_pass
+fail_full:
+    test_fail 0xBAD1
+
+message:
+    .asciz "uart_tx: hello, world\n"
+    .align 2
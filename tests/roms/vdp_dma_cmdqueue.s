@@ -0,0 +1,25 @@
+@ a command queue uploaded by DMA is visible to the command processor as soon as the DMA completes
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =VDP
+
+    @ build a one-command queue in main RAM: end of queue, token 0x42
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0x000042FF
+    str r1, [r0]
+
+    vdp_dma (MAIN_RAM + 0x100), 0x100, 1
+
+    mov r0, #0x100
+    str r0, [r4, #VDP_CMDPORT]
+
+    vdp_wait_token
+    cmp r0, #0x42
+    bne fail
+
+    test_pass
+fail:
+    test_fail 0xBAD1
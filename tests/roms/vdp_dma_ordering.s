@@ -0,0 +1,49 @@
+@ submissions & DMAs take effect in issue order, even when the guest doesn't wait in between:
+@ a queue submitted before a DMA must not see that DMA's data
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =VDP
+
+    @ queue A (token 1) & queue B (token 2) in main RAM
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0x000001FF
+    str r1, [r0]
+    ldr r1, =0x000002FF
+    str r1, [r0, #4]
+
+    @ DMA A, submit, DMA B over the top, submit again - no waiting at all
+    ldr r0, =(MAIN_RAM + 0x100)
+    str r0, [r4, #VDP_DMASRC]
+    mov r0, #0x100
+    str r0, [r4, #VDP_DMADST]
+    mov r0, #1
+    str r0, [r4, #VDP_DMALEN]
+    str r0, [r4, #VDP_DMACTRL]
+
+    mov r0, #0x100
+    str r0, [r4, #VDP_CMDPORT]
+
+    ldr r0, =(MAIN_RAM + 0x104)
+    str r0, [r4, #VDP_DMASRC]
+    mov r0, #1
+    str r0, [r4, #VDP_DMACTRL]
+
+    mov r0, #0x100
+    str r0, [r4, #VDP_CMDPORT]
+
+    vdp_wait_token
+    cmp r0, #1
+    bne fail_first
+
+    vdp_wait_token
+    cmp r0, #2
+    bne fail_second
+
+    test_pass
+fail_first:
+    test_fail 0xBAD1
+fail_second:
+    test_fail 0xBAD2
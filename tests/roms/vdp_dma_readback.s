@@ -0,0 +1,46 @@
+@ data uploaded by DMA is visible to the GPU: fill a 4x4 framebuffer with red by DMA & point the VDP at it
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =VDP
+
+    @ 16 red pixels
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0xFF0000FF
+    mov r2, #16
+fill:
+    str r1, [r0], #4
+    subs r2, r2, #1
+    bne fill
+
+    vdp_dma (MAIN_RAM + 0x100), 0x400, 16
+
+    @ command queue: FBDIM = 4x4, FBADDR = 0x400, end of queue
+    ldr r0, =(MAIN_RAM + 0x200)
+    ldr r1, =0x00000000
+    str r1, [r0]
+    ldr r1, =0x00040004
+    str r1, [r0, #4]
+    ldr r1, =0x00000100
+    str r1, [r0, #8]
+    ldr r1, =0x00000400
+    str r1, [r0, #12]
+    ldr r1, =0x000004FF
+    str r1, [r0, #16]
+
+    vdp_dma (MAIN_RAM + 0x200), 0x200, 5
+
+    mov r0, #0x200
+    str r0, [r4, #VDP_CMDPORT]
+
+    mov r0, #1
+    str r0, [r4, #VDP_FENCE]
+
+wait_fence:
+    ldr r0, [r4, #VDP_FENCE]
+    cmp r0, #1
+    bne wait_fence
+
+    test_pass
@@ -0,0 +1,39 @@
+@ a fence completes once all prior work has landed, & clears DRAWBUSY/FENCEPENDING
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =VDP
+
+    ldr r0, =(MAIN_RAM + 0x100)
+    ldr r1, =0x000003FF
+    str r1, [r0]
+
+    vdp_dma (MAIN_RAM + 0x100), 0x100, 1
+
+    mov r0, #0x100
+    str r0, [r4, #VDP_CMDPORT]
+
+    @ submitted work isn't confirmed complete yet
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #VDP_DRAWBUSY
+    beq fail_busy
+
+    mov r0, #7
+    str r0, [r4, #VDP_FENCE]
+
+wait_fence:
+    ldr r0, [r4, #VDP_FENCE]
+    cmp r0, #7
+    bne wait_fence
+
+    ldr r1, [r4, #VDP_STATUS]
+    tst r1, #(VDP_DRAWBUSY | VDP_FENCEPENDING)
+    bne fail_done
+
+    test_pass
+fail_busy:
+    test_fail 0xBAD1
+fail_done:
+    test_fail 0xBAD2
@@ -0,0 +1,94 @@
+@ a watchdog that's kicked keeps counting down from the top, & one that isn't warns with an IRQ & then resets the machine -
+@ which the guest can tell from STATUS when it boots again
+    .include "common.inc"
+
+    .text
+    .global _start
+_start:
+    b start
+    .org 0x18
+    b irq
+
+start:
+    msr cpsr_c, #0xd2
+    ldr sp, =(MAIN_RAM + 0x8000)
+    msr cpsr_c, #0xd3
+    ldr sp, =(MAIN_RAM + 0x10000)
+
+    ldr r4, =WATCHDOG
+
+    @ second time round: it should have warned first, then reset
+    ldr r0, [r4, #WATCHDOG_STATUS]
+    cmp r0, #(WATCHDOG_WARNED | WATCHDOG_RESET)
+    beq rebooted
+    cmp r0, #0
+    bne fail_status
+
+    @ r5 counts watchdog IRQs
+    mov r5, #0
+
+    ldr r0, =INTC
+    mov r1, #IRQ_WATCHDOG
+    str r1, [r0, #INTC_ENABLE]
+
+    @ 50ms, with a warning
+    ldr r0, =50000
+    str r0, [r4, #WATCHDOG_PERIOD]
+    mov r0, #(WATCHDOG_ENABLE | WATCHDOG_IRQEN)
+    str r0, [r4, #WATCHDOG_CTRL]
+
+    @ kept fed for 10 frames (~166ms, well past one period), it never fires
+    ldr r7, =SYSINFO
+    ldr r8, [r7, #SYSINFO_FRAME]
+    add r8, r8, #10
+    ldr r6, =WATCHDOG_KEY
+kick:
+    str r6, [r4, #WATCHDOG_KICK]
+    svc #BIOS_WAITVBLANK
+    ldr r0, [r7, #SYSINFO_FRAME]
+    cmp r0, r8
+    blo kick
+
+    cmp r5, #0
+    bne fail_early
+
+    @ a wrong key doesn't count as a kick
+    ldr r0, [r4, #WATCHDOG_REMAIN]
+    ldr r1, =12345
+    str r1, [r4, #WATCHDOG_KICK]
+    ldr r1, [r4, #WATCHDOG_REMAIN]
+    cmp r1, r0
+    bhi fail_kick
+
+    @ now starve it
+    cpsie i
+starve:
+    wfi
+    b starve
+
+rebooted:
+    @ the reset disabled it
+    ldr r0, [r4, #WATCHDOG_CTRL]
+    tst r0, #WATCHDOG_ENABLE
+    bne fail_enabled
+
+    test_pass
+fail_status:
+    test_fail 0xBAD1
+fail_early:
+    test_fail 0xBAD2
+fail_kick:
+    test_fail 0xBAD3
+fail_enabled:
+    test_fail 0xBAD4
+
+irq:
+    sub lr, lr, #4
+    stmfd sp!, {r0-r1, lr}
+
+    add r5, r5, #1
+    ldr r0, =INTC
+    mov r1, #IRQ_WATCHDOG
+    str r1, [r0, #INTC_ACK]
+
+    ldmfd sp!, {r0-r1, pc}^